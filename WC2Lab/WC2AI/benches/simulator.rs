@@ -0,0 +1,65 @@
+//! Simulator throughput in ticks per second
+//!
+//! Run with `cargo bench --bench simulator`; criterion reports the
+//! throughput line in elements per second, one element per tick.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use wc2_ai::ai_command::{AICommand, ResourceKind, Selection, UnitKind};
+use wc2_ai::simulator::EntityKind;
+use wc2_ai::{SimConfig, SimMap, Simulation};
+
+/// Ticks simulated per iteration
+const TICKS: u64 = 1000;
+
+/// Peasants and soldiers each player starts with
+const WORKERS: i32 = 40;
+const SOLDIERS: i32 = 80;
+
+/// Two players mining with their peasants while their armies fight in the middle
+fn busy_game() -> Simulation {
+    let mut map = SimMap::open_field(96, 96, 2);
+    let starts: Vec<(i32, i32)> = map.start_positions.values().copied().collect();
+    for (player, &(x, y)) in starts.iter().enumerate() {
+        let owner = player as u8;
+        for i in 0..WORKERS {
+            map.placed.push((owner, EntityKind::Unit(UnitKind::Peasant), x + i % 10, y + 5 + i / 10));
+        }
+        for i in 0..SOLDIERS {
+            let unit = if i % 4 == 0 { UnitKind::Archer } else { UnitKind::Footman };
+            // Armies form up between their base and the centre
+            let toward = if player == 0 { 1 } else { -1 };
+            map.placed.push((owner, EntityKind::Unit(unit), x + toward * (10 + i % 10), y + toward * (10 + i / 10)));
+        }
+    }
+
+    let mut sim = Simulation::new(SimConfig::default(), map);
+    for (player, &(x, y)) in starts.iter().enumerate() {
+        let player = player as u8;
+        sim.issue(player, &AICommand::Select { selection: Selection::AllWorkers });
+        sim.issue(player, &AICommand::Gather { resource: ResourceKind::Gold, x: x + 6, y });
+        sim.issue(player, &AICommand::Select { selection: Selection::AllMilitary });
+        sim.issue(player, &AICommand::AttackMove { x: 48, y: 48 });
+    }
+    sim
+}
+
+fn ticks_per_second(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulator");
+    group.throughput(Throughput::Elements(TICKS));
+    group.bench_function("step", |b| {
+        b.iter_batched(
+            busy_game,
+            |mut sim| {
+                for _ in 0..TICKS {
+                    sim.step();
+                }
+                sim
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ticks_per_second);
+criterion_main!(benches);
@@ -0,0 +1,701 @@
+//! Typed AI commands
+//!
+//! Every decision the AI makes is expressed as an [`AICommand`]. Commands flow
+//! from the controller's prioritized actions through the engine into the input
+//! simulator without being turned into strings on the way, and invalid commands
+//! are rejected with an error instead of being guessed at.
+
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Result};
+use serde::{Serialize, Deserialize};
+use wc2_shared::game_rules::{GameRules, SpellRules, UnitRules, UpgradeRules};
+
+use crate::ai_controller::{Direction, FormationType};
+use crate::input_simulator::GameHotkey;
+
+/// Longest wait a single command may request
+pub const MAX_WAIT_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum UnitKind {
+    Peasant,
+    Footman,
+    Archer,
+    Knight,
+    Paladin,
+    Mage,
+    Ballista,
+    /// The Orc name for the siege unit; the Human command model trains a Ballista
+    Catapult,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BuildingKind {
+    TownHall,
+    Farm,
+    Barracks,
+    LumberMill,
+    Blacksmith,
+    Tower,
+    ScoutTower,
+    Stable,
+    Church,
+    Workshop,
+    Wall,
+    Mine,
+    OilWell,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Gold,
+    Wood,
+    Oil,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum UpgradeKind {
+    MeleeWeapons,
+    Armor,
+    Arrows,
+    SiegeWeapons,
+    Rangers,
+    Longbows,
+    Marksmanship,
+    Scouting,
+    Paladins,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SpellKind {
+    Healing,
+    Exorcism,
+    HolyVision,
+    Flameshield,
+    Slow,
+    Invisibility,
+    Polymorph,
+    Blizzard,
+}
+
+/// Which units a select command picks
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Selection {
+    AllUnits,
+    AllWorkers,
+    AllMilitary,
+    AllBuildings,
+    Area { start_x: i32, start_y: i32, end_x: i32, end_y: i32 },
+}
+
+/// A single command issued by the AI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AICommand {
+    Train { unit: UnitKind },
+    BuildAt { building: BuildingKind, x: i32, y: i32 },
+    AttackMove { x: i32, y: i32 },
+    Move { x: i32, y: i32 },
+    Gather { resource: ResourceKind, x: i32, y: i32 },
+    Research { upgrade: UpgradeKind },
+    Cast { spell: SpellKind, x: i32, y: i32 },
+    SetRally { x: i32, y: i32 },
+    Select { selection: Selection },
+    Formation { formation: FormationType, x: i32, y: i32, facing: Direction },
+    /// Screen-space click, used for menu navigation
+    Click { x: i32, y: i32 },
+    Hotkey { hotkey: GameHotkey },
+    Wait { ms: u64 },
+}
+
+impl AICommand {
+    /// Check that the command can be executed on a map of the given size (in tiles)
+    ///
+    /// Train, build and research commands must also have an entry in the game rules.
+    pub fn validate(&self, map_size: (u32, u32)) -> Result<()> {
+        let on_map = |x: i32, y: i32| -> Result<()> {
+            if x < 0 || y < 0 || x as u32 >= map_size.0 || y as u32 >= map_size.1 {
+                bail!("{} targets ({}, {}) outside the {}x{} map", self, x, y, map_size.0, map_size.1);
+            }
+            Ok(())
+        };
+
+        match self {
+            AICommand::Train { unit } => unit.rules().map(|_| ()),
+            AICommand::Research { upgrade } => upgrade.rules().map(|_| ()),
+            AICommand::Hotkey { .. } => Ok(()),
+            AICommand::BuildAt { building, x, y } => {
+                building.rules()?;
+                if !building.is_buildable() {
+                    bail!("{} cannot be built by workers", building);
+                }
+                on_map(*x, *y)
+            }
+            AICommand::AttackMove { x, y }
+            | AICommand::Move { x, y }
+            | AICommand::Gather { x, y, .. }
+            | AICommand::Cast { x, y, .. }
+            | AICommand::SetRally { x, y }
+            | AICommand::Formation { x, y, .. } => on_map(*x, *y),
+            AICommand::Select { selection: Selection::Area { start_x, start_y, end_x, end_y } } => {
+                if *start_x < 0 || *start_y < 0 || *end_x < 0 || *end_y < 0 {
+                    bail!("{} has negative screen coordinates", self);
+                }
+                Ok(())
+            }
+            AICommand::Select { .. } => Ok(()),
+            AICommand::Click { x, y } => {
+                if *x < 0 || *y < 0 {
+                    bail!("{} has negative screen coordinates", self);
+                }
+                Ok(())
+            }
+            AICommand::Wait { ms } => {
+                if *ms == 0 || *ms > MAX_WAIT_MS {
+                    bail!("{} must wait between 1 and {} ms", self, MAX_WAIT_MS);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the command sends units into combat
+    pub fn is_offensive(&self) -> bool {
+        matches!(self, AICommand::AttackMove { .. } | AICommand::Formation { .. } | AICommand::Cast { .. })
+    }
+
+    /// Map tile the command is aimed at; screen clicks and commands without a place have none
+    pub fn target(&self) -> Option<(i32, i32)> {
+        match *self {
+            AICommand::BuildAt { x, y, .. }
+            | AICommand::AttackMove { x, y }
+            | AICommand::Move { x, y }
+            | AICommand::Gather { x, y, .. }
+            | AICommand::Cast { x, y, .. }
+            | AICommand::SetRally { x, y }
+            | AICommand::Formation { x, y, .. } => Some((x, y)),
+            AICommand::Train { .. }
+            | AICommand::Research { .. }
+            | AICommand::Select { .. }
+            | AICommand::Click { .. }
+            | AICommand::Hotkey { .. }
+            | AICommand::Wait { .. } => None,
+        }
+    }
+}
+
+/// Normalize a name for case-, space- and underscore-insensitive matching
+pub(crate) fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+macro_rules! named_kind {
+    ($kind:ident, $label:literal, { $($variant:ident),* $(,)? }) => {
+        impl $kind {
+            pub const ALL: &'static [$kind] = &[$($kind::$variant),*];
+        }
+
+        impl fmt::Display for $kind {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{:?}", self)
+            }
+        }
+
+        impl FromStr for $kind {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let wanted = normalize(s);
+                $kind::ALL.iter()
+                    .copied()
+                    .find(|kind| normalize(&kind.to_string()) == wanted)
+                    .ok_or_else(|| anyhow!("Unknown {}: {}", $label, s))
+            }
+        }
+    };
+}
+
+named_kind!(UnitKind, "unit type", {
+    Peasant, Footman, Archer, Knight, Paladin, Mage, Ballista, Catapult,
+});
+named_kind!(BuildingKind, "building type", {
+    TownHall, Farm, Barracks, LumberMill, Blacksmith, Tower, ScoutTower, Stable,
+    Church, Workshop, Wall, Mine, OilWell,
+});
+named_kind!(ResourceKind, "resource", { Gold, Wood, Oil });
+named_kind!(UpgradeKind, "upgrade", {
+    MeleeWeapons, Armor, Arrows, SiegeWeapons, Rangers, Longbows, Marksmanship,
+    Scouting, Paladins,
+});
+named_kind!(SpellKind, "spell", {
+    Healing, Exorcism, HolyVision, Flameshield, Slow, Invisibility, Polymorph, Blizzard,
+});
+
+impl UnitKind {
+    /// Hotkey that queues this unit in its production building
+    pub fn hotkey(self) -> GameHotkey {
+        match self {
+            UnitKind::Peasant => GameHotkey::BuildPeasant,
+            UnitKind::Footman => GameHotkey::BuildFootman,
+            UnitKind::Archer => GameHotkey::BuildArcher,
+            UnitKind::Knight => GameHotkey::BuildKnight,
+            UnitKind::Paladin => GameHotkey::BuildPaladin,
+            UnitKind::Mage => GameHotkey::BuildMage,
+            UnitKind::Ballista | UnitKind::Catapult => GameHotkey::BuildBallista,
+        }
+    }
+
+    /// Key of this unit in the shared game rules
+    pub fn rules_key(self) -> &'static str {
+        match self {
+            UnitKind::Peasant => "peasant",
+            UnitKind::Footman => "footman",
+            UnitKind::Archer => "archer",
+            UnitKind::Knight => "knight",
+            UnitKind::Paladin => "paladin",
+            UnitKind::Mage => "mage",
+            UnitKind::Ballista | UnitKind::Catapult => "ballista",
+        }
+    }
+
+    /// Unit kind for a game rules key, if the command model has one
+    pub fn from_rules_key(key: &str) -> Option<UnitKind> {
+        UnitKind::ALL.iter().copied().find(|unit| unit.rules_key() == key)
+    }
+
+    /// This unit's entry in the embedded game rules
+    pub fn rules(self) -> Result<&'static UnitRules> {
+        GameRules::embedded().unit(self.rules_key())
+            .ok_or_else(|| anyhow!("No game rules entry for unit type {}", self))
+    }
+}
+
+impl BuildingKind {
+    /// Hotkey that picks this building from a worker's build menu
+    pub fn hotkey(self) -> GameHotkey {
+        match self {
+            BuildingKind::TownHall => GameHotkey::BuildTownHall,
+            BuildingKind::Farm => GameHotkey::BuildFarm,
+            BuildingKind::Barracks => GameHotkey::BuildBarracks,
+            BuildingKind::LumberMill => GameHotkey::BuildLumberMill,
+            BuildingKind::Blacksmith => GameHotkey::BuildBlacksmith,
+            BuildingKind::Tower => GameHotkey::BuildTower,
+            BuildingKind::ScoutTower => GameHotkey::BuildScoutTower,
+            BuildingKind::Stable => GameHotkey::BuildStable,
+            BuildingKind::Church => GameHotkey::BuildChurch,
+            BuildingKind::Workshop => GameHotkey::BuildWorkshop,
+            BuildingKind::Wall => GameHotkey::BuildWall,
+            BuildingKind::Mine => GameHotkey::BuildMine,
+            BuildingKind::OilWell => GameHotkey::BuildOilWell,
+        }
+    }
+
+    /// Key of this building in the shared game rules
+    pub fn rules_key(self) -> &'static str {
+        match self {
+            BuildingKind::TownHall => "town_hall",
+            BuildingKind::Farm => "farm",
+            BuildingKind::Barracks => "barracks",
+            BuildingKind::LumberMill => "lumber_mill",
+            BuildingKind::Blacksmith => "blacksmith",
+            BuildingKind::Tower => "guard_tower",
+            BuildingKind::ScoutTower => "scout_tower",
+            BuildingKind::Stable => "stables",
+            BuildingKind::Church => "church",
+            BuildingKind::Workshop => "gnomish_inventor",
+            BuildingKind::Wall => "wall",
+            BuildingKind::Mine => "gold_mine",
+            BuildingKind::OilWell => "oil_platform",
+        }
+    }
+
+    /// Whether workers can build this; gold mines and walls only come with the map
+    pub fn is_buildable(self) -> bool {
+        !matches!(self, BuildingKind::Mine | BuildingKind::Wall)
+    }
+
+    /// Building kind for a game rules key, if the command model has one
+    pub fn from_rules_key(key: &str) -> Option<BuildingKind> {
+        BuildingKind::ALL.iter().copied().find(|building| building.rules_key() == key)
+    }
+
+    /// This building's entry in the embedded game rules
+    pub fn rules(self) -> Result<&'static UnitRules> {
+        GameRules::embedded().unit(self.rules_key())
+            .ok_or_else(|| anyhow!("No game rules entry for building type {}", self))
+    }
+}
+
+impl UpgradeKind {
+    /// Game rules keys of each research level, in order
+    pub fn rules_keys(self) -> &'static [&'static str] {
+        match self {
+            UpgradeKind::MeleeWeapons => &["swords_1", "swords_2"],
+            UpgradeKind::Armor => &["human_shields_1", "human_shields_2"],
+            UpgradeKind::Arrows => &["arrows_1", "arrows_2"],
+            UpgradeKind::SiegeWeapons => &["ballista_1", "ballista_2"],
+            UpgradeKind::Rangers => &["rangers"],
+            UpgradeKind::Longbows => &["longbow"],
+            UpgradeKind::Marksmanship => &["marksmanship"],
+            UpgradeKind::Scouting => &["ranger_scouting"],
+            UpgradeKind::Paladins => &["paladins"],
+        }
+    }
+
+    /// Upgrade kind with a research level under this game rules key
+    pub fn from_rules_key(key: &str) -> Option<UpgradeKind> {
+        UpgradeKind::ALL.iter().copied().find(|upgrade| upgrade.rules_keys().contains(&key))
+    }
+
+    /// Embedded game rules entry for the first research level
+    pub fn rules(self) -> Result<&'static UpgradeRules> {
+        GameRules::embedded().upgrade(self.rules_keys()[0])
+            .ok_or_else(|| anyhow!("No game rules entry for upgrade {}", self))
+    }
+
+    /// Embedded game rules entry for research level `level` (1-based); None past the last level
+    pub fn level_rules(self, level: u8) -> Option<&'static UpgradeRules> {
+        let key = self.rules_keys().get(usize::from(level).checked_sub(1)?)?;
+        GameRules::embedded().upgrade(key)
+    }
+}
+
+impl SpellKind {
+    /// Hotkey that readies this spell on its caster's command card
+    pub fn hotkey(self) -> GameHotkey {
+        GameHotkey::Cast(self)
+    }
+
+    /// Key of this spell in the shared game rules
+    pub fn rules_key(self) -> &'static str {
+        match self {
+            SpellKind::Healing => "healing",
+            SpellKind::Exorcism => "exorcism",
+            SpellKind::HolyVision => "holy_vision",
+            SpellKind::Flameshield => "flame_shield",
+            SpellKind::Slow => "slow",
+            SpellKind::Invisibility => "invisibility",
+            SpellKind::Polymorph => "polymorph",
+            SpellKind::Blizzard => "blizzard",
+        }
+    }
+
+    /// This spell's entry in the embedded game rules
+    pub fn rules(self) -> Result<&'static SpellRules> {
+        GameRules::embedded().spell(self.rules_key())
+            .ok_or_else(|| anyhow!("No game rules entry for spell {}", self))
+    }
+}
+
+impl fmt::Display for AICommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AICommand::Train { unit } => write!(f, "train {}", unit),
+            AICommand::BuildAt { building, x, y } => write!(f, "build {} {} {}", building, x, y),
+            AICommand::AttackMove { x, y } => write!(f, "attack {} {}", x, y),
+            AICommand::Move { x, y } => write!(f, "move {} {}", x, y),
+            AICommand::Gather { resource, x, y } => write!(f, "gather {} {} {}", resource, x, y),
+            AICommand::Research { upgrade } => write!(f, "research {}", upgrade),
+            AICommand::Cast { spell, x, y } => write!(f, "cast {} {} {}", spell, x, y),
+            AICommand::SetRally { x, y } => write!(f, "rally {} {}", x, y),
+            AICommand::Select { selection } => match selection {
+                Selection::AllUnits => write!(f, "select all"),
+                Selection::AllWorkers => write!(f, "select workers"),
+                Selection::AllMilitary => write!(f, "select military"),
+                Selection::AllBuildings => write!(f, "select buildings"),
+                Selection::Area { start_x, start_y, end_x, end_y } => {
+                    write!(f, "select {} {} {} {}", start_x, start_y, end_x, end_y)
+                }
+            },
+            AICommand::Formation { formation, x, y, facing } => {
+                write!(f, "formation {:?} {} {} {:?}", formation, x, y, facing)
+            }
+            AICommand::Click { x, y } => write!(f, "click {} {}", x, y),
+            AICommand::Hotkey { hotkey } => write!(f, "hotkey {:?}", hotkey),
+            AICommand::Wait { ms } => write!(f, "wait {}", ms),
+        }
+    }
+}
+
+/// Parses the text form written by `Display`, e.g. "build Barracks 100 100"
+impl FromStr for AICommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let (verb, args) = parts.split_first()
+            .ok_or_else(|| anyhow!("Empty AI command"))?;
+
+        let int = |index: usize| -> Result<i32> {
+            let arg = args.get(index)
+                .ok_or_else(|| anyhow!("Missing argument {} in AI command: {}", index + 1, s))?;
+            arg.parse().map_err(|_| anyhow!("Invalid number '{}' in AI command: {}", arg, s))
+        };
+        let word = |index: usize| -> Result<&str> {
+            args.get(index)
+                .copied()
+                .ok_or_else(|| anyhow!("Missing argument {} in AI command: {}", index + 1, s))
+        };
+        let arity = |count: usize| -> Result<()> {
+            if args.len() != count {
+                bail!("Expected {} arguments in AI command: {}", count, s);
+            }
+            Ok(())
+        };
+
+        let command = match verb.to_lowercase().as_str() {
+            "train" => {
+                arity(1)?;
+                AICommand::Train { unit: word(0)?.parse()? }
+            }
+            "build" => {
+                arity(3)?;
+                AICommand::BuildAt { building: word(0)?.parse()?, x: int(1)?, y: int(2)? }
+            }
+            "attack" => {
+                arity(2)?;
+                AICommand::AttackMove { x: int(0)?, y: int(1)? }
+            }
+            "move" => {
+                arity(2)?;
+                AICommand::Move { x: int(0)?, y: int(1)? }
+            }
+            "gather" => {
+                arity(3)?;
+                AICommand::Gather { resource: word(0)?.parse()?, x: int(1)?, y: int(2)? }
+            }
+            "research" => {
+                arity(1)?;
+                AICommand::Research { upgrade: word(0)?.parse()? }
+            }
+            "cast" => {
+                arity(3)?;
+                AICommand::Cast { spell: word(0)?.parse()?, x: int(1)?, y: int(2)? }
+            }
+            "rally" => {
+                arity(2)?;
+                AICommand::SetRally { x: int(0)?, y: int(1)? }
+            }
+            "select" if args.len() == 4 => AICommand::Select {
+                selection: Selection::Area { start_x: int(0)?, start_y: int(1)?, end_x: int(2)?, end_y: int(3)? },
+            },
+            "select" => {
+                arity(1)?;
+                let selection = match word(0)?.to_lowercase().as_str() {
+                    "all" => Selection::AllUnits,
+                    "workers" => Selection::AllWorkers,
+                    "military" => Selection::AllMilitary,
+                    "buildings" => Selection::AllBuildings,
+                    other => bail!("Unknown selection '{}' in AI command: {}", other, s),
+                };
+                AICommand::Select { selection }
+            }
+            "formation" => {
+                arity(4)?;
+                AICommand::Formation {
+                    formation: serde_json::from_value(serde_json::Value::String(word(0)?.to_string()))
+                        .map_err(|_| anyhow!("Unknown formation '{}' in AI command: {}", word(0).unwrap_or(""), s))?,
+                    x: int(1)?,
+                    y: int(2)?,
+                    facing: serde_json::from_value(serde_json::Value::String(word(3)?.to_string()))
+                        .map_err(|_| anyhow!("Unknown direction '{}' in AI command: {}", word(3).unwrap_or(""), s))?,
+                }
+            }
+            "click" => {
+                arity(2)?;
+                AICommand::Click { x: int(0)?, y: int(1)? }
+            }
+            "hotkey" => {
+                arity(1)?;
+                AICommand::Hotkey {
+                    hotkey: serde_json::from_value(serde_json::Value::String(word(0)?.to_string()))
+                        .map_err(|_| anyhow!("Unknown hotkey '{}' in AI command: {}", word(0).unwrap_or(""), s))?,
+                }
+            }
+            "wait" => {
+                arity(1)?;
+                let ms = word(0)?.parse()
+                    .map_err(|_| anyhow!("Invalid wait time in AI command: {}", s))?;
+                AICommand::Wait { ms }
+            }
+            _ => bail!("Unknown AI command: {}", s),
+        };
+
+        Ok(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wc2_shared::game_rules::Race;
+
+    fn every_command() -> Vec<AICommand> {
+        vec![
+            AICommand::Train { unit: UnitKind::Knight },
+            AICommand::BuildAt { building: BuildingKind::LumberMill, x: 12, y: 40 },
+            AICommand::AttackMove { x: 90, y: 3 },
+            AICommand::Move { x: 0, y: 0 },
+            AICommand::Gather { resource: ResourceKind::Wood, x: 7, y: 8 },
+            AICommand::Research { upgrade: UpgradeKind::SiegeWeapons },
+            AICommand::Cast { spell: SpellKind::HolyVision, x: 50, y: 60 },
+            AICommand::SetRally { x: 20, y: 21 },
+            AICommand::Select { selection: Selection::AllUnits },
+            AICommand::Select { selection: Selection::AllWorkers },
+            AICommand::Select { selection: Selection::AllMilitary },
+            AICommand::Select { selection: Selection::AllBuildings },
+            AICommand::Select { selection: Selection::Area { start_x: 10, start_y: 20, end_x: 300, end_y: 200 } },
+            AICommand::Formation { formation: FormationType::Wedge, x: 30, y: 31, facing: Direction::Southwest },
+            AICommand::Click { x: 320, y: 240 },
+            AICommand::Hotkey { hotkey: GameHotkey::SelectAllMilitary },
+            AICommand::Wait { ms: 250 },
+        ]
+    }
+
+    #[test]
+    fn test_text_form_round_trips() {
+        for command in every_command() {
+            let text = command.to_string();
+            assert_eq!(text.parse::<AICommand>().unwrap(), command, "{}", text);
+        }
+        assert_eq!("build Barracks 100 100".parse::<AICommand>().unwrap(),
+            AICommand::BuildAt { building: BuildingKind::Barracks, x: 100, y: 100 });
+        // Verbs and names are matched loosely, as scripts and config files write them
+        assert_eq!("TRAIN  footman".parse::<AICommand>().unwrap(), AICommand::Train { unit: UnitKind::Footman });
+        assert_eq!("build town_hall 5 6".parse::<AICommand>().unwrap(),
+            AICommand::BuildAt { building: BuildingKind::TownHall, x: 5, y: 6 });
+        assert_eq!("research melee-weapons".parse::<AICommand>().unwrap(),
+            AICommand::Research { upgrade: UpgradeKind::MeleeWeapons });
+    }
+
+    #[test]
+    fn test_malformed_text_is_rejected() {
+        for text in [
+            "",
+            "dance 1 2",
+            "train",
+            "train Grunt",
+            "train Footman Archer",
+            "build Barracks 100",
+            "build Barracks x 100",
+            "attack 1 2 3",
+            "gather Stone 1 2",
+            "select everyone",
+            "formation Blob 1 2 North",
+            "formation Line 1 2 Up",
+            "hotkey Teleport",
+            "wait soon",
+            "wait -5",
+            "wait 1 2",
+        ] {
+            assert!(text.parse::<AICommand>().is_err(), "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        let map = (128, 64);
+        for command in every_command() {
+            command.validate(map).unwrap();
+        }
+        assert!(AICommand::BuildAt { building: BuildingKind::Farm, x: 128, y: 0 }.validate(map).is_err());
+        assert!(AICommand::Move { x: -1, y: 5 }.validate(map).is_err());
+        assert!(AICommand::Gather { resource: ResourceKind::Gold, x: 5, y: 64 }.validate(map).is_err());
+        assert!(AICommand::Click { x: -3, y: 0 }.validate(map).is_err());
+        assert!(AICommand::Select { selection: Selection::Area { start_x: 0, start_y: -1, end_x: 5, end_y: 5 } }
+            .validate(map).is_err());
+        assert!(AICommand::Wait { ms: 0 }.validate(map).is_err());
+        // Mines and walls come with the map
+        assert!(AICommand::BuildAt { building: BuildingKind::Mine, x: 5, y: 5 }.validate(map).is_err());
+        assert!(AICommand::BuildAt { building: BuildingKind::Wall, x: 5, y: 5 }.validate(map).is_err());
+        assert!(AICommand::Wait { ms: MAX_WAIT_MS + 1 }.validate(map).is_err());
+        AICommand::Wait { ms: MAX_WAIT_MS }.validate(map).unwrap();
+        // Screen coordinates are not bounded by the map
+        AICommand::Click { x: 640, y: 480 }.validate(map).unwrap();
+    }
+
+    #[test]
+    fn test_offensive_commands() {
+        let offensive: Vec<String> = every_command().into_iter()
+            .filter(AICommand::is_offensive)
+            .map(|command| command.to_string())
+            .collect();
+        assert_eq!(offensive, vec!["attack 90 3", "cast HolyVision 50 60", "formation Wedge 30 31 Southwest"]);
+    }
+
+    #[test]
+    fn test_every_kind_has_a_rules_entry() {
+        let rules = GameRules::embedded();
+        for unit in UnitKind::ALL {
+            let entry = unit.rules().unwrap();
+            assert_eq!(entry.key, unit.rules_key());
+            assert!(!entry.is_building(), "{}", unit);
+            assert_eq!(entry.race, Race::Human, "{}", unit);
+            assert_eq!(UnitKind::from_rules_key(unit.rules_key()).map(UnitKind::rules_key), Some(unit.rules_key()));
+        }
+        for building in BuildingKind::ALL {
+            let entry = building.rules().unwrap();
+            assert_eq!(entry.key, building.rules_key());
+            assert_eq!(BuildingKind::from_rules_key(building.rules_key()), Some(*building));
+        }
+        for upgrade in UpgradeKind::ALL {
+            upgrade.rules().unwrap();
+            for key in upgrade.rules_keys() {
+                assert!(rules.upgrade(key).is_some(), "{}", key);
+                assert_eq!(UpgradeKind::from_rules_key(key), Some(*upgrade));
+            }
+        }
+        for spell in SpellKind::ALL {
+            assert_eq!(spell.rules().unwrap().race, Race::Human, "{}", spell);
+        }
+    }
+
+    #[test]
+    fn test_rules_lookup() {
+        assert_eq!(UnitKind::Mage.rules().unwrap().name, "Mage");
+        assert_eq!(BuildingKind::Tower.rules_key(), "guard_tower");
+        assert_eq!(BuildingKind::from_rules_key("stables"), Some(BuildingKind::Stable));
+        assert_eq!(UpgradeKind::from_rules_key("swords_2"), Some(UpgradeKind::MeleeWeapons));
+        // The Catapult is trained as its Human counterpart
+        assert_eq!(UnitKind::Catapult.rules().unwrap().name, "Ballista");
+        assert_eq!(UnitKind::from_rules_key("ballista"), Some(UnitKind::Ballista));
+        // Orc units and keys outside the command model have no kind
+        assert_eq!(UnitKind::from_rules_key("grunt"), None);
+        assert_eq!(UnitKind::from_rules_key("catapult"), None);
+        assert_eq!(BuildingKind::from_rules_key("castle"), None);
+        assert_eq!(UpgradeKind::from_rules_key("mage_tower"), None);
+    }
+
+    #[test]
+    fn test_hotkeys_follow_the_rules() {
+        for unit in UnitKind::ALL {
+            assert_eq!(unit.hotkey().production_key(), unit.rules().unwrap().hotkey, "{}", unit);
+        }
+        for building in BuildingKind::ALL.iter().filter(|building| building.rules().unwrap().hotkey.is_some()) {
+            assert_eq!(building.hotkey().production_key(), building.rules().unwrap().hotkey, "{}", building);
+        }
+        assert_eq!(UnitKind::Footman.hotkey(), GameHotkey::BuildFootman);
+        assert_eq!(UnitKind::Footman.hotkey().production_key(), Some('F'));
+        assert_eq!(UnitKind::Catapult.hotkey().production_key(), Some('B'));
+        assert_eq!(BuildingKind::Barracks.hotkey(), GameHotkey::BuildBarracks);
+        for spell in SpellKind::ALL {
+            assert_eq!(spell.hotkey().production_key(), spell.rules().unwrap().hotkey, "{}", spell);
+        }
+        assert_eq!(SpellKind::Healing.hotkey().production_key(), Some('H'));
+        assert_eq!(SpellKind::Blizzard.hotkey().production_key(), Some('B'));
+        // Selection and camera hotkeys are not on the command card
+        assert_eq!(GameHotkey::CameraHome.production_key(), None);
+    }
+
+    #[test]
+    fn test_targets_are_map_tiles() {
+        assert_eq!(AICommand::Gather { resource: ResourceKind::Gold, x: 6, y: 7 }.target(), Some((6, 7)));
+        assert_eq!(AICommand::BuildAt { building: BuildingKind::Farm, x: 2, y: 3 }.target(), Some((2, 3)));
+        assert_eq!(AICommand::Train { unit: UnitKind::Footman }.target(), None);
+        // Clicks are in screen space
+        assert_eq!(AICommand::Click { x: 100, y: 200 }.target(), None);
+    }
+}
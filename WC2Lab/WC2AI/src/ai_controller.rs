@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use anyhow::Result;
@@ -5,8 +7,23 @@ use serde::{Serialize, Deserialize};
 use log::{info, warn, error, debug};
 
 use crate::game_engine::{HeadlessGameState, GamePhase, PlayerResources, UnitInfo, BuildingInfo};
+use crate::ai_command::{AICommand, BuildingKind, ResourceKind, Selection, UnitKind, UpgradeKind};
+use crate::build_planner::{clock, BuildPlan, BuildPlanner, PlanGoal, PlannerStart};
+use crate::input_simulator::GameHotkey;
+use crate::game_backend::GameBackend;
+use crate::decision_trace::{ActionSource, CandidateTrace, DecisionTrace, Rejection};
+use crate::scripting::ScriptHost;
+use crate::simulator::NEUTRAL_PLAYER;
+use crate::strategy_bandit::{GameFeatures, GameStage, LearningExample, StrategyLearner, DRAW_REWARD};
+use wc2_shared::game_rules::{GameRules, Race};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Most commands issued in a single decision
+pub const MAX_ACTIONS_PER_DECISION: usize = 8;
+
+/// Peasants every strategy keeps gathering, whatever else it does
+pub const ECONOMY_WORKERS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum AIStrategy {
     Aggressive,    // Focus on early attacks and military
     Defensive,     // Build up defenses and economy
@@ -14,6 +31,7 @@ pub enum AIStrategy {
     Economic,      // Focus on resource gathering and building
     Rush,          // Fast military rush strategy
     Turtle,        // Defensive turtle strategy
+    Scripted(String), // Rhai strategy script of this name
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +44,28 @@ pub struct AIDecisionContext {
     pub resource_scarcity: ResourceScarcity,
     pub game_time: u64,
     pub map_size: (u32, u32),
+    /// Own peasants
+    #[serde(default)]
+    pub workers: usize,
+    /// Own peasants with nothing to do
+    #[serde(default)]
+    pub idle_workers: usize,
+    /// Centre tile of the player's town hall, or of anything it still owns
+    #[serde(default)]
+    pub home: Option<(i32, i32)>,
+    /// Seen enemy buildings and units, closest to home first, then the other start positions
+    #[serde(default)]
+    pub enemy_positions: Vec<(i32, i32)>,
+    /// Footprints of every building as (x, y, size) in tiles
+    #[serde(skip)]
+    pub occupied: Vec<(i32, i32, i32)>,
+}
+
+impl AIDecisionContext {
+    /// Middle tile of the map, the fallback target when nothing better is known
+    pub fn map_centre(&self) -> (i32, i32) {
+        ((self.map_size.0 / 2) as i32, (self.map_size.1 / 2) as i32)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -47,7 +87,7 @@ pub enum ResourceScarcity {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrioritizedAction {
-    pub action: String,
+    pub command: AICommand,
     pub priority: f64,
     pub reasoning: String,
     pub estimated_cost: u32,
@@ -63,15 +103,6 @@ pub struct AIPersonality {
     pub adaptability: f64,          // 0.0 to 1.0
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LearningExample {
-    pub situation: String,
-    pub action_taken: String,
-    pub outcome: String,
-    pub success_score: f64,
-    pub timestamp: u64,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AIAnalytics {
     pub current_strategy: AIStrategy,
@@ -148,7 +179,14 @@ pub struct AIController {
     last_decision_time: u64,
     personality: AIPersonality,
     action_queue: Arc<Mutex<Vec<PrioritizedAction>>>,
-    learning_data: Arc<Mutex<Vec<LearningExample>>>,
+    learning: Arc<Mutex<StrategyLearner>>,
+    learning_path: PathBuf,
+    /// Let the strategy bandit choose the strategy during play
+    adaptive: bool,
+    scripts: Option<Arc<Mutex<ScriptHost>>>,
+    last_trace: Arc<Mutex<Option<DecisionTrace>>>,
+    /// Research levels started so far, which the next research of an upgrade builds on
+    research_levels: Arc<Mutex<HashMap<UpgradeKind, u8>>>,
 }
 
 impl AIController {
@@ -161,13 +199,51 @@ impl AIController {
             last_decision_time: 0,
             personality: AIPersonality::default(),
             action_queue: Arc::new(Mutex::new(Vec::new())),
-            learning_data: Arc::new(Mutex::new(Vec::new())),
+            learning: Arc::new(Mutex::new(StrategyLearner::default())),
+            learning_path: PathBuf::from("exports/ai_learning.json"),
+            adaptive: true,
+            scripts: None,
+            last_trace: Arc::new(Mutex::new(None)),
+            research_levels: Arc::new(Mutex::new(HashMap::new())),
         };
         
         info!("✅ AI Controller initialized");
         Ok(controller)
     }
     
+    /// Controller with a fixed strategy and personality, e.g. for tournaments
+    pub async fn with_profile(strategy: AIStrategy, personality: AIPersonality) -> Result<Self> {
+        let mut controller = Self::new().await?;
+        controller.strategy = strategy;
+        controller.personality = personality;
+        controller.adaptive = false;
+        Ok(controller)
+    }
+    
+    /// Where `load_learning_data`/`save_learning_data` keep the strategy model
+    pub fn set_learning_path(&mut self, path: impl Into<PathBuf>) {
+        self.learning_path = path.into();
+    }
+    
+    /// Load the Rhai strategy scripts in `dir` for `AIStrategy::Scripted`; returns their names
+    pub async fn load_scripts(&mut self, dir: impl Into<PathBuf>) -> Result<Vec<String>> {
+        let host = ScriptHost::new(dir)?;
+        let names = host.names();
+        self.scripts = Some(Arc::new(Mutex::new(host)));
+        Ok(names)
+    }
+    
+    /// Strategy and personality in play: the bandit's pick when adaptive
+    async fn profile(&self) -> (AIStrategy, AIPersonality) {
+        if self.adaptive {
+            if let Some(strategy) = self.learning.lock().await.active.clone() {
+                let personality = AIPersonality::for_strategy(&strategy);
+                return (strategy, personality);
+            }
+        }
+        (self.strategy.clone(), self.personality.clone())
+    }
+    
     pub async fn initialize(&mut self) -> Result<()> {
         info!("🔧 Initializing AI Controller...");
         
@@ -181,37 +257,101 @@ impl AIController {
         Ok(())
     }
     
-    pub async fn make_decisions(&self, game_state: &HeadlessGameState) -> Result<Vec<String>> {
-        let mut actions = Vec::new();
-        
+    pub async fn make_decisions(&self, game_state: &HeadlessGameState) -> Result<Vec<AICommand>> {
         // Analyze current game state
         let context = self.analyze_game_state(game_state).await?;
         
         // Generate prioritized actions based on strategy
-        let prioritized_actions = self.generate_prioritized_actions(&context).await?;
+        let prioritized_actions = self.generate_prioritized_actions(game_state, &context).await?;
         
-        // Convert to simple action strings for execution
-        for action in prioritized_actions {
-            actions.push(action.action);
+        Ok(prioritized_actions.into_iter().map(|action| action.command).collect())
+    }
+    
+    /// Free site for a building near home, searching outwards like the simulator does
+    ///
+    /// Terrain is not observed, so only the map edge and other buildings are avoided.
+    fn building_site(&self, context: &AIDecisionContext, building: BuildingKind) -> Result<(i32, i32)> {
+        let Some((cx, cy)) = context.home else {
+            return Ok(context.map_centre());
+        };
+        let size = building.rules()?.size as i32;
+        let (width, height) = (context.map_size.0 as i32, context.map_size.1 as i32);
+        
+        // Keep a one tile gap around the site so peasants can still walk past
+        let free = |x: i32, y: i32| {
+            let (x0, y0, x1, y1) = (x - 1, y - 1, x + size + 1, y + size + 1);
+            x0 >= 0 && y0 >= 0 && x1 <= width && y1 <= height
+                && !context.occupied.iter().any(|&(bx, by, bs)| bx < x1 && bx + bs > x0 && by < y1 && by + bs > y0)
+        };
+        for radius in 3..width.max(height) {
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if (dx.abs() == radius || dy.abs() == radius) && free(cx + dx, cy + dy) {
+                        return Ok((cx + dx, cy + dy));
+                    }
+                }
+            }
         }
-        
-        Ok(actions)
+        Ok((cx, cy))
+    }
+    
+    /// Closest known enemy, or the middle of the map when none is known
+    fn enemy_position(&self, context: &AIDecisionContext) -> (i32, i32) {
+        context.enemy_positions.first().copied().unwrap_or(context.map_centre())
+    }
+    
+    fn build_command(&self, context: &AIDecisionContext, building: BuildingKind) -> Result<AICommand> {
+        let (x, y) = self.building_site(context, building)?;
+        Ok(AICommand::BuildAt { building, x, y })
+    }
+    
+    /// Gather at the resource closest to home
+    fn gather_command(&self, context: &AIDecisionContext, resource: ResourceKind) -> AICommand {
+        let (x, y) = context.home.unwrap_or(context.map_centre());
+        AICommand::Gather { resource, x, y }
+    }
+    
+    fn attack_command(&self, context: &AIDecisionContext) -> AICommand {
+        let (x, y) = self.enemy_position(context);
+        AICommand::AttackMove { x, y }
+    }
+    
+    fn scout_command(&self, context: &AIDecisionContext) -> AICommand {
+        let (x, y) = self.enemy_position(context);
+        AICommand::Move { x, y }
+    }
+    
+    fn defend_command(&self, context: &AIDecisionContext) -> AICommand {
+        let (x, y) = context.home.unwrap_or(context.map_centre());
+        AICommand::Move { x, y }
+    }
+    
+    fn formation_command(&self, context: &AIDecisionContext, formation: FormationType) -> AICommand {
+        let (x, y) = self.enemy_position(context);
+        AICommand::Formation { formation, x, y, facing: Direction::North }
     }
     
     /// Analyze current game state and create decision context
     async fn analyze_game_state(&self, game_state: &HeadlessGameState) -> Result<AIDecisionContext> {
         let threat_level = self.assess_threat_level(game_state).await?;
         let resource_scarcity = self.assess_resource_scarcity(&game_state.player_resources).await?;
+        let Surroundings { home, enemy_positions, occupied } = surroundings(game_state);
+        let (workers, idle_workers) = workers(game_state);
         
         let context = AIDecisionContext {
             current_phase: game_state.game_phase.clone(),
             resources: game_state.player_resources.clone(),
-            unit_count: game_state.units.len(),
-            building_count: game_state.buildings.len(),
+            unit_count: game_state.units.iter().filter(|unit| unit.owner == game_state.player).count(),
+            building_count: game_state.buildings.iter().filter(|building| building.owner == game_state.player).count(),
             threat_level,
             resource_scarcity,
             game_time: game_state.game_time,
             map_size: (game_state.map_info.width, game_state.map_info.height),
+            workers,
+            idle_workers,
+            home,
+            enemy_positions,
+            occupied,
         };
         
         Ok(context)
@@ -249,10 +389,10 @@ impl AIController {
     }
     
     /// Generate prioritized actions based on current context
-    async fn generate_prioritized_actions(&self, context: &AIDecisionContext) -> Result<Vec<PrioritizedAction>> {
+    async fn generate_prioritized_actions(&self, game_state: &HeadlessGameState, context: &AIDecisionContext) -> Result<Vec<PrioritizedAction>> {
         let mut actions = Vec::new();
         
-        match self.strategy {
+        match self.profile().await.0 {
             AIStrategy::Aggressive => {
                 actions.extend(self.generate_aggressive_actions(context).await?);
             }
@@ -271,6 +411,9 @@ impl AIController {
             AIStrategy::Turtle => {
                 actions.extend(self.generate_turtle_actions(context).await?);
             }
+            AIStrategy::Scripted(name) => {
+                actions.extend(self.generate_scripted_actions(&name, game_state, context).await?);
+            }
         }
         
         // Sort by priority
@@ -279,6 +422,20 @@ impl AIController {
         Ok(actions)
     }
     
+    /// Generate actions from a strategy script; a failing script sits the turn out
+    async fn generate_scripted_actions(&self, name: &str, game_state: &HeadlessGameState, context: &AIDecisionContext) -> Result<Vec<PrioritizedAction>> {
+        let scripts = self.scripts.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Strategy script {} requested but no scripts are loaded", name))?;
+        
+        match scripts.lock().await.run(name, game_state, context) {
+            Ok(actions) => Ok(actions),
+            Err(e) => {
+                warn!("⚠️ {}", e);
+                Ok(Vec::new())
+            }
+        }
+    }
+    
     /// Generate actions for aggressive strategy
     async fn generate_aggressive_actions(&self, context: &AIDecisionContext) -> Result<Vec<PrioritizedAction>> {
         let mut actions = Vec::new();
@@ -287,7 +444,7 @@ impl AIController {
             GamePhase::MainMenu => {
                 // Navigate to start game
                 actions.push(PrioritizedAction {
-                    action: "navigate_to_start_game".to_string(),
+                    command: AICommand::Hotkey { hotkey: GameHotkey::StartGame },
                     priority: 1.0,
                     reasoning: "Start game to begin aggressive strategy".to_string(),
                     estimated_cost: 0,
@@ -298,24 +455,24 @@ impl AIController {
                 if context.unit_count < 10 {
                     // Build army
                     actions.push(PrioritizedAction {
-                        action: "build_barracks".to_string(),
+                        command: self.build_command(context, BuildingKind::Barracks)?,
                         priority: 0.9,
                         reasoning: "Need barracks to train military units".to_string(),
-                        estimated_cost: 150,
+                        estimated_cost: BuildingKind::Barracks.rules()?.total_cost(),
                         time_to_complete: 2000,
                     });
                     
                     actions.push(PrioritizedAction {
-                        action: "train_peasants".to_string(),
+                        command: AICommand::Train { unit: UnitKind::Peasant },
                         priority: 0.8,
                         reasoning: "Need peasants for resource gathering".to_string(),
-                        estimated_cost: 75,
+                        estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
                         time_to_complete: 1000,
                     });
                 } else {
                     // Attack with existing army
                     actions.push(PrioritizedAction {
-                        action: "attack_move_forward".to_string(),
+                        command: self.attack_command(context),
                         priority: 0.95,
                         reasoning: "Aggressive strategy - attack enemy".to_string(),
                         estimated_cost: 0,
@@ -336,7 +493,7 @@ impl AIController {
         match context.current_phase {
             GamePhase::MainMenu => {
                 actions.push(PrioritizedAction {
-                    action: "navigate_to_start_game".to_string(),
+                    command: AICommand::Hotkey { hotkey: GameHotkey::StartGame },
                     priority: 1.0,
                     reasoning: "Start game to begin defensive strategy".to_string(),
                     estimated_cost: 0,
@@ -347,27 +504,27 @@ impl AIController {
                 if context.building_count < 5 {
                     // Build defensive structures
                     actions.push(PrioritizedAction {
-                        action: "build_town_hall".to_string(),
+                        command: self.build_command(context, BuildingKind::TownHall)?,
                         priority: 0.9,
                         reasoning: "Need town hall for base defense".to_string(),
-                        estimated_cost: 400,
+                        estimated_cost: BuildingKind::TownHall.rules()?.total_cost(),
                         time_to_complete: 3000,
                     });
                     
                     actions.push(PrioritizedAction {
-                        action: "build_farm".to_string(),
+                        command: self.build_command(context, BuildingKind::Farm)?,
                         priority: 0.8,
                         reasoning: "Need farms for food production".to_string(),
-                        estimated_cost: 75,
+                        estimated_cost: BuildingKind::Farm.rules()?.total_cost(),
                         time_to_complete: 1500,
                     });
                 } else {
                     // Build defensive units
                     actions.push(PrioritizedAction {
-                        action: "train_archers".to_string(),
+                        command: AICommand::Train { unit: UnitKind::Archer },
                         priority: 0.85,
                         reasoning: "Archers good for defensive positions".to_string(),
-                        estimated_cost: 125,
+                        estimated_cost: UnitKind::Archer.rules()?.total_cost(),
                         time_to_complete: 2000,
                     });
                 }
@@ -385,7 +542,7 @@ impl AIController {
         match context.current_phase {
             GamePhase::MainMenu => {
                 actions.push(PrioritizedAction {
-                    action: "navigate_to_start_game".to_string(),
+                    command: AICommand::Hotkey { hotkey: GameHotkey::StartGame },
                     priority: 1.0,
                     reasoning: "Start game to begin balanced strategy".to_string(),
                     estimated_cost: 0,
@@ -396,27 +553,27 @@ impl AIController {
                 // Balance economy and military
                 if context.resource_scarcity == ResourceScarcity::Critical {
                     actions.push(PrioritizedAction {
-                        action: "focus_on_economy".to_string(),
+                        command: AICommand::Train { unit: UnitKind::Peasant },
                         priority: 0.95,
                         reasoning: "Critical resource shortage - focus on economy".to_string(),
-                        estimated_cost: 0,
+                        estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
                         time_to_complete: 3000,
                     });
                 } else if context.threat_level == ThreatLevel::High {
                     actions.push(PrioritizedAction {
-                        action: "focus_on_military".to_string(),
+                        command: AICommand::Train { unit: UnitKind::Footman },
                         priority: 0.9,
                         reasoning: "High threat - focus on military".to_string(),
-                        estimated_cost: 0,
+                        estimated_cost: UnitKind::Footman.rules()?.total_cost(),
                         time_to_complete: 4000,
                     });
                 } else {
                     // Balanced development
                     actions.push(PrioritizedAction {
-                        action: "balanced_development".to_string(),
+                        command: self.build_command(context, BuildingKind::Farm)?,
                         priority: 0.8,
                         reasoning: "Balanced approach - develop both economy and military".to_string(),
-                        estimated_cost: 0,
+                        estimated_cost: BuildingKind::Farm.rules()?.total_cost(),
                         time_to_complete: 5000,
                     });
                 }
@@ -434,7 +591,7 @@ impl AIController {
         match context.current_phase {
             GamePhase::MainMenu => {
                 actions.push(PrioritizedAction {
-                    action: "navigate_to_start_game".to_string(),
+                    command: AICommand::Hotkey { hotkey: GameHotkey::StartGame },
                     priority: 1.0,
                     reasoning: "Start game to begin economic strategy".to_string(),
                     estimated_cost: 0,
@@ -444,26 +601,26 @@ impl AIController {
             GamePhase::InGame => {
                 // Focus on economy
                 actions.push(PrioritizedAction {
-                    action: "build_town_hall".to_string(),
+                    command: self.build_command(context, BuildingKind::TownHall)?,
                     priority: 0.95,
                     reasoning: "Town hall is foundation of economy".to_string(),
-                    estimated_cost: 400,
+                    estimated_cost: BuildingKind::TownHall.rules()?.total_cost(),
                     time_to_complete: 3000,
                 });
                 
                 actions.push(PrioritizedAction {
-                    action: "build_farm".to_string(),
+                    command: self.build_command(context, BuildingKind::Farm)?,
                     priority: 0.9,
                     reasoning: "Farms provide food for population".to_string(),
-                    estimated_cost: 75,
+                    estimated_cost: BuildingKind::Farm.rules()?.total_cost(),
                     time_to_complete: 1500,
                 });
                 
                 actions.push(PrioritizedAction {
-                    action: "train_peasants".to_string(),
+                    command: AICommand::Train { unit: UnitKind::Peasant },
                     priority: 0.85,
                     reasoning: "Peasants gather resources".to_string(),
-                    estimated_cost: 75,
+                    estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
                     time_to_complete: 1000,
                 });
             }
@@ -480,7 +637,7 @@ impl AIController {
         match context.current_phase {
             GamePhase::MainMenu => {
                 actions.push(PrioritizedAction {
-                    action: "navigate_to_start_game".to_string(),
+                    command: AICommand::Hotkey { hotkey: GameHotkey::StartGame },
                     priority: 1.0,
                     reasoning: "Start game to begin rush strategy".to_string(),
                     estimated_cost: 0,
@@ -488,18 +645,18 @@ impl AIController {
                 });
             }
             GamePhase::InGame => {
-                // Fast military rush
-                if context.unit_count < 5 {
+                // Fast military rush; peasants do not count towards the army
+                if context.unit_count - context.workers < 5 {
                     actions.push(PrioritizedAction {
-                        action: "fast_military_build".to_string(),
+                        command: AICommand::Train { unit: UnitKind::Footman },
                         priority: 0.95,
                         reasoning: "Rush strategy - build military quickly".to_string(),
-                        estimated_cost: 0,
+                        estimated_cost: UnitKind::Footman.rules()?.total_cost(),
                         time_to_complete: 2000,
                     });
                 } else {
                     actions.push(PrioritizedAction {
-                        action: "rush_attack".to_string(),
+                        command: self.attack_command(context),
                         priority: 1.0,
                         reasoning: "Rush strategy - attack immediately with available units".to_string(),
                         estimated_cost: 0,
@@ -520,7 +677,7 @@ impl AIController {
         match context.current_phase {
             GamePhase::MainMenu => {
                 actions.push(PrioritizedAction {
-                    action: "navigate_to_start_game".to_string(),
+                    command: AICommand::Hotkey { hotkey: GameHotkey::StartGame },
                     priority: 1.0,
                     reasoning: "Start game to begin turtle strategy".to_string(),
                     estimated_cost: 0,
@@ -530,18 +687,18 @@ impl AIController {
             GamePhase::InGame => {
                 // Build strong defenses
                 actions.push(PrioritizedAction {
-                    action: "build_defensive_structures".to_string(),
+                    command: self.build_command(context, BuildingKind::Tower)?,
                     priority: 0.95,
                     reasoning: "Turtle strategy - build strong defenses".to_string(),
-                    estimated_cost: 0,
+                    estimated_cost: BuildingKind::Tower.rules()?.total_cost(),
                     time_to_complete: 4000,
                 });
                 
                 actions.push(PrioritizedAction {
-                    action: "expand_economy_slowly".to_string(),
+                    command: AICommand::Train { unit: UnitKind::Peasant },
                     priority: 0.8,
                     reasoning: "Turtle strategy - expand economy slowly and safely".to_string(),
-                    estimated_cost: 0,
+                    estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
                     time_to_complete: 6000,
                 });
             }
@@ -554,16 +711,23 @@ impl AIController {
     fn create_decision_context(&self, game_state: &HeadlessGameState) -> AIDecisionContext {
         let threat_level = self.calculate_threat_level(game_state);
         let resource_scarcity = self.calculate_resource_scarcity(&game_state.player_resources);
+        let Surroundings { home, enemy_positions, occupied } = surroundings(game_state);
+        let (workers, idle_workers) = workers(game_state);
         
         AIDecisionContext {
             current_phase: game_state.game_phase.clone(),
             resources: game_state.player_resources.clone(),
-            unit_count: game_state.units.len(),
-            building_count: game_state.buildings.len(),
+            unit_count: game_state.units.iter().filter(|unit| unit.owner == game_state.player).count(),
+            building_count: game_state.buildings.iter().filter(|building| building.owner == game_state.player).count(),
             threat_level,
             resource_scarcity,
             game_time: game_state.game_time,
             map_size: (game_state.map_info.width, game_state.map_info.height),
+            workers,
+            idle_workers,
+            home,
+            enemy_positions,
+            occupied,
         }
     }
     
@@ -614,7 +778,7 @@ impl AIController {
             _ => {
                 // Default actions for other phases
                 actions.push(PrioritizedAction {
-                    action: "wait".to_string(),
+                    command: AICommand::Wait { ms: 1000 },
                     priority: 0.1,
                     reasoning: "Default action for unknown phase".to_string(),
                     estimated_cost: 0,
@@ -631,14 +795,14 @@ impl AIController {
         
         // Navigate to single player
         actions.push(PrioritizedAction {
-            action: "click 400 300".to_string(), // Single Player button
+            command: AICommand::Click { x: 400, y: 300 },
             priority: 1.0,
             reasoning: "Navigate to single player".to_string(),
             estimated_cost: 0,
             time_to_complete: 1000,
         });
         actions.push(PrioritizedAction {
-            action: "wait 500".to_string(),
+            command: AICommand::Wait { ms: 500 },
             priority: 0.5,
             reasoning: "Wait for navigation".to_string(),
             estimated_cost: 0,
@@ -647,14 +811,14 @@ impl AIController {
         
         // Navigate to custom scenario
         actions.push(PrioritizedAction {
-            action: "click 400 350".to_string(), // Custom Scenario button
+            command: AICommand::Click { x: 400, y: 350 },
             priority: 1.0,
             reasoning: "Navigate to custom scenario".to_string(),
             estimated_cost: 0,
             time_to_complete: 1000,
         });
         actions.push(PrioritizedAction {
-            action: "wait 500".to_string(),
+            command: AICommand::Wait { ms: 500 },
             priority: 0.5,
             reasoning: "Wait for navigation".to_string(),
             estimated_cost: 0,
@@ -663,14 +827,14 @@ impl AIController {
         
         // Select first map
         actions.push(PrioritizedAction {
-            action: "click 300 400".to_string(), // First map in list
+            command: AICommand::Click { x: 300, y: 400 },
             priority: 1.0,
             reasoning: "Select first map".to_string(),
             estimated_cost: 0,
             time_to_complete: 1000,
         });
         actions.push(PrioritizedAction {
-            action: "wait 500".to_string(),
+            command: AICommand::Wait { ms: 500 },
             priority: 0.5,
             reasoning: "Wait for selection".to_string(),
             estimated_cost: 0,
@@ -679,14 +843,14 @@ impl AIController {
         
         // Start game
         actions.push(PrioritizedAction {
-            action: "click 500 500".to_string(), // Start button
+            command: AICommand::Click { x: 500, y: 500 },
             priority: 1.0,
             reasoning: "Start game".to_string(),
             estimated_cost: 0,
             time_to_complete: 1000,
         });
         actions.push(PrioritizedAction {
-            action: "wait 1000".to_string(),
+            command: AICommand::Wait { ms: 1000 },
             priority: 0.5,
             reasoning: "Wait for game start".to_string(),
             estimated_cost: 0,
@@ -733,34 +897,34 @@ impl AIController {
             t if t < 10000 => {
                 // Early game - focus on economy
                 actions.push(PrioritizedAction {
-                    action: "build TownHall".to_string(),
+                    command: self.build_command(context, BuildingKind::TownHall)?,
                     priority: 0.9,
                     reasoning: "Early game - need town hall".to_string(),
-                    estimated_cost: 400,
+                    estimated_cost: BuildingKind::TownHall.rules()?.total_cost(),
                     time_to_complete: 3000,
                 });
                 actions.push(PrioritizedAction {
-                    action: "train Peasant".to_string(),
+                    command: AICommand::Train { unit: UnitKind::Peasant },
                     priority: 0.8,
                     reasoning: "Early game - need workers".to_string(),
-                    estimated_cost: 75,
+                    estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
                     time_to_complete: 1000,
                 });
             }
             t if t < 30000 => {
                 // Mid game - build military
                 actions.push(PrioritizedAction {
-                    action: "build Barracks".to_string(),
+                    command: self.build_command(context, BuildingKind::Barracks)?,
                     priority: 0.9,
                     reasoning: "Mid game - need military buildings".to_string(),
-                    estimated_cost: 150,
+                    estimated_cost: BuildingKind::Barracks.rules()?.total_cost(),
                     time_to_complete: 2000,
                 });
             }
             _ => {
                 // Late game - attack
                 actions.push(PrioritizedAction {
-                    action: "attack 64 64".to_string(),
+                    command: self.attack_command(context),
                     priority: 0.95,
                     reasoning: "Late game - attack enemy".to_string(),
                     estimated_cost: 0,
@@ -778,17 +942,17 @@ impl AIController {
         // Defensive actions
         if context.threat_level == ThreatLevel::Critical {
             actions.push(PrioritizedAction {
-                action: "build Tower".to_string(),
+                command: self.build_command(context, BuildingKind::Tower)?,
                 priority: 0.9,
                 reasoning: "Tower for defense".to_string(),
-                estimated_cost: 200,
+                estimated_cost: BuildingKind::Tower.rules()?.total_cost(),
                 time_to_complete: 2000,
             });
             actions.push(PrioritizedAction {
-                action: "build Tower".to_string(),
+                command: self.build_command(context, BuildingKind::Tower)?,
                 priority: 0.8,
                 reasoning: "Tower for defense".to_string(),
-                estimated_cost: 200,
+                estimated_cost: BuildingKind::Tower.rules()?.total_cost(),
                 time_to_complete: 2000,
             });
         }
@@ -796,14 +960,14 @@ impl AIController {
         // Offensive actions
         if context.unit_count > 5 {
             actions.push(PrioritizedAction {
-                action: "select all".to_string(),
+                command: AICommand::Select { selection: Selection::AllMilitary },
                 priority: 0.9,
                 reasoning: "Attack with all units".to_string(),
                 estimated_cost: 0,
                 time_to_complete: 1000,
             });
             actions.push(PrioritizedAction {
-                action: "attack 64 64".to_string(),
+                command: self.attack_command(context),
                 priority: 0.9,
                 reasoning: "Attack enemy".to_string(),
                 estimated_cost: 0,
@@ -820,34 +984,34 @@ impl AIController {
         // Essential buildings
         if context.building_count == 0 {
             actions.push(PrioritizedAction {
-                action: "build TownHall".to_string(),
+                command: self.build_command(context, BuildingKind::TownHall)?,
                 priority: 0.9,
                 reasoning: "Town hall is essential for base defense".to_string(),
-                estimated_cost: 400,
+                estimated_cost: BuildingKind::TownHall.rules()?.total_cost(),
                 time_to_complete: 3000,
             });
         } else if context.building_count == 1 {
             actions.push(PrioritizedAction {
-                action: "build Farm".to_string(),
+                command: self.build_command(context, BuildingKind::Farm)?,
                 priority: 0.8,
                 reasoning: "Farm for food production".to_string(),
-                estimated_cost: 75,
+                estimated_cost: BuildingKind::Farm.rules()?.total_cost(),
                 time_to_complete: 1500,
             });
         } else if context.building_count == 2 {
             actions.push(PrioritizedAction {
-                action: "build Barracks".to_string(),
+                command: self.build_command(context, BuildingKind::Barracks)?,
                 priority: 0.7,
                 reasoning: "Barracks for military training".to_string(),
-                estimated_cost: 150,
+                estimated_cost: BuildingKind::Barracks.rules()?.total_cost(),
                 time_to_complete: 2000,
             });
         } else if context.building_count == 3 {
             actions.push(PrioritizedAction {
-                action: "build Blacksmith".to_string(),
+                command: self.build_command(context, BuildingKind::Blacksmith)?,
                 priority: 0.6,
                 reasoning: "Blacksmith for unit upgrades".to_string(),
-                estimated_cost: 100,
+                estimated_cost: BuildingKind::Blacksmith.rules()?.total_cost(),
                 time_to_complete: 1500,
             });
         }
@@ -861,26 +1025,26 @@ impl AIController {
         // Train workers first
         if context.unit_count < 3 {
             actions.push(PrioritizedAction {
-                action: "train Peasant".to_string(),
+                command: AICommand::Train { unit: UnitKind::Peasant },
                 priority: 0.9,
                 reasoning: "Train Peasant for resource gathering".to_string(),
-                estimated_cost: 75,
+                estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
                 time_to_complete: 1000,
             });
         } else if context.unit_count < 5 {
             actions.push(PrioritizedAction {
-                action: "train Footman".to_string(),
+                command: AICommand::Train { unit: UnitKind::Footman },
                 priority: 0.8,
                 reasoning: "Train Footman for combat".to_string(),
-                estimated_cost: 125,
+                estimated_cost: UnitKind::Footman.rules()?.total_cost(),
                 time_to_complete: 2000,
             });
         } else if context.unit_count < 8 {
             actions.push(PrioritizedAction {
-                action: "train Archer".to_string(),
+                command: AICommand::Train { unit: UnitKind::Archer },
                 priority: 0.7,
                 reasoning: "Train Archer for ranged combat".to_string(),
-                estimated_cost: 100,
+                estimated_cost: UnitKind::Archer.rules()?.total_cost(),
                 time_to_complete: 1500,
             });
         }
@@ -894,7 +1058,7 @@ impl AIController {
         // Send workers to gather resources
         if context.resources.gold < 500 {
             actions.push(PrioritizedAction {
-                action: "click 32 32".to_string(), // Gold mine
+                command: self.gather_command(context, ResourceKind::Gold),
                 priority: 0.9,
                 reasoning: "Gather gold for economy".to_string(),
                 estimated_cost: 0,
@@ -904,7 +1068,7 @@ impl AIController {
         
         if context.resources.wood < 300 {
             actions.push(PrioritizedAction {
-                action: "click 96 96".to_string(), // Forest
+                command: self.gather_command(context, ResourceKind::Wood),
                 priority: 0.8,
                 reasoning: "Gather wood for building".to_string(),
                 estimated_cost: 0,
@@ -915,15 +1079,15 @@ impl AIController {
         Ok(actions)
     }
     
-    async fn prioritize_actions(&self, actions: Vec<String>) -> Result<Vec<PrioritizedAction>> {
+    async fn prioritize_actions(&self, commands: Vec<AICommand>) -> Result<Vec<PrioritizedAction>> {
         let mut prioritized = Vec::new();
         
-        for action in actions {
-            let priority = self.calculate_action_priority(&action).await?;
-            let reasoning = self.generate_action_reasoning(&action).await?;
+        for command in commands {
+            let priority = self.calculate_action_priority(&command).await?;
+            let reasoning = self.generate_action_reasoning(&command).await?;
             
             prioritized.push(PrioritizedAction {
-                action,
+                command,
                 priority,
                 reasoning,
                 estimated_cost: 0, // Mock for now
@@ -937,55 +1101,54 @@ impl AIController {
         Ok(prioritized)
     }
     
-    async fn calculate_action_priority(&self, action: &str) -> Result<f64> {
+    async fn calculate_action_priority(&self, command: &AICommand) -> Result<f64> {
         let mut priority = 0.5; // Base priority
         
         // Adjust based on action type
-        if action.contains("attack") {
-            priority += 0.3;
-        } else if action.contains("build") {
-            priority += 0.2;
-        } else if action.contains("train") {
-            priority += 0.1;
+        match command {
+            AICommand::AttackMove { .. } => priority += 0.3,
+            AICommand::BuildAt { .. } => priority += 0.2,
+            AICommand::Train { .. } => priority += 0.1,
+            _ => {}
         }
         
         // Adjust based on personality
-        priority += (self.personality.aggression_level - 0.5) * 0.2;
+        priority += (self.profile().await.1.aggression_level - 0.5) * 0.2;
         
         Ok(priority.max(0.0).min(1.0))
     }
     
-    async fn generate_action_reasoning(&self, action: &str) -> Result<String> {
-        let reasoning = match action {
-            a if a.contains("attack") => "Military action required for victory",
-            a if a.contains("build") => "Infrastructure needed for development",
-            a if a.contains("train") => "Unit production for army building",
-            a if a.contains("click") => "Navigation or selection action",
+    async fn generate_action_reasoning(&self, command: &AICommand) -> Result<String> {
+        let reasoning = match command {
+            AICommand::AttackMove { .. } => "Military action required for victory",
+            AICommand::BuildAt { .. } => "Infrastructure needed for development",
+            AICommand::Train { .. } => "Unit production for army building",
+            AICommand::Click { .. } | AICommand::Select { .. } => "Navigation or selection action",
             _ => "Standard game action",
         };
         
         Ok(reasoning.to_string())
     }
     
-    async fn get_immediate_actions(&self, _context: &AIDecisionContext) -> Result<Vec<String>> {
+    async fn get_immediate_actions(&self, _context: &AIDecisionContext) -> Result<Vec<AICommand>> {
         let mut actions = Vec::new();
         
         // Return high-priority actions immediately
         let queue = self.action_queue.lock().await;
         for action in queue.iter().take(3) {
             if action.priority > 0.7 {
-                actions.push(action.action.clone());
+                actions.push(action.command.clone());
             }
         }
         
         Ok(actions)
     }
     
-    pub async fn add_to_action_queue(&self, action: String, priority: f64) -> Result<()> {
+    pub async fn add_to_action_queue(&self, command: AICommand, priority: f64) -> Result<()> {
         let mut queue = self.action_queue.lock().await;
         
         let prioritized_action = PrioritizedAction {
-            action,
+            command,
             priority,
             reasoning: "Manual action".to_string(),
             estimated_cost: 0,
@@ -996,214 +1159,179 @@ impl AIController {
         Ok(())
     }
     
-    pub async fn get_next_action(&self) -> Option<String> {
+    pub async fn get_next_action(&self) -> Option<AICommand> {
         let mut queue = self.action_queue.lock().await;
-        
-        if let Some(action) = queue.pop() {
-            Some(action.action)
+        queue.pop().map(|action| action.command)
+    }
+    
+    /// Plan a build order towards `goal` and queue it in place of the current queue
+    pub async fn plan_build_order(&self, start: &PlannerStart, goal: &PlanGoal) -> Result<BuildPlan> {
+        info!("🧮 Planning build order...");
+        let plan = BuildPlanner::default().plan(start, goal)?;
+        let queued = self.queue_build_plan(&plan).await;
+        if plan.meets_goal {
+            info!("✅ Build order planned: {} steps ({} queued), done at {}", plan.steps.len(), queued, clock(plan.finish));
         } else {
-            None
+            warn!("⚠️ Best build order misses the goal: {} steps ({} queued), done at {}", plan.steps.len(), queued, clock(plan.finish));
         }
+        Ok(plan)
     }
     
-    pub async fn update_personality(&mut self) {
-        match self.strategy {
-            AIStrategy::Aggressive => {
-                self.personality.aggression_level = 0.9;
-                self.personality.risk_tolerance = 0.8;
-                self.personality.patience_level = 0.3;
-            }
-            AIStrategy::Defensive => {
-                self.personality.aggression_level = 0.2;
-                self.personality.risk_tolerance = 0.1;
-                self.personality.patience_level = 0.9;
-            }
-            AIStrategy::Balanced => {
-                self.personality.aggression_level = 0.5;
-                self.personality.risk_tolerance = 0.5;
-                self.personality.patience_level = 0.5;
-            }
-            AIStrategy::Economic => {
-                self.personality.aggression_level = 0.1;
-                self.personality.resource_efficiency = 0.9;
-                self.personality.patience_level = 0.8;
-            }
-            AIStrategy::Rush => {
-                self.personality.aggression_level = 1.0;
-                self.personality.risk_tolerance = 1.0;
-                self.personality.patience_level = 0.1;
-            }
-            AIStrategy::Turtle => {
-                self.personality.aggression_level = 0.0;
-                self.personality.risk_tolerance = 0.0;
-                self.personality.patience_level = 1.0;
-            }
+    /// Replace the action queue with the steps of a build order; returns how many were queued
+    pub async fn queue_build_plan(&self, plan: &BuildPlan) -> usize {
+        let rules = GameRules::embedded();
+        let mut planned = Vec::new();
+        for (index, step) in plan.steps.iter().enumerate() {
+            // The command model names human units; orc steps use their human counterpart
+            let race = rules.unit(&step.key).map(|unit| unit.race)
+                .or_else(|| rules.upgrade(&step.key).map(|upgrade| upgrade.race));
+            let key = match race {
+                Some(Race::Orc) => rules.counterpart(&step.key).unwrap_or(&step.key),
+                _ => &step.key,
+            };
+            
+            let command = if let Some(unit) = UnitKind::from_rules_key(key) {
+                AICommand::Train { unit }
+            } else if let Some(building) = BuildingKind::from_rules_key(key) {
+                // Sited when the step is issued, against the state at that time
+                AICommand::BuildAt { building, x: 0, y: 0 }
+            } else if let Some(upgrade) = UpgradeKind::from_rules_key(key) {
+                AICommand::Research { upgrade }
+            } else {
+                warn!("⚠️ No command for build order step {} ({}), skipping", index + 1, step.name);
+                continue;
+            };
+            
+            planned.push(PrioritizedAction {
+                command,
+                priority: 1.0 - index as f64 / (2.0 * plan.steps.len() as f64),
+                reasoning: format!("Build order step {}: {} at {}", index + 1, step.name, clock(step.start)),
+                estimated_cost: step.gold + step.wood + step.oil,
+                time_to_complete: (step.finish - step.start) as u64 * 1000,
+            });
         }
+        
+        // get_next_action takes from the back, so the first step goes last
+        let mut queue = self.action_queue.lock().await;
+        queue.clear();
+        queue.extend(planned.into_iter().rev());
+        queue.len()
+    }
+    
+    /// Next step of the queued build order, left in the queue until it is issued
+    async fn peek_queued_action(&self) -> Option<PrioritizedAction> {
+        self.action_queue.lock().await.last().cloned()
     }
     
+    /// Trace of the latest enhanced decision, if not taken yet
+    pub async fn take_decision_trace(&self) -> Option<DecisionTrace> {
+        self.last_trace.lock().await.take()
+    }
+    
+    pub async fn update_personality(&mut self) {
+        self.personality.tune_for(&self.strategy);
+    }
+    
+    /// Load the strategy model and its examples from the learning file
     pub async fn load_learning_data(&self) -> Result<()> {
-        // Mock learning data loading
-        let mut learning = self.learning_data.lock().await;
-        
-        learning.push(LearningExample {
-            situation: "Low resources".to_string(),
-            action_taken: "Build Farm".to_string(),
-            outcome: "Success".to_string(),
-            success_score: 0.8,
-            timestamp: 0,
-        });
-        
+        let loaded = StrategyLearner::load(&self.learning_path)?;
+        info!("📚 Loaded {} learning examples from {}", loaded.examples.len(), self.learning_path.display());
+        *self.learning.lock().await = loaded;
         Ok(())
     }
     
+    /// Save the strategy model and its examples to the learning file
     pub async fn save_learning_data(&self) -> Result<()> {
-        // Mock learning data saving
-        let learning = self.learning_data.lock().await;
-        info!("💾 Saved {} learning examples", learning.len());
+        let learning = self.learning.lock().await;
+        learning.save(&self.learning_path)?;
+        info!("💾 Saved {} learning examples to {}", learning.examples.len(), self.learning_path.display());
         Ok(())
     }
     
     pub async fn get_status(&self) -> String {
+        let (strategy, personality) = self.profile().await;
         format!(
             "AI Controller - Strategy: {:?}, Actions Queued: {}, Personality: Aggression={:.2}, Patience={:.2}",
-            self.strategy,
+            strategy,
             self.action_queue.lock().await.len(),
-            self.personality.aggression_level,
-            self.personality.patience_level
+            personality.aggression_level,
+            personality.patience_level
         )
     }
     
-    /// Record a learning example for strategy improvement
-    pub async fn record_learning_example(&self, situation: String, action: String, outcome: String, success_score: f64) -> Result<()> {
+    /// Record a learning example: the strategy played from a situation and the reward it earned
+    pub async fn record_learning_example(&self, features: &GameFeatures, strategy: AIStrategy, reward: f64) -> Result<()> {
         let example = LearningExample {
-            situation: situation.clone(),
-            action_taken: action.clone(),
-            outcome: outcome.clone(),
-            success_score,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
+            features: features.to_vector(),
+            stage: features.stage(),
+            strategy: strategy.clone(),
+            reward,
+            timestamp: crate::strategy_bandit::now(),
         };
         
-        let mut learning_data = self.learning_data.lock().await;
-        learning_data.push(example);
+        self.learning.lock().await.record(example);
         
-        info!("📚 Recorded learning example: {} -> {} (Score: {:.2})", 
-              situation, outcome, success_score);
+        info!("📚 Recorded learning example: {:?} at {:.1} min (Reward: {:.2})", 
+              strategy, features.minutes, reward);
         
         Ok(())
     }
     
-    /// Adapt personality based on stored learning data
-    async fn adapt_personality_from_data(&mut self) -> Result<()> {
-        let learning_data = self.learning_data.lock().await;
-        
-        let recent_examples = learning_data.iter()
-            .filter(|ex| ex.timestamp > std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs() - 300) // Last 5 minutes
-            .collect::<Vec<_>>();
-        
-        if recent_examples.is_empty() {
+    /// Let the bandit pick a strategy at game start and phase changes, and pay out rewards at game end
+    async fn update_learning(&self, game_state: &HeadlessGameState) -> Result<()> {
+        if !self.adaptive {
             return Ok(());
         }
         
-        let avg_success = recent_examples.iter()
-            .map(|ex| ex.success_score)
-            .sum::<f64>() / recent_examples.len() as f64;
-        
-        // Adjust personality based on recent success
-        if avg_success > 0.8 {
-            // Doing well - become more aggressive
-            self.personality.aggression_level = (self.personality.aggression_level + 0.1).min(1.0);
-            self.personality.risk_tolerance = (self.personality.risk_tolerance + 0.05).min(1.0);
-            info!("😤 Adapting: Becoming more aggressive due to success");
-        } else if avg_success < 0.4 {
-            // Struggling - become more defensive
-            self.personality.aggression_level = (self.personality.aggression_level - 0.1).max(0.0);
-            self.personality.patience_level = (self.personality.patience_level + 0.1).min(1.0);
-            info!("🛡️ Adapting: Becoming more defensive due to failures");
+        match game_state.game_phase {
+            GamePhase::Victory => {
+                self.record_outcome(1.0).await?;
+            }
+            GamePhase::Defeat => {
+                self.record_outcome(0.0).await?;
+            }
+            // Timed out, aborted or left for the menu without a result
+            _ if game_state.has_ended() => {
+                self.record_outcome(DRAW_REWARD).await?;
+            }
+            GamePhase::InGame => {
+                let threat = self.assess_threat_level(game_state).await?;
+                let features = GameFeatures::from_game_state(game_state, &threat);
+                let stage = GameStage::of(game_state);
+                if let Some(strategy) = self.learning.lock().await.decide(&features, stage) {
+                    info!("🎰 Strategy bandit chose {:?} for the {:?}", strategy, stage);
+                }
+            }
+            _ => {}
         }
         
         Ok(())
     }
     
-    /// Analyze learning data and adapt strategy
-    pub async fn adapt_strategy(&mut self) -> Result<()> {
-        // Get learning data and analyze it
-        let (successful_count, failed_count) = {
-            let learning_data = self.learning_data.lock().await;
-            
-            let successful_count = learning_data.iter()
-                .filter(|ex| ex.success_score > 0.7)
-                .count();
-            
-            let failed_count = learning_data.iter()
-                .filter(|ex| ex.success_score < 0.3)
-                .count();
-            
-            (successful_count, failed_count)
-        };
-        
-        // Adapt strategy based on success/failure patterns
-        if successful_count > failed_count {
-            // Current strategy is working well
-            info!("🎯 Current strategy performing well, maintaining approach");
-        } else {
-            // Current strategy needs adjustment
-            info!("🔄 Current strategy underperforming, adapting...");
-            self.adapt_strategy_based_on_failures().await?;
+    /// Reward every strategy decision of the game just finished and save the model; returns the number rewarded
+    pub async fn record_outcome(&self, reward: f64) -> Result<usize> {
+        let rewarded = self.learning.lock().await.finish(reward);
+        if rewarded > 0 {
+            info!("🏁 Rewarded {} strategy decisions with {:.2}", rewarded, reward);
+            self.save_learning_data().await?;
         }
-        
-        // Adapt personality based on outcomes
-        self.adapt_personality_from_data().await?;
-        
-        Ok(())
+        Ok(rewarded)
     }
     
-    /// Adapt strategy based on failed actions (without borrowing issues)
-    async fn adapt_strategy_based_on_failures(&mut self) -> Result<()> {
-        let failed_actions = {
-            let learning_data = self.learning_data.lock().await;
-            learning_data.iter()
-                .filter(|ex| ex.success_score < 0.3)
-                .map(|ex| (ex.situation.clone(), ex.action_taken.clone()))
-                .collect::<Vec<_>>()
-        };
-        
-        // Analyze common failure patterns
-        let mut failure_patterns = std::collections::HashMap::new();
-        
-        for (situation, action) in &failed_actions {
-            let pattern = format!("{} -> {}", situation, action);
-            *failure_patterns.entry(pattern).or_insert(0) += 1;
-        }
+    /// Adopt the strategy the bandit expects to do best in the latest situation
+    pub async fn adapt_strategy(&mut self) -> Result<()> {
+        let recommended = self.learning.lock().await.recommend();
         
-        // Find most common failure pattern
-        if let Some((pattern, count)) = failure_patterns.iter().max_by_key(|&(_, count)| count) {
-            info!("🚨 Most common failure pattern: {} ({} times)", pattern, count);
-            
-            // Adapt strategy to avoid this pattern
-            match pattern.as_str() {
-                pattern if pattern.contains("attack") && pattern.contains("low_units") => {
-                    info!("🛡️ Adapting: Avoid attacking with insufficient units");
-                    self.strategy = AIStrategy::Defensive;
-                }
-                pattern if pattern.contains("economy") && pattern.contains("threat") => {
-                    info!("⚔️ Adapting: Focus on military when under threat");
-                    self.strategy = AIStrategy::Aggressive;
-                }
-                pattern if pattern.contains("rush") && pattern.contains("defense") => {
-                    info!("🏗️ Adapting: Build defenses before rushing");
-                    self.strategy = AIStrategy::Balanced;
-                }
-                _ => {
-                    info!("🔄 Adapting: Switching to balanced strategy");
-                    self.strategy = AIStrategy::Balanced;
-                }
+        match recommended {
+            Some(strategy) if strategy != self.strategy => {
+                info!("🔄 Adapting: switching from {:?} to {:?}", self.strategy, strategy);
+                self.strategy = strategy;
+                self.update_personality().await;
+            }
+            Some(_) => {
+                info!("🎯 Current strategy performing well, maintaining approach");
             }
+            None => {}
         }
         
         Ok(())
@@ -1211,22 +1339,20 @@ impl AIController {
     
     /// Get current AI status and learning progress
     pub async fn get_ai_status(&self) -> Result<AIAnalytics> {
-        let learning_data = self.learning_data.lock().await;
+        let (strategy, personality) = self.profile().await;
+        let learning = self.learning.lock().await;
         let action_queue = self.action_queue.lock().await;
         
-        let total_examples = learning_data.len();
+        let total_examples = learning.examples.len();
         let avg_success = if total_examples > 0 {
-            learning_data.iter().map(|ex| ex.success_score).sum::<f64>() / total_examples as f64
+            learning.examples.iter().map(|ex| ex.reward).sum::<f64>() / total_examples as f64
         } else {
             0.0
         };
         
-        let recent_success = learning_data.iter()
-            .filter(|ex| ex.timestamp > std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs() - 600) // Last 10 minutes
-            .map(|ex| ex.success_score)
+        let recent_success = learning.examples.iter()
+            .filter(|ex| ex.timestamp > crate::strategy_bandit::now().saturating_sub(600)) // Last 10 minutes
+            .map(|ex| ex.reward)
             .collect::<Vec<_>>();
         
         let recent_avg = if !recent_success.is_empty() {
@@ -1236,20 +1362,20 @@ impl AIController {
         };
         
         let analytics = AIAnalytics {
-            current_strategy: self.strategy.clone(),
-            personality: self.personality.clone(),
+            current_strategy: strategy,
+            personality,
             total_learning_examples: total_examples,
             overall_success_rate: avg_success,
             recent_success_rate: recent_avg,
             actions_queued: action_queue.len(),
-            adaptation_count: 0, // TODO: Track adaptation count
+            adaptation_count: learning.decisions,
         };
         
         Ok(analytics)
     }
 
     /// Enhanced unit micro-management
-    pub async fn manage_unit_formations(&self, game_state: &HeadlessGameState) -> Result<Vec<String>> {
+    pub async fn manage_unit_formations(&self, game_state: &HeadlessGameState) -> Result<Vec<AICommand>> {
         let mut actions = Vec::new();
         
         // Analyze current unit positions and create optimal formations
@@ -1257,20 +1383,13 @@ impl AIController {
         
         for formation in formations {
             match formation.formation_type {
-                FormationType::Line => {
-                    actions.push(format!("formation_line_{}_{}_{}", 
-                        formation.center_x, formation.center_y, 
-                        formation.facing_direction as u8));
-                }
-                FormationType::Wedge => {
-                    actions.push(format!("formation_wedge_{}_{}_{}", 
-                        formation.center_x, formation.center_y, 
-                        formation.facing_direction as u8));
-                }
-                FormationType::Circle => {
-                    actions.push(format!("formation_circle_{}_{}_{}", 
-                        formation.center_x, formation.center_y, 
-                        formation.units.len()));
+                FormationType::Line | FormationType::Wedge | FormationType::Circle => {
+                    actions.push(AICommand::Formation {
+                        formation: formation.formation_type,
+                        x: formation.center_x,
+                        y: formation.center_y,
+                        facing: formation.facing_direction,
+                    });
                 }
                 _ => {}
             }
@@ -1284,7 +1403,7 @@ impl AIController {
         let mut formations = Vec::new();
         
         // Group units by type for specialized formations
-        let mut unit_groups: std::collections::HashMap<String, Vec<UnitInfo>> = std::collections::HashMap::new();
+        let mut unit_groups: HashMap<String, Vec<UnitInfo>> = HashMap::new();
         
         // For now, create mock unit data
         // In real implementation, this would come from game state
@@ -1351,7 +1470,7 @@ impl AIController {
         let formation_type = match unit_type {
             "Knight" | "Paladin" => FormationType::Wedge,
             "Peasant" => FormationType::Scattered,
-            "Priest" | "Mage" => FormationType::Circle,
+            "Mage" => FormationType::Circle,
             _ => FormationType::Line,
         };
         
@@ -1381,7 +1500,7 @@ impl AIController {
         match context.threat_level {
             ThreatLevel::None => {
                 tactics.push(PrioritizedAction {
-                    action: "scout_expand".to_string(),
+                    command: self.scout_command(context),
                     priority: 0.7,
                     reasoning: "No threat, good time to expand and explore".to_string(),
                     estimated_cost: 0,
@@ -1390,7 +1509,7 @@ impl AIController {
             }
             ThreatLevel::Low => {
                 tactics.push(PrioritizedAction {
-                    action: "scout_expand".to_string(),
+                    command: self.scout_command(context),
                     priority: 0.8,
                     reasoning: "Low threat, good time to expand".to_string(),
                     estimated_cost: 0,
@@ -1399,7 +1518,7 @@ impl AIController {
             }
             ThreatLevel::Medium => {
                 tactics.push(PrioritizedAction {
-                    action: "defensive_positioning".to_string(),
+                    command: self.defend_command(context),
                     priority: 0.9,
                     reasoning: "Medium threat, maintain defensive stance".to_string(),
                     estimated_cost: 100,
@@ -1408,19 +1527,19 @@ impl AIController {
             }
             ThreatLevel::High => {
                 tactics.push(PrioritizedAction {
-                    action: "emergency_defense".to_string(),
+                    command: self.build_command(context, BuildingKind::Tower)?,
                     priority: 1.0,
                     reasoning: "High threat, immediate defensive measures".to_string(),
-                    estimated_cost: 200,
+                    estimated_cost: BuildingKind::Tower.rules()?.total_cost(),
                     time_to_complete: 1000,
                 });
             }
             ThreatLevel::Critical => {
                 tactics.push(PrioritizedAction {
-                    action: "emergency_defense".to_string(),
+                    command: self.build_command(context, BuildingKind::Tower)?,
                     priority: 1.0,
                     reasoning: "Critical threat, maximum defensive measures".to_string(),
-                    estimated_cost: 300,
+                    estimated_cost: BuildingKind::Tower.rules()?.total_cost(),
                     time_to_complete: 500,
                 });
             }
@@ -1429,7 +1548,7 @@ impl AIController {
         // Add formation-based tactics
         if context.unit_count > 10 {
             tactics.push(PrioritizedAction {
-                action: "formation_attack".to_string(),
+                command: self.formation_command(context, FormationType::Wedge),
                 priority: 0.85,
                 reasoning: "Large army, use formation tactics".to_string(),
                 estimated_cost: 0,
@@ -1444,29 +1563,61 @@ impl AIController {
     pub async fn optimize_resource_management(&self, context: &AIDecisionContext) -> Result<Vec<PrioritizedAction>> {
         let mut optimizations = Vec::new();
         
+        // Idle peasants go to whichever stockpile is running short; buildings need about half as much wood as gold
+        if context.idle_workers > 0 {
+            let resource = if context.resources.wood * 2 < context.resources.gold { ResourceKind::Wood } else { ResourceKind::Gold };
+            optimizations.push(PrioritizedAction {
+                command: self.gather_command(context, resource),
+                priority: 0.9,
+                reasoning: format!("{} idle peasants, put them to work", context.idle_workers),
+                estimated_cost: 0,
+                time_to_complete: 1000,
+            });
+        }
+        
+        if context.workers < ECONOMY_WORKERS {
+            optimizations.push(PrioritizedAction {
+                command: AICommand::Train { unit: UnitKind::Peasant },
+                priority: 0.85,
+                reasoning: format!("Only {} peasants, grow the economy", context.workers),
+                estimated_cost: UnitKind::Peasant.rules()?.total_cost(),
+                time_to_complete: 4500,
+            });
+        }
+        
+        if context.resources.food_current >= context.resources.food_max {
+            optimizations.push(PrioritizedAction {
+                command: self.build_command(context, BuildingKind::Farm)?,
+                priority: 0.95,
+                reasoning: "Out of food, nothing can be trained".to_string(),
+                estimated_cost: BuildingKind::Farm.rules()?.total_cost(),
+                time_to_complete: 5000,
+            });
+        }
+        
         // Analyze resource scarcity and optimize accordingly
         match context.resource_scarcity {
             ResourceScarcity::Abundant => {
                 optimizations.push(PrioritizedAction {
-                    action: "expand_production".to_string(),
+                    command: self.build_command(context, BuildingKind::Barracks)?,
                     priority: 0.9,
                     reasoning: "Abundant resources, expand production capacity".to_string(),
-                    estimated_cost: 300,
+                    estimated_cost: BuildingKind::Barracks.rules()?.total_cost(),
                     time_to_complete: 8000,
                 });
             }
             ResourceScarcity::Normal => {
                 optimizations.push(PrioritizedAction {
-                    action: "balance_economy".to_string(),
+                    command: self.build_command(context, BuildingKind::Farm)?,
                     priority: 0.8,
                     reasoning: "Moderate resources, maintain balanced economy".to_string(),
-                    estimated_cost: 150,
+                    estimated_cost: BuildingKind::Farm.rules()?.total_cost(),
                     time_to_complete: 5000,
                 });
             }
             ResourceScarcity::Scarce => {
                 optimizations.push(PrioritizedAction {
-                    action: "emergency_economy".to_string(),
+                    command: self.gather_command(context, ResourceKind::Gold),
                     priority: 0.95,
                     reasoning: "Scarce resources, emergency economic measures".to_string(),
                     estimated_cost: 50,
@@ -1475,7 +1626,7 @@ impl AIController {
             }
             ResourceScarcity::Critical => {
                 optimizations.push(PrioritizedAction {
-                    action: "emergency_economy".to_string(),
+                    command: self.gather_command(context, ResourceKind::Gold),
                     priority: 1.0,
                     reasoning: "Critical resource shortage, emergency measures".to_string(),
                     estimated_cost: 25,
@@ -1484,29 +1635,32 @@ impl AIController {
             }
         }
         
-        // Building placement optimization
-        if context.building_count > 5 {
-            optimizations.push(PrioritizedAction {
-                action: "optimize_building_layout".to_string(),
-                priority: 0.7,
-                reasoning: "Multiple buildings, optimize layout for efficiency".to_string(),
-                estimated_cost: 0,
-                time_to_complete: 3000,
-            });
+        Ok(optimizations)
+    }
+
+    /// Observe the backend, decide and issue the resulting commands; returns the commands issued
+    pub async fn play_turn(&self, backend: &dyn GameBackend) -> Result<Vec<AICommand>> {
+        let game_state = backend.observe().await?;
+        self.update_learning(&game_state).await?;
+        
+        let mut issued = Vec::new();
+        // Selection already dropped commands the game would misinterpret
+        for command in self.make_enhanced_decisions(&game_state).await? {
+            debug!("🤖 Executing AI command: {}", command);
+            backend.issue(&command).await?;
+            issued.push(command);
         }
         
-        Ok(optimizations)
+        Ok(issued)
     }
 
     /// Enhanced decision making with advanced behaviors
-    pub async fn make_enhanced_decisions(&self, game_state: &HeadlessGameState) -> Result<Vec<String>> {
-        let mut actions = Vec::new();
-        
+    pub async fn make_enhanced_decisions(&self, game_state: &HeadlessGameState) -> Result<Vec<AICommand>> {
         // Analyze current game state
         let context = self.analyze_game_state(game_state).await?;
         
         // Generate basic prioritized actions
-        let basic_actions = self.generate_prioritized_actions(&context).await?;
+        let basic_actions = self.generate_prioritized_actions(game_state, &context).await?;
         
         // Generate advanced combat tactics
         let combat_tactics = self.generate_advanced_combat_tactics(&context).await?;
@@ -1514,20 +1668,283 @@ impl AIController {
         // Generate resource optimizations
         let resource_optimizations = self.optimize_resource_management(&context).await?;
         
-        // Combine and prioritize all actions
-        let mut all_actions = Vec::new();
-        all_actions.extend(basic_actions);
-        all_actions.extend(combat_tactics);
-        all_actions.extend(resource_optimizations);
+        // Combine all actions, remembering where each came from
+        let mut candidates = Vec::new();
+        let sources = [
+            (ActionSource::Strategy, basic_actions),
+            (ActionSource::CombatTactic, combat_tactics),
+            (ActionSource::ResourceManagement, resource_optimizations),
+        ];
+        for (source, actions) in sources {
+            candidates.extend(actions.into_iter().map(|action| (source, action.priority, action)));
+        }
         
-        // Sort by priority and convert to action strings
-        all_actions.sort_by(|a, b| b.priority.partial_cmp(&a.priority).unwrap_or(std::cmp::Ordering::Equal));
+        // A queued build order goes first, one step at a time as the bank allows
+        let mut queued = self.peek_queued_action().await;
+        if let Some(action) = queued.as_mut() {
+            if let AICommand::BuildAt { building, .. } = action.command {
+                action.command = self.build_command(&context, building)?;
+            }
+        }
+        if let Some(action) = queued.clone() {
+            candidates.push((ActionSource::BuildOrder, 1.0 + action.priority, action));
+        }
+        
+        // Rank by score and hand out the commands that pass selection
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        let traced = self.select_actions(game_state, &mut *self.research_levels.lock().await, candidates);
         
-        for action in all_actions {
-            actions.push(action.action);
+        let commands: Vec<AICommand> = traced.iter()
+            .filter(|(_, trace)| trace.is_chosen())
+            .map(|(command, _)| command.clone())
+            .collect();
+        if queued.is_some_and(|action| commands.contains(&action.command)) {
+            self.action_queue.lock().await.pop();
         }
         
-        Ok(actions)
+        *self.last_trace.lock().await = Some(DecisionTrace {
+            game_time: game_state.game_time,
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            player: game_state.player,
+            strategy: self.profile().await.0,
+            context,
+            candidates: traced.into_iter().map(|(_, trace)| trace).collect(),
+        });
+        
+        Ok(commands)
+    }
+    
+    /// Walk ranked candidates, keeping those that are valid, new, unlocked and affordable
+    ///
+    /// Research is costed at the level after those in `research_levels`, which counts the chosen research.
+    fn select_actions(&self, game_state: &HeadlessGameState, research_levels: &mut HashMap<UpgradeKind, u8>, candidates: Vec<(ActionSource, f64, PrioritizedAction)>) -> Vec<(AICommand, CandidateTrace)> {
+        let rules = GameRules::embedded();
+        let map_size = (game_state.map_info.width, game_state.map_info.height);
+        let owned = owned_rules_keys(game_state);
+        let resources = &game_state.player_resources;
+        let (mut gold, mut wood, mut oil) = (resources.gold, resources.wood, resources.oil);
+        let mut food_used = resources.food_current;
+        let mut chosen: Vec<AICommand> = Vec::new();
+        
+        candidates.into_iter()
+            .map(|(source, score, action)| {
+                // A command without a rules entry fails validation below, so it has no cost here
+                let (key, cost) = match &action.command {
+                    AICommand::Train { unit } => (Some(unit.rules_key()), unit.rules().ok().map(|rules| (rules.gold, rules.wood, rules.oil))),
+                    AICommand::BuildAt { building, .. } => (Some(building.rules_key()), building.rules().ok().map(|rules| (rules.gold, rules.wood, rules.oil))),
+                    AICommand::Research { upgrade } => {
+                        let level = research_levels.get(upgrade).copied().unwrap_or(0) + 1;
+                        let rules = upgrade.level_rules(level);
+                        (rules.map(|rules| rules.key.as_str()), rules.map(|rules| (rules.gold, rules.wood, rules.oil)))
+                    }
+                    _ => (None, None),
+                };
+                // Research requirements are not observable, so only units and buildings are checked
+                let missing: Vec<String> = match (key, &owned) {
+                    (Some(key), Some(owned)) => rules.missing(key, owned).into_iter()
+                        .filter(|entry| rules.unit(entry).is_some())
+                        .map(str::to_string)
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                
+                let rejected = if let Err(e) = action.command.validate(map_size) {
+                    Some(Rejection::Invalid { error: e.to_string() })
+                } else if matches!(action.command, AICommand::Research { .. }) && key.is_none() {
+                    Some(Rejection::Invalid { error: format!("{} is fully researched", action.command) })
+                } else if chosen.contains(&action.command) || site_taken(&action.command, &chosen) {
+                    Some(Rejection::Duplicate)
+                } else if !missing.is_empty() {
+                    Some(Rejection::Prerequisites { missing })
+                } else if let Some((need_gold, need_wood, need_oil)) = cost.filter(|&(g, w, o)| g > gold || w > wood || o > oil) {
+                    Some(Rejection::Cost {
+                        gold: need_gold,
+                        wood: need_wood,
+                        oil: need_oil,
+                        available_gold: gold,
+                        available_wood: wood,
+                        available_oil: oil,
+                    })
+                } else if matches!(action.command, AICommand::Train { .. }) && food_used >= resources.food_max {
+                    Some(Rejection::Food { used: food_used, supply: resources.food_max })
+                } else if chosen.len() >= MAX_ACTIONS_PER_DECISION {
+                    Some(Rejection::QueueFull { limit: MAX_ACTIONS_PER_DECISION })
+                } else {
+                    None
+                };
+                
+                match &rejected {
+                    None => {
+                        if let Some((g, w, o)) = cost {
+                            gold -= g;
+                            wood -= w;
+                            oil -= o;
+                        }
+                        match action.command {
+                            AICommand::Train { .. } => food_used += 1,
+                            AICommand::Research { upgrade } => *research_levels.entry(upgrade).or_insert(0) += 1,
+                            _ => {}
+                        }
+                        chosen.push(action.command.clone());
+                    }
+                    Some(rejection) => debug!("🚫 Dropped {}: {}", action.command, rejection),
+                }
+                
+                let trace = CandidateTrace {
+                    command: action.command.to_string(),
+                    source,
+                    priority: action.priority,
+                    score,
+                    estimated_cost: action.estimated_cost,
+                    reasoning: action.reasoning,
+                    rejected,
+                };
+                (action.command, trace)
+            })
+            .collect()
+    }
+}
+
+/// Rules keys of the units and finished buildings the observed player owns
+///
+/// `None` when none of them are recognised, as then the state says nothing about prerequisites.
+fn owned_rules_keys(game_state: &HeadlessGameState) -> Option<std::collections::BTreeSet<&'static str>> {
+    let units = game_state.units.iter()
+        .filter(|unit| unit.owner == game_state.player)
+        .filter_map(|unit| unit.unit_type.parse::<UnitKind>().ok())
+        .map(UnitKind::rules_key);
+    let buildings = game_state.buildings.iter()
+        .filter(|building| building.owner == game_state.player && building.is_completed)
+        .filter_map(|building| building.building_type.parse::<BuildingKind>().ok())
+        .map(BuildingKind::rules_key);
+    let owned: std::collections::BTreeSet<&'static str> = units.chain(buildings).collect();
+    (!owned.is_empty()).then_some(owned)
+}
+
+/// Own peasants, and how many of them have no order
+fn workers(game_state: &HeadlessGameState) -> (usize, usize) {
+    let workers: Vec<&UnitInfo> = game_state.units.iter()
+        .filter(|unit| unit.owner == game_state.player)
+        .filter(|unit| unit.unit_type.parse::<UnitKind>().is_ok_and(|kind| kind == UnitKind::Peasant))
+        .collect();
+    let idle = workers.iter().filter(|unit| unit.current_action.is_none()).count();
+    (workers.len(), idle)
+}
+
+/// Whether a build lands on tiles another chosen build already claimed
+fn site_taken(command: &AICommand, chosen: &[AICommand]) -> bool {
+    let AICommand::BuildAt { building, x, y } = command else {
+        return false;
+    };
+    let Ok(rules) = building.rules() else {
+        return false;
+    };
+    let size = rules.size as i32;
+    chosen.iter().any(|other| match other {
+        AICommand::BuildAt { building: other_building, x: other_x, y: other_y } => {
+            let other_size = other_building.rules().map_or(0, |rules| rules.size as i32);
+            *other_x < x + size && other_x + other_size > *x && *other_y < y + size && other_y + other_size > *y
+        }
+        _ => false,
+    })
+}
+
+/// Where things are, as far as the decision context needs to know
+struct Surroundings {
+    home: Option<(i32, i32)>,
+    enemy_positions: Vec<(i32, i32)>,
+    occupied: Vec<(i32, i32, i32)>,
+}
+
+/// Home tile, enemy positions and building footprints seen in a game state
+fn surroundings(game_state: &HeadlessGameState) -> Surroundings {
+    let footprint = |building: &BuildingInfo| {
+        let size = building.building_type.parse::<BuildingKind>()
+            .and_then(BuildingKind::rules)
+            .map_or(3, |rules| rules.size as i32);
+        (building.position.0, building.position.1, size)
+    };
+    let own_buildings = game_state.buildings.iter().filter(|building| building.owner == game_state.player);
+    let town_hall = own_buildings.clone()
+        .find(|building| building.building_type.parse::<BuildingKind>().is_ok_and(|kind| kind == BuildingKind::TownHall))
+        .or_else(|| own_buildings.clone().next())
+        .map(|building| {
+            let (x, y, size) = footprint(building);
+            (x + size / 2, y + size / 2)
+        });
+    let home = town_hall.or_else(|| {
+        game_state.units.iter().find(|unit| unit.owner == game_state.player).map(|unit| unit.position)
+    });
+    
+    let is_enemy = |owner: u8| owner != game_state.player && owner != NEUTRAL_PLAYER;
+    let from_home = |&(x, y): &(i32, i32)| {
+        let (hx, hy) = home.unwrap_or((x, y));
+        (x - hx).pow(2) + (y - hy).pow(2)
+    };
+    let mut buildings: Vec<(i32, i32)> = game_state.buildings.iter()
+        .filter(|building| is_enemy(building.owner))
+        .map(|building| building.position)
+        .collect();
+    let mut units: Vec<(i32, i32)> = game_state.units.iter()
+        .filter(|unit| is_enemy(unit.owner))
+        .map(|unit| unit.position)
+        .collect();
+    buildings.sort_by_key(from_home);
+    units.sort_by_key(from_home);
+    // Start positions come last, farthest first, as one of them is our own
+    let mut starts = game_state.map_info.starting_positions.clone();
+    starts.sort_by_key(|start| std::cmp::Reverse(from_home(start)));
+    starts.retain(|&start| home.is_none() || from_home(&start) > 0);
+    let enemy_positions = buildings.into_iter().chain(units).chain(starts).collect();
+    
+    let occupied = game_state.buildings.iter().map(footprint).collect();
+    Surroundings { home, enemy_positions, occupied }
+}
+
+impl AIPersonality {
+    /// Default personality tuned for a strategy
+    pub fn for_strategy(strategy: &AIStrategy) -> Self {
+        let mut personality = Self::default();
+        personality.tune_for(strategy);
+        personality
+    }
+    
+    /// Set the traits a strategy cares about, leaving the rest as they are
+    pub fn tune_for(&mut self, strategy: &AIStrategy) {
+        match strategy {
+            AIStrategy::Aggressive => {
+                self.aggression_level = 0.9;
+                self.risk_tolerance = 0.8;
+                self.patience_level = 0.3;
+            }
+            AIStrategy::Defensive => {
+                self.aggression_level = 0.2;
+                self.risk_tolerance = 0.1;
+                self.patience_level = 0.9;
+            }
+            AIStrategy::Balanced => {
+                self.aggression_level = 0.5;
+                self.risk_tolerance = 0.5;
+                self.patience_level = 0.5;
+            }
+            AIStrategy::Economic => {
+                self.aggression_level = 0.1;
+                self.resource_efficiency = 0.9;
+                self.patience_level = 0.8;
+            }
+            AIStrategy::Rush => {
+                self.aggression_level = 1.0;
+                self.risk_tolerance = 1.0;
+                self.patience_level = 0.1;
+            }
+            AIStrategy::Turtle => {
+                self.aggression_level = 0.0;
+                self.risk_tolerance = 0.0;
+                self.patience_level = 1.0;
+            }
+            // Scripts decide for themselves
+            AIStrategy::Scripted(_) => {}
+        }
     }
 }
 
@@ -1542,3 +1959,55 @@ impl Default for AIPersonality {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_engine::GameStatus;
+
+    #[tokio::test]
+    async fn test_timed_out_game_rewards_decisions_as_draw() {
+        let path = std::env::temp_dir().join(format!("wc2ai-draw-{}.json", std::process::id()));
+        let mut controller = AIController::new().await.unwrap();
+        controller.set_learning_path(&path);
+
+        let mut state = HeadlessGameState { game_phase: GamePhase::InGame, ..HeadlessGameState::default() };
+        controller.update_learning(&state).await.unwrap();
+        assert!(controller.learning.lock().await.has_pending());
+
+        // The simulator reports a timeout as a completed game still in progress
+        state.game_status = GameStatus::Completed;
+        controller.update_learning(&state).await.unwrap();
+        let learning = controller.learning.lock().await;
+        assert!(!learning.has_pending());
+        assert_eq!(learning.examples.iter().map(|example| example.reward).collect::<Vec<_>>(), vec![DRAW_REWARD]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_research_is_costed_by_level() {
+        let controller = AIController::new().await.unwrap();
+        let mut state = HeadlessGameState::default();
+        state.player_resources.gold = 2000;
+        let research = || PrioritizedAction {
+            command: AICommand::Research { upgrade: UpgradeKind::MeleeWeapons },
+            priority: 1.0,
+            reasoning: String::new(),
+            estimated_cost: 0,
+            time_to_complete: 0,
+        };
+        let mut levels = HashMap::new();
+
+        // Swords 1 costs 800 gold, swords 2 costs 2400 and there is no third level
+        let traced = controller.select_actions(&state, &mut levels, vec![(ActionSource::Strategy, 1.0, research())]);
+        assert!(traced[0].1.is_chosen());
+        let traced = controller.select_actions(&state, &mut levels, vec![(ActionSource::Strategy, 1.0, research())]);
+        assert!(matches!(traced[0].1.rejected, Some(Rejection::Cost { gold: 2400, .. })), "{:?}", traced[0].1.rejected);
+
+        state.player_resources.gold = 3000;
+        let traced = controller.select_actions(&state, &mut levels, vec![(ActionSource::Strategy, 1.0, research())]);
+        assert!(traced[0].1.is_chosen());
+        let traced = controller.select_actions(&state, &mut levels, vec![(ActionSource::Strategy, 1.0, research())]);
+        assert!(matches!(traced[0].1.rejected, Some(Rejection::Invalid { .. })));
+    }
+}
@@ -0,0 +1,1112 @@
+//! Build-order planner
+//!
+//! Searches for a timed build order that takes a starting economy to a goal
+//! such as "2 barracks + 6 grunts by 4:00" or "max army value at 8:00". The
+//! economy model is coarse but uses the simulator's figures: every worker
+//! shuttles between the hall and a mine or the tree line, production
+//! buildings work through one item at a time and a worker stops gathering
+//! while its building goes up. Partial orders are grown with a beam search
+//! over the tech tree from the game rules, and branches that cannot beat the
+//! best complete order are cut.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use wc2_shared::game_rules::{GameRules, Race, UnitClass};
+
+use crate::ai_command::{normalize, BuildingKind, UnitKind};
+use crate::game_engine::HeadlessGameState;
+use crate::simulator::{CARRY_AMOUNT, GOLD_MINING_TICKS, MAX_FOOD, TICKS_PER_SECOND, WOOD_CHOPPING_TICKS};
+
+/// Most workers a plan will own
+const MAX_WORKERS: u32 = 30;
+
+/// Most copies of one production building a plan will own
+const MAX_PRODUCERS: u32 = 4;
+
+/// Spare food beyond which no further farm is considered
+const FOOD_HEADROOM: u32 = 8;
+
+/// Most steps in one plan
+const MAX_STEPS: usize = 80;
+
+/// Least time past the deadline a goal may still be reached in
+const LATE_GOAL_SECS: u32 = 600;
+
+/// Economy a plan starts from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannerStart {
+    pub race: Race,
+    pub gold: u32,
+    pub wood: u32,
+    pub oil: u32,
+    /// Workers free for gathering and construction
+    pub workers: u32,
+    /// Finished buildings, other units and research by rules key
+    pub owned: BTreeMap<String, u32>,
+    /// Walk from the hall to the gold mine, in tiles
+    pub mine_distance: f64,
+    /// Walk from the hall to the nearest trees, in tiles
+    pub wood_distance: f64,
+    /// Game time the plan starts at, in seconds
+    pub time: u32,
+}
+
+impl PlannerStart {
+    /// The simulator's melee start: a hall, one worker and the default bank
+    pub fn melee(race: Race) -> Self {
+        let config = crate::simulator::SimConfig::default();
+        Self {
+            race,
+            gold: config.starting_gold,
+            wood: config.starting_wood,
+            oil: config.starting_oil,
+            workers: 1,
+            owned: BTreeMap::from([(hall_key(race).to_string(), 1)]),
+            mine_distance: 3.0,
+            wood_distance: 5.0,
+            time: 0,
+        }
+    }
+
+    /// Economy of `player` as observed through a backend
+    pub fn from_game_state(state: &HeadlessGameState, player: u8, race: Race) -> Self {
+        let rules = GameRules::embedded();
+        let mut start = Self {
+            gold: state.player_resources.gold,
+            wood: state.player_resources.wood,
+            oil: state.player_resources.oil,
+            workers: 0,
+            owned: BTreeMap::new(),
+            time: (state.game_time / TICKS_PER_SECOND as u64) as u32,
+            ..Self::melee(race)
+        };
+
+        // Backends report command-model names, which follow the human side
+        let own_key = |key: &'static str| -> String {
+            if race == Race::Human {
+                key.to_string()
+            } else {
+                rules.counterpart(key).unwrap_or(key).to_string()
+            }
+        };
+
+        for unit in state.units.iter().filter(|unit| unit.owner == player) {
+            let Ok(kind) = UnitKind::from_str(&unit.unit_type) else {
+                continue;
+            };
+            if kind == UnitKind::Peasant {
+                start.workers += 1;
+            } else {
+                *start.owned.entry(own_key(kind.rules_key())).or_insert(0) += 1;
+            }
+        }
+
+        let mut hall = None;
+        let mut mines = Vec::new();
+        for building in &state.buildings {
+            let Ok(kind) = BuildingKind::from_str(&building.building_type) else {
+                continue;
+            };
+            if kind == BuildingKind::Mine {
+                mines.push(building.position);
+            } else if building.owner == player && building.is_completed {
+                if kind == BuildingKind::TownHall {
+                    hall.get_or_insert(building.position);
+                }
+                *start.owned.entry(own_key(kind.rules_key())).or_insert(0) += 1;
+            }
+        }
+
+        // Positions are top-left corners; a hall is 4 tiles and a mine 3
+        if let Some((hx, hy)) = hall {
+            let gap = mines.iter()
+                .map(|&(mx, my)| ((mx + 1 - (hx + 2)).abs().max((my + 1 - (hy + 2)).abs()) - 3).max(1))
+                .min();
+            if let Some(gap) = gap {
+                start.mine_distance = gap as f64;
+            }
+        }
+
+        start
+    }
+}
+
+/// What a plan should achieve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PlanGoal {
+    /// Own at least these counts of units, buildings or research by `deadline` seconds
+    Reach { targets: BTreeMap<String, u32>, deadline: u32 },
+    /// Largest total cost of finished combat units at `at` seconds
+    MaxArmyValue { at: u32 },
+}
+
+impl FromStr for PlanGoal {
+    type Err = anyhow::Error;
+
+    /// Parses "2 barracks + 6 grunts by 4:00" or "max army value at 8:00"
+    fn from_str(s: &str) -> Result<Self> {
+        let text = s.trim().to_lowercase();
+        if let Some(time) = text.strip_prefix("max army value at") {
+            return Ok(PlanGoal::MaxArmyValue { at: parse_clock(time)? });
+        }
+
+        let (items, deadline) = text.rsplit_once(" by ")
+            .ok_or_else(|| anyhow!("Goal needs a deadline such as 'by 4:00': {}", s))?;
+        let mut targets = BTreeMap::new();
+        for item in items.split('+').map(str::trim) {
+            let (count, name) = match item.split_once(' ') {
+                Some((count, name)) if count.parse::<u32>().is_ok() => (count.parse()?, name),
+                _ => (1, item),
+            };
+            *targets.entry(rules_key_for_name(GameRules::embedded(), name)?).or_insert(0) += count;
+        }
+        Ok(PlanGoal::Reach { targets, deadline: parse_clock(deadline)? })
+    }
+}
+
+/// One item of a build order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// Seconds at which the item is started
+    pub start: u32,
+    /// Seconds at which the item is finished
+    pub finish: u32,
+    pub key: String,
+    pub name: String,
+    pub gold: u32,
+    pub wood: u32,
+    pub oil: u32,
+}
+
+/// A timed build order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildPlan {
+    pub steps: Vec<PlanStep>,
+    /// Seconds at which the goal is met, or the evaluation time for army goals
+    pub finish: u32,
+    /// Total cost of combat units finished by `finish`
+    pub army_value: u32,
+    /// Whether the plan meets the goal's deadline
+    pub meets_goal: bool,
+}
+
+impl fmt::Display for BuildPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{:>6}  {}", clock(step.start), step.name)?;
+        }
+        write!(
+            f,
+            "{} steps, done at {}, army value {}{}",
+            self.steps.len(),
+            clock(self.finish),
+            self.army_value,
+            if self.meets_goal { "" } else { " (misses the goal)" },
+        )
+    }
+}
+
+/// Worker trip figures behind the gathering rates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EconomyModel {
+    /// Resources returned per trip
+    pub carry: u32,
+    /// Seconds spent inside the mine per trip
+    pub mining_secs: f64,
+    /// Seconds spent chopping per trip
+    pub chopping_secs: f64,
+    /// Share of workers sent to chop wood
+    pub wood_share: f64,
+}
+
+impl Default for EconomyModel {
+    fn default() -> Self {
+        Self {
+            carry: CARRY_AMOUNT,
+            mining_secs: GOLD_MINING_TICKS as f64 / TICKS_PER_SECOND as f64,
+            chopping_secs: WOOD_CHOPPING_TICKS as f64 / TICKS_PER_SECOND as f64,
+            wood_share: 0.35,
+        }
+    }
+}
+
+/// Beam search over build orders
+pub struct BuildPlanner {
+    pub rules: Arc<GameRules>,
+    pub economy: EconomyModel,
+    /// Partial orders kept after each step
+    pub beam_width: usize,
+}
+
+impl Default for BuildPlanner {
+    fn default() -> Self {
+        Self::new(Arc::new(GameRules::embedded().clone()))
+    }
+}
+
+impl BuildPlanner {
+    pub fn new(rules: Arc<GameRules>) -> Self {
+        Self {
+            rules,
+            economy: EconomyModel::default(),
+            beam_width: 24,
+        }
+    }
+
+    /// Search for a build order that takes `start` to `goal`
+    pub fn plan(&self, start: &PlannerStart, goal: &PlanGoal) -> Result<BuildPlan> {
+        if start.race == Race::Neutral {
+            bail!("Cannot plan for the neutral side");
+        }
+        let search = Search::new(&self.rules, &self.economy, start, goal)?;
+        let initial = search.initial(start);
+
+        let mut beam = vec![initial];
+        let mut best: Option<(State, f64)> = None;
+        for _ in 0..MAX_STEPS {
+            let mut children = Vec::new();
+            for state in &beam {
+                for key in search.options(state) {
+                    if let Some(child) = search.try_start(state, key) {
+                        children.push(child);
+                    }
+                }
+            }
+
+            let mut frontier: HashMap<String, (State, f64)> = HashMap::new();
+            for child in children {
+                let evaluation = search.evaluate(&child);
+                if let Some(value) = evaluation.value {
+                    // Of equally good orders keep the one that leaves the stronger economy
+                    let better = best.as_ref().is_none_or(|(best_state, best_value)| {
+                        value > *best_value || (value == *best_value && search.workers(&child) > search.workers(best_state))
+                    });
+                    if better {
+                        best = Some((child.clone(), value));
+                    }
+                }
+                let Some(score) = evaluation.score else {
+                    continue;
+                };
+                if best.as_ref().is_some_and(|(_, best_value)| evaluation.bound <= *best_value) {
+                    continue;
+                }
+                let signature = child.signature();
+                if frontier.get(&signature).is_none_or(|(_, kept)| score > *kept) {
+                    frontier.insert(signature, (child, score));
+                }
+            }
+            if frontier.is_empty() {
+                break;
+            }
+
+            let mut ranked: Vec<(State, f64)> = frontier.into_values().collect();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+            ranked.truncate(self.beam_width.max(1));
+            beam = ranked.into_iter().map(|(state, _)| state).collect();
+        }
+
+        let (state, _) = best.ok_or_else(|| anyhow!("No build order reaches the goal before {}", clock(search.horizon)))?;
+        Ok(search.finish(state))
+    }
+}
+
+/// Key of the race's starting hall
+fn hall_key(race: Race) -> &'static str {
+    match race {
+        Race::Orc => "great_hall",
+        _ => "town_hall",
+    }
+}
+
+/// Key of the race's worker
+fn worker_key(race: Race) -> &'static str {
+    match race {
+        Race::Orc => "peon",
+        _ => "peasant",
+    }
+}
+
+/// Key of the race's farm
+fn farm_key(race: Race) -> &'static str {
+    match race {
+        Race::Orc => "pig_farm",
+        _ => "farm",
+    }
+}
+
+/// "m:ss" for a number of seconds
+pub fn clock(secs: u32) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Seconds from "m:ss" or a plain number of seconds
+pub fn parse_clock(text: &str) -> Result<u32> {
+    let text = text.trim();
+    let secs = match text.split_once(':') {
+        Some((minutes, seconds)) => minutes.parse::<u32>().ok()
+            .zip(seconds.parse::<u32>().ok().filter(|&seconds| seconds < 60))
+            .map(|(minutes, seconds)| minutes * 60 + seconds),
+        None => text.parse().ok(),
+    };
+    secs.ok_or_else(|| anyhow!("Invalid time '{}', expected m:ss or seconds", text))
+}
+
+/// Rules key for a unit, building or research named by key or display name, singular or plural
+fn rules_key_for_name(rules: &GameRules, name: &str) -> Result<String> {
+    let wanted = normalize(name);
+    let mut spellings = vec![wanted.clone()];
+    if let Some(stem) = wanted.strip_suffix("men") {
+        spellings.push(format!("{}man", stem));
+    }
+    if let Some(stem) = wanted.strip_suffix('s') {
+        spellings.push(stem.to_string());
+    }
+
+    let entries = rules.units.iter().map(|unit| (&unit.key, &unit.name))
+        .chain(rules.upgrades.iter().map(|upgrade| (&upgrade.key, &upgrade.name)));
+    let entries: Vec<_> = entries.collect();
+    spellings.iter()
+        .find_map(|spelling| {
+            entries.iter()
+                .find(|(key, _)| normalize(key) == *spelling)
+                .or_else(|| entries.iter().find(|(_, entry)| normalize(entry) == *spelling))
+        })
+        .map(|(key, _)| key.to_string())
+        .ok_or_else(|| anyhow!("Unknown unit, building or research: {}", name))
+}
+
+/// Something in production
+#[derive(Debug, Clone)]
+struct Job {
+    key: String,
+    done_at: u32,
+    /// Whether a worker is off gathering until this is done
+    builder: bool,
+}
+
+/// A building that trains, researches or upgrades one item at a time
+#[derive(Debug, Clone)]
+struct Producer {
+    key: String,
+    free_at: u32,
+}
+
+/// A partial build order and the economy it leads to
+#[derive(Debug, Clone)]
+struct State {
+    time: u32,
+    gold: f64,
+    wood: f64,
+    oil: u32,
+    gold_workers: u32,
+    wood_workers: u32,
+    owned: BTreeMap<String, u32>,
+    jobs: Vec<Job>,
+    producers: Vec<Producer>,
+    food_used: u32,
+    steps: Vec<PlanStep>,
+}
+
+impl State {
+    fn owns(&self, key: &str) -> bool {
+        self.owned.get(key).is_some_and(|&count| count > 0)
+    }
+
+    /// Owned plus in production
+    fn count(&self, key: &str) -> u32 {
+        self.owned.get(key).copied().unwrap_or(0) + self.jobs.iter().filter(|job| job.key == key).count() as u32
+    }
+
+    /// Earliest time a job for any of `keys` is done
+    fn next_done(&self, matches: impl Fn(&Job) -> bool) -> Option<u32> {
+        self.jobs.iter().filter(|job| matches(job)).map(|job| job.done_at).min()
+    }
+
+    /// Same holdings, finishing at the same times; orders that differ only in
+    /// the sequence items were started in lead to the same state
+    fn signature(&self) -> String {
+        let mut jobs: Vec<(&str, u32)> = self.jobs.iter().map(|job| (job.key.as_str(), job.done_at)).collect();
+        jobs.sort_unstable();
+        format!("{}|{:?}|{:?}|{}", self.time, self.owned, jobs, self.gold_workers + self.wood_workers)
+    }
+}
+
+/// What the search makes of a partial order
+struct Evaluation {
+    /// Value of stopping here, if that is a valid plan; higher is better
+    value: Option<f64>,
+    /// Rank among orders worth extending; None when extending cannot help
+    score: Option<f64>,
+    /// Best value any extension could reach
+    bound: f64,
+}
+
+/// Cost and production figures of one plannable item
+struct Item<'a> {
+    key: &'a str,
+    name: &'a str,
+    gold: u32,
+    wood: u32,
+    oil: u32,
+    secs: u32,
+    food: u32,
+    produced_at: &'a [String],
+    requires: &'a [String],
+    /// Built by a worker rather than trained, researched or upgraded in a building
+    by_worker: bool,
+}
+
+/// One planning run
+struct Search<'a> {
+    rules: &'a GameRules,
+    race: Race,
+    worker: &'static str,
+    farm: &'static str,
+    /// Resources per second from one worker on each resource
+    gold_rate: f64,
+    wood_rate: f64,
+    wood_share: f64,
+    horizon: u32,
+    /// Items the search may start, with the most it may own of each
+    caps: BTreeMap<&'a str, u32>,
+    /// Combat units counted towards army value
+    army: Vec<&'a str>,
+    targets: BTreeMap<&'a str, u32>,
+    deadline: u32,
+}
+
+impl<'a> Search<'a> {
+    fn new(rules: &'a GameRules, economy: &EconomyModel, start: &PlannerStart, goal: &PlanGoal) -> Result<Self> {
+        let race = start.race;
+        let worker = worker_key(race);
+        let tiles_per_sec = rules.unit(worker)
+            .map(|unit| unit.speed as f64 * TICKS_PER_SECOND as f64 / 100.0)
+            .filter(|&speed| speed > 0.0)
+            .unwrap_or(1.0);
+        let trip_rate = |distance: f64, work: f64| economy.carry as f64 / (2.0 * distance / tiles_per_sec + work);
+
+        let army: Vec<&str> = rules.units_of(race, UnitClass::Unit)
+            .filter(|unit| unit.key != worker && unit.basic_damage + unit.piercing_damage > 0 && !unit.produced_at.is_empty())
+            .map(|unit| unit.key.as_str())
+            .collect();
+
+        let mut search = Self {
+            rules,
+            race,
+            worker,
+            farm: farm_key(race),
+            gold_rate: trip_rate(start.mine_distance.max(0.0), economy.mining_secs),
+            wood_rate: trip_rate(start.wood_distance.max(0.0), economy.chopping_secs),
+            wood_share: economy.wood_share.clamp(0.0, 1.0),
+            horizon: 0,
+            caps: BTreeMap::new(),
+            army,
+            targets: BTreeMap::new(),
+            deadline: 0,
+        };
+
+        match goal {
+            PlanGoal::Reach { targets, deadline } => {
+                for (key, &count) in targets {
+                    let key = search.own_key(key)?;
+                    let oil = search.oil_needed(key, start) + search.item(key).map_or(0, |item| item.oil) * count.saturating_sub(1);
+                    if oil > start.oil {
+                        bail!("{} needs {} oil but only {} is banked, and the planner does not drill for oil", key, oil, start.oil);
+                    }
+                    search.targets.insert(key, count);
+                    search.allow(key, count);
+                }
+                search.deadline = *deadline;
+                // Room to report how late an order that cannot make the deadline would be
+                search.horizon = start.time.max(*deadline) + (*deadline).max(LATE_GOAL_SECS);
+            }
+            PlanGoal::MaxArmyValue { at } => {
+                // Only armies that need no oil; the model does not drill for it
+                for key in search.army.clone() {
+                    if search.oil_needed(key, start) <= start.oil {
+                        search.allow(key, MAX_FOOD);
+                    }
+                }
+                search.deadline = *at;
+                search.horizon = *at;
+            }
+        }
+        search.caps.insert(search.worker, MAX_WORKERS);
+        search.caps.insert(search.farm, MAX_FOOD);
+        Ok(search)
+    }
+
+    /// This race's key for an entry of either race
+    fn own_key(&self, key: &str) -> Result<&'a str> {
+        let entry_race = self.rules.unit(key).map(|unit| unit.race)
+            .or_else(|| self.rules.upgrade(key).map(|upgrade| upgrade.race))
+            .ok_or_else(|| anyhow!("Unknown unit, building or research: {}", key))?;
+        let key = if entry_race == self.race || entry_race == Race::Neutral {
+            key
+        } else {
+            self.rules.counterpart(key).ok_or_else(|| anyhow!("{} has no {:?} equivalent", key, self.race))?
+        };
+        self.rules.unit(key).map(|unit| unit.key.as_str())
+            .or_else(|| self.rules.upgrade(key).map(|upgrade| upgrade.key.as_str()))
+            .ok_or_else(|| anyhow!("Unknown unit, building or research: {}", key))
+    }
+
+    /// Oil for one `key` and whatever it needs that `start` does not own yet
+    fn oil_needed(&self, key: &str, start: &PlannerStart) -> u32 {
+        let missing: u32 = self.rules.prerequisites(key).into_iter()
+            .filter(|prerequisite| !start.owned.contains_key(*prerequisite))
+            .filter_map(|prerequisite| self.item(prerequisite))
+            .map(|item| item.oil)
+            .sum();
+        missing + self.item(key).map_or(0, |item| item.oil)
+    }
+
+    /// Let the search make up to `count` of `key`, its prerequisites and enough producers
+    fn allow(&mut self, key: &'a str, count: u32) {
+        let cap = self.caps.entry(key).or_insert(0);
+        *cap = (*cap).max(count);
+        for prerequisite in self.rules.prerequisites(key) {
+            if prerequisite != self.worker {
+                self.caps.entry(prerequisite).or_insert(1);
+            }
+        }
+        // A few more producers for anything made in numbers
+        if let Some(producer) = self.item(key).filter(|item| !item.by_worker).and_then(|item| item.produced_at.first()) {
+            if let Some(producer) = self.rules.unit(producer) {
+                let cap = self.caps.entry(producer.key.as_str()).or_insert(1);
+                *cap = (*cap).max(count.div_ceil(3).clamp(1, MAX_PRODUCERS));
+            }
+        }
+    }
+
+    fn item(&self, key: &str) -> Option<Item<'a>> {
+        if let Some(unit) = self.rules.unit(key) {
+            return Some(Item {
+                key: unit.key.as_str(),
+                name: unit.name.as_str(),
+                gold: unit.gold,
+                wood: unit.wood,
+                oil: unit.oil,
+                secs: unit.build_time.max(1),
+                food: unit.food,
+                produced_at: &unit.produced_at,
+                requires: &unit.requires,
+                by_worker: unit.produced_at.iter().any(|producer| producer == self.worker),
+            });
+        }
+        self.rules.upgrade(key).map(|upgrade| Item {
+            key: upgrade.key.as_str(),
+            name: upgrade.name.as_str(),
+            gold: upgrade.gold,
+            wood: upgrade.wood,
+            oil: upgrade.oil,
+            secs: upgrade.research_time.max(1),
+            food: 0,
+            produced_at: std::slice::from_ref(&upgrade.researched_at),
+            requires: &upgrade.requires,
+            by_worker: false,
+        })
+    }
+
+    fn initial(&self, start: &PlannerStart) -> State {
+        let mut state = State {
+            time: start.time,
+            gold: start.gold as f64,
+            wood: start.wood as f64,
+            oil: start.oil,
+            gold_workers: 0,
+            wood_workers: 0,
+            owned: start.owned.clone(),
+            jobs: Vec::new(),
+            producers: Vec::new(),
+            food_used: 0,
+            steps: Vec::new(),
+        };
+        // Workers listed with the other units count as free workers
+        let workers = start.workers + state.owned.remove(self.worker).unwrap_or(0);
+        for _ in 0..workers {
+            self.add_worker(&mut state);
+        }
+        let (mut food_used, mut producers) = (0, Vec::new());
+        for (key, &count) in &state.owned {
+            let Some(unit) = self.rules.unit(key) else {
+                continue;
+            };
+            food_used += unit.food * count;
+            if unit.is_building() {
+                producers.extend((0..count).map(|_| Producer { key: key.clone(), free_at: start.time }));
+            }
+        }
+        state.food_used = food_used + self.rules.unit(self.worker).map_or(1, |unit| unit.food) * workers;
+        state.producers = producers;
+        state
+    }
+
+    fn food_supply(&self, state: &State) -> u32 {
+        let supply: u32 = state.owned.iter()
+            .filter_map(|(key, &count)| self.rules.unit(key).map(|unit| unit.food_supply * count))
+            .sum();
+        supply.min(MAX_FOOD)
+    }
+
+    fn workers(&self, state: &State) -> u32 {
+        state.gold_workers + state.wood_workers
+            + state.jobs.iter().filter(|job| job.builder || job.key == self.worker).count() as u32
+    }
+
+    fn income(&self, state: &State) -> f64 {
+        state.gold_workers as f64 * self.gold_rate + state.wood_workers as f64 * self.wood_rate
+    }
+
+    /// Resources per second from a worker split between gold and wood as planned
+    fn worker_rate(&self) -> f64 {
+        self.gold_rate * (1.0 - self.wood_share) + self.wood_rate * self.wood_share
+    }
+
+    /// Resources gathered from now until `until`, counting workers still in training or building
+    fn gathered_by(&self, state: &State, until: u32) -> f64 {
+        let returning: f64 = state.jobs.iter()
+            .filter(|job| job.builder || job.key == self.worker)
+            .map(|job| until.saturating_sub(job.done_at) as f64 * self.worker_rate())
+            .sum();
+        self.income(state) * until.saturating_sub(state.time) as f64 + returning
+    }
+
+    /// Most resources that could be gathered from now until `until`: every worker on the
+    /// richer resource and every hall training workers back to back, ignoring their cost and food
+    fn most_gathered_by(&self, state: &State, until: u32) -> f64 {
+        let rate = self.gold_rate.max(self.wood_rate);
+        let secs = self.item(self.worker).map_or(1, |item| item.secs);
+        let mut workers = self.workers(state);
+        let mut gathered = (state.gold_workers + state.wood_workers) as f64 * rate * until.saturating_sub(state.time) as f64;
+        gathered += state.jobs.iter()
+            .filter(|job| job.builder || job.key == self.worker)
+            .map(|job| until.saturating_sub(job.done_at) as f64 * rate)
+            .sum::<f64>();
+
+        let halls = self.item(self.worker).map_or(&[][..], |item| item.produced_at);
+        let mut ready: Vec<u32> = state.producers.iter()
+            .filter(|producer| halls.contains(&producer.key))
+            .map(|producer| producer.free_at.max(state.time))
+            .collect();
+        while workers < MAX_WORKERS {
+            let Some(next) = ready.iter_mut().min() else {
+                break;
+            };
+            *next += secs;
+            if *next >= until {
+                break;
+            }
+            gathered += (until - *next) as f64 * rate;
+            workers += 1;
+        }
+        gathered
+    }
+
+    fn add_worker(&self, state: &mut State) {
+        let total = state.gold_workers + state.wood_workers + 1;
+        if (state.wood_workers as f64) < (total as f64 * self.wood_share).round() {
+            state.wood_workers += 1;
+        } else {
+            state.gold_workers += 1;
+        }
+    }
+
+    /// Take a worker off gathering, from whichever resource has more
+    fn take_worker(&self, state: &mut State) {
+        if state.gold_workers > state.wood_workers {
+            state.gold_workers -= 1;
+        } else {
+            state.wood_workers -= 1;
+        }
+    }
+
+    /// Gather until `until` without finishing anything
+    fn accrue(&self, state: &mut State, until: u32) {
+        let secs = until.saturating_sub(state.time) as f64;
+        state.gold += state.gold_workers as f64 * self.gold_rate * secs;
+        state.wood += state.wood_workers as f64 * self.wood_rate * secs;
+        state.time = state.time.max(until);
+    }
+
+    /// Move time forward to `until`, finishing jobs on the way
+    fn advance(&self, state: &mut State, until: u32) {
+        while let Some(index) = (0..state.jobs.len())
+            .filter(|&index| state.jobs[index].done_at <= until)
+            .min_by_key(|&index| state.jobs[index].done_at)
+        {
+            let job = state.jobs.remove(index);
+            self.accrue(state, job.done_at);
+            self.complete(state, job);
+        }
+        self.accrue(state, until);
+    }
+
+    fn complete(&self, state: &mut State, job: Job) {
+        if job.builder {
+            self.add_worker(state);
+        }
+        if job.key == self.worker {
+            self.add_worker(state);
+            return;
+        }
+
+        if let Some(unit) = self.rules.unit(&job.key).filter(|unit| unit.is_building()) {
+            // Keeps and guard towers replace the building they were upgraded from
+            let upgraded = state.producers.iter_mut()
+                .find(|producer| producer.free_at == job.done_at && unit.produced_at.contains(&producer.key));
+            match upgraded {
+                Some(producer) if !job.builder => {
+                    if let Some(count) = state.owned.get_mut(&producer.key) {
+                        *count = count.saturating_sub(1);
+                    }
+                    producer.key = job.key.clone();
+                }
+                _ => state.producers.push(Producer { key: job.key.clone(), free_at: job.done_at }),
+            }
+        }
+        *state.owned.entry(job.key).or_insert(0) += 1;
+    }
+
+    /// Items worth considering next
+    fn options(&self, state: &State) -> Vec<&'a str> {
+        self.caps.iter()
+            .filter(|&(&key, &cap)| {
+                if key == self.worker {
+                    self.workers(state) < cap
+                } else if key == self.farm {
+                    let pending = state.jobs.iter().filter(|job| job.key == self.farm).count() as u32;
+                    let supply = self.food_supply(state) + pending * self.rules.unit(key).map_or(4, |unit| unit.food_supply);
+                    supply < MAX_FOOD && supply < state.food_used + FOOD_HEADROOM
+                } else {
+                    state.count(key) < cap
+                }
+            })
+            .map(|(&key, _)| key)
+            .collect()
+    }
+
+    /// `state` with `key` started as early as possible, or None if it cannot start before the horizon
+    fn try_start(&self, state: &State, key: &str) -> Option<State> {
+        let item = self.item(key)?;
+        let mut state = state.clone();
+        let mut producer;
+        loop {
+            if state.time > self.horizon {
+                return None;
+            }
+            let mut ready = true;
+            let mut wake: Option<u32> = None;
+            let mut wait_for = |time: Option<u32>| -> bool {
+                match time {
+                    Some(time) => {
+                        wake = Some(wake.map_or(time, |wake: u32| wake.min(time)));
+                        true
+                    }
+                    None => false,
+                }
+            };
+
+            for requirement in item.requires {
+                if !state.owns(requirement) {
+                    ready = false;
+                    if !wait_for(state.next_done(|job| &job.key == requirement)) {
+                        return None;
+                    }
+                }
+            }
+
+            producer = None;
+            if item.by_worker {
+                if state.gold_workers + state.wood_workers == 0 {
+                    ready = false;
+                    if !wait_for(state.next_done(|job| job.builder || job.key == self.worker)) {
+                        return None;
+                    }
+                }
+            } else {
+                let free = (0..state.producers.len())
+                    .filter(|&index| item.produced_at.contains(&state.producers[index].key))
+                    .min_by_key(|&index| state.producers[index].free_at);
+                match free {
+                    Some(index) if state.producers[index].free_at <= state.time => producer = Some(index),
+                    Some(index) => {
+                        ready = false;
+                        wait_for(Some(state.producers[index].free_at));
+                    }
+                    None => {
+                        ready = false;
+                        if !wait_for(state.next_done(|job| item.produced_at.contains(&job.key))) {
+                            return None;
+                        }
+                    }
+                }
+            }
+
+            if item.food > 0 && state.food_used + item.food > self.food_supply(&state) {
+                ready = false;
+                let farms = state.next_done(|job| self.rules.unit(&job.key).is_some_and(|unit| unit.food_supply > 0));
+                if !wait_for(farms) {
+                    return None;
+                }
+            }
+
+            if state.oil < item.oil {
+                return None;
+            }
+            for (have, need, workers, rate) in [
+                (state.gold, item.gold, state.gold_workers, self.gold_rate),
+                (state.wood, item.wood, state.wood_workers, self.wood_rate),
+            ] {
+                let short = need as f64 - have;
+                if short <= 0.0 {
+                    continue;
+                }
+                ready = false;
+                let income = workers as f64 * rate;
+                // Without income on this resource only a new worker can help
+                let when = if income > 0.0 {
+                    Some(state.time + (short / income).ceil() as u32)
+                } else {
+                    state.next_done(|job| job.builder || job.key == self.worker)
+                };
+                if !wait_for(when) {
+                    return None;
+                }
+            }
+
+            if ready {
+                break;
+            }
+            let until = wake?.max(state.time + 1);
+            self.advance(&mut state, until);
+        }
+
+        state.gold -= item.gold as f64;
+        state.wood -= item.wood as f64;
+        state.oil -= item.oil;
+        state.food_used += item.food;
+        let done_at = state.time + item.secs;
+        if item.by_worker {
+            self.take_worker(&mut state);
+        }
+        if let Some(index) = producer {
+            state.producers[index].free_at = done_at;
+        }
+        state.jobs.push(Job { key: item.key.to_string(), done_at, builder: item.by_worker });
+        state.steps.push(PlanStep {
+            start: state.time,
+            finish: done_at,
+            key: item.key.to_string(),
+            name: item.name.to_string(),
+            gold: item.gold,
+            wood: item.wood,
+            oil: item.oil,
+        });
+        Some(state)
+    }
+
+    fn step_cost(&self, key: &str) -> f64 {
+        self.item(key).map_or(0.0, |item| (item.gold + item.wood + item.oil) as f64)
+    }
+
+    /// Total cost of combat units finished by `at`
+    fn army_value(&self, state: &State, at: u32) -> u32 {
+        state.steps.iter()
+            .filter(|step| step.finish <= at && self.army.contains(&step.key.as_str()))
+            .map(|step| step.gold + step.wood + step.oil)
+            .sum()
+    }
+
+    fn evaluate(&self, state: &State) -> Evaluation {
+        if self.targets.is_empty() {
+            // Army goals: anything finishing after the evaluation time is wasted
+            let last = state.steps.last().map_or(state.time, |step| step.finish);
+            if last > self.deadline {
+                return Evaluation { value: None, score: None, bound: f64::MIN };
+            }
+            let army = self.army_value(state, self.deadline) as f64;
+            let potential = state.gold + state.wood + self.gathered_by(state, self.deadline);
+            // Resources only count as far as the production buildings can turn them into units in time
+            let score = army + potential.min(self.army_capacity(state));
+            let bound = army + state.gold + state.wood + self.most_gathered_by(state, self.deadline);
+            return Evaluation { value: Some(army), score: Some(score), bound };
+        }
+
+        let done = self.targets.iter().all(|(key, &count)| state.count(key) >= count);
+        let finish = state.jobs.iter()
+            .filter(|job| self.targets.contains_key(job.key.as_str()))
+            .map(|job| job.done_at)
+            .max()
+            .unwrap_or(state.time)
+            .max(state.time);
+        if done {
+            return Evaluation { value: Some(-(finish as f64)), score: None, bound: -(finish as f64) };
+        }
+
+        // Estimated finish: what is left at the current gathering rate
+        let mut remaining: f64 = self.targets.iter()
+            .map(|(key, &count)| count.saturating_sub(state.count(key)) as f64 * self.step_cost(key))
+            .sum();
+        for (&key, _) in self.caps.iter().filter(|(key, _)| !self.targets.contains_key(*key)) {
+            let needed = self.targets.keys().any(|target| self.rules.prerequisites(target).contains(&key));
+            if needed && state.count(key) == 0 {
+                remaining += self.step_cost(key);
+            }
+        }
+        // Pay for the food the remaining units need
+        let food_needed: u32 = self.targets.iter()
+            .filter_map(|(key, &count)| self.item(key).map(|item| count.saturating_sub(state.count(key)) * item.food))
+            .sum();
+        let farm_supply = self.rules.unit(self.farm).map_or(4, |farm| farm.food_supply.max(1));
+        let pending_supply = state.jobs.iter().filter(|job| job.key == self.farm).count() as u32 * farm_supply;
+        let spare = (self.food_supply(state) + pending_supply).saturating_sub(state.food_used);
+        remaining += food_needed.saturating_sub(spare).div_ceil(farm_supply) as f64 * self.step_cost(self.farm);
+        let gathering = (remaining - state.gold - state.wood).max(0.0) / (self.workers(state) as f64 * self.worker_rate()).max(0.1);
+
+        // Queue the remaining targets in their buildings, which may still be going up
+        let producing = self.targets.iter()
+            .filter_map(|(key, &count)| {
+                let item = self.item(key).filter(|item| !item.by_worker)?;
+                let mut ready: Vec<u32> = state.producers.iter()
+                    .filter(|producer| item.produced_at.contains(&producer.key))
+                    .map(|producer| producer.free_at.max(state.time))
+                    .chain(state.jobs.iter().filter(|job| item.produced_at.contains(&job.key)).map(|job| job.done_at))
+                    .collect();
+                if ready.is_empty() {
+                    let setup = item.produced_at.first().and_then(|producer| self.item(producer)).map_or(0, |producer| producer.secs);
+                    ready.push(state.time + setup);
+                }
+                for _ in 0..count.saturating_sub(state.count(key)) {
+                    if let Some(next) = ready.iter_mut().min() {
+                        *next += item.secs;
+                    }
+                }
+                ready.into_iter().max()
+            })
+            .max()
+            .unwrap_or(state.time);
+
+        let estimate = (finish.max(producing) as f64).max(state.time as f64 + gathering);
+        // Finishing earlier is better, so a branch can do no better than now
+        Evaluation { value: None, score: Some(-estimate), bound: -(state.time as f64) }
+    }
+
+    /// Resources per second a building of type `producer` turns into army
+    fn army_rate(&self, state: &State, producer: &str, check_requirements: bool) -> f64 {
+        self.army.iter()
+            .filter_map(|key| self.item(key))
+            .filter(|item| item.oil == 0 && item.produced_at.iter().any(|at| at == producer))
+            .filter(|item| !check_requirements || item.requires.iter().all(|requirement| state.count(requirement) > 0))
+            .map(|item| (item.gold + item.wood) as f64 / item.secs as f64)
+            .fold(0.0, f64::max)
+    }
+
+    /// Army value the production buildings, built or going up, can still make before the deadline
+    fn army_capacity(&self, state: &State) -> f64 {
+        let ready = state.producers.iter().map(|producer| (producer.key.as_str(), producer.free_at))
+            .chain(state.jobs.iter().map(|job| (job.key.as_str(), job.done_at)));
+        let capacity: f64 = ready
+            .map(|(key, ready_at)| {
+                self.army_rate(state, key, true) * self.deadline.saturating_sub(ready_at.max(state.time)) as f64
+            })
+            .sum();
+        if capacity > 0.0 {
+            return capacity;
+        }
+
+        // Nothing can make army yet: assume a producer is started now
+        self.caps.keys()
+            .filter_map(|key| self.item(key).filter(|item| item.by_worker))
+            .map(|producer| {
+                let rate = self.army_rate(state, producer.key, false);
+                rate * self.deadline.saturating_sub(state.time + producer.secs) as f64
+            })
+            .fold(0.0, f64::max)
+    }
+
+    fn finish(&self, state: State) -> BuildPlan {
+        if self.targets.is_empty() {
+            return BuildPlan {
+                army_value: self.army_value(&state, self.deadline),
+                meets_goal: self.army_value(&state, self.deadline) > 0,
+                finish: self.deadline,
+                steps: state.steps,
+            };
+        }
+
+        let finish = state.steps.iter()
+            .filter(|step| self.targets.contains_key(step.key.as_str()))
+            .map(|step| step.finish)
+            .max()
+            .unwrap_or(state.time);
+        BuildPlan {
+            army_value: self.army_value(&state, finish),
+            meets_goal: finish <= self.deadline,
+            finish,
+            steps: state.steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(race: Race, goal: &str) -> BuildPlan {
+        BuildPlanner::default().plan(&PlannerStart::melee(race), &goal.parse().unwrap()).unwrap()
+    }
+
+    fn count(plan: &BuildPlan, key: &str) -> usize {
+        plan.steps.iter().filter(|step| step.key == key).count()
+    }
+
+    #[test]
+    fn test_army_goal_keeps_worker_first_orders() {
+        let goal: PlanGoal = "max army value at 8:00".parse().unwrap();
+        let planner = BuildPlanner::default();
+        let start = PlannerStart::melee(Race::Human);
+        let plan = planner.plan(&start, &goal).unwrap();
+        assert!(plan.meets_goal);
+        assert!(plan.army_value >= 1800, "{}", plan);
+        assert!(count(&plan, "peasant") > 0, "{}", plan);
+
+        // The bound may never promise less than the search then finds
+        let search = Search::new(&planner.rules, &planner.economy, &start, &goal).unwrap();
+        assert!(search.evaluate(&search.initial(&start)).bound >= plan.army_value as f64);
+    }
+
+    #[test]
+    fn test_unreachable_deadline_still_plans() {
+        // A barracks takes 200s and each grunt 60s, so three grunts a barracks cannot be out by 4:00
+        let plan = plan(Race::Orc, "2 barracks + 6 grunts by 4:00");
+        assert!(!plan.meets_goal);
+        assert_eq!((count(&plan, "orc_barracks"), count(&plan, "grunt")), (2, 6));
+        assert!(plan.finish >= 200 + 3 * 60 && plan.finish <= parse_clock("10:00").unwrap(), "{}", plan);
+    }
+
+    #[test]
+    fn test_two_barracks_six_footmen() {
+        let plan = plan(Race::Human, "2 barracks + 6 footmen by 8:00");
+        assert_eq!((count(&plan, "barracks"), count(&plan, "footman")), (2, 6));
+        assert!(plan.finish <= parse_clock("9:34").unwrap(), "{}", plan);
+        // Every footman needs food from the hall or a farm
+        let farms = count(&plan, "farm") as u32;
+        assert!(1 + 4 * farms >= 6 + 1 + count(&plan, "peasant") as u32);
+    }
+
+    #[test]
+    fn test_reachable_goal_meets_it() {
+        // The same army as the 4:00 goal, with time for the barracks and the grunt rounds
+        let plan = plan(Race::Orc, "2 barracks + 6 grunts by 10:00");
+        assert!(plan.meets_goal, "{}", plan);
+        assert_eq!((count(&plan, "orc_barracks"), count(&plan, "grunt")), (2, 6));
+        assert!(plan.finish <= parse_clock("10:00").unwrap(), "{}", plan);
+    }
+}
@@ -5,9 +5,33 @@ use anyhow::{Result, anyhow};
 use serde::{Serialize, Deserialize};
 use log::{info, warn, error, debug};
 use std::fs;
-use std::path::Path;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 use crate::game_engine::HeadlessGameState;
+use crate::game_backend::GameBackend;
+use crate::decision_trace::DecisionTrace;
+
+/// Start of the name of each game's decision trace in the output directory
+pub const DECISION_TRACE_PREFIX: &str = "decision_trace_";
+
+/// Decision trace of the game in progress
+struct TraceFile {
+    path: PathBuf,
+    /// Kept open for the whole game and flushed when it ends
+    writer: BufWriter<fs::File>,
+    /// Game time of the latest decision written
+    last_tick: u64,
+}
+
+impl TraceFile {
+    /// Write out what is still buffered
+    fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        debug!("📊 Closed decision trace {}", self.path.display());
+        Ok(())
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExportFormat {
@@ -115,20 +139,25 @@ pub struct DataExporter {
     performance_history: Arc<Mutex<Vec<PerformanceMetrics>>>,
     ai_decision_history: Arc<Mutex<Vec<AIDecisionData>>>,
     memory_analysis_history: Arc<Mutex<Vec<MemoryAnalysisData>>>,
+    decision_trace: Mutex<Option<TraceFile>>,
     last_export_time: u64,
 }
 
 impl DataExporter {
     pub async fn new() -> Result<Self> {
+        Self::with_config(ExportConfig::default()).await
+    }
+    
+    pub async fn with_config(config: ExportConfig) -> Result<Self> {
         info!("📊 Initializing Data Exporter...");
         
-        let config = ExportConfig::default();
         let exporter = Self {
             config,
             export_history: Arc::new(Mutex::new(Vec::new())),
             performance_history: Arc::new(Mutex::new(Vec::new())),
             ai_decision_history: Arc::new(Mutex::new(Vec::new())),
             memory_analysis_history: Arc::new(Mutex::new(Vec::new())),
+            decision_trace: Mutex::new(None),
             last_export_time: 0,
         };
         
@@ -185,6 +214,12 @@ impl DataExporter {
         Ok(())
     }
     
+    /// Export the state a game backend currently reports
+    pub async fn export_backend_state(&self, backend: &dyn GameBackend) -> Result<()> {
+        let game_state = backend.observe().await?;
+        self.export_game_state(&game_state).await
+    }
+    
     pub async fn export_game_state(&self, game_state: &HeadlessGameState) -> Result<()> {
         let filename = format!("game_state_{}.json", 
             chrono::Utc::now().format("%Y%m%d_%H%M%S"));
@@ -424,6 +459,71 @@ impl DataExporter {
         Ok(())
     }
     
+    /// Begin a new JSONL decision trace for the next game; returns its path
+    ///
+    /// The trace of the previous game is finished first.
+    pub async fn start_decision_trace(&self) -> Result<PathBuf> {
+        let mut current = self.decision_trace.lock().await;
+        if let Some(previous) = current.take() {
+            previous.finish()?;
+        }
+        let file = self.new_trace_file()?;
+        let path = file.path.clone();
+        *current = Some(file);
+        Ok(path)
+    }
+    
+    /// Flush and close the current game's decision trace
+    pub async fn finish_decision_trace(&self) -> Result<()> {
+        match self.decision_trace.lock().await.take() {
+            Some(file) => file.finish(),
+            None => Ok(()),
+        }
+    }
+    
+    /// Append one decision to the current game's decision trace
+    ///
+    /// A trace is started on the first decision, and again whenever the game
+    /// clock goes back, so every game gets a file of its own. Lines are
+    /// buffered until [`DataExporter::finish_decision_trace`] or the next trace.
+    pub async fn record_decision_trace(&self, trace: &DecisionTrace) -> Result<()> {
+        let mut current = self.decision_trace.lock().await;
+        let file = match current.take() {
+            Some(file) if trace.game_time >= file.last_tick => current.insert(file),
+            previous => {
+                if let Some(previous) = previous {
+                    previous.finish()?;
+                }
+                current.insert(self.new_trace_file()?)
+            }
+        };
+        
+        serde_json::to_writer(&mut file.writer, trace)?;
+        file.writer.write_all(b"\n")?;
+        file.last_tick = trace.game_time;
+        
+        debug!("📊 Traced decision at tick {} to {}", trace.game_time, file.path.display());
+        Ok(())
+    }
+    
+    /// Trace file with an unused name in the output directory, stamped with the current time
+    fn new_trace_file(&self) -> Result<TraceFile> {
+        let dir = Path::new(&self.config.output_directory);
+        fs::create_dir_all(dir)?;
+        let stamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
+        let mut path = dir.join(format!("{}{}.jsonl", DECISION_TRACE_PREFIX, stamp));
+        for n in 1.. {
+            if !path.exists() {
+                break;
+            }
+            path = dir.join(format!("{}{}_{}.jsonl", DECISION_TRACE_PREFIX, stamp, n));
+        }
+        
+        let writer = BufWriter::new(fs::File::create_new(&path)?);
+        info!("📊 Tracing decisions to {}", path.display());
+        Ok(TraceFile { path, writer, last_tick: 0 })
+    }
+    
     pub async fn export_memory_analysis_data(&self) -> Result<()> {
         let memory_data = MemoryAnalysisData {
             timestamp: std::time::SystemTime::now()
@@ -580,3 +680,34 @@ impl Default for ExportConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decision_trace::read_trace;
+    use crate::test_fixtures::decision_trace as trace;
+
+    #[tokio::test]
+    async fn test_each_game_gets_its_own_decision_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ExportConfig { output_directory: dir.path().to_string_lossy().to_string(), ..ExportConfig::default() };
+        let exporter = DataExporter::with_config(config).await.unwrap();
+
+        for tick in [10, 20, 30] {
+            exporter.record_decision_trace(&trace(tick)).await.unwrap();
+        }
+        // The clock going back means a new game
+        exporter.record_decision_trace(&trace(5)).await.unwrap();
+        let started = exporter.start_decision_trace().await.unwrap();
+        exporter.record_decision_trace(&trace(50)).await.unwrap();
+        exporter.finish_decision_trace().await.unwrap();
+
+        let mut files: Vec<PathBuf> = fs::read_dir(dir.path()).unwrap().map(|entry| entry.unwrap().path()).collect();
+        files.sort();
+        assert!(files.contains(&started));
+        let ticks: Vec<Vec<u64>> = files.iter()
+            .map(|path| read_trace(path).unwrap().iter().map(|trace| trace.game_time).collect())
+            .collect();
+        assert_eq!(ticks, vec![vec![10, 20, 30], vec![5], vec![50]]);
+    }
+}
@@ -0,0 +1,305 @@
+//! Structured trace of AI decisions
+//!
+//! Every decision tick records the context the AI saw, every candidate action
+//! with its priority and selection score, and for each candidate either that it
+//! was chosen or why it was dropped. When enabled, traces are written as one
+//! compact JSON object per line, one file per game, by
+//! `DataExporter::record_decision_trace` and read back by `wc2-why` to answer
+//! questions like "why didn't the bot build a tower at 5:12?".
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+
+use crate::ai_command::normalize;
+use crate::ai_controller::{AIDecisionContext, AIStrategy};
+use crate::build_planner::clock;
+use crate::simulator::TICKS_PER_SECOND;
+
+/// Where a candidate action came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionSource {
+    /// The current strategy, built-in or scripted
+    Strategy,
+    CombatTactic,
+    ResourceManagement,
+    BuildOrder,
+}
+
+/// Why a candidate action was not issued
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Rejection {
+    /// The command cannot be executed on this map
+    Invalid { error: String },
+    /// Not enough left in the bank after the actions chosen before it
+    Cost { gold: u32, wood: u32, oil: u32, available_gold: u32, available_wood: u32, available_oil: u32 },
+    /// Training would exceed the food supply
+    Food { used: u32, supply: u32 },
+    /// Buildings or units the command needs are not owned yet
+    Prerequisites { missing: Vec<String> },
+    /// The same command, or another building on the same tiles, was already chosen this tick
+    Duplicate,
+    /// The per-decision action limit was reached
+    QueueFull { limit: usize },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Invalid { error } => write!(f, "invalid: {}", error),
+            Rejection::Cost { gold, wood, oil, available_gold, available_wood, available_oil } => write!(
+                f, "cannot afford {}g/{}w/{}o with {}g/{}w/{}o left",
+                gold, wood, oil, available_gold, available_wood, available_oil
+            ),
+            Rejection::Food { used, supply } => write!(f, "no food, {}/{} used", used, supply),
+            Rejection::Prerequisites { missing } => write!(f, "missing {}", missing.join(", ")),
+            Rejection::Duplicate => write!(f, "duplicate of an action already chosen"),
+            Rejection::QueueFull { limit } => write!(f, "queue full ({} actions per decision)", limit),
+        }
+    }
+}
+
+/// One candidate action and what became of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateTrace {
+    /// Command in the text command syntax
+    pub command: String,
+    pub source: ActionSource,
+    pub priority: f64,
+    /// Value candidates were ranked by
+    pub score: f64,
+    pub estimated_cost: u32,
+    pub reasoning: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<Rejection>,
+}
+
+impl CandidateTrace {
+    pub fn is_chosen(&self) -> bool {
+        self.rejected.is_none()
+    }
+}
+
+/// Everything the AI considered in one decision tick
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionTrace {
+    /// Game ticks since the game started, as in `HeadlessGameState::game_time`
+    pub game_time: u64,
+    /// Wall-clock time in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub player: u8,
+    pub strategy: AIStrategy,
+    pub context: AIDecisionContext,
+    /// Candidates in ranking order
+    pub candidates: Vec<CandidateTrace>,
+}
+
+impl DecisionTrace {
+    /// Whole seconds since the game started
+    pub fn seconds(&self) -> u32 {
+        (self.game_time / TICKS_PER_SECOND as u64) as u32
+    }
+
+    pub fn chosen(&self) -> impl Iterator<Item = &CandidateTrace> {
+        self.candidates.iter().filter(|candidate| candidate.is_chosen())
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = &CandidateTrace> {
+        self.candidates.iter().filter(|candidate| !candidate.is_chosen())
+    }
+}
+
+/// Read a JSONL decision trace
+pub fn read_trace(path: &Path) -> Result<Vec<DecisionTrace>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open decision trace {}", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(index, line)| {
+            serde_json::from_str(&line?)
+                .with_context(|| format!("{}:{}: invalid decision trace entry", path.display(), index + 1))
+        })
+        .collect()
+}
+
+/// Whether a command mentions every word of the query, ignoring case and spacing
+pub fn matches_query(command: &str, query: &str) -> bool {
+    let command = normalize(command);
+    query.split_whitespace().all(|word| command.contains(&normalize(word)))
+}
+
+/// What happened to candidates matching a query in the ticks around a time
+#[derive(Debug, Clone)]
+pub struct QueryAnswer {
+    pub query: String,
+    /// Window searched, in game seconds
+    pub from: u32,
+    pub to: u32,
+    pub ticks_searched: usize,
+    /// (tick time in seconds, strategy, candidate)
+    pub matches: Vec<(u32, AIStrategy, CandidateTrace)>,
+    /// Strategies in play during the window and how many ticks each decided
+    pub strategies: BTreeMap<String, usize>,
+}
+
+/// Look up a command in the ticks within `window` seconds of `at`, or everywhere without a time
+pub fn query(traces: &[DecisionTrace], query: &str, at: Option<u32>, window: u32) -> QueryAnswer {
+    let (from, to) = match at {
+        Some(at) => (at.saturating_sub(window), at + window),
+        None => (0, u32::MAX),
+    };
+
+    let mut answer = QueryAnswer {
+        query: query.to_string(),
+        from,
+        to,
+        ticks_searched: 0,
+        matches: Vec::new(),
+        strategies: BTreeMap::new(),
+    };
+    for trace in traces.iter().filter(|trace| (from..=to).contains(&trace.seconds())) {
+        answer.ticks_searched += 1;
+        *answer.strategies.entry(format!("{:?}", trace.strategy)).or_insert(0) += 1;
+        for candidate in trace.candidates.iter().filter(|candidate| matches_query(&candidate.command, query)) {
+            answer.matches.push((trace.seconds(), trace.strategy.clone(), candidate.clone()));
+        }
+    }
+    answer
+}
+
+impl fmt::Display for QueryAnswer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let window = if self.to == u32::MAX {
+            "the whole game".to_string()
+        } else {
+            format!("{}-{}", clock(self.from), clock(self.to))
+        };
+
+        if self.ticks_searched == 0 {
+            return writeln!(f, "No decisions were traced during {}", window);
+        }
+        if self.matches.is_empty() {
+            writeln!(f, "No candidate matching '{}' was generated in {} decisions during {}", self.query, self.ticks_searched, window)?;
+            let strategies: Vec<String> = self.strategies.iter().map(|(name, ticks)| format!("{} ({} ticks)", name, ticks)).collect();
+            return writeln!(f, "Strategies in play: {}", strategies.join(", "));
+        }
+
+        writeln!(f, "'{}' during {}: {} candidates in {} decisions", self.query, window, self.matches.len(), self.ticks_searched)?;
+        for (secs, strategy, candidate) in &self.matches {
+            let verdict = match &candidate.rejected {
+                None => "chosen".to_string(),
+                Some(rejection) => format!("rejected, {}", rejection),
+            };
+            writeln!(f, "  {:>6}  {:<24} {:?}/{:?} priority {:.2} score {:.2}: {}",
+                     clock(*secs), candidate.command, strategy, candidate.source, candidate.priority, candidate.score, verdict)?;
+        }
+
+        // Tally the reasons so the dominant one stands out in long windows
+        let mut reasons: BTreeMap<String, usize> = BTreeMap::new();
+        for (_, _, candidate) in &self.matches {
+            let reason = match &candidate.rejected {
+                None => "chosen",
+                Some(Rejection::Invalid { .. }) => "invalid",
+                Some(Rejection::Cost { .. }) => "cost",
+                Some(Rejection::Food { .. }) => "food",
+                Some(Rejection::Prerequisites { .. }) => "prerequisites",
+                Some(Rejection::Duplicate) => "duplicate",
+                Some(Rejection::QueueFull { .. }) => "queue full",
+            };
+            *reasons.entry(reason.to_string()).or_insert(0) += 1;
+        }
+        let reasons: Vec<String> = reasons.iter().map(|(reason, count)| format!("{} x{}", reason, count)).collect();
+        writeln!(f, "Outcomes: {}", reasons.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_engine::HeadlessGameState;
+    use crate::memory_hooks::MemoryState;
+    use crate::test_fixtures::decision_trace;
+
+    fn candidate(command: &str, rejected: Option<Rejection>) -> CandidateTrace {
+        CandidateTrace {
+            command: command.to_string(),
+            source: ActionSource::Strategy,
+            priority: 0.5,
+            score: 0.5,
+            estimated_cost: 0,
+            reasoning: String::new(),
+            rejected,
+        }
+    }
+
+    #[test]
+    fn test_matches_query() {
+        assert!(matches_query("build GuardTower 10 12", "build tower"));
+        assert!(matches_query("build GuardTower 10 12", "Guard_Tower"));
+        assert!(matches_query("train Footman", "TRAIN foot"));
+        assert!(matches_query("train Footman", ""));
+        assert!(!matches_query("build GuardTower 10 12", "build barracks"));
+        assert!(!matches_query("train Footman", "train footman knight"));
+    }
+
+    #[test]
+    fn test_query_by_live_game_time() {
+        // Live states are stamped with epoch milliseconds; traces count from game start
+        let started = 1_760_000_000_000;
+        let mut memory = MemoryState { game_phase: "InGame".to_string(), timestamp: started, ..MemoryState::default() };
+        let mut state = HeadlessGameState::default();
+        state.update_from_memory_state(&memory);
+
+        let mut traces = Vec::new();
+        for secs in [60, 305, 312, 330] {
+            memory.timestamp = started + secs * 1000;
+            state.update_from_memory_state(&memory);
+            let mut trace = decision_trace(state.game_time);
+            trace.candidates = vec![
+                candidate("build GuardTower 10 12", Some(Rejection::Cost {
+                    gold: 550, wood: 200, oil: 0, available_gold: 100, available_wood: 400, available_oil: 0,
+                })),
+                candidate("train Peasant", None),
+            ];
+            traces.push(trace);
+        }
+        assert_eq!(traces.iter().map(DecisionTrace::seconds).collect::<Vec<_>>(), vec![60, 305, 312, 330]);
+
+        let at = crate::build_planner::parse_clock("5:12").unwrap();
+        let answer = query(&traces, "build tower", Some(at), 10);
+        assert_eq!((answer.from, answer.to, answer.ticks_searched), (302, 322, 2));
+        let times: Vec<u32> = answer.matches.iter().map(|(secs, _, _)| *secs).collect();
+        assert_eq!(times, vec![305, 312]);
+        assert!(answer.matches.iter().all(|(_, _, candidate)| !candidate.is_chosen()));
+        assert!(answer.to_string().contains("cost x2"));
+
+        // Without a time the whole game is searched
+        let answer = query(&traces, "train peasant", None, 10);
+        assert_eq!((answer.ticks_searched, answer.matches.len()), (4, 4));
+        assert_eq!(traces[0].chosen().count(), 1);
+        assert_eq!(traces[0].rejected().count(), 1);
+
+        let answer = query(&traces, "build tower", Some(20 * 60), 10);
+        assert!(answer.to_string().starts_with("No decisions were traced"));
+    }
+
+    #[test]
+    fn test_read_trace_skips_blank_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let lines: Vec<String> = [10, 20].iter()
+            .map(|&tick| serde_json::to_string(&decision_trace(tick)).unwrap())
+            .collect();
+        std::fs::write(&path, format!("{}\n\n{}\n", lines[0], lines[1])).unwrap();
+
+        let traces = read_trace(&path).unwrap();
+        assert_eq!(traces.iter().map(|trace| trace.game_time).collect::<Vec<_>>(), vec![10, 20]);
+    }
+}
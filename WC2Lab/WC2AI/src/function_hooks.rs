@@ -5,6 +5,8 @@ use anyhow::Result;
 use serde::{Serialize, Deserialize};
 use log::{info, warn, error, debug};
 
+use crate::ai_command::{AICommand, Selection};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionHook {
     pub original_address: u64,
@@ -24,20 +26,6 @@ pub enum HookType {
     ModifyReturn, // Call original function and modify return value
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum AIAction {
-    Click { x: i32, y: i32 },
-    Build { building_type: String },
-    Train { unit_type: String },
-    Move { unit_id: u32, x: i32, y: i32 },
-    Attack { unit_id: u32, target_id: u32 },
-    Select { unit_ids: Vec<u32> },
-    Deselect,
-    SetRallyPoint { x: i32, y: i32 },
-    Research { upgrade_type: String },
-    CastSpell { spell_type: String, target_x: i32, target_y: i32 },
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HookedFunction {
     pub name: String,
@@ -50,7 +38,7 @@ pub struct HookedFunction {
 pub struct FunctionHookManager {
     hooks: Arc<Mutex<HashMap<u64, FunctionHook>>>,
     hooked_functions: Arc<Mutex<Vec<HookedFunction>>>,
-    ai_action_queue: Arc<Mutex<Vec<AIAction>>>,
+    ai_action_queue: Arc<Mutex<Vec<AICommand>>>,
     process_handle: Option<u64>,
     base_address: Option<u64>,
 }
@@ -228,86 +216,81 @@ impl FunctionHookManager {
         Ok(())
     }
     
-    pub async fn execute_ai_action(&self, action: &str) -> Result<()> {
-        info!("🤖 Executing AI action: {}", action);
-        
-        // Parse and execute AI action
-        let ai_action = self.parse_ai_action(action)?;
+    pub async fn execute_ai_action(&self, command: &AICommand) -> Result<()> {
+        info!("🤖 Executing AI action: {}", command);
         
         // Add to action queue
         let mut queue = self.ai_action_queue.lock().await;
-        queue.push(ai_action.clone());
+        queue.push(command.clone());
         
         // Execute immediately for now
-        self.process_ai_action(&ai_action).await?;
+        self.process_ai_action(command).await?;
         
         Ok(())
     }
     
-    fn parse_ai_action(&self, action_str: &str) -> Result<AIAction> {
-        // Simple action parsing for now
-        if action_str.starts_with("click") {
-            // Parse "click x,y" format
-            let coords: Vec<&str> = action_str.split_whitespace().collect();
-            if coords.len() == 3 {
-                let x: i32 = coords[1].parse()?;
-                let y: i32 = coords[2].parse()?;
-                return Ok(AIAction::Click { x, y });
-            }
-        } else if action_str.starts_with("build") {
-            let building_type = action_str.split_whitespace().nth(1).unwrap_or("TownHall").to_string();
-            return Ok(AIAction::Build { building_type });
-        } else if action_str.starts_with("train") {
-            let unit_type = action_str.split_whitespace().nth(1).unwrap_or("Peasant").to_string();
-            return Ok(AIAction::Train { unit_type });
-        }
-        
-        // Default action
-        Ok(AIAction::Click { x: 64, y: 64 })
+    /// Parse a textual command (e.g. from a script or replay); unknown text is an error
+    pub fn parse_ai_action(&self, action_str: &str) -> Result<AICommand> {
+        action_str.parse()
     }
     
-    async fn process_ai_action(&self, action: &AIAction) -> Result<()> {
-        match action {
-            AIAction::Click { x, y } => {
+    async fn process_ai_action(&self, command: &AICommand) -> Result<()> {
+        match command {
+            AICommand::Click { x, y } => {
                 info!("🖱️ AI clicking at ({}, {})", x, y);
                 // Mock click execution
             }
-            AIAction::Build { building_type } => {
-                info!("🏗️ AI building {}", building_type);
+            AICommand::BuildAt { building, x, y } => {
+                info!("🏗️ AI building {} at ({}, {})", building, x, y);
                 // Mock building execution
             }
-            AIAction::Train { unit_type } => {
-                info!("⚔️ AI training {}", unit_type);
+            AICommand::Train { unit } => {
+                info!("⚔️ AI training {}", unit);
                 // Mock training execution
             }
-            AIAction::Move { unit_id, x, y } => {
-                info!("🚶 AI moving unit {} to ({}, {})", unit_id, x, y);
+            AICommand::Move { x, y } => {
+                info!("🚶 AI moving selection to ({}, {})", x, y);
                 // Mock movement execution
             }
-            AIAction::Attack { unit_id, target_id } => {
-                info!("⚔️ AI attacking with unit {} on target {}", unit_id, target_id);
+            AICommand::AttackMove { x, y } => {
+                info!("⚔️ AI attack moving to ({}, {})", x, y);
                 // Mock attack execution
             }
-            AIAction::Select { unit_ids } => {
-                info!("👆 AI selecting units: {:?}", unit_ids);
+            AICommand::Gather { resource, x, y } => {
+                info!("⛏️ AI gathering {} at ({}, {})", resource, x, y);
+                // Mock gathering execution
+            }
+            AICommand::Select { selection: Selection::Area { start_x, start_y, end_x, end_y } } => {
+                info!("👆 AI selecting units from ({}, {}) to ({}, {})", start_x, start_y, end_x, end_y);
                 // Mock selection execution
             }
-            AIAction::Deselect => {
-                info!("👆 AI deselecting all units");
-                // Mock deselection execution
+            AICommand::Select { selection } => {
+                info!("👆 AI selecting {:?}", selection);
+                // Mock selection execution
             }
-            AIAction::SetRallyPoint { x, y } => {
+            AICommand::SetRally { x, y } => {
                 info!("📍 AI setting rally point at ({}, {})", x, y);
                 // Mock rally point execution
             }
-            AIAction::Research { upgrade_type } => {
-                info!("🔬 AI researching {}", upgrade_type);
+            AICommand::Research { upgrade } => {
+                info!("🔬 AI researching {}", upgrade);
                 // Mock research execution
             }
-            AIAction::CastSpell { spell_type, target_x, target_y } => {
-                info!("✨ AI casting {} at ({}, {})", spell_type, target_x, target_y);
+            AICommand::Cast { spell, x, y } => {
+                info!("✨ AI casting {} at ({}, {})", spell, x, y);
                 // Mock spell casting execution
             }
+            AICommand::Formation { formation, x, y, facing } => {
+                info!("📐 AI forming {:?} at ({}, {}) facing {:?}", formation, x, y, facing);
+                // Mock formation execution
+            }
+            AICommand::Hotkey { hotkey } => {
+                info!("🎯 AI pressing {:?}", hotkey);
+                // Mock hotkey execution
+            }
+            AICommand::Wait { ms } => {
+                info!("⏳ AI waiting {}ms", ms);
+            }
         }
         
         Ok(())
@@ -334,7 +317,7 @@ impl FunctionHookManager {
         status
     }
     
-    pub async fn get_ai_action_queue(&self) -> Vec<AIAction> {
+    pub async fn get_ai_action_queue(&self) -> Vec<AICommand> {
         let queue = self.ai_action_queue.lock().await;
         queue.clone()
     }
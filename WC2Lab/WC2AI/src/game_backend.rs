@@ -0,0 +1,219 @@
+//! Game backends for the headless engine
+//!
+//! A `GameBackend` is whatever the engine plays against: it reports the game
+//! state, accepts typed AI commands and advances time. `LiveGameBackend` drives
+//! the real Warcraft II process through memory hooks and Win32 input, so it
+//! only exists on Windows; other backends let the same AI stack run anywhere.
+
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+
+use crate::ai_command::AICommand;
+use crate::game_engine::HeadlessGameState;
+
+#[cfg(windows)]
+use {
+    log::{warn, debug},
+    tokio::sync::Mutex,
+    crate::function_hooks::FunctionHookManager,
+    crate::input_simulator::{InputSimulator, InputSimulatorStatus, MouseAction},
+    crate::memory_hooks::MemoryHookManager,
+};
+
+/// Something the headless engine can observe and control
+#[async_trait]
+pub trait GameBackend: Send + Sync {
+    /// Short name used in logs and exported data
+    fn name(&self) -> &str;
+
+    /// Prepare the backend before the first observation
+    async fn connect(&self) -> Result<()>;
+
+    /// Current state of the game
+    async fn observe(&self) -> Result<HeadlessGameState>;
+
+    /// Carry out a single AI command
+    async fn issue(&self, command: &AICommand) -> Result<()>;
+
+    /// Let game time pass
+    async fn advance(&self, duration: Duration) -> Result<()>;
+
+    /// Release hooks and other resources
+    async fn disconnect(&self) -> Result<()>;
+}
+
+/// Backend the engine plays when none is given: the live game on Windows
+pub async fn default_backend() -> Result<Arc<dyn GameBackend>> {
+    #[cfg(windows)]
+    {
+        Ok(Arc::new(LiveGameBackend::new().await?))
+    }
+    #[cfg(not(windows))]
+    {
+        use crate::simulator::{SimConfig, SimMap, SimulatorBackend};
+        info!("🎲 No live game outside Windows, playing the headless simulator");
+        Ok(Arc::new(SimulatorBackend::new(SimConfig::default(), SimMap::open_field(64, 64, 2))))
+    }
+}
+
+/// Base addresses probed when reading the live game's memory
+#[cfg(windows)]
+const LIVE_BASE_ADDRESSES: [u64; 3] = [0x00400000, 0x10000000, 0x20000000];
+
+/// Backend driving the running Warcraft II process
+#[cfg(windows)]
+pub struct LiveGameBackend {
+    memory_hooks: Arc<MemoryHookManager>,
+    function_hooks: Arc<FunctionHookManager>,
+    input_simulator: Arc<Mutex<InputSimulator>>,
+    game_state: Mutex<HeadlessGameState>,
+}
+
+#[cfg(windows)]
+impl LiveGameBackend {
+    pub async fn new() -> Result<Self> {
+        Ok(Self::with_components(
+            Arc::new(MemoryHookManager::new().await?),
+            Arc::new(FunctionHookManager::new().await?),
+            Arc::new(Mutex::new(InputSimulator::new())),
+        ))
+    }
+
+    pub fn with_components(
+        memory_hooks: Arc<MemoryHookManager>,
+        function_hooks: Arc<FunctionHookManager>,
+        input_simulator: Arc<Mutex<InputSimulator>>,
+    ) -> Self {
+        Self {
+            memory_hooks,
+            function_hooks,
+            input_simulator,
+            game_state: Mutex::new(HeadlessGameState::default()),
+        }
+    }
+
+    /// Send a raw mouse action to the game window
+    pub async fn execute_mouse_action(&self, action: MouseAction) -> Result<()> {
+        self.input_simulator.lock().await.execute_mouse_action(action).await
+    }
+
+    /// Current status of the input simulator
+    pub async fn get_input_status(&self) -> InputSimulatorStatus {
+        self.input_simulator.lock().await.get_status()
+    }
+}
+
+#[cfg(windows)]
+#[async_trait]
+impl GameBackend for LiveGameBackend {
+    fn name(&self) -> &str {
+        "live"
+    }
+
+    async fn connect(&self) -> Result<()> {
+        self.memory_hooks.install_all_hooks().await?;
+        self.function_hooks.install_all_hooks().await?;
+
+        let mut simulator = self.input_simulator.lock().await;
+        simulator.initialize().await?;
+
+        // Check if Warcraft II is running
+        if !simulator.is_warcraft_ii_running().await {
+            info!("🎮 Warcraft II not running, attempting to launch...");
+
+            match simulator.launch_warcraft_ii().await {
+                Ok(()) => info!("✅ Warcraft II launched successfully"),
+                Err(e) => {
+                    warn!("⚠️ Failed to launch Warcraft II: {}", e);
+                    info!("💡 Please launch Warcraft II manually and try again");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn observe(&self) -> Result<HeadlessGameState> {
+        let running = self.input_simulator.lock().await.is_warcraft_ii_running().await;
+        let mut game_state = self.game_state.lock().await;
+
+        if !running {
+            debug!("🎮 Warcraft II not running, keeping last known state");
+            return Ok(game_state.clone());
+        }
+
+        if self.memory_hooks.memory_source().await.is_none() {
+            if let Err(e) = self.memory_hooks.initialize_process_connection().await {
+                debug!("⚠️ Cannot read game memory: {}", e);
+                return Ok(game_state.clone());
+            }
+        }
+        let Some(source) = self.memory_hooks.memory_source().await else {
+            return Ok(game_state.clone());
+        };
+
+        for base_addr in LIVE_BASE_ADDRESSES {
+            match self.memory_hooks.parse_game_state(source.as_ref(), base_addr).await {
+                Ok(memory_state) => {
+                    game_state.update_from_memory_state(&memory_state);
+                    break;
+                }
+                Err(e) => debug!("⚠️ Failed to read game state from 0x{:x}: {}", base_addr, e),
+            }
+        }
+
+        Ok(game_state.clone())
+    }
+
+    async fn issue(&self, command: &AICommand) -> Result<()> {
+        // Waiting needs neither lock, so observers keep reading while it runs
+        if let AICommand::Wait { ms } = command {
+            return self.advance(Duration::from_millis(*ms)).await;
+        }
+
+        let map_size = {
+            let state = self.game_state.lock().await;
+            (state.map_info.width, state.map_info.height)
+        };
+        let mut simulator = self.input_simulator.lock().await;
+        simulator.set_map_size(map_size);
+        simulator.execute_command(command).await
+    }
+
+    async fn advance(&self, duration: Duration) -> Result<()> {
+        // The live game runs in real time
+        tokio::time::sleep(duration).await;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.memory_hooks.uninstall_all_hooks().await?;
+        self.function_hooks.uninstall_all_hooks().await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_observe_during_wait() {
+        let backend = Arc::new(LiveGameBackend::with_components(
+            Arc::new(MemoryHookManager::new().await.unwrap()),
+            Arc::new(FunctionHookManager::new().await.unwrap()),
+            Arc::new(Mutex::new(InputSimulator::new())),
+        ));
+
+        let waiting = backend.clone();
+        let wait = tokio::spawn(async move { waiting.issue(&AICommand::Wait { ms: 60_000 }).await });
+        tokio::task::yield_now().await;
+
+        let observed = tokio::time::timeout(Duration::from_secs(1), backend.observe()).await;
+        assert!(observed.is_ok(), "observe blocked behind a Wait command");
+        wait.abort();
+    }
+}
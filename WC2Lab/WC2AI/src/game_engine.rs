@@ -1,16 +1,17 @@
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
-use anyhow::{Result, anyhow};
-use log::{info, warn, debug};
+use anyhow::Result;
+use log::{info, debug};
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 
-use crate::memory_hooks::MemoryHookManager;
-use crate::function_hooks::FunctionHookManager;
 use crate::ai_controller::AIController;
 use crate::data_exporter::DataExporter;
 use crate::replay_system::ReplaySystem;
-use crate::input_simulator::InputSimulator;
+use crate::ai_command::{AICommand, BuildingKind, Selection, UnitKind};
+use crate::game_backend::{self, GameBackend};
+use crate::simulator::TICKS_PER_SECOND;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HeadlessConfig {
@@ -22,6 +23,9 @@ pub struct HeadlessConfig {
     pub data_export_interval: u64,
     pub max_replay_size: usize,
     pub log_level: String,
+    /// Write every AI decision with its candidates and rejections to a decision trace, one file per game; off by default
+    #[serde(default)]
+    pub enable_decision_trace: bool,
 }
 
 impl Default for HeadlessConfig {
@@ -35,22 +39,30 @@ impl Default for HeadlessConfig {
             data_export_interval: 1000,
             max_replay_size: 100 * 1024 * 1024,
             log_level: "info".to_string(),
+            enable_decision_trace: false,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HeadlessGameState {
+    /// Player slot the state is observed for
+    #[serde(default)]
+    pub player: u8,
     pub game_phase: GamePhase,
     pub player_resources: PlayerResources,
     pub units: Vec<UnitInfo>,
     pub buildings: Vec<BuildingInfo>,
     pub map_info: MapInfo,
+    /// Game ticks since the game started, `TICKS_PER_SECOND` to the second
     pub game_time: u64,
     pub game_status: GameStatus,
     pub ai_actions_queued: usize,
     pub memory_hooks_active: usize,
     pub last_update: u64,
+    /// Wall-clock milliseconds at which a live game was first seen in progress
+    #[serde(skip)]
+    pub game_started_at: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,6 +132,7 @@ pub enum GameStatus {
 impl Default for HeadlessGameState {
     fn default() -> Self {
         Self {
+            player: 0,
             game_phase: GamePhase::MainMenu,
             player_resources: PlayerResources::default(),
             units: Vec::new(),
@@ -130,6 +143,7 @@ impl Default for HeadlessGameState {
             ai_actions_queued: 0,
             memory_hooks_active: 0,
             last_update: 0,
+            game_started_at: None,
         }
     }
 }
@@ -161,56 +175,40 @@ impl Default for MapInfo {
 
 pub struct HeadlessGameEngine {
     config: HeadlessConfig,
-    memory_hooks: Arc<MemoryHookManager>,
-    function_hooks: Arc<FunctionHookManager>,
+    backend: Arc<dyn GameBackend>,
     ai_controller: Arc<AIController>,
     data_exporter: Arc<DataExporter>,
     replay_system: Arc<ReplaySystem>,
-    input_simulator: Arc<Mutex<InputSimulator>>,  // Wrap in Mutex for mutable access
     game_state: Arc<Mutex<HeadlessGameState>>,
     running: Arc<Mutex<bool>>,
 }
 
 impl HeadlessGameEngine {
+    /// Create an engine playing the default backend, the live game on Windows
     pub async fn new(config: HeadlessConfig) -> Result<Self> {
+        let backend = game_backend::default_backend().await?;
+        Self::with_backend(config, backend).await
+    }
+    
+    /// Create an engine playing against any backend
+    pub async fn with_backend(config: HeadlessConfig, backend: Arc<dyn GameBackend>) -> Result<Self> {
         info!("🔧 Initializing Headless Game Engine...");
         
-        // Initialize all system components
-        let memory_hooks = Arc::new(MemoryHookManager::new().await?);
-        let function_hooks = Arc::new(FunctionHookManager::new().await?);
         let ai_controller = Arc::new(AIController::new().await?);
         let data_exporter = Arc::new(DataExporter::new().await?);
         let replay_system = Arc::new(ReplaySystem::new().await?);
-        let input_simulator = Arc::new(Mutex::new(InputSimulator::new()));
-        
-        let game_state = Arc::new(Mutex::new(HeadlessGameState::default()));
-        let running = Arc::new(Mutex::new(false));
-        
-        info!("✅ Headless Game Engine initialized successfully");
         
-        Ok(Self {
-            config,
-            memory_hooks,
-            function_hooks,
-            ai_controller,
-            data_exporter,
-            replay_system,
-            input_simulator,
-            game_state,
-            running,
-        })
+        Self::new_with_components(config, backend, ai_controller, data_exporter, replay_system).await
     }
     
     pub async fn new_with_components(
         config: HeadlessConfig,
-        memory_hooks: Arc<MemoryHookManager>,
-        function_hooks: Arc<FunctionHookManager>,
+        backend: Arc<dyn GameBackend>,
         ai_controller: Arc<AIController>,
         data_exporter: Arc<DataExporter>,
         replay_system: Arc<ReplaySystem>,
-        input_simulator: Arc<Mutex<InputSimulator>>,
     ) -> Result<Self> {
-        info!("🔧 Initializing Headless Game Engine with components...");
+        info!("🔧 Initializing Headless Game Engine with {} backend...", backend.name());
         
         let game_state = Arc::new(Mutex::new(HeadlessGameState::default()));
         let running = Arc::new(Mutex::new(false));
@@ -219,17 +217,20 @@ impl HeadlessGameEngine {
         
         Ok(Self {
             config,
-            memory_hooks,
-            function_hooks,
+            backend,
             ai_controller,
             data_exporter,
             replay_system,
-            input_simulator,
             game_state,
             running,
         })
     }
     
+    /// The backend this engine plays against
+    pub fn backend(&self) -> Arc<dyn GameBackend> {
+        self.backend.clone()
+    }
+    
     pub async fn start_headless_game(&mut self) -> Result<()> {
         info!("🎮 Starting headless WC2 Remastered...");
         
@@ -247,40 +248,17 @@ impl HeadlessGameEngine {
     }
     
     async fn initialize_game(&mut self) -> Result<()> {
-        info!("🎮 Initializing headless game...");
-        
-        // Initialize memory hooks - these are already initialized when created
-        // We just need to install the hooks
-        self.memory_hooks.install_all_hooks().await?;
+        info!("🎮 Initializing headless game on {} backend...", self.backend.name());
         
-        // Initialize function hooks - these are already initialized when created
-        // We just need to install the hooks
-        self.function_hooks.install_all_hooks().await?;
+        self.backend.connect().await?;
         
-        // Initialize input simulator
-        {
-            let mut simulator = self.input_simulator.lock().await;
-            simulator.initialize().await?;
-            
-            // Check if Warcraft II is running
-            if !simulator.is_warcraft_ii_running().await {
-                info!("🎮 Warcraft II not running, attempting to launch...");
-                
-                // Try to launch Warcraft II
-                match simulator.launch_warcraft_ii().await {
-                    Ok(()) => {
-                        info!("✅ Warcraft II launched successfully");
-                    }
-                    Err(e) => {
-                        warn!("⚠️ Failed to launch Warcraft II: {}", e);
-                        info!("💡 Please launch Warcraft II manually and try again");
-                    }
-                }
-            }
+        if self.config.enable_replay_recording {
+            self.replay_system.start_recording().await?;
         }
         
-        // Note: These components are already initialized when created
-        // We don't need to call initialize() on Arc-wrapped components
+        if self.config.enable_decision_trace {
+            self.data_exporter.start_decision_trace().await?;
+        }
         
         info!("✅ Game initialization complete");
         Ok(())
@@ -293,16 +271,15 @@ impl HeadlessGameEngine {
         *running = true;
         drop(running); // Release the lock
         
+        let tick = Duration::from_millis(16); // ~60 FPS
+        
         while *self.running.lock().await {
-            // Update memory state
-            self.update_memory_state().await?;
+            // Update game state from the backend
+            self.update_game_state().await?;
             
             // Update AI controller
             self.update_ai_controller().await?;
             
-            // Execute AI actions (empty vector for now)
-            self.execute_ai_actions(vec![]).await?;
-            
             // Export game data if performance monitoring is enabled
             if self.config.enable_performance_monitoring {
                 self.export_game_data().await?;
@@ -313,236 +290,45 @@ impl HeadlessGameEngine {
                 break;
             }
             
-            // Small delay to prevent busy waiting
-            tokio::time::sleep(tokio::time::Duration::from_millis(16)).await; // ~60 FPS
+            self.backend.advance(tick).await?;
         }
         
         info!("🔄 Game loop ended");
         Ok(())
     }
     
-    async fn update_memory_state(&mut self) -> Result<()> {
-        // Check if Warcraft II is running before attempting to read memory
-        {
-            let simulator = self.input_simulator.lock().await;
-            if simulator.is_warcraft_ii_running().await {
-                info!("🎮 Warcraft II is running, attempting to read real memory...");
-                
-                // Try to read memory from multiple base addresses
-                let base_addresses = vec![0x00400000, 0x10000000, 0x20000000];
-                
-                for base_addr in base_addresses {
-                    match self.memory_hooks.read_game_memory(base_addr, 4096).await {
-                        Ok(memory_data) => {
-                            match self.memory_hooks.parse_game_state(&memory_data).await {
-                                Ok(memory_state) => {
-                                    // Update game state with real memory data
-                                    let mut game_state = self.game_state.lock().await;
-                                    game_state.update_from_memory_state(&memory_state);
-                                    
-                                    break; // Successfully read and parsed memory
-                                }
-                                Err(e) => {
-                                    debug!("⚠️ Failed to parse memory from 0x{:x}: {}", base_addr, e);
-                                    continue;
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            debug!("⚠️ Failed to read memory from 0x{:x}: {}", base_addr, e);
-                            continue;
-                        }
-                    }
-                }
-            } else {
-                debug!("🎮 Warcraft II not running, using simulated memory state");
-                // Fall back to simulated state
-                let _current_state = self.memory_hooks.get_current_state().await?;
-            }
-        }
-        
+    async fn update_game_state(&self) -> Result<()> {
+        let observed = self.backend.observe().await?;
+        *self.game_state.lock().await = observed;
         Ok(())
     }
     
     async fn update_ai_controller(&self) -> Result<()> {
-        // Get current game state
-        let game_state = self.game_state.lock().await.clone();
+        // Let the AI observe, decide and act through the backend
+        let commands = self.ai_controller.play_turn(self.backend.as_ref()).await?;
         
-        // Let AI make enhanced decisions with advanced behaviors
-        let actions = self.ai_controller.make_enhanced_decisions(&game_state).await?;
-        
-        // Execute AI actions
-        if !actions.is_empty() {
-            self.execute_ai_actions(actions).await?;
-        }
-        
-        // Periodically adapt AI strategy
-        if game_state.game_time % 1000 == 0 { // Every 1000 game ticks
-            if let Some(ai_controller) = Arc::get_mut(&mut self.ai_controller.clone()) {
-                ai_controller.adapt_strategy().await?;
+        if self.config.enable_decision_trace {
+            if let Some(trace) = self.ai_controller.take_decision_trace().await {
+                self.data_exporter.record_decision_trace(&trace).await?;
             }
         }
         
-        Ok(())
-    }
-    
-    async fn execute_ai_actions(&self, actions: Vec<String>) -> Result<()> {
-        for action in actions {
-            debug!("🤖 Executing AI action: {}", action);
-            
-            // Parse and execute the action using a flexible parser
-            self.parse_and_execute_action(&action).await?;
-        }
-        
-        Ok(())
-    }
-    
-    /// Parse and execute AI actions with flexible parsing
-    async fn parse_and_execute_action(&self, action: &str) -> Result<()> {
-        let action_lower = action.to_lowercase();
-        
-        // Building actions: "build TownHall", "build Farm", etc.
-        if action_lower.starts_with("build ") {
-            let building_type = action[6..].trim(); // Remove "build " prefix
-            let (x, y) = self.get_optimal_building_position(building_type).await?;
-            self.ai_build_building(building_type, x, y).await?;
-            return Ok(());
-        }
-        
-        // Training actions: "train Peasant", "train Footman", etc.
-        if action_lower.starts_with("train ") {
-            let unit_type = action[6..].trim(); // Remove "train " prefix
-            self.ai_train_unit(unit_type).await?;
-            return Ok(());
-        }
-        
-        // Attack actions: "attack 64 64", "attack 128 128", etc.
-        if action_lower.starts_with("attack ") {
-            let coords = action[7..].trim(); // Remove "attack " prefix
-            if let Some((x, y)) = self.parse_coordinates(coords) {
-                self.ai_attack_move(x, y).await?;
-                return Ok(());
+        if self.config.enable_replay_recording {
+            let player = self.game_state.lock().await.player;
+            for command in &commands {
+                self.replay_system.record_ai_action(&command.to_string()).await?;
+                self.replay_system.record_player_action(player, command).await?;
             }
-        }
-        
-        // Click actions: "click 32 32", "click 64 64", etc.
-        if action_lower.starts_with("click ") {
-            let coords = action[6..].trim(); // Remove "click " prefix
-            if let Some((x, y)) = self.parse_coordinates(coords) {
-                let mouse_action = crate::input_simulator::MouseAction::Click { x, y };
-                self.execute_mouse_action(mouse_action).await?;
-                return Ok(());
+            if !commands.is_empty() {
+                self.replay_system.record_backend_snapshot(self.backend.as_ref()).await?;
             }
         }
         
-        // Formation actions: "formation_line", "formation_wedge", etc.
-        if action_lower.starts_with("formation_") {
-            self.execute_formation_action(action).await?;
-            return Ok(());
-        }
-        
-        // Resource gathering actions
-        if action_lower.contains("gather") || action_lower.contains("mine") {
-            self.execute_resource_gathering_action(action).await?;
-            return Ok(());
-        }
-        
-        // Default: try to execute as a hotkey
-        if let Ok(hotkey) = self.parse_hotkey_from_action(action) {
-            self.execute_game_hotkey(hotkey).await?;
-            return Ok(());
-        }
-        
-        // Unknown action - log and skip
-        warn!("⚠️ Unknown AI action: {}", action);
-        Ok(())
-    }
-    
-    /// Get optimal building position based on building type and current game state
-    async fn get_optimal_building_position(&self, building_type: &str) -> Result<(i32, i32)> {
-        // For now, return a simple position. In a real implementation,
-        // this would analyze the current map and building layout
-        match building_type.to_lowercase().as_str() {
-            "townhall" | "town hall" => Ok((64, 64)),
-            "farm" => Ok((80, 80)),
-            "barracks" => Ok((100, 100)),
-            "blacksmith" => Ok((120, 120)),
-            "tower" => Ok((90, 90)),
-            _ => Ok((100, 100)), // Default position
-        }
-    }
-    
-    /// Parse coordinates from string like "64 64" or "128 128"
-    fn parse_coordinates(&self, coords: &str) -> Option<(i32, i32)> {
-        let parts: Vec<&str> = coords.split_whitespace().collect();
-        if parts.len() == 2 {
-            if let (Ok(x), Ok(y)) = (parts[0].parse::<i32>(), parts[1].parse::<i32>()) {
-                return Some((x, y));
-            }
-        }
-        None
-    }
-    
-    /// Parse hotkey from action string
-    fn parse_hotkey_from_action(&self, action: &str) -> Result<crate::input_simulator::GameHotkey> {
-        match action.to_lowercase().as_str() {
-            "start_game" => Ok(crate::input_simulator::GameHotkey::StartGame),
-            "build_farm" => Ok(crate::input_simulator::GameHotkey::BuildFarm),
-            "build_barracks" => Ok(crate::input_simulator::GameHotkey::BuildBarracks),
-            "build_town_hall" => Ok(crate::input_simulator::GameHotkey::BuildTownHall),
-            "build_peasant" => Ok(crate::input_simulator::GameHotkey::BuildPeasant),
-            "build_archer" => Ok(crate::input_simulator::GameHotkey::BuildArcher),
-            "build_knight" => Ok(crate::input_simulator::GameHotkey::BuildKnight),
-            "attack_move" => Ok(crate::input_simulator::GameHotkey::Attack),
-            _ => Err(anyhow!("Unknown hotkey action: {}", action)),
-        }
-    }
-    
-    /// Execute resource gathering action
-    async fn execute_resource_gathering_action(&self, action: &str) -> Result<()> {
-        // Parse resource type and location from action
-        if action.to_lowercase().contains("gold") {
-            // Send workers to gold mine
-            let mouse_action = crate::input_simulator::MouseAction::Click { x: 32, y: 32 };
-            self.execute_mouse_action(mouse_action).await?;
-        } else if action.to_lowercase().contains("wood") {
-            // Send workers to forest
-            let mouse_action = crate::input_simulator::MouseAction::Click { x: 96, y: 96 };
-            self.execute_mouse_action(mouse_action).await?;
-        }
-        Ok(())
-    }
-    
-    /// Execute formation-based actions
-    async fn execute_formation_action(&self, action: &str) -> Result<()> {
-        // Parse formation action string (e.g., "formation_line_100_100_0")
-        let parts: Vec<&str> = action.split('_').collect();
-        if parts.len() >= 4 {
-            let formation_type = parts[1];
-            let center_x: i32 = parts[2].parse().unwrap_or(100);
-            let center_y: i32 = parts[3].parse().unwrap_or(100);
-            
-            match formation_type {
-                "line" => {
-                    debug!("📏 Executing line formation at ({}, {})", center_x, center_y);
-                    // In real implementation, this would select units and move them to line formation
-                    self.ai_attack_move(center_x, center_y).await?;
-                }
-                "wedge" => {
-                    debug!("🔺 Executing wedge formation at ({}, {})", center_x, center_y);
-                    // In real implementation, this would create a wedge formation
-                    self.ai_attack_move(center_x, center_y).await?;
-                }
-                "circle" => {
-                    if let Some(unit_count) = parts.get(4).and_then(|s| s.parse::<i32>().ok()) {
-                        debug!("⭕ Executing circle formation at ({}, {}) with {} units", center_x, center_y, unit_count);
-                        // In real implementation, this would arrange units in a circle
-                        self.ai_attack_move(center_x, center_y).await?;
-                    }
-                }
-                _ => {
-                    debug!("❓ Unknown formation type: {}", formation_type);
-                }
+        // Periodically adapt AI strategy
+        let game_time = self.game_state.lock().await.game_time;
+        if game_time % 1000 == 0 { // Every 1000 game ticks
+            if let Some(ai_controller) = Arc::get_mut(&mut self.ai_controller.clone()) {
+                ai_controller.adapt_strategy().await?;
             }
         }
         
@@ -592,121 +378,47 @@ impl HeadlessGameEngine {
         }
     }
     
-    // **AI CONTROL METHODS USING INPUT SIMULATOR**
+    // **AI CONTROL METHODS USING THE GAME BACKEND**
     
-    /// Execute a game hotkey
-    pub async fn execute_game_hotkey(&self, hotkey: crate::input_simulator::GameHotkey) -> Result<()> {
-        info!("🎯 Executing game hotkey: {:?}", hotkey);
+    /// Validate a command against the current map and send it to the backend
+    pub async fn issue_command(&self, command: AICommand) -> Result<()> {
+        let map_size = {
+            let game_state = self.game_state.lock().await;
+            (game_state.map_info.width, game_state.map_info.height)
+        };
+        command.validate(map_size)?;
         
-        // Now we can actually execute the hotkey using the mutex-protected input simulator
-        let mut input_sim = self.input_simulator.lock().await;
-        input_sim.execute_hotkey(hotkey.clone()).await?;
+        self.backend.issue(&command).await?;
         
-        info!("✅ Hotkey {:?} executed successfully", hotkey);
+        info!("✅ {} executed successfully", command);
         Ok(())
     }
     
-    /// Execute a mouse action
-    pub async fn execute_mouse_action(&self, action: crate::input_simulator::MouseAction) -> Result<()> {
-        info!("🖱️ Executing mouse action: {:?}", action);
-        
-        // Now we can actually execute the mouse action using the mutex-protected input simulator
-        let mut input_sim = self.input_simulator.lock().await;
-        input_sim.execute_mouse_action(action.clone()).await?;
-        
-        info!("✅ Mouse action {:?} executed successfully", action);
-        Ok(())
+    /// Execute a game hotkey
+    pub async fn execute_game_hotkey(&self, hotkey: crate::input_simulator::GameHotkey) -> Result<()> {
+        info!("🎯 Executing game hotkey: {:?}", hotkey);
+        self.issue_command(AICommand::Hotkey { hotkey }).await
     }
     
     /// AI builds a building
-    pub async fn ai_build_building(&self, building_type: &str, x: i32, y: i32) -> Result<()> {
-        info!("🏗️ AI building {} at ({}, {})", building_type, x, y);
-        
-        // Now we can actually execute the building action using the mutex-protected input simulator
-        let mut input_sim = self.input_simulator.lock().await;
-        
-        // Select the building type from the build menu
-        input_sim.execute_hotkey(crate::input_simulator::GameHotkey::BuildTownHall).await?;
-        
-        // Move mouse to position and click to place building
-        let mouse_action = crate::input_simulator::MouseAction::Click { x, y };
-        input_sim.execute_mouse_action(mouse_action).await?;
-        
-        info!("✅ Building {} at ({}, {}) executed successfully", building_type, x, y);
-        Ok(())
+    pub async fn ai_build_building(&self, building: BuildingKind, x: i32, y: i32) -> Result<()> {
+        self.issue_command(AICommand::BuildAt { building, x, y }).await
     }
     
     /// AI trains a unit
-    pub async fn ai_train_unit(&self, unit_type: &str) -> Result<()> {
-        info!("⚔️ AI training {}", unit_type);
-        
-        // Now we can actually execute the unit training action using the mutex-protected input simulator
-        let mut input_sim = self.input_simulator.lock().await;
-        
-        // Select the appropriate building for training this unit type
-        match unit_type {
-            "Peasant" => {
-                input_sim.execute_hotkey(crate::input_simulator::GameHotkey::BuildPeasant).await?;
-            }
-            "Archer" => {
-                input_sim.execute_hotkey(crate::input_simulator::GameHotkey::BuildArcher).await?;
-            }
-            "Knight" => {
-                input_sim.execute_hotkey(crate::input_simulator::GameHotkey::BuildKnight).await?;
-            }
-            _ => {
-                // Generic unit training - use the build menu
-                input_sim.execute_hotkey(crate::input_simulator::GameHotkey::BuildWorker).await?;
-            }
-        }
-        
-        info!("✅ Training {} executed successfully", unit_type);
-        Ok(())
+    pub async fn ai_train_unit(&self, unit: UnitKind) -> Result<()> {
+        self.issue_command(AICommand::Train { unit }).await
     }
     
     /// AI attack move
     pub async fn ai_attack_move(&self, x: i32, y: i32) -> Result<()> {
-        info!("⚔️ AI attack moving to ({}, {})", x, y);
-        
-        // Now we can actually execute the attack move action using the mutex-protected input simulator
-        let mut input_sim = self.input_simulator.lock().await;
-        
-        // Use attack move hotkey (A key) and then click at the target position
-        input_sim.execute_hotkey(crate::input_simulator::GameHotkey::Attack).await?;
-        
-        // Move mouse to target position and click
-        let mouse_action = crate::input_simulator::MouseAction::Click { x, y };
-        input_sim.execute_mouse_action(mouse_action).await?;
-        
-        info!("✅ Attack move to ({}, {}) executed successfully", x, y);
-        Ok(())
+        self.issue_command(AICommand::AttackMove { x, y }).await
     }
     
     /// AI select units
     pub async fn ai_select_units(&self, start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Result<()> {
-        info!("👆 AI selecting units from ({}, {}) to ({}, {})", start_x, start_y, end_x, end_y);
-        
-        // Now we can actually execute the unit selection action using the mutex-protected input simulator
-        let mut input_sim = self.input_simulator.lock().await;
-        
-        // Click and drag to select units in the specified area
-        let start_action = crate::input_simulator::MouseAction::Click { x: start_x, y: start_y };
-        let end_action = crate::input_simulator::MouseAction::Click { x: end_x, y: end_y };
-        
-        // Click at start position
-        input_sim.execute_mouse_action(start_action).await?;
-        
-        // Drag to end position (this would need to be implemented as a drag action)
-        // For now, we'll use a simple click at the end position
-        input_sim.execute_mouse_action(end_action).await?;
-        
-        info!("✅ Unit selection from ({}, {}) to ({}, {}) executed successfully", start_x, start_y, end_x, end_y);
-        Ok(())
-    }
-    
-    /// Get the current status of the input simulator
-    pub async fn get_input_status(&self) -> crate::input_simulator::InputSimulatorStatus {
-        self.input_simulator.lock().await.get_status()
+        let selection = Selection::Area { start_x, start_y, end_x, end_y };
+        self.issue_command(AICommand::Select { selection }).await
     }
     
     async fn cleanup_game(&mut self) -> Result<()> {
@@ -717,12 +429,16 @@ impl HeadlessGameEngine {
             self.replay_system.stop_recording().await?;
         }
         
-        // Uninstall hooks
-        self.memory_hooks.uninstall_all_hooks().await?;
-        self.function_hooks.uninstall_all_hooks().await?;
+        if self.config.enable_decision_trace {
+            self.data_exporter.finish_decision_trace().await?;
+        }
+        
+        // Release backend hooks
+        self.backend.disconnect().await?;
         
         // Export final data
         self.export_game_data().await?;
+        self.data_exporter.export_backend_state(self.backend.as_ref()).await?;
         
         info!("✅ Cleanup completed");
         Ok(())
@@ -732,78 +448,14 @@ impl HeadlessGameEngine {
         self.game_state.lock().await.clone()
     }
     
-    /// Read game memory and update the current game state
+    /// Observe the backend and update the current game state
     pub async fn read_game_memory(&self) -> Result<()> {
-        info!("🔍 Reading game memory to update state...");
+        info!("🔍 Reading game state from {} backend...", self.backend.name());
         
-        // Read memory from the game process
-        let memory_data = self.memory_hooks.read_game_memory(0x00400000, 4096).await?;
-        let memory_state = self.memory_hooks.parse_game_state(&memory_data).await?;
+        self.update_game_state().await?;
         
-        // Update our game state with the memory data
-        let mut game_state = self.game_state.lock().await;
-        
-        // Update game phase
-        game_state.game_phase = match memory_state.game_phase.as_str() {
-            "MainMenu" => GamePhase::MainMenu,
-            "InGame" => GamePhase::InGame,
-            "Paused" => GamePhase::Paused,
-            "Victory" => GamePhase::Victory,
-            "Defeat" => GamePhase::Defeat,
-            "Campaign" => GamePhase::Campaign,
-            "CustomScenario" => GamePhase::CustomScenario,
-            "Multiplayer" => GamePhase::Multiplayer,
-            "MemoryAccessible" => GamePhase::InGame, // Memory is accessible, assume in game
-            _ => GamePhase::MainMenu,
-        };
-        
-        // Update resources if available
-        if let Some(gold) = memory_state.player_resources.get("gold") {
-            game_state.player_resources.gold = *gold;
-        }
-        if let Some(wood) = memory_state.player_resources.get("wood") {
-            game_state.player_resources.wood = *wood;
-        }
-        if let Some(oil) = memory_state.player_resources.get("oil") {
-            game_state.player_resources.oil = *oil;
-        }
-        if let Some(food) = memory_state.player_resources.get("food") {
-            game_state.player_resources.food_current = *food;
-        }
-        
-        // Update units
-        game_state.units = memory_state.units.iter().map(|unit| UnitInfo {
-            id: unit.id,
-            unit_type: unit.unit_type.clone(),
-            position: (unit.x, unit.y),
-            health: unit.health,
-            max_health: unit.health, // We'll need to determine max health separately
-            owner: unit.owner,
-            is_selected: false, // This would need to be determined from game state
-            current_action: None, // This would need to be determined from game state
-        }).collect();
-        
-        // Update buildings
-        game_state.buildings = memory_state.buildings.iter().map(|building| BuildingInfo {
-            id: building.id,
-            building_type: building.building_type.clone(),
-            position: (building.x, building.y),
-            health: building.health,
-            max_health: building.health, // We'll need to determine max health separately
-            owner: building.owner,
-            is_completed: true, // Assume completed for now
-            current_production: None, // This would need to be determined from game state
-        }).collect();
-        
-        // Update map info
-        game_state.map_info.width = memory_state.map_data.width;
-        game_state.map_info.height = memory_state.map_data.height;
-        
-        // Update timestamp
-        game_state.last_update = memory_state.timestamp;
-        game_state.game_time = memory_state.timestamp;
-        
-        info!("✅ Game state updated from memory: {} units, {} buildings", 
+        let game_state = self.game_state.lock().await;
+        info!("✅ Game state updated: {} units, {} buildings", 
               game_state.units.len(), game_state.buildings.len());
         
         Ok(())
@@ -820,63 +472,32 @@ impl HeadlessGameEngine {
         Ok(())
     }
 
-    /// Start continuous memory monitoring
+    /// Start continuous game state monitoring
     pub async fn start_memory_monitoring(&self) -> Result<()> {
-        info!("🔍 Starting continuous memory monitoring...");
+        info!("🔍 Starting continuous state monitoring...");
         
-        let memory_hooks = self.memory_hooks.clone();
+        let backend = self.backend.clone();
         let game_state = self.game_state.clone();
         
-        // Spawn a background task for memory monitoring
+        // Spawn a background task for state monitoring
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(100)); // 10 FPS
             
             loop {
                 interval.tick().await;
                 
-                // Read game memory
-                // Try to read from a common memory address
-                match memory_hooks.read_game_memory(0x00400000, 4096).await {
-                    Ok(memory_data) => {
-                        // Parse the memory data
-                        match memory_hooks.parse_game_state(&memory_data).await {
-                            Ok(memory_state) => {
-                                // Update game state with memory data
-                                let mut state = game_state.lock().await;
-                                
-                                // Update basic info
-                                state.game_phase = match memory_state.game_phase.as_str() {
-                                    "MainMenu" => GamePhase::MainMenu,
-                                    "InGame" => GamePhase::InGame,
-                                    "Paused" => GamePhase::Paused,
-                                    "Victory" => GamePhase::Victory,
-                                    "Defeat" => GamePhase::Defeat,
-                                    "Campaign" => GamePhase::Campaign,
-                                    "CustomScenario" => GamePhase::CustomScenario,
-                                    "Multiplayer" => GamePhase::Multiplayer,
-                                    "MemoryAccessible" => GamePhase::InGame,
-                                    _ => GamePhase::MainMenu,
-                                };
-                                
-                                state.last_update = memory_state.timestamp;
-                                state.game_time = memory_state.timestamp;
-                                
-                                // Update memory hooks count
-                                state.memory_hooks_active = memory_state.units.len() + memory_state.buildings.len();
-                            }
-                            Err(e) => {
-                                debug!("⚠️ Memory parsing failed: {}", e);
-                            }
-                        }
+                match backend.observe().await {
+                    Ok(observed) => {
+                        *game_state.lock().await = observed;
                     }
                     Err(e) => {
-                        debug!("⚠️ Memory read failed: {}", e);
+                        debug!("⚠️ State observation failed: {}", e);
                     }
                 }
             }
         });
         
-        info!("✅ Memory monitoring started");
+        info!("✅ State monitoring started");
         Ok(())
     }
 
@@ -898,6 +519,17 @@ impl HeadlessGameEngine {
 }
 
 impl HeadlessGameState {
+    /// Whether the game is over or was left, with or without a result
+    pub fn has_ended(&self) -> bool {
+        matches!(self.game_status, GameStatus::Completed | GameStatus::Error)
+            || matches!(self.game_phase, GamePhase::MainMenu | GamePhase::Loading)
+    }
+
+    /// Seconds of game time since the game started
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.game_time as f64 / TICKS_PER_SECOND as f64
+    }
+
     pub fn update_from_memory(&mut self, _memory_data: &[u8]) {
         // This would parse the actual memory data from the game
         // For now, we'll simulate some updates
@@ -931,6 +563,7 @@ impl HeadlessGameState {
             "Campaign" => GamePhase::Campaign,
             "CustomScenario" => GamePhase::CustomScenario,
             "Multiplayer" => GamePhase::Multiplayer,
+            "MemoryAccessible" => GamePhase::InGame, // Memory is accessible, assume in game
             _ => GamePhase::MainMenu,
         };
         
@@ -954,9 +587,47 @@ impl HeadlessGameState {
             self.player_resources.population = *population;
         }
         
-        // Update timestamp
+        // Update units
+        self.units = memory_state.units.iter().map(|unit| UnitInfo {
+            id: unit.id,
+            unit_type: unit.unit_type.clone(),
+            position: (unit.x, unit.y),
+            health: unit.health,
+            max_health: unit.health, // We'll need to determine max health separately
+            owner: unit.owner,
+            is_selected: false, // This would need to be determined from game state
+            current_action: None, // This would need to be determined from game state
+        }).collect();
+        
+        // Update buildings
+        self.buildings = memory_state.buildings.iter().map(|building| BuildingInfo {
+            id: building.id,
+            building_type: building.building_type.clone(),
+            position: (building.x, building.y),
+            health: building.health,
+            max_health: building.health, // We'll need to determine max health separately
+            owner: building.owner,
+            is_completed: true, // Assume completed for now
+            current_production: None, // This would need to be determined from game state
+        }).collect();
+        
+        // Update map info
+        if memory_state.map_data.width > 0 && memory_state.map_data.height > 0 {
+            self.map_info.width = memory_state.map_data.width;
+            self.map_info.height = memory_state.map_data.height;
+        }
+        
+        // Memory states are stamped with wall-clock time; count ticks from the
+        // first poll that found a game in progress
         self.last_update = memory_state.timestamp;
-        self.game_time = memory_state.timestamp;
+        let started_at = match self.game_phase {
+            GamePhase::MainMenu | GamePhase::Loading => {
+                self.game_started_at = None;
+                memory_state.timestamp
+            }
+            _ => *self.game_started_at.get_or_insert(memory_state.timestamp),
+        };
+        self.game_time = memory_state.timestamp.saturating_sub(started_at) * TICKS_PER_SECOND as u64 / 1000;
         
         // Update memory hooks count
         self.memory_hooks_active = memory_state.units.len() + memory_state.buildings.len();
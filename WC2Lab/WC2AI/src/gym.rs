@@ -0,0 +1,26 @@
+use anyhow::Result;
+use log::info;
+use tokio::io::BufReader;
+
+use wc2_ai::rl_env::{serve_json, EnvConfig, WC2Env};
+
+/// RL environment server: line-delimited JSON on stdin/stdout, logs on stderr
+///
+/// Usage: `wc2-gym [config.json]`
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    env_logger::init();
+
+    let config = match std::env::args().nth(1) {
+        Some(path) => serde_json::from_str::<EnvConfig>(&std::fs::read_to_string(path)?)?,
+        None => EnvConfig::default(),
+    };
+
+    let mut env = WC2Env::new(config).await?;
+    info!("🏋️ Serving RL environment on stdin/stdout");
+    serve_json(&mut env, BufReader::new(tokio::io::stdin()), tokio::io::stdout()).await?;
+
+    info!("🔄 RL environment closed");
+    Ok(())
+}
@@ -1,20 +1,36 @@
-use anyhow::{anyhow, Result};
-use log::{info, warn};
+//! Keyboard and mouse control of the running game
+//!
+//! Hotkeys, mouse actions and the map view are plain data usable anywhere;
+//! `InputSimulator` sends them through Win32 input and only exists on Windows.
+
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use std::fs::OpenOptions;
-use std::io::Write;
-use chrono::Utc;
-use tokio::time::sleep;
+
+use crate::ai_command::{BuildingKind, SpellKind, UnitKind, UpgradeKind};
+
+#[cfg(windows)]
+use {
+    anyhow::{anyhow, Result},
+    chrono::Utc,
+    log::{info, warn},
+    std::fs::OpenOptions,
+    std::io::Write,
+    std::time::Duration,
+    tokio::time::sleep,
+    crate::ai_command::{AICommand, Selection},
+};
 
 // Windows API imports for real input simulation
-use windows::Win32::Foundation::*;
-use windows::Win32::System::Diagnostics::ToolHelp::*;
-use windows::Win32::System::Threading::*;
-use windows::Win32::UI::Input::KeyboardAndMouse::*;
-use windows::Win32::UI::WindowsAndMessaging::*;
-use windows::core::PCSTR;
+#[cfg(windows)]
+use windows::{
+    core::PCSTR,
+    Win32::Foundation::*,
+    Win32::System::Diagnostics::ToolHelp::*,
+    Win32::System::Threading::*,
+    Win32::UI::Input::KeyboardAndMouse::*,
+    Win32::UI::WindowsAndMessaging::*,
+};
 
+#[cfg(windows)]
 fn log_to_json(level: &str, message: &str) {
     let log_entry = serde_json::json!({
         "timestamp": Utc::now().to_rfc3339(),
@@ -60,12 +76,17 @@ pub enum GameHotkey {
     BuildArcher,
     BuildKnight,
     BuildPaladin,
-    BuildPriest,
     BuildMage,
     BuildCatapult,
     BuildBallista,
     BuildPeasant,    // Human worker
     BuildScoutTower, // Scout tower for scouting
+
+    // Research, in the building that researches the upgrade
+    Research(UpgradeKind),
+
+    // Spells, on the caster's command card
+    Cast(SpellKind),
     
     // Game commands
     PauseGame,
@@ -95,13 +116,44 @@ pub enum GameHotkey {
     HoldPosition,
     
     // Special abilities
-    CastSpell,
     UseAbility,
     SetRallyPoint,
     Repair,
     Harvest,
 }
 
+impl GameHotkey {
+    /// Command-card key for train, build, research and spell hotkeys, from the game rules
+    pub fn production_key(&self) -> Option<char> {
+        let rules = match self {
+            GameHotkey::Research(upgrade) => return upgrade.rules().ok()?.hotkey,
+            GameHotkey::Cast(spell) => return spell.rules().ok()?.hotkey,
+            GameHotkey::BuildTownHall => BuildingKind::TownHall.rules(),
+            GameHotkey::BuildBarracks => BuildingKind::Barracks.rules(),
+            GameHotkey::BuildFarm => BuildingKind::Farm.rules(),
+            GameHotkey::BuildTower => BuildingKind::Tower.rules(),
+            GameHotkey::BuildWall => BuildingKind::Wall.rules(),
+            GameHotkey::BuildLumberMill => BuildingKind::LumberMill.rules(),
+            GameHotkey::BuildBlacksmith => BuildingKind::Blacksmith.rules(),
+            GameHotkey::BuildStable => BuildingKind::Stable.rules(),
+            GameHotkey::BuildChurch => BuildingKind::Church.rules(),
+            GameHotkey::BuildWorkshop => BuildingKind::Workshop.rules(),
+            GameHotkey::BuildOilWell => BuildingKind::OilWell.rules(),
+            GameHotkey::BuildScoutTower => BuildingKind::ScoutTower.rules(),
+            GameHotkey::BuildWorker | GameHotkey::BuildPeasant => UnitKind::Peasant.rules(),
+            GameHotkey::BuildFootman => UnitKind::Footman.rules(),
+            GameHotkey::BuildArcher => UnitKind::Archer.rules(),
+            GameHotkey::BuildKnight => UnitKind::Knight.rules(),
+            GameHotkey::BuildPaladin => UnitKind::Paladin.rules(),
+            GameHotkey::BuildMage => UnitKind::Mage.rules(),
+            GameHotkey::BuildCatapult => UnitKind::Catapult.rules(),
+            GameHotkey::BuildBallista => UnitKind::Ballista.rules(),
+            _ => return None,
+        };
+        rules.ok()?.hotkey
+    }
+}
+
 /// Represents mouse actions for AI control
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MouseAction {
@@ -113,15 +165,77 @@ pub enum MouseAction {
     Move { x: i32, y: i32 },
 }
 
+/// Left edge of the map view on the 640x480 game screen
+const MAP_VIEW_LEFT: i32 = 176;
+
+/// Top edge of the map view
+const MAP_VIEW_TOP: i32 = 16;
+
+/// Tiles across and down the map view
+const MAP_VIEW_TILES: i32 = 14;
+
+/// Screen pixels per map tile
+const TILE_PIXELS: i32 = 32;
+
+/// Top-left corner of the minimap
+const MINIMAP_ORIGIN: (i32, i32) = (24, 24);
+
+/// Width and height of the minimap
+const MINIMAP_PIXELS: i32 = 128;
+
+/// Which map tiles the game screen shows
+///
+/// The game does not expose its camera, so the simulator tracks where it last
+/// put the camera by clicking the minimap. Until then the camera is unknown
+/// and every tile command scrolls first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewport {
+    /// Map size in tiles
+    pub map_size: (u32, u32),
+    /// Top-left tile of the map view
+    pub camera: Option<(i32, i32)>,
+}
+
+impl Viewport {
+    pub fn new(map_size: (u32, u32)) -> Self {
+        Self { map_size, camera: None }
+    }
+
+    /// Screen pixel at the centre of a tile, if the map view shows it
+    pub fn tile_to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (camera_x, camera_y) = self.camera?;
+        let (column, row) = (x - camera_x, y - camera_y);
+        let visible = (0..MAP_VIEW_TILES).contains(&column) && (0..MAP_VIEW_TILES).contains(&row);
+        visible.then(|| {
+            (MAP_VIEW_LEFT + column * TILE_PIXELS + TILE_PIXELS / 2, MAP_VIEW_TOP + row * TILE_PIXELS + TILE_PIXELS / 2)
+        })
+    }
+
+    /// Minimap pixel that centres the map view on a tile
+    pub fn minimap_point(&self, x: i32, y: i32) -> (i32, i32) {
+        let scale = |tile: i32, tiles: u32| (2 * tile + 1) * MINIMAP_PIXELS / (2 * tiles.max(1) as i32);
+        (MINIMAP_ORIGIN.0 + scale(x, self.map_size.0), MINIMAP_ORIGIN.1 + scale(y, self.map_size.1))
+    }
+
+    /// Camera after centring the map view on a tile, kept inside the map
+    pub fn centred_on(&self, x: i32, y: i32) -> (i32, i32) {
+        let clamp = |tile: i32, tiles: u32| (tile - MAP_VIEW_TILES / 2).clamp(0, (tiles as i32 - MAP_VIEW_TILES).max(0));
+        (clamp(x, self.map_size.0), clamp(y, self.map_size.1))
+    }
+}
+
 /// Main input simulator for AI control of Warcraft II
+#[cfg(windows)]
 #[derive(Debug, Clone)]
 pub struct InputSimulator {
     process_handle: Option<u64>,
     window_handle: Option<u64>,
     is_active: bool,
     status: InputSimulatorStatus,
+    viewport: Viewport,
 }
 
+#[cfg(windows)]
 impl InputSimulator {
     /// Create a new input simulator
     pub fn new() -> Self {
@@ -134,7 +248,41 @@ impl InputSimulator {
                 process_handle: None,
                 window_handle: None,
             },
+            viewport: Viewport::new((128, 128)),
+        }
+    }
+
+    /// Tell the simulator the size of the map being played, in tiles
+    pub fn set_map_size(&mut self, map_size: (u32, u32)) {
+        if self.viewport.map_size != map_size {
+            self.viewport = Viewport::new(map_size);
+        }
+    }
+
+    /// Scroll the map view to a tile if needed and return its screen pixel
+    async fn scroll_to_tile(&mut self, x: i32, y: i32) -> Result<(i32, i32)> {
+        if let Some(point) = self.viewport.tile_to_screen(x, y) {
+            return Ok(point);
         }
+
+        let (minimap_x, minimap_y) = self.viewport.minimap_point(x, y);
+        self.execute_mouse_action(MouseAction::Click { x: minimap_x, y: minimap_y }).await?;
+        self.viewport.camera = Some(self.viewport.centred_on(x, y));
+        sleep(Duration::from_millis(100)).await;
+
+        self.viewport
+            .tile_to_screen(x, y)
+            .ok_or_else(|| anyhow!("Tile ({}, {}) is outside the {}x{} map", x, y, self.viewport.map_size.0, self.viewport.map_size.1))
+    }
+
+    /// Press an order hotkey and click its target tile
+    ///
+    /// The view scrolls before the order, since a minimap click with an order
+    /// pending would carry out the order on the minimap point.
+    async fn order_at_tile(&mut self, hotkey: GameHotkey, x: i32, y: i32) -> Result<()> {
+        let (screen_x, screen_y) = self.scroll_to_tile(x, y).await?;
+        self.execute_hotkey(hotkey).await?;
+        self.execute_mouse_action(MouseAction::Click { x: screen_x, y: screen_y }).await
     }
 
     /// Initialize the input simulator
@@ -167,6 +315,15 @@ impl InputSimulator {
         // Ensure window is active before sending input
         self.ensure_window_active().await?;
 
+        // Letter keys share their virtual-key code with the uppercase ASCII letter
+        if let Some(key) = hotkey.production_key() {
+            self.send_key(key as u16, false).await?;
+            sleep(Duration::from_millis(50)).await;
+            self.send_key(key as u16, true).await?;
+            info!("✅ Hotkey executed: {:?}", hotkey);
+            return Ok(());
+        }
+
         match hotkey {
             GameHotkey::SelectAllUnits => {
                 self.send_key(VK_A.0, false).await?;  // Press A
@@ -188,26 +345,6 @@ impl InputSimulator {
                 sleep(Duration::from_millis(50)).await;
                 self.send_key(VK_B.0, true).await?;   // Release B
             }
-            GameHotkey::BuildTownHall => {
-                self.send_key(VK_T.0, false).await?;  // Press T
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_T.0, true).await?;   // Release T
-            }
-            GameHotkey::BuildBarracks => {
-                self.send_key(VK_R.0, false).await?;  // Press R
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_R.0, true).await?;   // Release R
-            }
-            GameHotkey::BuildWorker => {
-                self.send_key(VK_W.0, false).await?;  // Press W
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_W.0, true).await?;   // Release W
-            }
-            GameHotkey::BuildFootman => {
-                self.send_key(VK_F.0, false).await?;  // Press F
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_F.0, true).await?;   // Release F
-            }
             GameHotkey::Attack => {
                 self.send_key(VK_A.0, false).await?;  // Press A
                 sleep(Duration::from_millis(50)).await;
@@ -303,26 +440,6 @@ impl InputSimulator {
                 sleep(Duration::from_millis(50)).await;
                 self.send_key(VK_M.0, true).await?;   // Release M
             }
-            GameHotkey::BuildOilWell => {
-                self.send_key(VK_O.0, false).await?;  // Press O
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_O.0, true).await?;   // Release O
-            }
-            GameHotkey::BuildPeasant => {
-                self.send_key(VK_P.0, false).await?;  // Press P
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_P.0, true).await?;   // Release P
-            }
-            GameHotkey::BuildScoutTower => {
-                self.send_key(VK_T.0, false).await?;  // Press T
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_T.0, true).await?;   // Release T
-            }
-            GameHotkey::BuildTower => {
-                self.send_key(VK_T.0, false).await?;  // Press T
-                sleep(Duration::from_millis(50)).await;
-                self.send_key(VK_T.0, true).await?;   // Release T
-            }
             // Add more hotkeys as needed...
             _ => {
                 // warn!("⚠️ Hotkey {:?} not yet implemented, logging only", hotkey); // Removed warn!
@@ -931,63 +1048,72 @@ impl InputSimulator {
 
     // **HIGH-LEVEL GAME ACTIONS**
 
-    /// Build a specific building type
-    pub async fn build_building(&mut self, building_type: &str, x: i32, y: i32) -> Result<()> {
-        info!("🏗️ AI building {} at ({}, {})", building_type, x, y);
-        
-        // Select the building hotkey
-        let hotkey = match building_type.to_lowercase().as_str() {
-            "town hall" | "townhall" => GameHotkey::BuildTownHall,
-            "barracks" => GameHotkey::BuildBarracks,
-            "farm" => GameHotkey::BuildFarm,
-            "tower" => GameHotkey::BuildTower,
-            "wall" => GameHotkey::BuildWall,
-            "lumber mill" | "lumbermill" => GameHotkey::BuildLumberMill,
-            "blacksmith" => GameHotkey::BuildBlacksmith,
-            "stable" => GameHotkey::BuildStable,
-            "church" => GameHotkey::BuildChurch,
-            "workshop" => GameHotkey::BuildWorkshop,
-            "mine" => GameHotkey::BuildMine,
-            "oil well" => GameHotkey::BuildOilWell,
-            "scout tower" => GameHotkey::BuildScoutTower,
-            _ => return Err(anyhow!("Unknown building type: {}", building_type)),
-        };
-        
-        // Execute the building hotkey
-        self.execute_hotkey(hotkey).await?;
+    /// Execute a typed AI command as hotkeys and mouse actions
+    pub async fn execute_command(&mut self, command: &AICommand) -> Result<()> {
+        match command {
+            AICommand::Train { unit } => self.train_unit(*unit).await?,
+            AICommand::BuildAt { building, x, y } => self.build_building(*building, *x, *y).await?,
+            AICommand::AttackMove { x, y } => self.attack_move(*x, *y).await?,
+            AICommand::Move { x, y } => self.order_at_tile(GameHotkey::Move, *x, *y).await?,
+            AICommand::Gather { resource, x, y } => {
+                info!("⛏️ AI gathering {} at ({}, {})", resource, x, y);
+                self.execute_hotkey(GameHotkey::SelectAllWorkers).await?;
+                self.order_at_tile(GameHotkey::Harvest, *x, *y).await?;
+            }
+            AICommand::Research { upgrade } => {
+                info!("🔬 AI researching {}", upgrade);
+                self.execute_hotkey(GameHotkey::Research(*upgrade)).await?;
+            }
+            AICommand::Cast { spell, x, y } => {
+                info!("✨ AI casting {} at ({}, {})", spell, x, y);
+                self.order_at_tile(spell.hotkey(), *x, *y).await?;
+            }
+            AICommand::SetRally { x, y } => self.order_at_tile(GameHotkey::SetRallyPoint, *x, *y).await?,
+            AICommand::Select { selection } => match selection {
+                Selection::AllUnits => self.execute_hotkey(GameHotkey::SelectAllUnits).await?,
+                Selection::AllWorkers => self.execute_hotkey(GameHotkey::SelectAllWorkers).await?,
+                Selection::AllMilitary => self.execute_hotkey(GameHotkey::SelectAllMilitary).await?,
+                Selection::AllBuildings => self.execute_hotkey(GameHotkey::SelectAllBuildings).await?,
+                Selection::Area { start_x, start_y, end_x, end_y } => {
+                    self.select_units(*start_x, *start_y, *end_x, *end_y).await?
+                }
+            },
+            AICommand::Formation { formation, x, y, facing } => {
+                // Formations are approximated by an attack move to their centre
+                info!("📐 AI forming {:?} at ({}, {}) facing {:?}", formation, x, y, facing);
+                self.attack_move(*x, *y).await?;
+            }
+            AICommand::Click { x, y } => {
+                self.execute_mouse_action(MouseAction::Click { x: *x, y: *y }).await?;
+            }
+            AICommand::Hotkey { hotkey } => self.execute_hotkey(hotkey.clone()).await?,
+            AICommand::Wait { ms } => sleep(Duration::from_millis(*ms)).await,
+        }
+
+        Ok(())
+    }
+
+    /// Build a specific building type at a map tile
+    pub async fn build_building(&mut self, building: BuildingKind, x: i32, y: i32) -> Result<()> {
+        info!("🏗️ AI building {} at ({}, {})", building, x, y);
         
-        // Click where to build
-        self.execute_mouse_action(MouseAction::Click { x, y }).await?;
+        // Select the building hotkey and click where to build
+        self.order_at_tile(building.hotkey(), x, y).await?;
         
         Ok(())
     }
 
     /// Train a specific unit type
-    pub async fn train_unit(&mut self, unit_type: &str) -> Result<()> {
-        info!("⚔️ AI training {}", unit_type);
-        
-        // Select the training hotkey
-        let hotkey = match unit_type.to_lowercase().as_str() {
-            "worker" => GameHotkey::BuildWorker,
-            "footman" => GameHotkey::BuildFootman,
-            "archer" => GameHotkey::BuildArcher,
-            "knight" => GameHotkey::BuildKnight,
-            "paladin" => GameHotkey::BuildPaladin,
-            "priest" => GameHotkey::BuildPriest,
-            "mage" => GameHotkey::BuildMage,
-            "catapult" => GameHotkey::BuildCatapult,
-            "ballista" => GameHotkey::BuildBallista,
-            "peasant" => GameHotkey::BuildPeasant,
-            _ => return Err(anyhow!("Unknown unit type: {}", unit_type)),
-        };
+    pub async fn train_unit(&mut self, unit: UnitKind) -> Result<()> {
+        info!("⚔️ AI training {}", unit);
         
         // Execute the training hotkey
-        self.execute_hotkey(hotkey).await?;
+        self.execute_hotkey(unit.hotkey()).await?;
         
         Ok(())
     }
 
-    /// Attack move to a location
+    /// Attack move to a map tile
     pub async fn attack_move(&mut self, x: i32, y: i32) -> Result<()> {
         info!("⚔️ AI attack moving to ({}, {})", x, y);
         
@@ -995,8 +1121,7 @@ impl InputSimulator {
         self.execute_hotkey(GameHotkey::SelectAllMilitary).await?;
         
         // Attack move to location
-        self.execute_hotkey(GameHotkey::Attack).await?;
-        self.execute_mouse_action(MouseAction::Click { x, y }).await?;
+        self.order_at_tile(GameHotkey::Attack, x, y).await?;
         
         Ok(())
     }
@@ -1062,3 +1187,36 @@ pub struct InputSimulatorStatus {
     pub window_handle: Option<u64>,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_research_hotkeys() {
+        assert_eq!(GameHotkey::Research(UpgradeKind::MeleeWeapons).production_key(), Some('W'));
+        assert_eq!(GameHotkey::Research(UpgradeKind::Armor).production_key(), Some('S'));
+        assert_eq!(GameHotkey::Research(UpgradeKind::Arrows).production_key(), Some('U'));
+        assert_eq!(GameHotkey::Research(UpgradeKind::Paladins).production_key(), Some('P'));
+        assert!(UpgradeKind::ALL.iter().all(|upgrade| GameHotkey::Research(*upgrade).production_key().is_some()));
+        assert_eq!(GameHotkey::UseAbility.production_key(), None);
+    }
+
+    #[test]
+    fn test_tile_to_screen() {
+        let mut viewport = Viewport::new((64, 64));
+        assert_eq!(viewport.tile_to_screen(0, 0), None);
+
+        viewport.camera = Some(viewport.centred_on(32, 32));
+        assert_eq!(viewport.camera, Some((25, 25)));
+        assert_eq!(viewport.tile_to_screen(32, 32), Some((176 + 7 * 32 + 16, 16 + 7 * 32 + 16)));
+        assert_eq!(viewport.tile_to_screen(25, 38), Some((192, 16 + 13 * 32 + 16)));
+        assert_eq!(viewport.tile_to_screen(24, 32), None);
+        assert_eq!(viewport.tile_to_screen(39, 32), None);
+
+        // The view stops at the map edges
+        assert_eq!(viewport.centred_on(2, 63), (0, 50));
+        assert_eq!(viewport.minimap_point(0, 0), (25, 25));
+        assert_eq!(viewport.minimap_point(63, 63), (151, 151));
+    }
+}
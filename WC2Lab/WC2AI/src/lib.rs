@@ -14,6 +14,18 @@ pub mod ai_controller;
 pub mod data_exporter;
 pub mod replay_system;
 pub mod input_simulator;
+pub mod ai_command;
+pub mod game_backend;
+pub mod simulator;
+pub mod rl_env;
+pub mod tournament;
+pub mod build_planner;
+pub mod strategy_bandit;
+pub mod scripting;
+pub mod decision_trace;
+
+#[cfg(test)]
+mod test_fixtures;
 
 // Re-export main types for easy access
 pub use game_engine::{
@@ -28,6 +40,40 @@ pub use game_engine::{
     GameStatus,
 };
 
+pub use game_backend::{
+    GameBackend,
+    default_backend,
+};
+
+#[cfg(windows)]
+pub use game_backend::LiveGameBackend;
+
+pub use simulator::{
+    Simulation,
+    SimConfig,
+    SimMap,
+    SimulatorBackend,
+    CommandOutcome,
+    SimulatorSeat,
+};
+
+pub use rl_env::{
+    WC2Env,
+    EnvConfig,
+    EnvAction,
+    Observation,
+    RewardConfig,
+    StepInfo,
+};
+
+pub use tournament::{
+    run_tournament,
+    TournamentConfig,
+    TournamentReport,
+    TournamentMap,
+    BotConfig,
+};
+
 pub use memory_hooks::{
     MemoryHookManager,
     MemoryHook,
@@ -38,7 +84,44 @@ pub use memory_hooks::{
 pub use function_hooks::{
     FunctionHookManager,
     FunctionHook,
-    AIAction,
+};
+
+pub use ai_command::{
+    AICommand,
+    BuildingKind,
+    UnitKind,
+    ResourceKind,
+    UpgradeKind,
+    SpellKind,
+    Selection,
+};
+
+pub use build_planner::{
+    BuildPlanner,
+    BuildPlan,
+    PlanGoal,
+    PlannerStart,
+    EconomyModel,
+};
+
+pub use strategy_bandit::{
+    StrategyBandit,
+    StrategyLearner,
+    GameFeatures,
+    GameStage,
+    LearningExample,
+};
+
+pub use decision_trace::{
+    DecisionTrace,
+    CandidateTrace,
+    ActionSource,
+    Rejection,
+};
+
+pub use scripting::{
+    ScriptHost,
+    ScriptLimits,
 };
 
 pub use ai_controller::{
@@ -64,11 +147,14 @@ pub use replay_system::{
 };
 
 pub use input_simulator::{
-    InputSimulator,
     GameHotkey,
     MouseAction,
+    Viewport,
 };
 
+#[cfg(windows)]
+pub use input_simulator::InputSimulator;
+
 /// System version information
 pub const VERSION: &str = "1.0.0";
 pub const NAME: &str = "Headless WC2 Remastered";
@@ -78,22 +164,18 @@ pub async fn initialize_headless_system() -> anyhow::Result<HeadlessGameEngine>
     log::info!("🚀 Initializing Headless WC2 Remastered System v{}", VERSION);
     
     // Create system components
-    let memory_hooks = std::sync::Arc::new(MemoryHookManager::new().await?);
-    let function_hooks = std::sync::Arc::new(FunctionHookManager::new().await?);
+    let backend = default_backend().await?;
     let ai_controller = std::sync::Arc::new(AIController::new().await?);
     let data_exporter = std::sync::Arc::new(DataExporter::new().await?);
     let replay_system = std::sync::Arc::new(ReplaySystem::new().await?);
-    let input_simulator = std::sync::Arc::new(tokio::sync::Mutex::new(InputSimulator::new()));
     
     // Create the main game engine
     let game_engine = HeadlessGameEngine::new_with_components(
         HeadlessConfig::default(),
-        memory_hooks,
-        function_hooks,
+        backend,
         ai_controller,
         data_exporter,
         replay_system,
-        input_simulator,
     ).await?;
     
     log::info!("✅ Headless WC2 System initialized successfully");
@@ -115,6 +197,7 @@ pub fn get_system_info() -> serde_json::Value {
         },
         "architecture": {
             "game_engine": "HeadlessGameEngine",
+            "game_backend": if cfg!(windows) { "LiveGameBackend" } else { "SimulatorBackend" },
             "memory_system": "MemoryHookManager",
             "function_system": "FunctionHookManager",
             "ai_system": "AIController",
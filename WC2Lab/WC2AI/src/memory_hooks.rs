@@ -5,11 +5,13 @@ use anyhow::{Result, anyhow};
 use log::{info, warn, error, debug};
 use serde::{Serialize, Deserialize};
 use rand::Rng;
+use wc2_shared::memory_source::{self, MemorySource};
 
-// Windows API imports for real process control
-use windows::Win32::System::Threading::{OpenProcess, PROCESS_ALL_ACCESS};
-use windows::Win32::System::Diagnostics::ToolHelp::{CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS};
-use windows::Win32::Foundation::{HANDLE, CloseHandle};
+/// Name fragments identifying a Warcraft II process
+const WARCRAFT_PROCESS_NAMES: [&str; 4] = ["warcraft", "war2", "wc2", "battle.net"];
+
+/// Bytes read from a base address when parsing the game state
+pub const GAME_STATE_READ_SIZE: usize = 4096;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryHook {
@@ -90,8 +92,8 @@ pub struct MemoryHookManager {
     hooks: Arc<Mutex<HashMap<u64, MemoryHook>>>,
     memory_regions: Arc<Mutex<Vec<MemoryRegion>>>,
     current_state: Arc<Mutex<MemoryState>>,
-    process_handle: Option<HANDLE>, // Mock for now
-    process_id: Option<u32>,
+    /// Memory of the game process, or a snapshot of it
+    source: Arc<Mutex<Option<Arc<dyn MemorySource>>>>,
     base_address: Option<u64>,
 }
 
@@ -107,8 +109,7 @@ impl MemoryHookManager {
             hooks,
             memory_regions,
             current_state,
-            process_handle: None,
-            process_id: None,
+            source: Arc::new(Mutex::new(None)),
             base_address: None,
         };
         
@@ -223,10 +224,10 @@ impl MemoryHookManager {
     }
     
     /// Initialize connection to Warcraft II process
-    pub async fn initialize_process_connection(&mut self) -> Result<()> {
+    pub async fn initialize_process_connection(&self) -> Result<()> {
         info!("🔌 Initializing connection to Warcraft II process...");
         
-        if self.process_handle.is_none() {
+        if self.source.lock().await.is_none() {
             self.find_warcraft_process().await?;
         }
         
@@ -234,48 +235,25 @@ impl MemoryHookManager {
         Ok(())
     }
     
-    /// Read memory from the actual Warcraft II process using Windows API
+    /// Read memory from the connected Warcraft II process or snapshot
     pub async fn read_game_memory(&self, address: u64, size: usize) -> Result<Vec<u8>> {
         info!("🔍 Reading game memory from 0x{:x} ({} bytes)", address, size);
         
-        // Check if we have a valid process handle
-        let process_handle = match self.process_handle {
-            Some(handle) => handle,
-            None => {
-                return Err(anyhow!("No process handle available. Call initialize_process_connection() first."));
-            }
-        };
-        
-        // Use Windows API to read process memory
-        let mut buffer = vec![0u8; size];
-        let mut bytes_read: usize = 0;
-        
-        unsafe {
-            let result = windows::Win32::System::Diagnostics::Debug::ReadProcessMemory(
-                process_handle,
-                address as *const std::ffi::c_void,
-                buffer.as_mut_ptr() as *mut std::ffi::c_void,
-                size,
-                Some(&mut bytes_read as *mut usize)
-            );
-            
-            if result.is_err() {
-                let error = format!("Failed to read memory at 0x{:x}: {:?}", address, result);
-                error!("❌ {}", error);
-                return Err(anyhow!(error));
-            }
-        }
-        
-        // Resize buffer to actual bytes read
-        buffer.truncate(bytes_read);
+        let source = self.memory_source().await
+            .ok_or_else(|| anyhow!("No memory source available. Call initialize_process_connection() first."))?;
+        let buffer = source.read_bytes(address, size).map_err(|e| {
+            error!("❌ {}", e);
+            e
+        })?;
         
-        info!("✅ Game memory read completed: {} bytes from 0x{:x}", bytes_read, address);
+        info!("✅ Game memory read completed: {} bytes from 0x{:x}", buffer.len(), address);
         Ok(buffer)
     }
     
-    /// Parse game state from actual memory data
-    pub async fn parse_game_state(&self, memory_data: &[u8]) -> Result<MemoryState> {
-        info!("🔍 Parsing game state from memory data ({} bytes)", memory_data.len());
+    /// Parse game state from the memory at `address` in any memory source
+    pub async fn parse_game_state(&self, source: &dyn MemorySource, address: u64) -> Result<MemoryState> {
+        let memory_data = source.read_bytes(address, GAME_STATE_READ_SIZE)?;
+        info!("🔍 Parsing game state from {} at 0x{:x} ({} bytes)", source.describe(), address, memory_data.len());
         
         // This is a simplified parser - in a real implementation, you would have
         // detailed knowledge of Warcraft II's memory layout
@@ -299,7 +277,7 @@ impl MemoryHookManager {
         // Try to parse basic game information
         if memory_data.len() >= 1024 {
             // Look for common patterns in Warcraft II memory
-            self.parse_basic_game_info(memory_data, &mut state)?;
+            self.parse_basic_game_info(&memory_data, &mut state)?;
         }
         
         Ok(state)
@@ -350,71 +328,37 @@ impl MemoryHookManager {
         Ok(())
     }
     
-    /// Set the process handle for real memory access
-    pub fn set_process_handle(&mut self, handle: HANDLE, pid: u32) {
-        self.process_handle = Some(handle);
-        self.process_id = Some(pid);
-        info!("🔗 Memory hook manager connected to process PID: {}", pid);
+    /// Read game memory from `source`, e.g. a live process or a snapshot
+    pub async fn set_memory_source(&self, source: Arc<dyn MemorySource>) {
+        info!("🔗 Memory hook manager reading from {}", source.describe());
+        *self.source.lock().await = Some(source);
+    }
+    
+    /// The memory source in use, if connected
+    pub async fn memory_source(&self) -> Option<Arc<dyn MemorySource>> {
+        self.source.lock().await.clone()
     }
     
-    /// Get the current process information
-    pub fn get_process_info(&self) -> (Option<HANDLE>, Option<u32>) {
-        (self.process_handle, self.process_id)
+    /// ID of the connected process, if the source is a live process
+    pub async fn process_id(&self) -> Option<u32> {
+        self.source.lock().await.as_ref().and_then(|source| source.pid())
     }
 
-    /// Find and open the Warcraft II process using Windows API
-    async fn find_warcraft_process(&mut self) -> Result<()> {
+    /// Find and open the Warcraft II process
+    async fn find_warcraft_process(&self) -> Result<()> {
         info!("🔍 Searching for Warcraft II process...");
         
-        unsafe {
-            // Create a snapshot of all running processes
-            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)?;
+        for (pid, name) in memory_source::find_processes(&WARCRAFT_PROCESS_NAMES)? {
+            info!("🎮 Found Warcraft II process: {} (PID: {})", name, pid);
             
-            let mut process_entry = PROCESSENTRY32W {
-                dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
-                ..Default::default()
-            };
-            
-            // Get the first process
-            let mut result = Process32FirstW(snapshot, &mut process_entry);
-            
-            while result.is_ok() {
-                // Convert process name to string
-                let process_name = String::from_utf16_lossy(&process_entry.szExeFile)
-                    .trim_matches('\0')
-                    .to_lowercase();
-                
-                // Check if this is a Warcraft II process
-                if process_name.contains("warcraft") || 
-                   process_name.contains("war2") || 
-                   process_name.contains("wc2") ||
-                   process_name.contains("battle.net") {
-                    
-                    info!("🎮 Found Warcraft II process: {} (PID: {})", 
-                          String::from_utf16_lossy(&process_entry.szExeFile).trim_matches('\0'),
-                          process_entry.th32ProcessID);
-                    
-                    // Open the process with necessary access rights
-                    let process_handle = OpenProcess(
-                        PROCESS_ALL_ACCESS,
-                        false,
-                        process_entry.th32ProcessID
-                    )?;
-                    
-                    // Store the process handle and ID
-                    self.process_handle = Some(process_handle);
-                    self.process_id = Some(process_entry.th32ProcessID);
-                    
+            match memory_source::open_process(pid) {
+                Ok(source) => {
+                    self.set_memory_source(Arc::from(source)).await;
                     info!("✅ Successfully opened Warcraft II process");
                     return Ok(());
                 }
-                
-                // Get the next process
-                result = Process32NextW(snapshot, &mut process_entry);
+                Err(e) => warn!("⚠️ Cannot read memory of PID {}: {}", pid, e),
             }
-            
-            // Close the snapshot handle
-            CloseHandle(snapshot);
         }
         
         Err(anyhow!("Warcraft II process not found"))
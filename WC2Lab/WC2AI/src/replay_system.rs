@@ -8,6 +8,8 @@ use std::fs;
 use std::path::Path;
 
 use crate::game_engine::HeadlessGameState;
+use crate::game_backend::GameBackend;
+use crate::ai_command::AICommand;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ReplayEvent {
@@ -24,7 +26,15 @@ pub enum ReplayEvent {
     ResearchCompleted { timestamp: u64, player_id: u8, upgrade_type: String, cost: u32 },
     AIAction { timestamp: u64, action: String, reasoning: String, success: bool },
     StateChange { timestamp: u64, old_state: String, new_state: String },
-    PlayerAction { timestamp: u64, player_id: u8, action_type: String, details: String },
+    PlayerAction {
+        timestamp: u64,
+        player_id: u8,
+        action_type: String,
+        details: String,
+        /// Tile the action was aimed at, if any
+        #[serde(default)]
+        target: Option<(i32, i32)>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -318,6 +328,32 @@ impl ReplaySystem {
         Ok(())
     }
     
+    /// Record a command a player issued, with the tile it was aimed at
+    pub async fn record_player_action(&self, player_id: u8, command: &AICommand) -> Result<()> {
+        let action_type = match command {
+            AICommand::Move { .. } | AICommand::SetRally { .. } | AICommand::Formation { .. } => "Move",
+            AICommand::AttackMove { .. } | AICommand::Cast { .. } => "Attack",
+            AICommand::BuildAt { .. } => "Build",
+            AICommand::Train { .. } => "Train",
+            AICommand::Research { .. } => "Research",
+            AICommand::Select { .. } => "Select",
+            AICommand::Gather { .. } => "Gather",
+            AICommand::Click { .. } | AICommand::Hotkey { .. } | AICommand::Wait { .. } => "Other",
+        };
+        let event = ReplayEvent::PlayerAction {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_millis() as u64,
+            player_id,
+            action_type: action_type.to_string(),
+            details: command.to_string(),
+            target: command.target(),
+        };
+        
+        self.record_event(event).await?;
+        Ok(())
+    }
+    
     pub async fn record_ai_decision(&self, action: &str, reasoning: &str, success: bool) -> Result<()> {
         let event = ReplayEvent::AIAction {
             timestamp: std::time::SystemTime::now()
@@ -375,6 +411,12 @@ impl ReplaySystem {
         Ok(())
     }
     
+    /// Snapshot whatever state a game backend currently reports
+    pub async fn record_backend_snapshot(&self, backend: &dyn GameBackend) -> Result<()> {
+        let game_state = backend.observe().await?;
+        self.take_state_snapshot(&game_state).await
+    }
+    
     pub async fn take_state_snapshot(&self, game_state: &HeadlessGameState) -> Result<()> {
         let mut current = self.current_replay.lock().await;
         if let Some(replay) = &mut *current {
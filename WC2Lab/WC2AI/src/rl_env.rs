@@ -0,0 +1,684 @@
+//! Gym-style reinforcement learning environment
+//!
+//! `WC2Env` wraps a headless engine running on the simulator backend and
+//! exposes the usual `reset` / `step` loop: a fixed discrete action table
+//! with per-step action masks, fixed-shape feature planes plus scalar
+//! features as observations, and configurable reward shaping. `serve_json`
+//! speaks a line-delimited JSON protocol over any reader/writer pair so that
+//! Python trainers can drive the environment through a child process.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, bail, Result};
+use log::{info, debug};
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::ai_command::{AICommand, BuildingKind, ResourceKind, UnitKind, UpgradeKind};
+use crate::game_backend::GameBackend;
+use crate::game_engine::{GamePhase, HeadlessConfig, HeadlessGameEngine, HeadlessGameState};
+use crate::simulator::{EntityKind, SimConfig, SimMap, SimPlayerStats, Simulation, SimulatorBackend, TICKS_PER_SECOND};
+
+/// Feature planes in observation order
+pub const PLANE_FEATURES: [&str; 8] = [
+    "own_units",
+    "own_workers",
+    "own_buildings",
+    "enemy_units",
+    "enemy_buildings",
+    "trees",
+    "gold_mines",
+    "visible",
+];
+
+/// Scalar features in observation order
+pub const SCALAR_FEATURES: [&str; 10] = [
+    "gold",
+    "wood",
+    "oil",
+    "food_used",
+    "food_max",
+    "workers",
+    "military",
+    "buildings",
+    "visible_enemies",
+    "time",
+];
+
+/// Tiles around an own unit or building that are not under fog
+const SIGHT_RADIUS: i32 = 5;
+
+/// One entry of the discrete action table
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EnvAction {
+    NoOp,
+    Train(UnitKind),
+    /// Build on the closest free site around the town hall
+    Build(BuildingKind),
+    Research(UpgradeKind),
+    /// Send idle peasants to the resource closest to the town hall
+    Gather(ResourceKind),
+    /// Attack-move every military unit to the centre of a map region
+    AttackRegion(usize),
+}
+
+/// Built-in behaviour of the other player
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Opponent {
+    /// Does nothing; useful for economy-only training
+    Idle,
+    /// Harvests, builds barracks and attacks once it has a handful of footmen
+    Rush,
+}
+
+/// Weights for reward shaping; every term is added each step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardConfig {
+    pub victory: f32,
+    pub defeat: f32,
+    /// Per 100 gold or wood gathered
+    pub resources_gathered: f32,
+    /// Per point of damage dealt to enemies
+    pub damage_dealt: f32,
+    /// Per point of damage taken (normally negative)
+    pub damage_taken: f32,
+    pub kill: f32,
+    pub unit_trained: f32,
+    pub building_completed: f32,
+    /// Per action the simulator rejected (normally negative)
+    pub invalid_action: f32,
+    /// Added every step (normally zero or slightly negative)
+    pub step: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        Self {
+            victory: 1.0,
+            defeat: -1.0,
+            resources_gathered: 0.01,
+            damage_dealt: 0.001,
+            damage_taken: -0.001,
+            kill: 0.02,
+            unit_trained: 0.02,
+            building_completed: 0.05,
+            invalid_action: -0.01,
+            step: 0.0,
+        }
+    }
+}
+
+/// Environment settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvConfig {
+    pub sim: SimConfig,
+    /// Opponent player id
+    pub opponent_player: u8,
+    pub opponent: Opponent,
+    /// Edge length of every feature plane; maps are resampled to it
+    pub plane_size: usize,
+    /// Simulation ticks per environment step
+    pub ticks_per_step: u32,
+    /// Attack regions per map edge; the table has `attack_grid²` attack actions
+    pub attack_grid: usize,
+    /// Size of the generated map used when no PUD map is given
+    pub default_map_size: u32,
+    pub reward: RewardConfig,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            sim: SimConfig::default(),
+            opponent_player: 1,
+            opponent: Opponent::Rush,
+            plane_size: 32,
+            ticks_per_step: TICKS_PER_SECOND,
+            attack_grid: 3,
+            default_map_size: 64,
+            reward: RewardConfig::default(),
+        }
+    }
+}
+
+/// What the agent sees after `reset` and every `step`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Observation {
+    /// Feature planes, row-major with shape `plane_shape`
+    pub planes: Vec<f32>,
+    /// [channels, height, width]
+    pub plane_shape: [usize; 3],
+    pub scalars: Vec<f32>,
+    /// Which entries of the action table are currently valid
+    pub action_mask: Vec<bool>,
+}
+
+/// Diagnostics returned alongside each step
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepInfo {
+    pub tick: u64,
+    /// Actions that were masked out or refused, with the reason
+    pub rejected_actions: Vec<String>,
+    /// Reward per shaping term for this step
+    pub reward_terms: Vec<(String, f32)>,
+    /// "victory", "defeat" or "timeout" once the episode is done
+    pub outcome: Option<String>,
+}
+
+/// Description of the observation and action spaces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvSpec {
+    pub actions: Vec<String>,
+    pub plane_features: Vec<String>,
+    pub plane_shape: [usize; 3],
+    pub scalar_features: Vec<String>,
+}
+
+/// Reinforcement learning environment over the simulator
+pub struct WC2Env {
+    config: EnvConfig,
+    engine: HeadlessGameEngine,
+    backend: Arc<SimulatorBackend>,
+    actions: Vec<EnvAction>,
+    last_stats: SimPlayerStats,
+    done: bool,
+}
+
+impl WC2Env {
+    pub async fn new(config: EnvConfig) -> Result<Self> {
+        info!("🏋️ Initializing RL environment...");
+        if config.opponent_player == config.sim.player {
+            bail!("The opponent cannot be the agent's own player {}", config.sim.player);
+        }
+
+        let map = SimMap::open_field(config.default_map_size, config.default_map_size, 2);
+        let backend = Arc::new(SimulatorBackend::new(config.sim.clone(), map));
+        let engine_config = HeadlessConfig {
+            enable_replay_recording: false,
+            enable_performance_monitoring: false,
+            enable_decision_trace: false,
+            ..HeadlessConfig::default()
+        };
+        let engine = HeadlessGameEngine::with_backend(engine_config, backend.clone()).await?;
+
+        let actions = Self::action_table(config.attack_grid);
+        info!("✅ RL environment ready with {} actions", actions.len());
+
+        Ok(Self {
+            config,
+            engine,
+            backend,
+            actions,
+            last_stats: SimPlayerStats::default(),
+            done: true,
+        })
+    }
+
+    fn action_table(attack_grid: usize) -> Vec<EnvAction> {
+        let mut actions = vec![EnvAction::NoOp];
+        // Aliases such as the catapult train the same unit, so each unit appears once
+        actions.extend(UnitKind::ALL.iter()
+            .filter(|&&unit| UnitKind::from_rules_key(unit.rules_key()) == Some(unit))
+            .map(|&unit| EnvAction::Train(unit)));
+        // The simulator has no oil, so oil wells could never be built
+        actions.extend(BuildingKind::ALL.iter()
+            .filter(|&&building| building.is_buildable() && building != BuildingKind::OilWell)
+            .map(|&building| EnvAction::Build(building)));
+        actions.extend(UpgradeKind::ALL.iter().map(|&upgrade| EnvAction::Research(upgrade)));
+        actions.push(EnvAction::Gather(ResourceKind::Gold));
+        actions.push(EnvAction::Gather(ResourceKind::Wood));
+        actions.extend((0..attack_grid * attack_grid).map(EnvAction::AttackRegion));
+        actions
+    }
+
+    /// The discrete action table
+    pub fn actions(&self) -> &[EnvAction] {
+        &self.actions
+    }
+
+    pub fn spec(&self) -> EnvSpec {
+        let size = self.config.plane_size;
+        EnvSpec {
+            actions: self.actions.iter().map(|action| format!("{:?}", action)).collect(),
+            plane_features: PLANE_FEATURES.iter().map(|name| name.to_string()).collect(),
+            plane_shape: [PLANE_FEATURES.len(), size, size],
+            scalar_features: SCALAR_FEATURES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Start a new episode, on a PUD map or the generated open field
+    pub async fn reset(&mut self, seed: u64, map: Option<&Path>) -> Result<Observation> {
+        let map = match map {
+            Some(path) => SimMap::from_pud(path)?,
+            None => SimMap::open_field(self.config.default_map_size, self.config.default_map_size, 2),
+        };
+        let mut sim_config = self.config.sim.clone();
+        sim_config.seed = seed;
+        for player in [sim_config.player, self.config.opponent_player] {
+            if !sim_config.players.contains(&player) {
+                sim_config.players.push(player);
+            }
+        }
+
+        debug!("🏋️ Reset on {} with seed {}", map.name, seed);
+        self.backend.reset(sim_config, map).await;
+        self.engine.read_game_memory().await?;
+        self.last_stats = SimPlayerStats::default();
+        self.done = false;
+
+        let simulation = self.backend.simulation().lock().await;
+        Ok(self.observation(&simulation, &self.engine.get_game_state().await))
+    }
+
+    /// Apply action table indices, advance one step and score it
+    pub async fn step(&mut self, actions: &[usize]) -> Result<(Observation, f32, bool, StepInfo)> {
+        if self.done {
+            return Err(anyhow!("Episode is over; call reset first"));
+        }
+
+        let mut info = StepInfo::default();
+        let player = self.config.sim.player;
+
+        for &index in actions {
+            let action = *self.actions.get(index)
+                .ok_or_else(|| anyhow!("Action {} is outside the table of {}", index, self.actions.len()))?;
+            let command = {
+                let simulation = self.backend.simulation().lock().await;
+                self.command_for(&simulation, player, action)
+                    .filter(|command| simulation.would_accept(player, command))
+            };
+
+            match (action, command) {
+                (EnvAction::NoOp, _) => {}
+                (_, Some(command)) => {
+                    if let Err(e) = self.engine.issue_command(command).await {
+                        info.rejected_actions.push(format!("{:?}: {}", action, e));
+                    }
+                }
+                (_, None) => info.rejected_actions.push(format!("{:?}: not currently possible", action)),
+            }
+        }
+
+        {
+            let mut simulation = self.backend.simulation().lock().await;
+            self.play_opponent(&mut simulation);
+        }
+        let step = Duration::from_millis(self.config.ticks_per_step as u64 * 1000 / TICKS_PER_SECOND as u64);
+        self.backend.advance(step).await?;
+        self.engine.read_game_memory().await?;
+
+        let state = self.engine.get_game_state().await;
+        let simulation = self.backend.simulation().lock().await;
+        let stats = simulation.players.get(&player).map(|p| p.stats.clone()).unwrap_or_default();
+        let weights = &self.config.reward;
+
+        let mut terms = vec![
+            ("resources_gathered", weights.resources_gathered
+                * ((stats.gold_gathered + stats.wood_gathered) - (self.last_stats.gold_gathered + self.last_stats.wood_gathered)) as f32 / 100.0),
+            ("damage_dealt", weights.damage_dealt * (stats.damage_dealt - self.last_stats.damage_dealt) as f32),
+            ("damage_taken", weights.damage_taken * (stats.damage_taken - self.last_stats.damage_taken) as f32),
+            ("kill", weights.kill * (stats.kills - self.last_stats.kills) as f32),
+            ("unit_trained", weights.unit_trained * (stats.units_trained - self.last_stats.units_trained) as f32),
+            ("building_completed", weights.building_completed * (stats.buildings_completed - self.last_stats.buildings_completed) as f32),
+            ("invalid_action", weights.invalid_action * info.rejected_actions.len() as f32),
+            ("step", weights.step),
+        ];
+
+        let done = simulation.is_over();
+        if done {
+            let (outcome, reward) = match state.game_phase {
+                GamePhase::Victory => ("victory", weights.victory),
+                GamePhase::Defeat => ("defeat", weights.defeat),
+                _ => ("timeout", 0.0),
+            };
+            terms.push((outcome, reward));
+            info.outcome = Some(outcome.to_string());
+        }
+
+        let reward = terms.iter().map(|(_, value)| value).sum();
+        info.reward_terms = terms.into_iter()
+            .filter(|(_, value)| *value != 0.0)
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        info.tick = simulation.tick;
+
+        self.last_stats = stats;
+        self.done = done;
+        Ok((self.observation(&simulation, &state), reward, done, info))
+    }
+
+    /// Mask of actions the simulator would currently accept
+    pub async fn action_mask(&self) -> Vec<bool> {
+        let simulation = self.backend.simulation().lock().await;
+        self.mask(&simulation)
+    }
+
+    fn mask(&self, simulation: &Simulation) -> Vec<bool> {
+        let player = self.config.sim.player;
+        self.actions.iter()
+            .map(|&action| match action {
+                EnvAction::NoOp => true,
+                _ => self.command_for(simulation, player, action)
+                    .is_some_and(|command| simulation.would_accept(player, &command)),
+            })
+            .collect()
+    }
+
+    /// Concrete command for a table entry, if it has a target right now
+    fn command_for(&self, simulation: &Simulation, player: u8, action: EnvAction) -> Option<AICommand> {
+        match action {
+            EnvAction::NoOp => None,
+            EnvAction::Train(unit) => Some(AICommand::Train { unit }),
+            // The site search is the costly part, so skip it when nothing could be built
+            EnvAction::Build(building) => simulation.could_build(player, building)
+                .then(|| simulation.find_build_site(player, building))
+                .flatten()
+                .map(|(x, y)| AICommand::BuildAt { building, x, y }),
+            EnvAction::Research(upgrade) => Some(AICommand::Research { upgrade }),
+            EnvAction::Gather(resource) => {
+                let (x, y) = simulation.home(player)?;
+                Some(AICommand::Gather { resource, x, y })
+            }
+            EnvAction::AttackRegion(region) => {
+                let grid = self.config.attack_grid.max(1) as i32;
+                let (width, height) = (simulation.map.width as i32, simulation.map.height as i32);
+                let (column, row) = (region as i32 % grid, region as i32 / grid);
+                Some(AICommand::AttackMove {
+                    x: (column * 2 + 1) * width / (grid * 2),
+                    y: (row * 2 + 1) * height / (grid * 2),
+                })
+            }
+        }
+    }
+
+    fn play_opponent(&self, simulation: &mut Simulation) {
+        let player = self.config.opponent_player;
+        if self.config.opponent == Opponent::Idle {
+            return;
+        }
+
+        let count = |unit: UnitKind| {
+            simulation.entities.values().filter(|e| e.owner == player && e.kind == EntityKind::Unit(unit)).count()
+        };
+        let peasants = count(UnitKind::Peasant);
+        let footmen = count(UnitKind::Footman);
+        let (food_used, food_max) = simulation.food(player);
+        let constructing = simulation.entities.values().any(|e| e.owner == player && !e.completed);
+        let (gold, wood) = simulation.players.get(&player).map(|p| (p.gold, p.wood)).unwrap_or_default();
+
+        // Idle peasants go wherever the stockpile is short
+        let mut commands = Vec::new();
+        if let Some((x, y)) = simulation.home(player) {
+            let resource = if wood * 2 < gold { ResourceKind::Wood } else { ResourceKind::Gold };
+            commands.push(AICommand::Gather { resource, x, y });
+        }
+        let has_barracks = simulation.entities.values()
+            .any(|e| e.owner == player && e.kind == EntityKind::Building(BuildingKind::Barracks));
+
+        // One building at a time: farms when food runs out, then a barracks
+        let next_building = if constructing {
+            None
+        } else if food_used + 1 >= food_max {
+            Some(BuildingKind::Farm)
+        } else if peasants >= 5 && !has_barracks {
+            Some(BuildingKind::Barracks)
+        } else {
+            None
+        };
+        if let Some(building) = next_building {
+            if let Some((x, y)) = simulation.find_build_site(player, building) {
+                commands.push(AICommand::BuildAt { building, x, y });
+            }
+        }
+        if peasants < 5 {
+            commands.push(AICommand::Train { unit: UnitKind::Peasant });
+        }
+        commands.push(AICommand::Train { unit: UnitKind::Footman });
+        if footmen >= 6 {
+            if let Some(&(x, y)) = simulation.map.start_positions.get(&self.config.sim.player) {
+                commands.push(AICommand::AttackMove { x, y });
+            }
+        }
+
+        for command in &commands {
+            if simulation.would_accept(player, command) {
+                simulation.issue(player, command);
+            }
+        }
+    }
+
+    fn observation(&self, simulation: &Simulation, state: &HeadlessGameState) -> Observation {
+        let player = self.config.sim.player;
+        let size = self.config.plane_size;
+        let (width, height) = (simulation.map.width.max(1) as i32, simulation.map.height.max(1) as i32);
+        let cell = |x: i32, y: i32| {
+            let column = (x.clamp(0, width - 1) as usize * size) / width as usize;
+            let row = (y.clamp(0, height - 1) as usize * size) / height as usize;
+            row * size + column
+        };
+
+        // Fog: tiles near anything the player owns
+        let mut visible = vec![false; (width * height) as usize];
+        let own_positions = state.units.iter().filter(|u| u.owner == player).map(|u| u.position)
+            .chain(state.buildings.iter().filter(|b| b.owner == player).map(|b| b.position));
+        for (x, y) in own_positions {
+            for ty in (y - SIGHT_RADIUS).max(0)..=(y + SIGHT_RADIUS).min(height - 1) {
+                for tx in (x - SIGHT_RADIUS).max(0)..=(x + SIGHT_RADIUS).min(width - 1) {
+                    visible[(ty * width + tx) as usize] = true;
+                }
+            }
+        }
+        let is_visible = |(x, y): (i32, i32)| {
+            x >= 0 && y >= 0 && x < width && y < height && visible[(y * width + x) as usize]
+        };
+
+        let plane_len = size * size;
+        let mut planes = vec![0.0f32; PLANE_FEATURES.len() * plane_len];
+        let mut add = |channel: usize, (x, y): (i32, i32), value: f32| {
+            planes[channel * plane_len + cell(x, y)] += value;
+        };
+
+        let mut visible_enemies = 0;
+        for unit in &state.units {
+            if unit.owner == player {
+                add(0, unit.position, 1.0);
+                if unit.unit_type == UnitKind::Peasant.to_string() {
+                    add(1, unit.position, 1.0);
+                }
+            } else if is_visible(unit.position) {
+                add(3, unit.position, 1.0);
+                visible_enemies += 1;
+            }
+        }
+        for building in &state.buildings {
+            if building.owner == player {
+                add(2, building.position, 1.0);
+            } else if building.building_type != BuildingKind::Mine.to_string() && is_visible(building.position) {
+                add(4, building.position, 1.0);
+            }
+        }
+        for &position in &state.map_info.resource_locations {
+            add(6, position, 1.0);
+        }
+
+        // Terrain and fog as the share of each cell's tiles
+        let tiles_per_cell = (width as f32 / size as f32).max(1.0) * (height as f32 / size as f32).max(1.0);
+        for y in 0..height {
+            for x in 0..width {
+                if simulation.map.is_tree(x, y) {
+                    add(5, (x, y), 1.0 / tiles_per_cell);
+                }
+                if visible[(y * width + x) as usize] {
+                    add(7, (x, y), 1.0 / tiles_per_cell);
+                }
+            }
+        }
+        for value in planes[5 * plane_len..].iter_mut() {
+            *value = value.min(1.0);
+        }
+
+        let resources = &state.player_resources;
+        let own_units: Vec<_> = state.units.iter().filter(|u| u.owner == player).collect();
+        let workers = own_units.iter().filter(|u| u.unit_type == UnitKind::Peasant.to_string()).count();
+        let scalars = vec![
+            resources.gold as f32 / 1000.0,
+            resources.wood as f32 / 1000.0,
+            resources.oil as f32 / 1000.0,
+            resources.food_current as f32 / 10.0,
+            resources.food_max as f32 / 10.0,
+            workers as f32 / 10.0,
+            (own_units.len() - workers) as f32 / 10.0,
+            state.buildings.iter().filter(|b| b.owner == player).count() as f32 / 10.0,
+            visible_enemies as f32 / 10.0,
+            simulation.tick as f32 / simulation.config.max_ticks.max(1) as f32,
+        ];
+
+        Observation {
+            planes,
+            plane_shape: [PLANE_FEATURES.len(), size, size],
+            scalars,
+            action_mask: self.mask(simulation),
+        }
+    }
+}
+
+/// Request of the JSON protocol, one per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum EnvRequest {
+    Spec,
+    Reset { seed: u64, map: Option<String> },
+    Step { actions: Vec<usize> },
+    Mask,
+    Close,
+}
+
+/// Serve the environment over line-delimited JSON until `close` or end of input
+///
+/// Every request gets exactly one response line, `{"ok": true, ...}` with the
+/// result fields or `{"ok": false, "error": "..."}`.
+pub async fn serve_json<R, W>(env: &mut WC2Env, reader: R, mut writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = serde_json::from_str::<EnvRequest>(&line);
+        let closing = matches!(request, Ok(EnvRequest::Close));
+        let response = match request {
+            Ok(request) => handle_request(env, request).await,
+            Err(e) => Err(anyhow!("Invalid request: {}", e)),
+        };
+
+        let response = match response {
+            Ok(mut value) => {
+                value["ok"] = serde_json::Value::Bool(true);
+                value
+            }
+            Err(e) => serde_json::json!({ "ok": false, "error": e.to_string() }),
+        };
+        writer.write_all(serde_json::to_string(&response)?.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await?;
+
+        if closing {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_request(env: &mut WC2Env, request: EnvRequest) -> Result<serde_json::Value> {
+    let value = match request {
+        EnvRequest::Spec => serde_json::to_value(env.spec())?,
+        EnvRequest::Reset { seed, map } => {
+            let observation = env.reset(seed, map.as_deref().map(Path::new)).await?;
+            serde_json::json!({ "observation": observation })
+        }
+        EnvRequest::Step { actions } => {
+            let (observation, reward, done, info) = env.step(&actions).await?;
+            serde_json::json!({ "observation": observation, "reward": reward, "done": done, "info": info })
+        }
+        EnvRequest::Mask => serde_json::json!({ "action_mask": env.action_mask().await }),
+        EnvRequest::Close => serde_json::json!({}),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn small_env() -> WC2Env {
+        WC2Env::new(EnvConfig { default_map_size: 32, ..EnvConfig::default() }).await.unwrap()
+    }
+
+    fn json<T: Serialize>(value: &T) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    fn index_of(env: &WC2Env, action: EnvAction) -> usize {
+        env.actions().iter().position(|&entry| entry == action).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reset_and_step_are_deterministic() {
+        let (mut first, mut second) = (small_env().await, small_env().await);
+        let gather = index_of(&first, EnvAction::Gather(ResourceKind::Gold));
+        let farm = index_of(&first, EnvAction::Build(BuildingKind::Farm));
+
+        assert_eq!(json(&first.reset(3, None).await.unwrap()), json(&second.reset(3, None).await.unwrap()));
+        for step in 0..60 {
+            let actions = if step % 20 == 0 { vec![gather, farm] } else { vec![0] };
+            let (a, b) = (first.step(&actions).await.unwrap(), second.step(&actions).await.unwrap());
+            assert_eq!(json(&a), json(&b), "step {}", step);
+        }
+
+        // Resetting with the same seed starts the same episode again
+        let replayed = first.reset(3, None).await.unwrap();
+        assert_eq!(json(&replayed), json(&second.reset(3, None).await.unwrap()));
+        assert_eq!(replayed.action_mask, first.action_mask().await);
+    }
+
+    #[tokio::test]
+    async fn test_action_table_has_only_distinct_buildable_actions() {
+        let env = small_env().await;
+        for action in [
+            EnvAction::Train(UnitKind::Catapult),
+            EnvAction::Build(BuildingKind::Mine),
+            EnvAction::Build(BuildingKind::Wall),
+            EnvAction::Build(BuildingKind::OilWell),
+        ] {
+            assert!(!env.actions().contains(&action), "{:?}", action);
+        }
+        assert!(env.actions().contains(&EnvAction::Train(UnitKind::Ballista)));
+    }
+
+    #[tokio::test]
+    async fn test_opponent_must_be_another_player() {
+        let config = EnvConfig { opponent_player: SimConfig::default().player, ..EnvConfig::default() };
+        assert!(WC2Env::new(config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mask_agrees_with_step() {
+        let mut env = small_env().await;
+        let mask = env.reset(1, None).await.unwrap().action_mask;
+        assert!(mask[index_of(&env, EnvAction::Gather(ResourceKind::Gold))]);
+        assert!(mask[index_of(&env, EnvAction::Build(BuildingKind::Farm))]);
+        assert!(!mask[index_of(&env, EnvAction::Train(UnitKind::Footman))]);
+        assert!(!mask[index_of(&env, EnvAction::AttackRegion(0))]);
+
+        for (index, &allowed) in mask.iter().enumerate().skip(1) {
+            env.reset(1, None).await.unwrap();
+            let (_, _, _, info) = env.step(&[index]).await.unwrap();
+            assert_eq!(info.rejected_actions.is_empty(), allowed, "{:?}: {:?}", env.actions()[index], info.rejected_actions);
+        }
+    }
+}
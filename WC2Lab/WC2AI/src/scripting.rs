@@ -0,0 +1,444 @@
+//! Rhai strategy scripts
+//!
+//! Every `*.rhai` file in the script directory is a strategy named after the
+//! file. Each decision calls the script's `decide(state, context)` function with
+//! read-only copies of the `HeadlessGameState` and `AIDecisionContext`; the
+//! script answers by calling command functions such as `train("footman", 0.6)`
+//! or `build("farm", 20, 24, 0.8)`, which queue typed commands with a priority
+//! between 0 and 1. `unit_rules(key)` and `upgrade_rules(key)` look up the
+//! shared game rules.
+//!
+//! A script is recompiled when its file's modification time changes; each
+//! decision only checks the file of the script it runs, and `reload` picks up
+//! added and removed scripts. Scripts run sandboxed: no module imports or
+//! `eval`, and bounded operations, call depth, data sizes and commands per
+//! decision.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info, warn};
+use rhai::{Dynamic, Engine, EvalAltResult, Position, Scope, AST};
+
+use crate::ai_command::{AICommand, BuildingKind, ResourceKind, SpellKind, UnitKind, UpgradeKind};
+use crate::ai_controller::{AIDecisionContext, PrioritizedAction};
+use crate::game_engine::HeadlessGameState;
+use wc2_shared::game_rules::GameRules;
+
+/// Name of the function every strategy script defines
+pub const ENTRY_POINT: &str = "decide";
+
+/// Sandbox limits for a single decision
+#[derive(Debug, Clone)]
+pub struct ScriptLimits {
+    pub max_operations: u64,
+    pub max_call_levels: usize,
+    pub max_string_size: usize,
+    pub max_array_size: usize,
+    pub max_map_size: usize,
+    pub max_commands: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations: 100_000,
+            max_call_levels: 32,
+            max_string_size: 4096,
+            max_array_size: 1024,
+            max_map_size: 1024,
+            max_commands: 64,
+        }
+    }
+}
+
+struct LoadedScript {
+    path: PathBuf,
+    modified: SystemTime,
+    ast: AST,
+}
+
+/// Commands queued by the script currently running
+type CommandBuffer = Arc<Mutex<Vec<(AICommand, f64)>>>;
+
+/// Compiled strategy scripts from one directory
+pub struct ScriptHost {
+    dir: PathBuf,
+    engine: Engine,
+    scripts: BTreeMap<String, LoadedScript>,
+    /// Modification time of scripts whose latest version does not compile
+    broken: BTreeMap<String, SystemTime>,
+    queued: CommandBuffer,
+}
+
+impl ScriptHost {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        Self::with_limits(dir, ScriptLimits::default())
+    }
+
+    pub fn with_limits(dir: impl Into<PathBuf>, limits: ScriptLimits) -> Result<Self> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            bail!("Strategy script directory {} does not exist", dir.display());
+        }
+
+        let queued = CommandBuffer::default();
+        let mut host = Self {
+            dir,
+            engine: sandboxed_engine(&limits, queued.clone()),
+            scripts: BTreeMap::new(),
+            broken: BTreeMap::new(),
+            queued,
+        };
+        host.reload()?;
+        Ok(host)
+    }
+
+    /// Names of the loaded strategies
+    pub fn names(&self) -> Vec<String> {
+        self.scripts.keys().cloned().collect()
+    }
+
+    /// Pick up added, changed and removed scripts; returns the names (re)compiled
+    ///
+    /// A script that fails to compile keeps running its last good version.
+    pub fn reload(&mut self) -> Result<Vec<String>> {
+        let mut found = BTreeMap::new();
+        for entry in std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to list strategy scripts in {}", self.dir.display()))?
+        {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "rhai") {
+                if let Some(name) = path.file_stem().map(|stem| stem.to_string_lossy().to_string()) {
+                    found.insert(name, path);
+                }
+            }
+        }
+
+        self.broken.retain(|name, _| found.contains_key(name));
+        self.scripts.retain(|name, _| {
+            let keep = found.contains_key(name);
+            if !keep {
+                info!("📜 Strategy script {} removed", name);
+            }
+            keep
+        });
+
+        let mut compiled = Vec::new();
+        for (name, path) in found {
+            if self.refresh(&name, path)? {
+                compiled.push(name);
+            }
+        }
+
+        Ok(compiled)
+    }
+
+    /// Recompile `name` from `path` if the file changed since it was last seen; returns whether it was
+    fn refresh(&mut self, name: &str, path: PathBuf) -> Result<bool> {
+        let modified = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to read strategy script {}", path.display()))?;
+        let seen = self.scripts.get(name).map(|script| script.modified).or(self.broken.get(name).copied());
+        if seen == Some(modified) {
+            return Ok(false);
+        }
+
+        match self.compile(&path) {
+            Ok(ast) => {
+                info!("📜 Loaded strategy script {} from {}", name, path.display());
+                self.broken.remove(name);
+                self.scripts.insert(name.to_string(), LoadedScript { path, modified, ast });
+                Ok(true)
+            }
+            Err(e) => {
+                warn!("⚠️ {}", e);
+                // Remember the broken version so it is not recompiled every turn
+                self.broken.insert(name.to_string(), modified);
+                Ok(false)
+            }
+        }
+    }
+
+    fn compile(&self, path: &Path) -> Result<AST> {
+        let ast = self.engine.compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("Strategy script {} does not compile: {}", path.display(), e))?;
+        if !ast.iter_functions().any(|f| f.name == ENTRY_POINT && f.params.len() == 2) {
+            bail!("Strategy script {} has no {}(state, context) function", path.display(), ENTRY_POINT);
+        }
+        Ok(ast)
+    }
+
+    /// Run a strategy's `decide`, recompiled first if its file changed, and collect the commands it queued
+    pub fn run(&mut self, name: &str, state: &HeadlessGameState, context: &AIDecisionContext) -> Result<Vec<PrioritizedAction>> {
+        let path = self.scripts.get(name)
+            .map(|script| script.path.clone())
+            .unwrap_or_else(|| self.dir.join(format!("{}.rhai", name)));
+        // A deleted script keeps running until the next reload
+        if path.is_file() {
+            self.refresh(name, path)?;
+        }
+        let script = self.scripts.get(name)
+            .ok_or_else(|| anyhow!("No strategy script named {} in {}", name, self.dir.display()))?;
+
+        let state = rhai::serde::to_dynamic(state).map_err(|e| anyhow!("{}", e))?;
+        let context = rhai::serde::to_dynamic(context).map_err(|e| anyhow!("{}", e))?;
+
+        self.queued.lock().unwrap().clear();
+        // Whatever decide() returns is ignored; only the queued commands count
+        let result = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &script.ast, ENTRY_POINT, (state, context))
+            .map(|_| ());
+        let queued = std::mem::take(&mut *self.queued.lock().unwrap());
+        result.map_err(|e| anyhow!("Strategy script {} failed: {}", script.path.display(), e))?;
+
+        debug!("📜 Strategy script {} queued {} commands", name, queued.len());
+        queued.into_iter()
+            .map(|(command, priority)| {
+                let (estimated_cost, time_to_complete) = estimate(&command)?;
+                Ok(PrioritizedAction {
+                    command,
+                    priority,
+                    reasoning: format!("Strategy script {}", name),
+                    estimated_cost,
+                    time_to_complete,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Resource cost and time in ms of a command, from the game rules where known
+fn estimate(command: &AICommand) -> Result<(u32, u64)> {
+    Ok(match command {
+        AICommand::Train { unit } => {
+            let rules = unit.rules()?;
+            (rules.total_cost(), rules.build_time as u64 * 1000)
+        }
+        AICommand::BuildAt { building, .. } => {
+            let rules = building.rules()?;
+            (rules.total_cost(), rules.build_time as u64 * 1000)
+        }
+        AICommand::Research { upgrade } => {
+            let rules = upgrade.rules()?;
+            (rules.total_cost(), rules.research_time as u64 * 1000)
+        }
+        _ => (0, 1000),
+    })
+}
+
+type ScriptResult = std::result::Result<(), Box<EvalAltResult>>;
+
+fn script_error(message: impl std::fmt::Display) -> Box<EvalAltResult> {
+    Box::new(EvalAltResult::ErrorRuntime(message.to_string().into(), Position::NONE))
+}
+
+fn parse<T: std::str::FromStr<Err = anyhow::Error>>(name: &str) -> std::result::Result<T, Box<EvalAltResult>> {
+    name.parse().map_err(script_error)
+}
+
+fn coordinate(value: i64) -> std::result::Result<i32, Box<EvalAltResult>> {
+    i32::try_from(value).map_err(|_| script_error(format!("Coordinate {} out of range", value)))
+}
+
+fn sandboxed_engine(limits: &ScriptLimits, queued: CommandBuffer) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(limits.max_operations);
+    engine.set_max_call_levels(limits.max_call_levels);
+    engine.set_max_string_size(limits.max_string_size);
+    engine.set_max_array_size(limits.max_array_size);
+    engine.set_max_map_size(limits.max_map_size);
+    engine.set_max_modules(0);
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|text| info!("📜 {}", text));
+    engine.on_debug(|text, source, position| debug!("📜 {} {:?} @ {}", text, source, position));
+
+    // Read-only rules lookups; unknown keys give ()
+    engine.register_fn("unit_rules", |key: &str| {
+        GameRules::embedded().unit(key)
+            .and_then(|rules| rhai::serde::to_dynamic(rules).ok())
+            .unwrap_or(Dynamic::UNIT)
+    });
+    engine.register_fn("upgrade_rules", |key: &str| {
+        GameRules::embedded().upgrade(key)
+            .and_then(|rules| rhai::serde::to_dynamic(rules).ok())
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let max_commands = limits.max_commands;
+    let enqueue = move |command: AICommand, priority: f64| -> ScriptResult {
+        if !(0.0..=1.0).contains(&priority) {
+            return Err(script_error(format!("Priority {} of {} is not between 0 and 1", priority, command)));
+        }
+        let mut queued = queued.lock().unwrap();
+        if queued.len() >= max_commands {
+            return Err(script_error(format!("More than {} commands in one decision", max_commands)));
+        }
+        queued.push((command, priority));
+        Ok(())
+    };
+
+    let queue = enqueue.clone();
+    engine.register_fn("train", move |unit: &str, priority: f64| {
+        queue(AICommand::Train { unit: parse::<UnitKind>(unit)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("build", move |building: &str, x: i64, y: i64, priority: f64| {
+        queue(AICommand::BuildAt { building: parse::<BuildingKind>(building)?, x: coordinate(x)?, y: coordinate(y)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("attack", move |x: i64, y: i64, priority: f64| {
+        queue(AICommand::AttackMove { x: coordinate(x)?, y: coordinate(y)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("move_to", move |x: i64, y: i64, priority: f64| {
+        queue(AICommand::Move { x: coordinate(x)?, y: coordinate(y)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("gather", move |resource: &str, x: i64, y: i64, priority: f64| {
+        queue(AICommand::Gather { resource: parse::<ResourceKind>(resource)?, x: coordinate(x)?, y: coordinate(y)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("research", move |upgrade: &str, priority: f64| {
+        queue(AICommand::Research { upgrade: parse::<UpgradeKind>(upgrade)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("cast", move |spell: &str, x: i64, y: i64, priority: f64| {
+        queue(AICommand::Cast { spell: parse::<SpellKind>(spell)?, x: coordinate(x)?, y: coordinate(y)? }, priority)
+    });
+    let queue = enqueue.clone();
+    engine.register_fn("rally", move |x: i64, y: i64, priority: f64| {
+        queue(AICommand::SetRally { x: coordinate(x)?, y: coordinate(y)? }, priority)
+    });
+    // Anything else in the text command syntax, e.g. "select workers"
+    let queue = enqueue;
+    engine.register_fn("command", move |text: &str, priority: f64| {
+        queue(parse::<AICommand>(text)?, priority)
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_controller::{ResourceScarcity, ThreatLevel};
+    use crate::game_engine::{GamePhase, PlayerResources};
+    use crate::test_fixtures::{building, unit};
+
+    fn context(state: &HeadlessGameState) -> AIDecisionContext {
+        AIDecisionContext {
+            current_phase: GamePhase::InGame,
+            resources: state.player_resources.clone(),
+            unit_count: 0,
+            building_count: 0,
+            threat_level: ThreatLevel::None,
+            resource_scarcity: ResourceScarcity::Normal,
+            game_time: 0,
+            map_size: (64, 64),
+            workers: 0,
+            idle_workers: 0,
+            home: Some((10, 50)),
+            enemy_positions: vec![(52, 12)],
+            occupied: Vec::new(),
+        }
+    }
+
+    fn commands(actions: &[PrioritizedAction]) -> Vec<AICommand> {
+        actions.iter().map(|action| action.command.clone()).collect()
+    }
+
+    #[test]
+    fn test_host_functions_queue_typed_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::write(dir.join("calls.rhai"), r#"
+            fn decide(state, context) {
+                train("footman", 0.5);
+                build("farm", 3, 4, 0.25);
+                command("select workers", 1.0);
+                if unit_rules("no_such_unit") == () { attack(7, 8, 0.75); }
+            }
+        "#).unwrap();
+        std::fs::write(dir.join("bad_priority.rhai"), r#"fn decide(state, context) { train("footman", 1.5); }"#).unwrap();
+        std::fs::write(dir.join("flood.rhai"), r#"fn decide(state, context) { for i in 0..100 { train("footman", 0.5); } }"#).unwrap();
+        std::fs::write(dir.join("uses_eval.rhai"), r#"fn decide(state, context) { eval("1"); }"#).unwrap();
+
+        let mut host = ScriptHost::new(dir).unwrap();
+        assert_eq!(host.names(), vec!["bad_priority", "calls", "flood"]);
+
+        let state = HeadlessGameState::default();
+        let actions = host.run("calls", &state, &context(&state)).unwrap();
+        assert_eq!(commands(&actions), vec![
+            AICommand::Train { unit: UnitKind::Footman },
+            AICommand::BuildAt { building: BuildingKind::Farm, x: 3, y: 4 },
+            "select workers".parse().unwrap(),
+            AICommand::AttackMove { x: 7, y: 8 },
+        ]);
+        assert_eq!(actions.iter().map(|action| action.priority).collect::<Vec<_>>(), vec![0.5, 0.25, 1.0, 0.75]);
+
+        assert!(host.run("bad_priority", &state, &context(&state)).is_err());
+        assert!(host.run("flood", &state, &context(&state)).is_err());
+        assert!(host.run("uses_eval", &state, &context(&state)).is_err());
+    }
+
+    #[test]
+    fn test_scripts_recompile_only_when_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let path = dir.join("strategy.rhai");
+        let write = |unit: &str, modified: SystemTime| {
+            std::fs::write(&path, format!("fn decide(state, context) {{ train(\"{}\", 0.5); }}", unit)).unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        };
+        let then = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        write("footman", then);
+
+        let mut host = ScriptHost::new(dir).unwrap();
+        let state = HeadlessGameState::default();
+        let trained = |host: &mut ScriptHost, name: &str| commands(&host.run(name, &state, &context(&state)).unwrap());
+        assert_eq!(trained(&mut host, "strategy"), vec![AICommand::Train { unit: UnitKind::Footman }]);
+
+        // Same modification time: the compiled version keeps running
+        write("archer", then);
+        assert_eq!(trained(&mut host, "strategy"), vec![AICommand::Train { unit: UnitKind::Footman }]);
+        write("archer", then + std::time::Duration::from_secs(1));
+        assert_eq!(trained(&mut host, "strategy"), vec![AICommand::Train { unit: UnitKind::Archer }]);
+
+        // A script added after loading runs without a full reload
+        std::fs::write(dir.join("added.rhai"), "fn decide(state, context) { train(\"knight\", 0.5); }").unwrap();
+        assert_eq!(trained(&mut host, "added"), vec![AICommand::Train { unit: UnitKind::Knight }]);
+    }
+
+    #[test]
+    fn test_footman_push_counts_own_units_and_builds_near_home() {
+        let mut host = ScriptHost::new(concat!(env!("CARGO_MANIFEST_DIR"), "/scripts/strategies")).unwrap();
+        let mut state = HeadlessGameState {
+            player: 1,
+            player_resources: PlayerResources { gold: 2000, wood: 1000, oil: 0, food_current: 1, food_max: 5, population: 1 },
+            units: vec![unit("Peasant", 1)],
+            buildings: vec![building("TownHall", 1), building("Barracks", 0), building("Mine", 15)],
+            ..HeadlessGameState::default()
+        };
+        state.units.extend((0..8).map(|_| unit("Footman", 0)));
+
+        // The enemy's barracks and footmen are not ours
+        let actions = commands(&host.run("footman_push", &state, &context(&state)).unwrap());
+        assert_eq!(actions, vec![
+            AICommand::BuildAt { building: BuildingKind::Barracks, x: 14, y: 49 },
+            AICommand::Train { unit: UnitKind::Peasant },
+        ]);
+
+        state.buildings.push(building("Barracks", 1));
+        state.units.extend((0..8).map(|_| unit("Footman", 1)));
+        state.player_resources.food_current = 5;
+        let actions = commands(&host.run("footman_push", &state, &context(&state)).unwrap());
+        assert_eq!(actions, vec![
+            AICommand::BuildAt { building: BuildingKind::Farm, x: 4, y: 46 },
+            AICommand::Train { unit: UnitKind::Footman },
+            AICommand::AttackMove { x: 52, y: 12 },
+        ]);
+    }
+}
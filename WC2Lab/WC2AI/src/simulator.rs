@@ -0,0 +1,1985 @@
+//! Deterministic Warcraft II simulator backend
+//!
+//! A simplified but rules-faithful model of the WC2 economy and combat:
+//! peasants walk between mines, trees and depots, buildings and units cost
+//! resources and take time, farms cap food and combat is resolved per tick
+//! with WC2's armor and piercing damage formula. Maps load from PUD files
+//! through the PUD parser in wc2-shared.
+//! The simulation is seeded, uses integer positions and iterates entities in
+//! id order, so the same seed and commands always give the same game.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{info, debug};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Serialize, Deserialize};
+use tokio::sync::Mutex;
+use wc2_shared::game_rules::GameRules;
+use wc2_shared::pud_parser::PudParser;
+
+use crate::ai_command::{AICommand, BuildingKind, ResourceKind, Selection, UnitKind, UpgradeKind};
+use crate::game_backend::GameBackend;
+use crate::game_engine::{
+    BuildingInfo, GamePhase, GameStatus, HeadlessGameState, MapInfo, PlayerResources, UnitInfo,
+};
+
+/// Simulation ticks per game second
+pub const TICKS_PER_SECOND: u32 = 10;
+
+/// Position units per map tile; positions are stored in thousandths of a tile
+pub const TILE: i32 = 1000;
+
+/// Owner id of neutral map objects such as gold mines
+pub const NEUTRAL_PLAYER: u8 = 15;
+
+/// Resources a peasant carries back per trip
+pub(crate) const CARRY_AMOUNT: u32 = 100;
+
+/// Ticks a peasant spends inside a gold mine
+pub(crate) const GOLD_MINING_TICKS: u32 = 3 * TICKS_PER_SECOND;
+
+/// Ticks a peasant spends chopping a tree
+pub(crate) const WOOD_CHOPPING_TICKS: u32 = 45;
+
+/// Ticks between two attacks of the same unit
+const ATTACK_COOLDOWN_TICKS: u32 = TICKS_PER_SECOND;
+
+/// Gold in a mine when the map does not say
+const DEFAULT_MINE_GOLD: u32 = 10_000;
+
+/// Population cap regardless of farms
+pub(crate) const MAX_FOOD: u32 = 200;
+
+/// Combat and cost figures for a unit type
+#[derive(Debug, Clone, Copy)]
+pub struct UnitStats {
+    pub hp: i32,
+    pub armor: i32,
+    pub basic_damage: i32,
+    pub piercing_damage: i32,
+    /// Attack range in tiles (1 = melee)
+    pub range: i32,
+    /// Movement speed in thousandths of a tile per tick
+    pub speed: i32,
+    pub gold: u32,
+    pub wood: u32,
+    pub oil: u32,
+    pub build_seconds: u32,
+    pub trained_at: BuildingKind,
+}
+
+/// Cost and size figures for a building type
+#[derive(Debug, Clone, Copy)]
+pub struct BuildingStats {
+    pub hp: i32,
+    pub armor: i32,
+    /// Footprint edge length in tiles
+    pub size: i32,
+    pub gold: u32,
+    pub wood: u32,
+    pub oil: u32,
+    pub build_seconds: u32,
+    /// Food this building provides once complete
+    pub food: u32,
+    /// Towers shoot at enemies: (basic, piercing, range)
+    pub attack: Option<(i32, i32, i32)>,
+}
+
+/// Cost figures for one level of an upgrade
+#[derive(Debug, Clone, Copy)]
+pub struct UpgradeStats {
+    pub gold: u32,
+    pub wood: u32,
+    pub research_seconds: u32,
+    pub researched_at: BuildingKind,
+}
+
+/// Buildings the command model lacks, and the type standing in for each
+///
+/// There are no hall upgrades, so the Town Hall counts as a Keep and a Castle;
+/// casters come from the Church.
+const STAND_INS: &[(&str, BuildingKind)] = &[
+    ("keep", BuildingKind::TownHall),
+    ("castle", BuildingKind::TownHall),
+    ("mage_tower", BuildingKind::Church),
+];
+
+/// Simulated building for a rules key, via its Human counterpart or stand-in
+fn building_for_key(rules: &GameRules, key: &str) -> Option<BuildingKind> {
+    BuildingKind::from_rules_key(key)
+        .or_else(|| rules.counterpart(key).and_then(BuildingKind::from_rules_key))
+        .or_else(|| STAND_INS.iter().find(|(lacking, _)| *lacking == key).map(|&(_, stand_in)| stand_in))
+}
+
+/// Unit figures from the game rules
+///
+/// Units train at the first building the rules list for them; requirements
+/// are checked against the rules by `Simulation::missing_requirements`.
+pub fn unit_stats(rules: &GameRules, unit: UnitKind) -> UnitStats {
+    let entry = rules.unit(unit.rules_key()).expect("simulator units are in the game rules");
+    let trained_at = entry.produced_at.first()
+        .and_then(|key| building_for_key(rules, key))
+        .expect("simulator units train in a simulated building");
+
+    UnitStats {
+        hp: entry.hp as i32,
+        armor: entry.armor as i32,
+        basic_damage: entry.basic_damage as i32,
+        piercing_damage: entry.piercing_damage as i32,
+        range: entry.range as i32,
+        speed: entry.speed as i32 * TILE / 100,
+        gold: entry.gold,
+        wood: entry.wood,
+        oil: entry.oil,
+        build_seconds: entry.build_time,
+        trained_at,
+    }
+}
+
+/// Building figures from the game rules
+pub fn building_stats(rules: &GameRules, building: BuildingKind) -> BuildingStats {
+    let entry = rules.unit(building.rules_key()).expect("simulator buildings are in the game rules");
+
+    // Towers go up in one step here, so they also cost the Scout Tower they upgrade from
+    let base = entry.produced_at.first()
+        .and_then(|key| rules.unit(key))
+        .filter(|base| base.is_building());
+    let (base_gold, base_wood, base_oil, base_time) = base
+        .map(|base| (base.gold, base.wood, base.oil, base.build_time))
+        .unwrap_or_default();
+
+    let damage = entry.basic_damage + entry.piercing_damage;
+    BuildingStats {
+        hp: entry.hp as i32,
+        armor: entry.armor as i32,
+        size: entry.size as i32,
+        gold: entry.gold + base_gold,
+        wood: entry.wood + base_wood,
+        oil: entry.oil + base_oil,
+        build_seconds: entry.build_time + base_time,
+        food: entry.food_supply,
+        attack: (damage > 0).then_some((entry.basic_damage as i32, entry.piercing_damage as i32, entry.range as i32)),
+    }
+}
+
+/// Figures for researching `level` (1-based) of an upgrade; None past the last level
+pub fn upgrade_stats(rules: &GameRules, upgrade: UpgradeKind, level: u8) -> Option<UpgradeStats> {
+    let key = upgrade.rules_keys().get(level.checked_sub(1)? as usize)?;
+    let entry = rules.upgrade(key)?;
+    Some(UpgradeStats {
+        gold: entry.gold,
+        wood: entry.wood,
+        research_seconds: entry.research_time,
+        researched_at: BuildingKind::from_rules_key(&entry.researched_at)?,
+    })
+}
+
+/// Whether a WC2 terrain tile is part of a forest (the range TMapX treats as trees)
+pub fn is_tree_tile(tile: u16) -> bool {
+    (0x70..=0x7F).contains(&tile)
+}
+
+/// Simulator entity type for a PUD unit id; Orc types map to their Human counterpart
+pub fn pud_entity_kind(unit_id: u16) -> Option<EntityKind> {
+    use BuildingKind as B;
+    use UnitKind as U;
+    let kind = match unit_id {
+        0x00 | 0x01 => EntityKind::Unit(U::Footman),
+        0x02 | 0x03 => EntityKind::Unit(U::Peasant),
+        0x04 => EntityKind::Unit(U::Ballista),
+        0x05 => EntityKind::Unit(U::Catapult),
+        0x06 | 0x07 => EntityKind::Unit(U::Knight),
+        0x08 | 0x09 => EntityKind::Unit(U::Archer),
+        0x0A | 0x0B => EntityKind::Unit(U::Mage),
+        0x0C | 0x0D => EntityKind::Unit(U::Paladin),
+        0x3A | 0x3B => EntityKind::Building(B::Farm),
+        0x3C | 0x3D => EntityKind::Building(B::Barracks),
+        0x3E | 0x3F => EntityKind::Building(B::Church),
+        0x40 | 0x41 => EntityKind::Building(B::ScoutTower),
+        0x42 | 0x43 => EntityKind::Building(B::Stable),
+        0x44 | 0x45 => EntityKind::Building(B::Workshop),
+        0x4A | 0x4B | 0x58..=0x5B => EntityKind::Building(B::TownHall),
+        0x4C | 0x4D => EntityKind::Building(B::LumberMill),
+        0x52 | 0x53 => EntityKind::Building(B::Blacksmith),
+        0x56 | 0x57 => EntityKind::Building(B::OilWell),
+        0x5C => EntityKind::Building(B::Mine),
+        0x60..=0x63 => EntityKind::Building(B::Tower),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// PUD unit ids of the Human and Orc start position markers
+const PUD_START_POSITIONS: [u16; 2] = [0x5E, 0x5F];
+
+/// Terrain and start layout the simulation runs on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimMap {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub tileset: String,
+    /// Tree tiles, row-major; harvested trees are cleared
+    pub trees: Vec<bool>,
+    /// Start position per player
+    pub start_positions: BTreeMap<u8, (i32, i32)>,
+    /// Gold mines as (x, y, gold), top-left tile
+    pub gold_mines: Vec<(i32, i32, u32)>,
+    /// Pre-placed units and buildings as (owner, kind, x, y)
+    pub placed: Vec<(u8, EntityKind, i32, i32)>,
+    /// Unit data from the map's UDTA section; None plays with the embedded rules
+    #[serde(skip)]
+    pub rules: Option<Arc<GameRules>>,
+}
+
+impl SimMap {
+    /// Open grassland with a forest edge and one mine per start position
+    pub fn open_field(width: u32, height: u32, players: u8) -> Self {
+        let mut trees = vec![false; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                if x < 3 || y < 3 || x >= width.saturating_sub(3) || y >= height.saturating_sub(3) {
+                    trees[(y * width + x) as usize] = true;
+                }
+            }
+        }
+
+        let corners = [
+            (8, 8),
+            (width as i32 - 12, height as i32 - 12),
+            (width as i32 - 12, 8),
+            (8, height as i32 - 12),
+        ];
+        let mut start_positions = BTreeMap::new();
+        let mut gold_mines = Vec::new();
+        for (player, &(x, y)) in corners.iter().enumerate().take(players.clamp(1, 4) as usize) {
+            start_positions.insert(player as u8, (x, y));
+            gold_mines.push((x + 6, y, DEFAULT_MINE_GOLD));
+        }
+
+        Self {
+            name: "Open Field".to_string(),
+            width,
+            height,
+            tileset: "forest".to_string(),
+            trees,
+            start_positions,
+            gold_mines,
+            placed: Vec::new(),
+            rules: None,
+        }
+    }
+
+    /// Load a PUD map file
+    pub fn from_pud(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let mut map = Self::from_pud_bytes(&data)?;
+        if let Some(stem) = path.file_stem() {
+            map.name = stem.to_string_lossy().to_string();
+        }
+        Ok(map)
+    }
+
+    /// Parse PUD map data with the shared PUD parser
+    pub fn from_pud_bytes(data: &[u8]) -> Result<Self> {
+        let info = PudParser::from_data(data)
+            .and_then(|mut parser| parser.parse())
+            .map_err(|e| anyhow!("Invalid PUD map: {}", e))?;
+        let (width, height) = (info.width as u32, info.height as u32);
+
+        let mut trees = vec![false; (width * height) as usize];
+        for (tree, &tile) in trees.iter_mut().zip(&info.terrain) {
+            *tree = is_tree_tile(tile);
+        }
+
+        let mut rules = None;
+        if let Some(udta) = &info.unit_data {
+            let mut overridden = GameRules::embedded().clone();
+            if overridden.apply_udta(udta)? {
+                rules = Some(Arc::new(overridden));
+            }
+        }
+
+        let mut start_positions = BTreeMap::new();
+        let mut placed = Vec::new();
+        for unit in &info.units {
+            let (x, y) = (unit.x as i32, unit.y as i32);
+            if PUD_START_POSITIONS.contains(&unit.unit_type) {
+                start_positions.insert(unit.owner, (x, y));
+                continue;
+            }
+            match pud_entity_kind(unit.unit_type) {
+                // Mines come from the parser's resources, with their gold decoded
+                Some(EntityKind::Building(BuildingKind::Mine)) => {}
+                Some(kind) => placed.push((unit.owner, kind, x, y)),
+                None => debug!("🗺️ Skipping unsupported PUD unit 0x{:02x}", unit.unit_type),
+            }
+        }
+        let gold_mines = info.resources.iter()
+            .filter(|resource| resource.resource_type == 0)
+            .map(|mine| (mine.x as i32, mine.y as i32, mine.amount))
+            .collect();
+
+        Ok(Self {
+            name: info.map_name,
+            width,
+            height,
+            tileset: info.tileset_name,
+            trees,
+            start_positions,
+            gold_mines,
+            placed,
+            rules,
+        })
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height
+    }
+
+    /// Whether a tile still has trees on it
+    pub fn is_tree(&self, x: i32, y: i32) -> bool {
+        self.in_bounds(x, y) && self.trees[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    fn clear_tree(&mut self, x: i32, y: i32) {
+        if self.in_bounds(x, y) {
+            self.trees[(y as u32 * self.width + x as u32) as usize] = false;
+        }
+    }
+}
+
+/// Simulator settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimConfig {
+    pub seed: u64,
+    /// Player the backend issues commands for
+    pub player: u8,
+    /// Players that start with a town hall and a peasant at their start position
+    pub players: Vec<u8>,
+    pub starting_gold: u32,
+    pub starting_wood: u32,
+    pub starting_oil: u32,
+    /// Game ends as completed after this many ticks
+    pub max_ticks: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            player: 0,
+            players: vec![0, 1],
+            starting_gold: 2000,
+            starting_wood: 1000,
+            starting_oil: 0,
+            max_ticks: 60 * 60 * TICKS_PER_SECOND as u64,
+        }
+    }
+}
+
+/// What a simulated entity is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntityKind {
+    Unit(UnitKind),
+    Building(BuildingKind),
+}
+
+/// Current order of a unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Order {
+    Idle,
+    Move { x: i32, y: i32 },
+    AttackMove { x: i32, y: i32 },
+    Attack { target: u32 },
+    Gather { resource: ResourceKind, phase: GatherPhase },
+    Build { building: BuildingKind, x: i32, y: i32 },
+    /// Inside a building under construction
+    Constructing { site: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GatherPhase {
+    /// Walking to a mine id or tree tile
+    ToMine { mine: u32 },
+    ToTree { x: i32, y: i32 },
+    /// Inside the assigned mine, or chopping when there is none
+    Harvesting { ticks: u32, mine: Option<u32> },
+    /// Carrying back, then returning to the assigned mine
+    ToDepot { mine: Option<u32> },
+}
+
+/// A unit, building or gold mine
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub id: u32,
+    pub owner: u8,
+    pub kind: EntityKind,
+    /// Centre position in thousandths of a tile
+    pub x: i32,
+    pub y: i32,
+    pub hp: i32,
+    pub max_hp: i32,
+    /// Footprint edge length in tiles
+    pub size: i32,
+    /// Buildings under construction are not complete
+    pub completed: bool,
+    /// Ticks left on construction, training or research
+    pub progress_ticks: u32,
+    pub training: Option<UnitKind>,
+    pub researching: Option<UpgradeKind>,
+    pub rally: Option<(i32, i32)>,
+    /// Gold left in a mine
+    pub gold: u32,
+    pub carrying: Option<ResourceKind>,
+    /// Amount of the carried resource
+    pub carried: u32,
+    cooldown: u32,
+    order: Order,
+}
+
+impl Entity {
+    pub fn tile(&self) -> (i32, i32) {
+        (self.x / TILE, self.y / TILE)
+    }
+
+    pub fn is_unit(&self) -> bool {
+        matches!(self.kind, EntityKind::Unit(_))
+    }
+
+    pub fn is_worker(&self) -> bool {
+        self.kind == EntityKind::Unit(UnitKind::Peasant)
+    }
+
+    /// Whether a peasant may drop its order to build
+    fn can_build(&self) -> bool {
+        matches!(self.order, Order::Idle | Order::Gather { .. } | Order::Move { .. })
+    }
+
+    /// Half the footprint edge, in position units
+    fn half_size(&self) -> i32 {
+        self.size * TILE / 2
+    }
+}
+
+/// Resources and research state of a player
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimPlayer {
+    pub gold: u32,
+    pub wood: u32,
+    pub oil: u32,
+    pub upgrades: BTreeMap<String, u8>,
+    /// Whether the player ever had anything on the map
+    pub active: bool,
+    pub selection: Vec<u32>,
+    /// Running totals, e.g. for reward shaping
+    pub stats: SimPlayerStats,
+}
+
+/// Cumulative per-player game statistics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SimPlayerStats {
+    pub gold_gathered: u32,
+    pub wood_gathered: u32,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub kills: u32,
+    pub losses: u32,
+    pub units_trained: u32,
+    pub buildings_completed: u32,
+}
+
+impl SimPlayer {
+    fn upgrade_level(&self, upgrade: UpgradeKind) -> u8 {
+        self.upgrades.get(&upgrade.to_string()).copied().unwrap_or(0)
+    }
+
+    fn can_afford(&self, gold: u32, wood: u32, oil: u32) -> bool {
+        self.gold >= gold && self.wood >= wood && self.oil >= oil
+    }
+
+    fn pay(&mut self, gold: u32, wood: u32, oil: u32) {
+        self.gold -= gold;
+        self.wood -= wood;
+        self.oil -= oil;
+    }
+}
+
+/// Why the simulator refused a command; the game would just ignore it
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandOutcome {
+    Accepted,
+    Rejected(String),
+}
+
+/// Deterministic WC2 game simulation
+#[derive(Clone)]
+pub struct Simulation {
+    pub config: SimConfig,
+    pub map: SimMap,
+    pub tick: u64,
+    pub entities: BTreeMap<u32, Entity>,
+    pub players: BTreeMap<u8, SimPlayer>,
+    /// Unit, building and upgrade figures the game is played with
+    pub rules: Arc<GameRules>,
+    next_id: u32,
+    rng: StdRng,
+    index: SpatialIndex,
+}
+
+impl Simulation {
+    pub fn new(config: SimConfig, map: SimMap) -> Self {
+        let mut sim = Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            rules: map.rules.clone().unwrap_or_else(|| Arc::new(GameRules::embedded().clone())),
+            config,
+            map,
+            tick: 0,
+            entities: BTreeMap::new(),
+            players: BTreeMap::new(),
+            next_id: 1,
+            index: SpatialIndex::default(),
+        };
+        sim.setup();
+        sim.index.rebuild(&sim.map, &sim.entities);
+        sim
+    }
+
+    fn setup(&mut self) {
+        for &(x, y, gold) in &self.map.gold_mines.clone() {
+            let id = self.spawn(NEUTRAL_PLAYER, EntityKind::Building(BuildingKind::Mine), x, y);
+            if let Some(mine) = self.entities.get_mut(&id) {
+                mine.gold = if gold > 0 { gold } else { DEFAULT_MINE_GOLD };
+            }
+        }
+
+        for (owner, kind, x, y) in self.map.placed.clone() {
+            self.spawn(owner, kind, x, y);
+        }
+
+        for player in self.config.players.clone() {
+            let state = self.players.entry(player).or_default();
+            state.gold = self.config.starting_gold;
+            state.wood = self.config.starting_wood;
+            state.oil = self.config.starting_oil;
+
+            let has_hall = self.entities.values().any(|e| {
+                e.owner == player && e.kind == EntityKind::Building(BuildingKind::TownHall)
+            });
+            if !has_hall {
+                let center = (self.map.width as i32 / 2, self.map.height as i32 / 2);
+                let (x, y) = self.map.start_positions.get(&player).copied().unwrap_or(center);
+                self.spawn(player, EntityKind::Building(BuildingKind::TownHall), x, y);
+                self.spawn(player, EntityKind::Unit(UnitKind::Peasant), x, y + 4);
+            }
+        }
+
+        let owners: BTreeSet<u8> = self.entities.values().map(|e| e.owner).collect();
+        for owner in owners.into_iter().filter(|&owner| owner != NEUTRAL_PLAYER) {
+            self.players.entry(owner).or_default().active = true;
+        }
+    }
+
+    /// Place a completed entity with its top-left corner on a tile
+    fn spawn(&mut self, owner: u8, kind: EntityKind, x: i32, y: i32) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let (max_hp, size) = match kind {
+            EntityKind::Unit(unit) => (unit_stats(&self.rules, unit).hp, 1),
+            EntityKind::Building(building) => {
+                let stats = building_stats(&self.rules, building);
+                (stats.hp, stats.size)
+            }
+        };
+
+        self.entities.insert(id, Entity {
+            id,
+            owner,
+            kind,
+            x: x * TILE + size * TILE / 2,
+            y: y * TILE + size * TILE / 2,
+            hp: max_hp,
+            max_hp,
+            size,
+            completed: true,
+            progress_ticks: 0,
+            training: None,
+            researching: None,
+            rally: None,
+            gold: 0,
+            carrying: None,
+            carried: 0,
+            cooldown: 0,
+            order: Order::Idle,
+        });
+        if let Some(entity) = self.entities.get(&id) {
+            self.index.insert(entity);
+        }
+        id
+    }
+
+    /// Food used and provided for a player
+    pub fn food(&self, player: u8) -> (u32, u32) {
+        let mut used = 0;
+        let mut provided = 0;
+        for entity in self.entities.values().filter(|e| e.owner == player) {
+            match entity.kind {
+                EntityKind::Unit(_) => used += 1,
+                EntityKind::Building(building) if entity.completed => provided += building_stats(&self.rules, building).food,
+                EntityKind::Building(_) => {}
+            }
+            if entity.training.is_some() {
+                used += 1;
+            }
+        }
+        (used, provided.min(MAX_FOOD))
+    }
+
+
+    /// Rules keys of the units, finished buildings and research a player owns
+    ///
+    /// Stand-ins also count as the buildings they replace and every key as its
+    /// other-race counterpart, so the rules' tech tree applies unchanged.
+    fn owned_keys(&self, player: u8) -> BTreeSet<&str> {
+        let mut owned = BTreeSet::new();
+        for entity in self.entities.values().filter(|e| e.owner == player && e.completed) {
+            match entity.kind {
+                EntityKind::Unit(unit) => {
+                    owned.insert(unit.rules_key());
+                }
+                EntityKind::Building(building) => {
+                    owned.insert(building.rules_key());
+                    owned.extend(STAND_INS.iter().filter(|(_, stand_in)| *stand_in == building).map(|&(lacking, _)| lacking));
+                }
+            }
+        }
+        if let Some(state) = self.players.get(&player) {
+            for upgrade in UpgradeKind::ALL {
+                owned.extend(upgrade.rules_keys().iter().take(state.upgrade_level(*upgrade) as usize));
+            }
+        }
+        let counterparts: Vec<&str> = owned.iter().filter_map(|key| self.rules.counterpart(key)).collect();
+        owned.extend(counterparts);
+        owned
+    }
+
+    /// Rules requirements of a unit or building the player lacks, apart from whoever makes it
+    fn missing_requirements(&self, player: u8, key: &str) -> Vec<&str> {
+        let producers = self.rules.unit(key).map(|entry| entry.produced_at.as_slice()).unwrap_or_default();
+        self.rules.missing(key, &self.owned_keys(player)).into_iter()
+            .filter(|missing| !producers.iter().any(|producer| producer == missing))
+            .collect()
+    }
+
+    /// Carry out a command for a player
+    pub fn issue(&mut self, player: u8, command: &AICommand) -> CommandOutcome {
+        let outcome = self.apply(player, command);
+        if let CommandOutcome::Rejected(reason) = &outcome {
+            debug!("🎲 Player {} command '{}' rejected: {}", player, command, reason);
+        }
+        outcome
+    }
+
+    /// Tile of the player's town hall, or of anything it still owns
+    pub fn home(&self, player: u8) -> Option<(i32, i32)> {
+        self.entities.values()
+            .find(|e| e.owner == player && e.kind == EntityKind::Building(BuildingKind::TownHall))
+            .or_else(|| self.entities.values().find(|e| e.owner == player))
+            .map(|e| e.tile())
+    }
+
+    /// Whether a command would currently be accepted, without carrying it out
+    pub fn would_accept(&self, player: u8, command: &AICommand) -> bool {
+        self.rejection(player, command).is_none()
+    }
+
+    /// Why a command would be refused, checked without changing the game
+    fn rejection(&self, player: u8, command: &AICommand) -> Option<String> {
+        match command {
+            AICommand::Train { unit } => self.producer_for(player, *unit).err(),
+            AICommand::BuildAt { building, x, y } => self.builder_for(player, *building, *x, *y).err(),
+            AICommand::Research { upgrade } => self.lab_for(player, *upgrade).err(),
+            AICommand::Gather { resource, x, y } => self.gather_plan(player, *resource, *x, *y).err(),
+            AICommand::AttackMove { .. } | AICommand::Formation { .. } => {
+                self.army(player).is_empty().then(|| "no units to order".to_string())
+            }
+            AICommand::Move { .. } => self.selected_units(player).is_empty().then(|| "no units to order".to_string()),
+            AICommand::SetRally { .. } | AICommand::Select { .. } | AICommand::Wait { .. } => None,
+            AICommand::Cast { .. } => Some("spells are not simulated".to_string()),
+            AICommand::Click { .. } | AICommand::Hotkey { .. } => {
+                Some("raw input has no meaning in the simulator".to_string())
+            }
+        }
+    }
+
+    /// Free site for a building near the player's town hall, searching outwards
+    pub fn find_build_site(&self, player: u8, building: BuildingKind) -> Option<(i32, i32)> {
+        let (cx, cy) = self.home(player)?;
+        let size = building_stats(&self.rules, building).size;
+
+        // Keep a one tile gap around the site so peasants can still walk past
+        let max_radius = self.map.width.max(self.map.height) as i32;
+        for radius in 3..max_radius {
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if dx.abs() != radius && dy.abs() != radius {
+                        continue;
+                    }
+                    let (x, y) = (cx + dx, cy + dy);
+                    if self.placement_problem(x - 1, y - 1, size + 2).is_none() {
+                        return Some((x, y));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn apply(&mut self, player: u8, command: &AICommand) -> CommandOutcome {
+        use CommandOutcome::*;
+
+        if let Some(reason) = self.rejection(player, command) {
+            return Rejected(reason);
+        }
+        match command {
+            AICommand::Train { unit } => self.train(player, *unit),
+            AICommand::BuildAt { building, x, y } => self.build(player, *building, *x, *y),
+            AICommand::Research { upgrade } => self.research(player, *upgrade),
+            AICommand::Gather { resource, x, y } => self.gather(player, *resource, *x, *y),
+            AICommand::AttackMove { x, y } | AICommand::Formation { x, y, .. } => {
+                let army = self.army(player);
+                self.order_units(&army, Order::AttackMove { x: *x * TILE + TILE / 2, y: *y * TILE + TILE / 2 })
+            }
+            AICommand::Move { x, y } => {
+                let selected = self.selected_units(player);
+                self.order_units(&selected, Order::Move { x: *x * TILE + TILE / 2, y: *y * TILE + TILE / 2 })
+            }
+            AICommand::SetRally { x, y } => {
+                let mut buildings: Vec<u32> = self.players.get(&player)
+                    .map(|p| p.selection.clone())
+                    .unwrap_or_default();
+                buildings.retain(|id| self.entities.get(id).is_some_and(|e| !e.is_unit()));
+                if buildings.is_empty() {
+                    buildings = self.owned(player, |e| !e.is_unit());
+                }
+                for id in &buildings {
+                    if let Some(building) = self.entities.get_mut(id) {
+                        building.rally = Some((*x, *y));
+                    }
+                }
+                Accepted
+            }
+            AICommand::Select { selection } => {
+                let ids = match selection {
+                    Selection::AllUnits => self.owned(player, |e| e.is_unit()),
+                    Selection::AllWorkers => self.owned(player, |e| e.is_worker()),
+                    Selection::AllMilitary => self.owned(player, |e| e.is_unit() && !e.is_worker()),
+                    Selection::AllBuildings => self.owned(player, |e| !e.is_unit()),
+                    // There is no screen; the rectangle is read as map tiles
+                    Selection::Area { start_x, start_y, end_x, end_y } => {
+                        let (x0, x1) = (*start_x.min(end_x), *start_x.max(end_x));
+                        let (y0, y1) = (*start_y.min(end_y), *start_y.max(end_y));
+                        self.owned(player, |e| {
+                            let (tx, ty) = e.tile();
+                            tx >= x0 && tx <= x1 && ty >= y0 && ty <= y1
+                        })
+                    }
+                };
+                self.players.entry(player).or_default().selection = ids;
+                Accepted
+            }
+            // Time is shared by every player, so only whoever runs the game advances it
+            AICommand::Wait { .. } => Accepted,
+            AICommand::Cast { .. } | AICommand::Click { .. } | AICommand::Hotkey { .. } => {
+                unreachable!("refused by rejection()")
+            }
+        }
+    }
+
+    fn owned(&self, player: u8, filter: impl Fn(&Entity) -> bool) -> Vec<u32> {
+        self.entities.values()
+            .filter(|e| e.owner == player && filter(e))
+            .map(|e| e.id)
+            .collect()
+    }
+
+    /// The live backend selects all military before attack moving
+    fn army(&self, player: u8) -> Vec<u32> {
+        self.owned(player, |e| e.is_unit() && !e.is_worker())
+    }
+
+    fn selected_units(&self, player: u8) -> Vec<u32> {
+        self.players.get(&player)
+            .map(|p| p.selection.iter().copied().filter(|id| self.entities.get(id).is_some_and(|e| e.is_unit())).collect())
+            .unwrap_or_default()
+    }
+
+    fn order_units(&mut self, ids: &[u32], order: Order) -> CommandOutcome {
+        if ids.is_empty() {
+            return CommandOutcome::Rejected("no units to order".to_string());
+        }
+        for id in ids {
+            if let Some(entity) = self.entities.get_mut(id) {
+                if !matches!(entity.order, Order::Constructing { .. }) {
+                    entity.order = order;
+                }
+            }
+        }
+        CommandOutcome::Accepted
+    }
+
+    /// Idle building that can train a unit right now
+    fn producer_for(&self, player: u8, unit: UnitKind) -> Result<u32, String> {
+        let stats = unit_stats(&self.rules, unit);
+
+        let missing = self.missing_requirements(player, unit.rules_key());
+        if !missing.is_empty() {
+            return Err(format!("{} requires {}", unit, missing.join(", ")));
+        }
+        let producer = self.entities.values()
+            .find(|e| {
+                e.owner == player
+                    && e.completed
+                    && e.kind == EntityKind::Building(stats.trained_at)
+                    && e.training.is_none()
+                    && e.researching.is_none()
+            })
+            .map(|e| e.id);
+        let Some(producer) = producer else {
+            return Err(format!("no idle {} to train {}", stats.trained_at, unit));
+        };
+        let (used, provided) = self.food(player);
+        if used >= provided {
+            return Err("not enough food".to_string());
+        }
+        if !self.can_afford(player, stats.gold, stats.wood, stats.oil) {
+            return Err(format!("cannot afford {}", unit));
+        }
+        Ok(producer)
+    }
+
+    fn train(&mut self, player: u8, unit: UnitKind) -> CommandOutcome {
+        let producer = match self.producer_for(player, unit) {
+            Ok(producer) => producer,
+            Err(reason) => return CommandOutcome::Rejected(reason),
+        };
+        let stats = unit_stats(&self.rules, unit);
+        self.players.entry(player).or_default().pay(stats.gold, stats.wood, stats.oil);
+
+        if let Some(building) = self.entities.get_mut(&producer) {
+            building.training = Some(unit);
+            building.progress_ticks = stats.build_seconds * TICKS_PER_SECOND;
+        }
+        CommandOutcome::Accepted
+    }
+
+    /// Peasant that would go and build a building on a site
+    /// Whether a building could go up on some free site, checked before searching for one
+    pub fn could_build(&self, player: u8, building: BuildingKind) -> bool {
+        self.build_problem(player, building).is_none()
+    }
+
+    /// Why a building cannot be started anywhere, if it cannot
+    fn build_problem(&self, player: u8, building: BuildingKind) -> Option<String> {
+        let stats = building_stats(&self.rules, building);
+
+        match building {
+            _ if !building.is_buildable() => return Some(format!("{} cannot be built by workers", building)),
+            BuildingKind::OilWell => return Some("oil is not simulated".to_string()),
+            _ => {}
+        }
+        let missing = self.missing_requirements(player, building.rules_key());
+        if !missing.is_empty() {
+            return Some(format!("{} requires {}", building, missing.join(", ")));
+        }
+        if !self.can_afford(player, stats.gold, stats.wood, stats.oil) {
+            return Some(format!("cannot afford {}", building));
+        }
+        let has_builder = self.entities.values().any(|e| e.owner == player && e.is_worker() && e.can_build());
+        (!has_builder).then(|| "no peasant available".to_string())
+    }
+
+    /// Peasant that would go and build a building on a site
+    fn builder_for(&self, player: u8, building: BuildingKind, x: i32, y: i32) -> Result<u32, String> {
+        if let Some(reason) = self.build_problem(player, building) {
+            return Err(reason);
+        }
+        if let Some(reason) = self.placement_problem(x, y, building_stats(&self.rules, building).size) {
+            return Err(reason);
+        }
+
+        // Prefer an idle peasant, then the closest gathering one
+        let target = (x * TILE, y * TILE);
+        let builder = self.entities.values()
+            .filter(|e| e.owner == player && e.is_worker() && e.can_build())
+            .min_by_key(|e| (e.order != Order::Idle, distance(e.x, e.y, target.0, target.1), e.id))
+            .map(|e| e.id);
+        builder.ok_or_else(|| "no peasant available".to_string())
+    }
+
+    fn build(&mut self, player: u8, building: BuildingKind, x: i32, y: i32) -> CommandOutcome {
+        let builder = match self.builder_for(player, building, x, y) {
+            Ok(builder) => builder,
+            Err(reason) => return CommandOutcome::Rejected(reason),
+        };
+        if let Some(peasant) = self.entities.get_mut(&builder) {
+            peasant.order = Order::Build { building, x, y };
+        }
+        CommandOutcome::Accepted
+    }
+
+    /// Why a building cannot go on these tiles, if it cannot
+    fn placement_problem(&self, x: i32, y: i32, size: i32) -> Option<String> {
+        for ty in y..y + size {
+            for tx in x..x + size {
+                if !self.map.in_bounds(tx, ty) {
+                    return Some(format!("({}, {}) is off the map", tx, ty));
+                }
+                if self.map.is_tree(tx, ty) {
+                    return Some(format!("trees block ({}, {})", tx, ty));
+                }
+            }
+        }
+
+        let (x0, y0, x1, y1) = (x * TILE, y * TILE, (x + size) * TILE, (y + size) * TILE);
+        let blocked = self.entities.values().filter(|e| !e.is_unit()).any(|e| {
+            let half = e.half_size();
+            e.x - half < x1 && e.x + half > x0 && e.y - half < y1 && e.y + half > y0
+        });
+        blocked.then(|| format!("another building occupies ({}, {})", x, y))
+    }
+
+    /// Idle building that can research the next level of an upgrade right now
+    fn lab_for(&self, player: u8, upgrade: UpgradeKind) -> Result<(u32, UpgradeStats), String> {
+        let level = self.players.get(&player).map_or(0, |state| state.upgrade_level(upgrade));
+        let Some(stats) = upgrade_stats(&self.rules, upgrade, level + 1) else {
+            return Err(format!("{} is fully researched", upgrade));
+        };
+        let researching = self.entities.values().any(|e| e.owner == player && e.researching == Some(upgrade));
+        if researching {
+            return Err(format!("{} is already being researched", upgrade));
+        }
+        let lab = self.entities.values()
+            .find(|e| {
+                e.owner == player
+                    && e.completed
+                    && e.kind == EntityKind::Building(stats.researched_at)
+                    && e.training.is_none()
+                    && e.researching.is_none()
+            })
+            .map(|e| e.id);
+        let Some(lab) = lab else {
+            return Err(format!("no idle {} to research {}", stats.researched_at, upgrade));
+        };
+        if !self.can_afford(player, stats.gold, stats.wood, 0) {
+            return Err(format!("cannot afford {}", upgrade));
+        }
+        Ok((lab, stats))
+    }
+
+    fn research(&mut self, player: u8, upgrade: UpgradeKind) -> CommandOutcome {
+        let (lab, stats) = match self.lab_for(player, upgrade) {
+            Ok(found) => found,
+            Err(reason) => return CommandOutcome::Rejected(reason),
+        };
+        self.players.entry(player).or_default().pay(stats.gold, stats.wood, 0);
+
+        if let Some(building) = self.entities.get_mut(&lab) {
+            building.researching = Some(upgrade);
+            building.progress_ticks = stats.research_seconds * TICKS_PER_SECOND;
+        }
+        CommandOutcome::Accepted
+    }
+
+    /// Where gatherers would go and which peasants would be sent
+    fn gather_plan(&self, player: u8, resource: ResourceKind, x: i32, y: i32) -> Result<(GatherPhase, Vec<u32>), String> {
+        let phase = match resource {
+            ResourceKind::Gold => match self.nearest_mine(x * TILE, y * TILE) {
+                Some(mine) => GatherPhase::ToMine { mine },
+                None => return Err("no gold mine with gold left".to_string()),
+            },
+            ResourceKind::Wood => match self.nearest_tree(x, y) {
+                Some((tx, ty)) => GatherPhase::ToTree { x: tx, y: ty },
+                None => return Err("no trees left".to_string()),
+            },
+            ResourceKind::Oil => return Err("oil is not simulated".to_string()),
+        };
+
+        // Selected peasants if any, otherwise every idle peasant
+        let mut workers: Vec<u32> = self.selected_units(player);
+        workers.retain(|id| self.entities.get(id).is_some_and(|e| e.is_worker()));
+        if workers.is_empty() {
+            workers = self.owned(player, |e| e.is_worker() && e.order == Order::Idle);
+        }
+        if workers.is_empty() {
+            return Err("no units to order".to_string());
+        }
+        Ok((phase, workers))
+    }
+
+    fn gather(&mut self, player: u8, resource: ResourceKind, x: i32, y: i32) -> CommandOutcome {
+        match self.gather_plan(player, resource, x, y) {
+            Ok((phase, workers)) => self.order_units(&workers, Order::Gather { resource, phase }),
+            Err(reason) => CommandOutcome::Rejected(reason),
+        }
+    }
+
+    fn can_afford(&self, player: u8, gold: u32, wood: u32, oil: u32) -> bool {
+        self.players.get(&player).is_some_and(|state| state.can_afford(gold, wood, oil))
+    }
+
+    fn nearest_mine(&self, x: i32, y: i32) -> Option<u32> {
+        self.entities.values()
+            .filter(|e| e.kind == EntityKind::Building(BuildingKind::Mine) && e.gold > 0)
+            .min_by_key(|e| (distance(e.x, e.y, x, y), e.id))
+            .map(|e| e.id)
+    }
+
+    /// Closest tree tile to a tile, searching outwards in rings
+    fn nearest_tree(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let max_radius = self.map.width.max(self.map.height) as i32;
+        for radius in 0..=max_radius {
+            let mut best: Option<(i64, i32, i32)> = None;
+            for ty in y - radius..=y + radius {
+                for tx in x - radius..=x + radius {
+                    let on_ring = (tx - x).abs() == radius || (ty - y).abs() == radius;
+                    if on_ring && self.map.is_tree(tx, ty) {
+                        let d = ((tx - x) as i64).pow(2) + ((ty - y) as i64).pow(2);
+                        if best.is_none_or(|(bd, bx, by)| (d, ty, tx) < (bd, by, bx)) {
+                            best = Some((d, tx, ty));
+                        }
+                    }
+                }
+            }
+            if let Some((_, tx, ty)) = best {
+                return Some((tx, ty));
+            }
+        }
+        None
+    }
+
+    /// Closest completed depot of a player that accepts a resource
+    fn nearest_depot(&self, player: u8, resource: ResourceKind, x: i32, y: i32) -> Option<u32> {
+        self.entities.values()
+            .filter(|e| e.owner == player && e.completed)
+            .filter(|e| match e.kind {
+                EntityKind::Building(BuildingKind::TownHall) => true,
+                EntityKind::Building(BuildingKind::LumberMill) => resource == ResourceKind::Wood,
+                _ => false,
+            })
+            .min_by_key(|e| (distance(e.x, e.y, x, y), e.id))
+            .map(|e| e.id)
+    }
+
+    /// Advance the simulation by game time
+    pub fn run_for(&mut self, duration: Duration) {
+        let ticks = (duration.as_millis() as u64 * TICKS_PER_SECOND as u64).div_ceil(1000).max(1);
+        for _ in 0..ticks {
+            if self.is_over() {
+                break;
+            }
+            self.step();
+        }
+    }
+
+    /// Advance the simulation by one tick
+    pub fn step(&mut self) {
+        self.tick += 1;
+        self.index.rebuild(&self.map, &self.entities);
+
+        let ids: Vec<u32> = self.entities.keys().copied().collect();
+        for id in ids {
+            if !self.entities.contains_key(&id) {
+                continue;
+            }
+            match self.entities[&id].kind {
+                EntityKind::Unit(_) => self.update_unit(id),
+                EntityKind::Building(_) => self.update_building(id),
+            }
+        }
+
+        self.entities.retain(|_, e| e.hp > 0 || e.kind == EntityKind::Building(BuildingKind::Mine));
+    }
+
+    fn update_building(&mut self, id: u32) {
+        let Some(entity) = self.entities.get_mut(&id) else {
+            return;
+        };
+        let EntityKind::Building(building) = entity.kind else {
+            return;
+        };
+        let ready = entity.cooldown == 0;
+        entity.cooldown = entity.cooldown.saturating_sub(1);
+        let busy = entity.training.is_some() || entity.researching.is_some();
+
+        if !entity.completed {
+            // Construction advances while the builder is inside
+            let entity = self.entities.get_mut(&id).expect("building exists");
+            entity.progress_ticks = entity.progress_ticks.saturating_sub(1);
+            let total = building_stats(&self.rules, building).build_seconds * TICKS_PER_SECOND;
+            entity.hp = ((entity.max_hp as i64 * (total - entity.progress_ticks) as i64 / total.max(1) as i64) as i32).max(1);
+            if entity.progress_ticks == 0 {
+                entity.completed = true;
+                entity.hp = entity.max_hp;
+                let owner = entity.owner;
+                self.players.entry(owner).or_default().stats.buildings_completed += 1;
+                self.release_builder(id);
+            }
+            return;
+        }
+
+        if busy {
+            let entity = self.entities.get_mut(&id).expect("building exists");
+            entity.progress_ticks = entity.progress_ticks.saturating_sub(1);
+            if entity.progress_ticks == 0 {
+                let owner = entity.owner;
+                if let Some(upgrade) = entity.researching.take() {
+                    *self.players.entry(owner).or_default().upgrades.entry(upgrade.to_string()).or_insert(0) += 1;
+                }
+                if let Some(unit) = entity.training.take() {
+                    let (x, y, half, rally) = (entity.x, entity.y, entity.half_size(), entity.rally);
+                    self.players.entry(owner).or_default().stats.units_trained += 1;
+                    let spawned = self.spawn(owner, EntityKind::Unit(unit), (x - half) / TILE, (y + half) / TILE);
+                    if let (Some((rx, ry)), Some(new_unit)) = (rally, self.entities.get_mut(&spawned)) {
+                        new_unit.order = Order::Move { x: rx * TILE + TILE / 2, y: ry * TILE + TILE / 2 };
+                    }
+                }
+            }
+        }
+
+        if let Some((basic, piercing, range)) = building_stats(&self.rules, building).attack {
+            if ready {
+                if let Some(target) = self.nearest_enemy(&self.entities[&id], range) {
+                    self.strike(id, target, basic, piercing);
+                }
+            }
+        }
+    }
+
+    fn release_builder(&mut self, site: u32) {
+        for entity in self.entities.values_mut() {
+            if entity.order == (Order::Constructing { site }) {
+                entity.order = Order::Idle;
+            }
+        }
+    }
+
+    fn update_unit(&mut self, id: u32) {
+        let Some(entity) = self.entities.get_mut(&id) else {
+            return;
+        };
+        let EntityKind::Unit(unit) = entity.kind else {
+            return;
+        };
+        entity.cooldown = entity.cooldown.saturating_sub(1);
+        let (order, is_worker) = (entity.order, entity.is_worker());
+        let stats = unit_stats(&self.rules, unit);
+
+        match order {
+            Order::Idle => {
+                // Idle soldiers defend themselves against anything in range
+                if !is_worker {
+                    if let Some(target) = self.nearest_enemy(&self.entities[&id], stats.range) {
+                        self.attack(id, target);
+                    }
+                }
+            }
+            Order::Move { x, y } => {
+                if self.move_towards(id, x, y, 0) {
+                    self.set_order(id, Order::Idle);
+                }
+            }
+            Order::AttackMove { x, y } => {
+                let sight = stats.range.max(4) + 2;
+                match self.nearest_enemy(&self.entities[&id], sight) {
+                    Some(target) => self.attack(id, target),
+                    None => {
+                        if self.move_towards(id, x, y, 0) {
+                            self.set_order(id, Order::Idle);
+                        }
+                    }
+                }
+            }
+            Order::Attack { target } => {
+                if self.entities.get(&target).is_some_and(|t| t.hp > 0) {
+                    self.attack(id, target);
+                } else {
+                    self.set_order(id, Order::Idle);
+                }
+            }
+            Order::Gather { resource, phase } => self.update_gatherer(id, resource, phase),
+            Order::Build { building, x, y } => {
+                let size = building_stats(&self.rules, building).size;
+                let (cx, cy) = (x * TILE + size * TILE / 2, y * TILE + size * TILE / 2);
+                if self.move_towards(id, cx, cy, size * TILE / 2 + TILE / 2) {
+                    self.start_construction(id, building, x, y);
+                }
+            }
+            Order::Constructing { site } => {
+                if !self.entities.contains_key(&site) {
+                    self.set_order(id, Order::Idle);
+                }
+            }
+        }
+    }
+
+    fn start_construction(&mut self, builder: u32, building: BuildingKind, x: i32, y: i32) {
+        let owner = self.entities[&builder].owner;
+        let stats = building_stats(&self.rules, building);
+
+        // The site may have been taken or the money spent while walking
+        let affordable = self.players.get(&owner).is_some_and(|p| p.can_afford(stats.gold, stats.wood, stats.oil));
+        if self.placement_problem(x, y, stats.size).is_some() || !affordable {
+            debug!("🎲 Player {} could not place {} at ({}, {})", owner, building, x, y);
+            self.set_order(builder, Order::Idle);
+            return;
+        }
+        self.players.entry(owner).or_default().pay(stats.gold, stats.wood, stats.oil);
+
+        let site = self.spawn(owner, EntityKind::Building(building), x, y);
+        if let Some(entity) = self.entities.get_mut(&site) {
+            entity.completed = false;
+            entity.progress_ticks = stats.build_seconds * TICKS_PER_SECOND;
+            entity.hp = 1;
+        }
+        self.set_order(builder, Order::Constructing { site });
+    }
+
+    fn update_gatherer(&mut self, id: u32, resource: ResourceKind, phase: GatherPhase) {
+        let (x, y, owner) = {
+            let entity = &self.entities[&id];
+            (entity.x, entity.y, entity.owner)
+        };
+        let gather = |phase| Order::Gather { resource, phase };
+
+        match phase {
+            GatherPhase::ToMine { mine } => {
+                let Some(target) = self.entities.get(&mine).filter(|m| m.gold > 0) else {
+                    // Mine ran dry; look for another one
+                    match self.nearest_mine(x, y) {
+                        Some(next) => self.set_order(id, gather(GatherPhase::ToMine { mine: next })),
+                        None => self.set_order(id, Order::Idle),
+                    }
+                    return;
+                };
+                let (mx, my, reach) = (target.x, target.y, target.half_size() + TILE / 2);
+                if self.move_towards(id, mx, my, reach) {
+                    self.set_order(id, gather(GatherPhase::Harvesting { ticks: GOLD_MINING_TICKS, mine: Some(mine) }));
+                }
+            }
+            GatherPhase::ToTree { x, y } => {
+                if !self.map.is_tree(x, y) {
+                    match self.nearest_tree(x, y) {
+                        Some((tx, ty)) => self.set_order(id, gather(GatherPhase::ToTree { x: tx, y: ty })),
+                        None => self.set_order(id, Order::Idle),
+                    }
+                    return;
+                }
+                if self.move_towards(id, x * TILE + TILE / 2, y * TILE + TILE / 2, TILE) {
+                    self.set_order(id, gather(GatherPhase::Harvesting { ticks: WOOD_CHOPPING_TICKS, mine: None }));
+                }
+            }
+            GatherPhase::Harvesting { ticks, mine } if ticks > 1 => {
+                self.set_order(id, gather(GatherPhase::Harvesting { ticks: ticks - 1, mine }));
+            }
+            GatherPhase::Harvesting { mine, .. } => {
+                let harvested = match resource {
+                    ResourceKind::Gold => {
+                        // A nearly empty mine pays out what it has left
+                        match mine.and_then(|mine| self.entities.get_mut(&mine)).filter(|mine| mine.gold > 0) {
+                            Some(mine) => {
+                                let taken = mine.gold.min(CARRY_AMOUNT);
+                                mine.gold -= taken;
+                                taken
+                            }
+                            None => 0,
+                        }
+                    }
+                    _ => {
+                        let (tx, ty) = (x / TILE, y / TILE);
+                        match self.nearest_tree(tx, ty) {
+                            Some((x, y)) if (x - tx).abs() <= 2 && (y - ty).abs() <= 2 => {
+                                self.map.clear_tree(x, y);
+                                CARRY_AMOUNT
+                            }
+                            _ => 0,
+                        }
+                    }
+                };
+                if let Some(peasant) = self.entities.get_mut(&id) {
+                    peasant.carrying = (harvested > 0).then_some(resource);
+                    peasant.carried = harvested;
+                }
+                self.set_order(id, gather(GatherPhase::ToDepot { mine }));
+            }
+            GatherPhase::ToDepot { mine } => {
+                let Some(depot) = self.nearest_depot(owner, resource, x, y) else {
+                    self.set_order(id, Order::Idle);
+                    return;
+                };
+                let depot = &self.entities[&depot];
+                let (dx, dy, reach) = (depot.x, depot.y, depot.half_size() + TILE / 2);
+                if !self.move_towards(id, dx, dy, reach) {
+                    return;
+                }
+
+                let unloaded = self.entities.get_mut(&id)
+                    .and_then(|e| e.carrying.take().map(|kind| (kind, std::mem::take(&mut e.carried))));
+                if let Some((kind, amount)) = unloaded {
+                    let player = self.players.entry(owner).or_default();
+                    match kind {
+                        ResourceKind::Gold => {
+                            player.gold += amount;
+                            player.stats.gold_gathered += amount;
+                        }
+                        ResourceKind::Wood => {
+                            player.wood += amount;
+                            player.stats.wood_gathered += amount;
+                        }
+                        ResourceKind::Oil => player.oil += amount,
+                    }
+                }
+
+                // A dry mine sends the peasant on to the nearest other one
+                let next = match resource {
+                    ResourceKind::Gold => mine.or_else(|| self.nearest_mine(x, y)).map(|mine| GatherPhase::ToMine { mine }),
+                    _ => self.nearest_tree(x / TILE, y / TILE).map(|(x, y)| GatherPhase::ToTree { x, y }),
+                };
+                match next {
+                    Some(phase) => self.set_order(id, gather(phase)),
+                    None => self.set_order(id, Order::Idle),
+                }
+            }
+        }
+    }
+
+    fn set_order(&mut self, id: u32, order: Order) {
+        if let Some(entity) = self.entities.get_mut(&id) {
+            entity.order = order;
+        }
+    }
+
+    /// Step a unit towards a point; true once it is within `reach` of it
+    fn move_towards(&mut self, id: u32, x: i32, y: i32, reach: i32) -> bool {
+        let Some(entity) = self.entities.get_mut(&id) else {
+            return false;
+        };
+        let EntityKind::Unit(unit) = entity.kind else {
+            return false;
+        };
+
+        let (dx, dy) = ((x - entity.x) as i64, (y - entity.y) as i64);
+        let dist = ((dx * dx + dy * dy) as f64).sqrt() as i64;
+        if dist <= reach as i64 {
+            return true;
+        }
+
+        let step = (unit_stats(&self.rules, unit).speed as i64).min(dist - reach as i64);
+        entity.x += (dx * step / dist.max(1)) as i32;
+        entity.y += (dy * step / dist.max(1)) as i32;
+        dist - step <= reach as i64
+    }
+
+    /// Closest enemy within a range in tiles, measured edge to edge
+    fn nearest_enemy(&self, entity: &Entity, range: i32) -> Option<u32> {
+        let max_gap = (range - 1).max(0) * TILE;
+        self.index.near(entity.x, entity.y, max_gap + entity.half_size() + INDEX_SLACK)
+            .filter_map(|id| self.entities.get(&id))
+            .filter(|other| other.owner != entity.owner && other.owner != NEUTRAL_PLAYER && other.hp > 0)
+            .map(|other| (edge_gap(entity, other), other.id))
+            .filter(|(gap, _)| *gap <= max_gap)
+            .min()
+            .map(|(_, id)| id)
+    }
+
+    /// Move into range of a target and hit it when ready
+    fn attack(&mut self, id: u32, target: u32) {
+        let (Some(attacker), Some(victim)) = (self.entities.get(&id), self.entities.get(&target)) else {
+            return;
+        };
+        let EntityKind::Unit(unit) = attacker.kind else {
+            return;
+        };
+        let stats = unit_stats(&self.rules, unit);
+        let gap = edge_gap(attacker, victim);
+        let (attack_moving, cooldown, owner) = (matches!(attacker.order, Order::AttackMove { .. }), attacker.cooldown, attacker.owner);
+        let (victim_x, victim_y, victim_half) = (victim.x, victim.y, victim.half_size());
+
+        if !attack_moving {
+            self.set_order(id, Order::Attack { target });
+        }
+
+        if gap > (stats.range - 1).max(0) * TILE {
+            let reach = victim_half + TILE / 2 + (stats.range - 1).max(0) * TILE;
+            self.move_towards(id, victim_x, victim_y, reach);
+            return;
+        }
+        if cooldown > 0 {
+            return;
+        }
+
+        let (basic, piercing, _) = self.upgrade_bonus(owner, unit);
+        self.strike(id, target, stats.basic_damage + basic, stats.piercing_damage + piercing);
+    }
+
+    /// Research bonuses a player's unit gets: (basic damage, piercing damage, armor)
+    fn upgrade_bonus(&self, player: u8, unit: UnitKind) -> (i32, i32, i32) {
+        let Some(state) = self.players.get(&player) else {
+            return (0, 0, 0);
+        };
+        let mut bonus = (0, 0, 0);
+        for &upgrade in UpgradeKind::ALL {
+            let researched = upgrade.rules_keys().iter().take(state.upgrade_level(upgrade) as usize);
+            for entry in researched.filter_map(|key| self.rules.upgrade(key)) {
+                if entry.applies_to.iter().any(|target| target == unit.rules_key()) {
+                    bonus.0 += entry.basic_damage as i32;
+                    bonus.1 += entry.piercing_damage as i32;
+                    bonus.2 += entry.armor as i32;
+                }
+            }
+        }
+        bonus
+    }
+
+    /// Apply WC2's damage formula: (basic - armor, at least 0) + piercing, scaled by 50-100%
+    fn strike(&mut self, attacker: u32, target: u32, basic: i32, piercing: i32) {
+        let Some(victim) = self.entities.get(&target) else {
+            return;
+        };
+        let armor = match victim.kind {
+            EntityKind::Unit(unit) => unit_stats(&self.rules, unit).armor + self.upgrade_bonus(victim.owner, unit).2,
+            EntityKind::Building(building) => building_stats(&self.rules, building).armor,
+        };
+
+        let full = (basic - armor).max(0) + piercing;
+        let damage = (full * self.rng.gen_range(50..=100) / 100).max(1);
+
+        let Some(victim) = self.entities.get_mut(&target) else {
+            return;
+        };
+        let dealt = damage.min(victim.hp.max(0));
+        victim.hp -= damage;
+        let (victim_owner, killed) = (victim.owner, victim.hp <= 0);
+
+        let attacker_owner = match self.entities.get_mut(&attacker) {
+            Some(attacker) => {
+                attacker.cooldown = ATTACK_COOLDOWN_TICKS;
+                attacker.owner
+            }
+            None => return,
+        };
+
+        let attacker_stats = &mut self.players.entry(attacker_owner).or_default().stats;
+        attacker_stats.damage_dealt += dealt as u32;
+        attacker_stats.kills += killed as u32;
+        let victim_stats = &mut self.players.entry(victim_owner).or_default().stats;
+        victim_stats.damage_taken += dealt as u32;
+        victim_stats.losses += killed as u32;
+    }
+
+    /// Whether a player has lost everything it had
+    pub fn is_defeated(&self, player: u8) -> bool {
+        self.players.get(&player).is_some_and(|p| p.active) && !self.entities.values().any(|e| e.owner == player)
+    }
+
+    /// Whether the game has ended
+    pub fn is_over(&self) -> bool {
+        if self.tick >= self.config.max_ticks || self.is_defeated(self.config.player) {
+            return true;
+        }
+        let enemies: Vec<u8> = self.players.iter()
+            .filter(|(id, p)| **id != self.config.player && p.active)
+            .map(|(id, _)| *id)
+            .collect();
+        !enemies.is_empty() && enemies.iter().all(|&enemy| self.is_defeated(enemy))
+    }
+
+    /// The only player left standing, if the game was decided
+    pub fn winner(&self) -> Option<u8> {
+        let mut standing = self.players.iter()
+            .filter(|(id, p)| p.active && !self.is_defeated(**id))
+            .map(|(id, _)| *id);
+        match (standing.next(), standing.next()) {
+            (Some(winner), None) => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// Game state as seen by one player
+    pub fn observe(&self, player: u8) -> HeadlessGameState {
+        let state = self.players.get(&player).cloned().unwrap_or_default();
+        let (food_current, food_max) = self.food(player);
+
+        let units = self.entities.values()
+            .filter(|e| e.is_unit())
+            .map(|e| UnitInfo {
+                id: e.id,
+                unit_type: match e.kind {
+                    EntityKind::Unit(unit) => unit.to_string(),
+                    EntityKind::Building(building) => building.to_string(),
+                },
+                position: e.tile(),
+                health: e.hp.max(0) as u32,
+                max_health: e.max_hp as u32,
+                owner: e.owner,
+                is_selected: state.selection.contains(&e.id),
+                current_action: match &e.order {
+                    Order::Idle => None,
+                    Order::Move { .. } => Some("move".to_string()),
+                    Order::AttackMove { .. } | Order::Attack { .. } => Some("attack".to_string()),
+                    Order::Gather { resource, .. } => Some(format!("gather {}", resource)),
+                    Order::Build { building, .. } => Some(format!("build {}", building)),
+                    Order::Constructing { .. } => Some("construct".to_string()),
+                },
+            })
+            .collect();
+
+        let buildings = self.entities.values()
+            .filter(|e| !e.is_unit())
+            .map(|e| {
+                BuildingInfo {
+                    id: e.id,
+                    building_type: match e.kind {
+                        EntityKind::Building(building) => building.to_string(),
+                        EntityKind::Unit(unit) => unit.to_string(),
+                    },
+                    position: ((e.x - e.half_size()) / TILE, (e.y - e.half_size()) / TILE),
+                    health: e.hp.max(0) as u32,
+                    max_health: e.max_hp as u32,
+                    owner: e.owner,
+                    is_completed: e.completed,
+                    current_production: e.training.map(|unit| unit.to_string())
+                        .or_else(|| e.researching.map(|upgrade| upgrade.to_string())),
+                }
+            })
+            .collect();
+
+        let game_phase = if self.is_defeated(player) {
+            GamePhase::Defeat
+        } else if self.is_over() && self.tick < self.config.max_ticks {
+            GamePhase::Victory
+        } else {
+            GamePhase::InGame
+        };
+
+        HeadlessGameState {
+            player,
+            game_phase,
+            player_resources: PlayerResources {
+                gold: state.gold,
+                wood: state.wood,
+                oil: state.oil,
+                food_current,
+                food_max,
+                population: food_current,
+            },
+            units,
+            buildings,
+            map_info: MapInfo {
+                width: self.map.width,
+                height: self.map.height,
+                terrain_type: self.map.tileset.clone(),
+                starting_positions: self.map.start_positions.values().copied().collect(),
+                resource_locations: self.entities.values()
+                    .filter(|e| e.kind == EntityKind::Building(BuildingKind::Mine) && e.gold > 0)
+                    .map(|e| e.tile())
+                    .collect(),
+            },
+            game_time: self.tick,
+            game_status: if self.is_over() { GameStatus::Completed } else { GameStatus::Running },
+            ai_actions_queued: 0,
+            memory_hooks_active: 0,
+            last_update: self.tick,
+            game_started_at: None,
+        }
+    }
+}
+
+/// Straight-line distance between two points in position units
+fn distance(x0: i32, y0: i32, x1: i32, y1: i32) -> i64 {
+    let (dx, dy) = ((x1 - x0) as i64, (y1 - y0) as i64);
+    ((dx * dx + dy * dy) as f64).sqrt() as i64
+}
+
+/// Gap between two footprints along the larger axis, in position units
+fn edge_gap(a: &Entity, b: &Entity) -> i32 {
+    let gap_x = (a.x - b.x).abs() - a.half_size() - b.half_size();
+    let gap_y = (a.y - b.y).abs() - a.half_size() - b.half_size();
+    gap_x.max(gap_y).max(0)
+}
+
+/// Edge length of a spatial index cell, in thousandths of a tile
+const INDEX_CELL: i32 = 8 * TILE;
+
+/// Extra search distance past a range: half the largest footprint plus a
+/// tile for units that moved since the index was built
+const INDEX_SLACK: i32 = 3 * TILE;
+
+/// Player-owned entities bucketed into map cells at the start of each tick,
+/// so range queries only look at nearby cells instead of every entity
+#[derive(Clone, Default)]
+struct SpatialIndex {
+    columns: i32,
+    rows: i32,
+    cells: Vec<Vec<u32>>,
+}
+
+impl SpatialIndex {
+    fn rebuild(&mut self, map: &SimMap, entities: &BTreeMap<u32, Entity>) {
+        self.columns = map.width as i32 * TILE / INDEX_CELL + 1;
+        self.rows = map.height as i32 * TILE / INDEX_CELL + 1;
+        self.cells.iter_mut().for_each(Vec::clear);
+        self.cells.resize_with((self.columns * self.rows) as usize, Vec::new);
+        for entity in entities.values() {
+            self.insert(entity);
+        }
+    }
+
+    /// Add an entity placed since the last rebuild
+    fn insert(&mut self, entity: &Entity) {
+        if self.cells.is_empty() || entity.owner == NEUTRAL_PLAYER {
+            return;
+        }
+        let (column, row) = self.cell(entity.x, entity.y);
+        self.cells[(row * self.columns + column) as usize].push(entity.id);
+    }
+
+    fn cell(&self, x: i32, y: i32) -> (i32, i32) {
+        ((x / INDEX_CELL).clamp(0, self.columns - 1), (y / INDEX_CELL).clamp(0, self.rows - 1))
+    }
+
+    /// Ids in the cells overlapping the square within `reach` of a point
+    fn near(&self, x: i32, y: i32, reach: i32) -> impl Iterator<Item = u32> + '_ {
+        let (left, top) = self.cell(x - reach, y - reach);
+        let (right, bottom) = self.cell(x + reach, y + reach);
+        (top..=bottom).flat_map(move |row| {
+            (left..=right).flat_map(move |column| self.cells[(row * self.columns + column) as usize].iter().copied())
+        })
+    }
+}
+
+/// Backend running the engine against the simulator instead of the live game
+pub struct SimulatorBackend {
+    simulation: Mutex<Simulation>,
+}
+
+impl SimulatorBackend {
+    pub fn new(config: SimConfig, map: SimMap) -> Self {
+        info!("🎲 Simulator backend on {} ({}x{}), seed {}", map.name, map.width, map.height, config.seed);
+        Self {
+            simulation: Mutex::new(Simulation::new(config, map)),
+        }
+    }
+
+    /// Simulator backend on a PUD map
+    pub fn from_pud(config: SimConfig, path: &Path) -> Result<Self> {
+        Ok(Self::new(config, SimMap::from_pud(path)?))
+    }
+
+    /// Start a new game in place
+    pub async fn reset(&self, config: SimConfig, map: SimMap) {
+        debug!("🎲 Resetting simulator on {}, seed {}", map.name, config.seed);
+        *self.simulation.lock().await = Simulation::new(config, map);
+    }
+
+    /// Direct access to the simulation, e.g. to drive other players
+    pub fn simulation(&self) -> &Mutex<Simulation> {
+        &self.simulation
+    }
+
+    /// Backend for one player of this game
+    pub fn seat(self: &Arc<Self>, player: u8) -> SimulatorSeat {
+        SimulatorSeat {
+            backend: self.clone(),
+            player,
+            name: format!("simulator seat {}", player),
+        }
+    }
+}
+
+/// One player's side of a shared simulation, for games between several AIs
+///
+/// Time only passes when whoever runs the match advances the shared backend,
+/// so `advance` and `Wait` commands are no-ops here, as in the simulation itself.
+pub struct SimulatorSeat {
+    backend: Arc<SimulatorBackend>,
+    player: u8,
+    name: String,
+}
+
+impl SimulatorSeat {
+    pub fn player(&self) -> u8 {
+        self.player
+    }
+}
+
+#[async_trait]
+impl GameBackend for SimulatorSeat {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn connect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn observe(&self) -> Result<HeadlessGameState> {
+        Ok(self.backend.simulation.lock().await.observe(self.player))
+    }
+
+    async fn issue(&self, command: &AICommand) -> Result<()> {
+        self.backend.simulation.lock().await.issue(self.player, command);
+        Ok(())
+    }
+
+    async fn advance(&self, _duration: Duration) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GameBackend for SimulatorBackend {
+    fn name(&self) -> &str {
+        "simulator"
+    }
+
+    async fn connect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn observe(&self) -> Result<HeadlessGameState> {
+        let simulation = self.simulation.lock().await;
+        Ok(simulation.observe(simulation.config.player))
+    }
+
+    async fn issue(&self, command: &AICommand) -> Result<()> {
+        let mut simulation = self.simulation.lock().await;
+        let player = simulation.config.player;
+        // One AI drives this game, so waiting lets its time pass as in the live game
+        if let AICommand::Wait { ms } = command {
+            simulation.run_for(Duration::from_millis(*ms));
+        }
+        // Refused commands are ignored, as the game would
+        simulation.issue(player, command);
+        Ok(())
+    }
+
+    async fn advance(&self, duration: Duration) -> Result<()> {
+        self.simulation.lock().await.run_for(duration);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(seed: u64) -> Simulation {
+        Simulation::new(SimConfig { seed, ..SimConfig::default() }, SimMap::open_field(32, 32, 2))
+    }
+
+    /// Commands both players issue, keyed by the tick they are issued on
+    fn script() -> Vec<(u64, u8, AICommand)> {
+        let mut script = Vec::new();
+        for player in [0, 1] {
+            script.push((0, player, AICommand::Gather { resource: ResourceKind::Gold, x: 20, y: 20 }));
+            script.push((10, player, AICommand::Train { unit: UnitKind::Peasant }));
+            script.push((600, player, AICommand::Select { selection: Selection::AllWorkers }));
+            script.push((610, player, AICommand::Gather { resource: ResourceKind::Wood, x: 16, y: 2 }));
+            script.push((900, player, AICommand::BuildAt { building: BuildingKind::Farm, x: 14, y: 14 + 6 * player as i32 }));
+        }
+        script
+    }
+
+    fn play(mut sim: Simulation, ticks: u64) -> Simulation {
+        let script = script();
+        for tick in 0..ticks {
+            for (_, player, command) in script.iter().filter(|(at, _, _)| *at == tick) {
+                sim.issue(*player, command);
+            }
+            sim.step();
+        }
+        sim
+    }
+
+    fn fingerprint(sim: &Simulation) -> String {
+        let observed: Vec<String> = [0, 1].iter()
+            .map(|&player| serde_json::to_string(&sim.observe(player)).unwrap())
+            .collect();
+        format!("{} {:?} {}", sim.tick, sim.players, observed.join(""))
+    }
+
+    #[test]
+    fn test_seeded_replay_is_identical() {
+        let first = play(game(7), 2000);
+        let second = play(game(7), 2000);
+        assert_eq!(fingerprint(&first), fingerprint(&second));
+        assert!(first.players.values().all(|player| player.stats.gold_gathered > 0));
+        assert_eq!(first.players[&0].stats.buildings_completed, 1);
+    }
+
+    #[test]
+    fn test_would_accept_matches_issue() {
+        let mut sim = game(1);
+        let commands = [
+            AICommand::Train { unit: UnitKind::Peasant },
+            AICommand::Train { unit: UnitKind::Footman },
+            AICommand::BuildAt { building: BuildingKind::Barracks, x: 16, y: 14 },
+            AICommand::BuildAt { building: BuildingKind::Farm, x: 0, y: 0 },
+            AICommand::BuildAt { building: BuildingKind::Mine, x: 20, y: 20 },
+            AICommand::Research { upgrade: UpgradeKind::MeleeWeapons },
+            AICommand::Gather { resource: ResourceKind::Gold, x: 14, y: 8 },
+            AICommand::Gather { resource: ResourceKind::Oil, x: 14, y: 8 },
+            AICommand::AttackMove { x: 20, y: 20 },
+            AICommand::Move { x: 20, y: 20 },
+            AICommand::Select { selection: Selection::AllWorkers },
+            AICommand::Cast { spell: crate::ai_command::SpellKind::Healing, x: 1, y: 1 },
+            AICommand::Wait { ms: 1000 },
+        ];
+        for round in 0..4 {
+            for command in &commands {
+                let expected = sim.clone().issue(0, command) == CommandOutcome::Accepted;
+                assert_eq!(sim.would_accept(0, command), expected, "round {}: {}", round, command);
+            }
+            sim.issue(0, &commands[round]);
+            sim.run_for(Duration::from_secs(30));
+        }
+    }
+
+    #[test]
+    fn test_wait_leaves_time_to_the_runner() {
+        let mut sim = game(0);
+        assert_eq!(sim.issue(0, &AICommand::Wait { ms: 5000 }), CommandOutcome::Accepted);
+        assert_eq!(sim.tick, 0);
+    }
+
+    #[test]
+    fn test_gold_comes_from_the_assigned_mine() {
+        let mut map = SimMap::open_field(32, 32, 1);
+        map.gold_mines.push((8, 20, DEFAULT_MINE_GOLD));
+        let mut sim = Simulation::new(SimConfig { players: vec![0], ..SimConfig::default() }, map);
+        let mine_at = |sim: &Simulation, x: i32, y: i32| {
+            sim.entities.values()
+                .find(|e| e.kind == EntityKind::Building(BuildingKind::Mine) && e.tile() == (x + 1, y + 1))
+                .map(|e| e.gold)
+                .unwrap()
+        };
+
+        assert_eq!(sim.issue(0, &AICommand::Gather { resource: ResourceKind::Gold, x: 9, y: 21 }), CommandOutcome::Accepted);
+        sim.run_for(Duration::from_secs(300));
+
+        assert!(sim.players[&0].stats.gold_gathered >= 3 * CARRY_AMOUNT);
+        assert_eq!(mine_at(&sim, 14, 8), DEFAULT_MINE_GOLD);
+        let carried: u32 = sim.entities.values().filter(|e| e.carrying == Some(ResourceKind::Gold)).map(|e| e.carried).sum();
+        assert_eq!(mine_at(&sim, 8, 20), DEFAULT_MINE_GOLD - sim.players[&0].stats.gold_gathered - carried);
+    }
+
+    #[test]
+    fn test_nearly_empty_mine_pays_what_is_left() {
+        let mut map = SimMap::open_field(32, 32, 1);
+        map.gold_mines.push((8, 20, CARRY_AMOUNT / 2));
+        let mut sim = Simulation::new(SimConfig { players: vec![0], ..SimConfig::default() }, map);
+
+        assert_eq!(sim.issue(0, &AICommand::Gather { resource: ResourceKind::Gold, x: 9, y: 21 }), CommandOutcome::Accepted);
+        while sim.players[&0].stats.gold_gathered == 0 && sim.tick < 3000 {
+            sim.step();
+        }
+        assert_eq!(sim.players[&0].stats.gold_gathered, CARRY_AMOUNT / 2);
+        assert!(sim.entities.values().any(|e| e.kind == EntityKind::Building(BuildingKind::Mine) && e.gold == 0));
+    }
+
+    #[test]
+    fn test_tiny_maps_do_not_panic() {
+        for size in 0..4 {
+            let map = SimMap::open_field(size, size, 1);
+            assert!(map.trees.iter().all(|&tree| tree));
+            let mut sim = Simulation::new(SimConfig { players: vec![0], ..SimConfig::default() }, map);
+            sim.run_for(Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_nearest_enemy_matches_a_full_scan() {
+        let mut map = SimMap::open_field(48, 48, 2);
+        for i in 0..40 {
+            map.placed.push((0, EntityKind::Unit(UnitKind::Footman), 16 + i % 8, 16 + i / 8));
+            map.placed.push((1, EntityKind::Unit(UnitKind::Archer), 26 + i % 8, 20 + i / 8));
+        }
+        let mut sim = Simulation::new(SimConfig::default(), map);
+        for player in [0, 1] {
+            sim.issue(player, &AICommand::Select { selection: Selection::AllMilitary });
+            sim.issue(player, &AICommand::AttackMove { x: 24, y: 24 });
+        }
+
+        for _ in 0..10 {
+            sim.run_for(Duration::from_secs(2));
+            for entity in sim.entities.values().filter(|e| e.is_unit()) {
+                for range in [1, 4, 9] {
+                    let scanned = sim.entities.values()
+                        .filter(|other| other.owner != entity.owner && other.owner != NEUTRAL_PLAYER && other.hp > 0)
+                        .map(|other| (edge_gap(entity, other), other.id))
+                        .filter(|(gap, _)| *gap <= (range - 1).max(0) * TILE)
+                        .min()
+                        .map(|(_, id)| id);
+                    assert_eq!(sim.nearest_enemy(entity, range), scanned);
+                }
+            }
+        }
+        assert!(sim.players.values().any(|player| player.stats.kills > 0));
+    }
+
+    #[test]
+    fn test_requirements_follow_the_game_rules() {
+        let mut sim = game(0);
+        let missing = |sim: &Simulation, key: &str| -> Vec<String> {
+            sim.missing_requirements(0, key).into_iter().map(str::to_string).collect()
+        };
+
+        // The Town Hall stands in for the Keep and Castle the command model lacks
+        assert_eq!(missing(&sim, "knight"), ["stables"]);
+        assert!(missing(&sim, "stables").is_empty());
+        assert!(missing(&sim, "church").is_empty());
+        assert_eq!(missing(&sim, UnitKind::Catapult.rules_key()), ["blacksmith"]);
+
+        sim.spawn(0, EntityKind::Building(BuildingKind::Stable), 20, 20);
+        sim.spawn(0, EntityKind::Building(BuildingKind::Blacksmith), 24, 20);
+        assert!(missing(&sim, "knight").is_empty());
+        assert!(missing(&sim, UnitKind::Catapult.rules_key()).is_empty());
+        assert_eq!(missing(&sim, "paladin"), ["paladins"]);
+        sim.players.get_mut(&0).unwrap().upgrades.insert(UpgradeKind::Paladins.to_string(), 1);
+        assert!(missing(&sim, "paladin").is_empty());
+
+        assert_eq!(unit_stats(&sim.rules, UnitKind::Mage).trained_at, BuildingKind::Church);
+        assert_eq!(unit_stats(&sim.rules, UnitKind::Catapult).trained_at, BuildingKind::Barracks);
+    }
+
+    #[test]
+    fn test_pud_map_loads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../TMapX/MapTests/Garden of War.pud");
+        let map = SimMap::from_pud(Path::new(path)).unwrap();
+        assert_eq!(map.name, "Garden of War");
+        assert_eq!((map.width, map.height), (128, 128));
+        assert_eq!(map.trees.len(), 128 * 128);
+        assert!(map.trees.iter().any(|&tree| tree));
+        assert!(!map.start_positions.is_empty());
+        assert!(map.gold_mines.iter().all(|&(x, y, gold)| map.in_bounds(x, y) && gold > 0));
+
+        let sim = Simulation::new(SimConfig::default(), map);
+        assert!(!sim.is_over());
+        assert!(SimMap::from_pud_bytes(b"not a map").is_err());
+    }
+}
@@ -0,0 +1,473 @@
+//! Contextual-bandit strategy selection
+//!
+//! Learning examples are numeric feature vectors of the game state paired with
+//! the strategy that was played from there and the reward the game paid out.
+//! A LinUCB model over those vectors picks the `AIStrategy` (and with it the
+//! personality preset) at game start and again at every phase change; the
+//! rewards arrive when the game ends and the model is persisted as JSON.
+
+use std::path::Path;
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+use crate::ai_command::UnitKind;
+use crate::ai_controller::{AIStrategy, ThreatLevel};
+use crate::game_engine::HeadlessGameState;
+
+/// Names of the feature vector entries, in order
+pub const FEATURE_NAMES: &[&str] = &[
+    "bias", "minutes", "gold", "wood", "oil", "food_used", "population",
+    "workers", "melee", "ranged", "siege", "casters", "buildings",
+    "threat", "map_width", "map_height", "gold_mines",
+];
+
+/// Strategies the bandit chooses between
+pub const STRATEGIES: &[AIStrategy] = &[
+    AIStrategy::Aggressive,
+    AIStrategy::Defensive,
+    AIStrategy::Balanced,
+    AIStrategy::Economic,
+    AIStrategy::Rush,
+    AIStrategy::Turtle,
+];
+
+/// Exploration weight of the upper confidence bound
+pub const DEFAULT_ALPHA: f64 = 0.5;
+
+/// Reward for a game that ended without a victory or defeat
+pub const DRAW_REWARD: f64 = 0.5;
+
+/// Coarse game phase; a new strategy is chosen whenever it changes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GameStage {
+    Opening,
+    Midgame,
+    Lategame,
+}
+
+impl GameStage {
+    /// Stage the game has reached
+    pub fn of(state: &HeadlessGameState) -> Self {
+        Self::at_minutes(state.elapsed_seconds() / 60.0)
+    }
+
+    pub fn at_minutes(minutes: f64) -> Self {
+        match minutes {
+            m if m < 5.0 => GameStage::Opening,
+            m if m < 12.0 => GameStage::Midgame,
+            _ => GameStage::Lategame,
+        }
+    }
+}
+
+/// Numeric description of a game situation
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GameFeatures {
+    pub minutes: f64,
+    pub gold: u32,
+    pub wood: u32,
+    pub oil: u32,
+    pub food_current: u32,
+    pub food_max: u32,
+    pub population: u32,
+    pub workers: u32,
+    pub melee: u32,
+    pub ranged: u32,
+    pub siege: u32,
+    pub casters: u32,
+    pub buildings: u32,
+    pub threat: f64,
+    pub map_width: u32,
+    pub map_height: u32,
+    pub gold_mines: u32,
+}
+
+impl GameFeatures {
+    pub fn from_game_state(state: &HeadlessGameState, threat: &ThreatLevel) -> Self {
+        let mut features = Self {
+            minutes: state.elapsed_seconds() / 60.0,
+            gold: state.player_resources.gold,
+            wood: state.player_resources.wood,
+            oil: state.player_resources.oil,
+            food_current: state.player_resources.food_current,
+            food_max: state.player_resources.food_max,
+            population: state.player_resources.population,
+            buildings: state.buildings.iter().filter(|building| building.owner == state.player).count() as u32,
+            threat: match threat {
+                ThreatLevel::None => 0.0,
+                ThreatLevel::Low => 0.25,
+                ThreatLevel::Medium => 0.5,
+                ThreatLevel::High => 0.75,
+                ThreatLevel::Critical => 1.0,
+            },
+            map_width: state.map_info.width,
+            map_height: state.map_info.height,
+            gold_mines: state.map_info.resource_locations.len() as u32,
+            ..Self::default()
+        };
+
+        // Unit types the command vocabulary does not know count towards nothing
+        for unit in state.units.iter().filter(|unit| unit.owner == state.player) {
+            match unit.unit_type.parse::<UnitKind>() {
+                Ok(UnitKind::Peasant) => features.workers += 1,
+                Ok(UnitKind::Footman | UnitKind::Knight | UnitKind::Paladin) => features.melee += 1,
+                Ok(UnitKind::Archer) => features.ranged += 1,
+                Ok(UnitKind::Ballista | UnitKind::Catapult) => features.siege += 1,
+                Ok(UnitKind::Mage) => features.casters += 1,
+                Err(_) => {}
+            }
+        }
+
+        features
+    }
+
+    pub fn stage(&self) -> GameStage {
+        GameStage::at_minutes(self.minutes)
+    }
+
+    /// Feature vector laid out as `FEATURE_NAMES`, scaled to roughly 0..1
+    pub fn to_vector(&self) -> Vec<f64> {
+        let count = |n: u32, scale: f64| n as f64 / scale;
+        let food = if self.food_max > 0 { self.food_current as f64 / self.food_max as f64 } else { 1.0 };
+        vec![
+            1.0,
+            (self.minutes / 30.0).min(1.0),
+            count(self.gold, 5000.0).min(1.0),
+            count(self.wood, 3000.0).min(1.0),
+            count(self.oil, 3000.0).min(1.0),
+            food.min(1.0),
+            count(self.population, 100.0).min(1.0),
+            count(self.workers, 30.0).min(1.0),
+            count(self.melee, 30.0).min(1.0),
+            count(self.ranged, 30.0).min(1.0),
+            count(self.siege, 10.0).min(1.0),
+            count(self.casters, 10.0).min(1.0),
+            count(self.buildings, 40.0).min(1.0),
+            self.threat,
+            count(self.map_width, 128.0),
+            count(self.map_height, 128.0),
+            count(self.gold_mines, 16.0).min(1.0),
+        ]
+    }
+}
+
+/// Strategy played from a situation and the reward it earned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearningExample {
+    pub features: Vec<f64>,
+    pub stage: GameStage,
+    pub strategy: AIStrategy,
+    /// 1.0 for a win, `DRAW_REWARD` for a draw, 0.0 for a loss
+    pub reward: f64,
+    pub timestamp: u64,
+}
+
+/// Ridge regression of reward on features for one strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanditArm {
+    pub strategy: AIStrategy,
+    /// Inverse of `I + sum(x x^T)`, kept up to date with Sherman-Morrison
+    a_inv: Vec<Vec<f64>>,
+    /// `sum(reward * x)`
+    b: Vec<f64>,
+    pub pulls: u64,
+    pub total_reward: f64,
+}
+
+impl BanditArm {
+    fn new(strategy: AIStrategy, dimensions: usize) -> Self {
+        let a_inv = (0..dimensions)
+            .map(|row| (0..dimensions).map(|col| if row == col { 1.0 } else { 0.0 }).collect())
+            .collect();
+        Self { strategy, a_inv, b: vec![0.0; dimensions], pulls: 0, total_reward: 0.0 }
+    }
+
+    fn a_inv_times(&self, x: &[f64]) -> Vec<f64> {
+        self.a_inv.iter().map(|row| dot(row, x)).collect()
+    }
+
+    /// Expected reward and confidence width at `x`
+    fn estimate(&self, x: &[f64]) -> (f64, f64) {
+        let theta = self.a_inv_times(&self.b);
+        let width = dot(x, &self.a_inv_times(x)).max(0.0).sqrt();
+        (dot(&theta, x), width)
+    }
+
+    fn update(&mut self, x: &[f64], reward: f64) {
+        // A^-1 is symmetric, so (A + x x^T)^-1 = A^-1 - (A^-1 x)(A^-1 x)^T / (1 + x^T A^-1 x)
+        let u = self.a_inv_times(x);
+        let denominator = 1.0 + dot(x, &u);
+        for (row, u_row) in self.a_inv.iter_mut().zip(&u) {
+            for (cell, u_col) in row.iter_mut().zip(&u) {
+                *cell -= u_row * u_col / denominator;
+            }
+        }
+        for (b, x) in self.b.iter_mut().zip(x) {
+            *b += reward * x;
+        }
+        self.pulls += 1;
+        self.total_reward += reward;
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// LinUCB over the strategies in `STRATEGIES`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyBandit {
+    pub alpha: f64,
+    /// Feature layout the arms were trained on
+    pub features: Vec<String>,
+    pub arms: Vec<BanditArm>,
+}
+
+impl StrategyBandit {
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha,
+            features: FEATURE_NAMES.iter().map(|name| name.to_string()).collect(),
+            arms: STRATEGIES.iter().map(|strategy| BanditArm::new(strategy.clone(), FEATURE_NAMES.len())).collect(),
+        }
+    }
+
+    /// Whether the model was trained on the current feature layout
+    pub fn is_current(&self) -> bool {
+        self.features.iter().map(String::as_str).eq(FEATURE_NAMES.iter().copied())
+            && self.arms.len() == STRATEGIES.len()
+    }
+
+    /// Strategy with the highest upper confidence bound, for exploration
+    pub fn select(&self, x: &[f64]) -> AIStrategy {
+        self.best_by(|arm| {
+            let (mean, width) = arm.estimate(x);
+            mean + self.alpha * width
+        })
+    }
+
+    /// Strategy with the highest expected reward
+    pub fn greedy(&self, x: &[f64]) -> AIStrategy {
+        self.best_by(|arm| arm.estimate(x).0)
+    }
+
+    pub fn update(&mut self, strategy: &AIStrategy, x: &[f64], reward: f64) {
+        if let Some(arm) = self.arms.iter_mut().find(|arm| arm.strategy == *strategy) {
+            arm.update(x, reward);
+        }
+    }
+
+    fn best_by(&self, score: impl Fn(&BanditArm) -> f64) -> AIStrategy {
+        self.arms.iter()
+            .map(|arm| (arm, score(arm)))
+            .fold(None, |best: Option<(&BanditArm, f64)>, (arm, value)| match best {
+                Some((_, top)) if top >= value => best,
+                _ => Some((arm, value)),
+            })
+            .map(|(arm, _)| arm.strategy.clone())
+            .unwrap_or(AIStrategy::Balanced)
+    }
+}
+
+impl Default for StrategyBandit {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALPHA)
+    }
+}
+
+/// Strategy chosen during the current game, waiting for its reward
+#[derive(Debug, Clone)]
+struct PendingDecision {
+    features: Vec<f64>,
+    stage: GameStage,
+    strategy: AIStrategy,
+}
+
+/// Bandit model, its training examples and the decisions of the game in progress
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StrategyLearner {
+    pub model: StrategyBandit,
+    pub examples: Vec<LearningExample>,
+    #[serde(skip)]
+    pending: Vec<PendingDecision>,
+    #[serde(skip)]
+    stage: Option<GameStage>,
+    /// Features of the latest decision point
+    #[serde(skip)]
+    latest: Option<Vec<f64>>,
+    /// Strategy currently in play, if the bandit chose one
+    #[serde(skip)]
+    pub active: Option<AIStrategy>,
+    /// Decisions made since the learner was created or loaded
+    #[serde(skip)]
+    pub decisions: usize,
+}
+
+impl StrategyLearner {
+    /// Learner saved at `path`; a fresh one when the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read learning data {}", path.display()))?;
+        let learner: Self = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse learning data {}", path.display()))?;
+
+        if !learner.model.is_current() {
+            warn!("⚠️ Learning data {} uses an old feature layout, starting over", path.display());
+            return Ok(Self::default());
+        }
+        Ok(learner)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write learning data {}", path.display()))
+    }
+
+    /// Choose a strategy when the game starts or enters a new stage; `None` in between
+    pub fn decide(&mut self, features: &GameFeatures, stage: GameStage) -> Option<AIStrategy> {
+        let x = features.to_vector();
+        self.latest = Some(x.clone());
+        if self.stage == Some(stage) {
+            return None;
+        }
+
+        let strategy = self.model.select(&x);
+        self.pending.push(PendingDecision { features: x, stage, strategy: strategy.clone() });
+        self.stage = Some(stage);
+        self.active = Some(strategy.clone());
+        self.decisions += 1;
+        Some(strategy)
+    }
+
+    /// Whether decisions of the game in progress are still waiting for a reward
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Strategy the model currently expects to do best at the latest decision point
+    pub fn recommend(&self) -> Option<AIStrategy> {
+        self.latest.as_ref().map(|x| self.model.greedy(x))
+    }
+
+    /// Train on one example and keep it
+    pub fn record(&mut self, example: LearningExample) {
+        self.model.update(&example.strategy, &example.features, example.reward);
+        self.examples.push(example);
+    }
+
+    /// Pay `reward` to every decision of the finished game; returns how many there were
+    pub fn finish(&mut self, reward: f64) -> usize {
+        let timestamp = now();
+        let pending = std::mem::take(&mut self.pending);
+        let count = pending.len();
+        for decision in pending {
+            self.record(LearningExample {
+                features: decision.features,
+                stage: decision.stage,
+                strategy: decision.strategy,
+                reward,
+                timestamp,
+            });
+        }
+        self.stage = None;
+        self.active = None;
+        count
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_hooks::MemoryState;
+    use crate::test_fixtures::{building, unit};
+
+    #[test]
+    fn test_features_count_only_own_units_and_buildings() {
+        let state = HeadlessGameState {
+            player: 1,
+            units: vec![unit("Peasant", 1), unit("Footman", 1), unit("Footman", 0), unit("Archer", 0)],
+            buildings: vec![building("TownHall", 1), building("TownHall", 0), building("GoldMine", 15)],
+            ..HeadlessGameState::default()
+        };
+
+        let features = GameFeatures::from_game_state(&state, &ThreatLevel::None);
+        assert_eq!((features.workers, features.melee, features.ranged, features.buildings), (1, 1, 0, 1));
+    }
+
+    #[test]
+    fn test_draw_rewards_pending_decisions() {
+        let mut learner = StrategyLearner::default();
+        let features = GameFeatures::default();
+        assert!(learner.decide(&features, GameStage::Opening).is_some());
+        assert!(learner.decide(&features, GameStage::Opening).is_none());
+        assert!(learner.has_pending());
+
+        assert_eq!(learner.finish(DRAW_REWARD), 1);
+        assert!(!learner.has_pending() && learner.active.is_none());
+        assert_eq!(learner.examples[0].reward, DRAW_REWARD);
+    }
+
+    #[test]
+    fn test_live_state_uses_elapsed_game_time() {
+        // Live memory states are stamped with wall-clock epoch milliseconds
+        let started = 1_760_000_000_000;
+        let mut memory = MemoryState { game_phase: "InGame".to_string(), timestamp: started, ..MemoryState::default() };
+        let mut state = HeadlessGameState::default();
+        state.update_from_memory_state(&memory);
+
+        memory.timestamp = started + 3 * 60 * 1000;
+        state.update_from_memory_state(&memory);
+        let features = GameFeatures::from_game_state(&state, &ThreatLevel::None);
+        assert!((features.minutes - 3.0).abs() < 1e-9);
+        assert_eq!(GameStage::of(&state), GameStage::Opening);
+
+        memory.timestamp = started + 8 * 60 * 1000;
+        state.update_from_memory_state(&memory);
+        assert_eq!(GameStage::of(&state), GameStage::Midgame);
+
+        // Leaving for the menu starts the clock over
+        memory.game_phase = "MainMenu".to_string();
+        state.update_from_memory_state(&memory);
+        assert_eq!(state.game_time, 0);
+    }
+
+    #[test]
+    fn test_learner_survives_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("learning").join("bandit.json");
+        assert!(StrategyLearner::load(&path).unwrap().examples.is_empty());
+
+        let mut learner = StrategyLearner::default();
+        let features = GameFeatures { minutes: 2.0, gold: 800, workers: 6, ..GameFeatures::default() };
+        let strategy = learner.decide(&features, GameStage::Opening).unwrap();
+        learner.finish(1.0);
+        learner.save(&path).unwrap();
+
+        let loaded = StrategyLearner::load(&path).unwrap();
+        assert_eq!(loaded.examples.len(), 1);
+        assert_eq!(loaded.examples[0].strategy, strategy);
+        let x = features.to_vector();
+        let arm = |learner: &StrategyLearner| learner.model.arms.iter()
+            .find(|arm| arm.strategy == strategy)
+            .map(|arm| (arm.pulls, arm.estimate(&x).0))
+            .unwrap();
+        let (saved, restored) = (arm(&learner), arm(&loaded));
+        assert_eq!(saved.0, restored.0);
+        assert!((saved.1 - restored.1).abs() < 1e-9);
+        assert_eq!(loaded.model.greedy(&x), learner.model.greedy(&x));
+    }
+}
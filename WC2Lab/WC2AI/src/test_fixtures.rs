@@ -0,0 +1,59 @@
+//! Values shared by the unit tests of several modules
+
+use crate::ai_controller::{AIDecisionContext, AIStrategy, ResourceScarcity, ThreatLevel};
+use crate::decision_trace::DecisionTrace;
+use crate::game_engine::{BuildingInfo, GamePhase, PlayerResources, UnitInfo};
+
+/// A unit of `owner` with no position, order or health to speak of
+pub fn unit(unit_type: &str, owner: u8) -> UnitInfo {
+    UnitInfo {
+        id: 0,
+        unit_type: unit_type.to_string(),
+        position: (0, 0),
+        health: 1,
+        max_health: 1,
+        owner,
+        is_selected: false,
+        current_action: None,
+    }
+}
+
+/// A completed, idle building of `owner`
+pub fn building(building_type: &str, owner: u8) -> BuildingInfo {
+    BuildingInfo {
+        id: 0,
+        building_type: building_type.to_string(),
+        position: (0, 0),
+        health: 1,
+        max_health: 1,
+        owner,
+        is_completed: true,
+        current_production: None,
+    }
+}
+
+/// Decision of player 0 at `game_time` ticks without any candidates
+pub fn decision_trace(game_time: u64) -> DecisionTrace {
+    DecisionTrace {
+        game_time,
+        timestamp: 0,
+        player: 0,
+        strategy: AIStrategy::Balanced,
+        context: AIDecisionContext {
+            current_phase: GamePhase::InGame,
+            resources: PlayerResources::default(),
+            unit_count: 0,
+            building_count: 0,
+            threat_level: ThreatLevel::None,
+            resource_scarcity: ResourceScarcity::Normal,
+            game_time,
+            map_size: (32, 32),
+            workers: 0,
+            idle_workers: 0,
+            home: None,
+            enemy_positions: Vec::new(),
+            occupied: Vec::new(),
+        },
+        candidates: Vec::new(),
+    }
+}
@@ -0,0 +1,558 @@
+//! Self-play tournaments between AI configurations
+//!
+//! Every pair of bots plays a number of seeded games on each map of the pool,
+//! with sides swapped between games. Games run in parallel on the simulator
+//! backend; results are rated afterwards in game order (sequential Elo, and
+//! Glicko with one rating period per round robin) so the report does not
+//! depend on scheduling.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use log::{info, debug};
+use serde::{Serialize, Deserialize};
+use tokio::sync::Semaphore;
+
+use crate::ai_controller::{AIController, AIPersonality, AIStrategy};
+use crate::game_backend::GameBackend;
+use crate::simulator::{
+    building_stats, unit_stats, EntityKind, SimConfig, SimMap, SimPlayerStats, Simulation, SimulatorBackend,
+    TICKS_PER_SECOND,
+};
+
+/// One tournament entrant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BotConfig {
+    pub name: String,
+    pub strategy: AIStrategy,
+    /// Personality override; the strategy's own personality when absent
+    pub personality: Option<AIPersonality>,
+}
+
+impl BotConfig {
+    pub fn new(strategy: AIStrategy) -> Self {
+        let name = match &strategy {
+            AIStrategy::Scripted(script) => script.clone(),
+            _ => format!("{:?}", strategy),
+        };
+        Self {
+            name,
+            strategy,
+            personality: None,
+        }
+    }
+
+    /// Every combination of strategies and named personalities
+    pub fn grid(strategies: &[AIStrategy], personalities: &[(String, AIPersonality)]) -> Vec<Self> {
+        strategies.iter()
+            .flat_map(|strategy| personalities.iter().map(move |(name, personality)| Self {
+                name: format!("{:?}/{}", strategy, name),
+                strategy: strategy.clone(),
+                personality: Some(personality.clone()),
+            }))
+            .collect()
+    }
+
+    async fn controller(&self, script_dir: Option<&PathBuf>) -> Result<AIController> {
+        let personality = self.personality.clone().unwrap_or_default();
+        let mut controller = AIController::with_profile(self.strategy.clone(), personality).await?;
+        if self.personality.is_none() {
+            controller.update_personality().await;
+        }
+        if let AIStrategy::Scripted(name) = &self.strategy {
+            let dir = script_dir.ok_or_else(|| anyhow!("Bot {} runs script {} but no script_dir is set", self.name, name))?;
+            controller.load_scripts(dir).await?;
+        }
+        Ok(controller)
+    }
+}
+
+/// A map of the pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentMap {
+    Pud { path: PathBuf },
+    OpenField { size: u32 },
+}
+
+impl TournamentMap {
+    pub fn name(&self) -> String {
+        match self {
+            TournamentMap::Pud { path } => path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            TournamentMap::OpenField { size } => format!("Open Field {}x{}", size, size),
+        }
+    }
+
+    fn load(&self) -> Result<SimMap> {
+        match self {
+            TournamentMap::Pud { path } => SimMap::from_pud(path),
+            TournamentMap::OpenField { size } => Ok(SimMap::open_field(*size, *size, 2)),
+        }
+    }
+}
+
+/// Tournament settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TournamentConfig {
+    pub bots: Vec<BotConfig>,
+    pub maps: Vec<TournamentMap>,
+    /// Games per pair of bots on each map
+    pub games_per_pair: u32,
+    pub base_seed: u64,
+    /// Games still running after this many ticks are draws, unless adjudicated
+    pub max_ticks: u64,
+    /// Decide timed-out games by material: cost of everything still standing plus the stockpile
+    pub adjudicate_timeouts: bool,
+    /// Simulation ticks between two decisions of each bot
+    pub decision_ticks: u32,
+    /// Games played at the same time
+    pub parallelism: usize,
+    pub elo_k: f64,
+    /// Glicko constant c: a rating deviation grows to `sqrt(RD² + c²)` at the start of each rating period
+    pub glicko_c: f64,
+    /// Directory of the Rhai scripts behind `AIStrategy::Scripted` bots
+    pub script_dir: Option<PathBuf>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        let strategies = [
+            AIStrategy::Aggressive,
+            AIStrategy::Defensive,
+            AIStrategy::Balanced,
+            AIStrategy::Economic,
+            AIStrategy::Rush,
+            AIStrategy::Turtle,
+        ];
+        Self {
+            bots: strategies.into_iter().map(BotConfig::new).collect(),
+            maps: vec![TournamentMap::OpenField { size: 64 }],
+            games_per_pair: 4,
+            base_seed: 0,
+            max_ticks: 30 * 60 * TICKS_PER_SECOND as u64,
+            adjudicate_timeouts: false,
+            decision_ticks: TICKS_PER_SECOND,
+            parallelism: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4),
+            elo_k: 32.0,
+            // An RD of 50 is back to the initial 350 after 100 periods
+            glicko_c: 34.6,
+            script_dir: None,
+        }
+    }
+}
+
+/// Result of a single game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub index: usize,
+    /// Round robin this game belongs to; every pair meets once per round
+    pub round: usize,
+    pub map: String,
+    pub seed: u64,
+    /// Bot indices playing as player 0 and player 1
+    pub bots: [usize; 2],
+    /// Index of the winning bot; none for a draw
+    pub winner: Option<usize>,
+    /// Whether the winner was decided on material at the time limit
+    pub adjudicated: bool,
+    /// Material of player 0 and player 1 at the end
+    pub material: [u32; 2],
+    pub ticks: u64,
+    pub stats: [SimPlayerStats; 2],
+}
+
+/// Elo and Glicko ratings of a bot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BotRating {
+    pub name: String,
+    pub elo: f64,
+    pub glicko: f64,
+    pub glicko_rd: f64,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// Everything a tournament produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentReport {
+    pub bots: Vec<String>,
+    pub games: Vec<GameRecord>,
+    /// Sorted by Elo, best first
+    pub ratings: Vec<BotRating>,
+    /// `win_matrix[i][j]`: games bot i won against bot j
+    pub win_matrix: Vec<Vec<u32>>,
+    /// `games_matrix[i][j]`: games played between bot i and bot j
+    pub games_matrix: Vec<Vec<u32>>,
+    pub average_game_ticks: f64,
+}
+
+/// Game to play: bots for player 0 and 1, map and seed
+#[derive(Debug, Clone)]
+struct Fixture {
+    index: usize,
+    round: usize,
+    bots: [usize; 2],
+    map: usize,
+    seed: u64,
+}
+
+/// Play the whole tournament
+pub async fn run_tournament(config: TournamentConfig) -> Result<TournamentReport> {
+    if config.bots.len() < 2 {
+        return Err(anyhow!("A tournament needs at least two bots"));
+    }
+    if config.maps.is_empty() {
+        return Err(anyhow!("A tournament needs at least one map"));
+    }
+
+    // Load every map once up front so a broken PUD fails fast
+    let maps: Vec<Arc<SimMap>> = config.maps.iter()
+        .map(|map| map.load().map(Arc::new))
+        .collect::<Result<_>>()?;
+
+    let mut fixtures = Vec::new();
+    for first in 0..config.bots.len() {
+        for second in first + 1..config.bots.len() {
+            for map in 0..maps.len() {
+                for game in 0..config.games_per_pair {
+                    let bots = if game % 2 == 0 { [first, second] } else { [second, first] };
+                    fixtures.push(Fixture {
+                        index: fixtures.len(),
+                        round: map * config.games_per_pair as usize + game as usize,
+                        bots,
+                        map,
+                        seed: config.base_seed.wrapping_add(game as u64),
+                    });
+                }
+            }
+        }
+    }
+
+    info!("🏆 Starting tournament: {} bots, {} maps, {} games", config.bots.len(), maps.len(), fixtures.len());
+    let started = Instant::now();
+
+    let config = Arc::new(config);
+    let semaphore = Arc::new(Semaphore::new(config.parallelism.max(1)));
+    let mut handles = Vec::new();
+    for fixture in fixtures {
+        let config = config.clone();
+        let map = maps[fixture.map].clone();
+        let semaphore = semaphore.clone();
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            // Games are CPU bound, so they run on the blocking pool instead of a runtime worker
+            let runtime = tokio::runtime::Handle::current();
+            tokio::task::spawn_blocking(move || runtime.block_on(play_game(&config, &fixture, &map))).await?
+        }));
+    }
+
+    let mut games = Vec::new();
+    for handle in handles {
+        games.push(handle.await??);
+    }
+    // Round by round, so sequential Elo sees every pair before any pair meets again
+    games.sort_by_key(|game| (game.round, game.index));
+
+    info!("✅ Tournament finished: {} games in {:.1}s", games.len(), started.elapsed().as_secs_f64());
+    Ok(build_report(&config, games))
+}
+
+async fn play_game(config: &TournamentConfig, fixture: &Fixture, map: &SimMap) -> Result<GameRecord> {
+    let sim_config = SimConfig {
+        seed: fixture.seed,
+        player: 0,
+        players: vec![0, 1],
+        max_ticks: config.max_ticks,
+        ..SimConfig::default()
+    };
+    let backend = Arc::new(SimulatorBackend::new(sim_config, map.clone()));
+    let seats = [backend.seat(0), backend.seat(1)];
+    let controllers = [
+        config.bots[fixture.bots[0]].controller(config.script_dir.as_ref()).await?,
+        config.bots[fixture.bots[1]].controller(config.script_dir.as_ref()).await?,
+    ];
+
+    let turn = Duration::from_millis(config.decision_ticks.max(1) as u64 * 1000 / TICKS_PER_SECOND as u64);
+    while !backend.simulation().lock().await.is_over() {
+        for (controller, seat) in controllers.iter().zip(&seats) {
+            controller.play_turn(seat).await?;
+        }
+        backend.advance(turn).await?;
+    }
+
+    let simulation = backend.simulation().lock().await;
+    let material = [material(&simulation, 0), material(&simulation, 1)];
+    let mut winner = simulation.winner().map(|player| fixture.bots[player as usize]);
+    let adjudicated = winner.is_none() && config.adjudicate_timeouts && material[0] != material[1];
+    if adjudicated {
+        winner = Some(fixture.bots[if material[0] > material[1] { 0 } else { 1 }]);
+    }
+    let stats = [0u8, 1].map(|player| {
+        simulation.players.get(&player).map(|p| p.stats.clone()).unwrap_or_default()
+    });
+
+    debug!("🏆 Game {} on {}: {} vs {} -> {:?} after {} ticks",
+           fixture.index, map.name, config.bots[fixture.bots[0]].name, config.bots[fixture.bots[1]].name,
+           winner.map(|bot| &config.bots[bot].name), simulation.tick);
+
+    Ok(GameRecord {
+        index: fixture.index,
+        round: fixture.round,
+        map: config.maps[fixture.map].name(),
+        seed: fixture.seed,
+        bots: fixture.bots,
+        winner,
+        adjudicated,
+        material,
+        ticks: simulation.tick,
+        stats,
+    })
+}
+
+/// Gold and wood value of a player's units, buildings and stockpile
+fn material(simulation: &Simulation, player: u8) -> u32 {
+    let standing: u32 = simulation.entities.values()
+        .filter(|entity| entity.owner == player)
+        .map(|entity| match entity.kind {
+            EntityKind::Unit(unit) => {
+                let stats = unit_stats(&simulation.rules, unit);
+                stats.gold + stats.wood
+            }
+            EntityKind::Building(building) => {
+                let stats = building_stats(&simulation.rules, building);
+                stats.gold + stats.wood
+            }
+        })
+        .sum();
+    let stockpile = simulation.players.get(&player).map(|p| p.gold + p.wood).unwrap_or(0);
+    standing + stockpile
+}
+
+/// Score of a game from one bot's side: 1 win, 0.5 draw, 0 loss
+fn score(game: &GameRecord, bot: usize) -> f64 {
+    match game.winner {
+        Some(winner) if winner == bot => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+fn build_report(config: &TournamentConfig, games: Vec<GameRecord>) -> TournamentReport {
+    let count = config.bots.len();
+    let mut win_matrix = vec![vec![0u32; count]; count];
+    let mut games_matrix = vec![vec![0u32; count]; count];
+    let mut records = vec![(0u32, 0u32, 0u32); count];
+
+    // Elo, updated game by game
+    let mut elo = vec![1500.0f64; count];
+    for game in &games {
+        let [a, b] = game.bots;
+        let expected = 1.0 / (1.0 + 10f64.powf((elo[b] - elo[a]) / 400.0));
+        let delta = config.elo_k * (score(game, a) - expected);
+        elo[a] += delta;
+        elo[b] -= delta;
+
+        games_matrix[a][b] += 1;
+        games_matrix[b][a] += 1;
+        match game.winner {
+            Some(winner) => {
+                let loser = if winner == a { b } else { a };
+                win_matrix[winner][loser] += 1;
+                records[winner].0 += 1;
+                records[loser].2 += 1;
+            }
+            None => {
+                records[a].1 += 1;
+                records[b].1 += 1;
+            }
+        }
+    }
+
+    let glicko = glicko_ratings(count, &games, config.glicko_c);
+    let mut ratings: Vec<BotRating> = (0..count)
+        .map(|bot| BotRating {
+            name: config.bots[bot].name.clone(),
+            elo: elo[bot],
+            glicko: glicko[bot].0,
+            glicko_rd: glicko[bot].1,
+            wins: records[bot].0,
+            draws: records[bot].1,
+            losses: records[bot].2,
+        })
+        .collect();
+    ratings.sort_by(|a, b| b.elo.partial_cmp(&a.elo).unwrap_or(std::cmp::Ordering::Equal));
+
+    let average_game_ticks = if games.is_empty() {
+        0.0
+    } else {
+        games.iter().map(|game| game.ticks as f64).sum::<f64>() / games.len() as f64
+    };
+
+    TournamentReport {
+        bots: config.bots.iter().map(|bot| bot.name.clone()).collect(),
+        games,
+        ratings,
+        win_matrix,
+        games_matrix,
+        average_game_ticks,
+    }
+}
+
+/// Rating deviation of a bot without games, and the most it grows back to
+const INITIAL_RD: f64 = 350.0;
+
+/// Glicko-1 ratings and deviations, one rating period per round robin
+fn glicko_ratings(count: usize, games: &[GameRecord], c: f64) -> Vec<(f64, f64)> {
+    let q = 10f64.ln() / 400.0;
+    let g = |rd: f64| 1.0 / (1.0 + 3.0 * q * q * rd * rd / (std::f64::consts::PI * std::f64::consts::PI)).sqrt();
+
+    let mut ratings = vec![(1500.0f64, INITIAL_RD); count];
+    let rounds = games.iter().map(|game| game.round + 1).max().unwrap_or(0);
+    for round in 0..rounds {
+        let round_games: Vec<&GameRecord> = games.iter().filter(|game| game.round == round).collect();
+        ratings = (0..count)
+            .map(|bot| {
+                let (rating, rd) = ratings[bot];
+                // Uncertainty grows with the time since the last period
+                let rd = (rd * rd + c * c).sqrt().min(INITIAL_RD);
+                let mut d_inverse = 0.0;
+                let mut improvement = 0.0;
+                for game in round_games.iter().filter(|game| game.bots.contains(&bot)) {
+                    let opponent = if game.bots[0] == bot { game.bots[1] } else { game.bots[0] };
+                    let (opponent_rating, opponent_rd) = ratings[opponent];
+                    let g_opponent = g(opponent_rd);
+                    let expected = 1.0 / (1.0 + 10f64.powf(-g_opponent * (rating - opponent_rating) / 400.0));
+                    d_inverse += q * q * g_opponent * g_opponent * expected * (1.0 - expected);
+                    improvement += g_opponent * (score(game, bot) - expected);
+                }
+                if d_inverse == 0.0 {
+                    return (rating, rd);
+                }
+
+                let precision = 1.0 / (rd * rd) + d_inverse;
+                (rating + q / precision * improvement, (1.0 / precision).sqrt())
+            })
+            .collect();
+    }
+    ratings
+}
+
+impl TournamentReport {
+    /// Ratings table and win matrix as plain text
+    pub fn to_table(&self) -> String {
+        let width = self.bots.iter().map(|name| name.len()).max().unwrap_or(4).max(4);
+        let mut out = String::new();
+
+        out.push_str(&format!("{:<4} {:<width$} {:>7} {:>7} {:>5} {:>9}\n", "Rank", "Bot", "Elo", "Glicko", "RD", "W-D-L", width = width));
+        for (rank, rating) in self.ratings.iter().enumerate() {
+            out.push_str(&format!(
+                "{:<4} {:<width$} {:>7.0} {:>7.0} {:>5.0} {:>9}\n",
+                rank + 1,
+                rating.name,
+                rating.elo,
+                rating.glicko,
+                rating.glicko_rd,
+                format!("{}-{}-{}", rating.wins, rating.draws, rating.losses),
+                width = width,
+            ));
+        }
+
+        out.push_str("\nWin rate (row vs column, draws count half)\n");
+        out.push_str(&format!("{:<width$}", "", width = width));
+        for column in 0..self.bots.len() {
+            out.push_str(&format!(" {:>6}", format!("#{}", column + 1)));
+        }
+        out.push('\n');
+        for (row, name) in self.bots.iter().enumerate() {
+            out.push_str(&format!("{:<width$}", name, width = width));
+            for column in 0..self.bots.len() {
+                let played = self.games_matrix[row][column];
+                if row == column || played == 0 {
+                    out.push_str(&format!(" {:>6}", "-"));
+                } else {
+                    let wins = self.win_matrix[row][column];
+                    let draws = played - wins - self.win_matrix[column][row];
+                    let rate = (wins as f64 + draws as f64 / 2.0) / played as f64;
+                    out.push_str(&format!(" {:>5.0}%", rate * 100.0));
+                }
+            }
+            out.push('\n');
+        }
+        for (column, name) in self.bots.iter().enumerate() {
+            out.push_str(&format!("#{} = {}\n", column + 1, name));
+        }
+        out.push_str(&format!("\n{} games, average length {:.0} ticks\n", self.games.len(), self.average_game_ticks));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(round: usize, bots: [usize; 2], winner: Option<usize>) -> GameRecord {
+        GameRecord {
+            index: 0,
+            round,
+            map: String::new(),
+            seed: 0,
+            bots,
+            winner,
+            adjudicated: false,
+            material: [0, 0],
+            ticks: 0,
+            stats: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_glicko_deviation_grows_between_periods() {
+        let first = vec![game(0, [0, 1], Some(0)), game(0, [1, 2], Some(1))];
+        let after_first = glicko_ratings(3, &first, 34.6);
+
+        // Bot 2 sits the second period out, so only its uncertainty changes
+        let mut both = first.clone();
+        both.push(game(1, [0, 1], None));
+        let after_second = glicko_ratings(3, &both, 34.6);
+        assert_eq!(after_second[2].0, after_first[2].0);
+        assert!((after_second[2].1 - (after_first[2].1.powi(2) + 34.6f64.powi(2)).sqrt()).abs() < 1e-9);
+
+        // Without inflation an idle bot keeps its deviation
+        let idle = glicko_ratings(3, &both, 0.0);
+        assert_eq!(idle[2].1, glicko_ratings(3, &first, 0.0)[2].1);
+    }
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config: TournamentConfig = serde_json::from_str(r#"{"games_per_pair": 2}"#).unwrap();
+        assert_eq!(config.games_per_pair, 2);
+        assert_eq!(config.bots.len(), TournamentConfig::default().bots.len());
+        assert_eq!(config.elo_k, 32.0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rush_beats_balanced_on_open_field() {
+        let limit = 30 * 60 * TICKS_PER_SECOND as u64;
+        let config = TournamentConfig {
+            bots: vec![BotConfig::new(AIStrategy::Rush), BotConfig::new(AIStrategy::Balanced)],
+            games_per_pair: 2,
+            max_ticks: limit,
+            parallelism: 2,
+            ..TournamentConfig::default()
+        };
+        let report = run_tournament(config).await.unwrap();
+
+        // One game from each side, both decided outright before the time limit
+        for game in &report.games {
+            assert_eq!(game.winner, Some(0), "{:?}", game);
+            assert!(!game.adjudicated && game.ticks < limit);
+            assert!(game.stats.iter().all(|stats| stats.gold_gathered > 0 && stats.buildings_completed > 0));
+        }
+        assert_eq!(report.ratings[0].name, "Rush");
+    }
+}
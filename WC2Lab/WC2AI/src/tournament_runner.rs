@@ -0,0 +1,29 @@
+use anyhow::Result;
+use log::info;
+
+use wc2_ai::tournament::{run_tournament, TournamentConfig};
+
+/// Self-play tournament runner
+///
+/// Usage: `wc2-tournament [config.json] [report.json]`
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let config = match args.next() {
+        Some(path) => serde_json::from_str::<TournamentConfig>(&std::fs::read_to_string(path)?)?,
+        None => TournamentConfig::default(),
+    };
+
+    let report = run_tournament(config).await?;
+    println!("{}", report.to_table());
+
+    if let Some(path) = args.next() {
+        std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        info!("💾 Tournament report saved to {}", path);
+    }
+
+    Ok(())
+}
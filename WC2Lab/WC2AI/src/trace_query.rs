@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+
+use wc2_ai::build_planner::{clock, parse_clock};
+use wc2_ai::decision_trace::{query, read_trace};
+
+/// Default number of seconds searched either side of the asked time
+const DEFAULT_WINDOW: u32 = 10;
+
+/// Answer questions about a decision trace
+///
+/// Traces are written only with `HeadlessConfig::enable_decision_trace` set, one
+/// `decision_trace_<date>_<time>.jsonl` per game in the export directory.
+///
+/// Usage:
+///   `wc2-why <decision_trace_*.jsonl> <command words> [m:ss] [window secs]`
+///     what happened to matching candidates, e.g. `wc2-why trace.jsonl "build tower" 5:12`
+///   `wc2-why <decision_trace_*.jsonl> @<m:ss>`
+///     every candidate of the decision closest to that time
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let usage = || anyhow!("Usage: wc2-why <decision_trace_*.jsonl> (<command words> [m:ss] [window] | @<m:ss>)");
+    let path = PathBuf::from(args.next().ok_or_else(usage)?);
+    let question = args.next().ok_or_else(usage)?;
+    let traces = read_trace(&path)?;
+
+    if let Some(at) = question.strip_prefix('@') {
+        let at = parse_clock(at)?;
+        let trace = traces.iter()
+            .min_by_key(|trace| trace.seconds().abs_diff(at))
+            .ok_or_else(|| anyhow!("{} holds no decisions", path.display()))?;
+
+        let resources = &trace.context.resources;
+        println!("Decision at {} (player {}, {:?}, threat {:?})",
+                 clock(trace.seconds()), trace.player, trace.strategy, trace.context.threat_level);
+        println!("Bank {}g/{}w/{}o, food {}/{}",
+                 resources.gold, resources.wood, resources.oil, resources.food_current, resources.food_max);
+        for candidate in &trace.candidates {
+            let verdict = candidate.rejected.as_ref()
+                .map(|rejection| format!("rejected, {}", rejection))
+                .unwrap_or_else(|| "chosen".to_string());
+            println!("  {:<24} {:?} priority {:.2} score {:.2}: {} ({})",
+                     candidate.command, candidate.source, candidate.priority, candidate.score, verdict, candidate.reasoning);
+        }
+        return Ok(());
+    }
+
+    let at = args.next().map(|at| parse_clock(&at)).transpose()?;
+    let window = args.next().map(|window| window.parse()).transpose()?.unwrap_or(DEFAULT_WINDOW);
+    print!("{}", query(&traces, &question, at, window));
+    Ok(())
+}
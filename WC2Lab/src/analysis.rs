@@ -1,11 +1,12 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use log::{info, warn, error, debug};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use chrono::{DateTime, Utc};
 
-use crate::game_state::GameState;
-use crate::events::GameEvent;
+use crate::game_state::{GameState, ResourceInfo};
+use crate::events::{EventData, EventType, GameEvent};
+use crate::utils::{correlation_coefficient, find_peaks, moving_average, standard_deviation};
 
 /// Analysis result from the laboratory
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -268,6 +269,9 @@ pub struct EventSequence {
     pub average_duration: f64,
     /// Player correlation
     pub player_correlation: HashMap<u8, f64>,
+    /// Confidence that the sequence is more than chance
+    #[serde(default)]
+    pub confidence: f64,
 }
 
 /// Timing pattern
@@ -283,6 +287,9 @@ pub struct TimingPattern {
     pub average_interval: f64,
     /// Standard deviation
     pub standard_deviation: f64,
+    /// Confidence in the periodicity
+    #[serde(default)]
+    pub confidence: f64,
 }
 
 /// Player behavior pattern
@@ -298,6 +305,9 @@ pub struct PlayerPattern {
     pub frequency: f64,
     /// Success rate
     pub success_rate: f64,
+    /// Confidence from the number of observations
+    #[serde(default)]
+    pub confidence: f64,
 }
 
 /// Performance analysis
@@ -472,80 +482,550 @@ pub struct PatternStage {
     pub characteristics: HashMap<String, serde_json::Value>,
 }
 
-/// Analyze collected data
-pub async fn analyze_collected_data() -> Result<Vec<AnalysisResult>> {
-    info!("🔍 Starting data analysis...");
-    
-    let mut results = Vec::new();
-    
-    // For now, create sample analysis results
-    // In a real implementation, this would analyze actual collected data
-    
-    let memory_analysis = AnalysisResult {
-        id: "mem_001".to_string(),
-        timestamp: Utc::now(),
-        analysis_type: AnalysisType::MemoryStructure,
-        results: AnalysisData::MemoryStructure(MemoryStructureAnalysis {
-            data_structures: vec![
-                DataStructure {
-                    name: "GameState".to_string(),
-                    address: 0x10000000,
-                    size: 1024,
-                    fields: vec![
-                        StructureField {
-                            name: "game_phase".to_string(),
-                            offset: 0,
-                            size: 4,
-                            field_type: FieldType::Integer32,
-                            value: Some(serde_json::json!(1)),
-                        },
-                        StructureField {
-                            name: "game_time".to_string(),
-                            offset: 4,
-                            size: 4,
-                            field_type: FieldType::Integer32,
-                            value: Some(serde_json::json!(0)),
-                        },
-                    ],
-                    confidence: 0.85,
-                },
-            ],
-            memory_layouts: vec![],
-            pointer_relationships: vec![],
-            data_types: vec![],
-        }),
-        confidence: 0.85,
-        metadata: HashMap::new(),
-    };
-    
-    results.push(memory_analysis);
-    
-    let pattern_analysis = AnalysisResult {
-        id: "pat_001".to_string(),
-        timestamp: Utc::now(),
-        analysis_type: AnalysisType::PatternRecognition,
-        results: AnalysisData::PatternRecognition(PatternRecognitionAnalysis {
-            patterns: vec![
-                RecognizedPattern {
-                    name: "UnitMovement".to_string(),
-                    pattern_type: "Behavior".to_string(),
-                    description: "Regular unit movement patterns".to_string(),
-                    signature: vec![0x55, 0x4E, 0x49, 0x54], // "UNIT"
-                    occurrence_count: 150,
-                    first_occurrence: Utc::now(),
-                    last_occurrence: Utc::now(),
-                },
-            ],
-            confidence_scores: HashMap::new(),
-            pattern_evolution: vec![],
-        }),
-        confidence: 0.90,
-        metadata: HashMap::new(),
-    };
-    
-    results.push(pattern_analysis);
-    
+/// Observations at which a finding is trusted halfway
+const HALF_CONFIDENCE_SAMPLES: f64 = 10.0;
+
+/// Settings for the session analysis pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAnalysisConfig {
+    /// Longest run of event types counted as a sequence
+    pub max_sequence_length: usize,
+    /// Occurrences a sequence needs before it is reported
+    pub min_sequence_support: u64,
+    /// Sequences kept, most confident first
+    pub max_sequences: usize,
+    /// Width of the event rate bins in seconds
+    pub bin_seconds: f64,
+    /// Moving average window applied before searching periods and correlations
+    pub smoothing_window: usize,
+    /// Weakest correlation reported, also the autocorrelation a period needs
+    pub min_correlation: f64,
+    /// Longest period searched for, which bounds the autocorrelation lags
+    pub max_period_seconds: f64,
+}
+
+impl Default for SessionAnalysisConfig {
+    fn default() -> Self {
+        Self {
+            max_sequence_length: 4,
+            min_sequence_support: 3,
+            max_sequences: 20,
+            bin_seconds: 1.0,
+            smoothing_window: 3,
+            min_correlation: 0.5,
+            max_period_seconds: 600.0,
+        }
+    }
+}
+
+/// Finds sequences, periodicities, player habits and correlated variables in a recorded session
+pub struct SessionAnalyzer {
+    config: SessionAnalysisConfig,
+}
+
+/// What one player did over a session
+#[derive(Default)]
+struct PlayerTally {
+    gather_events: usize,
+    gathered: u64,
+    spent: u64,
+    produced: HashSet<(bool, u32)>,
+    lost: HashSet<(bool, u32)>,
+    research_started: usize,
+    research_completed: usize,
+}
+
+impl SessionAnalyzer {
+    /// Fails when the rate bins are not a positive width or the smoothing window is empty
+    pub fn new(config: SessionAnalysisConfig) -> Result<Self> {
+        if !(config.bin_seconds.is_finite() && config.bin_seconds > 0.0) {
+            bail!("Event rate bins must be a positive number of seconds, not {}", config.bin_seconds);
+        }
+        if config.smoothing_window == 0 {
+            bail!("The smoothing window must cover at least one bin");
+        }
+        Ok(Self { config })
+    }
+
+    /// Analyze the events of a session and the game states they were synthesized from
+    pub fn analyze(&self, events: &[GameEvent], states: &[GameState]) -> Result<Vec<AnalysisResult>> {
+        let mut events: Vec<&GameEvent> = events.iter().collect();
+        events.sort_by_key(|event| (event.timestamp, event.id));
+
+        let mut results = Vec::new();
+        if let (Some(first), Some(last)) = (events.first(), events.last()) {
+            let duration = seconds_between(first.timestamp, last.timestamp);
+            let analysis = EventSequenceAnalysis {
+                common_sequences: self.common_sequences(&events),
+                timing_patterns: self.timing_patterns(&events)?,
+                player_patterns: self.player_patterns(&events, duration),
+            };
+            let confidences: Vec<f64> = analysis.common_sequences.iter().map(|sequence| sequence.confidence)
+                .chain(analysis.timing_patterns.iter().map(|pattern| pattern.confidence))
+                .chain(analysis.player_patterns.iter().map(|pattern| pattern.confidence))
+                .collect();
+
+            let mut metadata = HashMap::new();
+            metadata.insert("events".to_string(), serde_json::json!(events.len()));
+            metadata.insert("duration_seconds".to_string(), serde_json::json!(duration));
+            results.push(AnalysisResult {
+                id: "seq_001".to_string(),
+                timestamp: Utc::now(),
+                analysis_type: AnalysisType::EventSequences,
+                results: AnalysisData::EventSequences(analysis),
+                confidence: mean(&confidences),
+                metadata,
+            });
+        }
+
+        if states.len() > 3 {
+            let variable_correlations = self.variable_correlations(states)?;
+            let significances: Vec<f64> = variable_correlations.iter().map(|correlation| correlation.significance).collect();
+
+            let mut metadata = HashMap::new();
+            metadata.insert("states".to_string(), serde_json::json!(states.len()));
+            results.push(AnalysisResult {
+                id: "cor_001".to_string(),
+                timestamp: Utc::now(),
+                analysis_type: AnalysisType::DataCorrelation,
+                results: AnalysisData::DataCorrelation(DataCorrelationAnalysis {
+                    variable_correlations,
+                    event_correlations: Vec::new(),
+                    state_correlations: Vec::new(),
+                }),
+                confidence: mean(&significances),
+                metadata,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Runs of event types that occur more often than their types' frequencies predict
+    fn common_sequences(&self, events: &[&GameEvent]) -> Vec<EventSequence> {
+        let names: Vec<String> = events.iter().map(|event| type_name(&event.event_type)).collect();
+        let mut type_counts: HashMap<&str, f64> = HashMap::new();
+        for name in &names {
+            *type_counts.entry(name.as_str()).or_default() += 1.0;
+        }
+
+        let mut sequences = Vec::new();
+        for length in 2..=self.config.max_sequence_length {
+            if names.len() < length {
+                break;
+            }
+            let windows = names.len() - length + 1;
+            let mut occurrences: HashMap<&[String], Vec<usize>> = HashMap::new();
+            for start in 0..windows {
+                occurrences.entry(&names[start..start + length]).or_default().push(start);
+            }
+
+            for (gram, starts) in occurrences {
+                let frequency = starts.len() as u64;
+                if frequency < self.config.min_sequence_support {
+                    continue;
+                }
+                let expected = windows as f64 * gram.iter()
+                    .map(|name| type_counts[name.as_str()] / names.len() as f64)
+                    .product::<f64>();
+                let lift = frequency as f64 / expected;
+                if lift <= 1.0 {
+                    continue;
+                }
+
+                let durations: Vec<f64> = starts.iter()
+                    .map(|&start| seconds_between(events[start].timestamp, events[start + length - 1].timestamp))
+                    .collect();
+                // A run belongs to a player only when every event in it is theirs
+                let mut player_runs: HashMap<u8, u64> = HashMap::new();
+                for &start in &starts {
+                    let run = &events[start..start + length];
+                    if let Some(player_id) = run[0].player_id.filter(|&player_id| run.iter().all(|event| event.player_id == Some(player_id))) {
+                        *player_runs.entry(player_id).or_default() += 1;
+                    }
+                }
+
+                sequences.push(EventSequence {
+                    name: gram.join(" -> "),
+                    events: gram.to_vec(),
+                    frequency,
+                    average_duration: mean(&durations),
+                    player_correlation: player_runs.into_iter()
+                        .map(|(player_id, runs)| (player_id, runs as f64 / frequency as f64))
+                        .collect(),
+                    confidence: (1.0 - 1.0 / lift) * sample_confidence(starts.len()),
+                });
+            }
+        }
+
+        sequences.sort_by(|a, b| b.confidence.total_cmp(&a.confidence)
+            .then(b.frequency.cmp(&a.frequency))
+            .then(a.name.cmp(&b.name)));
+        sequences.truncate(self.config.max_sequences);
+        sequences
+    }
+
+    /// Event types whose rate repeats, found as peaks in the autocorrelation of the smoothed rate
+    fn timing_patterns(&self, events: &[&GameEvent]) -> Result<Vec<TimingPattern>> {
+        let (Some(first), Some(last)) = (events.first(), events.last()) else {
+            return Ok(Vec::new());
+        };
+        let bin_seconds = self.config.bin_seconds;
+        let bins = (seconds_between(first.timestamp, last.timestamp) / bin_seconds) as usize + 1;
+        let max_lag = (self.config.max_period_seconds / bin_seconds) as usize;
+
+        let mut rates: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        for event in events {
+            let bin = (seconds_between(first.timestamp, event.timestamp) / bin_seconds) as usize;
+            rates.entry(type_name(&event.event_type)).or_insert_with(|| vec![0.0; bins])[bin] += 1.0;
+        }
+
+        let mut patterns = Vec::new();
+        for (event_type, rate) in rates {
+            let smoothed = moving_average(&rate, self.config.smoothing_window);
+            let autocorrelation = (1..=max_lag.min(smoothed.len() / 2))
+                .map(|lag| correlation_coefficient(&smoothed[..smoothed.len() - lag], &smoothed[lag..]))
+                .collect::<Result<Vec<f64>>>()?;
+            if autocorrelation.len() < 3 {
+                continue;
+            }
+
+            let lags: Vec<f64> = find_peaks(&autocorrelation, self.config.min_correlation).iter()
+                .map(|&index| (index + 1) as f64)
+                .collect();
+            let Some(&period) = lags.first() else {
+                continue;
+            };
+            let spacing: Vec<f64> = std::iter::once(period)
+                .chain(lags.windows(2).map(|pair| pair[1] - pair[0]))
+                .map(|lag| lag * bin_seconds)
+                .collect();
+            let average_interval = mean(&spacing);
+            let cycles = (rate.len() as f64 / period) as usize;
+
+            patterns.push(TimingPattern {
+                name: format!("{} every {:.1}s", event_type, average_interval),
+                confidence: autocorrelation[period as usize - 1].clamp(0.0, 1.0) * sample_confidence(cycles),
+                event_type,
+                standard_deviation: standard_deviation(&spacing),
+                average_interval,
+                timing_distribution: autocorrelation,
+            });
+        }
+
+        Ok(patterns)
+    }
+
+    /// How each player gathered, produced and researched
+    fn player_patterns(&self, events: &[&GameEvent], duration: f64) -> Vec<PlayerPattern> {
+        let mut tallies: BTreeMap<u8, PlayerTally> = BTreeMap::new();
+        for event in events {
+            let Some(player_id) = event.player_id else {
+                continue;
+            };
+            let tally = tallies.entry(player_id).or_default();
+            match (&event.event_type, &event.data) {
+                (EventType::ResourceGathered, EventData::Resource { amount, .. }) => {
+                    tally.gather_events += 1;
+                    tally.gathered += *amount as u64;
+                }
+                (EventType::ResourceSpent, EventData::Resource { amount, .. }) => tally.spent += *amount as u64,
+                (EventType::UnitCreated | EventType::UnitTrained | EventType::BuildingCreated, data) => {
+                    tally.produced.extend(entity_key(data));
+                }
+                (EventType::UnitDestroyed | EventType::BuildingDestroyed, data) => tally.lost.extend(entity_key(data)),
+                (EventType::ResearchStarted, _) => tally.research_started += 1,
+                (EventType::ResearchCompleted, _) => tally.research_completed += 1,
+                _ => {}
+            }
+        }
+
+        let minutes = duration.max(self.config.bin_seconds) / 60.0;
+        let mut patterns = Vec::new();
+        for (player_id, tally) in tallies {
+            if tally.gather_events > 0 {
+                patterns.push(PlayerPattern {
+                    player_id,
+                    behavior_type: "Economy".to_string(),
+                    description: format!("Gathered {} and spent {} resources", tally.gathered, tally.spent),
+                    frequency: tally.gather_events as f64 / minutes,
+                    success_rate: ratio(tally.spent as f64, tally.gathered as f64),
+                    confidence: sample_confidence(tally.gather_events),
+                });
+            }
+            if !tally.produced.is_empty() {
+                let lost = tally.produced.intersection(&tally.lost).count();
+                patterns.push(PlayerPattern {
+                    player_id,
+                    behavior_type: "Production".to_string(),
+                    description: format!("Produced {} units and buildings, {} of them lost", tally.produced.len(), lost),
+                    frequency: tally.produced.len() as f64 / minutes,
+                    success_rate: ratio((tally.produced.len() - lost) as f64, tally.produced.len() as f64),
+                    confidence: sample_confidence(tally.produced.len()),
+                });
+            }
+            if tally.research_started > 0 {
+                patterns.push(PlayerPattern {
+                    player_id,
+                    behavior_type: "Research".to_string(),
+                    description: format!("Started {} researches, completed {}", tally.research_started, tally.research_completed),
+                    frequency: tally.research_started as f64 / minutes,
+                    success_rate: ratio(tally.research_completed as f64, tally.research_started as f64),
+                    confidence: sample_confidence(tally.research_started),
+                });
+            }
+        }
+        patterns
+    }
+
+    /// Correlations between the smoothed per-player resources and unit and building counts
+    fn variable_correlations(&self, states: &[GameState]) -> Result<Vec<VariableCorrelation>> {
+        let players: BTreeSet<u8> = states.iter()
+            .flat_map(|state| state.resources.keys().copied().chain(state.players.iter().map(|player| player.id)))
+            .collect();
+
+        let mut series: Vec<(String, Vec<f64>)> = Vec::new();
+        for player_id in players {
+            let resource = |amount: fn(&ResourceInfo) -> u32| -> Vec<f64> {
+                states.iter()
+                    .map(|state| state.resources.get(&player_id).map_or(0.0, |resources| amount(resources) as f64))
+                    .collect()
+            };
+            series.push((format!("player{}.gold", player_id), resource(|resources| resources.gold)));
+            series.push((format!("player{}.wood", player_id), resource(|resources| resources.wood)));
+            series.push((format!("player{}.ore", player_id), resource(|resources| resources.ore)));
+            series.push((format!("player{}.oil", player_id), resource(|resources| resources.oil)));
+            series.push((format!("player{}.units", player_id), states.iter()
+                .map(|state| state.units.iter().filter(|unit| unit.owner == player_id).count() as f64)
+                .collect()));
+            series.push((format!("player{}.buildings", player_id), states.iter()
+                .map(|state| state.buildings.iter().filter(|building| building.owner == player_id).count() as f64)
+                .collect()));
+        }
+
+        // Constant variables carry no information and would correlate as zero anyway
+        let smoothed: Vec<(String, Vec<f64>)> = series.into_iter()
+            .map(|(name, values)| (name, moving_average(&values, self.config.smoothing_window)))
+            .filter(|(_, values)| standard_deviation(values) > 0.0)
+            .collect();
+
+        let mut correlations = Vec::new();
+        for (index, (variable1, values1)) in smoothed.iter().enumerate() {
+            for (variable2, values2) in &smoothed[index + 1..] {
+                let correlation = correlation_coefficient(values1, values2)?;
+                if correlation.abs() < self.config.min_correlation {
+                    continue;
+                }
+                correlations.push(VariableCorrelation {
+                    variable1: variable1.clone(),
+                    variable2: variable2.clone(),
+                    correlation,
+                    significance: significance(correlation, values1.len()),
+                });
+            }
+        }
+
+        correlations.sort_by(|a, b| b.correlation.abs().total_cmp(&a.correlation.abs())
+            .then(a.variable1.cmp(&b.variable1))
+            .then(a.variable2.cmp(&b.variable2)));
+        Ok(correlations)
+    }
+}
+
+/// Analyze a recorded session's events and game states with the default settings
+pub async fn analyze_collected_data(events: &[GameEvent], states: &[GameState]) -> Result<Vec<AnalysisResult>> {
+    info!("🔍 Starting data analysis of {} events and {} game states...", events.len(), states.len());
+
+    let results = SessionAnalyzer::new(SessionAnalysisConfig::default())?.analyze(events, states)?;
+
     info!("✅ Data analysis completed: {} results generated", results.len());
-    
+
     Ok(results)
 }
+
+fn type_name(event_type: &EventType) -> String {
+    format!("{:?}", event_type)
+}
+
+fn entity_key(data: &EventData) -> Option<(bool, u32)> {
+    match data {
+        EventData::Unit { unit_id, .. } => Some((false, *unit_id)),
+        EventData::Building { building_id, .. } => Some((true, *building_id)),
+        _ => None,
+    }
+}
+
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 / 1000.0
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+fn ratio(part: f64, total: f64) -> f64 {
+    if total == 0.0 {
+        0.0
+    } else {
+        (part / total).min(1.0)
+    }
+}
+
+/// Grows from 0 towards 1 with the number of observations
+fn sample_confidence(observations: usize) -> f64 {
+    observations as f64 / (observations as f64 + HALF_CONFIDENCE_SAMPLES)
+}
+
+/// Two-sided confidence that a correlation over `samples` points is not zero (Fisher z-test)
+fn significance(correlation: f64, samples: usize) -> f64 {
+    if samples <= 3 {
+        return 0.0;
+    }
+    let z = correlation.clamp(-0.999_999, 0.999_999).atanh() * ((samples - 3) as f64).sqrt();
+    erf(z.abs() / std::f64::consts::SQRT_2)
+}
+
+/// Error function, Abramowitz and Stegun 7.1.26
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let polynomial = ((((1.061_405_429 * t - 1.453_152_027) * t + 1.421_413_741) * t - 0.284_496_736) * t + 0.254_829_592) * t;
+    let y = 1.0 - polynomial * (-x * x).exp();
+    if x < 0.0 { -y } else { y }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn event(id: u64, millis: i64, event_type: EventType, data: EventData, player_id: u8) -> GameEvent {
+        GameEvent {
+            id,
+            timestamp: DateTime::from_timestamp(0, 0).unwrap() + Duration::milliseconds(millis),
+            event_type,
+            data,
+            frame: 0,
+            game_time: (millis / 1000) as u32,
+            player_id: Some(player_id),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn unit(unit_id: u32) -> EventData {
+        EventData::Unit { unit_id, unit_type: "Footman".to_string(), position: None, health: None, owner: Some(1) }
+    }
+
+    fn resource(amount: u32) -> EventData {
+        EventData::Resource { resource_type: "gold".to_string(), amount, player_id: 0 }
+    }
+
+    /// Player 0 mines every 5 seconds and spends half of it, player 1 trains and moves a unit each cycle
+    fn session() -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        for cycle in 0..13u32 {
+            let start = cycle as i64 * 5000;
+            let id = events.len() as u64;
+            events.push(event(id, start, EventType::ResourceGathered, resource(100), 0));
+            events.push(event(id + 1, start + 2000, EventType::UnitCreated, unit(cycle), 1));
+            events.push(event(id + 2, start + 2500, EventType::UnitMoved, unit(cycle), 1));
+            events.push(event(id + 3, start + 3000, EventType::ResourceSpent, resource(50), 0));
+        }
+        events
+    }
+
+    #[test]
+    fn test_event_sequences_and_periods() {
+        let results = SessionAnalyzer::new(SessionAnalysisConfig::default()).unwrap().analyze(&session(), &[]).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].confidence > 0.0);
+        let AnalysisData::EventSequences(analysis) = &results[0].results else {
+            panic!("expected an event sequence analysis");
+        };
+
+        let created_moved = analysis.common_sequences.iter()
+            .find(|sequence| sequence.name == "UnitCreated -> UnitMoved")
+            .unwrap();
+        assert_eq!(created_moved.frequency, 13);
+        assert!((created_moved.average_duration - 0.5).abs() < 1e-9);
+        assert_eq!(created_moved.player_correlation[&1], 1.0);
+        assert!(created_moved.confidence > 0.4);
+
+        let gathering = analysis.timing_patterns.iter()
+            .find(|pattern| pattern.event_type == "ResourceGathered")
+            .unwrap();
+        assert_eq!(gathering.average_interval, 5.0);
+        assert_eq!(gathering.standard_deviation, 0.0);
+        assert!(gathering.confidence > 0.0);
+    }
+
+    #[test]
+    fn test_rejects_degenerate_config() {
+        for config in [
+            SessionAnalysisConfig { bin_seconds: 0.0, ..SessionAnalysisConfig::default() },
+            SessionAnalysisConfig { bin_seconds: f64::NAN, ..SessionAnalysisConfig::default() },
+            SessionAnalysisConfig { smoothing_window: 0, ..SessionAnalysisConfig::default() },
+        ] {
+            assert!(SessionAnalyzer::new(config).is_err());
+        }
+    }
+
+    #[test]
+    fn test_period_search_is_capped() {
+        let config = SessionAnalysisConfig { max_period_seconds: 12.0, ..SessionAnalysisConfig::default() };
+        let results = SessionAnalyzer::new(config).unwrap().analyze(&session(), &[]).unwrap();
+        let AnalysisData::EventSequences(analysis) = &results[0].results else {
+            panic!("expected an event sequence analysis");
+        };
+
+        let gathering = analysis.timing_patterns.iter()
+            .find(|pattern| pattern.event_type == "ResourceGathered")
+            .unwrap();
+        assert_eq!(gathering.timing_distribution.len(), 12);
+        assert_eq!(gathering.average_interval, 5.0);
+    }
+
+    #[test]
+    fn test_player_patterns() {
+        let mut events = session();
+        events.push(event(100, 61_000, EventType::UnitDestroyed, unit(0), 1));
+        let analyzer = SessionAnalyzer::new(SessionAnalysisConfig::default()).unwrap();
+        let results = analyzer.analyze(&events, &[]).unwrap();
+        let AnalysisData::EventSequences(analysis) = &results[0].results else {
+            panic!("expected an event sequence analysis");
+        };
+
+        let economy = analysis.player_patterns.iter().find(|pattern| pattern.player_id == 0).unwrap();
+        assert_eq!(economy.behavior_type, "Economy");
+        assert_eq!(economy.success_rate, 0.5);
+        let production = analysis.player_patterns.iter().find(|pattern| pattern.player_id == 1).unwrap();
+        assert_eq!(production.behavior_type, "Production");
+        assert_eq!(production.success_rate, 12.0 / 13.0);
+    }
+
+    #[test]
+    fn test_variable_correlations() {
+        let states: Vec<GameState> = (0..20u32)
+            .map(|step| {
+                let mut state = GameState::empty();
+                state.resources.insert(0, ResourceInfo { gold: 100 * step, wood: 500, ore: 0, oil: 1000 - 30 * step });
+                state
+            })
+            .collect();
+
+        let results = SessionAnalyzer::new(SessionAnalysisConfig::default()).unwrap().analyze(&[], &states).unwrap();
+        assert_eq!(results.len(), 1);
+        let AnalysisData::DataCorrelation(analysis) = &results[0].results else {
+            panic!("expected a correlation analysis");
+        };
+        assert_eq!(analysis.variable_correlations.len(), 1);
+        let correlation = &analysis.variable_correlations[0];
+        assert_eq!((correlation.variable1.as_str(), correlation.variable2.as_str()), ("player0.gold", "player0.oil"));
+        assert!((correlation.correlation + 1.0).abs() < 1e-9);
+        assert!(correlation.significance > 0.99);
+        assert!(results[0].confidence > 0.99);
+    }
+}
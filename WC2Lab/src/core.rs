@@ -5,9 +5,10 @@ use std::path::PathBuf;
 use chrono::{DateTime, Utc};
 use log::{info, warn, error};
 
-use crate::analysis::AnalysisResult;
-use crate::game_state::GameState;
-use crate::events::GameEvent;
+use crate::analysis::{analyze_collected_data, AnalysisResult};
+use crate::event_log::{EventLogConfig, EventLogWriter};
+use crate::game_state::{GameState, GameStateTracker};
+use crate::events::{EventRecorder, GameEvent};
 
 /// Main laboratory coordinator for WC2 Remastered analysis
 pub struct Laboratory {
@@ -94,6 +95,21 @@ impl Laboratory {
         Ok(())
     }
 
+    /// An event recorder that appends to the `events` log in the session's output directory
+    ///
+    /// Events are detected with the thresholds `tracker` detects state changes with.
+    /// The log can be read back with `read-events` and analyzed with `analyze-session`.
+    pub fn event_recorder(&self, tracker: &GameStateTracker) -> Result<EventRecorder> {
+        let log = EventLogWriter::open(&self.output_dir.join("events"), EventLogConfig::default())?;
+        let mut recorder = EventRecorder::new()?
+            .with_thresholds(tracker.change_thresholds().clone())
+            .with_event_log(log);
+        if !self.config.event_recording {
+            recorder.stop_recording()?;
+        }
+        Ok(recorder)
+    }
+
     /// Add a new game state to the laboratory
     pub fn add_game_state(&mut self, state: GameState) {
         self.game_states.push(state);
@@ -106,6 +122,20 @@ impl Laboratory {
         info!("📝 Added game event #{}", self.game_events.len());
     }
 
+    /// Take over the events and game states of a recorded session
+    pub fn import_session(&mut self, events: Vec<GameEvent>, states: Vec<GameState>) {
+        info!("📼 Imported {} game events and {} game states", events.len(), states.len());
+        self.game_events.extend(events);
+        self.game_states.extend(states);
+    }
+
+    /// Analyze the collected events and game states, keeping the results
+    pub async fn analyze_session(&mut self) -> Result<Vec<AnalysisResult>> {
+        let results = analyze_collected_data(&self.game_events, &self.game_states).await?;
+        self.analysis_results.extend(results.iter().cloned());
+        Ok(results)
+    }
+
     /// Add analysis results
     pub fn add_analysis_result(&mut self, result: AnalysisResult) {
         self.analysis_results.push(result);
@@ -168,6 +198,11 @@ impl Laboratory {
         Ok(())
     }
 
+    /// Configuration of the session
+    pub fn config(&self) -> &LaboratoryConfig {
+        &self.config
+    }
+
     /// Get current laboratory statistics
     pub fn get_stats(&self) -> LaboratoryStats {
         LaboratoryStats {
@@ -0,0 +1,642 @@
+//! Durable event log
+//!
+//! An [`EventLogWriter`] appends recorded events to segment files in a
+//! directory, so a lab session of any length keeps its events on disk instead
+//! of in memory and loses at most the record being written when it crashes.
+//!
+//! - The active segment, `segment-NNNNNNNN.log`, is a sequence of records: a
+//!   little-endian u32 payload length, a CRC-32 of the payload and the bincode
+//!   payload. A torn or corrupt tail is cut off when the log is reopened.
+//! - When the active segment grows past a size or spans more than an age of
+//!   game events it is sealed: its records are compressed into zstd frames of
+//!   [`BLOCK_RECORDS`] records each (`.zst`), with a sparse index (`.idx`) of
+//!   every frame's offset, time range, event types and players.
+//!
+//! An [`EventLogReader`] iterates the events of all segments in order, using
+//! the index to skip frames outside a time range or without a matching event.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{EventData, EventFilter, EventType, GameEvent};
+
+/// Records per compressed frame and sparse index entry
+pub const BLOCK_RECORDS: usize = 1024;
+
+/// Length and CRC-32 before each payload
+const RECORD_HEADER: usize = 8;
+
+const CRC32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// When the active segment is sealed and how hard records are pushed to disk
+#[derive(Debug, Clone)]
+pub struct EventLogConfig {
+    /// Seal the active segment once it holds this many bytes
+    pub max_segment_bytes: u64,
+    /// Seal the active segment once its events span this long
+    pub max_segment_age: Option<Duration>,
+    /// fsync after every record instead of only handing it to the OS
+    pub sync_every_record: bool,
+    pub compression_level: i32,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        Self {
+            max_segment_bytes: 64 << 20,
+            max_segment_age: Some(Duration::hours(1)),
+            sync_every_record: false,
+            compression_level: 3,
+        }
+    }
+}
+
+/// An event as written to the log
+///
+/// bincode cannot read back `serde_json::Value`s, so the event data and
+/// metadata, which may hold them, are stored as JSON text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredEvent {
+    id: u64,
+    timestamp: DateTime<Utc>,
+    event_type: EventType,
+    frame: u32,
+    game_time: u32,
+    player_id: Option<u8>,
+    data: String,
+    metadata: String,
+}
+
+impl StoredEvent {
+    fn new(event: &GameEvent) -> Result<Self> {
+        Ok(Self {
+            id: event.id,
+            timestamp: event.timestamp,
+            event_type: event.event_type.clone(),
+            frame: event.frame,
+            game_time: event.game_time,
+            player_id: event.player_id,
+            data: serde_json::to_string(&event.data)?,
+            metadata: serde_json::to_string(&event.metadata)?,
+        })
+    }
+
+    fn into_event(self) -> Result<GameEvent> {
+        let data: EventData = serde_json::from_str(&self.data)?;
+        Ok(GameEvent {
+            id: self.id,
+            timestamp: self.timestamp,
+            event_type: self.event_type,
+            data,
+            frame: self.frame,
+            game_time: self.game_time,
+            player_id: self.player_id,
+            metadata: serde_json::from_str(&self.metadata)?,
+        })
+    }
+}
+
+/// Index entry of one compressed frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockIndex {
+    /// Byte offset of the frame in the `.zst` file
+    pub offset: u64,
+    pub len: u64,
+    pub records: u32,
+    pub first_timestamp: DateTime<Utc>,
+    pub last_timestamp: DateTime<Utc>,
+    /// Event types present, as their debug names
+    pub event_types: Vec<String>,
+    pub players: Vec<u8>,
+}
+
+/// Index of a sealed segment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentIndex {
+    pub segment: u64,
+    pub records: u64,
+    pub last_id: u64,
+    pub blocks: Vec<BlockIndex>,
+}
+
+fn segment_path(dir: &Path, segment: u64, extension: &str) -> PathBuf {
+    dir.join(format!("segment-{:08}.{}", segment, extension))
+}
+
+/// Segment numbers in `dir` with the given extension, in order
+fn segments(dir: &Path, extension: &str) -> Result<Vec<u64>> {
+    let mut found: Vec<u64> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list event log {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .filter_map(|path| path.file_stem()?.to_str()?.strip_prefix("segment-")?.parse().ok())
+        .collect();
+    found.sort_unstable();
+    Ok(found)
+}
+
+fn encode_record(event: &GameEvent) -> Result<Vec<u8>> {
+    let payload = bincode::serialize(&StoredEvent::new(event)?)?;
+    let mut record = Vec::with_capacity(RECORD_HEADER + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&CRC32.checksum(&payload).to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+/// The payload of the record at `position` and where the next one starts, unless the record is torn or corrupt
+fn next_record(bytes: &[u8], position: usize) -> Option<(&[u8], usize)> {
+    let header = bytes.get(position..position + RECORD_HEADER)?;
+    let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let start = position + RECORD_HEADER;
+    let payload = bytes.get(start..start.checked_add(len)?)?;
+    (CRC32.checksum(payload) == crc).then_some((payload, start + len))
+}
+
+/// Decode the intact records of a raw segment, returning them with the length they cover
+fn decode_records(bytes: &[u8]) -> Result<(Vec<StoredEvent>, usize)> {
+    let mut records = Vec::new();
+    let mut position = 0;
+    while let Some((payload, next)) = next_record(bytes, position) {
+        records.push(bincode::deserialize(payload)?);
+        position = next;
+    }
+    Ok((records, position))
+}
+
+/// Compress a raw segment into indexed frames and remove it
+///
+/// The frames and index are written under temporary names and renamed index
+/// first, so a segment with a `.zst` file always has its index, and a `.log`
+/// left next to a `.zst` was already sealed.
+fn seal(dir: &Path, segment: u64, level: i32) -> Result<SegmentIndex> {
+    let raw_path = segment_path(dir, segment, "log");
+    let bytes = std::fs::read(&raw_path).with_context(|| format!("Failed to read {}", raw_path.display()))?;
+    let (records, _) = decode_records(&bytes)?;
+
+    let mut compressed = Vec::new();
+    let mut blocks = Vec::new();
+    let mut position = 0;
+    for chunk in records.chunks(BLOCK_RECORDS) {
+        // Frames hold the framed records, so they decode like a raw segment
+        let start = position;
+        for _ in chunk {
+            position = next_record(&bytes, position).map(|(_, next)| next).unwrap_or(position);
+        }
+        let frame = zstd::bulk::compress(&bytes[start..position], level)?;
+
+        let mut event_types = BTreeSet::new();
+        let mut players = BTreeSet::new();
+        for record in chunk {
+            event_types.insert(format!("{:?}", record.event_type));
+            players.extend(record.player_id);
+        }
+        blocks.push(BlockIndex {
+            offset: compressed.len() as u64,
+            len: frame.len() as u64,
+            records: chunk.len() as u32,
+            first_timestamp: chunk[0].timestamp,
+            last_timestamp: chunk[chunk.len() - 1].timestamp,
+            event_types: event_types.into_iter().collect(),
+            players: players.into_iter().collect(),
+        });
+        compressed.extend_from_slice(&frame);
+    }
+
+    let index = SegmentIndex {
+        segment,
+        records: records.len() as u64,
+        last_id: records.last().map_or(0, |record| record.id),
+        blocks,
+    };
+    let write = |extension: &str, bytes: &[u8]| -> Result<()> {
+        let temporary = segment_path(dir, segment, &format!("{}.tmp", extension));
+        let mut file = File::create(&temporary)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, segment_path(dir, segment, extension))?;
+        Ok(())
+    };
+    write("idx", &bincode::serialize(&index)?)?;
+    write("zst", &compressed)?;
+    std::fs::remove_file(&raw_path)?;
+
+    info!("🗜️ Sealed event log segment {} ({} events, {} → {} bytes)", segment, index.records, bytes.len(), compressed.len());
+    Ok(index)
+}
+
+fn load_index(dir: &Path, segment: u64) -> Result<SegmentIndex> {
+    let path = segment_path(dir, segment, "idx");
+    let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    bincode::deserialize(&bytes).with_context(|| format!("Invalid event log index {}", path.display()))
+}
+
+/// Appends events to a log directory
+pub struct EventLogWriter {
+    dir: PathBuf,
+    config: EventLogConfig,
+    segment: u64,
+    file: BufWriter<File>,
+    bytes: u64,
+    /// Time of the first event in the active segment
+    started: Option<DateTime<Utc>>,
+    last_id: u64,
+}
+
+impl EventLogWriter {
+    /// Open the log in `dir`, creating it, or continuing it after the last intact record
+    pub fn open(dir: &Path, config: EventLogConfig) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create event log {}", dir.display()))?;
+        let sealed = segments(dir, "zst")?;
+
+        // Finish what a crash interrupted: drop raw copies of sealed segments, seal all but the newest raw one
+        let mut raw = Vec::new();
+        for segment in segments(dir, "log")? {
+            if sealed.contains(&segment) {
+                std::fs::remove_file(segment_path(dir, segment, "log"))?;
+            } else {
+                raw.push(segment);
+            }
+        }
+        let active = raw.pop();
+        for segment in raw {
+            seal(dir, segment, config.compression_level)?;
+        }
+
+        let mut last_id = match sealed.last() {
+            Some(&segment) => load_index(dir, segment)?.last_id,
+            None => 0,
+        };
+        let segment = active.or_else(|| sealed.last().map(|segment| segment + 1)).unwrap_or(1);
+        let path = segment_path(dir, segment, "log");
+
+        // Cut off a record torn by a crash so new ones follow the last intact one
+        let bytes = if path.exists() { std::fs::read(&path)? } else { Vec::new() };
+        let (records, intact) = decode_records(&bytes)?;
+        if intact < bytes.len() {
+            warn!("⚠️ Dropping {} bytes of a torn record at the end of {}", bytes.len() - intact, path.display());
+        }
+        let file = OpenOptions::new().create(true).write(true).truncate(false).open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.set_len(intact as u64)?;
+        let mut file = BufWriter::new(file);
+        file.seek(SeekFrom::End(0))?;
+
+        if let Some(record) = records.last() {
+            last_id = record.id;
+        }
+        info!("📼 Event log {} open at segment {} ({} events so far in it)", dir.display(), segment, records.len());
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            config,
+            segment,
+            file,
+            bytes: intact as u64,
+            started: records.first().map(|record| record.timestamp),
+            last_id,
+        })
+    }
+
+    /// Directory of the log, for opening an [`EventLogReader`] on it
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// ID of the last event written, 0 for an empty log
+    pub fn last_id(&self) -> u64 {
+        self.last_id
+    }
+
+    pub fn append(&mut self, event: &GameEvent) -> Result<()> {
+        let too_old = match (self.started, self.config.max_segment_age) {
+            (Some(started), Some(age)) => event.timestamp - started >= age,
+            _ => false,
+        };
+        if self.bytes > 0 && (self.bytes >= self.config.max_segment_bytes || too_old) {
+            self.rotate()?;
+        }
+
+        let record = encode_record(event)?;
+        self.file.write_all(&record)?;
+        self.file.flush()?;
+        if self.config.sync_every_record {
+            self.file.get_ref().sync_data()?;
+        }
+        self.bytes += record.len() as u64;
+        self.started.get_or_insert(event.timestamp);
+        self.last_id = event.id;
+        Ok(())
+    }
+
+    /// Seal the active segment and start a new one
+    pub fn rotate(&mut self) -> Result<()> {
+        self.flush()?;
+        seal(&self.dir, self.segment, self.config.compression_level)?;
+
+        self.segment += 1;
+        let path = segment_path(&self.dir, self.segment, "log");
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        self.file = BufWriter::new(file);
+        self.bytes = 0;
+        self.started = None;
+        Ok(())
+    }
+
+    /// Push written records to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_data()?;
+        Ok(())
+    }
+}
+
+/// A segment as the reader sees it
+#[derive(Debug, Clone)]
+enum Segment {
+    Sealed(SegmentIndex),
+    Raw(u64),
+}
+
+/// Reads the events of a log directory
+pub struct EventLogReader {
+    dir: PathBuf,
+    segments: Vec<Segment>,
+}
+
+impl EventLogReader {
+    pub fn open(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            bail!("No event log at {}", dir.display());
+        }
+        let sealed = segments(dir, "zst")?;
+        let mut found = Vec::new();
+        for &segment in &sealed {
+            found.push(Segment::Sealed(load_index(dir, segment)?));
+        }
+        // Raw segments next to a sealed copy are leftovers of an interrupted seal
+        for segment in segments(dir, "log")?.into_iter().filter(|segment| !sealed.contains(segment)) {
+            found.push(Segment::Raw(segment));
+        }
+        found.sort_by_key(|segment| match segment {
+            Segment::Sealed(index) => index.segment,
+            Segment::Raw(segment) => *segment,
+        });
+        Ok(Self { dir: dir.to_path_buf(), segments: found })
+    }
+
+    /// Events passing `filter` with timestamps in `from..=until`, in the order written
+    pub fn events(&self, filter: &EventFilter, from: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> EventLogIter<'_> {
+        EventLogIter {
+            reader: self,
+            filter: filter.clone(),
+            from,
+            until,
+            segment: 0,
+            block: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Total events, from the indexes and by counting raw segments
+    pub fn count(&self) -> Result<u64> {
+        let mut total = 0;
+        for segment in &self.segments {
+            total += match segment {
+                Segment::Sealed(index) => index.records,
+                Segment::Raw(segment) => {
+                    let bytes = std::fs::read(segment_path(&self.dir, *segment, "log"))?;
+                    decode_records(&bytes)?.0.len() as u64
+                }
+            };
+        }
+        Ok(total)
+    }
+}
+
+/// Iterator over the events of an [`EventLogReader`]
+pub struct EventLogIter<'a> {
+    reader: &'a EventLogReader,
+    filter: EventFilter,
+    from: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    segment: usize,
+    block: usize,
+    pending: VecDeque<GameEvent>,
+}
+
+impl EventLogIter<'_> {
+    /// Whether a frame may hold a wanted event, judging by its index entry
+    fn block_wanted(&self, block: &BlockIndex) -> bool {
+        let in_time = self.from.is_none_or(|from| block.last_timestamp >= from)
+            && self.until.is_none_or(|until| block.first_timestamp <= until);
+        let has_type = self.filter.event_types.is_empty()
+            || self.filter.event_types.iter().any(|event_type| block.event_types.contains(&format!("{:?}", event_type)));
+        let has_player = self.filter.player_ids.is_empty()
+            || self.filter.player_ids.iter().any(|player| block.players.contains(player));
+        in_time && has_type && has_player
+    }
+
+    /// Queue the wanted events of framed records
+    fn queue(&mut self, bytes: &[u8]) -> Result<()> {
+        for record in decode_records(bytes)?.0 {
+            let in_time = self.from.is_none_or(|from| record.timestamp >= from)
+                && self.until.is_none_or(|until| record.timestamp <= until);
+            if in_time && self.filter.matches(&record.event_type, record.player_id) {
+                self.pending.push_back(record.into_event()?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the next frame or raw segment; false when the log is exhausted
+    fn advance(&mut self) -> Result<bool> {
+        let Some(segment) = self.reader.segments.get(self.segment) else {
+            return Ok(false);
+        };
+        match segment {
+            Segment::Sealed(index) => {
+                let Some(block) = index.blocks.get(self.block) else {
+                    self.segment += 1;
+                    self.block = 0;
+                    return Ok(true);
+                };
+                self.block += 1;
+                if !self.block_wanted(block) {
+                    return Ok(true);
+                }
+                let path = segment_path(&self.reader.dir, index.segment, "zst");
+                let mut file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+                file.seek(SeekFrom::Start(block.offset))?;
+                let mut frame = vec![0u8; block.len as usize];
+                file.read_exact(&mut frame)?;
+                let bytes = zstd::stream::decode_all(frame.as_slice())
+                    .with_context(|| format!("Corrupt frame at {} in {}", block.offset, path.display()))?;
+                self.queue(&bytes)?;
+            }
+            Segment::Raw(number) => {
+                self.segment += 1;
+                let path = segment_path(&self.reader.dir, *number, "log");
+                let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+                self.queue(&bytes)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Iterator for EventLogIter<'_> {
+    type Item = Result<GameEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.advance() {
+                Ok(true) => continue,
+                Ok(false) => return None,
+                Err(e) => {
+                    // Stop after reporting, rather than retrying the same frame forever
+                    self.segment = self.reader.segments.len();
+                    debug!("Event log read failed: {}", e);
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::events::{EventRecorder, ReplayData, RECENT_EVENTS};
+
+    fn event(id: u64, event_type: EventType, player_id: Option<u8>) -> GameEvent {
+        let mut metadata = HashMap::new();
+        metadata.insert("serial".to_string(), serde_json::json!(id));
+        GameEvent {
+            id,
+            timestamp: DateTime::from_timestamp(1_700_000_000 + id as i64, 0).unwrap(),
+            event_type,
+            data: EventData::Resource { resource_type: "gold".to_string(), amount: id as u32, player_id: 0 },
+            frame: id as u32,
+            game_time: 0,
+            player_id,
+            metadata,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorder_exports_from_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = EventLogWriter::open(dir.path(), EventLogConfig::default()).unwrap();
+        let mut recorder = EventRecorder::new().unwrap().with_event_log(log);
+        let total = 2 * RECENT_EVENTS as u32 + 1;
+        for frame in 0..total {
+            recorder.record_event(EventType::UnitCreated, EventData::None, frame, 0, None).unwrap();
+        }
+        assert!(recorder.get_events().len() < total as usize);
+
+        let path = dir.path().join("events.json");
+        recorder.export_events(&path).await.unwrap();
+        let exported: Vec<GameEvent> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(exported.len(), total as usize);
+        assert_eq!((exported[0].id, exported[0].frame), (1, 0));
+
+        let path = dir.path().join("replay.json");
+        recorder.export_replay(&path).await.unwrap();
+        let replay: ReplayData = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(replay.events.len(), total as usize);
+        assert_eq!(replay.events.last().unwrap().frame, total - 1);
+
+        recorder.clear_events();
+        recorder.record_event(EventType::UnitCreated, EventData::None, total, 0, None).unwrap();
+        assert_eq!(recorder.get_events()[0].id, total as u64 + 1);
+    }
+
+    #[tokio::test]
+    async fn test_recorder_exports_from_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recorder = EventRecorder::new().unwrap();
+        let path = dir.path().join("events.json");
+        recorder.export_events(&path).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+
+        for frame in 0..3 {
+            recorder.record_event(EventType::UnitCreated, EventData::None, frame, 0, Some(1)).unwrap();
+        }
+        recorder.export_events(&path).await.unwrap();
+        let exported: Vec<GameEvent> = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(exported.iter().map(|event| event.frame).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    fn small() -> EventLogConfig {
+        EventLogConfig { max_segment_bytes: 16 << 10, max_segment_age: None, ..Default::default() }
+    }
+
+    #[test]
+    fn test_rotate_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = EventLogWriter::open(dir.path(), small()).unwrap();
+        for id in 1..=3000 {
+            let event_type = if id % 100 == 0 { EventType::UnitDestroyed } else { EventType::ResourceGathered };
+            writer.append(&event(id, event_type, Some((id % 2) as u8))).unwrap();
+        }
+        writer.flush().unwrap();
+        assert!(segments(dir.path(), "zst").unwrap().len() > 1);
+
+        let reader = EventLogReader::open(dir.path()).unwrap();
+        assert_eq!(reader.count().unwrap(), 3000);
+        let all: Vec<GameEvent> = reader.events(&EventFilter::default(), None, None).map(Result::unwrap).collect();
+        assert_eq!(all.len(), 3000);
+        assert!(all.windows(2).all(|pair| pair[0].id + 1 == pair[1].id));
+        assert_eq!(all[41].metadata["serial"], serde_json::json!(42));
+
+        let destroyed = EventFilter { event_types: vec![EventType::UnitDestroyed], ..Default::default() };
+        assert_eq!(reader.events(&destroyed, None, None).count(), 30);
+
+        let from = DateTime::from_timestamp(1_700_002_000, 0);
+        let until = DateTime::from_timestamp(1_700_002_009, 0);
+        let odd = EventFilter { player_ids: vec![1], ..Default::default() };
+        let ids: Vec<u64> = reader.events(&odd, from, until).map(|event| event.unwrap().id).collect();
+        assert_eq!(ids, vec![2001, 2003, 2005, 2007, 2009]);
+    }
+
+    #[test]
+    fn test_recovers_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = EventLogWriter::open(dir.path(), EventLogConfig::default()).unwrap();
+        for id in 1..=5 {
+            writer.append(&event(id, EventType::UnitCreated, None)).unwrap();
+        }
+        drop(writer);
+
+        // A crash in the middle of writing the sixth record
+        let path = segment_path(dir.path(), 1, "log");
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&encode_record(&event(6, EventType::UnitCreated, None)).unwrap()[..20]).unwrap();
+        drop(file);
+        assert_eq!(EventLogReader::open(dir.path()).unwrap().count().unwrap(), 5);
+
+        let mut writer = EventLogWriter::open(dir.path(), EventLogConfig::default()).unwrap();
+        assert_eq!(writer.last_id(), 5);
+        writer.append(&event(6, EventType::UnitCreated, None)).unwrap();
+        writer.rotate().unwrap();
+        drop(writer);
+
+        // Reopening continues after the sealed segment
+        let writer = EventLogWriter::open(dir.path(), EventLogConfig::default()).unwrap();
+        assert_eq!((writer.segment, writer.last_id()), (2, 6));
+        let reader = EventLogReader::open(dir.path()).unwrap();
+        let ids: Vec<u64> = reader.events(&EventFilter::default(), None, None).map(|event| event.unwrap().id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5, 6]);
+    }
+}
@@ -0,0 +1,583 @@
+//! Game events from consecutive game states
+//!
+//! Memory polling only yields snapshots of the game. An [`EventSynthesizer`]
+//! compares each state with the one before and describes the difference as
+//! [`EventType`]s: units and buildings appearing, disappearing, losing or
+//! regaining health, resources going up or down, research starting and ending,
+//! and a player winning.
+//!
+//! The game keeps units in a fixed table and reuses the slot of a dead unit for
+//! the next one, so slot numbers do not identify a unit over time. Events carry
+//! a serial number instead, assigned when a unit or building first appears and
+//! replaced when its slot is taken over by something of another type or owner,
+//! or by a unit of the same type that appears far away or back at full health.
+//!
+//! A state that lost all its players, or all units and buildings at once in the
+//! middle of a game, was not read completely; it is ignored rather than turned
+//! into a destruction event for everything followed by a creation event for
+//! everything on the next good read.
+
+use std::collections::{HashMap, HashSet};
+use log::{debug, warn};
+
+use crate::events::{EventData, EventType};
+use crate::game_state::{BuildingInfo, ChangeThresholds, GamePhase, GameState, ProductType, Race, UnitInfo, UpgradeType};
+
+/// Research that disappears at this progress or later finished; earlier it was cancelled
+const RESEARCH_COMPLETE_PROGRESS: u8 = 90;
+
+/// Tiles a unit cannot cover between two polls; a unit of the same type this far
+/// from the slot's last position is a new one
+const SLOT_REUSE_DISTANCE: f32 = 8.0;
+
+/// Most health a unit regains between two polls, from Holy Light; a bigger rise
+/// back to full health is a new unit in the slot
+const MOST_HEALED_PER_POLL: u32 = 40;
+
+/// An event found between two states, before the recorder gives it an ID and time
+#[derive(Debug, Clone)]
+pub struct SynthesizedEvent {
+    pub event_type: EventType,
+    pub data: EventData,
+    pub player_id: Option<u8>,
+}
+
+/// Serial numbers of the occupants of table slots
+#[derive(Debug, Default)]
+struct Identities {
+    serials: HashMap<u32, u32>,
+}
+
+impl Identities {
+    fn get(&mut self, slot: u32, next_serial: &mut u32) -> u32 {
+        *self.serials.entry(slot).or_insert_with(|| {
+            *next_serial += 1;
+            *next_serial
+        })
+    }
+
+    /// A new occupant took the slot
+    fn renew(&mut self, slot: u32, next_serial: &mut u32) -> u32 {
+        self.serials.remove(&slot);
+        self.get(slot, next_serial)
+    }
+}
+
+/// Turns a sequence of game states into events
+pub struct EventSynthesizer {
+    thresholds: ChangeThresholds,
+    previous: Option<GameState>,
+    units: Identities,
+    buildings: Identities,
+    /// Highest health seen of the unit in each slot, taken as its full health
+    full_health: HashMap<u32, u8>,
+    next_serial: u32,
+    victory_reported: bool,
+}
+
+impl EventSynthesizer {
+    pub fn new(thresholds: ChangeThresholds) -> Self {
+        Self {
+            thresholds,
+            previous: None,
+            units: Identities::default(),
+            buildings: Identities::default(),
+            full_health: HashMap::new(),
+            next_serial: 0,
+            victory_reported: false,
+        }
+    }
+
+    /// Events between the last observed state and `state`
+    ///
+    /// The first state is the baseline: what is already there produces no events.
+    /// Incompletely read states produce none either and are not compared with.
+    pub fn observe(&mut self, state: &GameState) -> Vec<SynthesizedEvent> {
+        if self.previous.as_ref().is_some_and(|previous| is_partial(previous, state)) {
+            warn!("⚠️ Ignoring incomplete game state at frame {}", state.frame);
+            return Vec::new();
+        }
+        let Some(previous) = self.previous.replace(state.clone()) else {
+            for unit in &state.units {
+                self.units.get(unit.id, &mut self.next_serial);
+                self.full_health.insert(unit.id, unit.health);
+            }
+            for building in &state.buildings {
+                self.buildings.get(building.id, &mut self.next_serial);
+            }
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        self.diff_units(&previous, state, &mut events);
+        self.diff_buildings(&previous, state, &mut events);
+        self.diff_resources(&previous, state, &mut events);
+        self.check_victory(&previous, state, &mut events);
+
+        debug!("🔄 {} events between frames {} and {}", events.len(), previous.frame, state.frame);
+        events
+    }
+
+    fn diff_units(&mut self, previous: &GameState, current: &GameState, events: &mut Vec<SynthesizedEvent>) {
+        let before: HashMap<u32, &UnitInfo> = previous.units.iter().map(|unit| (unit.id, unit)).collect();
+        let mut present = HashSet::new();
+
+        for unit in &current.units {
+            present.insert(unit.id);
+            let Some(old) = before.get(&unit.id) else {
+                let serial = self.units.renew(unit.id, &mut self.next_serial);
+                self.full_health.insert(unit.id, unit.health);
+                events.push(unit_event(EventType::UnitCreated, unit, serial));
+                continue;
+            };
+
+            let (dx, dy) = (unit.position.0 - old.position.0, unit.position.1 - old.position.1);
+            let distance = (dx * dx + dy * dy).sqrt();
+            let full = self.full_health.get(&unit.id).copied().unwrap_or(old.health);
+            // A new unit of the same type starts where it was trained, at full health.
+            // Units too frail to take a full Holy Light cannot regain more than half their health.
+            let max_health = unit.unit_type.max_health().unwrap_or(full as u32);
+            let most_healed = MOST_HEALED_PER_POLL.min(max_health / 2);
+            let restored = unit.health >= full && (unit.health - old.health) as u32 > most_healed;
+            if old.unit_type != unit.unit_type || old.owner != unit.owner || distance >= SLOT_REUSE_DISTANCE || restored {
+                let serial = self.units.get(unit.id, &mut self.next_serial);
+                events.push(unit_event(EventType::UnitDestroyed, old, serial));
+                let serial = self.units.renew(unit.id, &mut self.next_serial);
+                self.full_health.insert(unit.id, unit.health);
+                events.push(unit_event(EventType::UnitCreated, unit, serial));
+                continue;
+            }
+
+            let serial = self.units.get(unit.id, &mut self.next_serial);
+            self.full_health.insert(unit.id, full.max(unit.health));
+            let health = unit.health as i16 - old.health as i16;
+            if health.unsigned_abs() >= self.thresholds.health_change as u16 && health != 0 {
+                let event_type = if health < 0 { EventType::UnitDamaged } else { EventType::UnitHealed };
+                events.push(unit_event(event_type, unit, serial));
+            }
+
+            if distance >= self.thresholds.position_change {
+                events.push(unit_event(EventType::UnitMoved, unit, serial));
+            }
+        }
+
+        for old in previous.units.iter().filter(|unit| !present.contains(&unit.id)) {
+            let serial = self.units.get(old.id, &mut self.next_serial);
+            self.units.serials.remove(&old.id);
+            self.full_health.remove(&old.id);
+            events.push(unit_event(EventType::UnitDestroyed, old, serial));
+        }
+    }
+
+    fn diff_buildings(&mut self, previous: &GameState, current: &GameState, events: &mut Vec<SynthesizedEvent>) {
+        let before: HashMap<u32, &BuildingInfo> = previous.buildings.iter().map(|building| (building.id, building)).collect();
+        let mut present = HashSet::new();
+
+        for building in &current.buildings {
+            present.insert(building.id);
+            let Some(old) = before.get(&building.id) else {
+                let serial = self.buildings.renew(building.id, &mut self.next_serial);
+                events.push(building_event(EventType::BuildingCreated, building, serial));
+                continue;
+            };
+
+            // A foundation laid where a destroyed building stood starts its construction over
+            let replaced = old.building_type != building.building_type
+                || old.owner != building.owner
+                || building.construction_progress < old.construction_progress;
+            if replaced {
+                let serial = self.buildings.get(building.id, &mut self.next_serial);
+                events.push(building_event(EventType::BuildingDestroyed, old, serial));
+                let serial = self.buildings.renew(building.id, &mut self.next_serial);
+                events.push(building_event(EventType::BuildingCreated, building, serial));
+                continue;
+            }
+
+            let serial = self.buildings.get(building.id, &mut self.next_serial);
+            let health = building.health as i16 - old.health as i16;
+            let built = old.construction_progress >= 100 && building.construction_progress >= 100;
+            if health <= -(self.thresholds.health_change as i16) && health != 0 {
+                events.push(building_event(EventType::BuildingDamaged, building, serial));
+            } else if built && health >= self.thresholds.health_change as i16 && health != 0 {
+                // While under construction, health rises with the construction
+                events.push(building_event(EventType::BuildingRepaired, building, serial));
+            }
+
+            let researching = research(building);
+            let researched = research(old);
+            if let Some((upgrade, progress)) = researched {
+                if researching.map(|(upgrade, _)| upgrade) != Some(upgrade) && progress >= RESEARCH_COMPLETE_PROGRESS {
+                    events.push(research_event(EventType::ResearchCompleted, building, upgrade, serial));
+                }
+            }
+            if let Some((upgrade, _)) = researching {
+                if researched.map(|(upgrade, _)| upgrade) != Some(upgrade) {
+                    events.push(research_event(EventType::ResearchStarted, building, upgrade, serial));
+                }
+            }
+        }
+
+        for old in previous.buildings.iter().filter(|building| !present.contains(&building.id)) {
+            let serial = self.buildings.get(old.id, &mut self.next_serial);
+            self.buildings.serials.remove(&old.id);
+            events.push(building_event(EventType::BuildingDestroyed, old, serial));
+        }
+    }
+
+    fn diff_resources(&self, previous: &GameState, current: &GameState, events: &mut Vec<SynthesizedEvent>) {
+        let mut players: Vec<&u8> = current.resources.keys().collect();
+        players.sort();
+        for player_id in players {
+            let (Some(old), Some(new)) = (previous.resources.get(player_id), current.resources.get(player_id)) else {
+                continue;
+            };
+            let amounts = [
+                ("gold", old.gold, new.gold),
+                ("wood", old.wood, new.wood),
+                ("ore", old.ore, new.ore),
+                ("oil", old.oil, new.oil),
+            ];
+            for (resource_type, old, new) in amounts {
+                let amount = old.abs_diff(new);
+                if amount == 0 || amount < self.thresholds.resource_change {
+                    continue;
+                }
+                let event_type = if new > old { EventType::ResourceGathered } else { EventType::ResourceSpent };
+                events.push(SynthesizedEvent {
+                    event_type,
+                    data: EventData::Resource { resource_type: resource_type.to_string(), amount, player_id: *player_id },
+                    player_id: Some(*player_id),
+                });
+            }
+        }
+    }
+
+    /// One victory per game: the last player standing, or the game reaching its end
+    fn check_victory(&mut self, previous: &GameState, current: &GameState, events: &mut Vec<SynthesizedEvent>) {
+        if self.victory_reported {
+            return;
+        }
+        let alive = |state: &GameState| state.players.iter()
+            .filter(|player| player.is_alive && !matches!(player.race, Race::Neutral))
+            .count();
+        let survivors: Vec<_> = current.players.iter()
+            .filter(|player| player.is_alive && !matches!(player.race, Race::Neutral))
+            .collect();
+
+        let last_standing = alive(previous) > 1 && survivors.len() == 1;
+        let ended = matches!(current.game_phase, GamePhase::Ending) && !matches!(previous.game_phase, GamePhase::Ending);
+        if !last_standing && !ended {
+            return;
+        }
+
+        self.victory_reported = true;
+        let winner = (survivors.len() == 1).then(|| survivors[0]);
+        events.push(SynthesizedEvent {
+            event_type: EventType::VictoryCondition,
+            data: winner.map_or(EventData::None, |player| EventData::Player {
+                player_id: player.id,
+                player_name: player.name.clone(),
+                race: format!("{:?}", player.race),
+            }),
+            player_id: winner.map(|player| player.id),
+        });
+    }
+}
+
+/// Whether `state` was read only in part: its players are gone, or in the middle
+/// of a game every unit and building vanished at once
+fn is_partial(previous: &GameState, state: &GameState) -> bool {
+    if !previous.players.is_empty() && state.players.is_empty() {
+        return true;
+    }
+    let in_game = !matches!(state.game_phase, GamePhase::Loading | GamePhase::MainMenu | GamePhase::Ending);
+    let had_entities = !previous.units.is_empty() || !previous.buildings.is_empty();
+    in_game && had_entities && state.units.is_empty() && state.buildings.is_empty()
+}
+
+/// The upgrade a building is researching and how far along it is
+fn research(building: &BuildingInfo) -> Option<(&UpgradeType, u8)> {
+    match &building.current_production {
+        Some(production) => match &production.product_type {
+            ProductType::Upgrade(upgrade) => Some((upgrade, production.progress)),
+            _ => None,
+        },
+        None => None,
+    }
+}
+
+fn unit_event(event_type: EventType, unit: &UnitInfo, serial: u32) -> SynthesizedEvent {
+    SynthesizedEvent {
+        event_type,
+        data: EventData::Unit {
+            unit_id: serial,
+            unit_type: format!("{:?}", unit.unit_type),
+            position: Some(unit.position),
+            health: Some(unit.health),
+            owner: Some(unit.owner),
+        },
+        player_id: Some(unit.owner),
+    }
+}
+
+fn building_event(event_type: EventType, building: &BuildingInfo, serial: u32) -> SynthesizedEvent {
+    SynthesizedEvent {
+        event_type,
+        data: EventData::Building {
+            building_id: serial,
+            building_type: format!("{:?}", building.building_type),
+            position: Some(building.position),
+            health: Some(building.health),
+            owner: Some(building.owner),
+        },
+        player_id: Some(building.owner),
+    }
+}
+
+fn research_event(event_type: EventType, building: &BuildingInfo, upgrade: &UpgradeType, serial: u32) -> SynthesizedEvent {
+    SynthesizedEvent {
+        event_type,
+        data: EventData::Action {
+            action_type: format!("{:?}", upgrade),
+            target_id: Some(serial),
+            target_position: Some(building.position),
+            parameters: HashMap::new(),
+        },
+        player_id: Some(building.owner),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventRecorder;
+    use crate::game_state::{BuildingType, PlayerColor, PlayerInfo, ProductionInfo, ResourceInfo, UnitType};
+
+    fn unit(slot: u32, unit_type: UnitType, owner: u8, health: u8) -> UnitInfo {
+        UnitInfo {
+            id: slot,
+            unit_type,
+            position: (10.0, 10.0),
+            owner,
+            health,
+            mana: 0,
+            experience: 0,
+            orders: Vec::new(),
+            target: None,
+            is_selected: false,
+            is_moving: false,
+            is_attacking: false,
+        }
+    }
+
+    fn building(slot: u32, health: u8, research: Option<(UpgradeType, u8)>) -> BuildingInfo {
+        BuildingInfo {
+            id: slot,
+            building_type: BuildingType::HumanBlacksmith,
+            position: (20.0, 20.0),
+            owner: 0,
+            health,
+            construction_progress: 100,
+            is_selected: false,
+            current_production: research.map(|(upgrade, progress)| ProductionInfo {
+                product_type: ProductType::Upgrade(upgrade),
+                progress,
+                time_remaining: 0,
+            }),
+            production_queue: Vec::new(),
+        }
+    }
+
+    fn player(id: u8, race: Race, alive: bool) -> PlayerInfo {
+        PlayerInfo {
+            id,
+            name: format!("Player {}", id + 1),
+            race,
+            color: PlayerColor::Red,
+            is_human: id == 0,
+            is_alive: alive,
+            team: None,
+            resources: ResourceInfo { gold: 0, wood: 0, ore: 0, oil: 0 },
+            score: 0,
+        }
+    }
+
+    fn state(units: Vec<UnitInfo>, buildings: Vec<BuildingInfo>, gold: u32) -> GameState {
+        let mut state = GameState::empty();
+        state.units = units;
+        state.buildings = buildings;
+        state.players = vec![player(0, Race::Human, true), player(1, Race::Orc, true)];
+        state.resources.insert(0, ResourceInfo { gold, wood: 500, ore: 0, oil: 0 });
+        state
+    }
+
+    fn types(events: &[SynthesizedEvent]) -> Vec<EventType> {
+        events.iter().map(|event| event.event_type.clone()).collect()
+    }
+
+    fn unit_id(event: &SynthesizedEvent) -> u32 {
+        match event.data {
+            EventData::Unit { unit_id, .. } => unit_id,
+            _ => panic!("not a unit event"),
+        }
+    }
+
+    #[test]
+    fn test_units_and_reused_slots() {
+        let mut synthesizer = EventSynthesizer::new(ChangeThresholds::default());
+        assert!(synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 60)], vec![], 1000)).is_empty());
+
+        // Damage below the threshold is ignored, a new unit in slot 1 appears
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 57), unit(1, UnitType::Grunt, 1, 60)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitCreated]);
+        let grunt = unit_id(&events[0]);
+
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 40), unit(1, UnitType::Grunt, 1, 60)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitDamaged]);
+
+        // The footman dies and its slot goes to a peasant in the same poll
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 30), unit(1, UnitType::Grunt, 1, 60)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitDestroyed, EventType::UnitCreated]);
+        assert_ne!(unit_id(&events[0]), unit_id(&events[1]));
+        assert_ne!(unit_id(&events[1]), grunt);
+
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 30)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitDestroyed]);
+        assert_eq!(unit_id(&events[0]), grunt);
+    }
+
+    #[test]
+    fn test_same_type_slot_reuse() {
+        let mut synthesizer = EventSynthesizer::new(ChangeThresholds::default());
+        synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 60)], vec![], 1000));
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 10)], vec![], 1000));
+        let first = unit_id(&events[0]);
+
+        // Holy Light heals 40: the same footman
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 50)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitHealed]);
+        assert_eq!(unit_id(&events[0]), first);
+
+        // Died and a footman trained into the slot between polls: back at full health
+        synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 10)], vec![], 1000));
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Footman, 0, 60)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitDestroyed, EventType::UnitCreated]);
+        assert_eq!(unit_id(&events[0]), first);
+        let second = unit_id(&events[1]);
+        assert_ne!(second, first);
+
+        // Walking is a move; appearing across the map is another footman
+        let mut moved = unit(0, UnitType::Footman, 0, 60);
+        moved.position = (12.0, 10.0);
+        let events = synthesizer.observe(&state(vec![moved.clone()], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitMoved]);
+        moved.position = (60.0, 40.0);
+        let events = synthesizer.observe(&state(vec![moved], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitDestroyed, EventType::UnitCreated]);
+        assert_eq!(unit_id(&events[0]), second);
+    }
+
+    #[test]
+    fn test_frail_unit_slot_reuse() {
+        // A peasant has 30 health, so it never regains more than Holy Light heals
+        let mut synthesizer = EventSynthesizer::new(ChangeThresholds::default());
+        synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 30)], vec![], 1000));
+        let first = unit_id(&synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 20)], vec![], 1000))[0]);
+
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 30)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitHealed]);
+        assert_eq!(unit_id(&events[0]), first);
+
+        synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 4)], vec![], 1000));
+        let events = synthesizer.observe(&state(vec![unit(0, UnitType::Peasant, 0, 30)], vec![], 1000));
+        assert_eq!(types(&events), vec![EventType::UnitDestroyed, EventType::UnitCreated]);
+        assert_eq!(unit_id(&events[0]), first);
+    }
+
+    #[tokio::test]
+    async fn test_recorder_records_state_sequence() {
+        let thresholds = ChangeThresholds { health_change: 20, ..ChangeThresholds::default() };
+        let mut recorder = EventRecorder::new().unwrap().with_thresholds(thresholds);
+        let mut states = vec![
+            state(vec![unit(0, UnitType::Footman, 0, 60)], vec![], 1000),
+            // Below the recorder's threshold, though above the default one
+            state(vec![unit(0, UnitType::Footman, 0, 45), unit(1, UnitType::Grunt, 1, 60)], vec![], 1000),
+            state(vec![unit(0, UnitType::Footman, 0, 20), unit(1, UnitType::Grunt, 1, 60)], vec![], 1000),
+            state(vec![unit(1, UnitType::Grunt, 1, 60)], vec![], 1000),
+        ];
+        for (index, state) in states.iter_mut().enumerate() {
+            state.frame = index as u32 * 10;
+            state.game_time = index as u32 * 500;
+        }
+        for state in &states {
+            recorder.record_events(state).await.unwrap();
+        }
+
+        let recorded: Vec<(EventType, u32, u32, Option<u8>)> = recorder.get_events().iter()
+            .map(|event| (event.event_type.clone(), event.frame, event.game_time, event.player_id))
+            .collect();
+        assert_eq!(recorded, vec![
+            (EventType::UnitCreated, 10, 500, Some(1)),
+            (EventType::UnitDamaged, 20, 1000, Some(0)),
+            (EventType::UnitDestroyed, 30, 1500, Some(0)),
+        ]);
+        let ids: Vec<u64> = recorder.get_events().iter().map(|event| event.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(recorder.get_stats().total_events, 3);
+    }
+
+    #[test]
+    fn test_partial_states_are_ignored() {
+        let mut synthesizer = EventSynthesizer::new(ChangeThresholds::default());
+        let full = state(vec![unit(0, UnitType::Footman, 0, 60)], vec![building(0, 100, None)], 1000);
+        synthesizer.observe(&full);
+
+        // The unit and building tables failed to read
+        assert!(synthesizer.observe(&state(vec![], vec![], 1000)).is_empty());
+        let mut no_players = full.clone();
+        no_players.players.clear();
+        assert!(synthesizer.observe(&no_players).is_empty());
+        assert!(synthesizer.observe(&full).is_empty());
+
+        // After the game everything may go at once
+        let mut ended = state(vec![], vec![], 1000);
+        ended.game_phase = GamePhase::Ending;
+        let events = synthesizer.observe(&ended);
+        assert_eq!(types(&events), vec![EventType::UnitDestroyed, EventType::BuildingDestroyed, EventType::VictoryCondition]);
+    }
+
+    #[test]
+    fn test_buildings_resources_and_research() {
+        let mut synthesizer = EventSynthesizer::new(ChangeThresholds::default());
+        synthesizer.observe(&state(vec![], vec![building(0, 100, None)], 1000));
+
+        let events = synthesizer.observe(&state(vec![], vec![building(0, 70, Some((UpgradeType::HumanWeapon, 0)))], 800));
+        assert_eq!(types(&events), vec![EventType::BuildingDamaged, EventType::ResearchStarted, EventType::ResourceSpent]);
+        assert!(matches!(&events[2].data, EventData::Resource { amount: 200, player_id: 0, .. }));
+
+        let events = synthesizer.observe(&state(vec![], vec![building(0, 90, Some((UpgradeType::HumanWeapon, 95)))], 805));
+        assert_eq!(types(&events), vec![EventType::BuildingRepaired]);
+
+        let events = synthesizer.observe(&state(vec![], vec![building(0, 90, None)], 900));
+        assert_eq!(types(&events), vec![EventType::ResearchCompleted, EventType::ResourceGathered]);
+
+        // Cancelled early: no completion
+        synthesizer.observe(&state(vec![], vec![building(0, 90, Some((UpgradeType::HumanArmor, 10)))], 900));
+        assert!(synthesizer.observe(&state(vec![], vec![building(0, 90, None)], 900)).is_empty());
+    }
+
+    #[test]
+    fn test_victory_reported_once() {
+        let mut synthesizer = EventSynthesizer::new(ChangeThresholds::default());
+        let mut game = state(vec![], vec![], 0);
+        synthesizer.observe(&game);
+
+        game.players[1].is_alive = false;
+        let events = synthesizer.observe(&game);
+        assert_eq!(types(&events), vec![EventType::VictoryCondition]);
+        assert_eq!(events[0].player_id, Some(0));
+
+        game.game_phase = GamePhase::Ending;
+        assert!(synthesizer.observe(&game).is_empty());
+    }
+}
@@ -2,10 +2,14 @@ use anyhow::Result;
 use log::{info, warn, error, debug};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
 
-use crate::game_state::{GameState, StateChange};
+use crate::event_log::{EventLogReader, EventLogWriter};
+use crate::event_synth::EventSynthesizer;
+use crate::game_state::{ChangeThresholds, GameState, StateChange};
 
 /// Game event for replay recording
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +141,9 @@ pub enum EventData {
     Custom(HashMap<String, serde_json::Value>),
 }
 
+/// Events kept in memory while a log holds the full record
+pub(crate) const RECENT_EVENTS: usize = 10_000;
+
 /// Event recorder for WC2 Remastered
 pub struct EventRecorder {
     /// Recorded events
@@ -151,6 +158,10 @@ pub struct EventRecorder {
     event_filters: Vec<EventFilter>,
     /// Recording statistics
     stats: RecordingStats,
+    /// Derives events from consecutive game states
+    synthesizer: EventSynthesizer,
+    /// Durable log every recorded event is appended to
+    log: Option<EventLogWriter>,
 }
 
 /// Event filter for selective recording
@@ -211,9 +222,26 @@ impl EventRecorder {
                 duration: chrono::Duration::zero(),
                 events_per_second: 0.0,
             },
+            synthesizer: EventSynthesizer::new(ChangeThresholds::default()),
+            log: None,
         })
     }
 
+    /// Append events to `log`, keeping only the most recent ones in memory
+    ///
+    /// Event IDs continue after the last event already in the log.
+    pub fn with_event_log(mut self, log: EventLogWriter) -> Self {
+        self.next_event_id = self.next_event_id.max(log.last_id() + 1);
+        self.log = Some(log);
+        self
+    }
+
+    /// Compare game states with `thresholds`, e.g. the ones of the state tracker
+    pub fn with_thresholds(mut self, thresholds: ChangeThresholds) -> Self {
+        self.synthesizer = EventSynthesizer::new(thresholds);
+        self
+    }
+
     /// Start recording events
     pub fn start_recording(&mut self, output_file: Option<PathBuf>) -> Result<()> {
         self.recording_enabled = true;
@@ -232,6 +260,9 @@ impl EventRecorder {
     pub fn stop_recording(&mut self) -> Result<()> {
         self.recording_enabled = false;
         self.stats.duration = Utc::now() - self.stats.start_time;
+        if let Some(log) = &mut self.log {
+            log.flush()?;
+        }
         
         if self.stats.duration.num_seconds() > 0 {
             self.stats.events_per_second = self.stats.total_events as f64 / self.stats.duration.num_seconds() as f64;
@@ -244,72 +275,17 @@ impl EventRecorder {
         Ok(())
     }
 
-    /// Record events from game state changes
+    /// Record the events between the previous game state and `game_state`
+    ///
+    /// The first state recorded only sets the baseline later states are compared with.
     pub async fn record_events(&mut self, game_state: &GameState) -> Result<()> {
         if !self.recording_enabled {
             return Ok(());
         }
 
-        // This would be called with actual state changes
-        // For now, we'll create some sample events
-        
-        let frame = game_state.frame;
-        let game_time = game_state.game_time;
-        
-        // Record unit events
-        for unit in &game_state.units {
-            if unit.is_moving {
-                self.record_event(
-                    EventType::UnitMoved,
-                    EventData::Unit {
-                        unit_id: unit.id,
-                        unit_type: format!("{:?}", unit.unit_type),
-                        position: Some(unit.position),
-                        health: Some(unit.health),
-                        owner: Some(unit.owner),
-                    },
-                    frame,
-                    game_time,
-                    Some(unit.owner),
-                )?;
-            }
-            
-            if unit.is_attacking {
-                self.record_event(
-                    EventType::UnitAttacked,
-                    EventData::Unit {
-                        unit_id: unit.id,
-                        unit_type: format!("{:?}", unit.unit_type),
-                        position: Some(unit.position),
-                        health: Some(unit.health),
-                        owner: Some(unit.owner),
-                    },
-                    frame,
-                    game_time,
-                    Some(unit.owner),
-                )?;
-            }
+        for event in self.synthesizer.observe(game_state) {
+            self.record_event(event.event_type, event.data, game_state.frame, game_state.game_time, event.player_id)?;
         }
-        
-        // Record building events
-        for building in &game_state.buildings {
-            if building.construction_progress == 100 {
-                self.record_event(
-                    EventType::BuildingCreated,
-                    EventData::Building {
-                        building_id: building.id,
-                        building_type: format!("{:?}", building.building_type),
-                        position: Some(building.position),
-                        health: Some(building.health),
-                        owner: Some(building.owner),
-                    },
-                    frame,
-                    game_time,
-                    Some(building.owner),
-                )?;
-            }
-        }
-        
         Ok(())
     }
 
@@ -342,6 +318,14 @@ impl EventRecorder {
             metadata: HashMap::new(),
         };
 
+        // Write to the log before anything else, so a failed write records nothing
+        if let Some(log) = &mut self.log {
+            log.append(&event)?;
+            if self.events.len() >= 2 * RECENT_EVENTS {
+                self.events.drain(..RECENT_EVENTS);
+            }
+        }
+
         // Add to events list
         self.events.push(event.clone());
         self.next_event_id += 1;
@@ -364,31 +348,9 @@ impl EventRecorder {
 
     /// Check if an event should be recorded based on filters
     fn should_record_event(&self, event_type: &EventType, player_id: Option<u8>) -> bool {
-        if self.event_filters.is_empty() {
-            return true; // No filters = record everything
-        }
-
-        for filter in &self.event_filters {
-            // Check event type filter
-            if !filter.event_types.is_empty() && !filter.event_types.contains(event_type) {
-                continue;
-            }
-
-            // Check player ID filter
-            if !filter.player_ids.is_empty() {
-                if let Some(pid) = player_id {
-                    if !filter.player_ids.contains(&pid) {
-                        continue;
-                    }
-                } else {
-                    continue; // No player ID for this event
-                }
-            }
-
-            return true; // Event passes this filter
-        }
-
-        false // Event doesn't pass any filter
+        // No filters = record everything
+        self.event_filters.is_empty()
+            || self.event_filters.iter().any(|filter| filter.matches(event_type, player_id))
     }
 
     /// Add an event filter
@@ -403,7 +365,7 @@ impl EventRecorder {
         info!("🗑️  Cleared all event filters");
     }
 
-    /// Get recorded events
+    /// Get recorded events, only the most recent ones with an event log attached
     pub fn get_events(&self) -> &[GameEvent] {
         &self.events
     }
@@ -429,29 +391,44 @@ impl EventRecorder {
             .collect()
     }
 
+    /// Write every recorded event to `writer` as a JSON array, returning the count.
+    ///
+    /// With an event log attached the events are streamed from it, since only the
+    /// most recent events are kept in memory then.
+    fn write_events<W: Write>(&self, writer: &mut W) -> Result<usize> {
+        match &self.log {
+            Some(log) => {
+                let reader = EventLogReader::open(log.dir())?;
+                write_json_array(writer, reader.events(&EventFilter::default(), None, None))
+            }
+            None => write_json_array(writer, self.events.iter().map(Ok)),
+        }
+    }
+
     /// Export events to file
     pub async fn export_events(&self, file_path: &PathBuf) -> Result<()> {
-        let events_json = serde_json::to_string_pretty(&self.events)?;
-        std::fs::write(file_path, events_json)?;
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        let count = self.write_events(&mut writer)?;
+        writer.flush()?;
         
-        info!("💾 Exported {} events to {:?}", self.events.len(), file_path);
+        info!("💾 Exported {} events to {:?}", count, file_path);
         Ok(())
     }
 
     /// Export events in replay format
+    ///
+    /// Written field by field so the events can be streamed; the result reads back as [`ReplayData`].
     pub async fn export_replay(&self, file_path: &PathBuf) -> Result<()> {
-        let replay_data = ReplayData {
-            version: "1.0".to_string(),
-            game_name: "Warcraft II Remastered".to_string(),
-            created_at: Utc::now(),
-            events: self.events.clone(),
-            metadata: HashMap::new(),
-        };
-
-        let replay_json = serde_json::to_string_pretty(&replay_data)?;
-        std::fs::write(file_path, replay_json)?;
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        write!(writer, "{{\n  \"version\": {},\n  \"game_name\": {},\n  \"created_at\": {},\n  \"metadata\": {{}},\n  \"events\": ",
+            serde_json::to_string("1.0")?,
+            serde_json::to_string("Warcraft II Remastered")?,
+            serde_json::to_string(&Utc::now())?)?;
+        let count = self.write_events(&mut writer)?;
+        writer.write_all(b"\n}\n")?;
+        writer.flush()?;
         
-        info!("🎮 Exported replay with {} events to {:?}", self.events.len(), file_path);
+        info!("🎮 Exported replay with {} events to {:?}", count, file_path);
         Ok(())
     }
 
@@ -460,10 +437,11 @@ impl EventRecorder {
         &self.stats
     }
 
-    /// Clear all recorded events
+    /// Clear all recorded events kept in memory; an attached event log keeps them
     pub fn clear_events(&mut self) {
         self.events.clear();
-        self.next_event_id = 1;
+        // IDs stay unique within the log
+        self.next_event_id = self.log.as_ref().map_or(1, |log| log.last_id() + 1);
         self.stats.total_events = 0;
         self.stats.events_by_type.clear();
         self.stats.events_by_player.clear();
@@ -472,6 +450,20 @@ impl EventRecorder {
     }
 }
 
+/// Write `items` as a JSON array with one item per line, returning how many were written
+fn write_json_array<W: Write, T: Serialize>(writer: &mut W, items: impl IntoIterator<Item = Result<T>>) -> Result<usize> {
+    let mut count = 0;
+    writer.write_all(b"[")?;
+    for item in items {
+        let item = item?;
+        writer.write_all(if count == 0 { b"\n  " } else { b",\n  " })?;
+        serde_json::to_writer(&mut *writer, &item)?;
+        count += 1;
+    }
+    writer.write_all(if count == 0 { b"]" } else { b"\n]" })?;
+    Ok(count)
+}
+
 /// Replay data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplayData {
@@ -487,6 +479,17 @@ pub struct ReplayData {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+impl EventFilter {
+    /// Whether an event of this type and player passes the filter
+    pub fn matches(&self, event_type: &EventType, player_id: Option<u8>) -> bool {
+        if !self.event_types.is_empty() && !self.event_types.contains(event_type) {
+            return false;
+        }
+        // Events without a player only pass filters without player IDs
+        self.player_ids.is_empty() || player_id.is_some_and(|pid| self.player_ids.contains(&pid))
+    }
+}
+
 impl Default for EventFilter {
     fn default() -> Self {
         Self {
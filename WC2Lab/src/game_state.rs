@@ -4,7 +4,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
+use crate::layout_schema::LayoutSchema;
 use crate::memory::ProcessMemoryMap;
+use wc2_shared::game_rules::GameRules;
+use wc2_shared::memory_source::MemorySource;
 
 /// Complete game state for WC2 Remastered
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +36,38 @@ pub struct GameState {
     pub victory_conditions: VictoryConditions,
 }
 
+impl GameState {
+    /// A running game with nothing on it yet, for decoders to fill in
+    pub fn empty() -> Self {
+        let victory_conditions = VictoryConditions {
+            victory_type: VictoryType::Annihilation,
+            time_limit: None,
+            resource_goal: None,
+            unit_goal: None,
+        };
+        Self {
+            timestamp: Utc::now(),
+            game_phase: GamePhase::Running,
+            map: MapInfo {
+                name: String::new(),
+                dimensions: (0, 0),
+                map_type: MapType::Custom,
+                starting_resources: ResourceInfo { gold: 0, wood: 0, ore: 0, oil: 0 },
+                victory_conditions: victory_conditions.clone(),
+                tiles: Vec::new(),
+            },
+            players: Vec::new(),
+            units: Vec::new(),
+            buildings: Vec::new(),
+            resources: HashMap::new(),
+            game_time: 0,
+            frame: 0,
+            game_speed: GameSpeed::Normal,
+            victory_conditions,
+        }
+    }
+}
+
 /// Game phases in WC2
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GamePhase {
@@ -186,7 +221,7 @@ pub struct UnitInfo {
     pub is_attacking: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnitType {
     // Human units
     Peasant,
@@ -216,6 +251,39 @@ pub enum UnitType {
     Castle,
 }
 
+impl UnitType {
+    /// Key of this type in the shared game rules; WC1 casters have none
+    pub fn rules_key(&self) -> Option<&'static str> {
+        Some(match self {
+            UnitType::Peasant => "peasant",
+            UnitType::Footman => "footman",
+            UnitType::Knight => "knight",
+            UnitType::Archer => "archer",
+            UnitType::Paladin => "paladin",
+            UnitType::Mage => "mage",
+            UnitType::Peon => "peon",
+            UnitType::Grunt => "grunt",
+            UnitType::Troll => "axethrower",
+            UnitType::Ogre => "ogre",
+            UnitType::Catapult => "catapult",
+            UnitType::DeathKnight => "death_knight",
+            UnitType::GoldMine => "gold_mine",
+            UnitType::OilPlatform => "oil_platform",
+            UnitType::Farm => "farm",
+            UnitType::Barracks => "barracks",
+            UnitType::Church => "church",
+            UnitType::Tower => "guard_tower",
+            UnitType::Castle => "castle",
+            UnitType::Cleric | UnitType::Shaman => return None,
+        })
+    }
+
+    /// Full health of this type from the embedded game rules
+    pub fn max_health(&self) -> Option<u32> {
+        GameRules::embedded().unit(self.rules_key()?).map(|rules| rules.hp)
+    }
+}
+
 /// Unit orders
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnitOrder {
@@ -266,7 +334,7 @@ pub struct BuildingInfo {
     pub production_queue: Vec<ProductionInfo>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BuildingType {
     // Human buildings
     TownHall,
@@ -295,6 +363,39 @@ pub enum BuildingType {
     OilPlatform,
 }
 
+impl BuildingType {
+    /// Key of this type in the shared game rules
+    pub fn rules_key(&self) -> &'static str {
+        match self {
+            BuildingType::TownHall => "town_hall",
+            BuildingType::HumanBarracks => "barracks",
+            BuildingType::Church => "church",
+            BuildingType::MageTower => "mage_tower",
+            BuildingType::HumanFarm => "farm",
+            BuildingType::HumanLumberMill => "lumber_mill",
+            BuildingType::HumanBlacksmith => "blacksmith",
+            BuildingType::HumanTower => "guard_tower",
+            BuildingType::Castle => "castle",
+            BuildingType::GreatHall => "great_hall",
+            BuildingType::OrcBarracks => "orc_barracks",
+            BuildingType::Temple => "temple_of_the_damned",
+            BuildingType::OgreMound => "ogre_mound",
+            BuildingType::OrcFarm => "pig_farm",
+            BuildingType::OrcLumberMill => "troll_lumber_mill",
+            BuildingType::OrcBlacksmith => "orc_blacksmith",
+            BuildingType::OrcTower => "orc_guard_tower",
+            BuildingType::Fortress => "fortress",
+            BuildingType::GoldMine => "gold_mine",
+            BuildingType::OilPlatform => "oil_platform",
+        }
+    }
+
+    /// Full health of this type from the embedded game rules
+    pub fn max_health(&self) -> Option<u32> {
+        GameRules::embedded().unit(self.rules_key()).map(|rules| rules.hp)
+    }
+}
+
 /// Production information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProductionInfo {
@@ -306,14 +407,14 @@ pub struct ProductionInfo {
     pub time_remaining: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProductType {
     Unit(UnitType),
     Building(BuildingType),
     Upgrade(UpgradeType),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UpgradeType {
     // Human upgrades
     HumanArmor,
@@ -383,6 +484,8 @@ pub struct GameStateTracker {
     max_states: usize,
     /// State change detection thresholds
     change_thresholds: ChangeThresholds,
+    /// Layout the state is decoded with; without one a sample state is produced
+    schema: Option<LayoutSchema>,
 }
 
 /// Thresholds for detecting state changes
@@ -396,26 +499,42 @@ pub struct ChangeThresholds {
     pub resource_change: u32,
 }
 
+impl Default for ChangeThresholds {
+    fn default() -> Self {
+        Self {
+            health_change: 5,
+            position_change: 1.0,
+            resource_change: 10,
+        }
+    }
+}
+
 impl GameStateTracker {
     /// Create a new game state tracker
     pub fn new() -> Result<Self> {
         Ok(Self {
             previous_states: Vec::new(),
             max_states: 100,
-            change_thresholds: ChangeThresholds {
-                health_change: 5,
-                position_change: 1.0,
-                resource_change: 10,
-            },
+            change_thresholds: ChangeThresholds::default(),
+            schema: None,
         })
     }
 
-    /// Track game state from memory map
-    pub async fn track_state(&mut self, memory_map: &ProcessMemoryMap) -> Result<GameState> {
-        info!("📊 Tracking game state from memory map (PID: {})", memory_map.pid);
+    /// Decode game state from memory with `schema`
+    pub fn with_schema(mut self, schema: LayoutSchema) -> Self {
+        info!("📐 Decoding game state with layout schema for build {}", schema.build);
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Track game state from the regions of `memory_map`, read through `source`
+    ///
+    /// `source` can be the live process or a snapshot of it taken earlier.
+    pub async fn track_state(&mut self, source: &dyn MemorySource, memory_map: &ProcessMemoryMap) -> Result<GameState> {
+        info!("📊 Tracking game state from {} (PID: {})", source.describe(), memory_map.pid);
         
         // Extract game state from memory regions
-        let game_state = self.extract_game_state(memory_map).await?;
+        let game_state = self.extract_game_state(source, memory_map).await?;
         
         // Detect significant changes
         if let Some(previous_state) = self.previous_states.last() {
@@ -441,7 +560,16 @@ impl GameStateTracker {
     }
 
     /// Extract game state from memory map
-    async fn extract_game_state(&self, memory_map: &ProcessMemoryMap) -> Result<GameState> {
+    async fn extract_game_state(&self, source: &dyn MemorySource, memory_map: &ProcessMemoryMap) -> Result<GameState> {
+        // Fail early when the mapped memory is gone, e.g. the game has exited
+        if let Some(region) = memory_map.regions.first() {
+            source.read_bytes(region.base_address, 1)?;
+        }
+
+        if let Some(schema) = &self.schema {
+            return schema.decode_state(source);
+        }
+
         // This is where we'd implement the actual memory parsing
         // For now, create a sample state for testing
         
@@ -596,6 +724,17 @@ impl GameStateTracker {
         changes
     }
 
+    /// Detect changes with `thresholds` instead of the defaults
+    pub fn with_change_thresholds(mut self, thresholds: ChangeThresholds) -> Self {
+        self.change_thresholds = thresholds;
+        self
+    }
+
+    /// Thresholds changes are detected with
+    pub fn change_thresholds(&self) -> &ChangeThresholds {
+        &self.change_thresholds
+    }
+
     /// Get the most recent game state
     pub fn get_latest_state(&self) -> Option<&GameState> {
         self.previous_states.last()
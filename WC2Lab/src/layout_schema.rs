@@ -0,0 +1,716 @@
+//! Declarative memory layouts
+//!
+//! A [`LayoutSchema`] describes where the game keeps its structures and how
+//! their fields are encoded, so that reading game state from memory needs no
+//! code per game build: a patch that moves the unit table only needs a new
+//! schema file. Schemas live in one TOML file per build, found like signature
+//! files by build name or executable hash:
+//!
+//! ```toml
+//! format = 1
+//! build = "1.0.1.6321"
+//! exe_sha256 = "…"
+//! pointer_size = 4
+//!
+//! [[structure]]
+//! name = "units"
+//! kind = "units"
+//! base = "war2.exe+0x1A2B3C -> +0x0"
+//! stride = 0x98
+//! count = 1600
+//! skip_if_zero = "health"
+//!
+//! [[structure.field]]
+//! name = "type"
+//! offset = 0x20
+//! type = "u8"
+//! values = { 2 = "Footman", 3 = "Grunt" }
+//! ```
+//!
+//! Structures with a `kind` fill the matching part of [`GameState`]; their
+//! fields are picked up by name (see [`StructureKind`]). Every structure can
+//! also be decoded into analysis [`DataStructure`]s.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wc2_shared::memory_source::MemorySource;
+
+use crate::analysis::{DataStructure, FieldType, StructureField};
+use crate::game_state::{BuildingInfo, BuildingType, GameState, PlayerColor, PlayerInfo, Race, ResourceInfo, UnitInfo, UnitType};
+use crate::pointer_scan::PointerPath;
+use crate::signatures::{find_build_file, BuildFile};
+
+/// Schema file format understood by [`LayoutSchema::load`]
+pub const SCHEMA_FORMAT_VERSION: u32 = 1;
+
+/// Part of the game state a structure holds
+///
+/// Fields are matched by name; missing ones keep a default.
+/// - `units`: `id`, `type`, `x`, `y`, `owner`, `health`, `mana`, `experience`,
+///   `target`, `selected`, `moving`, `attacking`
+/// - `buildings`: `id`, `type`, `x`, `y`, `owner`, `health`, `construction`, `selected`
+///
+/// `health` of units and buildings is read as hit points and stored as a
+/// percentage of the type's full health in the game rules.
+/// - `players`: `id`, `name`, `race`, `color`, `human`, `alive`, `team`,
+///   `gold`, `wood`, `ore`, `oil`, `score`
+/// - `game`: `phase`, `speed`, `game_time`, `frame`, `map_name`, `map_width`, `map_height`
+///
+/// Enum fields (`type`, `race`, `color`, `phase`, `speed`) need `values` mapping
+/// raw numbers to variant names; a slot whose value has no mapping is skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructureKind {
+    Units,
+    Buildings,
+    Players,
+    Game,
+}
+
+/// Encoding of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaFieldType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    Bool,
+    Pointer,
+    /// NUL-terminated text in a fixed `size` buffer
+    String,
+}
+
+impl SchemaFieldType {
+    fn field_type(self) -> FieldType {
+        match self {
+            SchemaFieldType::U8 | SchemaFieldType::I8 => FieldType::Integer8,
+            SchemaFieldType::U16 | SchemaFieldType::I16 => FieldType::Integer16,
+            SchemaFieldType::U32 | SchemaFieldType::I32 => FieldType::Integer32,
+            SchemaFieldType::F32 => FieldType::Float32,
+            SchemaFieldType::Bool => FieldType::Boolean,
+            SchemaFieldType::Pointer => FieldType::Pointer,
+            SchemaFieldType::String => FieldType::String,
+        }
+    }
+}
+
+/// One `[[structure.field]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaField {
+    pub name: String,
+    /// Bytes from the start of the record
+    pub offset: usize,
+    #[serde(rename = "type")]
+    pub field_type: SchemaFieldType,
+    /// Buffer length of a string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    /// Multiplier applied to numbers, e.g. 0.03125 for pixel positions in tiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// Names for raw values, keyed by the value in decimal
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, String>,
+}
+
+impl SchemaField {
+    fn size(&self, pointer_size: usize) -> usize {
+        match self.field_type {
+            SchemaFieldType::U8 | SchemaFieldType::I8 | SchemaFieldType::Bool => 1,
+            SchemaFieldType::U16 | SchemaFieldType::I16 => 2,
+            SchemaFieldType::U32 | SchemaFieldType::I32 | SchemaFieldType::F32 => 4,
+            SchemaFieldType::Pointer => pointer_size,
+            SchemaFieldType::String => self.size.unwrap_or(0),
+        }
+    }
+
+    /// Decode the field from a record's bytes
+    fn decode(&self, record: &[u8], pointer_size: usize) -> Value {
+        let bytes = &record[self.offset..self.offset + self.size(pointer_size)];
+        let number = match self.field_type {
+            SchemaFieldType::U8 => bytes[0] as f64,
+            SchemaFieldType::I8 => bytes[0] as i8 as f64,
+            SchemaFieldType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            SchemaFieldType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            SchemaFieldType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            SchemaFieldType::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            SchemaFieldType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            SchemaFieldType::Bool => return Value::Bool(bytes[0] != 0),
+            SchemaFieldType::Pointer => {
+                let mut pointer = [0u8; 8];
+                pointer[..bytes.len()].copy_from_slice(bytes);
+                return Value::from(u64::from_le_bytes(pointer));
+            }
+            SchemaFieldType::String => {
+                let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+                return Value::String(String::from_utf8_lossy(&bytes[..end]).into_owned());
+            }
+        };
+
+        if let Some(name) = self.values.get(&(number as i64).to_string()) {
+            return Value::String(name.clone());
+        }
+        match self.scale {
+            Some(scale) => Value::from(number * scale),
+            None if self.field_type == SchemaFieldType::F32 => Value::from(number),
+            None => Value::from(number as i64),
+        }
+    }
+}
+
+/// One `[[structure]]` entry: a record, or an array of records, found through a pointer path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaStructure {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<StructureKind>,
+    /// Pointer path to the first record
+    pub base: String,
+    /// Bytes between records; defaults to the end of the last field
+    #[serde(default)]
+    pub stride: usize,
+    #[serde(default = "default_count")]
+    pub count: usize,
+    /// Field marking unused slots of an array by being zero
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_if_zero: Option<String>,
+    #[serde(default, rename = "field")]
+    pub fields: Vec<SchemaField>,
+}
+
+fn default_count() -> usize {
+    1
+}
+
+fn default_pointer_size() -> usize {
+    4
+}
+
+impl SchemaStructure {
+    fn record_size(&self, pointer_size: usize) -> usize {
+        let end = self.fields.iter().map(|field| field.offset + field.size(pointer_size)).max().unwrap_or(0);
+        self.stride.max(end)
+    }
+}
+
+/// A decoded record
+#[derive(Debug, Clone)]
+struct Record {
+    index: usize,
+    address: u64,
+    values: HashMap<String, Value>,
+}
+
+impl Record {
+    fn number(&self, name: &str) -> Option<f64> {
+        match self.values.get(name)? {
+            Value::Bool(flag) => Some(if *flag { 1.0 } else { 0.0 }),
+            value => value.as_f64(),
+        }
+    }
+
+    fn integer<T: TryFrom<i64>>(&self, name: &str) -> Option<T> {
+        self.number(name).and_then(|number| T::try_from(number as i64).ok())
+    }
+
+    /// A number clamped to 0-255, for percentages and levels
+    fn byte(&self, name: &str) -> Option<u8> {
+        self.number(name).map(|number| number.clamp(0.0, 255.0) as u8)
+    }
+
+    /// Hit points as a percentage of `max_health`; without it they are taken to be one already
+    fn health(&self, max_health: Option<u32>) -> Option<u8> {
+        let number = self.number("health")?;
+        let percent = match max_health.filter(|&max| max > 0) {
+            Some(max) => number * 100.0 / f64::from(max),
+            None => number,
+        };
+        Some(percent.clamp(0.0, 100.0).round() as u8)
+    }
+
+    fn flag(&self, name: &str) -> Option<bool> {
+        self.number(name).map(|number| number != 0.0)
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        self.values.get(name)?.as_str().map(str::to_string)
+    }
+
+    /// A mapped enum value as the game-state type with that variant name
+    fn variant<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        serde_json::from_value(self.values.get(name)?.clone()).ok()
+    }
+
+    fn position(&self) -> (f32, f32) {
+        (self.number("x").unwrap_or(0.0) as f32, self.number("y").unwrap_or(0.0) as f32)
+    }
+}
+
+/// The memory layout of one game build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutSchema {
+    pub format: u32,
+    /// Game build the layout was made for, e.g. "1.0.1.6321"
+    pub build: String,
+    /// SHA-256 of the game executable of that build, lowercase hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe_sha256: Option<String>,
+    /// 4 for the 32-bit game, 8 for 64-bit builds
+    #[serde(default = "default_pointer_size")]
+    pub pointer_size: usize,
+    #[serde(default, rename = "structure")]
+    pub structures: Vec<SchemaStructure>,
+}
+
+impl LayoutSchema {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read layout schema {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid layout schema {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let schema: Self = toml::from_str(text)?;
+        if schema.format != SCHEMA_FORMAT_VERSION {
+            bail!("Schema format {} is not supported, only format {}", schema.format, SCHEMA_FORMAT_VERSION);
+        }
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    /// Find the file in `dir` for a build, matching its build name or executable hash
+    pub fn find(dir: &Path, build_or_sha256: &str) -> Result<Option<(PathBuf, Self)>> {
+        find_build_file(dir, build_or_sha256)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.pointer_size != 4 && self.pointer_size != 8 {
+            bail!("Pointer size must be 4 or 8 bytes, not {}", self.pointer_size);
+        }
+        for structure in &self.structures {
+            structure.base.parse::<PointerPath>().with_context(|| format!("Structure {}", structure.name))?;
+            if structure.count == 0 {
+                bail!("Structure {} has no records", structure.name);
+            }
+            if structure.count > 1 && structure.stride == 0 {
+                bail!("Structure {} has {} records but no stride", structure.name, structure.count);
+            }
+            for field in &structure.fields {
+                if field.field_type == SchemaFieldType::String && field.size.unwrap_or(0) == 0 {
+                    bail!("String field {}.{} needs a size", structure.name, field.name);
+                }
+                if structure.stride > 0 && field.offset + field.size(self.pointer_size) > structure.stride {
+                    bail!("Field {}.{} runs past the stride of 0x{:x}", structure.name, field.name, structure.stride);
+                }
+            }
+            if let Some(marker) = &structure.skip_if_zero {
+                if !structure.fields.iter().any(|field| &field.name == marker) {
+                    bail!("Structure {} skips slots on unknown field {}", structure.name, marker);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the records of a structure, leaving out unused slots
+    fn read_records(&self, structure: &SchemaStructure, source: &dyn MemorySource) -> Result<Vec<Record>> {
+        let path: PointerPath = structure.base.parse()?;
+        let base = path.resolve(source, self.pointer_size)?;
+        let record_size = structure.record_size(self.pointer_size);
+        let stride = structure.stride.max(record_size);
+
+        // Read the whole array at once; a short read keeps the complete records
+        let bytes = source.read_bytes(base, stride * (structure.count - 1) + record_size)?;
+        let mut records = Vec::new();
+        for index in 0..structure.count {
+            let start = index * stride;
+            let Some(record) = bytes.get(start..start + record_size) else {
+                break;
+            };
+            let values: HashMap<String, Value> = structure.fields.iter()
+                .map(|field| (field.name.clone(), field.decode(record, self.pointer_size)))
+                .collect();
+            let record = Record { index, address: base + start as u64, values };
+            if structure.skip_if_zero.as_deref().is_some_and(|marker| record.number(marker) == Some(0.0)) {
+                continue;
+            }
+            records.push(record);
+        }
+        debug!("📐 {}: {} records at 0x{:x}", structure.name, records.len(), base);
+        Ok(records)
+    }
+
+    /// Every record of every structure, as analysis data structures
+    ///
+    /// Structures whose base cannot be resolved, e.g. before a game has started,
+    /// are skipped with a warning.
+    pub fn decode_structures(&self, source: &dyn MemorySource) -> Vec<DataStructure> {
+        let mut decoded = Vec::new();
+        for structure in &self.structures {
+            let records = match self.read_records(structure, source) {
+                Ok(records) => records,
+                Err(e) => {
+                    warn!("⚠️ Skipping structure {}: {}", structure.name, e);
+                    continue;
+                }
+            };
+            for mut record in records {
+                let fields = structure.fields.iter()
+                    .map(|field| StructureField {
+                        name: field.name.clone(),
+                        offset: field.offset,
+                        size: field.size(self.pointer_size),
+                        field_type: field.field_type.field_type(),
+                        value: record.values.remove(&field.name),
+                    })
+                    .collect();
+                decoded.push(DataStructure {
+                    name: format!("{}[{}]", structure.name, record.index),
+                    address: record.address,
+                    size: structure.record_size(self.pointer_size),
+                    fields,
+                    confidence: 1.0,
+                });
+            }
+        }
+        decoded
+    }
+
+    /// Game state decoded from the structures with a kind
+    ///
+    /// Unlike [`Self::decode_structures`] this fails when any of them cannot be
+    /// read: a state missing its units would look like every unit had died.
+    pub fn decode_state(&self, source: &dyn MemorySource) -> Result<GameState> {
+        let mut state = GameState::empty();
+        for structure in &self.structures {
+            let Some(kind) = structure.kind else {
+                continue;
+            };
+            let records = self.read_records(structure, source)
+                .with_context(|| format!("Failed to read structure {} from {}", structure.name, source.describe()))?;
+            match kind {
+                StructureKind::Units => state.units.extend(records.iter().filter_map(unit)),
+                StructureKind::Buildings => state.buildings.extend(records.iter().filter_map(building)),
+                StructureKind::Players => {
+                    for player in records.iter().filter_map(player) {
+                        state.resources.insert(player.id, player.resources.clone());
+                        state.players.push(player);
+                    }
+                }
+                StructureKind::Game => {
+                    if let Some(record) = records.first() {
+                        apply_game(record, &mut state);
+                    }
+                }
+            }
+        }
+
+        info!("📐 Decoded {} players, {} units and {} buildings from {}",
+              state.players.len(), state.units.len(), state.buildings.len(), source.describe());
+        Ok(state)
+    }
+}
+
+fn unit(record: &Record) -> Option<UnitInfo> {
+    let unit_type: UnitType = record.variant("type")?;
+    Some(UnitInfo {
+        id: record.integer("id").or_else(|| u32::try_from(record.index).ok())?,
+        position: record.position(),
+        owner: record.integer("owner").unwrap_or(0),
+        health: record.health(unit_type.max_health()).unwrap_or(0),
+        unit_type,
+        mana: record.byte("mana").unwrap_or(0),
+        experience: record.byte("experience").unwrap_or(0),
+        orders: Vec::new(),
+        target: record.integer("target").filter(|&target: &u32| target != 0),
+        is_selected: record.flag("selected").unwrap_or(false),
+        is_moving: record.flag("moving").unwrap_or(false),
+        is_attacking: record.flag("attacking").unwrap_or(false),
+    })
+}
+
+fn building(record: &Record) -> Option<BuildingInfo> {
+    let building_type: BuildingType = record.variant("type")?;
+    Some(BuildingInfo {
+        id: record.integer("id").or_else(|| u32::try_from(record.index).ok())?,
+        position: record.position(),
+        owner: record.integer("owner").unwrap_or(0),
+        health: record.health(building_type.max_health()).unwrap_or(0),
+        building_type,
+        construction_progress: record.byte("construction").unwrap_or(100),
+        is_selected: record.flag("selected").unwrap_or(false),
+        current_production: None,
+        production_queue: Vec::new(),
+    })
+}
+
+fn player(record: &Record) -> Option<PlayerInfo> {
+    const COLORS: [PlayerColor; 8] = [
+        PlayerColor::Red, PlayerColor::Blue, PlayerColor::Green, PlayerColor::Purple,
+        PlayerColor::Orange, PlayerColor::Pink, PlayerColor::Teal, PlayerColor::Yellow,
+    ];
+    let id: u8 = record.integer("id").or_else(|| u8::try_from(record.index).ok())?;
+    let amount = |name: &str| record.integer(name).unwrap_or(0);
+    Some(PlayerInfo {
+        id,
+        name: record.text("name").filter(|name| !name.is_empty()).unwrap_or_else(|| format!("Player {}", u32::from(id) + 1)),
+        race: record.variant("race").unwrap_or(Race::Neutral),
+        color: record.variant("color").unwrap_or_else(|| COLORS[id as usize % COLORS.len()].clone()),
+        is_human: record.flag("human").unwrap_or(false),
+        is_alive: record.flag("alive").unwrap_or(true),
+        team: record.integer("team"),
+        resources: ResourceInfo { gold: amount("gold"), wood: amount("wood"), ore: amount("ore"), oil: amount("oil") },
+        score: amount("score"),
+    })
+}
+
+fn apply_game(record: &Record, state: &mut GameState) {
+    if let Some(phase) = record.variant("phase") {
+        state.game_phase = phase;
+    }
+    if let Some(speed) = record.variant("speed") {
+        state.game_speed = speed;
+    }
+    state.game_time = record.integer("game_time").unwrap_or(state.game_time);
+    state.frame = record.integer("frame").unwrap_or(state.frame);
+    if let Some(name) = record.text("map_name") {
+        state.map.name = name;
+    }
+    let (width, height) = state.map.dimensions;
+    state.map.dimensions = (record.integer("map_width").unwrap_or(width), record.integer("map_height").unwrap_or(height));
+}
+
+impl BuildFile for LayoutSchema {
+    const KIND: &'static str = "layout schemas";
+
+    fn load(path: &Path) -> Result<Self> {
+        Self::load(path)
+    }
+
+    fn matches(&self, build_or_sha256: &str) -> bool {
+        self.build == build_or_sha256
+            || self.exe_sha256.as_ref().is_some_and(|hash| hash.eq_ignore_ascii_case(build_or_sha256))
+    }
+}
+
+/// Load a schema file, or the one for a build or executable hash in `dir`
+pub fn load_schema(dir: &Path, file_or_build: &str) -> Result<LayoutSchema> {
+    if Path::new(file_or_build).is_file() {
+        return LayoutSchema::load(Path::new(file_or_build));
+    }
+    LayoutSchema::find(dir, file_or_build)?
+        .map(|(_, schema)| schema)
+        .ok_or_else(|| anyhow!("No layout schema for build {} in {}", file_or_build, dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wc2_shared::memory_source::{MemoryRegion, SnapshotSource};
+
+    const SCHEMA: &str = r#"
+format = 1
+build = "test"
+
+[[structure]]
+name = "game"
+kind = "game"
+base = "war2.exe+0x100"
+
+[[structure.field]]
+name = "frame"
+offset = 0
+type = "u32"
+
+[[structure.field]]
+name = "map_width"
+offset = 4
+type = "u16"
+
+[[structure]]
+name = "players"
+kind = "players"
+base = "war2.exe+0x200"
+stride = 0x20
+count = 2
+
+[[structure.field]]
+name = "race"
+offset = 0
+type = "u8"
+values = { 0 = "Human", 1 = "Orc" }
+
+[[structure.field]]
+name = "gold"
+offset = 4
+type = "u32"
+
+[[structure.field]]
+name = "name"
+offset = 8
+type = "string"
+size = 16
+
+[[structure]]
+name = "units"
+kind = "units"
+base = "war2.exe+0x10 -> +0x0"
+stride = 0x10
+count = 4
+skip_if_zero = "health"
+
+[[structure.field]]
+name = "type"
+offset = 0
+type = "u8"
+values = { 2 = "Footman", 3 = "Grunt", 10 = "TownHall" }
+
+[[structure.field]]
+name = "health"
+offset = 1
+type = "u8"
+
+[[structure.field]]
+name = "x"
+offset = 4
+type = "u16"
+scale = 0.5
+
+[[structure.field]]
+name = "y"
+offset = 6
+type = "u16"
+scale = 0.5
+
+[[structure]]
+name = "buildings"
+kind = "buildings"
+base = "war2.exe+0x10 -> +0x0"
+stride = 0x10
+count = 4
+skip_if_zero = "health"
+
+[[structure.field]]
+name = "type"
+offset = 0
+type = "u8"
+values = { 10 = "TownHall" }
+
+[[structure.field]]
+name = "health"
+offset = 1
+type = "u8"
+"#;
+
+    fn memory() -> SnapshotSource {
+        memory_with_units_at(0x600000)
+    }
+
+    fn memory_with_units_at(units_pointer: u32) -> SnapshotSource {
+        let mut module = vec![0u8; 0x400];
+        module[0x10..0x14].copy_from_slice(&units_pointer.to_le_bytes());
+        module[0x100..0x104].copy_from_slice(&1234u32.to_le_bytes());
+        module[0x104..0x106].copy_from_slice(&128u16.to_le_bytes());
+        module[0x220] = 1;
+        module[0x224..0x228].copy_from_slice(&2000u32.to_le_bytes());
+        module[0x228..0x22d].copy_from_slice(b"Grom\0");
+
+        // A footman, an empty slot, a town hall and a grunt
+        let mut units = vec![0u8; 0x40];
+        units[0x00..0x08].copy_from_slice(&[2, 60, 0, 0, 20, 0, 40, 0]);
+        units[0x20..0x22].copy_from_slice(&[10, 100]);
+        units[0x30..0x32].copy_from_slice(&[3, 30]);
+
+        let mut source = SnapshotSource::new("test");
+        let region = |base: u64, name: Option<&str>| MemoryRegion {
+            base,
+            size: 0,
+            readable: true,
+            writable: true,
+            executable: false,
+            name: name.map(str::to_string),
+        };
+        source.add_region(region(0x400000, Some("war2.exe")), module).unwrap();
+        source.add_region(region(0x600000, None), units).unwrap();
+        source
+    }
+
+    #[test]
+    fn test_decode_state() {
+        let schema = LayoutSchema::parse(SCHEMA).unwrap();
+        let state = schema.decode_state(&memory()).unwrap();
+
+        assert_eq!(state.frame, 1234);
+        assert_eq!(state.map.dimensions, (128, 0));
+
+        assert_eq!(state.players.len(), 2);
+        assert!(matches!(state.players[0].race, Race::Human));
+        assert_eq!(state.players[0].name, "Player 1");
+        assert!(matches!(state.players[1].race, Race::Orc));
+        assert_eq!((state.players[1].name.as_str(), state.players[1].resources.gold), ("Grom", 2000));
+        assert_eq!(state.resources[&1].gold, 2000);
+
+        // The town hall slot is not a unit type, and the empty slot is skipped
+        assert_eq!(state.units.len(), 2);
+        assert!(matches!(state.units[0].unit_type, UnitType::Footman));
+        // Hit points become a percentage of the type's full health
+        assert_eq!((state.units[0].id, state.units[0].health, state.units[0].position), (0, 100, (10.0, 20.0)));
+        assert!(matches!(state.units[1].unit_type, UnitType::Grunt));
+        assert_eq!((state.units[1].id, state.units[1].health), (3, 50));
+
+        assert_eq!(state.buildings.len(), 1);
+        assert!(matches!(state.buildings[0].building_type, BuildingType::TownHall));
+        assert_eq!(state.buildings[0].health, 8);
+    }
+
+    #[test]
+    fn test_unresolvable_structure_fails_the_state() {
+        // Before a game starts the unit table pointer is still null
+        let source = memory_with_units_at(0);
+        let schema = LayoutSchema::parse(SCHEMA).unwrap();
+
+        let error = schema.decode_state(&source).unwrap_err();
+        assert!(format!("{:#}", error).contains("structure units"));
+        // Structures on their own still decode the others
+        assert!(schema.decode_structures(&source).iter().any(|structure| structure.name == "game[0]"));
+    }
+
+    #[test]
+    fn test_decode_structures() {
+        let schema = LayoutSchema::parse(SCHEMA).unwrap();
+        let structures = schema.decode_structures(&memory());
+        let units: Vec<&DataStructure> = structures.iter().filter(|s| s.name.starts_with("units")).collect();
+        assert_eq!(units.len(), 3);
+        assert_eq!((units[0].name.as_str(), units[0].address, units[0].size), ("units[0]", 0x600000, 0x10));
+        assert!(matches!(units[0].fields[0].field_type, FieldType::Integer8));
+        assert_eq!(units[0].fields[0].value, Some(Value::String("Footman".to_string())));
+        assert_eq!(units[0].fields[2].value, Some(Value::from(10.0)));
+    }
+
+    #[test]
+    fn test_rejects_bad_schemas() {
+        assert!(LayoutSchema::parse("format = 2\nbuild = \"x\"").is_err());
+        let no_stride = "format = 1\nbuild = \"x\"\n[[structure]]\nname = \"u\"\nbase = \"0x10\"\ncount = 2";
+        assert!(LayoutSchema::parse(no_stride).is_err());
+        let past_stride = "format = 1\nbuild = \"x\"\n[[structure]]\nname = \"u\"\nbase = \"0x10\"\nstride = 2\n\
+                           [[structure.field]]\nname = \"hp\"\noffset = 0\ntype = \"u32\"";
+        assert!(LayoutSchema::parse(past_stride).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("test.toml"), SCHEMA).unwrap();
+        assert!(LayoutSchema::find(dir.path(), "test").unwrap().is_some());
+        assert!(LayoutSchema::find(dir.path(), "other").unwrap().is_none());
+
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("schemas").join("example.toml");
+        assert_eq!(LayoutSchema::load(&example).unwrap().structures.len(), 3);
+    }
+}
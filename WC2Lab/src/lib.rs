@@ -5,9 +5,15 @@
 
 pub mod core;
 pub mod memory;
+pub mod signatures;
+pub mod value_scan;
+pub mod pointer_scan;
+pub mod layout_schema;
 pub mod process;
 pub mod game_state;
 pub mod events;
+pub mod event_synth;
+pub mod event_log;
 pub mod analysis;
 pub mod utils;
 pub mod ai_agent;
@@ -16,10 +22,16 @@ pub mod custom_game_builder;
 // Re-export main types for convenience
 pub use core::{Laboratory, LaboratoryConfig, ExtractionDepth, OutputFormat};
 pub use process::{ProcessMonitor, WC2Process, ProcessStatus};
-pub use memory::{MemoryAnalyzer, ProcessMemoryMap, MemoryRegion, MemoryAnalysis};
+pub use memory::{MemoryAnalyzer, ProcessMemoryMap, MemoryRegion, MemoryAnalysis, MemorySignature, OffsetResolution};
+pub use signatures::{SignatureScanner, SignatureSet, SignatureMatch};
+pub use value_scan::{ValueScanSession, ValueType, FirstScan, NextScan};
+pub use pointer_scan::{PointerPath, PointerScanner, PointerScanConfig};
+pub use layout_schema::{LayoutSchema, StructureKind};
 pub use game_state::{GameState, GameStateTracker, GamePhase, UnitType, BuildingType};
 pub use events::{EventRecorder, GameEvent, EventType, EventData};
-pub use analysis::{AnalysisResult, AnalysisType, AnalysisData};
+pub use event_synth::{EventSynthesizer, SynthesizedEvent};
+pub use event_log::{EventLogWriter, EventLogReader, EventLogConfig};
+pub use analysis::{AnalysisResult, AnalysisType, AnalysisData, SessionAnalyzer, SessionAnalysisConfig};
 pub use ai_agent::{AIAgent, AIAction, ActionSequences, MenuTarget, GameType};
 pub use custom_game_builder::{CustomGameBuilder, BuildConfig, BuildType, BuildStatus};
 
@@ -1,12 +1,26 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::{info, warn, error};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::env;
 
 use wc2_remastered_lab::{
     create_laboratory, init_logging,
-    CustomGameBuilder, BuildConfig, BuildType
+    CustomGameBuilder, BuildConfig, BuildType,
+    MemoryAnalyzer, ProcessMemoryMap, GameStateTracker, SignatureSet,
+    ValueScanSession, ValueType, FirstScan, NextScan,
+    PointerPath, PointerScanner, PointerScanConfig
 };
+use wc2_remastered_lab::layout_schema::load_schema;
+use wc2_remastered_lab::events::{EventFilter, EventType};
+use wc2_remastered_lab::{EventLogReader, GameState};
+use wc2_remastered_lab::utils::{format_bytes, load_json};
+use wc2_remastered_lab::pointer_scan::parse_hex;
+use wc2_shared::memory_source::{self, MemorySource};
+use wc2_shared::wc2snap;
+use wc2_shared::dosbox::{find_dosbox_processes, DosboxSource};
+
+/// How long `analyze-game` tracks the game for by default
+const TRACKING_SECONDS: u64 = 60;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -26,14 +40,73 @@ async fn main() -> Result<()> {
             run_ai_agent_demo().await?;
         }
         Some("analyze-game") => {
-            run_real_time_analysis().await?;
+            let seconds = args.get(2).map(|s| s.parse().map_err(|_| anyhow!("Invalid duration: {}", s))).transpose()?;
+            run_real_time_analysis(seconds.unwrap_or(TRACKING_SECONDS)).await?;
+        }
+        Some("snapshot") => {
+            let note = (args.len() > 3).then(|| args[3..].join(" "));
+            capture_snapshot(args.get(2).map(|s| s.as_str()), note).await?;
+        }
+        Some("load-snapshot") => match args.get(2) {
+            Some(path) => load_snapshot(Path::new(path)).await?,
+            None => info!("Usage: {} load-snapshot <file.{}>", args[0], wc2snap::EXTENSION),
+        },
+        Some("value-scan") if args.len() >= 5 => {
+            value_scan(Path::new(&args[2]), &args[3], &args[4..]).await?;
+        }
+        Some("value-scan") => {
+            info!("Usage: {} value-scan <session> <pid|file.{}> first <u8|u16|u32|f32> <value|min..max|unknown>", args[0], wc2snap::EXTENSION);
+            info!("       {} value-scan <session> <pid|file.{}> next <changed|unchanged|increased [n]|decreased [n]|equals v|min..max>", args[0], wc2snap::EXTENSION);
+            info!("       {} value-scan <session> <pid|file.{}> list", args[0], wc2snap::EXTENSION);
+        }
+        Some("resolve-pointer") if args.len() >= 4 => {
+            resolve_pointer(&args[2], &args[3], args.get(4).map(|s| s.as_str())).await?;
+        }
+        Some("resolve-pointer") => {
+            info!("Usage: {} resolve-pointer <pid|file.{}> \"module+0x1A2B3C -> +0x10 -> +0x48\" [pointer size]", args[0], wc2snap::EXTENSION);
         }
+        Some("pointer-scan") if args.len() >= 3 => {
+            pointer_scan(&args[2..]).await?;
+        }
+        Some("pointer-scan") => {
+            info!("Usage: {} pointer-scan [--depth n] [--max-offset 0x..] [--pointer-size 4|8] <pid|file.{}>@<address>...", args[0], wc2snap::EXTENSION);
+            info!("       Paths start in a loaded module; dosbox targets have none, so they find no paths");
+        }
+        Some("decode-state") if args.len() >= 4 => {
+            decode_state(&args[2], &args[3]).await?;
+        }
+        Some("decode-state") => {
+            info!("Usage: {} decode-state <pid|file.{}> <schema.toml|build>", args[0], wc2snap::EXTENSION);
+        }
+        Some("read-events") => match args.get(2) {
+            Some(dir) => read_events(Path::new(dir), args.get(3).map(|s| s.as_str()), args.get(4).map(|s| s.as_str())).await?,
+            None => info!("Usage: {} read-events <log dir> [event type|all] [player]", args[0]),
+        },
+        Some("analyze-session") => match args.get(2) {
+            Some(dir) => analyze_session(Path::new(dir), args.get(3).map(Path::new)).await?,
+            None => info!("Usage: {} analyze-session <log dir> [game_states.json]", args[0]),
+        },
+        Some("scan-signatures") => match args.get(2) {
+            Some(target) => scan_signatures(target, args.get(3).map(|s| s.as_str())).await?,
+            None => info!("Usage: {} scan-signatures <pid|file.{}> [signatures.toml|build]", args[0], wc2snap::EXTENSION),
+        },
         Some(unknown) => {
             info!("Unknown command: {}", unknown);
             info!("Available commands:");
             info!("  custom-build  - Set up custom game build environment");
             info!("  ai-demo       - Run AI Agent demonstration");
-            info!("  analyze-game  - Analyze running WC2 Remastered game");
+            info!("  analyze-game [seconds] - Analyze running WC2 Remastered game and record its events");
+            info!("  snapshot [pid|auto] [note] - Capture the game's memory to a .wc2snap file");
+            info!("  load-snapshot <file> - Re-run analysis against a captured snapshot");
+            info!("  scan-signatures <pid|file> [signatures] - Find signatures in a process or snapshot");
+            info!("  value-scan <session> <pid|file> first|next|list ... - Narrow down the address of a value");
+            info!("  pointer-scan <pid|file>@<address>... - Find static pointer paths to a value in every snapshot");
+            info!("  resolve-pointer <pid|file> <path> - Follow a static pointer path");
+            info!("  decode-state <pid|file> <schema> - Decode game state with a layout schema");
+            info!("  (any <pid|file> may be dosbox[:pid|file] to read the DOS game inside DOSBox,");
+            info!("   except for pointer-scan: guest RAM has no modules to start paths from)");
+            info!("  read-events <log dir> [type] [player] - List events of a recorded event log");
+            info!("  analyze-session <log dir> [states] - Analyze a recorded event log and state history");
             info!("  (no args)     - Run default laboratory mode");
         }
         None => {
@@ -127,7 +200,10 @@ async fn run_ai_agent_demo() -> Result<()> {
 }
 
 /// **NEW: Run real-time game analysis using our AI Agent system**
-async fn run_real_time_analysis() -> Result<()> {
+///
+/// The game is then tracked for `seconds`, or until Ctrl+C, and its events are
+/// written to the event log of a laboratory session.
+async fn run_real_time_analysis(seconds: u64) -> Result<()> {
     info!("🔍 Starting Real-Time Game Analysis Mode...");
     info!("🎯 This will analyze the running WC2 Remastered game to create headless specifications");
     
@@ -177,7 +253,283 @@ async fn run_real_time_analysis() -> Result<()> {
     
     info!("🎉 Real-time analysis completed successfully!");
     info!("🚀 Next step: Use these specifications to create the headless version");
-    
+
+    track_game(game_analysis.process_info.pid, seconds).await
+}
+
+/// Track the game state of `pid` and record its events until `seconds` pass or Ctrl+C is pressed
+async fn track_game(pid: u32, seconds: u64) -> Result<()> {
+    let source = memory_source::open_process(pid)?;
+    let memory_map = ProcessMemoryMap::from_source(source.as_ref())?;
+
+    let mut laboratory = create_laboratory()?;
+    laboratory.start_session().await?;
+    let mut tracker = GameStateTracker::new()?;
+    let mut recorder = laboratory.event_recorder(&tracker)?;
+
+    info!("🎥 Recording events of PID {} for {}s (Ctrl+C to stop)...", pid, seconds);
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(laboratory.config().memory_scan_interval));
+    let deadline = tokio::time::sleep(std::time::Duration::from_secs(seconds));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let game_state = tracker.track_state(source.as_ref(), &memory_map).await?;
+                recorder.record_events(&game_state).await?;
+                laboratory.add_game_state(game_state);
+            }
+            _ = &mut deadline => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    recorder.stop_recording()?;
+    laboratory.save_results(&[]).await?;
+    info!("📼 Read the events back with read-events or analyze-session on the session's events directory");
+    Ok(())
+}
+
+/// Capture the memory of the running game, or of `pid`, to a snapshot file
+async fn capture_snapshot(pid: Option<&str>, note: Option<String>) -> Result<()> {
+    let (pid, process_name) = match pid.filter(|pid| *pid != "auto") {
+        Some(pid) => (pid.parse::<u32>().map_err(|_| anyhow!("Invalid PID: {}", pid))?, None),
+        None => memory_source::find_processes(&["warcraft"])?
+            .into_iter()
+            .next()
+            .map(|(pid, name)| (pid, Some(name)))
+            .ok_or_else(|| anyhow!("Warcraft II is not running; pass its PID"))?,
+    };
+    info!("📸 Capturing memory of PID {}...", pid);
+
+    let source = memory_source::open_process(pid)?;
+    let mut analyzer = MemoryAnalyzer::new()?;
+    let memory_map = analyzer.analyze_source(source.as_ref()).await?;
+
+    let output_dir = PathBuf::from("output").join("snapshots");
+    std::fs::create_dir_all(&output_dir)?;
+    let path = output_dir.join(format!("wc2-{}-{}.{}", pid, chrono::Utc::now().format("%Y%m%d-%H%M%S"), wc2snap::EXTENSION));
+    memory_map.capture_snapshot(source.as_ref(), &path, process_name.as_deref(), note.as_deref())?;
+
+    info!("💾 Snapshot saved to {}", path.display());
+    Ok(())
+}
+
+/// Run the memory analysis and state tracking against a snapshot file
+async fn load_snapshot(path: &Path) -> Result<()> {
+    let (memory_map, metadata, snapshot) = ProcessMemoryMap::load_snapshot(path)?;
+    info!("📊 Snapshot Details:");
+    info!("   • Process: {} (PID: {})", metadata.process_name.as_deref().unwrap_or("unknown"), memory_map.pid);
+    info!("   • Captured: {} on {}", metadata.captured_at, metadata.platform);
+    if let Some(note) = &metadata.note {
+        info!("   • Note: {}", note);
+    }
+    info!("   • Memory Regions: {} ({} bytes)", memory_map.regions.len(), memory_map.total_size);
+
+    let mut analyzer = MemoryAnalyzer::new()?;
+    analyzer.analyze_source(&snapshot).await?;
+    let stats = analyzer.get_stats();
+    info!("   • Game State Regions: {}", stats.game_state_regions);
+    info!("   • Unit Data Regions: {}", stats.unit_data_regions);
+
+    let mut tracker = GameStateTracker::new()?;
+    let game_state = tracker.track_state(&snapshot, &memory_map).await?;
+    info!("   • Game Phase: {:?}", game_state.game_phase);
+    info!("   • Units: {}, Buildings: {}", game_state.units.len(), game_state.buildings.len());
+
+    Ok(())
+}
+
+/// Open a process by PID or a snapshot file, or the DOS game inside one as `dosbox[:pid|file]`
+fn open_target(target: &str) -> Result<Box<dyn MemorySource>> {
+    let host = match target.strip_prefix("dosbox:") {
+        Some(host) => Some(open_target(host)?),
+        None if target == "dosbox" => {
+            let (pid, name) = find_dosbox_processes()?.into_iter().next()
+                .ok_or_else(|| anyhow!("No DOSBox process is running"))?;
+            info!("🎯 Found {} (PID: {})", name, pid);
+            Some(memory_source::open_process(pid)?)
+        }
+        None => None,
+    };
+    if let Some(host) = host {
+        let guest = DosboxSource::locate(host, None)?;
+        info!("🕹️  DOSBox guest RAM: {} at host 0x{:x}", format_bytes(guest.size()), guest.host_base());
+        return Ok(Box::new(guest));
+    }
+
+    match target.parse::<u32>() {
+        Ok(pid) => memory_source::open_process(pid),
+        Err(_) => Ok(Box::new(wc2snap::load(Path::new(target))?.1)),
+    }
+}
+
+/// Run one step of a value scan saved in `session_path`
+async fn value_scan(session_path: &Path, target: &str, step: &[String]) -> Result<()> {
+    let source = open_target(target)?;
+    let session = match step[0].as_str() {
+        "first" if step.len() == 3 => {
+            let memory_map = ProcessMemoryMap::from_source(source.as_ref())?;
+            let value_type: ValueType = step[1].parse()?;
+            let scan: FirstScan = step[2].parse()?;
+            ValueScanSession::first_scan(source.as_ref(), &memory_map, value_type, scan)?
+        }
+        "next" if step.len() >= 2 => {
+            let mut session = ValueScanSession::load(session_path)?;
+            let scan: NextScan = step[1..].join(" ").parse()?;
+            session.next_scan(source.as_ref(), scan)?;
+            session
+        }
+        "list" => ValueScanSession::load(session_path)?,
+        _ => return Err(anyhow!("Unknown value-scan step: {}", step.join(" "))),
+    };
+
+    info!("📊 {} candidates after {} scans:", session.len(), session.history.len());
+    for (candidate, current) in session.current_values(source.as_ref(), 20) {
+        info!("   • 0x{:x} = {} (was {})", candidate.address, current, candidate.value);
+    }
+    if step[0] != "list" {
+        session.save(session_path)?;
+    }
+    Ok(())
+}
+
+/// Follow a pointer path in a process or snapshot
+async fn resolve_pointer(target: &str, path: &str, pointer_size: Option<&str>) -> Result<()> {
+    let source = open_target(target)?;
+    let path: PointerPath = path.parse()?;
+    let pointer_size = match pointer_size {
+        Some(size) => size.parse().map_err(|_| anyhow!("Invalid pointer size: {}", size))?,
+        None => PointerScanConfig::default().pointer_size,
+    };
+
+    let base = path.base_address(source.as_ref())?;
+    info!("🔗 {} in {}:", path, source.describe());
+    info!("   • start 0x{:x}", base);
+    for hop in path.resolve_chain(source.as_ref(), pointer_size)? {
+        info!("   • [0x{:x}] {} = 0x{:x}", hop.source_address, hop.relationship_type, hop.target_address);
+    }
+    Ok(())
+}
+
+/// Find pointer paths that lead to the given address of each process or snapshot
+async fn pointer_scan(args: &[String]) -> Result<()> {
+    let mut config = PointerScanConfig::default();
+    let mut targets = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("{} needs a value", name));
+        match arg.as_str() {
+            "--depth" => config.max_depth = value(arg)?.parse().map_err(|_| anyhow!("Invalid depth"))?,
+            "--max-offset" => config.max_offset = parse_hex(value(arg)?)?,
+            "--pointer-size" => config.pointer_size = value(arg)?.parse().map_err(|_| anyhow!("Invalid pointer size"))?,
+            target => {
+                let (source, address) = target.rsplit_once('@')
+                    .ok_or_else(|| anyhow!("Expected <pid|file>@<address>, got {}", target))?;
+                targets.push((open_target(source)?, parse_hex(address)?));
+            }
+        }
+    }
+    if targets.len() < 2 {
+        warn!("⚠️ Paths from a single session are rarely stable; pass two or more snapshots");
+    }
+
+    let scanner = PointerScanner::new(config)?;
+    let sources: Vec<(&dyn MemorySource, u64)> = targets.iter().map(|(source, address)| (source.as_ref(), *address)).collect();
+    let paths = scanner.scan_all(&sources)?;
+
+    info!("📊 {} pointer paths in all {} sessions:", paths.len(), sources.len());
+    for path in paths.iter().take(50) {
+        info!("   • {}", path);
+    }
+    if paths.is_empty() {
+        warn!("⚠️ No pointer paths found; try a larger --depth or --max-offset");
+    }
+    Ok(())
+}
+
+/// Decode the game state of a process or snapshot with a schema file, or the schema of a build in `schemas/`
+async fn decode_state(target: &str, schema: &str) -> Result<()> {
+    let source = open_target(target)?;
+    let schema = load_schema(Path::new("schemas"), schema)?;
+    let memory_map = ProcessMemoryMap::from_source(source.as_ref())?;
+
+    let mut tracker = GameStateTracker::new()?.with_schema(schema);
+    let game_state = tracker.track_state(source.as_ref(), &memory_map).await?;
+    info!("📊 Game State of {}:", source.describe());
+    info!("   • Frame: {}, Map: {}x{}", game_state.frame, game_state.map.dimensions.0, game_state.map.dimensions.1);
+    for player in &game_state.players {
+        info!("   • {} ({:?}): {} gold, {} wood, {} oil",
+              player.name, player.race, player.resources.gold, player.resources.wood, player.resources.oil);
+    }
+    info!("   • Units: {}, Buildings: {}", game_state.units.len(), game_state.buildings.len());
+    Ok(())
+}
+
+/// List the events of an event log, optionally of one type and player
+async fn read_events(dir: &Path, event_type: Option<&str>, player: Option<&str>) -> Result<()> {
+    let mut filter = EventFilter::default();
+    if let Some(event_type) = event_type.filter(|event_type| *event_type != "all") {
+        let event_type: EventType = serde_json::from_value(serde_json::Value::String(event_type.to_string()))
+            .map_err(|_| anyhow!("Unknown event type: {}", event_type))?;
+        filter.event_types.push(event_type);
+    }
+    if let Some(player) = player {
+        filter.player_ids.push(player.parse().map_err(|_| anyhow!("Invalid player: {}", player))?);
+    }
+
+    let reader = EventLogReader::open(dir)?;
+    info!("📼 Event log {} holds {} events", dir.display(), reader.count()?);
+    for event in reader.events(&filter, None, None) {
+        let event = event?;
+        info!("   • #{} frame {} {:?} {:?}", event.id, event.frame, event.event_type, event.data);
+    }
+    Ok(())
+}
+
+/// Analyze a recorded event log, with the game states saved alongside it, and save the results
+async fn analyze_session(dir: &Path, states: Option<&Path>) -> Result<()> {
+    let reader = EventLogReader::open(dir)?;
+    let events = reader.events(&EventFilter::default(), None, None).collect::<Result<Vec<_>>>()?;
+    let states: Vec<GameState> = match states {
+        Some(path) => load_json(&path.to_path_buf())?,
+        None => Vec::new(),
+    };
+
+    let mut laboratory = create_laboratory()?;
+    laboratory.start_session().await?;
+    laboratory.import_session(events, states);
+    let results = laboratory.analyze_session().await?;
+    for result in &results {
+        info!("   • {} {:?} (confidence {:.2})", result.id, result.analysis_type, result.confidence);
+    }
+    laboratory.save_results(&results).await?;
+    Ok(())
+}
+
+/// Scan a process or snapshot for the signatures of a file, or of a build in `signatures/`
+async fn scan_signatures(target: &str, signatures: Option<&str>) -> Result<()> {
+    let source = open_target(target)?;
+
+    let mut analyzer = MemoryAnalyzer::new()?;
+    if let Some(signatures) = signatures {
+        let path = if Path::new(signatures).is_file() {
+            PathBuf::from(signatures)
+        } else {
+            SignatureSet::find(Path::new("signatures"), signatures)?
+                .map(|(path, _)| path)
+                .ok_or_else(|| anyhow!("No signature file for build {} in signatures/", signatures))?
+        };
+        analyzer.load_signatures(&path)?;
+    }
+
+    let matches = analyzer.scan_signatures(source.as_ref())?;
+    info!("📊 Signature Matches in {}:", source.describe());
+    for found in &matches {
+        info!("   • {} at 0x{:x} -> 0x{:x}", found.signature, found.address, found.resolved);
+    }
+    if matches.is_empty() {
+        warn!("⚠️ No signatures matched");
+    }
     Ok(())
 }
 
@@ -241,7 +593,17 @@ fn print_usage() {
     println!("  {}                    - Run default laboratory with AI Agent", env::args().next().unwrap());
     println!("  {} custom-build       - Initialize custom game builder for headless WC2", env::args().next().unwrap());
     println!("  {} ai-demo            - Run AI Agent demonstration mode", env::args().next().unwrap());
+    println!("  {} snapshot [pid] [note] - Capture the game's memory to a .wc2snap file", env::args().next().unwrap());
+    println!("  {} load-snapshot <file> - Re-run analysis against a captured snapshot", env::args().next().unwrap());
+    println!("  {} scan-signatures <pid|file> [signatures] - Find signatures in a process or snapshot", env::args().next().unwrap());
+    println!("  {} value-scan <session> <pid|file> first|next|list ... - Narrow down the address of a value", env::args().next().unwrap());
+    println!("  {} pointer-scan <pid|file>@<address>... - Find static pointer paths to a value in every snapshot", env::args().next().unwrap());
+    println!("  {} resolve-pointer <pid|file> <path> - Follow a static pointer path", env::args().next().unwrap());
+    println!("  {} decode-state <pid|file> <schema> - Decode game state with a layout schema", env::args().next().unwrap());
+    println!("  {} read-events <log dir> [type] [player] - List events of a recorded event log", env::args().next().unwrap());
+    println!("  {} analyze-session <log dir> [states] - Analyze a recorded event log and state history", env::args().next().unwrap());
     println!("  {} help               - Show this help message", env::args().next().unwrap());
+    println!("  (any <pid|file> may be dosbox[:pid|file] to read the DOS game inside DOSBox)");
     println!();
     println!("Commands:");
     println!("  custom-build          - Set up environment for building custom headless WC2");
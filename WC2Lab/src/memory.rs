@@ -2,16 +2,14 @@ use anyhow::Result;
 use log::{info, warn, error, debug};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::mem;
-use windows::Win32::System::Memory::{
-    VirtualQueryEx, MEMORY_BASIC_INFORMATION, PAGE_READWRITE, PAGE_READONLY
-};
-use windows::Win32::System::Threading::{OpenProcess, PROCESS_VM_READ, PROCESS_QUERY_INFORMATION};
-use windows::Win32::Foundation::{CloseHandle, HANDLE};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::Cursor;
+use std::path::Path;
+use wc2_shared::memory_source::{self, MemorySource, SnapshotSource};
+use wc2_shared::wc2snap::{self, SnapshotMetadata};
 
 use crate::process::WC2Process;
+use crate::signatures::{SignatureMatch, SignatureScanner, SignatureSet};
 
 /// Memory region information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +30,39 @@ pub struct MemoryRegion {
     pub writable: bool,
     /// Whether this region is executable
     pub executable: bool,
+    /// Module or file mapped at this region, where the source knows it
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl From<&memory_source::MemoryRegion> for MemoryRegion {
+    fn from(region: &memory_source::MemoryRegion) -> Self {
+        Self {
+            base_address: region.base,
+            size: region.size as usize,
+            protection: region.win32_protection(),
+            state: 0x1000, // MEM_COMMIT, sources only list committed memory
+            memory_type: 0,
+            readable: region.readable,
+            writable: region.writable,
+            executable: region.executable,
+            name: region.name.clone(),
+        }
+    }
+}
+
+impl From<&MemoryRegion> for memory_source::MemoryRegion {
+    fn from(region: &MemoryRegion) -> Self {
+        let (readable, writable, executable) = memory_source::win32_access(region.protection);
+        Self {
+            base: region.base_address,
+            size: region.size as u64,
+            readable: readable || region.readable,
+            writable: writable || region.writable,
+            executable: executable || region.executable,
+            name: region.name.clone(),
+        }
+    }
 }
 
 /// Memory map for a process
@@ -47,6 +78,62 @@ pub struct ProcessMemoryMap {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl ProcessMemoryMap {
+    /// Map the readable regions of a memory source without analyzing them
+    pub fn from_source(source: &dyn MemorySource) -> Result<Self> {
+        let regions: Vec<MemoryRegion> = source.regions()?
+            .iter()
+            .filter(|region| region.readable && region.size > 0)
+            .map(MemoryRegion::from)
+            .collect();
+        Ok(Self {
+            pid: source.pid().unwrap_or(0),
+            total_size: regions.iter().map(|region| region.size).sum(),
+            regions,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+
+    /// Copy the mapped regions out of `source` and archive them as a `.wc2snap` file
+    ///
+    /// Returns the metadata written, including how many regions could be read.
+    pub fn capture_snapshot(
+        &self,
+        source: &dyn MemorySource,
+        path: &Path,
+        process_name: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<SnapshotMetadata> {
+        let regions: Vec<memory_source::MemoryRegion> = self.regions.iter().map(Into::into).collect();
+        let snapshot = SnapshotSource::capture_regions(source, &regions)?;
+
+        let mut metadata = SnapshotMetadata::for_source(source);
+        metadata.pid = metadata.pid.or(Some(self.pid));
+        metadata.process_name = process_name.map(str::to_string);
+        metadata.note = note.map(str::to_string);
+        wc2snap::save(path, &metadata, &snapshot)?;
+
+        info!("📸 Captured {} of {} regions ({} bytes) to {}",
+              snapshot.region_data().count(), self.regions.len(), snapshot.total_bytes(), path.display());
+        Ok(metadata)
+    }
+
+    /// Load a `.wc2snap` file as the memory map it was captured from and a source reading its bytes
+    pub fn load_snapshot(path: &Path) -> Result<(Self, SnapshotMetadata, SnapshotSource)> {
+        let (metadata, snapshot) = wc2snap::load(path)?;
+        let regions: Vec<MemoryRegion> = snapshot.region_data().map(|(region, _)| region.into()).collect();
+        let memory_map = Self {
+            pid: metadata.pid.unwrap_or(0),
+            total_size: regions.iter().map(|region| region.size).sum(),
+            regions,
+            timestamp: metadata.captured_at,
+        };
+
+        info!("📂 Loaded snapshot {} taken {} ({} regions)", path.display(), metadata.captured_at, memory_map.regions.len());
+        Ok((memory_map, metadata, snapshot))
+    }
+}
+
 /// Memory analyzer for WC2 Remastered
 pub struct MemoryAnalyzer {
     /// Known memory patterns for WC2
@@ -64,12 +151,30 @@ pub struct MemorySignature {
     pub name: String,
     /// Pattern bytes
     pub pattern: Vec<u8>,
-    /// Pattern mask (0 = exact match, 1 = wildcard)
+    /// Bit mask applied before comparing each byte (0xFF = exact match, 0x00 = wildcard)
     pub mask: Vec<u8>,
-    /// Expected offset from pattern start
+    /// Offset from pattern start of the address or operand of interest
     pub offset: usize,
     /// Description of what this signature identifies
     pub description: String,
+    /// How `offset` turns a match into the address of interest
+    #[serde(default)]
+    pub resolve: OffsetResolution,
+}
+
+/// How a signature match resolves to the address it identifies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OffsetResolution {
+    /// The match address plus `offset`
+    #[default]
+    Address,
+    /// A 32-bit absolute address stored at `offset`, as in x86 `mov ecx, [0x4A1234]`
+    Absolute,
+    /// A 32-bit displacement stored at `offset`, relative to the end of the instruction
+    ///
+    /// Covers x64 RIP-relative operands and `call`/`jmp rel32`. The instruction ends
+    /// at `instruction_end` bytes from the match, or right after the displacement.
+    Relative { instruction_end: Option<usize> },
 }
 
 /// Memory analysis result
@@ -144,6 +249,7 @@ impl MemoryAnalyzer {
                 mask: vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
                 offset: 0,
                 description: "Game state structure identifier".to_string(),
+                resolve: OffsetResolution::Address,
             },
             // Unit data signature
             MemorySignature {
@@ -152,6 +258,7 @@ impl MemoryAnalyzer {
                 mask: vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
                 offset: 0,
                 description: "Unit data structure identifier".to_string(),
+                resolve: OffsetResolution::Address,
             },
             // Resource signature
             MemorySignature {
@@ -160,6 +267,7 @@ impl MemoryAnalyzer {
                 mask: vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
                 offset: 0,
                 description: "Resource structure identifier".to_string(),
+                resolve: OffsetResolution::Address,
             },
         ]
     }
@@ -167,93 +275,40 @@ impl MemoryAnalyzer {
     /// Analyze a process and create a memory map
     pub async fn analyze_process(&mut self, process: &WC2Process) -> Result<ProcessMemoryMap> {
         info!("🔍 Analyzing memory for process {} (PID: {})", process.name, process.pid);
-        
-        let handle = unsafe {
-            OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, process.pid)
-        }?;
-        
-        if handle.is_invalid() {
-            return Err(anyhow::anyhow!("Failed to open process for memory analysis"));
-        }
-        
-        let mut memory_map = ProcessMemoryMap {
-            pid: process.pid,
-            regions: Vec::new(),
-            total_size: 0,
-            timestamp: chrono::Utc::now(),
-        };
-        
-        // Scan memory regions
-        let mut current_address: u64 = 0;
-        let mut total_size = 0;
-        
-        loop {
-            let mut memory_info = MEMORY_BASIC_INFORMATION::default();
-            let result = unsafe {
-                VirtualQueryEx(
-                    handle,
-                    Some(current_address as *const _),
-                    &mut memory_info,
-                    mem::size_of::<MEMORY_BASIC_INFORMATION>(),
-                )
-            };
-            
-            if result == 0 {
-                break; // No more regions
-            }
-            
-            let region = MemoryRegion {
-                base_address: memory_info.BaseAddress as u64,
-                size: memory_info.RegionSize,
-                protection: memory_info.Protect.0,
-                state: memory_info.State.0,
-                memory_type: memory_info.Type.0,
-                readable: memory_info.Protect.0 & PAGE_READONLY.0 != 0 || 
-                         memory_info.Protect.0 & PAGE_READWRITE.0 != 0,
-                writable: memory_info.Protect.0 & PAGE_READWRITE.0 != 0,
-                executable: memory_info.Protect.0 & 0x40 != 0, // PAGE_EXECUTE_READWRITE
-            };
-            
-            if region.readable && region.size > 0 {
-                memory_map.regions.push(region.clone());
-                total_size += region.size;
-                
-                // Analyze this region for WC2 data
-                if let Ok(analysis) = self.analyze_memory_region(&handle, &region).await {
+        let source = memory_source::open_process(process.pid)?;
+        self.analyze_source(source.as_ref()).await
+    }
+
+    /// Map the readable regions of any memory source and analyze each of them
+    pub async fn analyze_source(&mut self, source: &dyn MemorySource) -> Result<ProcessMemoryMap> {
+        info!("🔍 Analyzing memory of {}", source.describe());
+
+        let memory_map = ProcessMemoryMap::from_source(source)?;
+        for region in &memory_map.regions {
+            // Analyze this region for WC2 data
+            match self.analyze_memory_region(source, region).await {
+                Ok(analysis) => {
                     self.analysis_cache.insert(region.base_address, analysis);
                 }
-            }
-            
-            current_address = memory_info.BaseAddress as u64 + memory_info.RegionSize as u64;
-            
-            // Safety check to prevent infinite loops
-            if current_address == 0 || current_address > 0x7FFFFFFFFFFF {
-                break;
+                Err(e) => debug!("⚠️ Skipping region 0x{:x}: {}", region.base_address, e),
             }
         }
-        
-        memory_map.total_size = total_size;
-        
-        unsafe { let _ = CloseHandle(handle); }
-        
-        info!("💾 Memory analysis completed: {} regions, {} total bytes", 
-              memory_map.regions.len(), total_size);
-        
+
+        info!("💾 Memory analysis completed: {} regions, {} total bytes",
+              memory_map.regions.len(), memory_map.total_size);
+
         Ok(memory_map)
     }
 
     /// Analyze a specific memory region
     async fn analyze_memory_region(
         &self,
-        handle: &HANDLE,
+        source: &dyn MemorySource,
         region: &MemoryRegion,
     ) -> Result<MemoryAnalysis> {
         // Read a sample of the memory region
         let sample_size = std::cmp::min(region.size, 4096); // 4KB sample
-        let mut buffer = vec![0u8; sample_size];
-        
-        // Note: In a real implementation, we'd use ReadProcessMemory here
-        // For now, we'll simulate the analysis
+        let buffer = source.read_bytes(region.base_address, sample_size)?;
         
         // Check for known patterns
         let mut best_match = None;
@@ -306,19 +361,13 @@ impl MemoryAnalyzer {
             }
             
             let buffer_byte = buffer[i + signature.offset];
-            let mask_bit = signature.mask.get(i).unwrap_or(&0xFF);
+            let mask = signature.mask.get(i).copied().unwrap_or(0xFF);
             
-            if *mask_bit == 0xFF {
-                // Exact match required
-                if buffer_byte == pattern_byte {
-                    matches += 1;
-                }
-                total_checks += 1;
-            } else {
-                // Wildcard - always match
+            // Masked-out bits always match
+            if buffer_byte & mask == pattern_byte & mask {
                 matches += 1;
-                total_checks += 1;
             }
+            total_checks += 1;
         }
         
         if total_checks == 0 {
@@ -387,10 +436,35 @@ impl MemoryAnalyzer {
         mask: &[u8],
     ) -> Result<Vec<u64>> {
         info!("🔍 Searching for memory pattern in process {}", process.pid);
-        
-        // This would implement actual memory pattern searching
-        // For now, return a placeholder
-        Ok(vec![])
+        let source = memory_source::open_process(process.pid)?;
+        self.search_pattern(source.as_ref(), pattern, mask)
+    }
+
+    /// Addresses in all readable regions of `source` where a masked pattern matches
+    pub fn search_pattern(&self, source: &dyn MemorySource, pattern: &[u8], mask: &[u8]) -> Result<Vec<u64>> {
+        let signature = MemorySignature {
+            name: "pattern".to_string(),
+            pattern: pattern.to_vec(),
+            mask: mask.to_vec(),
+            offset: 0,
+            description: String::new(),
+            resolve: OffsetResolution::Address,
+        };
+        let matches = SignatureScanner::new(&[signature])?.scan(source, &source.regions()?)?;
+        Ok(matches.into_iter().map(|found| found.address).collect())
+    }
+
+    /// Scan all readable regions of `source` for every loaded signature
+    pub fn scan_signatures(&self, source: &dyn MemorySource) -> Result<Vec<SignatureMatch>> {
+        SignatureScanner::new(&self.memory_signatures)?.scan(source, &source.regions()?)
+    }
+
+    /// Replace the signatures with those of a game build's signature file
+    pub fn load_signatures(&mut self, path: &Path) -> Result<SignatureSet> {
+        let set = SignatureSet::load(path)?;
+        self.memory_signatures = set.compile()?;
+        info!("📜 Loaded {} signatures for build {} from {}", self.memory_signatures.len(), set.build, path.display());
+        Ok(set)
     }
 
     /// Get cached analysis for a memory address
@@ -431,3 +505,56 @@ pub struct MemoryAnalysisStats {
     pub unit_data_regions: usize,
     pub cache_size: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::GameStateTracker;
+    use wc2_shared::memory_source::SnapshotSource;
+
+    fn snapshot() -> SnapshotSource {
+        let mut snapshot = SnapshotSource::new("test").with_pid(Some(7));
+        let mut bytes = b"GAMESTAT".to_vec();
+        bytes.resize(0x2000, 0);
+        let region = memory_source::MemoryRegion {
+            base: 0x400000,
+            size: 0,
+            readable: true,
+            writable: true,
+            executable: false,
+            name: None,
+        };
+        snapshot.add_region(region, bytes).unwrap();
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn test_analyze_snapshot_source() {
+        let source = snapshot();
+        let mut analyzer = MemoryAnalyzer::new().unwrap();
+        let memory_map = analyzer.analyze_source(&source).await.unwrap();
+
+        assert_eq!(memory_map.pid, 7);
+        assert_eq!(memory_map.total_size, 0x2000);
+        assert_eq!(memory_map.regions[0].protection, 0x04);
+        let analysis = analyzer.get_cached_analysis(0x400000).unwrap();
+        assert!(matches!(analysis.analysis_type, AnalysisType::GameState));
+        assert_eq!(analysis.confidence, 1.0);
+
+        // Archive the memory and re-run the analysis against the file
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wc2snap");
+        let metadata = memory_map.capture_snapshot(&source, &path, Some("Warcraft II.exe"), Some("test")).unwrap();
+        assert_eq!(metadata.pid, Some(7));
+        let (loaded_map, loaded_metadata, loaded) = ProcessMemoryMap::load_snapshot(&path).unwrap();
+        assert_eq!(loaded_metadata, metadata);
+        assert_eq!((loaded_map.pid, loaded_map.total_size), (7, 0x2000));
+        assert_eq!(loaded_map.regions[0].protection, 0x04);
+        assert_eq!(loaded.read_bytes(0x400000, 8).unwrap(), b"GAMESTAT");
+
+        let mut tracker = GameStateTracker::new().unwrap();
+        assert!(tracker.track_state(&loaded, &loaded_map).await.is_ok());
+        assert!(tracker.track_state(&source, &memory_map).await.is_ok());
+        assert!(tracker.track_state(&SnapshotSource::new("empty"), &memory_map).await.is_err());
+    }
+}
@@ -0,0 +1,531 @@
+//! Static pointer paths
+//!
+//! Game objects are allocated on the heap, so their addresses change every
+//! session. A [`PointerPath`] such as `war2.exe+0x1A2B3C -> +0x10 -> +0x48`
+//! starts from memory of a loaded module instead, whose offset is the same each
+//! run, and follows pointers from there to the object. A [`PointerScanner`]
+//! finds such paths by walking backwards from a target address, and keeps only
+//! the ones that reach the target in every snapshot given to it.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use wc2_shared::memory_source::{self, MemorySource};
+
+use crate::analysis::{MemoryLayout, MemoryRegion as LayoutRegion, PointerRelationship};
+use crate::value_scan::SCAN_CHUNK;
+
+/// A module-relative (or absolute) address followed by pointer dereferences
+///
+/// Each offset dereferences the current address and adds the offset to the
+/// pointer read, so `base -> +a -> +b` is `[[base] + a] + b`. Offsets are written
+/// in hex, with or without `0x`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PointerPath {
+    /// File name of the module the path starts in, or none for an absolute base
+    pub module: Option<String>,
+    /// Offset from the module base, or the absolute start address
+    pub base: u64,
+    pub offsets: Vec<i64>,
+}
+
+impl PointerPath {
+    /// Address the path starts at in `source`
+    pub fn base_address(&self, source: &dyn MemorySource) -> Result<u64> {
+        let Some(module) = &self.module else {
+            return Ok(self.base);
+        };
+        let layouts = module_layouts(&source.regions()?);
+        let layout = layouts.iter()
+            .find(|layout| layout.name.eq_ignore_ascii_case(module))
+            .ok_or_else(|| anyhow!("Module {} is not loaded in {}", module, source.describe()))?;
+        Ok(layout.base_address + self.base)
+    }
+
+    /// Follow the path, returning each dereference as a relationship from the pointer to the address it leads to
+    pub fn resolve_chain(&self, source: &dyn MemorySource, pointer_size: usize) -> Result<Vec<PointerRelationship>> {
+        check_pointer_size(pointer_size)?;
+        let mut address = self.base_address(source)?;
+        let mut chain = Vec::with_capacity(self.offsets.len());
+        for (depth, &offset) in self.offsets.iter().enumerate() {
+            let pointer = read_pointer(source, address, pointer_size)
+                .map_err(|e| anyhow!("{} breaks at level {} (0x{:x}): {}", self, depth + 1, address, e))?;
+            let target = pointer.wrapping_add_signed(offset);
+            chain.push(PointerRelationship {
+                source_address: address,
+                target_address: target,
+                relationship_type: format!("pointer {}", format_offset(offset)),
+                confidence: 1.0,
+            });
+            address = target;
+        }
+        Ok(chain)
+    }
+
+    /// Address the path leads to in `source`
+    pub fn resolve(&self, source: &dyn MemorySource, pointer_size: usize) -> Result<u64> {
+        match self.resolve_chain(source, pointer_size)?.last() {
+            Some(last) => Ok(last.target_address),
+            None => self.base_address(source),
+        }
+    }
+}
+
+impl FromStr for PointerPath {
+    type Err = anyhow::Error;
+
+    /// `module+0x1A2B3C -> +0x10 -> +0x48`, `0x4A1234 -> +0x10` or `module -> +0x8`
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split("->").map(str::trim);
+        let start = parts.next().filter(|start| !start.is_empty())
+            .ok_or_else(|| anyhow!("Empty pointer path"))?;
+
+        let (module, base) = match start.rsplit_once('+') {
+            Some((module, offset)) => (Some(module.trim().to_string()), parse_hex(offset.trim())?),
+            None => match parse_hex(start) {
+                Ok(address) => (None, address),
+                Err(_) => (Some(start.to_string()), 0),
+            },
+        };
+        if module.as_deref().is_some_and(str::is_empty) {
+            bail!("Missing module name in pointer path {}", s);
+        }
+
+        let offsets = parts.map(parse_offset).collect::<Result<Vec<_>>>()
+            .map_err(|e| anyhow!("Invalid pointer path {}: {}", s, e))?;
+        Ok(Self { module, base, offsets })
+    }
+}
+
+impl fmt::Display for PointerPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.module {
+            Some(module) => write!(f, "{}+0x{:X}", module, self.base)?,
+            None => write!(f, "0x{:X}", self.base)?,
+        }
+        for &offset in &self.offsets {
+            write!(f, " -> {}", format_offset(offset))?;
+        }
+        Ok(())
+    }
+}
+
+fn format_offset(offset: i64) -> String {
+    if offset < 0 {
+        format!("-0x{:X}", offset.unsigned_abs())
+    } else {
+        format!("+0x{:X}", offset)
+    }
+}
+
+/// Parse hex with or without a `0x` prefix
+pub fn parse_hex(s: &str) -> Result<u64> {
+    let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).unwrap_or(s);
+    u64::from_str_radix(digits, 16).map_err(|_| anyhow!("Invalid hex number {}", s))
+}
+
+fn parse_offset(s: &str) -> Result<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let offset = i64::try_from(parse_hex(digits.trim())?).map_err(|_| anyhow!("Offset {} is too large", s))?;
+    Ok(if negative { -offset } else { offset })
+}
+
+fn check_pointer_size(pointer_size: usize) -> Result<()> {
+    if pointer_size != 4 && pointer_size != 8 {
+        bail!("Pointer size must be 4 or 8 bytes, not {}", pointer_size);
+    }
+    Ok(())
+}
+
+fn read_pointer(source: &dyn MemorySource, address: u64, pointer_size: usize) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    source.read_exact(address, &mut bytes[..pointer_size])?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn decode_pointer(bytes: &[u8]) -> u64 {
+    let mut value = [0u8; 8];
+    value[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(value)
+}
+
+/// File name of a region's backing path, for regions backed by a file
+fn module_name(region: &memory_source::MemoryRegion) -> Option<&str> {
+    let name = region.name.as_deref()?;
+    // Pseudo-mappings such as [heap] and [stack] move between runs like any allocation
+    if name.starts_with('[') {
+        return None;
+    }
+    name.rsplit(['/', '\\']).next().filter(|file| !file.is_empty())
+}
+
+/// Group the file-backed regions of an address space into one layout per module, in address order
+pub fn module_layouts(regions: &[memory_source::MemoryRegion]) -> Vec<MemoryLayout> {
+    let mut layouts: Vec<(&str, MemoryLayout)> = Vec::new();
+    for region in regions {
+        let (Some(path), Some(name)) = (region.name.as_deref(), module_name(region)) else {
+            continue;
+        };
+        let purpose = match (region.executable, region.writable) {
+            (true, _) => "code",
+            (false, true) => "data",
+            (false, false) => "read-only data",
+        };
+        let layout_region = LayoutRegion {
+            name: name.to_string(),
+            start_address: region.base,
+            end_address: region.end(),
+            purpose: purpose.to_string(),
+        };
+
+        match layouts.iter_mut().find(|(existing, _)| *existing == path) {
+            Some((_, layout)) => {
+                let end = (layout.base_address + layout.size as u64).max(region.end());
+                layout.base_address = layout.base_address.min(region.base);
+                layout.size = (end - layout.base_address) as usize;
+                layout.regions.push(layout_region);
+            }
+            None => layouts.push((path, MemoryLayout {
+                name: name.to_string(),
+                base_address: region.base,
+                size: region.size as usize,
+                regions: vec![layout_region],
+            })),
+        }
+    }
+
+    let mut layouts: Vec<MemoryLayout> = layouts.into_iter().map(|(_, layout)| layout).collect();
+    layouts.sort_by_key(|layout| layout.base_address);
+    layouts
+}
+
+/// Limits of a pointer scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointerScanConfig {
+    /// Most dereferences in a path
+    pub max_depth: usize,
+    /// Largest offset added to a pointer, i.e. how far into a structure a field may lie
+    pub max_offset: u64,
+    /// 4 for the 32-bit game, 8 for 64-bit hosts
+    pub pointer_size: usize,
+    /// Stop after this many paths
+    pub max_results: usize,
+}
+
+impl Default for PointerScanConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_offset: 0x1000,
+            pointer_size: 4,
+            max_results: 10_000,
+        }
+    }
+}
+
+/// Every aligned value of an address space that points into mapped memory
+///
+/// The whole map is held in memory: 16 bytes per pointer found, which for a
+/// process with a few hundred megabytes of heap can itself run to hundreds of
+/// megabytes while a scan runs.
+struct PointerMap {
+    /// (value, address holding it), sorted by value
+    pointers: Vec<(u64, u64)>,
+    modules: Vec<MemoryLayout>,
+}
+
+impl PointerMap {
+    fn build(source: &dyn MemorySource, pointer_size: usize) -> Result<Self> {
+        let regions: Vec<memory_source::MemoryRegion> = source.regions()?
+            .into_iter()
+            .filter(|region| region.readable && region.size > 0)
+            .collect();
+        let mapped = |value: u64| {
+            let index = regions.partition_point(|region| region.end() <= value);
+            regions.get(index).is_some_and(|region| region.contains(value))
+        };
+
+        // Regions are read a chunk at a time; each read runs a pointer past the
+        // chunk so that pointers straddling the boundary are still seen once
+        let mut pointers = Vec::new();
+        for region in &regions {
+            let size = region.size as usize;
+            for chunk in (0..size).step_by(SCAN_CHUNK) {
+                let base = region.base + chunk as u64;
+                let bytes = match source.read_bytes(base, (SCAN_CHUNK + pointer_size - 1).min(size - chunk)) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        debug!("⚠️ Skipping chunk 0x{:x}: {}", base, e);
+                        continue;
+                    }
+                };
+                let first = ((pointer_size as u64 - base % pointer_size as u64) % pointer_size as u64) as usize;
+                let end = SCAN_CHUNK.min(bytes.len().saturating_sub(pointer_size - 1));
+                for offset in (first..end).step_by(pointer_size) {
+                    let value = decode_pointer(&bytes[offset..offset + pointer_size]);
+                    if value != 0 && mapped(value) {
+                        pointers.push((value, base + offset as u64));
+                    }
+                }
+            }
+        }
+        pointers.sort_unstable();
+
+        debug!("🔗 {} pointers in {}", pointers.len(), source.describe());
+        Ok(Self { pointers, modules: module_layouts(&regions) })
+    }
+
+    /// The module an address lies in, if any
+    fn module_at(&self, address: u64) -> Option<&MemoryLayout> {
+        self.modules.iter().find(|layout| {
+            layout.regions.iter().any(|region| (region.start_address..region.end_address).contains(&address))
+        })
+    }
+}
+
+/// Finds static pointer paths to an address
+pub struct PointerScanner {
+    config: PointerScanConfig,
+}
+
+impl PointerScanner {
+    pub fn new(config: PointerScanConfig) -> Result<Self> {
+        check_pointer_size(config.pointer_size)?;
+        if config.max_depth == 0 {
+            bail!("Pointer scan depth must be at least 1");
+        }
+        Ok(Self { config })
+    }
+
+    /// Paths from module memory that lead to `target` in `source`, shortest first
+    ///
+    /// Every pointer of `source` is collected into memory first, see [`PointerMap`].
+    pub fn scan(&self, source: &dyn MemorySource, target: u64) -> Result<Vec<PointerPath>> {
+        let map = PointerMap::build(source, self.config.pointer_size)?;
+        let found = self.paths_to(&map, target, self.config.max_depth, &mut HashMap::new()).to_vec();
+
+        info!("🔗 {} pointer paths to 0x{:x} in {}", found.len(), target, source.describe());
+        Ok(found)
+    }
+
+    /// Paths that lead to each snapshot's own target address in all of them
+    ///
+    /// The object usually sits elsewhere in every session, so each source comes
+    /// with where the object was found in it. Candidates come from the first
+    /// source and are kept only if they resolve to the target in every other one.
+    pub fn scan_all(&self, targets: &[(&dyn MemorySource, u64)]) -> Result<Vec<PointerPath>> {
+        let Some(((first, target), rest)) = targets.split_first() else {
+            bail!("Pointer scan needs at least one memory source");
+        };
+
+        let mut paths = self.scan(*first, *target)?;
+        for (source, target) in rest {
+            paths.retain(|path| path.resolve(*source, self.config.pointer_size).ok() == Some(*target));
+            info!("🔗 {} pointer paths also lead to 0x{:x} in {}", paths.len(), target, source.describe());
+        }
+        Ok(paths)
+    }
+
+    /// Static paths of at most `depth` dereferences that lead to `target`, shortest first
+    ///
+    /// Heap structures point at each other (linked lists, back pointers), so the
+    /// same address is often reached again through another route. The static
+    /// paths into an address are found once per remaining depth and joined to
+    /// every route out of it, which keeps the search from growing with the
+    /// number of nearby pointers to the power of the depth. Each address keeps
+    /// at most `max_results` paths, the shortest ones.
+    fn paths_to(
+        &self,
+        map: &PointerMap,
+        target: u64,
+        depth: usize,
+        memo: &mut HashMap<(u64, usize), Rc<Vec<PointerPath>>>,
+    ) -> Rc<Vec<PointerPath>> {
+        if let Some(paths) = memo.get(&(target, depth)) {
+            return paths.clone();
+        }
+
+        let mut paths = Vec::new();
+        let lowest = target.saturating_sub(self.config.max_offset);
+        let start = map.pointers.partition_point(|(value, _)| *value < lowest);
+        for &(value, address) in map.pointers[start..].iter().take_while(|(value, _)| *value <= target) {
+            let offset = (target - value) as i64;
+            match map.module_at(address) {
+                Some(module) => paths.push(PointerPath {
+                    module: Some(module.name.clone()),
+                    base: address - module.base_address,
+                    offsets: vec![offset],
+                }),
+                None if depth > 1 => {
+                    for prefix in self.paths_to(map, address, depth - 1, memo).iter() {
+                        let mut path = prefix.clone();
+                        path.offsets.push(offset);
+                        paths.push(path);
+                    }
+                }
+                None => {}
+            }
+        }
+        paths.sort_by(|a, b| a.offsets.len().cmp(&b.offsets.len()).then_with(|| a.offsets.cmp(&b.offsets)));
+        paths.truncate(self.config.max_results);
+
+        let paths = Rc::new(paths);
+        memo.insert((target, depth), paths.clone());
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wc2_shared::memory_source::{MemoryRegion, SnapshotSource};
+
+    const MODULE: u64 = 0x400000;
+
+    /// The game module holds a pointer to a player list that points to the player with gold at +0x48
+    fn session(list: u64, player: u64, decoy: u32) -> SnapshotSource {
+        let mut module = vec![0u8; 0x2000];
+        module[0x1100..0x1104].copy_from_slice(&(list as u32).to_le_bytes());
+        module[0x1200..0x1204].copy_from_slice(&decoy.to_le_bytes());
+        let mut heap = vec![0u8; 0x1000];
+        heap[(list - 0x2000000) as usize + 0x10..][..4].copy_from_slice(&(player as u32).to_le_bytes());
+
+        let mut source = SnapshotSource::new("session");
+        let region = |base: u64, writable: bool, name: Option<&str>| MemoryRegion {
+            base,
+            size: 0,
+            readable: true,
+            writable,
+            executable: false,
+            name: name.map(str::to_string),
+        };
+        source.add_region(region(MODULE, true, Some(r"C:\Games\Warcraft II\war2.exe")), module).unwrap();
+        source.add_region(region(0x2000000, true, None), heap).unwrap();
+        source.add_region(region(0x3000000, true, Some("[heap]")), vec![0u8; 0x1000]).unwrap();
+        source
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        let path: PointerPath = "war2.exe+0x1A2B3C -> +0x10 -> -0x8".parse().unwrap();
+        assert_eq!(path, PointerPath { module: Some("war2.exe".to_string()), base: 0x1A2B3C, offsets: vec![0x10, -0x8] });
+        assert_eq!(path.to_string(), "war2.exe+0x1A2B3C -> +0x10 -> -0x8");
+
+        let absolute: PointerPath = "4A1234 -> 10".parse().unwrap();
+        assert_eq!(absolute, PointerPath { module: None, base: 0x4A1234, offsets: vec![0x10] });
+        assert_eq!("Warcraft II.exe".parse::<PointerPath>().unwrap().module.as_deref(), Some("Warcraft II.exe"));
+        assert!("+0x10".parse::<PointerPath>().is_err());
+        assert!("war2.exe+0x10 -> +zz".parse::<PointerPath>().is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let source = session(0x2000100, 0x2000800, 0);
+        let path: PointerPath = "WAR2.EXE+0x1100 -> +0x10 -> +0x48".parse().unwrap();
+        assert_eq!(path.resolve(&source, 4).unwrap(), 0x2000848);
+
+        let chain = path.resolve_chain(&source, 4).unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!((chain[0].source_address, chain[0].target_address), (0x401100, 0x2000110));
+        assert_eq!((chain[1].source_address, chain[1].target_address), (0x2000110, 0x2000848));
+
+        assert!("other.dll+0x10 -> +0x0".parse::<PointerPath>().unwrap().resolve(&source, 4).is_err());
+        assert!(path.resolve(&source, 2).is_err());
+
+        let layouts = module_layouts(&source.regions().unwrap());
+        assert_eq!(layouts.len(), 1);
+        assert_eq!((layouts[0].name.as_str(), layouts[0].base_address), ("war2.exe", MODULE));
+    }
+
+    #[test]
+    fn test_scan_intersects_sessions() {
+        // The decoy pointer reaches the player in the first session only
+        let first = session(0x2000100, 0x2000800, 0x2000800);
+        let second = session(0x2000400, 0x2000a00, 0x2000800);
+
+        let scanner = PointerScanner::new(PointerScanConfig { max_depth: 3, max_offset: 0x100, ..Default::default() }).unwrap();
+        let found = scanner.scan(&first, 0x2000848).unwrap();
+        assert_eq!(found[0].to_string(), "war2.exe+0x1200 -> +0x48");
+        assert!(found.iter().any(|path| path.to_string() == "war2.exe+0x1100 -> +0x10 -> +0x48"));
+
+        let stable = scanner.scan_all(&[(&first, 0x2000848), (&second, 0x2000a48)]).unwrap();
+        let stable: Vec<String> = stable.iter().map(ToString::to_string).collect();
+        assert_eq!(stable, vec!["war2.exe+0x1100 -> +0x10 -> +0x48"]);
+    }
+
+    #[test]
+    fn test_scan_keeps_every_route_through_a_shared_address() {
+        // A list node at 0x2000300 holds the player at +0x0 and again at +0x10,
+        // and a heap pointer the module points to leads to the node. Only the
+        // +0x10 field still holds the player in the second session.
+        let session = |first_field: u32| {
+            let mut module = vec![0u8; 0x2000];
+            module[0x1100..0x1104].copy_from_slice(&0x2000600u32.to_le_bytes());
+            let mut heap = vec![0u8; 0x1000];
+            heap[0x300..0x304].copy_from_slice(&first_field.to_le_bytes());
+            heap[0x310..0x314].copy_from_slice(&0x2000800u32.to_le_bytes());
+            heap[0x600..0x604].copy_from_slice(&0x2000300u32.to_le_bytes());
+
+            let mut source = SnapshotSource::new("routes");
+            let region = |base: u64, name: Option<&str>| MemoryRegion {
+                base,
+                size: 0,
+                readable: true,
+                writable: true,
+                executable: false,
+                name: name.map(str::to_string),
+            };
+            source.add_region(region(MODULE, Some("war2.exe")), module).unwrap();
+            source.add_region(region(0x2000000, None), heap).unwrap();
+            source
+        };
+        let (first, second) = (session(0x2000800), session(0));
+
+        let scanner = PointerScanner::new(PointerScanConfig { max_depth: 3, max_offset: 0x100, ..Default::default() }).unwrap();
+        let found: Vec<String> = scanner.scan(&first, 0x2000848).unwrap().iter().map(ToString::to_string).collect();
+        assert_eq!(found, vec![
+            "war2.exe+0x1100 -> +0x0 -> +0x0 -> +0x48",
+            "war2.exe+0x1100 -> +0x0 -> +0x10 -> +0x48",
+        ]);
+
+        let stable = scanner.scan_all(&[(&first, 0x2000848), (&second, 0x2000848)]).unwrap();
+        assert_eq!(stable.len(), 1);
+        assert_eq!(stable[0].to_string(), "war2.exe+0x1100 -> +0x0 -> +0x10 -> +0x48");
+    }
+
+    #[test]
+    fn test_scan_stays_bounded_in_pointer_cycles() {
+        // 64 heap words all pointing back into the heap, with one module pointer to it
+        let mut heap = vec![0u8; 0x100];
+        for (index, word) in heap.chunks_mut(4).enumerate() {
+            word.copy_from_slice(&(0x2000000 + (index as u32 * 4 + 0x10) % 0x100).to_le_bytes());
+        }
+        let mut module = vec![0u8; 0x100];
+        module[0x20..0x24].copy_from_slice(&0x2000000u32.to_le_bytes());
+        let mut source = SnapshotSource::new("tangle");
+        let region = |base: u64, name: Option<&str>| MemoryRegion {
+            base,
+            size: 0,
+            readable: true,
+            writable: true,
+            executable: false,
+            name: name.map(str::to_string),
+        };
+        source.add_region(region(MODULE, Some("war2.exe")), module).unwrap();
+        source.add_region(region(0x2000000, None), heap).unwrap();
+
+        // Walking every route is 64^6 walks; each address is expanded once per depth instead
+        let config = PointerScanConfig { max_depth: 6, max_offset: 0x100, max_results: 500, ..Default::default() };
+        let found = PointerScanner::new(config).unwrap().scan(&source, 0x2000080).unwrap();
+        assert_eq!(found[0].to_string(), "war2.exe+0x20 -> +0x80");
+        assert_eq!(found.len(), 500);
+        assert!(found.windows(2).all(|pair| pair[0].offsets.len() <= pair[1].offsets.len()));
+    }
+}
@@ -0,0 +1,455 @@
+//! Masked byte-signature scanning
+//!
+//! Signatures are written IDA-style, e.g. `"8B 0D ?? ?? ?? ?? 85 C9"`, where
+//! `??` matches any byte and `8?` any byte whose high nibble is 8. A
+//! [`SignatureScanner`] searches every given region of a memory source for all
+//! its signatures at once, reading regions in chunks that overlap by the longest
+//! pattern so matches across chunk boundaries are found exactly once, and
+//! scanning regions on several threads.
+//!
+//! Signature sets live in one TOML file per game build:
+//!
+//! ```toml
+//! format = 1
+//! build = "1.0.1.6321"
+//! exe_sha256 = "…"
+//!
+//! [[signature]]
+//! name = "unit_table"
+//! pattern = "48 8B 0D ?? ?? ?? ?? 48 85 C9"
+//! offset = 3
+//! resolve = "relative"
+//! description = "mov rcx, [rip+unit_table]"
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use wc2_shared::memory_source::{MemoryRegion, MemorySource};
+
+use crate::memory::{MemorySignature, OffsetResolution};
+
+/// Signature file format understood by [`SignatureSet::load`]
+pub const SIGNATURE_FORMAT_VERSION: u32 = 1;
+
+/// Bytes scanned per read, before the overlap
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Parse an IDA-style pattern into bytes and a bit mask (0xFF exact, 0x00 any byte)
+pub fn parse_pattern(pattern: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut bytes = Vec::new();
+    let mut mask = Vec::new();
+    for token in pattern.split_whitespace() {
+        if token == "?" || token == "??" {
+            bytes.push(0);
+            mask.push(0);
+            continue;
+        }
+        let nibbles: Vec<char> = token.chars().collect();
+        if nibbles.len() != 2 {
+            bail!("Invalid pattern byte '{}' in \"{}\"", token, pattern);
+        }
+        let (mut byte, mut byte_mask) = (0u8, 0u8);
+        for (shift, nibble) in [(4, nibbles[0]), (0, nibbles[1])] {
+            if nibble != '?' {
+                let value = nibble.to_digit(16)
+                    .ok_or_else(|| anyhow!("Invalid pattern byte '{}' in \"{}\"", token, pattern))?;
+                byte |= (value as u8) << shift;
+                byte_mask |= 0x0F << shift;
+            }
+        }
+        bytes.push(byte);
+        mask.push(byte_mask);
+    }
+    if bytes.is_empty() {
+        bail!("Empty pattern");
+    }
+    Ok((bytes, mask))
+}
+
+/// Format bytes and mask back into an IDA-style pattern
+pub fn format_pattern(bytes: &[u8], mask: &[u8]) -> String {
+    bytes.iter()
+        .zip(mask)
+        .map(|(&byte, &mask)| {
+            let nibble = |shift: u8| {
+                if mask >> shift & 0x0F == 0x0F {
+                    format!("{:X}", byte >> shift & 0x0F)
+                } else {
+                    "?".to_string()
+                }
+            };
+            format!("{}{}", nibble(4), nibble(0))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// How an entry in a signature file resolves its offset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolveKind {
+    #[default]
+    Address,
+    Absolute,
+    Relative,
+}
+
+/// One `[[signature]]` entry of a signature file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureEntry {
+    pub name: String,
+    /// IDA-style pattern
+    pub pattern: String,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub resolve: ResolveKind,
+    /// For `relative`, where the instruction ends counted from the match start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instruction_end: Option<usize>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl SignatureEntry {
+    pub fn to_signature(&self) -> Result<MemorySignature> {
+        let (pattern, mask) = parse_pattern(&self.pattern)
+            .with_context(|| format!("Signature {}", self.name))?;
+        let resolve = match self.resolve {
+            ResolveKind::Address => OffsetResolution::Address,
+            ResolveKind::Absolute => OffsetResolution::Absolute,
+            ResolveKind::Relative => OffsetResolution::Relative { instruction_end: self.instruction_end },
+        };
+        Ok(MemorySignature {
+            name: self.name.clone(),
+            pattern,
+            mask,
+            offset: self.offset,
+            description: self.description.clone(),
+            resolve,
+        })
+    }
+}
+
+/// The signatures for one game build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureSet {
+    pub format: u32,
+    /// Game build the signatures were made for, e.g. "1.0.1.6321"
+    pub build: String,
+    /// SHA-256 of the game executable of that build, lowercase hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe_sha256: Option<String>,
+    #[serde(default, rename = "signature")]
+    pub signatures: Vec<SignatureEntry>,
+}
+
+impl SignatureSet {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read signature file {}", path.display()))?;
+        let set: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid signature file {}", path.display()))?;
+        if set.format != SIGNATURE_FORMAT_VERSION {
+            bail!("{} is signature format {}, only format {} is supported",
+                  path.display(), set.format, SIGNATURE_FORMAT_VERSION);
+        }
+        // Compile once up front so a bad pattern names its file
+        set.compile().with_context(|| format!("Invalid signature file {}", path.display()))?;
+        Ok(set)
+    }
+
+    /// Find the file in `dir` for a build, matching its build name or executable hash
+    pub fn find(dir: &Path, build_or_sha256: &str) -> Result<Option<(PathBuf, Self)>> {
+        find_build_file(dir, build_or_sha256)
+    }
+
+    pub fn compile(&self) -> Result<Vec<MemorySignature>> {
+        self.signatures.iter().map(SignatureEntry::to_signature).collect()
+    }
+}
+
+impl BuildFile for SignatureSet {
+    const KIND: &'static str = "signature files";
+
+    fn load(path: &Path) -> Result<Self> {
+        Self::load(path)
+    }
+
+    fn matches(&self, build_or_sha256: &str) -> bool {
+        self.build == build_or_sha256
+            || self.exe_sha256.as_ref().is_some_and(|hash| hash.eq_ignore_ascii_case(build_or_sha256))
+    }
+}
+
+/// A TOML file made for one game build, kept one per build in a directory
+pub(crate) trait BuildFile: Sized {
+    /// What the files are called in errors, e.g. "signature files"
+    const KIND: &'static str;
+
+    fn load(path: &Path) -> Result<Self>;
+
+    /// Whether the file is for the build with this name or executable hash
+    fn matches(&self, build_or_sha256: &str) -> bool;
+}
+
+/// The first file in `dir`, by name, for a build name or executable hash
+pub(crate) fn find_build_file<T: BuildFile>(dir: &Path, build_or_sha256: &str) -> Result<Option<(PathBuf, T)>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {} in {}", T::KIND, dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let file = T::load(&path)?;
+        if file.matches(build_or_sha256) {
+            return Ok(Some((path, file)));
+        }
+    }
+    Ok(None)
+}
+
+/// Where a signature matched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureMatch {
+    pub signature: String,
+    /// Address of the first pattern byte
+    pub address: u64,
+    /// Address the signature's offset resolves to
+    pub resolved: u64,
+}
+
+struct Compiled {
+    signature: MemorySignature,
+    /// Index of the first exact byte, used to find candidate positions
+    anchor: usize,
+}
+
+/// Scans memory for many signatures in one pass
+pub struct SignatureScanner {
+    compiled: Vec<Compiled>,
+    /// Signatures by the value of their anchor byte
+    by_anchor: Vec<Vec<usize>>,
+    longest: usize,
+    chunk_size: usize,
+    threads: usize,
+}
+
+impl SignatureScanner {
+    pub fn new(signatures: &[MemorySignature]) -> Result<Self> {
+        let mut compiled = Vec::new();
+        let mut by_anchor = vec![Vec::new(); 256];
+        for signature in signatures {
+            validate(signature)?;
+            let anchor = signature.mask.iter().position(|&mask| mask == 0xFF)
+                .ok_or_else(|| anyhow!("Signature {} has no exact byte to anchor on", signature.name))?;
+            by_anchor[signature.pattern[anchor] as usize].push(compiled.len());
+            compiled.push(Compiled { signature: signature.clone(), anchor });
+        }
+
+        let longest = compiled.iter().map(|c| c.signature.pattern.len()).max().unwrap_or(0);
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Ok(Self { compiled, by_anchor, longest, chunk_size: DEFAULT_CHUNK_SIZE, threads })
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Scan the readable regions among `regions`, returning matches ordered by signature and address
+    pub fn scan(&self, source: &dyn MemorySource, regions: &[MemoryRegion]) -> Result<Vec<SignatureMatch>> {
+        let regions: Vec<&MemoryRegion> = regions.iter().filter(|region| region.readable && region.size > 0).collect();
+        if self.compiled.is_empty() || regions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Workers take the next region until none are left
+        let next = AtomicUsize::new(0);
+        let found = Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for _ in 0..self.threads.min(regions.len()) {
+                scope.spawn(|| {
+                    while let Some(region) = regions.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let matches = self.scan_region(source, region);
+                        found.lock().unwrap().extend(matches);
+                    }
+                });
+            }
+        });
+
+        let mut found = found.into_inner().unwrap();
+        found.sort_by(|a, b| a.signature.cmp(&b.signature).then(a.address.cmp(&b.address)));
+        info!("🔍 Signature scan of {} regions in {}: {} matches", regions.len(), source.describe(), found.len());
+        Ok(found)
+    }
+
+    fn scan_region(&self, source: &dyn MemorySource, region: &MemoryRegion) -> Vec<SignatureMatch> {
+        let overlap = self.longest - 1;
+        let mut matches = Vec::new();
+        let mut position = region.base;
+        while position < region.end() {
+            let len = (region.end() - position).min((self.chunk_size + overlap) as u64) as usize;
+            let chunk = match source.read_bytes(position, len) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    debug!("⚠️ Stopped scanning region 0x{:x} at 0x{:x}: {}", region.base, position, e);
+                    break;
+                }
+            };
+            // Matches starting in the overlap are reported by the next chunk
+            let limit = if len == (region.end() - position) as usize { chunk.len() } else { self.chunk_size };
+            self.scan_chunk(&chunk, position, limit, &mut matches);
+            if chunk.len() < len {
+                break;
+            }
+            position += self.chunk_size as u64;
+        }
+        matches
+    }
+
+    /// Find matches starting before `limit` in a buffer read from `base`
+    fn scan_chunk(&self, chunk: &[u8], base: u64, limit: usize, matches: &mut Vec<SignatureMatch>) {
+        for (index, &byte) in chunk.iter().enumerate() {
+            for &candidate in &self.by_anchor[byte as usize] {
+                let compiled = &self.compiled[candidate];
+                let Some(start) = index.checked_sub(compiled.anchor) else {
+                    continue;
+                };
+                let signature = &compiled.signature;
+                if start >= limit || start + signature.pattern.len() > chunk.len() {
+                    continue;
+                }
+                if is_match(&chunk[start..], signature) {
+                    let address = base + start as u64;
+                    matches.push(SignatureMatch {
+                        signature: signature.name.clone(),
+                        address,
+                        resolved: resolve(signature, &chunk[start..], address),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn validate(signature: &MemorySignature) -> Result<()> {
+    if signature.pattern.is_empty() || signature.pattern.len() != signature.mask.len() {
+        bail!("Signature {} needs a pattern and a mask of the same length", signature.name);
+    }
+    let operand = match signature.resolve {
+        OffsetResolution::Address => 0,
+        OffsetResolution::Absolute | OffsetResolution::Relative { .. } => 4,
+    };
+    if signature.offset + operand > signature.pattern.len() {
+        bail!("Offset {} of signature {} is outside its pattern", signature.offset, signature.name);
+    }
+    Ok(())
+}
+
+/// Whether `data` starts with the signature's pattern under its mask
+pub fn is_match(data: &[u8], signature: &MemorySignature) -> bool {
+    data.len() >= signature.pattern.len()
+        && signature.pattern.iter()
+            .zip(&signature.mask)
+            .zip(data)
+            .all(|((&pattern, &mask), &byte)| byte & mask == pattern & mask)
+}
+
+/// Resolve a signature's offset against a match of it at `address`
+fn resolve(signature: &MemorySignature, matched: &[u8], address: u64) -> u64 {
+    let operand = || {
+        let bytes = &matched[signature.offset..signature.offset + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    match signature.resolve {
+        OffsetResolution::Address => address + signature.offset as u64,
+        OffsetResolution::Absolute => operand() as u64,
+        OffsetResolution::Relative { instruction_end } => {
+            let end = instruction_end.unwrap_or(signature.offset + 4);
+            (address + end as u64).wrapping_add_signed(operand() as i32 as i64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wc2_shared::memory_source::SnapshotSource;
+
+    fn region(base: u64) -> MemoryRegion {
+        MemoryRegion { base, size: 0, readable: true, writable: false, executable: true, name: None }
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        let (bytes, mask) = parse_pattern("8B 0D ?? ? 8? C9").unwrap();
+        assert_eq!(bytes, vec![0x8B, 0x0D, 0, 0, 0x80, 0xC9]);
+        assert_eq!(mask, vec![0xFF, 0xFF, 0, 0, 0xF0, 0xFF]);
+        assert_eq!(format_pattern(&bytes, &mask), "8B 0D ?? ?? 8? C9");
+        assert!(parse_pattern("8B 0G").is_err());
+        assert!(parse_pattern("").is_err());
+    }
+
+    #[test]
+    fn test_scan_resolves_offsets_across_chunks() {
+        // mov ecx, [0x004A1234] / test ecx, ecx at 0x401000 + 4093, straddling chunks of 4096
+        let mut code = vec![0xCC; 8192];
+        code[4093..4101].copy_from_slice(&[0x8B, 0x0D, 0x34, 0x12, 0x4A, 0x00, 0x85, 0xC9]);
+        // mov rcx, [rip+0x100] / test rcx, rcx at 0x401000 + 6000
+        code[6000..6010].copy_from_slice(&[0x48, 0x8B, 0x0D, 0x00, 0x01, 0x00, 0x00, 0x48, 0x85, 0xC9]);
+        let mut source = SnapshotSource::new("test");
+        source.add_region(region(0x401000), code).unwrap();
+
+        let toml = r#"
+            format = 1
+            build = "test"
+
+            [[signature]]
+            name = "absolute"
+            pattern = "8B 0D ?? ?? ?? ?? 85 C9"
+            offset = 2
+            resolve = "absolute"
+
+            [[signature]]
+            name = "relative"
+            pattern = "48 8B 0D ?? ?? ?? ?? 48 85 C9"
+            offset = 3
+            resolve = "relative"
+        "#;
+        let set: SignatureSet = toml::from_str(toml).unwrap();
+        let scanner = SignatureScanner::new(&set.compile().unwrap()).unwrap().with_chunk_size(4096).with_threads(2);
+        let matches = scanner.scan(&source, &source.regions().unwrap()).unwrap();
+
+        assert_eq!(matches, vec![
+            SignatureMatch { signature: "absolute".to_string(), address: 0x401000 + 4093, resolved: 0x4A1234 },
+            SignatureMatch { signature: "relative".to_string(), address: 0x401000 + 6000, resolved: 0x401000 + 6007 + 0x100 },
+        ]);
+    }
+
+    #[test]
+    fn test_rejects_bad_signatures() {
+        let bad = SignatureEntry {
+            name: "bad".to_string(),
+            pattern: "?? ?? 85".to_string(),
+            offset: 0,
+            resolve: ResolveKind::Absolute,
+            instruction_end: None,
+            description: String::new(),
+        };
+        assert!(SignatureScanner::new(&[bad.to_signature().unwrap()]).is_err());
+        let wildcards = SignatureEntry { pattern: "?? ??".to_string(), resolve: ResolveKind::Address, ..bad };
+        assert!(SignatureScanner::new(&[wildcards.to_signature().unwrap()]).is_err());
+    }
+}
@@ -0,0 +1,550 @@
+//! Iterative value scanning
+//!
+//! A [`ValueScanSession`] finds the address of a game field the way memory
+//! editors do: a first scan collects every address holding a value (or, for an
+//! unknown starting value, copies the memory), and each next scan keeps only the
+//! candidates whose value changed the way the researcher saw it change in game,
+//! e.g. "gold decreased by 400 after training a footman". Sessions are saved
+//! between steps so a search can continue across runs.
+//!
+//! Memory is read a megabyte at a time, and the copy kept by an unknown-value
+//! first scan is stored as zstd-compressed chunks, so a scan of a large process
+//! neither reads whole regions at once nor keeps them raw in memory and on disk.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use wc2_shared::memory_source::MemorySource;
+
+use crate::memory::ProcessMemoryMap;
+
+/// Largest span of candidates fetched with a single read
+const READ_SPAN: u64 = 4096;
+
+/// Bytes of a region read (and, for an unknown value, copied) at a time
+pub(crate) const SCAN_CHUNK: usize = 1 << 20;
+
+/// zstd level of the memory copied by an unknown-value first scan
+const COPY_COMPRESSION_LEVEL: i32 = 3;
+
+/// Width and encoding of the value searched for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    F32,
+}
+
+impl ValueType {
+    pub fn width(self) -> usize {
+        match self {
+            ValueType::U8 => 1,
+            ValueType::U16 => 2,
+            ValueType::U32 | ValueType::F32 => 4,
+        }
+    }
+
+    /// Decode a little-endian value from the start of `bytes`
+    pub fn decode(self, bytes: &[u8]) -> f64 {
+        match self {
+            ValueType::U8 => bytes[0] as f64,
+            ValueType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ValueType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ValueType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        }
+    }
+
+    /// Whether two decoded values are equal, allowing float rounding for f32
+    pub fn same(self, a: f64, b: f64) -> bool {
+        match self {
+            ValueType::F32 => (a - b).abs() <= 1e-5 * b.abs().max(1.0),
+            _ => a == b,
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "u8" | "byte" => Ok(ValueType::U8),
+            "u16" | "word" => Ok(ValueType::U16),
+            "u32" | "dword" => Ok(ValueType::U32),
+            "f32" | "float" => Ok(ValueType::F32),
+            _ => bail!("Unknown value type {} (u8, u16, u32 or f32)", s),
+        }
+    }
+}
+
+/// What the first scan looks for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FirstScan {
+    Exact(f64),
+    /// Inclusive range
+    Range { min: f64, max: f64 },
+    /// Any value; the next scan compares against a copy of memory
+    Unknown,
+}
+
+/// How a next scan narrows the candidates, comparing with their previous values
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NextScan {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(f64),
+    DecreasedBy(f64),
+    Equals(f64),
+    /// Inclusive range
+    Range { min: f64, max: f64 },
+}
+
+impl NextScan {
+    fn keeps(self, value_type: ValueType, previous: f64, current: f64) -> bool {
+        match self {
+            NextScan::Changed => !value_type.same(current, previous),
+            NextScan::Unchanged => value_type.same(current, previous),
+            NextScan::Increased => current > previous && !value_type.same(current, previous),
+            NextScan::Decreased => current < previous && !value_type.same(current, previous),
+            NextScan::IncreasedBy(n) => value_type.same(current - previous, n),
+            NextScan::DecreasedBy(n) => value_type.same(previous - current, n),
+            NextScan::Equals(value) => value_type.same(current, value),
+            NextScan::Range { min, max } => (min..=max).contains(&current),
+        }
+    }
+}
+
+impl FirstScan {
+    fn keeps(self, value_type: ValueType, value: f64) -> bool {
+        match self {
+            FirstScan::Exact(expected) => value_type.same(value, expected),
+            FirstScan::Range { min, max } => (min..=max).contains(&value),
+            FirstScan::Unknown => true,
+        }
+    }
+}
+
+/// Parse "min..max" into an inclusive range
+fn parse_range(s: &str) -> Result<Option<(f64, f64)>> {
+    let Some((min, max)) = s.split_once("..") else {
+        return Ok(None);
+    };
+    let min: f64 = min.parse().map_err(|_| anyhow!("Invalid range {}", s))?;
+    let max: f64 = max.parse().map_err(|_| anyhow!("Invalid range {}", s))?;
+    if min > max {
+        bail!("Invalid range {}", s);
+    }
+    Ok(Some((min, max)))
+}
+
+fn parse_number(s: Option<&str>, scan: &str) -> Result<f64> {
+    let s = s.ok_or_else(|| anyhow!("{} needs a value", scan))?;
+    s.parse().map_err(|_| anyhow!("Invalid value {}", s))
+}
+
+impl FromStr for FirstScan {
+    type Err = anyhow::Error;
+
+    /// `1500`, `100..200` or `unknown`
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("unknown") {
+            return Ok(FirstScan::Unknown);
+        }
+        if let Some((min, max)) = parse_range(s)? {
+            return Ok(FirstScan::Range { min, max });
+        }
+        Ok(FirstScan::Exact(parse_number(Some(s), "exact")?))
+    }
+}
+
+impl FromStr for NextScan {
+    type Err = anyhow::Error;
+
+    /// `changed`, `unchanged`, `increased [n]`, `decreased [n]`, `equals v`, `v` or `min..max`
+    fn from_str(s: &str) -> Result<Self> {
+        let mut words = s.split_whitespace();
+        let scan = words.next().ok_or_else(|| anyhow!("Empty scan"))?.to_lowercase();
+        let argument = words.next();
+        let parsed = match scan.as_str() {
+            "changed" => NextScan::Changed,
+            "unchanged" => NextScan::Unchanged,
+            "increased" => match argument {
+                Some(_) => NextScan::IncreasedBy(parse_number(argument, "increased")?),
+                None => NextScan::Increased,
+            },
+            "decreased" => match argument {
+                Some(_) => NextScan::DecreasedBy(parse_number(argument, "decreased")?),
+                None => NextScan::Decreased,
+            },
+            "equals" | "=" => NextScan::Equals(parse_number(argument, "equals")?),
+            other => match parse_range(other)? {
+                Some((min, max)) => NextScan::Range { min, max },
+                None => NextScan::Equals(parse_number(Some(other), "equals")?),
+            },
+        };
+        if words.next().is_some() {
+            bail!("Unexpected words in scan '{}'", s);
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for NextScan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NextScan::Changed => write!(f, "changed"),
+            NextScan::Unchanged => write!(f, "unchanged"),
+            NextScan::Increased => write!(f, "increased"),
+            NextScan::Decreased => write!(f, "decreased"),
+            NextScan::IncreasedBy(n) => write!(f, "increased by {}", n),
+            NextScan::DecreasedBy(n) => write!(f, "decreased by {}", n),
+            NextScan::Equals(value) => write!(f, "equals {}", value),
+            NextScan::Range { min, max } => write!(f, "between {} and {}", min, max),
+        }
+    }
+}
+
+/// An address still in the running and the value it held at the last scan
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    pub address: u64,
+    pub value: f64,
+}
+
+/// A chunk of memory copied by an unknown-value first scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CopiedRegion {
+    base: u64,
+    /// Uncompressed length
+    len: usize,
+    /// zstd frame of the copied bytes
+    compressed: Vec<u8>,
+}
+
+impl CopiedRegion {
+    fn new(base: u64, bytes: &[u8]) -> Result<Self> {
+        let compressed = zstd::bulk::compress(bytes, COPY_COMPRESSION_LEVEL)?;
+        Ok(Self { base, len: bytes.len(), compressed })
+    }
+
+    fn bytes(&self) -> Result<Vec<u8>> {
+        zstd::bulk::decompress(&self.compressed, self.len)
+            .with_context(|| format!("Corrupt copy of 0x{:x}", self.base))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Candidates {
+    /// Every aligned address of the copied memory
+    Unknown(Vec<CopiedRegion>),
+    /// Sorted by address
+    Addresses(Vec<Candidate>),
+}
+
+/// One step of a session and how many candidates it left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanStep {
+    pub scan: String,
+    pub remaining: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// A value search narrowed step by step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueScanSession {
+    pub value_type: ValueType,
+    /// Candidate addresses are multiples of this
+    pub alignment: u64,
+    candidates: Candidates,
+    pub history: Vec<ScanStep>,
+}
+
+impl ValueScanSession {
+    /// Scan the writable regions of `memory_map`, aligned to the value width
+    pub fn first_scan(
+        source: &dyn MemorySource,
+        memory_map: &ProcessMemoryMap,
+        value_type: ValueType,
+        scan: FirstScan,
+    ) -> Result<Self> {
+        Self::first_scan_aligned(source, memory_map, value_type, value_type.width() as u64, scan)
+    }
+
+    pub fn first_scan_aligned(
+        source: &dyn MemorySource,
+        memory_map: &ProcessMemoryMap,
+        value_type: ValueType,
+        alignment: u64,
+        scan: FirstScan,
+    ) -> Result<Self> {
+        if alignment == 0 {
+            bail!("Alignment must be at least 1");
+        }
+
+        // Game fields live in writable memory; code and read-only data cannot change.
+        // Regions are read a chunk at a time, each chunk overlapping the next by
+        // one value so that values straddling the boundary are still seen once.
+        let width = value_type.width();
+        let mut copied = Vec::new();
+        let mut found = Vec::new();
+        for region in memory_map.regions.iter().filter(|region| region.readable && region.writable) {
+            for offset in (0..region.size).step_by(SCAN_CHUNK) {
+                let base = region.base_address + offset as u64;
+                let len = (SCAN_CHUNK + width - 1).min(region.size - offset);
+                let bytes = match source.read_bytes(base, len) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        debug!("⚠️ Skipping chunk 0x{:x}: {}", base, e);
+                        continue;
+                    }
+                };
+                if scan == FirstScan::Unknown {
+                    copied.push(CopiedRegion::new(base, &bytes)?);
+                    continue;
+                }
+                for (address, value) in values(base, &bytes, value_type, alignment) {
+                    if scan.keeps(value_type, value) {
+                        found.push(Candidate { address, value });
+                    }
+                }
+            }
+        }
+
+        let candidates = if scan == FirstScan::Unknown {
+            Candidates::Unknown(copied)
+        } else {
+            found.sort_by_key(|candidate| candidate.address);
+            Candidates::Addresses(found)
+        };
+        let mut session = Self { value_type, alignment, candidates, history: Vec::new() };
+        session.record(format!("first scan {:?}", scan));
+        Ok(session)
+    }
+
+    /// Keep the candidates whose value changed as `scan` describes; returns how many remain
+    pub fn next_scan(&mut self, source: &dyn MemorySource, scan: NextScan) -> Result<usize> {
+        let value_type = self.value_type;
+        let kept = match &self.candidates {
+            Candidates::Unknown(copied) => {
+                let mut kept = Vec::new();
+                for region in copied {
+                    // Compare what is still readable of each copied chunk
+                    let Ok(current) = source.read_bytes(region.base, region.len) else {
+                        continue;
+                    };
+                    let previous = region.bytes()?;
+                    let previous = values(region.base, &previous, value_type, self.alignment);
+                    let current = values(region.base, &current, value_type, self.alignment);
+                    for ((address, previous), (_, current)) in previous.zip(current) {
+                        if scan.keeps(value_type, previous, current) {
+                            kept.push(Candidate { address, value: current });
+                        }
+                    }
+                }
+                kept
+            }
+            Candidates::Addresses(candidates) => {
+                let mut kept = Vec::new();
+                for (candidate, current) in read_candidates(source, candidates, value_type) {
+                    if scan.keeps(value_type, candidate.value, current) {
+                        kept.push(Candidate { address: candidate.address, value: current });
+                    }
+                }
+                kept
+            }
+        };
+
+        self.candidates = Candidates::Addresses(kept);
+        self.record(scan.to_string());
+        Ok(self.len())
+    }
+
+    /// Number of candidates left
+    pub fn len(&self) -> usize {
+        match &self.candidates {
+            Candidates::Unknown(copied) => copied.iter()
+                .map(|region| value_count(region.base, region.len, self.value_type, self.alignment))
+                .sum(),
+            Candidates::Addresses(candidates) => candidates.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Up to `limit` candidates with the values of the last scan, none before a next scan of an unknown value
+    pub fn candidates(&self, limit: usize) -> Vec<Candidate> {
+        match &self.candidates {
+            Candidates::Unknown(_) => Vec::new(),
+            Candidates::Addresses(candidates) => candidates.iter().take(limit).copied().collect(),
+        }
+    }
+
+    /// Current values of up to `limit` candidates, without narrowing
+    pub fn current_values(&self, source: &dyn MemorySource, limit: usize) -> Vec<(Candidate, f64)> {
+        read_candidates(source, &self.candidates(limit), self.value_type)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = bincode::serialize(self)?;
+        std::fs::write(path, bytes).with_context(|| format!("Failed to save value scan {}", path.display()))?;
+        info!("💾 Saved value scan with {} candidates to {}", self.len(), path.display());
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read value scan {}", path.display()))?;
+        bincode::deserialize(&bytes).with_context(|| format!("Invalid value scan {}", path.display()))
+    }
+
+    fn record(&mut self, scan: String) {
+        let remaining = self.len();
+        info!("🔎 Value scan '{}': {} candidates", scan, remaining);
+        self.history.push(ScanStep { scan, remaining, timestamp: chrono::Utc::now() });
+    }
+}
+
+/// Aligned (address, value) pairs of a buffer read from `base`
+fn values(base: u64, bytes: &[u8], value_type: ValueType, alignment: u64) -> impl Iterator<Item = (u64, f64)> + '_ {
+    let width = value_type.width();
+    let first = (alignment - base % alignment) % alignment;
+    (first as usize..bytes.len().saturating_sub(width - 1))
+        .step_by(alignment as usize)
+        .map(move |offset| (base + offset as u64, value_type.decode(&bytes[offset..offset + width])))
+}
+
+/// Number of pairs [`values`] yields for a buffer of `len` bytes read from `base`
+fn value_count(base: u64, len: usize, value_type: ValueType, alignment: u64) -> usize {
+    let first = ((alignment - base % alignment) % alignment) as usize;
+    let end = len.saturating_sub(value_type.width() - 1);
+    if first >= end { 0 } else { (end - first - 1) / alignment as usize + 1 }
+}
+
+/// Current values of sorted candidates, reading nearby ones together; unreadable ones are dropped
+fn read_candidates(source: &dyn MemorySource, candidates: &[Candidate], value_type: ValueType) -> Vec<(Candidate, f64)> {
+    let width = value_type.width();
+    let mut read = Vec::with_capacity(candidates.len());
+    let mut start = 0;
+    while start < candidates.len() {
+        let base = candidates[start].address;
+        let end = candidates[start..].iter()
+            .position(|candidate| candidate.address - base >= READ_SPAN)
+            .map_or(candidates.len(), |offset| start + offset);
+
+        let len = (candidates[end - 1].address - base) as usize + width;
+        if let Ok(bytes) = source.read_bytes(base, len) {
+            for candidate in &candidates[start..end] {
+                let offset = (candidate.address - base) as usize;
+                if offset + width <= bytes.len() {
+                    read.push((*candidate, value_type.decode(&bytes[offset..offset + width])));
+                }
+            }
+        }
+        start = end;
+    }
+    read
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wc2_shared::memory_source::{MemoryRegion, SnapshotSource};
+
+    fn memory(gold: u32, wood: u32) -> SnapshotSource {
+        let mut bytes = vec![0u8; 0x1000];
+        bytes[0x100..0x104].copy_from_slice(&gold.to_le_bytes());
+        bytes[0x200..0x204].copy_from_slice(&wood.to_le_bytes());
+        // A decoy that also holds the starting gold but never changes
+        bytes[0x300..0x304].copy_from_slice(&2000u32.to_le_bytes());
+        let mut source = SnapshotSource::new("test");
+        let region = MemoryRegion { base: 0x500000, size: 0, readable: true, writable: true, executable: false, name: None };
+        source.add_region(region, bytes).unwrap();
+        source
+    }
+
+    #[test]
+    fn test_exact_then_decreased_by() {
+        let before = memory(2000, 1000);
+        let map = ProcessMemoryMap::from_source(&before).unwrap();
+        let mut session = ValueScanSession::first_scan(&before, &map, ValueType::U32, FirstScan::Exact(2000.0)).unwrap();
+        assert_eq!(session.len(), 2);
+
+        // Trained a footman for 600 gold
+        let after = memory(1400, 1000);
+        assert_eq!(session.next_scan(&after, "decreased 600".parse().unwrap()).unwrap(), 1);
+        assert_eq!(session.candidates(10), vec![Candidate { address: 0x500100, value: 1400.0 }]);
+        assert_eq!(session.history.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gold.scan");
+        session.save(&path).unwrap();
+        let mut loaded = ValueScanSession::load(&path).unwrap();
+        assert_eq!(loaded.next_scan(&memory(1400, 900), NextScan::Unchanged).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_unknown_then_changed() {
+        let before = memory(2000, 1000);
+        let map = ProcessMemoryMap::from_source(&before).unwrap();
+        let mut session = ValueScanSession::first_scan(&before, &map, ValueType::U32, FirstScan::Unknown).unwrap();
+        assert_eq!(session.len(), 0x1000 / 4);
+        assert!(session.candidates(10).is_empty());
+
+        assert_eq!(session.next_scan(&memory(2000, 1100), NextScan::Increased).unwrap(), 1);
+        assert_eq!(session.candidates(1)[0].address, 0x500200);
+        assert_eq!(session.next_scan(&memory(2000, 1100), "1000..1200".parse().unwrap()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_value_straddling_a_chunk_boundary() {
+        // A 2 MiB + 2 byte region, with a u32 across the first chunk boundary
+        let at = SCAN_CHUNK - 2;
+        let snapshot = |value: u32| {
+            let mut bytes = vec![0u8; 2 * SCAN_CHUNK + 2];
+            bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            let mut source = SnapshotSource::new("test");
+            let region = MemoryRegion { base: 0x500000, size: 0, readable: true, writable: true, executable: false, name: None };
+            source.add_region(region, bytes).unwrap();
+            source
+        };
+        let before = snapshot(0x12345678);
+        let map = ProcessMemoryMap::from_source(&before).unwrap();
+
+        let exact = ValueScanSession::first_scan_aligned(&before, &map, ValueType::U32, 1, FirstScan::Exact(305419896.0)).unwrap();
+        assert_eq!(exact.candidates(10), vec![Candidate { address: 0x500000 + at as u64, value: 305419896.0 }]);
+
+        // Every byte address holding a whole u32 is a candidate exactly once
+        let mut unknown = ValueScanSession::first_scan_aligned(&before, &map, ValueType::U32, 1, FirstScan::Unknown).unwrap();
+        assert_eq!(unknown.len(), 2 * SCAN_CHUNK - 1);
+        assert_eq!(unknown.next_scan(&snapshot(0x12345679), NextScan::Equals(305419897.0)).unwrap(), 1);
+        assert_eq!(unknown.candidates(1)[0].address, 0x500000 + at as u64);
+    }
+
+    #[test]
+    fn test_value_count_matches_values() {
+        for (base, len, alignment) in [(0x1000, 0, 4), (0x1001, 3, 1), (0x1001, 16, 4), (0x1003, 4, 4), (0x1000, 7, 2)] {
+            let bytes = vec![0u8; len];
+            assert_eq!(value_count(base, len, ValueType::U32, alignment), values(base, &bytes, ValueType::U32, alignment).count());
+        }
+    }
+
+    #[test]
+    fn test_parse_scans() {
+        assert_eq!("unknown".parse::<FirstScan>().unwrap(), FirstScan::Unknown);
+        assert_eq!("10..20".parse::<FirstScan>().unwrap(), FirstScan::Range { min: 10.0, max: 20.0 });
+        assert_eq!("increased".parse::<NextScan>().unwrap(), NextScan::Increased);
+        assert_eq!("equals 5".parse::<NextScan>().unwrap(), NextScan::Equals(5.0));
+        assert_eq!("1.5".parse::<NextScan>().unwrap(), NextScan::Equals(1.5));
+        assert!("decreased lots".parse::<NextScan>().is_err());
+        assert!("20..10".parse::<FirstScan>().is_err());
+        assert_eq!("dword".parse::<ValueType>().unwrap(), ValueType::U32);
+        assert!(ValueType::F32.same(0.1 + 0.2, 0.3));
+    }
+}
@@ -55,6 +55,7 @@ impl BinaryAnalyzer {
             file_hash,
             header,
             patterns,
+            format: None,
         })
     }
 
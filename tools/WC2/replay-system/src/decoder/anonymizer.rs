@@ -0,0 +1,354 @@
+//! Replay anonymizer for publishing replays
+//!
+//! Rewrites player names to stable pseudonyms, masks or strips chat and drops
+//! identifying file details, then re-encodes the replay so tournament games
+//! can be shared for research without exposing who played them.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use super::{DecodedReplay, ReplayDecoder};
+use super::events::EventType;
+use super::formats::w2r;
+
+/// What to do with chat messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatPolicy {
+    /// Leave chat untouched
+    Keep,
+    /// Replace every character of the message with `*`, keeping its length
+    Mask,
+    /// Remove chat events entirely
+    Strip,
+}
+
+/// Anonymizer settings
+#[derive(Debug, Clone)]
+pub struct AnonymizerConfig {
+    /// Secret mixed into pseudonyms; reuse it to keep names stable across replays
+    ///
+    /// Defaults to a random salt, so pseudonyms only match within one run.
+    /// Without a secret salt, a list of known ladder names reverses them.
+    pub salt: String,
+    /// Prefix of generated pseudonyms (e.g. "Player-3fa9c1d2")
+    pub name_prefix: String,
+    pub chat: ChatPolicy,
+    /// Format to re-encode into; `None` keeps the source format
+    pub output_format: Option<String>,
+    /// Fixed pseudonyms that take precedence over generated ones
+    pub pseudonyms: HashMap<String, String>,
+}
+
+impl Default for AnonymizerConfig {
+    fn default() -> Self {
+        Self {
+            salt: random_hex(),
+            name_prefix: "Player".to_string(),
+            chat: ChatPolicy::Mask,
+            output_format: None,
+            pseudonyms: HashMap::new(),
+        }
+    }
+}
+
+/// An anonymized replay and its encoded file contents
+pub struct AnonymizedReplay {
+    pub replay: DecodedReplay,
+    /// Name of the format the bytes are in, or "json" for the JSON export
+    pub format: String,
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+impl AnonymizedReplay {
+    /// Write the encoded replay into a directory and return its path
+    pub fn write_to(&self, directory: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(directory)?;
+        let path = directory.join(&self.filename);
+        std::fs::write(&path, &self.bytes)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Rewrites decoded replays so they can be published
+pub struct ReplayAnonymizer {
+    config: AnonymizerConfig,
+    decoder: ReplayDecoder,
+}
+
+impl ReplayAnonymizer {
+    pub fn new(config: AnonymizerConfig) -> Self {
+        Self::with_decoder(config, ReplayDecoder::new())
+    }
+
+    /// Create an anonymizer that encodes through a decoder's format registry
+    pub fn with_decoder(mut config: AnonymizerConfig, decoder: ReplayDecoder) -> Self {
+        if config.salt.is_empty() {
+            warn!("Anonymizer salt is empty, using a random one");
+            config.salt = random_hex();
+        }
+        Self { config, decoder }
+    }
+
+    /// Pseudonym for a player name; the same name and salt always give the same result
+    pub fn pseudonym(&self, name: &str) -> String {
+        if let Some(pseudonym) = self.config.pseudonyms.get(name) {
+            return pseudonym.clone();
+        }
+
+        let digest = self.salted_digest(name.trim().to_lowercase().as_bytes());
+        format!("{}-{}", self.config.name_prefix, &digest[..8])
+    }
+
+    /// Anonymize a replay and re-encode it
+    pub fn anonymize(&self, replay: &DecodedReplay) -> Result<AnonymizedReplay> {
+        let mut anonymized = replay.clone();
+
+        // Player names, from both the metadata and the game state
+        let mut names: HashMap<String, String> = HashMap::new();
+        let real_names = replay.metadata.players.iter()
+            .map(|player| &player.name)
+            .chain(replay.game_state.players.values().map(|player| &player.name));
+        for name in real_names {
+            if !name.is_empty() {
+                names.entry(name.clone()).or_insert_with(|| self.pseudonym(name));
+            }
+        }
+
+        for player in &mut anonymized.metadata.players {
+            if let Some(pseudonym) = names.get(&player.name) {
+                player.name = pseudonym.clone();
+            }
+        }
+        for player in anonymized.game_state.players.values_mut() {
+            if let Some(pseudonym) = names.get(&player.name) {
+                player.name = pseudonym.clone();
+            }
+        }
+
+        // Annotation text can quote player names
+        for track in &mut anonymized.tracks {
+            for annotation in &mut track.annotations {
+                annotation.label = replace_names(&annotation.label, &names);
+                annotation.detail = replace_names(&annotation.detail, &names);
+            }
+        }
+
+        self.apply_chat_policy(&mut anonymized);
+
+        // The original file name and hash identify the source file, so the new id owes nothing to them
+        let replay_id = self.salted_digest(random_hex().as_bytes());
+        let replay_id = format!("replay-{}", &replay_id[..12]);
+        anonymized.analysis.header = None;
+        anonymized.analysis.patterns = None;
+
+        let format_name = self.config.output_format.clone()
+            .or_else(|| anonymized.analysis.format.clone());
+        let encoder = format_name.as_deref()
+            .and_then(|name| self.decoder.formats().get(name))
+            .filter(|format| format.can_encode());
+
+        match encoder {
+            Some(format) => {
+                let filename = format!("{}.{}", replay_id, format.name());
+                let bytes = format.encode(&anonymized)?;
+
+                // Decode the output again so checksums match what readers will see
+                let decoded = self.decoder.decode_bytes(Path::new(&filename), &bytes)
+                    .context("Re-encoded replay could not be decoded")?;
+                if decoded.events.len() != anonymized.events.len() {
+                    anyhow::bail!(
+                        "Re-encoded replay has {} events, expected {}",
+                        decoded.events.len(),
+                        anonymized.events.len()
+                    );
+                }
+
+                anonymized.metadata.filename = filename.clone();
+                anonymized.metadata.file_size = bytes.len() as u64;
+                anonymized.metadata.checksum = decoded.analysis.file_hash.clone();
+                anonymized.events = decoded.events;
+                anonymized.analysis = decoded.analysis;
+
+                Ok(AnonymizedReplay {
+                    replay: anonymized,
+                    format: format.name().to_string(),
+                    filename,
+                    bytes,
+                })
+            }
+            None => {
+                if let Some(name) = &format_name {
+                    warn!("Replay format {} cannot encode, exporting JSON instead", name);
+                }
+
+                let filename = format!("{}.json", replay_id);
+                anonymized.metadata.filename = filename.clone();
+                anonymized.analysis.filename = filename.clone();
+
+                // The checksum covers the export with its checksum fields blank
+                anonymized.metadata.checksum.clear();
+                anonymized.analysis.file_hash.clear();
+                let checksum = format!("{:x}", Sha256::digest(serde_json::to_vec(&anonymized)?));
+                anonymized.metadata.checksum = checksum.clone();
+                anonymized.analysis.file_hash = checksum;
+
+                let bytes = serde_json::to_vec_pretty(&anonymized)?;
+                anonymized.metadata.file_size = bytes.len() as u64;
+                anonymized.analysis.file_size = bytes.len() as u64;
+
+                Ok(AnonymizedReplay {
+                    replay: anonymized,
+                    format: "json".to_string(),
+                    filename,
+                    bytes,
+                })
+            }
+        }
+    }
+
+    /// Mask or drop chat events. Chat payloads are a player byte followed by the text.
+    fn apply_chat_policy(&self, replay: &mut DecodedReplay) {
+        match self.config.chat {
+            ChatPolicy::Keep => {}
+            ChatPolicy::Strip => {
+                replay.events.retain(|event| !matches!(event.event_type, EventType::Chat));
+            }
+            ChatPolicy::Mask => {
+                for event in &mut replay.events {
+                    if !matches!(event.event_type, EventType::Chat) {
+                        continue;
+                    }
+                    let text_start = event.data.len() - w2r::event_payload(event).len() + 1;
+                    if let Some(text) = event.data.get_mut(text_start..) {
+                        for byte in text.iter_mut().filter(|byte| **byte != 0) {
+                            *byte = b'*';
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn salted_digest(&self, data: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.config.salt.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(data);
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// 128 random bits as hex
+fn random_hex() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+/// Replace every occurrence of the real names in a piece of text, longest first
+fn replace_names(text: &str, names: &HashMap<String, String>) -> String {
+    let mut ordered: Vec<_> = names.iter().collect();
+    ordered.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+    ordered.into_iter().fold(text.to_string(), |text, (name, pseudonym)| text.replace(name.as_str(), pseudonym))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::events::GameEvent;
+    use crate::decoder::formats::ReplayFormat;
+    use crate::decoder::formats::w2r::W2RFormat;
+    use crate::test_fixtures::replay;
+
+    /// Identifiers a client could leave in the header's reserved bytes
+    const HOST_TAG: &[u8] = b"host=lan-box-7;acct=grom#4431";
+
+    fn event(event_type: EventType, timestamp: u32, data: Vec<u8>) -> GameEvent {
+        GameEvent { event_type, timestamp, size: data.len() as u16, data }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    /// A decoded W2R game between Grom and Orgrim with one chat line and its source bytes
+    fn decoded_game() -> (DecodedReplay, Vec<u8>) {
+        let mut source = replay(2, vec![
+            event(EventType::PlayerAction, 100, vec![0, 1, 10, 0, 12, 0]),
+            event(EventType::Chat, 200, [&[1u8][..], b"gl hf Grom"].concat()),
+            event(EventType::UnitDestroyed, 300, vec![7, 0, 0, 0]),
+        ]);
+        source.metadata.players[1].name = "Orgrim".to_string();
+
+        let mut bytes = W2RFormat::new().encode(&source).unwrap();
+        bytes[32..32 + HOST_TAG.len()].copy_from_slice(HOST_TAG);
+        let decoded = ReplayDecoder::new().decode_bytes(Path::new("Grom-vs-Orgrim.w2r"), &bytes).unwrap();
+        (decoded, bytes)
+    }
+
+    #[test]
+    fn test_anonymize_decoded_replay() {
+        let (decoded, source) = decoded_game();
+        assert_eq!(decoded.analysis.format.as_deref(), Some("w2r"));
+        assert!(contains(&source, HOST_TAG));
+
+        let anonymizer = ReplayAnonymizer::new(AnonymizerConfig::default());
+        let output = anonymizer.anonymize(&decoded).unwrap();
+        assert_eq!(output.format, "w2r");
+        assert!(output.filename.starts_with("replay-") && output.filename.ends_with(".w2r"));
+
+        // Names become pseudonyms; neither they, the file name nor the header identifiers survive
+        let names: Vec<&str> = output.replay.metadata.players.iter().map(|player| player.name.as_str()).collect();
+        assert_eq!(names, vec![anonymizer.pseudonym("Grom"), anonymizer.pseudonym("Orgrim")]);
+        for identifier in [&b"Grom"[..], b"Orgrim", b"lan-box-7", b"grom#4431"] {
+            assert!(!contains(&output.bytes, identifier), "{}", String::from_utf8_lossy(identifier));
+        }
+        assert!(!output.replay.metadata.filename.contains("Grom"));
+        assert!(output.replay.analysis.header.as_ref().is_some_and(|header| header.reserved.iter().all(|byte| *byte == 0)));
+
+        // Chat is masked to its length, keeping the speaker; other events are untouched
+        let payloads: Vec<&[u8]> = output.replay.events.iter().map(w2r::event_payload).collect();
+        assert_eq!(payloads, vec![&[0u8, 1, 10, 0, 12, 0][..], b"\x01**********", &[7u8, 0, 0, 0][..]]);
+
+        // The output decodes on its own and its checksums are the ones recorded
+        let redecoded = ReplayDecoder::new().decode_bytes(Path::new(&output.filename), &output.bytes).unwrap();
+        assert_eq!(redecoded.events.iter().map(w2r::event_payload).collect::<Vec<_>>(), payloads);
+        assert_eq!(redecoded.metadata.players.iter().map(|player| player.name.as_str()).collect::<Vec<_>>(), names);
+        assert_eq!(output.replay.metadata.checksum, redecoded.analysis.file_hash);
+        assert_eq!(output.replay.analysis.file_hash, redecoded.analysis.file_hash);
+        assert_ne!(output.replay.metadata.checksum, decoded.analysis.file_hash);
+        assert_eq!(output.replay.metadata.file_size, output.bytes.len() as u64);
+    }
+
+    #[test]
+    fn test_anonymize_strips_chat() {
+        let (decoded, _) = decoded_game();
+        let config = AnonymizerConfig { chat: ChatPolicy::Strip, ..Default::default() };
+        let output = ReplayAnonymizer::new(config).anonymize(&decoded).unwrap();
+        let types: Vec<u8> = output.replay.events.iter().map(|event| w2r::event_type_code(&event.event_type)).collect();
+        assert_eq!(types, vec![0x01, 0x06]);
+        assert!(!contains(&output.bytes, b"gl hf"));
+    }
+
+    #[test]
+    fn test_pseudonyms_are_salted() {
+        let first = ReplayAnonymizer::new(AnonymizerConfig::default());
+        let second = ReplayAnonymizer::new(AnonymizerConfig::default());
+        assert_eq!(first.pseudonym("Grom"), first.pseudonym(" grom "));
+        assert_ne!(first.pseudonym("Grom"), second.pseudonym("Grom"));
+
+        let unsalted = format!("{:x}", Sha256::digest(b"\0grom"));
+        let empty = ReplayAnonymizer::new(AnonymizerConfig { salt: String::new(), ..Default::default() });
+        assert_ne!(empty.pseudonym("Grom"), format!("Player-{}", &unsalted[..8]));
+
+        let fixed = AnonymizerConfig { salt: "ladder-2026".to_string(), ..Default::default() };
+        assert_eq!(
+            ReplayAnonymizer::new(fixed.clone()).pseudonym("Grom"),
+            ReplayAnonymizer::new(fixed).pseudonym("Grom"),
+        );
+    }
+}
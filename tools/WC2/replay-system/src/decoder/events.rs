@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize};
+use wc2_shared::game_rules::{GameRules, UnitRules};
 
 /// Game event extracted from replay
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,7 +16,10 @@ pub enum EventType {
     PlayerAction,
     UnitMove,
     UnitAttack,
+    UnitCreated,
+    UnitDestroyed,
     BuildingConstruct,
+    BuildingDestroyed,
     ResourceGather,
     UnitTrain,
     Research,
@@ -111,6 +115,42 @@ pub enum BuildingType {
     Unknown,
 }
 
+impl BuildingType {
+    /// Key of this building in the shared game-rules table
+    pub fn rules_key(&self) -> Option<&'static str> {
+        match self {
+            BuildingType::TownHall => Some("town_hall"),
+            BuildingType::Barracks => Some("barracks"),
+            BuildingType::Farm => Some("farm"),
+            BuildingType::LumberMill => Some("lumber_mill"),
+            BuildingType::Blacksmith => Some("blacksmith"),
+            BuildingType::Church => Some("church"),
+            BuildingType::Tower => Some("guard_tower"),
+            BuildingType::Castle => Some("castle"),
+            BuildingType::GreatHall => Some("great_hall"),
+            BuildingType::FarmOrc => Some("pig_farm"),
+            BuildingType::BarracksOrc => Some("orc_barracks"),
+            BuildingType::WarMill => Some("troll_lumber_mill"),
+            BuildingType::Temple => Some("altar_of_storms"),
+            BuildingType::TowerOrc => Some("orc_guard_tower"),
+            BuildingType::Fortress => Some("fortress"),
+            BuildingType::Peon | BuildingType::Unknown => None,
+        }
+    }
+
+    /// Canonical stats and costs for this building
+    pub fn rules(&self) -> Option<&'static UnitRules> {
+        self.rules_key().and_then(|key| GameRules::embedded().unit(key))
+    }
+}
+
+/// Target coordinate of a player action that has no target on the map
+pub const NO_TARGET: u16 = u16::MAX;
+
+/// Gatherer ID of a resource gathering event that only records the player,
+/// who then follows the location as a trailing byte
+pub const NO_GATHERER: u32 = u32::MAX;
+
 /// Resource gathering event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceGather {
@@ -162,6 +202,34 @@ pub enum UnitType {
     Unknown,
 }
 
+impl UnitType {
+    /// Key of this unit in the shared game-rules table
+    pub fn rules_key(&self) -> Option<&'static str> {
+        match self {
+            UnitType::Peasant => Some("peasant"),
+            UnitType::Footman => Some("footman"),
+            UnitType::Knight => Some("knight"),
+            UnitType::Archer => Some("archer"),
+            UnitType::Paladin => Some("paladin"),
+            UnitType::Mage => Some("mage"),
+            UnitType::Ballista => Some("ballista"),
+            UnitType::Peon => Some("peon"),
+            UnitType::Grunt => Some("grunt"),
+            UnitType::Ogre => Some("ogre"),
+            UnitType::Troll => Some("axethrower"),
+            UnitType::Catapult => Some("catapult"),
+            UnitType::DeathKnight => Some("death_knight"),
+            UnitType::OgreMage => Some("ogre_mage"),
+            UnitType::Unknown => None,
+        }
+    }
+
+    /// Canonical stats and costs for this unit
+    pub fn rules(&self) -> Option<&'static UnitRules> {
+        self.rules_key().and_then(|key| GameRules::embedded().unit(key))
+    }
+}
+
 /// Research event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Research {
@@ -0,0 +1,173 @@
+//! Replay format registry
+//!
+//! Every replay container the system understands implements [`ReplayFormat`].
+//! The [`FormatRegistry`] sniffs incoming files and hands them to whichever
+//! format claims them with the highest confidence, so new formats can be added
+//! without touching the parser.
+
+pub mod w2r;
+pub mod wc2ai;
+
+use std::sync::Arc;
+use anyhow::Result;
+
+use crate::structures::{FileAnalysis, FileType};
+use crate::decoder::{DecodedReplay, ReplayMetadata, AnnotationTrack};
+use super::events::GameEvent;
+
+/// Leading bytes of a file that formats are given to sniff
+pub const SNIFF_LENGTH: usize = 4096;
+
+/// How sure a format is that it can decode a given file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    None,
+    Low,
+    Medium,
+    High,
+    Certain,
+}
+
+/// A replay container that can be detected and decoded
+pub trait ReplayFormat: Send + Sync {
+    /// Short unique name of the format (e.g. "w2r")
+    fn name(&self) -> &'static str;
+
+    /// File type reported in the file analysis for this format
+    fn file_type(&self) -> FileType {
+        FileType::Unknown
+    }
+
+    /// Inspect the leading bytes of a file, at most [`SNIFF_LENGTH`] of them,
+    /// and report how likely it is ours
+    fn sniff(&self, data: &[u8]) -> Confidence;
+
+    /// Inspect the file name; formats without naming conventions keep the default
+    fn sniff_filename(&self, _filename: &str) -> Confidence {
+        Confidence::None
+    }
+
+    /// Decode replay metadata from the full file contents
+    fn decode_metadata(&self, data: &[u8], analysis: &FileAnalysis) -> Result<ReplayMetadata>;
+
+    /// Decode all game events from the full file contents
+    fn decode_events(&self, data: &[u8]) -> Result<Vec<GameEvent>>;
+
+    /// Decode annotation tracks; most formats carry none
+    fn decode_tracks(&self, _data: &[u8]) -> Result<Vec<AnnotationTrack>> {
+        Ok(Vec::new())
+    }
+
+    /// Whether [`ReplayFormat::encode`] is implemented
+    fn can_encode(&self) -> bool {
+        false
+    }
+
+    /// Write a decoded replay back into this format's container
+    fn encode(&self, _replay: &DecodedReplay) -> Result<Vec<u8>> {
+        anyhow::bail!("Replay format {} does not support encoding", self.name())
+    }
+}
+
+/// Registry of known replay formats consulted by the decoder
+#[derive(Clone, Default)]
+pub struct FormatRegistry {
+    formats: Vec<Arc<dyn ReplayFormat>>,
+}
+
+impl FormatRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with all built-in formats registered
+    pub fn with_builtin_formats() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(w2r::W2RFormat::new()));
+        registry.register(Arc::new(wc2ai::WC2AIFormat::new()));
+        registry
+    }
+
+    /// Register a format. A format with the same name replaces the old one.
+    pub fn register(&mut self, format: Arc<dyn ReplayFormat>) {
+        self.formats.retain(|existing| existing.name() != format.name());
+        self.formats.push(format);
+    }
+
+    /// Look up a format by name
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ReplayFormat>> {
+        self.formats.iter().find(|format| format.name() == name).cloned()
+    }
+
+    /// Names of all registered formats, in registration order
+    pub fn format_names(&self) -> Vec<&'static str> {
+        self.formats.iter().map(|format| format.name()).collect()
+    }
+
+    /// Find the format that best matches a file.
+    ///
+    /// Content and filename sniffing are combined by taking the stronger of
+    /// the two. Ties go to the format registered first. Only the first
+    /// [`SNIFF_LENGTH`] bytes of `data` are looked at.
+    pub fn detect(&self, filename: &str, data: &[u8]) -> Option<Arc<dyn ReplayFormat>> {
+        let data = &data[..data.len().min(SNIFF_LENGTH)];
+        let mut best: Option<(Confidence, &Arc<dyn ReplayFormat>)> = None;
+
+        for format in &self.formats {
+            let confidence = format.sniff(data).max(format.sniff_filename(filename));
+            if confidence == Confidence::None {
+                continue;
+            }
+            if best.map_or(true, |(best_confidence, _)| confidence > best_confidence) {
+                best = Some((confidence, format));
+            }
+        }
+
+        best.map(|(_, format)| format.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::formats::w2r::W2R_MAGIC;
+
+    const RECORDING: &[u8] = br#"{"replay_id": "r1", "game_name": "ladder"}"#;
+
+    #[test]
+    fn test_lookup_by_name() {
+        let registry = FormatRegistry::with_builtin_formats();
+        assert_eq!(registry.format_names(), ["w2r", "wc2ai"]);
+        assert_eq!(registry.get("wc2ai").unwrap().name(), "wc2ai");
+        assert!(registry.get("pud").is_none());
+
+        // A format registered under an existing name replaces it
+        let mut registry = registry;
+        registry.register(Arc::new(w2r::W2RFormat::new()));
+        assert_eq!(registry.format_names(), ["wc2ai", "w2r"]);
+    }
+
+    #[test]
+    fn test_detect() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let detected = |filename: &str, data: &[u8]| registry.detect(filename, data).map(|format| format.name());
+
+        assert_eq!(detected("replay.bin", W2R_MAGIC), Some("w2r"));
+        assert_eq!(detected("game.json", RECORDING), Some("wc2ai"));
+        // Contents outrank a file name pointing elsewhere
+        assert_eq!(detected("game.w2r", RECORDING), Some("wc2ai"));
+        // Without recognisable contents the file name decides
+        assert_eq!(detected("012345678.idx", b"????"), Some("w2r"));
+        assert_eq!(detected("notes.json", b"{}"), Some("wc2ai"));
+        assert_eq!(detected("notes.txt", b"hello"), None);
+    }
+
+    #[test]
+    fn test_detect_only_sniffs_the_head() {
+        let registry = FormatRegistry::with_builtin_formats();
+        let mut data = vec![b' '; SNIFF_LENGTH];
+        data.extend_from_slice(RECORDING);
+        assert!(registry.detect("recording", &data).is_none());
+    }
+}
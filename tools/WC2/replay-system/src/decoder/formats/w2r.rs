@@ -0,0 +1,373 @@
+//! WC2 Remastered `.w2r` replay format
+
+use std::io::{Read, Seek, SeekFrom, Cursor, Write};
+use anyhow::Result;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::{TimeZone, Utc};
+
+use crate::structures::{FileAnalysis, FileType};
+use crate::decoder::{DecodedReplay, ReplayMetadata, GameType, PlayerInfo, Race, PlayerColor};
+use crate::decoder::events::{GameEvent, EventType};
+use super::{Confidence, ReplayFormat};
+
+/// Magic bytes at the start of a W2R replay
+pub const W2R_MAGIC: &[u8; 4] = b"W2R\0";
+
+/// Size of the fixed W2R header preceding metadata and events
+pub const W2R_HEADER_SIZE: usize = 64;
+
+/// Size of the metadata block before the player table
+pub const W2R_METADATA_SIZE: usize = 50;
+
+/// Size of one player entry in the metadata block
+pub const W2R_PLAYER_SIZE: usize = 20;
+
+/// Size of the header preceding every event record
+pub const W2R_EVENT_HEADER_SIZE: usize = 7;
+
+/// Container version written by the encoder
+pub const W2R_VERSION: u32 = 1;
+
+/// Event type code used in W2R event records
+pub fn event_type_code(event_type: &EventType) -> u8 {
+    match event_type {
+        EventType::PlayerAction => 0x01,
+        EventType::UnitMove => 0x02,
+        EventType::UnitAttack => 0x03,
+        EventType::BuildingConstruct => 0x04,
+        EventType::UnitCreated => 0x05,
+        EventType::UnitDestroyed => 0x06,
+        EventType::BuildingDestroyed => 0x07,
+        EventType::ResourceGather => 0x08,
+        EventType::UnitTrain => 0x09,
+        EventType::Research => 0x0A,
+        EventType::Chat => 0x0B,
+        EventType::Unknown => 0xFF,
+    }
+}
+
+/// Event payload without the W2R record header.
+///
+/// Events decoded from W2R files keep their record header in `data`; events
+/// from other formats carry only the payload.
+pub fn event_payload(event: &GameEvent) -> &[u8] {
+    let data = &event.data;
+    let has_header = data.len() >= W2R_EVENT_HEADER_SIZE
+        && data.len() == event.size as usize
+        && data[0] == event_type_code(&event.event_type)
+        && data[1..5] == event.timestamp.to_le_bytes()
+        && data[5..7] == event.size.to_le_bytes();
+
+    if has_header {
+        &data[W2R_EVENT_HEADER_SIZE..]
+    } else {
+        data
+    }
+}
+
+/// Built-in decoder for WC2 Remastered replays
+#[derive(Debug, Clone, Default)]
+pub struct W2RFormat;
+
+impl W2RFormat {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Parse a single event at given offset
+    fn parse_event_at_offset(&self, buffer: &[u8], offset: usize) -> Result<Option<GameEvent>> {
+        if offset >= buffer.len() {
+            return Ok(None);
+        }
+
+        let mut cursor = Cursor::new(&buffer[offset..]);
+
+        // Read event header
+        let event_type = cursor.read_u8()?;
+        let timestamp = cursor.read_u32::<LittleEndian>()?;
+        let size = cursor.read_u16::<LittleEndian>()?;
+
+        // A zero-sized or truncated event means we ran into garbage
+        if size == 0 || offset + size as usize > buffer.len() {
+            return Ok(None);
+        }
+
+        let event_type = match event_type {
+            0x01 => EventType::PlayerAction,
+            0x02 => EventType::UnitMove,
+            0x03 => EventType::UnitAttack,
+            0x04 => EventType::BuildingConstruct,
+            0x05 => EventType::UnitCreated,
+            0x06 => EventType::UnitDestroyed,
+            0x07 => EventType::BuildingDestroyed,
+            0x08 => EventType::ResourceGather,
+            0x09 => EventType::UnitTrain,
+            0x0A => EventType::Research,
+            0x0B => EventType::Chat,
+            _ => EventType::Unknown,
+        };
+
+        Ok(Some(GameEvent {
+            event_type,
+            timestamp,
+            size,
+            data: buffer[offset..offset + size as usize].to_vec(),
+        }))
+    }
+
+    /// Read a fixed-length string from cursor
+    fn read_string(&self, cursor: &mut Cursor<&[u8]>, length: usize) -> Result<String> {
+        let mut buffer = vec![0u8; length];
+        cursor.read_exact(&mut buffer)?;
+
+        // Find null terminator
+        let null_pos = buffer.iter().position(|&b| b == 0).unwrap_or(length);
+        let string_bytes = &buffer[0..null_pos];
+
+        Ok(String::from_utf8_lossy(string_bytes).to_string())
+    }
+
+    /// Write a string into a fixed-length, null-padded field
+    fn write_string(&self, buffer: &mut Vec<u8>, value: &str, length: usize) {
+        let mut end = value.len().min(length);
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        buffer.extend_from_slice(&value.as_bytes()[..end]);
+        buffer.resize(buffer.len() + length - end, 0);
+    }
+
+    /// Offset of the first event record, just past the player table
+    fn events_offset(&self, data: &[u8]) -> usize {
+        let player_count_offset = W2R_HEADER_SIZE + W2R_METADATA_SIZE - 1;
+        match data.get(player_count_offset) {
+            Some(&player_count) => W2R_HEADER_SIZE + W2R_METADATA_SIZE + player_count as usize * W2R_PLAYER_SIZE,
+            None => data.len(),
+        }
+    }
+}
+
+impl ReplayFormat for W2RFormat {
+    fn name(&self) -> &'static str {
+        "w2r"
+    }
+
+    fn file_type(&self) -> FileType {
+        FileType::W2RReplay
+    }
+
+    fn sniff(&self, data: &[u8]) -> Confidence {
+        if data.len() >= 4 && &data[0..4] == W2R_MAGIC {
+            Confidence::Certain
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn sniff_filename(&self, filename: &str) -> Confidence {
+        // Remastered stores replays as 13-character "0xxxxxxxx.idx" files
+        if filename.ends_with(".idx") && filename.starts_with('0') && filename.len() == 13 {
+            Confidence::High
+        } else if filename.ends_with(".w2r") {
+            Confidence::Medium
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn decode_metadata(&self, data: &[u8], analysis: &FileAnalysis) -> Result<ReplayMetadata> {
+        let mut cursor = Cursor::new(data);
+
+        // Skip to metadata section (assume after header)
+        cursor.seek(SeekFrom::Start(W2R_HEADER_SIZE as u64))?;
+
+        // Read basic metadata
+        let game_version = self.read_string(&mut cursor, 16)?;
+        let map_name = self.read_string(&mut cursor, 32)?;
+        let game_type = cursor.read_u8()?;
+        let player_count = cursor.read_u8()?;
+
+        // Parse players
+        let mut players = Vec::new();
+        for _ in 0..player_count {
+            let name = self.read_string(&mut cursor, 16)?;
+            let race = cursor.read_u8()?;
+            let team = cursor.read_u8()?;
+            let color = cursor.read_u8()?;
+            let is_winner = cursor.read_u8()? != 0;
+
+            players.push(PlayerInfo {
+                name,
+                race: match race {
+                    0 => Race::Human,
+                    1 => Race::Orc,
+                    _ => Race::Unknown,
+                },
+                team,
+                color: match color {
+                    0 => PlayerColor::Red,
+                    1 => PlayerColor::Blue,
+                    2 => PlayerColor::Green,
+                    3 => PlayerColor::Yellow,
+                    4 => PlayerColor::Purple,
+                    5 => PlayerColor::Orange,
+                    6 => PlayerColor::White,
+                    7 => PlayerColor::Black,
+                    _ => PlayerColor::Red,
+                },
+                is_winner,
+                apm: 0.0, // Will be calculated later
+            });
+        }
+
+        let creation_date = analysis.header.as_ref()
+            .filter(|header| header.timestamp != 0)
+            .and_then(|header| Utc.timestamp_opt(header.timestamp as i64, 0).single())
+            .unwrap_or_else(Utc::now);
+
+        Ok(ReplayMetadata {
+            filename: analysis.filename.clone(),
+            file_size: analysis.file_size,
+            creation_date,
+            game_version,
+            map_name,
+            game_type: match game_type {
+                0 => GameType::Campaign,
+                1 => GameType::Skirmish,
+                2 => GameType::Multiplayer,
+                3 => GameType::Custom,
+                _ => GameType::Unknown,
+            },
+            players,
+            duration: std::time::Duration::from_secs(0), // Will be calculated from events
+            checksum: analysis.file_hash.clone(),
+        })
+    }
+
+    fn decode_events(&self, data: &[u8]) -> Result<Vec<GameEvent>> {
+        let mut events = Vec::new();
+
+        // Events follow the metadata block and player table that `decode_metadata`
+        // reads; starting right after the header would parse those as events
+        let mut offset = self.events_offset(data);
+
+        while offset < data.len() {
+            match self.parse_event_at_offset(data, offset) {
+                Ok(Some(event)) => {
+                    offset += event.size as usize;
+                    events.push(event);
+                }
+                // A short read at the tail just ends the stream
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        Ok(events)
+    }
+
+    fn can_encode(&self) -> bool {
+        true
+    }
+
+    fn encode(&self, replay: &DecodedReplay) -> Result<Vec<u8>> {
+        let metadata = &replay.metadata;
+        if metadata.players.len() > u8::MAX as usize {
+            anyhow::bail!("W2R replays hold at most {} players", u8::MAX);
+        }
+
+        // Header; the file size is patched in once the body is written
+        let mut buffer = Vec::with_capacity(W2R_HEADER_SIZE);
+        buffer.extend_from_slice(W2R_MAGIC);
+        buffer.write_u32::<LittleEndian>(W2R_VERSION)?;
+        buffer.write_u32::<LittleEndian>(0)?;
+        buffer.write_u64::<LittleEndian>(metadata.creation_date.timestamp().max(0) as u64)?;
+        buffer.write_u32::<LittleEndian>(0)?; // Flags
+        buffer.resize(W2R_HEADER_SIZE, 0);
+
+        // Metadata
+        self.write_string(&mut buffer, &metadata.game_version, 16);
+        self.write_string(&mut buffer, &metadata.map_name, 32);
+        buffer.write_u8(match metadata.game_type {
+            GameType::Campaign => 0,
+            GameType::Skirmish => 1,
+            GameType::Multiplayer => 2,
+            GameType::Custom => 3,
+            GameType::Unknown => 0xFF,
+        })?;
+        buffer.write_u8(metadata.players.len() as u8)?;
+
+        for player in &metadata.players {
+            self.write_string(&mut buffer, &player.name, 16);
+            buffer.write_u8(match player.race {
+                Race::Human => 0,
+                Race::Orc => 1,
+                Race::Unknown => 0xFF,
+            })?;
+            buffer.write_u8(player.team)?;
+            buffer.write_u8(match player.color {
+                PlayerColor::Red => 0,
+                PlayerColor::Blue => 1,
+                PlayerColor::Green => 2,
+                PlayerColor::Yellow => 3,
+                PlayerColor::Purple => 4,
+                PlayerColor::Orange => 5,
+                PlayerColor::White => 6,
+                PlayerColor::Black => 7,
+            })?;
+            buffer.write_u8(player.is_winner as u8)?;
+        }
+
+        // Events
+        for event in &replay.events {
+            let payload = event_payload(event);
+            let size = u16::try_from(W2R_EVENT_HEADER_SIZE + payload.len())
+                .map_err(|_| anyhow::anyhow!("Event at {}ms is too large for a W2R record", event.timestamp))?;
+
+            buffer.write_u8(event_type_code(&event.event_type))?;
+            buffer.write_u32::<LittleEndian>(event.timestamp)?;
+            buffer.write_u16::<LittleEndian>(size)?;
+            buffer.write_all(payload)?;
+        }
+
+        let file_size = u32::try_from(buffer.len())
+            .map_err(|_| anyhow::anyhow!("Replay is too large for a W2R file"))?;
+        buffer[8..12].copy_from_slice(&file_size.to_le_bytes());
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A W2R file with `players` player entries and one event record
+    fn w2r_file(players: u8) -> Vec<u8> {
+        let mut data = W2R_MAGIC.to_vec();
+        data.resize(W2R_HEADER_SIZE + W2R_METADATA_SIZE - 1, 0);
+        data.push(players);
+        for _ in 0..players {
+            let mut player = b"Grom".to_vec();
+            player.resize(W2R_PLAYER_SIZE, 1);
+            data.extend(player);
+        }
+        data.push(0x04);
+        data.extend(250u32.to_le_bytes());
+        data.extend(15u16.to_le_bytes());
+        data.extend([7, 0, 0, 0, 0, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_events_start_after_player_table() {
+        let format = W2RFormat::new();
+        for players in [0, 1, 8] {
+            let events = format.decode_events(&w2r_file(players)).unwrap();
+            assert_eq!(events.len(), 1, "{} players", players);
+            assert!(matches!(events[0].event_type, EventType::BuildingConstruct));
+            assert_eq!(events[0].timestamp, 250);
+            assert_eq!(&events[0].data[7..], &[7, 0, 0, 0, 0, 0, 0, 0]);
+        }
+    }
+}
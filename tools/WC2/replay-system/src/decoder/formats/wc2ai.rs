@@ -0,0 +1,554 @@
+//! Sessions recorded by the WC2AI `ReplaySystem`
+//!
+//! WC2AI saves its recordings as pretty-printed JSON (`<replay_id>.json`).
+//! This format maps them onto the unified event stream so bot games open in
+//! the same viewer as human games. AI decisions are not game events; they are
+//! exposed as a separate annotation track instead.
+
+use std::collections::HashMap;
+use anyhow::{Context, Result};
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
+
+use crate::structures::{FileAnalysis, FileType};
+use crate::decoder::{
+    ReplayMetadata, GameType, PlayerInfo, Race, PlayerColor, AnnotationTrack, Annotation,
+};
+use crate::decoder::events::{GameEvent, EventType, NO_GATHERER, NO_TARGET};
+use super::{Confidence, ReplayFormat, SNIFF_LENGTH};
+
+/// Name of the annotation track holding AI decisions
+pub const AI_DECISION_TRACK: &str = "AI Decisions";
+
+/// Decoder for WC2AI JSON recordings
+#[derive(Debug, Clone, Default)]
+pub struct WC2AIFormat;
+
+/// Subset of WC2AI's `ReplayData` needed for conversion
+#[derive(Debug, Deserialize)]
+struct RecordedSession {
+    metadata: RecordedMetadata,
+    #[serde(default)]
+    players: Vec<RecordedPlayer>,
+    #[serde(default)]
+    events: Vec<RecordedEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordedMetadata {
+    game_name: String,
+    map_name: String,
+    #[serde(default)]
+    game_duration: u64,
+    #[serde(default)]
+    created_at: u64,
+    #[serde(default)]
+    checksum: String,
+    #[serde(default)]
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordedPlayer {
+    id: u8,
+    name: String,
+    race: String,
+    color: String,
+}
+
+/// Mirror of WC2AI's `ReplayEvent`; timestamps are Unix milliseconds.
+/// Fields the viewer has no use for are left out and ignored by serde.
+#[derive(Debug, Deserialize)]
+enum RecordedEvent {
+    GameStart { timestamp: u64, map_name: String },
+    GameEnd { timestamp: u64, winner: u8 },
+    UnitCreated { timestamp: u64, unit_id: u32, unit_type: String, position: (i32, i32), owner: u8 },
+    UnitDestroyed { timestamp: u64, unit_id: u32, killer_id: Option<u32> },
+    UnitMoved { timestamp: u64, unit_id: u32, from: (i32, i32), to: (i32, i32) },
+    UnitAttacked { timestamp: u64, attacker_id: u32, target_id: u32, damage: u32 },
+    BuildingConstructed { timestamp: u64, building_id: u32, building_type: String, position: (i32, i32), owner: u8 },
+    BuildingDestroyed { timestamp: u64, building_id: u32, destroyer_id: Option<u32> },
+    ResourceGathered { timestamp: u64, player_id: u8, resource_type: String, amount: u32 },
+    UnitTrained { timestamp: u64, building_id: u32, unit_type: String },
+    ResearchCompleted { timestamp: u64, player_id: u8, upgrade_type: String },
+    AIAction { timestamp: u64, action: String, reasoning: String, success: bool },
+    StateChange { timestamp: u64 },
+    PlayerAction {
+        timestamp: u64,
+        player_id: u8,
+        action_type: String,
+        /// Tile the action was aimed at, if any
+        #[serde(default)]
+        target: Option<(i32, i32)>,
+    },
+}
+
+impl RecordedEvent {
+    fn timestamp(&self) -> u64 {
+        match self {
+            RecordedEvent::GameStart { timestamp, .. }
+            | RecordedEvent::GameEnd { timestamp, .. }
+            | RecordedEvent::UnitCreated { timestamp, .. }
+            | RecordedEvent::UnitDestroyed { timestamp, .. }
+            | RecordedEvent::UnitMoved { timestamp, .. }
+            | RecordedEvent::UnitAttacked { timestamp, .. }
+            | RecordedEvent::BuildingConstructed { timestamp, .. }
+            | RecordedEvent::BuildingDestroyed { timestamp, .. }
+            | RecordedEvent::ResourceGathered { timestamp, .. }
+            | RecordedEvent::UnitTrained { timestamp, .. }
+            | RecordedEvent::ResearchCompleted { timestamp, .. }
+            | RecordedEvent::AIAction { timestamp, .. }
+            | RecordedEvent::StateChange { timestamp, .. }
+            | RecordedEvent::PlayerAction { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+impl RecordedSession {
+    /// Wall-clock time of the first recorded event, used as game time zero
+    fn start_time(&self) -> u64 {
+        self.events
+            .iter()
+            .find_map(|event| match event {
+                RecordedEvent::GameStart { timestamp, .. } => Some(*timestamp),
+                _ => None,
+            })
+            .or_else(|| self.events.iter().map(RecordedEvent::timestamp).min())
+            .unwrap_or(0)
+    }
+
+    /// Convert a recorded wall-clock timestamp into replay game time
+    fn game_time(&self, start: u64, timestamp: u64) -> u32 {
+        timestamp.saturating_sub(start).min(u32::MAX as u64) as u32
+    }
+}
+
+/// Who owns which building, as far as the recording has got
+///
+/// WC2AI records training per building, while the unified stream carries the
+/// owner with the event, so conversion keeps track of building owners.
+#[derive(Debug, Default)]
+struct Ownership {
+    buildings: HashMap<u32, u8>,
+}
+
+impl Ownership {
+    fn observe(&mut self, event: &RecordedEvent) {
+        if let RecordedEvent::BuildingConstructed { building_id, owner, .. } = event {
+            self.buildings.insert(*building_id, *owner);
+        }
+    }
+}
+
+impl WC2AIFormat {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<RecordedSession> {
+        serde_json::from_slice(data).context("Invalid WC2AI recording")
+    }
+
+    /// Build a raw event; the payload uses the layout `GameState` decodes
+    fn event(event_type: EventType, timestamp: u32, data: Vec<u8>) -> GameEvent {
+        GameEvent {
+            event_type,
+            timestamp,
+            size: data.len() as u16,
+            data,
+        }
+    }
+
+    fn coord(value: i32) -> [u8; 2] {
+        (value.clamp(0, u16::MAX as i32) as u16).to_le_bytes()
+    }
+
+    /// Map a WC2AI event onto the unified event stream, if it has a counterpart
+    fn convert_event(event: &RecordedEvent, timestamp: u32, ownership: &Ownership) -> Option<GameEvent> {
+        match event {
+            RecordedEvent::UnitCreated { unit_id, unit_type, position, owner, .. } => {
+                let mut data = unit_id.to_le_bytes().to_vec();
+                data.push(unit_type_code(unit_type));
+                data.push(*owner);
+                data.extend_from_slice(&Self::coord(position.0));
+                data.extend_from_slice(&Self::coord(position.1));
+                Some(Self::event(EventType::UnitCreated, timestamp, data))
+            }
+            RecordedEvent::UnitDestroyed { unit_id, killer_id, .. } => {
+                let mut data = unit_id.to_le_bytes().to_vec();
+                data.extend_from_slice(&killer_id.unwrap_or(u32::MAX).to_le_bytes());
+                Some(Self::event(EventType::UnitDestroyed, timestamp, data))
+            }
+            RecordedEvent::UnitMoved { unit_id, from, to, .. } => {
+                let mut data = unit_id.to_le_bytes().to_vec();
+                data.extend_from_slice(&Self::coord(from.0));
+                data.extend_from_slice(&Self::coord(from.1));
+                data.extend_from_slice(&Self::coord(to.0));
+                data.extend_from_slice(&Self::coord(to.1));
+                Some(Self::event(EventType::UnitMove, timestamp, data))
+            }
+            RecordedEvent::UnitAttacked { attacker_id, target_id, damage, .. } => {
+                let mut data = attacker_id.to_le_bytes().to_vec();
+                data.extend_from_slice(&target_id.to_le_bytes());
+                data.extend_from_slice(&(*damage).min(u16::MAX as u32).to_le_bytes()[..2]);
+                data.extend_from_slice(&[0, 0]);
+                Some(Self::event(EventType::UnitAttack, timestamp, data))
+            }
+            RecordedEvent::BuildingConstructed { building_id, building_type, position, owner, .. } => {
+                // W2R fields, construction time unknown, then the id so later events find the building
+                let mut data = vec![building_type_code(building_type)];
+                data.extend_from_slice(&Self::coord(position.0));
+                data.extend_from_slice(&Self::coord(position.1));
+                data.push(*owner);
+                data.extend_from_slice(&0u32.to_le_bytes());
+                data.extend_from_slice(&building_id.to_le_bytes());
+                Some(Self::event(EventType::BuildingConstruct, timestamp, data))
+            }
+            RecordedEvent::BuildingDestroyed { building_id, destroyer_id, .. } => {
+                let mut data = building_id.to_le_bytes().to_vec();
+                data.extend_from_slice(&destroyer_id.unwrap_or(u32::MAX).to_le_bytes());
+                Some(Self::event(EventType::BuildingDestroyed, timestamp, data))
+            }
+            RecordedEvent::ResourceGathered { player_id, resource_type, amount, .. } => {
+                // WC2AI records gathering per player, not which unit gathered
+                let mut data = NO_GATHERER.to_le_bytes().to_vec();
+                data.push(match resource_type.to_lowercase().as_str() {
+                    "gold" => 0,
+                    "wood" | "lumber" => 1,
+                    "oil" => 2,
+                    _ => u8::MAX,
+                });
+                data.extend_from_slice(&(*amount).min(u16::MAX as u32).to_le_bytes()[..2]);
+                data.extend_from_slice(&[0; 4]);
+                data.push(*player_id);
+                Some(Self::event(EventType::ResourceGather, timestamp, data))
+            }
+            RecordedEvent::UnitTrained { building_id, unit_type, .. } => {
+                let mut data = building_id.to_le_bytes().to_vec();
+                data.push(unit_type_code(unit_type));
+                data.push(ownership.buildings.get(building_id).copied().unwrap_or(0));
+                data.extend_from_slice(&[0, 0]);
+                Some(Self::event(EventType::UnitTrain, timestamp, data))
+            }
+            RecordedEvent::ResearchCompleted { player_id, upgrade_type, .. } => {
+                let mut data = vec![*player_id];
+                data.extend_from_slice(upgrade_type.as_bytes());
+                Some(Self::event(EventType::Research, timestamp, data))
+            }
+            RecordedEvent::PlayerAction { player_id, action_type, target, .. } => {
+                let mut data = vec![*player_id, action_type_code(action_type)];
+                match target {
+                    Some((x, y)) => {
+                        data.extend_from_slice(&Self::coord(*x));
+                        data.extend_from_slice(&Self::coord(*y));
+                    }
+                    None => data.extend_from_slice(&[NO_TARGET.to_le_bytes(), NO_TARGET.to_le_bytes()].concat()),
+                }
+                data.extend_from_slice(&[0; 2]);
+                Some(Self::event(EventType::PlayerAction, timestamp, data))
+            }
+            RecordedEvent::GameStart { .. }
+            | RecordedEvent::GameEnd { .. }
+            | RecordedEvent::AIAction { .. }
+            | RecordedEvent::StateChange { .. } => None,
+        }
+    }
+}
+
+impl ReplayFormat for WC2AIFormat {
+    fn name(&self) -> &'static str {
+        "wc2ai"
+    }
+
+    fn file_type(&self) -> FileType {
+        FileType::GameData
+    }
+
+    fn sniff(&self, data: &[u8]) -> Confidence {
+        let head = &data[..data.len().min(SNIFF_LENGTH)];
+        let starts_like_json = head.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+        if !starts_like_json {
+            return Confidence::None;
+        }
+
+        let text = String::from_utf8_lossy(head);
+        if text.contains("\"replay_id\"") && text.contains("\"game_name\"") {
+            Confidence::High
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn sniff_filename(&self, filename: &str) -> Confidence {
+        if filename.ends_with(".json") {
+            Confidence::Low
+        } else {
+            Confidence::None
+        }
+    }
+
+    fn decode_metadata(&self, data: &[u8], analysis: &FileAnalysis) -> Result<ReplayMetadata> {
+        let session = self.parse(data)?;
+        let start = session.start_time();
+
+        let mut map_name = session.metadata.map_name.clone();
+        let mut winner = None;
+        let mut end = None;
+        for event in &session.events {
+            match event {
+                RecordedEvent::GameStart { map_name: name, .. } if !name.is_empty() => {
+                    map_name = name.clone();
+                }
+                RecordedEvent::GameEnd { timestamp, winner: id, .. } => {
+                    winner = Some(*id);
+                    end = Some(*timestamp);
+                }
+                _ => {}
+            }
+        }
+
+        let last = session.events.iter().map(RecordedEvent::timestamp).max().unwrap_or(start);
+        let duration_ms = if session.metadata.game_duration > 0 {
+            session.metadata.game_duration
+        } else {
+            end.unwrap_or(last).saturating_sub(start)
+        };
+
+        let players = session
+            .players
+            .iter()
+            .map(|player| PlayerInfo {
+                name: player.name.clone(),
+                race: match player.race.to_lowercase().as_str() {
+                    "human" => Race::Human,
+                    "orc" => Race::Orc,
+                    _ => Race::Unknown,
+                },
+                team: player.id,
+                color: match player.color.to_lowercase().as_str() {
+                    "red" => PlayerColor::Red,
+                    "blue" => PlayerColor::Blue,
+                    "green" => PlayerColor::Green,
+                    "yellow" => PlayerColor::Yellow,
+                    "purple" | "violet" => PlayerColor::Purple,
+                    "orange" => PlayerColor::Orange,
+                    "white" => PlayerColor::White,
+                    "black" => PlayerColor::Black,
+                    _ => PlayerColor::Red,
+                },
+                is_winner: winner == Some(player.id),
+                apm: 0.0,
+            })
+            .collect();
+
+        let version = if session.metadata.version.is_empty() {
+            session.metadata.game_name.clone()
+        } else {
+            format!("{} (WC2AI {})", session.metadata.game_name, session.metadata.version)
+        };
+
+        Ok(ReplayMetadata {
+            filename: analysis.filename.clone(),
+            file_size: analysis.file_size,
+            creation_date: Utc
+                .timestamp_millis_opt(session.metadata.created_at as i64)
+                .single()
+                .unwrap_or_else(Utc::now),
+            game_version: version,
+            map_name,
+            game_type: GameType::Custom,
+            players,
+            duration: std::time::Duration::from_millis(duration_ms),
+            checksum: if session.metadata.checksum.is_empty() {
+                analysis.file_hash.clone()
+            } else {
+                session.metadata.checksum.clone()
+            },
+        })
+    }
+
+    fn decode_events(&self, data: &[u8]) -> Result<Vec<GameEvent>> {
+        let session = self.parse(data)?;
+        let start = session.start_time();
+
+        let mut ownership = Ownership::default();
+        let mut events: Vec<GameEvent> = session
+            .events
+            .iter()
+            .filter_map(|event| {
+                ownership.observe(event);
+                let timestamp = session.game_time(start, event.timestamp());
+                Self::convert_event(event, timestamp, &ownership)
+            })
+            .collect();
+        events.sort_by_key(|event| event.timestamp);
+
+        Ok(events)
+    }
+
+    fn decode_tracks(&self, data: &[u8]) -> Result<Vec<AnnotationTrack>> {
+        let session = self.parse(data)?;
+        let start = session.start_time();
+
+        let annotations: Vec<Annotation> = session
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                RecordedEvent::AIAction { timestamp, action, reasoning, success } => Some(Annotation {
+                    timestamp: session.game_time(start, *timestamp),
+                    label: action.clone(),
+                    detail: reasoning.clone(),
+                    success: Some(*success),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        if annotations.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![AnnotationTrack {
+            name: AI_DECISION_TRACK.to_string(),
+            annotations,
+        }])
+    }
+}
+
+/// Unit code as understood by `GameState::parse_unit_type`
+fn unit_type_code(name: &str) -> u8 {
+    match name.to_lowercase().replace([' ', '_'], "").as_str() {
+        "peasant" => 0,
+        "footman" => 1,
+        "knight" => 2,
+        "archer" | "elvenarcher" => 3,
+        "paladin" => 4,
+        "mage" => 5,
+        "ballista" => 6,
+        "peon" => 7,
+        "grunt" => 8,
+        "ogre" => 9,
+        "troll" | "trollaxethrower" | "axethrower" => 10,
+        "catapult" => 11,
+        "deathknight" => 12,
+        "ogremage" => 13,
+        _ => u8::MAX,
+    }
+}
+
+/// Building code as understood by `GameState::parse_building_type`
+fn building_type_code(name: &str) -> u8 {
+    match name.to_lowercase().replace([' ', '_'], "").as_str() {
+        "townhall" => 0,
+        "barracks" | "humanbarracks" => 1,
+        "farm" | "humanfarm" => 2,
+        "lumbermill" | "elvenlumbermill" => 3,
+        "blacksmith" | "humanblacksmith" => 4,
+        "church" => 5,
+        "tower" | "scouttower" | "guardtower" | "cannontower" => 6,
+        "castle" => 7,
+        "greathall" => 8,
+        "orcbarracks" | "barracksorc" => 9,
+        "pigfarm" | "orcfarm" | "farmorc" => 10,
+        "warmill" | "trollumbermill" => 11,
+        "temple" | "altarofstorms" => 12,
+        "watchtower" | "orctower" | "towerorc" => 13,
+        "fortress" => 14,
+        _ => u8::MAX,
+    }
+}
+
+/// Action code for `PlayerAction` payloads, mirroring `events::ActionType`
+fn action_type_code(name: &str) -> u8 {
+    match name.to_lowercase().as_str() {
+        "move" => 0,
+        "attack" => 1,
+        "build" => 2,
+        "train" => 3,
+        "research" => 4,
+        "select" => 5,
+        "deselect" => 6,
+        _ => u8::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::game_state::GameState;
+
+    const RECORDING: &str = r#"{
+        "replay_id": "bot-game",
+        "metadata": { "game_name": "WC2AI", "map_name": "open_field" },
+        "players": [{ "id": 1, "name": "Bot", "race": "Human", "color": "Red" }],
+        "events": [
+            { "GameStart": { "timestamp": 1000, "map_name": "open_field" } },
+            { "UnitCreated": { "timestamp": 1000, "unit_id": 7, "unit_type": "Peasant", "position": [4, 5], "owner": 1 } },
+            { "BuildingConstructed": { "timestamp": 2000, "building_id": 42, "building_type": "Barracks", "position": [10, 12], "owner": 1 } },
+            { "ResourceGathered": { "timestamp": 3000, "player_id": 1, "resource_type": "Gold", "amount": 100 } },
+            { "UnitTrained": { "timestamp": 4000, "building_id": 42, "unit_type": "Footman" } },
+            { "BuildingDestroyed": { "timestamp": 5000, "building_id": 42, "destroyer_id": null } }
+        ]
+    }"#;
+
+    #[test]
+    fn test_ids_survive_into_game_state() {
+        let events = WC2AIFormat::new().decode_events(RECORDING.as_bytes()).unwrap();
+        assert_eq!(events.len(), 5);
+
+        let mut state = GameState::new();
+        for event in &events[..4] {
+            state.apply_event(event);
+        }
+        let barracks = &state.buildings[&42];
+        assert_eq!((barracks.player_id, barracks.x, barracks.y), (1, 10.0, 12.0));
+        assert_eq!(barracks.training_queue.len(), 1);
+        assert_eq!(state.resources[&1].gold, 100);
+
+        state.apply_event(&events[4]);
+        assert!(state.buildings.is_empty());
+    }
+
+    #[test]
+    fn test_gathering_without_units_goes_to_the_player() {
+        let recording = r#"{
+            "replay_id": "bot-game",
+            "metadata": { "game_name": "WC2AI", "map_name": "open_field" },
+            "players": [{ "id": 1, "name": "Bot", "race": "Human", "color": "Red" }],
+            "events": [
+                { "GameStart": { "timestamp": 1000, "map_name": "open_field" } },
+                { "ResourceGathered": { "timestamp": 2000, "player_id": 2, "resource_type": "Wood", "amount": 50 } },
+                { "UnitCreated": { "timestamp": 3000, "unit_id": 7, "unit_type": "Peasant", "position": [4, 5], "owner": 1 } },
+                { "ResourceGathered": { "timestamp": 4000, "player_id": 2, "resource_type": "Wood", "amount": 25 } }
+            ]
+        }"#;
+        let events = WC2AIFormat::new().decode_events(recording.as_bytes()).unwrap();
+        let gathered: Vec<&GameEvent> = events.iter().filter(|event| matches!(event.event_type, EventType::ResourceGather)).collect();
+        assert_eq!(gathered.len(), 2);
+        // No unit is named as the gatherer, even once player 1 has one
+        assert!(gathered.iter().all(|event| event.data[..4] == NO_GATHERER.to_le_bytes() && event.data[11] == 2));
+
+        let mut state = GameState::new();
+        for event in &events {
+            state.apply_event(event);
+        }
+        assert_eq!(state.resources[&2].wood, 75);
+        assert!(!state.resources.contains_key(&1));
+    }
+
+    #[test]
+    fn test_player_actions_keep_their_target() {
+        let recording = r#"{
+            "replay_id": "bot-game",
+            "metadata": { "game_name": "WC2AI", "map_name": "open_field" },
+            "players": [{ "id": 1, "name": "Bot", "race": "Human", "color": "Red" }],
+            "events": [
+                { "PlayerAction": { "timestamp": 1000, "player_id": 1, "action_type": "Move", "target": [20, 30] } },
+                { "PlayerAction": { "timestamp": 2000, "player_id": 1, "action_type": "Train" } }
+            ]
+        }"#;
+        let events = WC2AIFormat::new().decode_events(recording.as_bytes()).unwrap();
+        assert_eq!(events[0].data, [1, 0, 20, 0, 30, 0, 0, 0]);
+        assert_eq!(events[1].data[2..6], [NO_TARGET.to_le_bytes(), NO_TARGET.to_le_bytes()].concat());
+    }
+}
@@ -113,6 +113,15 @@ impl GameState {
             super::events::EventType::UnitAttack => {
                 self.handle_unit_attack(event);
             }
+            super::events::EventType::UnitCreated => {
+                self.handle_unit_created(event);
+            }
+            super::events::EventType::UnitDestroyed => {
+                self.handle_unit_destroyed(event);
+            }
+            super::events::EventType::BuildingDestroyed => {
+                self.handle_building_destroyed(event);
+            }
             super::events::EventType::BuildingConstruct => {
                 self.handle_building_construct(event);
             }
@@ -198,9 +207,69 @@ impl GameState {
         }
     }
 
+    /// Handle unit creation event
+    fn handle_unit_created(&mut self, event: &super::events::GameEvent) {
+        if event.data.len() >= 10 {
+            let unit_id = u32::from_le_bytes([
+                event.data[0], event.data[1], event.data[2], event.data[3]
+            ]);
+            let unit_type = event.data[4];
+            let player_id = event.data[5];
+            let x = u16::from_le_bytes([event.data[6], event.data[7]]);
+            let y = u16::from_le_bytes([event.data[8], event.data[9]]);
+
+            let unit_type = self.parse_unit_type(unit_type);
+            let (max_health, max_mana) = unit_type
+                .rules()
+                .map(|rules| (rules.hp as u16, rules.mana as u16))
+                .unwrap_or((100, 0));
+
+            let unit = UnitState {
+                id: unit_id,
+                unit_type,
+                player_id,
+                x: x as f32,
+                y: y as f32,
+                health: max_health, // Starting health
+                max_health,
+                mana: 0,
+                max_mana,
+                level: 1,
+                experience: 0,
+                is_moving: false,
+                target_x: None,
+                target_y: None,
+                is_attacking: false,
+                target_id: None,
+            };
+
+            self.units.insert(unit_id, unit);
+        }
+    }
+
+    /// Handle unit destruction event
+    fn handle_unit_destroyed(&mut self, event: &super::events::GameEvent) {
+        if event.data.len() >= 4 {
+            let unit_id = u32::from_le_bytes([
+                event.data[0], event.data[1], event.data[2], event.data[3]
+            ]);
+            self.units.remove(&unit_id);
+        }
+    }
+
+    /// Handle building destruction event
+    fn handle_building_destroyed(&mut self, event: &super::events::GameEvent) {
+        if event.data.len() >= 4 {
+            let building_id = u32::from_le_bytes([
+                event.data[0], event.data[1], event.data[2], event.data[3]
+            ]);
+            self.buildings.remove(&building_id);
+        }
+    }
+
     /// Handle building construction event
     fn handle_building_construct(&mut self, event: &super::events::GameEvent) {
-        if event.data.len() >= 8 {
+        if event.data.len() >= 10 {
             let building_type = event.data[0];
             let x = u16::from_le_bytes([event.data[1], event.data[2]]);
             let y = u16::from_le_bytes([event.data[3], event.data[4]]);
@@ -209,15 +278,21 @@ impl GameState {
                 event.data[6], event.data[7], event.data[8], event.data[9]
             ]);
             
-            let building_id = self.generate_building_id();
+            let building_type = self.parse_building_type(building_type);
+            let max_health = building_type.rules().map(|rules| rules.hp as u16).unwrap_or(100);
+
+            // Recordings that know the building's id append it after the W2R fields
+            let building_id = event.data.get(10..14)
+                .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]))
+                .unwrap_or_else(|| self.generate_building_id());
             let building = BuildingState {
                 id: building_id,
-                building_type: self.parse_building_type(building_type),
+                building_type,
                 player_id,
                 x: x as f32,
                 y: y as f32,
-                health: 100, // Starting health
-                max_health: 100,
+                health: max_health, // Starting health
+                max_health,
                 construction_progress: 0.0,
                 is_constructing: true,
                 training_queue: Vec::new(),
@@ -253,15 +328,28 @@ impl GameState {
             let resource_type = event.data[4];
             let amount = u16::from_le_bytes([event.data[5], event.data[6]]);
             
-            // Find which player owns this unit
-            if let Some(unit) = self.units.get(&unit_id) {
-                if let Some(resources) = self.resources.get_mut(&unit.player_id) {
-                    match resource_type {
-                        0 => resources.gold += amount as u32,
-                        1 => resources.wood += amount as u32,
-                        2 => resources.oil += amount as u32,
-                        _ => {}
-                    }
+            // Credit the gatherer's owner, or the player of a gathering without a gatherer
+            let player_id = match self.units.get(&unit_id) {
+                Some(unit) => Some(unit.player_id),
+                None if unit_id == super::events::NO_GATHERER => event.data.get(11).copied(),
+                None => None,
+            };
+            if let Some(player_id) = player_id {
+                let resources = self.resources.entry(player_id).or_insert_with(|| ResourceState {
+                    player_id,
+                    gold: 0,
+                    wood: 0,
+                    oil: 0,
+                    food_used: 0,
+                    food_capacity: 0,
+                    population: 0,
+                    max_population: 0,
+                });
+                match resource_type {
+                    0 => resources.gold += amount as u32,
+                    1 => resources.wood += amount as u32,
+                    2 => resources.oil += amount as u32,
+                    _ => {}
                 }
             }
         }
@@ -343,3 +431,23 @@ impl GameState {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::events::{EventType, GameEvent};
+
+    #[test]
+    fn test_short_building_payloads_are_ignored() {
+        let mut state = GameState::new();
+        for len in 0..10 {
+            let data = vec![1u8; len];
+            state.apply_event(&GameEvent { event_type: EventType::BuildingConstruct, timestamp: 0, size: len as u16, data });
+        }
+        assert!(state.buildings.is_empty());
+
+        let data = vec![1, 10, 0, 12, 0, 2, 0, 0, 0, 0];
+        state.apply_event(&GameEvent { event_type: EventType::BuildingConstruct, timestamp: 0, size: 10, data });
+        assert_eq!(state.buildings.len(), 1);
+    }
+}
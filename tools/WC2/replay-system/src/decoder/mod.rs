@@ -6,51 +6,154 @@
 pub mod parser;
 pub mod events;
 pub mod game_state;
+pub mod formats;
+pub mod anonymizer;
 
+use std::fs;
+use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
 use anyhow::Result;
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
+use tracing::warn;
 
 use crate::structures::FileAnalysis;
+use formats::{FormatRegistry, ReplayFormat, SNIFF_LENGTH};
 
 /// Main replay decoder that handles file parsing and event extraction
 #[derive(Clone)]
 pub struct ReplayDecoder {
     parser: parser::ReplayParser,
     game_state: game_state::GameState,
+    formats: FormatRegistry,
 }
 
 impl ReplayDecoder {
     pub fn new() -> Self {
+        Self::with_registry(FormatRegistry::with_builtin_formats())
+    }
+
+    /// Create a decoder that consults a custom format registry
+    pub fn with_registry(formats: FormatRegistry) -> Self {
         Self {
             parser: parser::ReplayParser::new(),
             game_state: game_state::GameState::new(),
+            formats,
         }
     }
 
+    /// Register an additional replay format
+    pub fn register_format(&mut self, format: Arc<dyn ReplayFormat>) {
+        self.formats.register(format);
+    }
+
+    /// Get the format registry
+    pub fn formats(&self) -> &FormatRegistry {
+        &self.formats
+    }
+
     /// Decode a replay file and extract all game events
     pub fn decode_replay(&self, file_path: &Path) -> Result<DecodedReplay> {
-        let analysis = self.parser.analyze_file(file_path)?;
-        let events = self.parser.parse_events(&analysis)?;
-        let metadata = self.parser.extract_metadata(&analysis)?;
-        
+        let data = fs::read(file_path)?;
+        self.decode_bytes(file_path, &data)
+    }
+
+    /// Decode replay contents that are already in memory
+    pub fn decode_bytes(&self, file_path: &Path, data: &[u8]) -> Result<DecodedReplay> {
+        let mut analysis = self.parser.analyze_bytes(file_path, data.len() as u64, data);
+
+        let (metadata, events, tracks) = match self.formats.detect(&analysis.filename, data) {
+            Some(format) => {
+                analysis.format = Some(format.name().to_string());
+                analysis.file_type = format.file_type();
+                (
+                    format.decode_metadata(data, &analysis)?,
+                    format.decode_events(data)?,
+                    format.decode_tracks(data)?,
+                )
+            }
+            None => {
+                warn!("No registered replay format recognises {}", analysis.filename);
+                (self.parser.basic_metadata(&analysis), Vec::new(), Vec::new())
+            }
+        };
+
         Ok(DecodedReplay {
             metadata,
             events,
             game_state: self.game_state.clone(),
             analysis,
+            tracks,
         })
     }
 
     /// Get replay metadata without full decoding
     pub fn get_metadata(&self, file_path: &Path) -> Result<ReplayMetadata> {
-        self.parser.extract_metadata_from_file(file_path)
+        let data = fs::read(file_path)?;
+        self.metadata_from_bytes(file_path, &data)
+    }
+
+    /// Decode only the metadata of replay contents that are already in memory
+    fn metadata_from_bytes(&self, file_path: &Path, data: &[u8]) -> Result<ReplayMetadata> {
+        let mut analysis = self.parser.analyze_bytes(file_path, data.len() as u64, data);
+
+        match self.formats.detect(&analysis.filename, data) {
+            Some(format) => {
+                analysis.format = Some(format.name().to_string());
+                format.decode_metadata(data, &analysis)
+            }
+            None => Ok(self.parser.basic_metadata(&analysis)),
+        }
     }
 
     /// List all available replays in a directory
+    ///
+    /// Only the head of each file is read to detect its format; files no
+    /// format recognises are skipped without reading the rest.
     pub fn list_replays(&self, directory: &Path) -> Result<Vec<ReplayInfo>> {
-        self.parser.scan_replay_directory(directory)
+        let mut replays = Vec::new();
+
+        if !directory.exists() {
+            return Ok(replays);
+        }
+
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let filename = path.file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+
+            let mut head = Vec::with_capacity(SNIFF_LENGTH);
+            let read_head = fs::File::open(&path)
+                .and_then(|file| file.take(SNIFF_LENGTH as u64).read_to_end(&mut head));
+            if read_head.is_err() || self.formats.detect(filename, &head).is_none() {
+                continue;
+            }
+
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+
+            if let Ok(metadata) = self.metadata_from_bytes(&path, &data) {
+                replays.push(ReplayInfo {
+                    filename: metadata.filename,
+                    file_size: metadata.file_size,
+                    creation_date: metadata.creation_date,
+                    map_name: metadata.map_name,
+                    game_type: metadata.game_type,
+                    player_count: metadata.players.len() as u8,
+                    duration: metadata.duration,
+                });
+            }
+        }
+
+        Ok(replays)
     }
 }
 
@@ -61,6 +164,25 @@ pub struct DecodedReplay {
     pub events: Vec<events::GameEvent>,
     pub game_state: game_state::GameState,
     pub analysis: FileAnalysis,
+    /// Extra timelines shown alongside the game events (e.g. AI decisions)
+    #[serde(default)]
+    pub tracks: Vec<AnnotationTrack>,
+}
+
+/// A named timeline of annotations rendered next to the event stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationTrack {
+    pub name: String,
+    pub annotations: Vec<Annotation>,
+}
+
+/// Single annotation on a track
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub timestamp: u32, // Game time in milliseconds
+    pub label: String,
+    pub detail: String,
+    pub success: Option<bool>,
 }
 
 /// Replay metadata information
@@ -130,3 +252,22 @@ pub enum PlayerColor {
     White,
     Black,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::replay;
+
+    #[test]
+    fn test_list_replays_skips_unknown_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let w2r = formats::w2r::W2RFormat::new().encode(&replay(2, Vec::new())).unwrap();
+        fs::write(dir.path().join("game.w2r"), w2r).unwrap();
+        fs::write(dir.path().join("notes.txt"), vec![b'x'; 4 * SNIFF_LENGTH]).unwrap();
+
+        let replays = ReplayDecoder::new().list_replays(dir.path()).unwrap();
+        assert_eq!(replays.len(), 1);
+        assert_eq!(replays[0].filename, "game.w2r");
+        assert_eq!((replays[0].map_name.as_str(), replays[0].player_count), ("Garden of War", 2));
+    }
+}
@@ -3,9 +3,25 @@
 //! This module handles loading, caching, and managing all graphics assets
 //! needed for rendering replays with Remastered-quality visuals.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::decoder::PlayerColor;
+use super::sprite_sheets::{
+    self, AtlasPacker, Direction, SheetMetadata, SpriteSheet, ATLAS_PAGE_SIZE, WC2_SHEET_DIRECTIONS,
+};
+
+/// Environment variable pointing at the wartest extractor output directory
+pub const ASSET_DIR_ENV: &str = "WC2_ASSET_DIR";
+
+/// Default output directory of the wartest extractor
+pub const DEFAULT_ASSET_DIR: &str = "output";
+
+/// Side of the texture standing in for assets the extractor did not write
+const PLACEHOLDER_SIZE: u32 = 32;
 
 /// Asset types that can be loaded
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -23,6 +39,39 @@ pub struct AssetManager {
     asset_cache: HashMap<String, Asset>,
     asset_paths: HashMap<AssetType, PathBuf>,
     loaded_assets: HashMap<String, bool>,
+    sprite_sheets: HashMap<String, LoadedSheet>,
+    atlas_pages: usize,
+}
+
+/// Bookkeeping for a sprite sheet packed into the atlas
+struct LoadedSheet {
+    directions: u8,
+    animations: Vec<String>,
+}
+
+/// Animation frame resolved for drawing
+#[derive(Debug, Clone)]
+pub struct ResolvedFrame {
+    pub texture_id: String,
+    pub region: SpriteRegion,
+    pub pivot: (f32, f32),
+    pub flip_x: bool,
+}
+
+/// Unit entry in the extractor's `mongodb/wc2_units.json`
+#[derive(Debug, Deserialize)]
+struct ExtractedUnit {
+    name: String,
+    category: String,
+    #[serde(default)]
+    sprite_data: Option<ExtractedSpriteData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedSpriteData {
+    width: u16,
+    height: u16,
+    frame_count: u16,
 }
 
 /// Generic asset representation
@@ -45,34 +94,35 @@ pub enum AssetData {
 
 /// Texture asset data
 pub struct TextureAsset {
-    width: u32,
-    height: u32,
-    format: TextureFormat,
-    data: Vec<u8>,
-    mipmaps: Vec<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub data: Vec<u8>,
+    pub mipmaps: Vec<Vec<u8>>,
 }
 
 /// Sprite asset data
 pub struct SpriteAsset {
-    texture_id: String,
-    region: SpriteRegion,
-    pivot: (f32, f32),
-    animations: Vec<String>, // Changed from Vec<Animation> to Vec<String> to avoid circular reference
+    pub texture_id: String,
+    pub region: SpriteRegion,
+    pub pivot: (f32, f32),
+    pub animations: Vec<String>, // Changed from Vec<Animation> to Vec<String> to avoid circular reference
 }
 
 /// Animation asset data
 pub struct AnimationAsset {
-    frames: Vec<AnimationFrame>,
-    frame_rate: f32,
-    loop_type: LoopType,
+    pub frames: Vec<AnimationFrame>,
+    pub frame_rate: f32,
+    pub loop_type: LoopType,
 }
 
 /// Sound asset data
 pub struct SoundAsset {
-    sample_rate: u32,
-    channels: u8,
-    data: Vec<u8>,
-    duration: f32,
+    pub sample_rate: u32,
+    pub channels: u8,
+    /// Interleaved 16-bit little-endian PCM samples
+    pub data: Vec<u8>,
+    pub duration: f32,
 }
 
 /// Music asset data
@@ -103,18 +153,20 @@ pub enum TextureFormat {
 }
 
 /// Sprite region within texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct SpriteRegion {
-    x: u32,
-    y: u32,
-    width: u32,
-    height: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
 }
 
 /// Animation frame data
 pub struct AnimationFrame {
-    sprite_id: String,
-    duration: f32,
-    offset: (f32, f32),
+    pub sprite_id: String,
+    pub duration: f32,
+    pub offset: (f32, f32),
+    pub flip_x: bool, // Mirrored facing (west-side directions)
 }
 
 /// Loop type for animations
@@ -147,34 +199,268 @@ pub struct AssetMetadata {
 }
 
 impl AssetManager {
-    /// Create a new asset manager
+    /// Create a new asset manager rooted at `$WC2_ASSET_DIR` (or `output`)
     pub fn new() -> Result<Self> {
+        let root = std::env::var_os(ASSET_DIR_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_ASSET_DIR));
+        Self::with_root(&root)
+    }
+
+    /// Create an asset manager reading from a wartest extractor output directory
+    pub fn with_root(root: &Path) -> Result<Self> {
         let asset_cache = HashMap::new();
-        let asset_paths = Self::initialize_asset_paths()?;
+        let asset_paths = Self::initialize_asset_paths(root)?;
         let loaded_assets = HashMap::new();
         
         Ok(Self {
             asset_cache,
             asset_paths,
             loaded_assets,
+            sprite_sheets: HashMap::new(),
+            atlas_pages: 0,
         })
     }
     
     /// Initialize asset paths for different asset types
-    fn initialize_asset_paths() -> Result<HashMap<AssetType, PathBuf>> {
+    fn initialize_asset_paths(root: &Path) -> Result<HashMap<AssetType, PathBuf>> {
         let mut paths = HashMap::new();
         
-        // Set paths for WC2 Remastered assets
-        // These would typically point to the game installation directory
-        paths.insert(AssetType::Texture, PathBuf::from("../../Warcraft II Remastered/Data/Art"));
-        paths.insert(AssetType::Sprite, PathBuf::from("../../Warcraft II Remastered/Data/Art"));
-        paths.insert(AssetType::Animation, PathBuf::from("../../Warcraft II Remastered/Data/Art"));
-        paths.insert(AssetType::Sound, PathBuf::from("../../Warcraft II Remastered/Data/Speech"));
-        paths.insert(AssetType::Music, PathBuf::from("../../Warcraft II Remastered/Data/Music"));
-        paths.insert(AssetType::Font, PathBuf::from("../../Warcraft II Remastered/Data/Font"));
+        // Layout written by the wartest extractor
+        let images = root.join("images");
+        paths.insert(AssetType::Texture, images.clone());
+        paths.insert(AssetType::Sprite, images.clone());
+        paths.insert(AssetType::Animation, images);
+        paths.insert(AssetType::Sound, root.join("sounds"));
+        paths.insert(AssetType::Music, root.join("music"));
+        paths.insert(AssetType::Font, root.join("fonts"));
         
         Ok(paths)
     }
+
+    /// Load every sprite sheet below the image directory into texture atlases.
+    ///
+    /// Returns the number of sheets loaded. Sheets that fail to decode are
+    /// skipped with a warning so one bad file does not block the viewer.
+    pub fn load_sprite_sheets(&mut self) -> Result<usize> {
+        let images = self.asset_paths.get(&AssetType::Sprite)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No path configured for sprites"))?;
+        if !images.exists() {
+            return Ok(0);
+        }
+
+        let fallback = Self::read_extracted_units(&images);
+        let mut sheets = Vec::new();
+        for entry in WalkDir::new(&images)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().map_or(false, |ext| ext == "png")) {
+
+            let stem = entry.path().file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            match SpriteSheet::load(entry.path(), fallback.get(&stem)) {
+                Ok(sheet) => sheets.push(sheet),
+                Err(e) => tracing::warn!("Skipping sprite sheet {}: {}", entry.path().display(), e),
+            }
+        }
+
+        let count = sheets.len();
+        self.pack_sprite_sheets(sheets)?;
+        Ok(count)
+    }
+
+    /// Pack cut sprite sheets into atlas pages and register their assets
+    pub fn pack_sprite_sheets(&mut self, sheets: Vec<SpriteSheet>) -> Result<()> {
+        let mut packer = AtlasPacker::new(ATLAS_PAGE_SIZE, 1);
+        let first_page = self.atlas_pages;
+
+        for sheet in sheets {
+            let mut sprite_ids = Vec::with_capacity(sheet.frames.len());
+            for (index, frame) in sheet.frames.iter().enumerate() {
+                let (page, region) = packer.insert(frame)?;
+                let sprite_id = format!("sprite/{}/{}", sheet.name, index);
+                self.insert_asset(&sprite_id, AssetType::Sprite, AssetData::Sprite(SpriteAsset {
+                    texture_id: format!("atlas/{}", first_page + page),
+                    region,
+                    pivot: sheet.pivot,
+                    animations: Vec::new(),
+                }));
+                sprite_ids.push(sprite_id);
+            }
+
+            let mut animations = Vec::new();
+            for (action, spec) in &sheet.animations {
+                for direction in Direction::ALL {
+                    let frames: Vec<AnimationFrame> = spec.steps.iter()
+                        .filter_map(|&step| sheet.frame_index(step, direction))
+                        .map(|(index, flip_x)| AnimationFrame {
+                            sprite_id: sprite_ids[index].clone(),
+                            duration: 1.0 / spec.frame_rate.max(0.1),
+                            offset: (0.0, 0.0),
+                            flip_x,
+                        })
+                        .collect();
+                    if frames.is_empty() {
+                        continue;
+                    }
+
+                    let animation_id = Self::animation_id(&sheet.name, action, direction);
+                    self.insert_asset(&animation_id, AssetType::Animation, AssetData::Animation(AnimationAsset {
+                        frames,
+                        frame_rate: spec.frame_rate,
+                        loop_type: if spec.looping { LoopType::Loop } else { LoopType::None },
+                    }));
+                }
+                animations.push(action.clone());
+            }
+
+            self.sprite_sheets.insert(sheet.name.clone(), LoadedSheet {
+                directions: sheet.directions,
+                animations,
+            });
+        }
+
+        let pages = packer.into_pages();
+        self.atlas_pages += pages.len();
+        for (offset, page) in pages.into_iter().enumerate() {
+            let texture_id = format!("atlas/{}", first_page + offset);
+            let (width, height) = page.pixels.dimensions();
+            self.insert_asset(&texture_id, AssetType::Texture, AssetData::Texture(TextureAsset {
+                width,
+                height,
+                format: TextureFormat::RGBA8,
+                data: page.pixels.into_raw(),
+                mipmaps: Vec::new(),
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the frame of a sheet animation to draw at a point in time
+    pub fn animation_frame(&self, sheet: &str, action: &str, direction: Direction, elapsed: f32) -> Option<ResolvedFrame> {
+        let animation = match &self.asset_cache.get(&Self::animation_id(sheet, action, direction))?.data {
+            AssetData::Animation(animation) => animation,
+            _ => return None,
+        };
+
+        let tick = (elapsed.max(0.0) * animation.frame_rate) as usize;
+        let count = animation.frames.len();
+        let index = match animation.loop_type {
+            LoopType::None => tick.min(count - 1),
+            LoopType::Loop => tick % count,
+            LoopType::PingPong if count > 1 => {
+                let period = 2 * count - 2;
+                let phase = tick % period;
+                if phase < count { phase } else { period - phase }
+            }
+            LoopType::PingPong => 0,
+        };
+
+        let frame = &animation.frames[index];
+        match &self.asset_cache.get(&frame.sprite_id)?.data {
+            AssetData::Sprite(sprite) => Some(ResolvedFrame {
+                texture_id: sprite.texture_id.clone(),
+                region: sprite.region,
+                pivot: sprite.pivot,
+                flip_x: frame.flip_x,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Names of the animations available for a loaded sheet
+    pub fn sheet_animations(&self, sheet: &str) -> Option<&[String]> {
+        self.sprite_sheets.get(sheet).map(|loaded| loaded.animations.as_slice())
+    }
+
+    /// Number of facings stored in a loaded sheet
+    pub fn sheet_directions(&self, sheet: &str) -> Option<u8> {
+        self.sprite_sheets.get(sheet).map(|loaded| loaded.directions)
+    }
+
+    /// Get (creating on first use) an atlas page recolored for a player.
+    ///
+    /// Returns the texture ID of the remapped copy.
+    pub fn player_texture(&mut self, texture_id: &str, color: &PlayerColor) -> Result<String> {
+        if matches!(color, PlayerColor::Red) {
+            return Ok(texture_id.to_string());
+        }
+
+        let remapped_id = format!("{}@{:?}", texture_id, color).to_lowercase();
+        if self.asset_cache.contains_key(&remapped_id) {
+            return Ok(remapped_id);
+        }
+
+        let (width, height, data) = match self.asset_cache.get(texture_id).map(|asset| &asset.data) {
+            Some(AssetData::Texture(texture)) if matches!(texture.format, TextureFormat::RGBA8) => {
+                (texture.width, texture.height, texture.data.clone())
+            }
+            _ => anyhow::bail!("No RGBA texture loaded for {}", texture_id),
+        };
+
+        let mut pixels = image::RgbaImage::from_raw(width, height, data)
+            .ok_or_else(|| anyhow::anyhow!("Texture {} has invalid dimensions", texture_id))?;
+        sprite_sheets::remap_player_color(&mut pixels, color);
+
+        self.insert_asset(&remapped_id, AssetType::Texture, AssetData::Texture(TextureAsset {
+            width,
+            height,
+            format: TextureFormat::RGBA8,
+            data: pixels.into_raw(),
+            mipmaps: Vec::new(),
+        }));
+        Ok(remapped_id)
+    }
+
+    fn animation_id(sheet: &str, action: &str, direction: Direction) -> String {
+        format!("anim/{}/{}/{:?}", sheet, action, direction).to_lowercase()
+    }
+
+    /// Cache an asset generated in memory
+    fn insert_asset(&mut self, asset_id: &str, asset_type: AssetType, data: AssetData) {
+        let file_path = self.asset_paths.get(&asset_type).cloned().unwrap_or_default();
+        self.asset_cache.insert(asset_id.to_string(), Asset {
+            id: asset_id.to_string(),
+            asset_type,
+            data,
+            metadata: AssetMetadata {
+                file_path,
+                file_size: 0,
+                creation_date: chrono::Utc::now(),
+                checksum: String::new(),
+            },
+        });
+        self.loaded_assets.insert(asset_id.to_string(), true);
+    }
+
+    /// Frame layouts from the extractor's unit database, keyed by sheet name
+    fn read_extracted_units(images: &Path) -> HashMap<String, SheetMetadata> {
+        let database = images.parent()
+            .map(|root| root.join("mongodb").join("wc2_units.json"))
+            .unwrap_or_default();
+        let units: Vec<ExtractedUnit> = std::fs::read_to_string(&database)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+
+        units.into_iter()
+            .filter_map(|unit| {
+                let sprite = unit.sprite_data?;
+                Some((unit.name.to_lowercase(), SheetMetadata {
+                    frame_width: Some(sprite.width as u32),
+                    frame_height: Some(sprite.height as u32),
+                    frame_count: Some(sprite.frame_count as u32),
+                    directions: Some(if unit.category == "unit" { WC2_SHEET_DIRECTIONS } else { 1 }),
+                    ..SheetMetadata::default()
+                }))
+            })
+            .collect()
+    }
     
     /// Load an asset by ID
     pub fn load_asset(&mut self, asset_id: &str) -> Result<&Asset> {
@@ -267,80 +553,66 @@ impl AssetManager {
     
     /// Load texture asset
     fn load_texture_asset(&self, asset_path: &PathBuf, asset_id: &str) -> Result<TextureAsset> {
-        // This is a placeholder implementation
-        // In a real implementation, this would load actual texture files
+        let file_path = asset_path.join(format!("{}.png", asset_id));
+        let image = image::open(&file_path)
+            .with_context(|| format!("Failed to decode texture: {}", file_path.display()))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
         
         Ok(TextureAsset {
-            width: 64,
-            height: 64,
+            width,
+            height,
             format: TextureFormat::RGBA8,
-            data: vec![0; 64 * 64 * 4], // Placeholder data
+            data: image.into_raw(),
             mipmaps: Vec::new(),
         })
     }
     
     /// Load sprite asset
     fn load_sprite_asset(&self, asset_path: &PathBuf, asset_id: &str) -> Result<SpriteAsset> {
-        // This is a placeholder implementation
+        // Individual sprites live in the atlas built by `load_sprite_sheets`
+        let file_path = asset_path.join(format!("{}.png", asset_id));
+        let (width, height) = image::image_dimensions(&file_path)
+            .with_context(|| format!("Failed to read sprite: {}", file_path.display()))?;
         
         Ok(SpriteAsset {
             texture_id: asset_id.to_string(),
-            region: SpriteRegion { x: 0, y: 0, width: 64, height: 64 },
+            region: SpriteRegion { x: 0, y: 0, width, height },
             pivot: (0.5, 0.5),
             animations: Vec::new(),
         })
     }
     
     /// Load animation asset
+    ///
+    /// Per-direction animations are cut from their sheet by `load_sprite_sheets`,
+    /// so one that is not cached by now has no frames on disk.
     fn load_animation_asset(&self, asset_path: &PathBuf, asset_id: &str) -> Result<AnimationAsset> {
-        // This is a placeholder implementation
-        
-        Ok(AnimationAsset {
-            frames: Vec::new(),
-            frame_rate: 24.0,
-            loop_type: LoopType::Loop,
-        })
+        anyhow::bail!("Animation {} is not in any sprite sheet under {}", asset_id, asset_path.display())
     }
     
     /// Load sound asset
     fn load_sound_asset(&self, asset_path: &PathBuf, asset_id: &str) -> Result<SoundAsset> {
-        // This is a placeholder implementation
+        let file_path = asset_path.join(format!("{}.wav", asset_id));
+        let bytes = std::fs::read(&file_path)
+            .with_context(|| format!("Failed to read sound: {}", file_path.display()))?;
         
-        Ok(SoundAsset {
-            sample_rate: 44100,
-            channels: 2,
-            data: Vec::new(),
-            duration: 0.0,
-        })
+        super::audio::decode_wav(&bytes)
+            .with_context(|| format!("Failed to decode sound: {}", file_path.display()))
     }
     
     /// Load music asset
+    ///
+    /// The extractor does not export music yet, so there is nothing to decode.
     fn load_music_asset(&self, asset_path: &PathBuf, asset_id: &str) -> Result<MusicAsset> {
-        // This is a placeholder implementation
-        
-        Ok(MusicAsset {
-            sample_rate: 44100,
-            channels: 2,
-            data: Vec::new(),
-            duration: 0.0,
-            loop_point: None,
-        })
+        anyhow::bail!("Music {} is not supported: the extractor writes no music to {}", asset_id, asset_path.display())
     }
     
     /// Load font asset
+    ///
+    /// The extractor does not export fonts yet; the UI draws text with egui's own.
     fn load_font_asset(&self, asset_path: &PathBuf, asset_id: &str) -> Result<FontAsset> {
-        // This is a placeholder implementation
-        
-        Ok(FontAsset {
-            family: "Arial".to_string(),
-            size: 12,
-            glyphs: HashMap::new(),
-            metrics: FontMetrics {
-                ascent: 10.0,
-                descent: 2.0,
-                line_height: 12.0,
-            },
-        })
+        anyhow::bail!("Font {} is not supported: the extractor writes no fonts to {}", asset_id, asset_path.display())
     }
     
     /// Check if an asset is loaded
@@ -353,6 +625,14 @@ impl AssetManager {
         self.asset_cache.get(asset_id)
     }
     
+    /// Load a sound effect, decoding it from disk on first use
+    pub fn load_sound(&mut self, asset_id: &str) -> Result<&SoundAsset> {
+        match &self.load_asset(asset_id)?.data {
+            AssetData::Sound(sound) => Ok(sound),
+            _ => Err(anyhow::anyhow!("Asset {} is not a sound", asset_id)),
+        }
+    }
+    
     /// Unload an asset to free memory
     pub fn unload_asset(&mut self, asset_id: &str) -> Result<()> {
         if self.asset_cache.remove(asset_id).is_some() {
@@ -373,6 +653,9 @@ impl AssetManager {
     }
     
     /// Preload common assets
+    ///
+    /// Assets the extractor did not write are replaced by a placeholder texture
+    /// with a warning, so a partial extraction still renders.
     pub fn preload_common_assets(&mut self) -> Result<()> {
         // Load commonly used assets like UI elements, basic units, etc.
         let common_assets = vec![
@@ -385,12 +668,30 @@ impl AssetManager {
         ];
         
         for asset_id in common_assets {
-            self.load_asset(asset_id)?;
+            if let Err(e) = self.load_asset(asset_id) {
+                tracing::warn!("Using a placeholder for {}: {:#}", asset_id, e);
+                self.insert_asset(asset_id, AssetType::Texture, AssetData::Texture(Self::placeholder_texture()));
+            }
         }
         
         Ok(())
     }
     
+    /// Magenta and black checkerboard standing in for a missing texture
+    fn placeholder_texture() -> TextureAsset {
+        let image = image::RgbaImage::from_fn(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, |x, y| {
+            if (x / 4 + y / 4) % 2 == 0 { image::Rgba([255, 0, 255, 255]) } else { image::Rgba([0, 0, 0, 255]) }
+        });
+        
+        TextureAsset {
+            width: PLACEHOLDER_SIZE,
+            height: PLACEHOLDER_SIZE,
+            format: TextureFormat::RGBA8,
+            data: image.into_raw(),
+            mipmaps: Vec::new(),
+        }
+    }
+    
     /// Get memory usage statistics
     pub fn get_memory_usage(&self) -> MemoryUsage {
         let mut total_size = 0;
@@ -428,3 +729,37 @@ pub struct MemoryUsage {
     pub cache_hits: u64,
     pub cache_misses: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preload_falls_back_to_placeholders() {
+        let root = tempfile::tempdir().unwrap();
+        let images = root.path().join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        image::RgbaImage::from_pixel(16, 8, image::Rgba([1, 2, 3, 255])).save(images.join("ui_button.png")).unwrap();
+
+        let mut assets = AssetManager::with_root(root.path()).unwrap();
+        assets.preload_common_assets().unwrap();
+
+        let size = |asset_id: &str| match &assets.get_asset(asset_id).unwrap().data {
+            AssetData::Texture(texture) => (texture.width, texture.height),
+            _ => panic!("{} is not a texture", asset_id),
+        };
+        assert_eq!(size("ui_button"), (16, 8));
+        assert_eq!(size("unit_peasant"), (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE));
+        assert!(assets.is_asset_loaded("building_barracks"));
+    }
+
+    #[test]
+    fn test_unextracted_assets_fail_to_load() {
+        let root = tempfile::tempdir().unwrap();
+        let mut assets = AssetManager::with_root(root.path()).unwrap();
+        for asset_id in ["anim/footman/walk/north", "music_human1", "font_menu", "ui_panel"] {
+            assert!(assets.load_asset(asset_id).is_err(), "{}", asset_id);
+            assert!(!assets.is_asset_loaded(asset_id));
+        }
+    }
+}
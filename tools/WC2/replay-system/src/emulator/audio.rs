@@ -0,0 +1,634 @@
+//! Offline audio track rendering for replays
+//!
+//! Walks a decoded replay's event stream and mixes unit acknowledgement,
+//! combat and building-complete sounds at their timestamps into a WAV file.
+//! Sounds are attenuated and panned by their distance from a camera path.
+//! Nothing here touches an audio device; the output is a plain file that can
+//! be muxed with exported frames.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use anyhow::{Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::decoder::DecodedReplay;
+use crate::decoder::events::{EventType, GameEvent, NO_TARGET};
+use crate::decoder::formats::w2r;
+use crate::decoder::game_state::GameState;
+use super::assets::{AssetManager, SoundAsset};
+
+/// Kind of sound placed on the audio track
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundCue {
+    UnitAcknowledge,
+    Combat,
+    BuildingComplete,
+}
+
+impl SoundCue {
+    /// Asset ID of the sound played for this cue (`<sounds>/<id>.wav`)
+    pub fn default_asset_id(self) -> &'static str {
+        match self {
+            SoundCue::UnitAcknowledge => "sound_acknowledge",
+            SoundCue::Combat => "sound_combat",
+            SoundCue::BuildingComplete => "sound_building_complete",
+        }
+    }
+}
+
+/// A cue placed on the timeline at a map position
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedCue {
+    pub cue: SoundCue,
+    pub timestamp: u32, // Game time in milliseconds
+    pub position: (f32, f32), // Map tiles
+}
+
+/// Camera position over time, in map tiles
+#[derive(Debug, Clone, Default)]
+pub struct CameraPath {
+    keyframes: Vec<(u32, (f32, f32))>,
+}
+
+impl CameraPath {
+    /// Camera that never moves
+    pub fn fixed(position: (f32, f32)) -> Self {
+        Self { keyframes: vec![(0, position)] }
+    }
+
+    /// Add a keyframe; keyframes may be added in any order
+    pub fn add_keyframe(&mut self, timestamp: u32, position: (f32, f32)) {
+        let index = self.keyframes.partition_point(|(time, _)| *time <= timestamp);
+        self.keyframes.insert(index, (timestamp, position));
+    }
+
+    /// Linearly interpolated camera position at a game time
+    pub fn position_at(&self, timestamp: u32) -> (f32, f32) {
+        let index = self.keyframes.partition_point(|(time, _)| *time <= timestamp);
+        match (index.checked_sub(1).map(|i| self.keyframes[i]), self.keyframes.get(index)) {
+            (Some((t0, p0)), Some(&(t1, p1))) => {
+                let t = (timestamp - t0) as f32 / (t1 - t0).max(1) as f32;
+                (p0.0 + (p1.0 - p0.0) * t, p0.1 + (p1.1 - p0.1) * t)
+            }
+            (Some((_, p)), None) | (None, Some(&(_, p))) => p,
+            (None, None) => (0.0, 0.0),
+        }
+    }
+}
+
+/// Mixer settings
+#[derive(Debug, Clone)]
+pub struct AudioMixerConfig {
+    pub sample_rate: u32,
+    pub master_volume: f32,
+    /// Distance in tiles within which sounds play at full volume
+    pub reference_distance: f32,
+    /// Distance in tiles beyond which sounds are silent
+    pub max_distance: f32,
+    /// Silence appended after the last cue, in milliseconds
+    pub tail_ms: u32,
+    /// Asset IDs to use per cue instead of the defaults
+    pub cue_sounds: HashMap<SoundCue, String>,
+}
+
+impl Default for AudioMixerConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            master_volume: 0.8,
+            reference_distance: 8.0,
+            max_distance: 48.0,
+            tail_ms: 2000,
+            cue_sounds: HashMap::new(),
+        }
+    }
+}
+
+/// Frames mixed at a time while a track streams to its writer
+const MIX_BLOCK_FRAMES: usize = 4096;
+
+/// Length of a rendered stereo track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderedTrack {
+    pub sample_rate: u32,
+    /// Stereo frames written
+    pub frames: u64,
+}
+
+impl RenderedTrack {
+    /// Duration of the track in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames as f32 / self.sample_rate as f32
+    }
+}
+
+/// A cue's sound placed on the track with its stereo gains
+struct Voice {
+    cue: SoundCue,
+    start: u64,
+    left: f32,
+    right: f32,
+}
+
+/// Offline mixer turning replay events into an audio track
+pub struct ReplayAudioMixer {
+    config: AudioMixerConfig,
+}
+
+impl ReplayAudioMixer {
+    pub fn new(config: AudioMixerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Place sound cues for every relevant event in the replay
+    pub fn collect_cues(&self, replay: &DecodedReplay) -> Vec<PlacedCue> {
+        // Replay from an empty state so unit positions match each event's time
+        let mut state = GameState::new();
+        let mut cues = Vec::new();
+
+        for event in &replay.events {
+            // W2R-decoded events keep their record header in `data`
+            let data = w2r::event_payload(event);
+            match event.event_type {
+                EventType::PlayerAction if data.len() >= 6 => {
+                    let x = u16::from_le_bytes([data[2], data[3]]);
+                    let y = u16::from_le_bytes([data[4], data[5]]);
+                    // Actions without a target have nowhere to be heard from
+                    if x != NO_TARGET && y != NO_TARGET {
+                        cues.push(PlacedCue {
+                            cue: SoundCue::UnitAcknowledge,
+                            timestamp: event.timestamp,
+                            position: (x as f32, y as f32),
+                        });
+                    }
+                }
+                EventType::UnitAttack if data.len() >= 8 => {
+                    let attacker_id = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+                    let target_id = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+                    let position = state.units.get(&target_id)
+                        .or_else(|| state.units.get(&attacker_id))
+                        .map(|unit| (unit.x, unit.y));
+                    if let Some(position) = position {
+                        cues.push(PlacedCue {
+                            cue: SoundCue::Combat,
+                            timestamp: event.timestamp,
+                            position,
+                        });
+                    }
+                }
+                EventType::BuildingConstruct if data.len() >= 10 => {
+                    let x = u16::from_le_bytes([data[1], data[2]]);
+                    let y = u16::from_le_bytes([data[3], data[4]]);
+                    let construction_time = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
+                    cues.push(PlacedCue {
+                        cue: SoundCue::BuildingComplete,
+                        timestamp: event.timestamp.saturating_add(construction_time),
+                        position: (x as f32, y as f32),
+                    });
+                }
+                _ => {}
+            }
+            state.apply_event(&GameEvent { data: data.to_vec(), size: data.len() as u16, ..event.clone() });
+        }
+
+        cues.sort_by_key(|cue| cue.timestamp);
+        cues
+    }
+
+    /// Mix the replay's audio track along a camera path into a 16-bit stereo WAV file
+    pub fn render_to_file(&self, replay: &DecodedReplay, camera: &CameraPath, asset_manager: &mut AssetManager, output_path: &Path) -> Result<RenderedTrack> {
+        let file = std::fs::File::create(output_path)
+            .with_context(|| format!("Failed to create {}", output_path.display()))?;
+        self.render(replay, camera, asset_manager, std::io::BufWriter::new(file))
+    }
+
+    /// Mix the replay's audio track along a camera path, streaming it to `writer` as a 16-bit stereo WAV
+    ///
+    /// The track is mixed a block at a time, so memory use follows the cue
+    /// sounds rather than the length of the replay.
+    pub fn render<W: Write>(&self, replay: &DecodedReplay, camera: &CameraPath, asset_manager: &mut AssetManager, mut writer: W) -> Result<RenderedTrack> {
+        let rate = self.config.sample_rate;
+        if rate == 0 {
+            anyhow::bail!("Audio mixer sample rate must be positive");
+        }
+        let cues = self.collect_cues(replay);
+
+        // Resolve each cue's sound once, resampled to the mixer rate
+        let mut sounds: HashMap<SoundCue, Vec<f32>> = HashMap::new();
+        for cue in [SoundCue::UnitAcknowledge, SoundCue::Combat, SoundCue::BuildingComplete] {
+            let asset_id = self.config.cue_sounds.get(&cue)
+                .map(String::as_str)
+                .unwrap_or(cue.default_asset_id());
+            let samples = match asset_manager.load_sound(asset_id).and_then(|sound| to_mono(sound, rate)) {
+                Ok(samples) => samples,
+                Err(e) => {
+                    tracing::warn!("Sound {} unavailable ({}), using a synthesized placeholder", asset_id, e);
+                    synthesize_cue(cue, rate)
+                }
+            };
+            sounds.insert(cue, samples);
+        }
+
+        let last_end = cues.iter()
+            .map(|cue| cue.timestamp as u64 * rate as u64 / 1000 + sounds[&cue.cue].len() as u64)
+            .max()
+            .unwrap_or(0);
+        let replay_end = replay.metadata.duration.as_millis() as u64 * rate as u64 / 1000;
+        let frames = last_end.max(replay_end) + self.config.tail_ms as u64 * rate as u64 / 1000;
+
+        // Cues are sorted by time, so voices are sorted by their first frame
+        let mut voices = Vec::new();
+        for cue in &cues {
+            let camera_position = camera.position_at(cue.timestamp);
+            let dx = cue.position.0 - camera_position.0;
+            let dy = cue.position.1 - camera_position.1;
+            let gain = self.distance_gain((dx * dx + dy * dy).sqrt()) * self.config.master_volume;
+            if gain <= 0.0 {
+                continue;
+            }
+
+            // Equal-power pan from the horizontal offset
+            let pan = (dx / self.config.max_distance).clamp(-1.0, 1.0);
+            let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+            voices.push(Voice {
+                cue: cue.cue,
+                start: cue.timestamp as u64 * rate as u64 / 1000,
+                left: angle.cos() * gain,
+                right: angle.sin() * gain,
+            });
+        }
+        let voice_end = |voice: &Voice| voice.start + sounds[&voice.cue].len() as u64;
+
+        write_wav_header(&mut writer, rate, 2, frames * 2)?;
+        let mut block = vec![0.0f32; MIX_BLOCK_FRAMES * 2];
+        let mut active: Vec<&Voice> = Vec::new();
+        let mut pending = voices.iter().peekable();
+        for block_start in (0..frames).step_by(MIX_BLOCK_FRAMES) {
+            let block_end = (block_start + MIX_BLOCK_FRAMES as u64).min(frames);
+            let mixed = &mut block[..(block_end - block_start) as usize * 2];
+            mixed.fill(0.0);
+
+            while let Some(voice) = pending.next_if(|voice| voice.start < block_end) {
+                active.push(voice);
+            }
+            for voice in &active {
+                let sound = &sounds[&voice.cue];
+                for frame in block_start.max(voice.start)..block_end.min(voice_end(voice)) {
+                    let sample = sound[(frame - voice.start) as usize];
+                    let index = (frame - block_start) as usize * 2;
+                    mixed[index] += sample * voice.left;
+                    mixed[index + 1] += sample * voice.right;
+                }
+            }
+            active.retain(|voice| voice_end(voice) > block_end);
+
+            // Soft-limit overlapping cues instead of hard clipping
+            for sample in mixed.iter() {
+                writer.write_i16::<LittleEndian>(to_pcm(sample.tanh()))?;
+            }
+        }
+        writer.flush()?;
+
+        Ok(RenderedTrack { sample_rate: rate, frames })
+    }
+
+    /// Gain for a sound at a distance (in tiles) from the camera
+    fn distance_gain(&self, distance: f32) -> f32 {
+        let reference = self.config.reference_distance;
+        let max = self.config.max_distance.max(reference + f32::EPSILON);
+        if distance <= reference {
+            1.0
+        } else if distance >= max {
+            0.0
+        } else {
+            let t = (distance - reference) / (max - reference);
+            (1.0 - t) * (1.0 - t)
+        }
+    }
+}
+
+/// Downmix a 16-bit PCM sound to mono and resample it linearly
+fn to_mono(sound: &SoundAsset, target_rate: u32) -> Result<Vec<f32>> {
+    let channels = sound.channels.max(1) as usize;
+    let mono: Vec<f32> = sound.data
+        .chunks_exact(2 * channels)
+        .map(|frame| {
+            frame.chunks_exact(2)
+                .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32)
+                .sum::<f32>() / channels as f32
+        })
+        .collect();
+
+    if sound.sample_rate == 0 || target_rate == 0 {
+        anyhow::bail!("Cannot resample from {} Hz to {} Hz", sound.sample_rate, target_rate);
+    }
+    if sound.sample_rate == target_rate || mono.is_empty() {
+        return Ok(mono);
+    }
+
+    let ratio = sound.sample_rate as f64 / target_rate as f64;
+    let length = (mono.len() as f64 / ratio) as usize;
+    Ok((0..length)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let a = mono[index.min(mono.len() - 1)];
+            let b = mono[(index + 1).min(mono.len() - 1)];
+            a + (b - a) * fraction
+        })
+        .collect())
+}
+
+/// Short tone used when a cue's sound file is missing
+fn synthesize_cue(cue: SoundCue, rate: u32) -> Vec<f32> {
+    let (frequency, duration) = match cue {
+        SoundCue::UnitAcknowledge => (660.0, 0.12),
+        SoundCue::Combat => (180.0, 0.08),
+        SoundCue::BuildingComplete => (440.0, 0.4),
+    };
+    let length = (rate as f32 * duration) as usize;
+    (0..length)
+        .map(|i| {
+            let t = i as f32 / rate as f32;
+            let envelope = 1.0 - i as f32 / length as f32;
+            (t * frequency * std::f32::consts::TAU).sin() * envelope * 0.5
+        })
+        .collect()
+}
+
+/// Decode a PCM WAV file (8- or 16-bit) into a sound asset with 16-bit samples
+pub fn decode_wav(bytes: &[u8]) -> Result<SoundAsset> {
+    let mut cursor = Cursor::new(bytes);
+    let mut tag = [0u8; 4];
+
+    cursor.read_exact(&mut tag)?;
+    if &tag != b"RIFF" {
+        anyhow::bail!("Not a RIFF file");
+    }
+    cursor.read_u32::<LittleEndian>()?;
+    cursor.read_exact(&mut tag)?;
+    if &tag != b"WAVE" {
+        anyhow::bail!("Not a WAVE file");
+    }
+
+    let mut format = None;
+    let mut data = None;
+    while format.is_none() || data.is_none() {
+        if cursor.read_exact(&mut tag).is_err() {
+            break;
+        }
+        let size = cursor.read_u32::<LittleEndian>()? as usize;
+        let start = cursor.position() as usize;
+        let end = (start + size).min(bytes.len());
+
+        match &tag {
+            b"fmt " => {
+                let audio_format = cursor.read_u16::<LittleEndian>()?;
+                let channels = cursor.read_u16::<LittleEndian>()?;
+                let sample_rate = cursor.read_u32::<LittleEndian>()?;
+                cursor.read_u32::<LittleEndian>()?; // Byte rate
+                cursor.read_u16::<LittleEndian>()?; // Block align
+                let bits = cursor.read_u16::<LittleEndian>()?;
+                if audio_format != 1 {
+                    anyhow::bail!("Unsupported WAV encoding {}", audio_format);
+                }
+                format = Some((channels, sample_rate, bits));
+            }
+            b"data" => data = Some(&bytes[start..end]),
+            _ => {}
+        }
+
+        // Chunks are word aligned
+        cursor.set_position((start + size + size % 2) as u64);
+    }
+
+    let (channels, sample_rate, bits) = format.ok_or_else(|| anyhow::anyhow!("WAV has no fmt chunk"))?;
+    if sample_rate == 0 {
+        anyhow::bail!("WAV has a sample rate of 0");
+    }
+    let raw = data.ok_or_else(|| anyhow::anyhow!("WAV has no data chunk"))?;
+    let pcm: Vec<u8> = match bits {
+        16 => raw[..raw.len() - raw.len() % 2].to_vec(),
+        8 => raw.iter()
+            .flat_map(|&sample| (((sample as i16) - 128) << 8).to_le_bytes())
+            .collect(),
+        _ => anyhow::bail!("Unsupported WAV bit depth {}", bits),
+    };
+
+    let frames = pcm.len() / 2 / channels.max(1) as usize;
+    Ok(SoundAsset {
+        sample_rate,
+        channels: channels as u8,
+        duration: frames as f32 / sample_rate as f32,
+        data: pcm,
+    })
+}
+
+/// Convert a sample in -1.0..=1.0 to 16-bit PCM
+fn to_pcm(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// Write the header of a 16-bit PCM WAV stream holding `samples` interleaved samples
+pub fn write_wav_header<W: Write>(writer: &mut W, sample_rate: u32, channels: u16, samples: u64) -> Result<()> {
+    let data_size = u32::try_from(samples * 2)
+        .ok()
+        .filter(|size| *size <= u32::MAX - 36)
+        .ok_or_else(|| anyhow::anyhow!("{} samples do not fit in a WAV file", samples))?;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_u32::<LittleEndian>(36 + data_size)?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_u32::<LittleEndian>(16)?;
+    writer.write_u16::<LittleEndian>(1)?; // PCM
+    writer.write_u16::<LittleEndian>(channels)?;
+    writer.write_u32::<LittleEndian>(sample_rate)?;
+    writer.write_u32::<LittleEndian>(sample_rate * channels as u32 * 2)?;
+    writer.write_u16::<LittleEndian>(channels * 2)?;
+    writer.write_u16::<LittleEndian>(16)?;
+
+    writer.write_all(b"data")?;
+    writer.write_u32::<LittleEndian>(data_size)?;
+    Ok(())
+}
+
+/// Write interleaved 16-bit PCM samples as a WAV stream
+pub fn write_wav<W: Write>(mut writer: W, sample_rate: u32, channels: u16, samples: &[i16]) -> Result<()> {
+    write_wav_header(&mut writer, sample_rate, channels, samples.len() as u64)?;
+    for sample in samples {
+        writer.write_i16::<LittleEndian>(*sample)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::formats::ReplayFormat;
+    use crate::decoder::formats::w2r::W2RFormat;
+    use crate::test_fixtures::replay;
+
+    const RATE: u32 = 1000;
+
+    fn order(timestamp: u32, x: u16, y: u16) -> GameEvent {
+        let mut data = vec![0, 0];
+        data.extend(x.to_le_bytes());
+        data.extend(y.to_le_bytes());
+        GameEvent { event_type: EventType::PlayerAction, timestamp, size: data.len() as u16, data }
+    }
+
+    /// Assets whose acknowledge sound is 10 ms of constant half-scale signal
+    fn assets(root: &Path) -> AssetManager {
+        let sounds = root.join("sounds");
+        std::fs::create_dir_all(&sounds).unwrap();
+        let file = std::fs::File::create(sounds.join("sound_acknowledge.wav")).unwrap();
+        write_wav(file, RATE, 1, &[i16::MAX / 2; 10]).unwrap();
+        AssetManager::with_root(root).unwrap()
+    }
+
+    fn mixer() -> ReplayAudioMixer {
+        ReplayAudioMixer::new(AudioMixerConfig { sample_rate: RATE, master_volume: 1.0, tail_ms: 100, ..Default::default() })
+    }
+
+    /// Render to memory and return the left and right channel
+    fn render(replay: &DecodedReplay, camera: &CameraPath) -> (RenderedTrack, Vec<i16>, Vec<i16>) {
+        let root = tempfile::tempdir().unwrap();
+        let mut bytes = Vec::new();
+        let track = mixer().render(replay, camera, &mut assets(root.path()), &mut bytes).unwrap();
+        let pcm: Vec<i16> = decode_wav(&bytes).unwrap().data
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect();
+        let left = pcm.iter().step_by(2).copied().collect();
+        let right = pcm.iter().skip(1).step_by(2).copied().collect();
+        (track, left, right)
+    }
+
+    #[test]
+    fn test_cues_are_placed_at_event_times() {
+        let mut construct = vec![0, 12, 0, 14, 0, 0];
+        construct.extend(500u32.to_le_bytes());
+        let events = vec![
+            GameEvent { event_type: EventType::BuildingConstruct, timestamp: 2000, size: 10, data: construct },
+            order(1000, 10, 10),
+            order(1500, 10, 10),
+        ];
+        let replay = replay(2, events);
+
+        let cues = mixer().collect_cues(&replay);
+        let placed: Vec<(SoundCue, u32)> = cues.iter().map(|cue| (cue.cue, cue.timestamp)).collect();
+        assert_eq!(placed, vec![
+            (SoundCue::UnitAcknowledge, 1000),
+            (SoundCue::UnitAcknowledge, 1500),
+            (SoundCue::BuildingComplete, 2500),
+        ]);
+        assert_eq!(cues[2].position, (12.0, 14.0));
+
+        // One frame per millisecond: each acknowledgement sounds for 10 frames from its event
+        let (_, left, _) = render(&replay, &CameraPath::fixed((10.0, 10.0)));
+        let sounding: Vec<usize> = left.iter().enumerate()
+            .filter(|(_, sample)| **sample != 0)
+            .map(|(frame, _)| frame)
+            .take_while(|frame| *frame < 2500)
+            .collect();
+        let expected: Vec<usize> = (1000..1010).chain(1500..1510).collect();
+        assert_eq!(sounding, expected);
+    }
+
+    #[test]
+    fn test_cues_from_decoded_w2r_events() {
+        let mut construct = vec![0, 12, 0, 14, 0, 0];
+        construct.extend(500u32.to_le_bytes());
+        let mut created = vec![5, 0, 0, 0, 3, 0];
+        created.extend(20u16.to_le_bytes());
+        created.extend(22u16.to_le_bytes());
+        let mut attack = vec![9, 0, 0, 0, 5, 0, 0, 0];
+        attack.extend(4u16.to_le_bytes());
+        let events = vec![
+            order(1000, 10, 11),
+            GameEvent { event_type: EventType::BuildingConstruct, timestamp: 2000, size: 10, data: construct },
+            GameEvent { event_type: EventType::UnitCreated, timestamp: 2500, size: 10, data: created },
+            GameEvent { event_type: EventType::UnitAttack, timestamp: 3000, size: 10, data: attack },
+        ];
+
+        // Decoded events carry their record header in front of the payload
+        let format = W2RFormat::new();
+        let decoded = format.decode_events(&format.encode(&replay(2, events)).unwrap()).unwrap();
+        assert!(decoded.iter().all(|event| event.data.len() > w2r::event_payload(event).len()));
+
+        let cues = mixer().collect_cues(&replay(2, decoded));
+        let placed: Vec<(SoundCue, u32, (f32, f32))> = cues.iter().map(|cue| (cue.cue, cue.timestamp, cue.position)).collect();
+        assert_eq!(placed, vec![
+            (SoundCue::UnitAcknowledge, 1000, (10.0, 11.0)),
+            (SoundCue::BuildingComplete, 2500, (12.0, 14.0)),
+            (SoundCue::Combat, 3000, (20.0, 22.0)),
+        ]);
+    }
+
+    #[test]
+    fn test_zero_sample_rate_is_rejected() {
+        let mut bytes = Vec::new();
+        write_wav(&mut bytes, 0, 1, &[0; 4]).unwrap();
+        assert!(decode_wav(&bytes).is_err());
+
+        let sound = SoundAsset { sample_rate: 0, channels: 1, duration: 0.0, data: vec![0; 8] };
+        assert!(to_mono(&sound, RATE).is_err());
+
+        let root = tempfile::tempdir().unwrap();
+        let mixer = ReplayAudioMixer::new(AudioMixerConfig { sample_rate: 0, ..Default::default() });
+        assert!(mixer.render(&replay(2, vec![order(0, 1, 1)]), &CameraPath::default(), &mut assets(root.path()), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_gain_follows_the_camera_path() {
+        // The camera pans east from the sounds, 25 tiles away at 1 s and 100 at 4 s
+        let mut camera = CameraPath::fixed((10.0, 10.0));
+        camera.add_keyframe(4000, (110.0, 10.0));
+        assert_eq!(camera.position_at(1000), (35.0, 10.0));
+
+        let replay = replay(2, vec![order(0, 10, 10), order(1000, 10, 10), order(4000, 10, 10)]);
+        let (_, left, right) = render(&replay, &camera);
+        let level = |frame: usize| (left[frame] as i32).abs() + (right[frame] as i32).abs();
+
+        assert!(level(0) > level(1000));
+        assert!(level(1000) > 0);
+        assert_eq!(level(4000), 0);
+        // Sounds west of the camera lean left
+        assert_eq!(left[0], right[0]);
+        assert!(left[1000] > right[1000]);
+    }
+
+    #[test]
+    fn test_wav_header_and_sample_count() {
+        let mut replay = replay(2, vec![order(9000, 10, 10)]);
+        replay.metadata.duration = std::time::Duration::from_secs(5);
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("track.wav");
+        let track = mixer().render_to_file(&replay, &CameraPath::fixed((10.0, 10.0)), &mut assets(root.path()), &path).unwrap();
+
+        // The last cue ends after the replay does, then the tail follows
+        assert_eq!(track, RenderedTrack { sample_rate: RATE, frames: 9010 + 100 });
+        assert!((track.duration() - 9.11).abs() < 1e-4);
+
+        let bytes = std::fs::read(&path).unwrap();
+        let field = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let short = |at: usize| u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap());
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(field(4) as usize, bytes.len() - 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!((short(20), short(22), field(24), short(34)), (1, 2, RATE, 16));
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(field(40) as u64, track.frames * 4);
+        assert_eq!(bytes.len() as u64, 44 + track.frames * 4);
+
+        let decoded = decode_wav(&bytes).unwrap();
+        assert_eq!((decoded.channels, decoded.sample_rate), (2, RATE));
+        assert_eq!(decoded.data.len() as u64, track.frames * 4);
+    }
+}
@@ -7,6 +7,8 @@ pub mod renderer;
 pub mod game_engine;
 pub mod playback;
 pub mod assets;
+pub mod sprite_sheets;
+pub mod audio;
 
 use crate::decoder::{DecodedReplay, ReplayInfo};
 use anyhow::Result;
@@ -44,7 +46,10 @@ impl WC2ReplayEmulator {
         let game_engine = game_engine::GameEngine::new()?;
         let renderer = renderer::GraphicsRenderer::new()?;
         let playback_controller = playback::PlaybackController::new();
-        let asset_manager = assets::AssetManager::new()?;
+        let mut asset_manager = assets::AssetManager::new()?;
+        if let Err(e) = asset_manager.load_sprite_sheets() {
+            tracing::warn!("Failed to load sprite sheets: {}", e);
+        }
         
         // Initialize sample replays for testing
         let sample_replays = Self::create_sample_replays();
@@ -156,7 +161,7 @@ impl WC2ReplayEmulator {
     pub fn render_frame(&mut self) -> Result<()> {
         if let Some(replay) = &self.current_replay {
             if let Some(game_state) = self.game_engine.get_current_state() {
-                self.renderer.render_frame(game_state, &self.asset_manager)?;
+                self.renderer.render_frame(game_state, &mut self.asset_manager)?;
             }
         }
         Ok(())
@@ -172,6 +177,18 @@ impl WC2ReplayEmulator {
         self.renderer.export_video(output_path, fps)
     }
     
+    /// Render the replay's sound effects to a WAV file, heard from the current camera
+    pub fn export_audio(&mut self, output_path: &PathBuf) -> Result<()> {
+        let replay = self.current_replay.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No replay loaded"))?;
+        
+        let (x, y) = self.renderer.get_camera_position();
+        let camera = audio::CameraPath::fixed((x / renderer::TILE_SIZE, y / renderer::TILE_SIZE));
+        let mixer = audio::ReplayAudioMixer::new(audio::AudioMixerConfig::default());
+        mixer.render_to_file(replay, &camera, &mut self.asset_manager, output_path)?;
+        Ok(())
+    }
+    
     /// Load a sample replay for testing
     pub fn load_sample_replay(&mut self, sample_index: usize) -> Result<()> {
         if let Some(sample) = self.sample_replays.get(sample_index) {
@@ -206,7 +223,9 @@ impl WC2ReplayEmulator {
                     file_hash: "sample_hash".to_string(),
                     header: None,
                     patterns: None,
+                    format: Some("w2r".to_string()),
                 },
+                tracks: Vec::new(),
             };
             
             self.current_replay = Some(mock_replay.clone());
@@ -312,9 +331,44 @@ impl eframe::App for WC2ReplayEmulator {
                         ui.label(egui::RichText::new(format!("Duration: {:?}", replay.metadata.duration))
                             .color(egui::Color32::from_rgb(200, 200, 200)));
                     });
-                
+
                 ui.add_space(15.0);
-                
+
+                // Annotation tracks (e.g. AI decisions from WC2AI recordings)
+                for track in &replay.tracks {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(35, 35, 45))
+                        .rounding(egui::Rounding::same(8.0))
+                        .inner_margin(egui::Margin::same(15.0))
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new(format!("🤖 {}", track.name)).size(16.0).strong());
+                            ui.add_space(5.0);
+                            egui::ScrollArea::vertical()
+                                .id_source(&track.name)
+                                .max_height(150.0)
+                                .show(ui, |ui| {
+                                    for annotation in &track.annotations {
+                                        let seconds = annotation.timestamp / 1000;
+                                        let (marker, color) = match annotation.success {
+                                            Some(true) => ("✔", egui::Color32::from_rgb(100, 200, 100)),
+                                            Some(false) => ("✖", egui::Color32::from_rgb(220, 100, 100)),
+                                            None => ("•", egui::Color32::from_rgb(200, 200, 200)),
+                                        };
+                                        ui.label(egui::RichText::new(format!(
+                                            "{:02}:{:02} {} {} — {}",
+                                            seconds / 60,
+                                            seconds % 60,
+                                            marker,
+                                            annotation.label,
+                                            annotation.detail
+                                        )).color(color));
+                                    }
+                                });
+                        });
+
+                    ui.add_space(15.0);
+                }
+
                 // Playback controls section
                 egui::Frame::none()
                     .fill(egui::Color32::from_rgb(35, 35, 35))
@@ -4,7 +4,8 @@
 //! that match or exceed WC2 Remastered visual quality.
 
 use crate::decoder::game_state::GameState;
-use crate::emulator::assets::AssetManager;
+use crate::emulator::assets::{AssetManager, SpriteRegion};
+use crate::emulator::sprite_sheets::Direction;
 use anyhow::Result;
 use std::path::PathBuf;
 
@@ -53,12 +54,17 @@ pub enum TextureFilter {
     Anisotropic,
 }
 
+/// Size of a map tile in pixels
+pub const TILE_SIZE: f32 = 32.0;
+
 /// Sprite representation for rendering
 pub struct Sprite {
     position: (f32, f32),
     size: (f32, f32),
     rotation: f32,
-    texture_id: u32,
+    texture_id: String,
+    source: SpriteRegion,
+    flip_x: bool,
     color: (f32, f32, f32, f32),
     animation_frame: u32,
 }
@@ -124,7 +130,7 @@ impl GraphicsRenderer {
     }
     
     /// Render a frame based on current game state
-    pub fn render_frame(&mut self, game_state: &GameState, asset_manager: &AssetManager) -> Result<()> {
+    pub fn render_frame(&mut self, game_state: &GameState, asset_manager: &mut AssetManager) -> Result<()> {
         // Clear the render target
         self.clear_render_target()?;
         
@@ -149,7 +155,9 @@ impl GraphicsRenderer {
     }
     
     /// Render the game world
-    fn render_world(&mut self, game_state: &GameState, asset_manager: &AssetManager) -> Result<()> {
+    fn render_world(&mut self, game_state: &GameState, asset_manager: &mut AssetManager) -> Result<()> {
+        self.sprite_batch.clear();
+
         // Render terrain and map
         self.render_terrain(game_state, asset_manager)?;
         
@@ -174,20 +182,91 @@ impl GraphicsRenderer {
     }
     
     /// Render buildings
-    fn render_buildings(&self, game_state: &GameState, asset_manager: &AssetManager) -> Result<()> {
-        // Render all buildings in the game state
-        // This is a placeholder implementation
+    fn render_buildings(&mut self, game_state: &GameState, asset_manager: &mut AssetManager) -> Result<()> {
+        let elapsed = game_state.current_time_seconds();
+        let mut buildings: Vec<_> = game_state.buildings.values().collect();
+        buildings.sort_by_key(|building| building.id);
+
+        for building in buildings {
+            // Sheets are written under the game-rules key, e.g. "town_hall"
+            let Some(sheet) = building.building_type.rules_key() else {
+                continue;
+            };
+            let action = if building.is_constructing { "construct" } else { "idle" };
+            let frame = asset_manager.animation_frame(sheet, action, Direction::South, elapsed)
+                .or_else(|| asset_manager.animation_frame(sheet, "idle", Direction::South, elapsed));
+            if let Some(frame) = frame {
+                let texture_id = self.player_texture(game_state, building.player_id, &frame.texture_id, asset_manager);
+                self.sprite_batch.add_sprite(Sprite {
+                    position: (building.x * TILE_SIZE, building.y * TILE_SIZE),
+                    size: (frame.region.width as f32, frame.region.height as f32),
+                    rotation: 0.0,
+                    texture_id,
+                    source: frame.region,
+                    flip_x: frame.flip_x,
+                    color: (1.0, 1.0, 1.0, 1.0),
+                    animation_frame: 0,
+                });
+            }
+        }
         
         Ok(())
     }
     
     /// Render units
-    fn render_units(&self, game_state: &GameState, asset_manager: &AssetManager) -> Result<()> {
-        // Render all units in the game state
-        // This is a placeholder implementation
+    fn render_units(&mut self, game_state: &GameState, asset_manager: &mut AssetManager) -> Result<()> {
+        let elapsed = game_state.current_time_seconds();
+        let mut units: Vec<_> = game_state.units.values().collect();
+        units.sort_by_key(|unit| unit.id);
+
+        for unit in units {
+            let Some(sheet) = unit.unit_type.rules_key() else {
+                continue;
+            };
+            let direction = match (unit.target_x, unit.target_y) {
+                (Some(tx), Some(ty)) => Direction::from_vector(tx - unit.x, ty - unit.y),
+                _ => Direction::South,
+            };
+            let action = if unit.is_attacking {
+                "attack"
+            } else if unit.is_moving {
+                "walk"
+            } else {
+                "idle"
+            };
+
+            let frame = asset_manager.animation_frame(sheet, action, direction, elapsed)
+                .or_else(|| asset_manager.animation_frame(sheet, "idle", direction, elapsed));
+            if let Some(frame) = frame {
+                let texture_id = self.player_texture(game_state, unit.player_id, &frame.texture_id, asset_manager);
+                let size = (frame.region.width as f32, frame.region.height as f32);
+                self.sprite_batch.add_sprite(Sprite {
+                    position: (
+                        unit.x * TILE_SIZE + TILE_SIZE / 2.0 - size.0 * frame.pivot.0,
+                        unit.y * TILE_SIZE + TILE_SIZE / 2.0 - size.1 * frame.pivot.1,
+                    ),
+                    size,
+                    rotation: 0.0,
+                    texture_id,
+                    source: frame.region,
+                    flip_x: frame.flip_x,
+                    color: (1.0, 1.0, 1.0, 1.0),
+                    animation_frame: 0,
+                });
+            }
+        }
         
         Ok(())
     }
+
+    /// Atlas texture recolored for the owning player, falling back to the base art
+    fn player_texture(&self, game_state: &GameState, player_id: u8, texture_id: &str, asset_manager: &mut AssetManager) -> String {
+        match game_state.players.get(&player_id) {
+            Some(player) => asset_manager.player_texture(texture_id, &player.color)
+                .unwrap_or_else(|_| texture_id.to_string()),
+            None => texture_id.to_string(),
+        }
+    }
     
     /// Render visual effects
     fn render_effects(&self, game_state: &GameState, asset_manager: &AssetManager) -> Result<()> {
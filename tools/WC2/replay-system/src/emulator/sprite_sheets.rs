@@ -0,0 +1,411 @@
+//! Sprite sheet pipeline for extracted WC2 art
+//!
+//! The wartest extractor writes unit and building sprite sheets as PNG files,
+//! optionally with a JSON file of frame metadata next to each sheet. This
+//! module cuts those sheets into frames, packs the frames into texture atlas
+//! pages, resolves per-direction animation frames and remaps player colors.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::{Context, Result};
+use image::{GenericImage, GenericImageView, RgbaImage};
+use serde::{Serialize, Deserialize};
+
+use crate::decoder::PlayerColor;
+use super::assets::SpriteRegion;
+
+/// Edge length of a texture atlas page in pixels
+pub const ATLAS_PAGE_SIZE: u32 = 2048;
+
+/// WC2 unit sheets store five facings (N to S) and mirror the western ones
+pub const WC2_SHEET_DIRECTIONS: u8 = 5;
+
+/// Facing of a unit on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Facing for a movement vector in map coordinates (y grows southwards)
+    pub fn from_vector(dx: f32, dy: f32) -> Self {
+        if dx == 0.0 && dy == 0.0 {
+            return Direction::South;
+        }
+        // Angle measured clockwise from north
+        let angle = dx.atan2(-dy).to_degrees().rem_euclid(360.0);
+        let sector = ((angle + 22.5) / 45.0) as usize % 8;
+        Self::ALL[sector]
+    }
+
+    /// Index of the direction, clockwise from north
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    /// Column in a sheet with `directions` facings and whether to mirror it
+    pub fn sheet_column(self, directions: u8) -> (u8, bool) {
+        match directions {
+            0 | 1 => (0, false),
+            8 => (self.index(), false),
+            _ => {
+                let index = self.index();
+                if index <= 4 {
+                    (index.min(directions - 1), false)
+                } else {
+                    ((8 - index).min(directions - 1), true)
+                }
+            }
+        }
+    }
+}
+
+/// Frame metadata stored next to a sprite sheet PNG
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SheetMetadata {
+    /// Width of a grid cell; defaults to the sheet width divided by `directions`
+    #[serde(default)]
+    pub frame_width: Option<u32>,
+    /// Height of a grid cell; defaults to the frame width
+    #[serde(default)]
+    pub frame_height: Option<u32>,
+    /// Number of frames to use from the grid
+    #[serde(default)]
+    pub frame_count: Option<u32>,
+    /// Facings stored per animation step (5 for WC2 units, 1 for buildings)
+    #[serde(default)]
+    pub directions: Option<u8>,
+    /// Explicit frame rectangles, as written for Remastered HD sheets; taken in
+    /// numeric order of their names
+    #[serde(default)]
+    pub frames: BTreeMap<String, FrameEntry>,
+    /// Named animations as lists of animation steps
+    #[serde(default)]
+    pub animations: BTreeMap<String, AnimationSpec>,
+    /// Anchor point inside a frame, relative to its size
+    #[serde(default)]
+    pub pivot: Option<(f32, f32)>,
+}
+
+/// Explicit frame rectangle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameEntry {
+    pub frame: FrameRect,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FrameRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/// Named animation inside a sheet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationSpec {
+    /// Animation steps; each step holds one frame per direction
+    pub steps: Vec<u32>,
+    #[serde(default = "default_frame_rate")]
+    pub frame_rate: f32,
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+}
+
+/// Order frame names by their trailing number so "frame2" comes before "frame10"
+fn frame_sort_key(name: &str) -> (&str, Option<u64>, &str) {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, number) = stem.split_at(stem.len() - digits);
+    (prefix, number.parse().ok(), name)
+}
+
+fn default_frame_rate() -> f32 {
+    8.0
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+/// A sprite sheet cut into individual frames
+pub struct SpriteSheet {
+    pub name: String,
+    pub directions: u8,
+    pub frames: Vec<RgbaImage>,
+    pub animations: BTreeMap<String, AnimationSpec>,
+    pub pivot: (f32, f32),
+}
+
+impl SpriteSheet {
+    /// Load a sheet PNG and its optional `<name>.json` metadata
+    pub fn load(png_path: &Path, fallback: Option<&SheetMetadata>) -> Result<Self> {
+        let name = png_path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid sprite sheet name: {}", png_path.display()))?
+            .to_lowercase();
+
+        let metadata_path = png_path.with_extension("json");
+        let metadata = if metadata_path.exists() {
+            let text = std::fs::read_to_string(&metadata_path)?;
+            serde_json::from_str(&text)
+                .with_context(|| format!("Invalid sheet metadata: {}", metadata_path.display()))?
+        } else {
+            fallback.cloned().unwrap_or_default()
+        };
+
+        let image = image::open(png_path)
+            .with_context(|| format!("Failed to decode sprite sheet: {}", png_path.display()))?;
+        let keyed = !image.color().has_alpha();
+        let mut pixels = image.to_rgba8();
+        if keyed {
+            // Classic GRP exports have no alpha; palette index 0 (black) is transparent
+            for pixel in pixels.pixels_mut() {
+                if pixel[0] == 0 && pixel[1] == 0 && pixel[2] == 0 {
+                    pixel[3] = 0;
+                }
+            }
+        }
+
+        Ok(Self::from_image(name, &pixels, &metadata))
+    }
+
+    /// Cut an already decoded sheet into frames
+    pub fn from_image(name: String, sheet: &RgbaImage, metadata: &SheetMetadata) -> Self {
+        let directions = metadata.directions.unwrap_or(1).max(1);
+        let mut frames = Vec::new();
+
+        if !metadata.frames.is_empty() {
+            let mut entries: Vec<_> = metadata.frames.iter().collect();
+            entries.sort_by_key(|(name, _)| frame_sort_key(name));
+            for (_, entry) in entries {
+                let rect = entry.frame;
+                if rect.x + rect.w <= sheet.width() && rect.y + rect.h <= sheet.height() {
+                    frames.push(sheet.view(rect.x, rect.y, rect.w, rect.h).to_image());
+                }
+            }
+        } else {
+            let frame_width = metadata.frame_width
+                .unwrap_or(sheet.width() / directions as u32)
+                .clamp(1, sheet.width().max(1));
+            let frame_height = metadata.frame_height
+                .unwrap_or(frame_width)
+                .clamp(1, sheet.height().max(1));
+            let columns = (sheet.width() / frame_width).max(1);
+            let rows = (sheet.height() / frame_height).max(1);
+            let available = columns * rows;
+            let count = metadata.frame_count.unwrap_or(available).min(available);
+
+            for index in 0..count {
+                let x = (index % columns) * frame_width;
+                let y = (index / columns) * frame_height;
+                frames.push(sheet.view(x, y, frame_width, frame_height).to_image());
+            }
+        }
+
+        let steps = (frames.len() as u32 / directions as u32).max(1);
+        let mut animations = metadata.animations.clone();
+        if animations.is_empty() {
+            animations.insert("idle".to_string(), AnimationSpec {
+                steps: vec![0],
+                frame_rate: default_frame_rate(),
+                looping: true,
+            });
+            animations.insert("walk".to_string(), AnimationSpec {
+                steps: (0..steps).collect(),
+                frame_rate: default_frame_rate(),
+                looping: true,
+            });
+        }
+
+        Self {
+            name,
+            directions,
+            frames,
+            animations,
+            pivot: metadata.pivot.unwrap_or((0.5, 0.5)),
+        }
+    }
+
+    /// Index of the frame shown for an animation step facing a direction
+    pub fn frame_index(&self, step: u32, direction: Direction) -> Option<(usize, bool)> {
+        let (column, flip) = direction.sheet_column(self.directions);
+        let index = (step * self.directions as u32 + column as u32) as usize;
+        (index < self.frames.len()).then_some((index, flip))
+    }
+}
+
+/// One page of a texture atlas
+pub struct AtlasPage {
+    pub pixels: RgbaImage,
+    cursor_x: u32,
+    cursor_y: u32,
+    shelf_height: u32,
+}
+
+/// Shelf packer placing frames into fixed-size atlas pages
+pub struct AtlasPacker {
+    page_size: u32,
+    padding: u32,
+    pages: Vec<AtlasPage>,
+}
+
+impl AtlasPacker {
+    pub fn new(page_size: u32, padding: u32) -> Self {
+        Self {
+            page_size,
+            padding,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Place a frame and return the page index and its region
+    pub fn insert(&mut self, frame: &RgbaImage) -> Result<(usize, SpriteRegion)> {
+        let (width, height) = frame.dimensions();
+        if width + self.padding > self.page_size || height + self.padding > self.page_size {
+            anyhow::bail!("Frame {}x{} does not fit an atlas page of {}", width, height, self.page_size);
+        }
+
+        let page_index = match self.find_space(width, height) {
+            Some(index) => index,
+            None => {
+                self.pages.push(AtlasPage {
+                    pixels: RgbaImage::new(self.page_size, self.page_size),
+                    cursor_x: 0,
+                    cursor_y: 0,
+                    shelf_height: 0,
+                });
+                self.pages.len() - 1
+            }
+        };
+
+        let padding = self.padding;
+        let page_size = self.page_size;
+        let page = &mut self.pages[page_index];
+        if page.cursor_x + width + padding > page_size {
+            page.cursor_x = 0;
+            page.cursor_y += page.shelf_height + padding;
+            page.shelf_height = 0;
+        }
+
+        let (x, y) = (page.cursor_x, page.cursor_y);
+        page.pixels.copy_from(frame, x, y)?;
+        page.cursor_x += width + padding;
+        page.shelf_height = page.shelf_height.max(height);
+
+        Ok((page_index, SpriteRegion { x, y, width, height }))
+    }
+
+    /// Finish packing and hand out the atlas pages
+    pub fn into_pages(self) -> Vec<AtlasPage> {
+        self.pages
+    }
+
+    fn find_space(&self, width: u32, height: u32) -> Option<usize> {
+        self.pages.iter().position(|page| {
+            let fits_on_shelf = page.cursor_x + width + self.padding <= self.page_size
+                && page.cursor_y + height.max(page.shelf_height) <= self.page_size;
+            let fits_on_new_shelf = page.cursor_y + page.shelf_height + self.padding + height <= self.page_size;
+            fits_on_shelf || fits_on_new_shelf
+        })
+    }
+}
+
+/// The four shades WC2 uses for a player color, brightest first
+pub fn player_color_ramp(color: &PlayerColor) -> [[u8; 3]; 4] {
+    match color {
+        PlayerColor::Red => [[164, 0, 0], [124, 0, 0], [92, 4, 0], [68, 4, 0]],
+        PlayerColor::Blue => [[12, 72, 204], [4, 40, 160], [0, 20, 116], [0, 4, 76]],
+        PlayerColor::Green => [[44, 180, 148], [20, 132, 92], [4, 84, 44], [0, 40, 12]],
+        PlayerColor::Purple => [[152, 72, 176], [116, 44, 132], [80, 24, 88], [44, 8, 44]],
+        PlayerColor::Orange => [[248, 140, 20], [200, 96, 16], [152, 60, 16], [108, 32, 12]],
+        PlayerColor::Black => [[40, 40, 60], [28, 28, 44], [20, 20, 32], [12, 12, 20]],
+        PlayerColor::White => [[224, 224, 224], [152, 152, 180], [84, 84, 128], [36, 40, 76]],
+        PlayerColor::Yellow => [[252, 252, 72], [228, 204, 40], [204, 160, 16], [180, 116, 0]],
+    }
+}
+
+/// Replace the red team shades (sheets are extracted in red) with another player's
+pub fn remap_player_color(pixels: &mut RgbaImage, color: &PlayerColor) {
+    if matches!(color, PlayerColor::Red) {
+        return;
+    }
+
+    let source = player_color_ramp(&PlayerColor::Red);
+    let target = player_color_ramp(color);
+    for pixel in pixels.pixels_mut() {
+        if let Some(shade) = source.iter().position(|rgb| rgb[..] == pixel.0[..3]) {
+            pixel.0[..3].copy_from_slice(&target[shade]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::assets::AssetManager;
+    use image::Rgba;
+
+    /// A strip of 1x1 frames whose red channel is the frame index
+    fn numbered_strip(count: u32) -> RgbaImage {
+        RgbaImage::from_fn(count, 1, |x, _| Rgba([x as u8, 0, 0, 255]))
+    }
+
+    #[test]
+    fn test_explicit_frames_follow_numeric_order() {
+        let mut metadata = SheetMetadata::default();
+        for index in 0..12 {
+            metadata.frames.insert(format!("frame{}.png", index), FrameEntry {
+                frame: FrameRect { x: index, y: 0, w: 1, h: 1 },
+            });
+        }
+
+        let sheet = SpriteSheet::from_image("strip".to_string(), &numbered_strip(12), &metadata);
+        let order: Vec<u8> = sheet.frames.iter().map(|frame| frame.get_pixel(0, 0)[0]).collect();
+        assert_eq!(order, (0..12).collect::<Vec<u8>>());
+        assert!(frame_sort_key("walk2") < frame_sort_key("walk10"));
+    }
+
+    #[test]
+    fn test_sheets_load_into_atlas() {
+        let root = tempfile::tempdir().unwrap();
+        let images = root.path().join("images").join("buildings");
+        std::fs::create_dir_all(&images).unwrap();
+
+        let sheet = RgbaImage::from_pixel(64, 32, Rgba([90, 90, 90, 255]));
+        sheet.save(images.join("town_hall.png")).unwrap();
+        std::fs::write(images.join("town_hall.json"), r#"{
+            "frame_width": 32,
+            "frame_height": 32,
+            "animations": { "construct": { "steps": [0, 1], "frame_rate": 1.0, "looping": false } }
+        }"#).unwrap();
+
+        let mut assets = AssetManager::with_root(root.path()).unwrap();
+        assert_eq!(assets.load_sprite_sheets().unwrap(), 1);
+        assert_eq!(assets.sheet_directions("town_hall"), Some(1));
+
+        let first = assets.animation_frame("town_hall", "construct", Direction::South, 0.0).unwrap();
+        let second = assets.animation_frame("town_hall", "construct", Direction::South, 1.5).unwrap();
+        assert_eq!((first.region.width, first.region.height), (32, 32));
+        assert_ne!((first.region.x, first.region.y), (second.region.x, second.region.y));
+        assert!(assets.animation_frame("townhall", "construct", Direction::South, 0.0).is_none());
+    }
+}
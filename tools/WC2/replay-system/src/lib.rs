@@ -7,6 +7,9 @@ pub mod decoder;
 pub mod structures;
 pub mod emulator;
 
+#[cfg(test)]
+mod test_fixtures;
+
 use std::sync::Arc;
 use anyhow::Result;
 
@@ -8,6 +8,9 @@ pub struct FileAnalysis {
     pub file_hash: String,
     pub header: Option<FileHeader>,
     pub patterns: Option<Vec<DataPattern>>,
+    /// Name of the registered replay format that claimed this file
+    #[serde(default)]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
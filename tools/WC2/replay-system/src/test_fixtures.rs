@@ -0,0 +1,44 @@
+//! Values shared by the unit tests of several modules
+
+use chrono::Utc;
+use crate::decoder::{DecodedReplay, GameType, PlayerColor, PlayerInfo, Race, ReplayMetadata};
+use crate::decoder::events::GameEvent;
+use crate::decoder::game_state::GameState;
+use crate::structures::{FileAnalysis, FileType};
+
+/// A W2R multiplayer replay with `players` copies of the same player and the given events
+pub fn replay(players: usize, events: Vec<GameEvent>) -> DecodedReplay {
+    let player = PlayerInfo {
+        name: "Grom".to_string(),
+        race: Race::Orc,
+        team: 1,
+        color: PlayerColor::Red,
+        is_winner: false,
+        apm: 0.0,
+    };
+    DecodedReplay {
+        metadata: ReplayMetadata {
+            filename: "game.w2r".to_string(),
+            file_size: 0,
+            creation_date: Utc::now(),
+            game_version: "2.0".to_string(),
+            map_name: "Garden of War".to_string(),
+            game_type: GameType::Multiplayer,
+            players: vec![player; players],
+            duration: std::time::Duration::from_secs(0),
+            checksum: String::new(),
+        },
+        events,
+        game_state: GameState::new(),
+        analysis: FileAnalysis {
+            filename: "game.w2r".to_string(),
+            file_size: 0,
+            file_type: FileType::W2RReplay,
+            file_hash: String::new(),
+            header: None,
+            patterns: None,
+            format: None,
+        },
+        tracks: Vec::new(),
+    }
+}
@@ -151,7 +151,7 @@ pub struct GameDataHeader {
 impl GameDataHeader {
     /// Parse header from bytes
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < 16 {
+        if bytes.len() < 14 {
             return Err(anyhow::anyhow!("Header too small: {} bytes", bytes.len()));
         }
         
@@ -227,8 +227,8 @@ mod tests {
     fn test_parse_strings() {
         let bytes = b"Hello\0World";
         
-        assert_eq!(DataUtils::parse_string(&bytes, 0, 5).unwrap(), "Hello");
-        assert_eq!(DataUtils::parse_null_terminated_string(&bytes, 0).unwrap(), "Hello");
+        assert_eq!(DataUtils::parse_string(bytes, 0, 5).unwrap(), "Hello");
+        assert_eq!(DataUtils::parse_null_terminated_string(bytes, 0).unwrap(), "Hello");
     }
 
     #[test]
@@ -0,0 +1,277 @@
+//! Guest memory of the DOS game running inside DOSBox
+//!
+//! DOSBox allocates the emulated machine's RAM as one block in its own heap,
+//! so the DOS and BNE versions of Warcraft II live at some host address that
+//! changes every run. [`DosboxSource::locate`] finds that block by the BIOS
+//! state DOSBox writes into it (the interrupt vector table, the BIOS data area
+//! and the BIOS date) and exposes the guest's physical address space as a
+//! [`MemorySource`], so address 0 is the first byte of guest RAM.
+//!
+//! Real-mode `segment:offset` pairs translate with [`real_mode_address`]. The
+//! game runs under DOS/4GW with flat selectors and paging off, so its linear
+//! addresses already are guest physical addresses. That only holds without
+//! EMS: with `ems=true` DOSBox offers VCPI, DOS/4GW runs as a VCPI client with
+//! paging on, and linear addresses no longer match physical ones. Guests with
+//! an EMS driver are refused rather than read at the wrong addresses.
+
+use anyhow::{bail, Result};
+use tracing::{debug, warn};
+
+use crate::memory_source::{self, MemoryRegion, MemorySource};
+
+/// DOSBox's smallest `memsize`
+const MIN_GUEST_RAM: u64 = 1 << 20;
+
+/// DOSBox's largest `memsize`, 63 MB
+const MAX_GUEST_RAM: u64 = 63 << 20;
+
+/// Bytes into a heap block searched for the start of guest RAM, to skip allocator headers
+const HEADER_SEARCH: usize = 0x1000;
+
+/// Interrupt vectors that must point into the BIOS segment
+const MIN_BIOS_VECTORS: usize = 16;
+
+/// Segment of the BIOS and of DOSBox's callbacks
+const BIOS_SEGMENT: u16 = 0xF000;
+
+/// Conventional memory size in KB, in the BIOS data area
+const BDA_MEMORY_SIZE: usize = 0x413;
+
+/// BIOS release date, "MM/DD/YY"
+const BIOS_DATE: u64 = 0xFFFF5;
+
+/// Interrupt of the EMS driver
+const EMS_INTERRUPT: usize = 0x67;
+
+/// Device name at offset 0x0A of the EMS driver's segment, the documented way to detect EMS
+const EMS_DEVICE_NAME: &[u8; 8] = b"EMMXXXX0";
+
+/// Guest physical address of a real-mode `segment:offset`
+pub fn real_mode_address(segment: u16, offset: u16) -> u64 {
+    ((segment as u64) << 4) + offset as u64
+}
+
+/// Running DOSBox processes as (pid, name) pairs
+pub fn find_dosbox_processes() -> Result<Vec<(u32, String)>> {
+    memory_source::find_processes(&["dosbox"])
+}
+
+/// The guest RAM of a DOSBox process, addressed by guest physical address
+pub struct DosboxSource {
+    host: Box<dyn MemorySource>,
+    /// Host address of guest address 0
+    host_base: u64,
+    /// Bytes of guest RAM
+    size: u64,
+}
+
+impl DosboxSource {
+    /// Open the DOSBox process `pid` and locate its guest RAM
+    pub fn attach(pid: u32, memsize_mb: Option<u64>) -> Result<Self> {
+        Self::locate(memory_source::open_process(pid)?, memsize_mb)
+    }
+
+    /// Find the guest RAM in `host`, a DOSBox process or a snapshot of one
+    ///
+    /// `memsize_mb` is the `memsize` from the DOSBox config. Without it the guest
+    /// is assumed to fill the rest of the host block in whole megabytes, up to
+    /// DOSBox's limit.
+    pub fn locate(host: Box<dyn MemorySource>, memsize_mb: Option<u64>) -> Result<Self> {
+        let mut found = Vec::new();
+        for region in host.regions()? {
+            if !region.readable || !region.writable || region.size < MIN_GUEST_RAM {
+                continue;
+            }
+            if let Some(host_base) = find_guest_base(host.as_ref(), &region) {
+                debug!("Guest RAM signature at 0x{:x} in region 0x{:x}-0x{:x}", host_base, region.base, region.end());
+                found.push((host_base, region.end() - host_base));
+            }
+        }
+
+        let Some(&(host_base, available)) = found.first() else {
+            bail!("No DOSBox guest RAM found in {}", host.describe());
+        };
+        if found.len() > 1 {
+            warn!("{} blocks look like DOSBox guest RAM in {}, using the one at 0x{:x}", found.len(), host.describe(), host_base);
+        }
+
+        let size = match memsize_mb {
+            Some(memsize_mb) if memsize_mb << 20 > available => {
+                bail!("memsize {} MB does not fit the {} bytes at 0x{:x} in {}", memsize_mb, available, host_base, host.describe());
+            }
+            Some(memsize_mb) => memsize_mb << 20,
+            None => (available & !(MIN_GUEST_RAM - 1)).min(MAX_GUEST_RAM),
+        };
+        let source = Self { host, host_base, size };
+        if source.has_ems() {
+            bail!("{} has EMS enabled, so DOS/4GW runs with paging and its addresses are not guest physical ones; \
+                   set ems=false in the [dos] section of the DOSBox config", source.describe());
+        }
+        Ok(source)
+    }
+
+    /// Whether an EMS driver is installed, which also provides VCPI
+    fn has_ems(&self) -> bool {
+        let Ok(vector) = self.read_bytes(EMS_INTERRUPT as u64 * 4, 4) else {
+            return false;
+        };
+        let segment = u16::from_le_bytes([vector[2], vector[3]]);
+        self.read_bytes(real_mode_address(segment, 0x0A), EMS_DEVICE_NAME.len())
+            .is_ok_and(|name| name == EMS_DEVICE_NAME)
+    }
+
+    /// Host address of guest address 0
+    pub fn host_base(&self) -> u64 {
+        self.host_base
+    }
+
+    /// Bytes of guest RAM
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Host address of a guest physical or DOS/4GW linear address
+    pub fn host_address(&self, linear: u64) -> Option<u64> {
+        (linear < self.size).then(|| self.host_base + linear)
+    }
+
+    /// Host address of a real-mode `segment:offset`
+    pub fn host_address_of_real(&self, segment: u16, offset: u16) -> Option<u64> {
+        self.host_address(real_mode_address(segment, offset))
+    }
+}
+
+impl MemorySource for DosboxSource {
+    fn describe(&self) -> String {
+        format!("DOSBox guest in {}", self.host.describe())
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.host.pid()
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        Ok(vec![MemoryRegion {
+            base: 0,
+            size: self.size,
+            readable: true,
+            writable: true,
+            executable: true,
+            name: Some("[guest ram]".to_string()),
+        }])
+    }
+
+    fn read(&self, address: u64, buf: &mut [u8]) -> Result<usize> {
+        let Some(host_address) = self.host_address(address) else {
+            bail!("Address 0x{:x} is outside the {} bytes of {}", address, self.size, self.describe());
+        };
+        let len = buf.len().min((self.size - address) as usize);
+        self.host.read(host_address, &mut buf[..len])
+    }
+}
+
+/// Host address in `region` where guest RAM starts, if it holds DOSBox's BIOS state
+fn find_guest_base(host: &dyn MemorySource, region: &MemoryRegion) -> Option<u64> {
+    // The interrupt vector table and BIOS data area of every candidate start
+    let head = host.read_bytes(region.base, HEADER_SEARCH + BDA_MEMORY_SIZE + 2).ok()?;
+    (0..HEADER_SEARCH.min(head.len().saturating_sub(BDA_MEMORY_SIZE + 2)))
+        .step_by(16)
+        .filter(|&start| looks_like_low_memory(&head[start..]))
+        .map(|start| region.base + start as u64)
+        .find(|&host_base| {
+            host_base + BIOS_DATE + 8 <= region.end()
+                && host.read_bytes(host_base + BIOS_DATE, 8).is_ok_and(|date| is_bios_date(&date))
+        })
+}
+
+/// Whether `memory` starts with a real-mode interrupt vector table and BIOS data area
+fn looks_like_low_memory(memory: &[u8]) -> bool {
+    let bios_vectors = memory[..0x400]
+        .chunks_exact(4)
+        .filter(|vector| u16::from_le_bytes([vector[2], vector[3]]) == BIOS_SEGMENT)
+        .count();
+    let memory_kb = u16::from_le_bytes([memory[BDA_MEMORY_SIZE], memory[BDA_MEMORY_SIZE + 1]]);
+    bios_vectors >= MIN_BIOS_VECTORS && (256..=640).contains(&memory_kb)
+}
+
+fn is_bios_date(date: &[u8]) -> bool {
+    date.len() == 8
+        && date.iter().enumerate().all(|(index, byte)| match index {
+            2 | 5 => *byte == b'/',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_source::SnapshotSource;
+
+    const HOST_BASE: u64 = 0x7f12_3400_0000;
+
+    fn region(base: u64) -> MemoryRegion {
+        MemoryRegion { base, size: 0, readable: true, writable: true, executable: false, name: None }
+    }
+
+    /// 2 MB of guest RAM after a 0x20 byte heap header, as DOSBox's BIOS leaves it
+    fn guest_ram() -> Vec<u8> {
+        let mut bytes = vec![0u8; 0x20 + (2 << 20)];
+        let guest = &mut bytes[0x20..];
+        for vector in 0..256 {
+            guest[vector * 4..vector * 4 + 4].copy_from_slice(&[0x60, 0xFE, 0x00, 0xF0]);
+        }
+        guest[BDA_MEMORY_SIZE..BDA_MEMORY_SIZE + 2].copy_from_slice(&640u16.to_le_bytes());
+        guest[BIOS_DATE as usize..BIOS_DATE as usize + 8].copy_from_slice(b"01/01/92");
+        guest[0x12350..0x12354].copy_from_slice(&1234u32.to_le_bytes());
+        guest[0x1A0000..0x1A0004].copy_from_slice(&5678u32.to_le_bytes());
+        bytes
+    }
+
+    fn host(guest: Vec<u8>) -> Box<dyn MemorySource> {
+        let mut snapshot = SnapshotSource::new("dosbox");
+        snapshot.add_region(region(0x10000), vec![0u8; 2 << 20]).unwrap();
+        snapshot.add_region(region(HOST_BASE), guest).unwrap();
+        Box::new(snapshot)
+    }
+
+    #[test]
+    fn test_locate_and_translate() {
+        let source = DosboxSource::locate(host(guest_ram()), None).unwrap();
+        assert_eq!((source.host_base(), source.size()), (HOST_BASE + 0x20, 2 << 20));
+        assert_eq!(real_mode_address(0x1234, 0x0010), 0x12350);
+        assert_eq!(source.host_address_of_real(0x1234, 0x0010), Some(HOST_BASE + 0x20 + 0x12350));
+        assert_eq!(source.host_address(2 << 20), None);
+
+        assert_eq!(source.read_u32(real_mode_address(0x1234, 0x0010)).unwrap(), 1234);
+        assert_eq!(source.read_u32(0x1A0000).unwrap(), 5678);
+        assert_eq!(source.read_bytes((2 << 20) - 2, 4).unwrap().len(), 2);
+        assert!(source.read_u32(2 << 20).is_err());
+        assert_eq!(source.regions().unwrap()[0].size, 2 << 20);
+    }
+
+    #[test]
+    fn test_memsize_and_missing_guest() {
+        let source = DosboxSource::locate(host(guest_ram()), Some(1)).unwrap();
+        assert_eq!(source.size(), 1 << 20);
+        assert!(DosboxSource::locate(host(guest_ram()), Some(4)).is_err());
+
+        let mut guest = guest_ram();
+        guest[0x20 + BIOS_DATE as usize + 2] = b'-';
+        assert!(DosboxSource::locate(host(guest), None).is_err());
+    }
+
+    #[test]
+    fn test_refuses_guests_with_ems() {
+        // ems=true: INT 67h points to a driver segment named EMMXXXX0
+        let mut guest = guest_ram();
+        let vector = 0x20 + EMS_INTERRUPT * 4;
+        guest[vector..vector + 4].copy_from_slice(&[0x00, 0x00, 0x00, 0xE0]);
+        guest[0x20 + 0xE000A..0x20 + 0xE0012].copy_from_slice(EMS_DEVICE_NAME);
+        let error = DosboxSource::locate(host(guest.clone()), None).err().unwrap();
+        assert!(error.to_string().contains("ems=false"));
+
+        // The vector alone, without the driver name, is not EMS
+        guest[0x20 + 0xE000A] = 0;
+        assert!(DosboxSource::locate(host(guest), None).is_ok());
+    }
+}
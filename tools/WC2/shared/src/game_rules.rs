@@ -0,0 +1,486 @@
+//! Canonical Warcraft II game rules
+//!
+//! One table of every unit, building, upgrade and spell with its costs, build
+//! time and combat figures, plus the tech tree that connects them. The table
+//! ships embedded in the crate and can be overridden per map from a PUD's
+//! UDTA section. Everything is keyed by a snake_case name (`"footman"`,
+//! `"town_hall"`, `"swords_1"`); units and buildings also carry their PUD id.
+//! Figures follow the Battle.net Edition 2.02 balance. Campaign heroes are not
+//! included.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// The rules table compiled into the crate
+const EMBEDDED_RULES: &str = include_str!("../data/game_rules.json");
+
+/// Number of unit slots in the UDTA arrays
+const UDTA_UNITS: usize = 110;
+
+/// Size of a UDTA section in bytes
+const UDTA_SIZE: usize = 5696;
+
+/// Side a unit or building belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Race {
+    Human,
+    Orc,
+    Neutral,
+}
+
+/// Whether an entry moves around or stands on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitClass {
+    Unit,
+    Building,
+}
+
+/// A unit or building
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitRules {
+    /// PUD unit id
+    pub id: u16,
+    pub key: String,
+    pub name: String,
+    pub race: Race,
+    pub class: UnitClass,
+    pub hp: u32,
+    pub armor: u32,
+    #[serde(default)]
+    pub basic_damage: u32,
+    #[serde(default)]
+    pub piercing_damage: u32,
+    /// Attack range in tiles; 0 for entries that cannot attack
+    #[serde(default)]
+    pub range: u32,
+    /// Sight radius in tiles
+    pub sight: u32,
+    /// Movement speed in the game's speed units; 0 for buildings
+    #[serde(default)]
+    pub speed: u32,
+    /// Maximum mana; 0 for non-casters
+    #[serde(default)]
+    pub mana: u32,
+    #[serde(default)]
+    pub gold: u32,
+    #[serde(default)]
+    pub wood: u32,
+    #[serde(default)]
+    pub oil: u32,
+    /// Build or training time in game seconds
+    pub build_time: u32,
+    /// Food the entry occupies
+    #[serde(default)]
+    pub food: u32,
+    /// Food the entry provides once complete
+    #[serde(default)]
+    pub food_supply: u32,
+    /// Footprint edge length in tiles
+    #[serde(default = "default_size")]
+    pub size: u32,
+    /// Any one of these trains, builds or upgrades into this entry
+    #[serde(default)]
+    pub produced_at: Vec<String>,
+    /// All of these must be owned or researched first
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Key pressed on the command card
+    #[serde(default)]
+    pub hotkey: Option<char>,
+    /// Equivalent entry of the other race
+    #[serde(default)]
+    pub counterpart: Option<String>,
+}
+
+fn default_size() -> u32 {
+    1
+}
+
+impl UnitRules {
+    pub fn is_building(&self) -> bool {
+        self.class == UnitClass::Building
+    }
+
+    /// Gold, wood and oil added together
+    pub fn total_cost(&self) -> u32 {
+        self.gold + self.wood + self.oil
+    }
+}
+
+/// A researchable upgrade; each level is its own entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeRules {
+    /// Index in the game's research table, shared with spells
+    pub id: u16,
+    pub key: String,
+    pub name: String,
+    pub race: Race,
+    #[serde(default)]
+    pub gold: u32,
+    #[serde(default)]
+    pub wood: u32,
+    #[serde(default)]
+    pub oil: u32,
+    /// Research time in game seconds
+    pub research_time: u32,
+    pub researched_at: String,
+    /// Key pressed on the command card of the researching building
+    #[serde(default)]
+    pub hotkey: Option<char>,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Units the bonuses below apply to
+    #[serde(default)]
+    pub applies_to: Vec<String>,
+    #[serde(default)]
+    pub basic_damage: u32,
+    #[serde(default)]
+    pub piercing_damage: u32,
+    #[serde(default)]
+    pub armor: u32,
+    #[serde(default)]
+    pub range: u32,
+    #[serde(default)]
+    pub sight: u32,
+    #[serde(default)]
+    pub counterpart: Option<String>,
+}
+
+impl UpgradeRules {
+    /// Gold, wood and oil added together
+    pub fn total_cost(&self) -> u32 {
+        self.gold + self.wood + self.oil
+    }
+}
+
+/// A spell; spells without `researched_at` are known from the start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpellRules {
+    /// Index in the game's research table, shared with upgrades
+    pub id: u16,
+    pub key: String,
+    pub name: String,
+    pub race: Race,
+    /// Unit that casts the spell
+    pub caster: String,
+    /// Mana per cast (per hit point for Healing and Exorcism)
+    pub mana: u32,
+    /// Cast range in tiles; 0 for self-cast spells
+    pub range: u32,
+    #[serde(default)]
+    pub gold: u32,
+    #[serde(default)]
+    pub research_time: u32,
+    #[serde(default)]
+    pub researched_at: Option<String>,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Key pressed on the caster's command card
+    #[serde(default)]
+    pub hotkey: Option<char>,
+}
+
+/// The full rules table with lookups and tech-tree queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRules {
+    pub units: Vec<UnitRules>,
+    pub upgrades: Vec<UpgradeRules>,
+    pub spells: Vec<SpellRules>,
+}
+
+impl GameRules {
+    /// The embedded table, parsed once
+    pub fn embedded() -> &'static GameRules {
+        static RULES: OnceLock<GameRules> = OnceLock::new();
+        RULES.get_or_init(|| Self::from_json(EMBEDDED_RULES).expect("embedded game rules are valid"))
+    }
+
+    /// Parse and validate a rules table in the embedded JSON format
+    pub fn from_json(json: &str) -> Result<Self> {
+        let rules: GameRules = serde_json::from_str(json)?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Embedded rules with a PUD map's UDTA overrides applied, if it has any
+    pub fn from_pud(data: &[u8]) -> Result<Self> {
+        let mut rules = Self::embedded().clone();
+        if let Some(udta) = pud_section(data, "UDTA")? {
+            rules.apply_udta(udta)?;
+        }
+        Ok(rules)
+    }
+
+    /// Override unit figures from a PUD UDTA section
+    ///
+    /// Returns false when the section asks for the default data. Only the
+    /// figures this table models are read: sight, hit points, build time,
+    /// costs, range, armor and damage.
+    pub fn apply_udta(&mut self, udta: &[u8]) -> Result<bool> {
+        if udta.len() < UDTA_SIZE {
+            bail!("UDTA section is {} bytes, expected {}", udta.len(), UDTA_SIZE);
+        }
+        if read_u16(udta, 0) != 0 {
+            return Ok(false);
+        }
+
+        // Array offsets within the section, in file order
+        let sight = 2 + UDTA_UNITS * 2 + 508 * 2;
+        let hp = sight + UDTA_UNITS * 4;
+        let build_time = hp + UDTA_UNITS * 2 + UDTA_UNITS;
+        let gold = build_time + UDTA_UNITS;
+        let wood = gold + UDTA_UNITS;
+        let oil = wood + UDTA_UNITS;
+        let range = oil + UDTA_UNITS + UDTA_UNITS * 4 * 2;
+        let armor = range + UDTA_UNITS * 3;
+        let basic_damage = armor + UDTA_UNITS * 3;
+        let piercing_damage = basic_damage + UDTA_UNITS;
+
+        for unit in self.units.iter_mut().filter(|unit| (unit.id as usize) < UDTA_UNITS) {
+            let i = unit.id as usize;
+            unit.sight = u32::from_le_bytes([udta[sight + i * 4], udta[sight + i * 4 + 1], udta[sight + i * 4 + 2], udta[sight + i * 4 + 3]]);
+            unit.hp = read_u16(udta, hp + i * 2) as u32;
+            unit.build_time = udta[build_time + i] as u32;
+            // Costs are stored in tens
+            unit.gold = udta[gold + i] as u32 * 10;
+            unit.wood = udta[wood + i] as u32 * 10;
+            unit.oil = udta[oil + i] as u32 * 10;
+            unit.range = udta[range + i] as u32;
+            unit.armor = udta[armor + i] as u32;
+            unit.basic_damage = udta[basic_damage + i] as u32;
+            unit.piercing_damage = udta[piercing_damage + i] as u32;
+        }
+        Ok(true)
+    }
+
+    pub fn unit(&self, key: &str) -> Option<&UnitRules> {
+        self.units.iter().find(|unit| unit.key == key)
+    }
+
+    pub fn unit_by_id(&self, id: u16) -> Option<&UnitRules> {
+        self.units.iter().find(|unit| unit.id == id)
+    }
+
+    pub fn upgrade(&self, key: &str) -> Option<&UpgradeRules> {
+        self.upgrades.iter().find(|upgrade| upgrade.key == key)
+    }
+
+    pub fn spell(&self, key: &str) -> Option<&SpellRules> {
+        self.spells.iter().find(|spell| spell.key == key)
+    }
+
+    /// Units or buildings of one race
+    pub fn units_of(&self, race: Race, class: UnitClass) -> impl Iterator<Item = &UnitRules> {
+        self.units.iter().filter(move |unit| unit.race == race && unit.class == class)
+    }
+
+    /// The other race's equivalent of a unit, building or upgrade
+    pub fn counterpart(&self, key: &str) -> Option<&str> {
+        let forward = self.unit(key).and_then(|unit| unit.counterpart.as_deref())
+            .or_else(|| self.upgrade(key).and_then(|upgrade| upgrade.counterpart.as_deref()));
+        forward.or_else(|| {
+            let units = self.units.iter().filter(|unit| unit.counterpart.as_deref() == Some(key)).map(|unit| unit.key.as_str());
+            let upgrades = self.upgrades.iter().filter(|upgrade| upgrade.counterpart.as_deref() == Some(key)).map(|upgrade| upgrade.key.as_str());
+            units.chain(upgrades).next()
+        })
+    }
+
+    /// Direct requirements of any entry: (all of `requires`, any one of `producers`)
+    fn node(&self, key: &str) -> Option<(Vec<&str>, Vec<&str>)> {
+        if let Some(unit) = self.unit(key) {
+            return Some((key_list(&unit.requires), key_list(&unit.produced_at)));
+        }
+        if let Some(upgrade) = self.upgrade(key) {
+            return Some((key_list(&upgrade.requires), vec![upgrade.researched_at.as_str()]));
+        }
+        self.spell(key).map(|spell| {
+            let mut requires = key_list(&spell.requires);
+            requires.push(spell.caster.as_str());
+            (requires, spell.researched_at.as_deref().into_iter().collect())
+        })
+    }
+
+    /// Everything needed before `key` can be made, in the order to make it
+    ///
+    /// Where several producers qualify the first listed one is followed, so
+    /// a Peasant needs a Town Hall rather than a Keep.
+    pub fn prerequisites(&self, key: &str) -> Vec<&str> {
+        let mut order = Vec::new();
+        let mut seen = BTreeSet::new();
+        self.visit(key, &mut seen, &mut order);
+        order.retain(|&entry| entry != key);
+        order
+    }
+
+    fn visit<'a>(&'a self, key: &str, seen: &mut BTreeSet<String>, order: &mut Vec<&'a str>) {
+        if !seen.insert(key.to_string()) {
+            return;
+        }
+        let Some((requires, producers)) = self.node(key) else {
+            return;
+        };
+        for dependency in producers.into_iter().take(1).chain(requires) {
+            self.visit(dependency, seen, order);
+        }
+        if let Some(entry) = self.key_ref(key) {
+            order.push(entry);
+        }
+    }
+
+    fn key_ref(&self, key: &str) -> Option<&str> {
+        self.unit(key).map(|unit| unit.key.as_str())
+            .or_else(|| self.upgrade(key).map(|upgrade| upgrade.key.as_str()))
+            .or_else(|| self.spell(key).map(|spell| spell.key.as_str()))
+    }
+
+    /// Requirements of `key` that `owned` does not satisfy yet
+    ///
+    /// `owned` holds keys of units, buildings and finished research. A
+    /// missing producer is reported as the first one listed.
+    pub fn missing<'a>(&'a self, key: &str, owned: &BTreeSet<&str>) -> Vec<&'a str> {
+        let Some((requires, producers)) = self.node(key) else {
+            return Vec::new();
+        };
+        let mut missing: Vec<&str> = requires.into_iter().filter(|entry| !owned.contains(entry)).collect();
+        if !producers.is_empty() && !producers.iter().any(|producer| owned.contains(producer)) {
+            missing.insert(0, producers[0]);
+        }
+        missing
+    }
+
+    /// Whether `key` can be made with what is owned now
+    pub fn is_available(&self, key: &str, owned: &BTreeSet<&str>) -> bool {
+        self.node(key).is_some() && self.missing(key, owned).is_empty()
+    }
+
+    /// Entries that list `key` as a requirement or producer
+    pub fn unlocks(&self, key: &str) -> Vec<&str> {
+        let keys = self.units.iter().map(|unit| unit.key.as_str())
+            .chain(self.upgrades.iter().map(|upgrade| upgrade.key.as_str()))
+            .chain(self.spells.iter().map(|spell| spell.key.as_str()));
+        keys.filter(|entry| {
+            self.node(entry).is_some_and(|(requires, producers)| requires.contains(&key) || producers.contains(&key))
+        })
+        .collect()
+    }
+
+    /// Check that keys are unique and every reference resolves
+    fn validate(&self) -> Result<()> {
+        let mut keys = BTreeMap::new();
+        let all = self.units.iter().map(|unit| unit.key.as_str())
+            .chain(self.upgrades.iter().map(|upgrade| upgrade.key.as_str()))
+            .chain(self.spells.iter().map(|spell| spell.key.as_str()));
+        for key in all {
+            if keys.insert(key, ()).is_some() {
+                bail!("duplicate game rules key '{}'", key);
+            }
+        }
+
+        let mut ids = BTreeSet::new();
+        for unit in &self.units {
+            if !ids.insert(unit.id) {
+                bail!("duplicate unit id {} ('{}')", unit.id, unit.key);
+            }
+        }
+
+        for key in keys.keys() {
+            let (requires, producers) = self.node(key).ok_or_else(|| anyhow!("unknown key '{}'", key))?;
+            if let Some(unknown) = requires.iter().chain(&producers).find(|entry| !keys.contains_key(*entry)) {
+                bail!("'{}' refers to unknown entry '{}'", key, unknown);
+            }
+        }
+        let counterparts = self.units.iter().filter_map(|unit| unit.counterpart.as_deref())
+            .chain(self.upgrades.iter().filter_map(|upgrade| upgrade.counterpart.as_deref()))
+            .chain(self.upgrades.iter().flat_map(|upgrade| upgrade.applies_to.iter().map(String::as_str)));
+        for reference in counterparts {
+            if !keys.contains_key(reference) {
+                bail!("game rules refer to unknown entry '{}'", reference);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn key_list(keys: &[String]) -> Vec<&str> {
+    keys.iter().map(String::as_str).collect()
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Body of a named section in PUD data
+fn pud_section<'a>(data: &'a [u8], name: &str) -> Result<Option<&'a [u8]>> {
+    let mut offset = 0;
+    while offset + 8 <= data.len() {
+        let length = u32::from_le_bytes([data[offset + 4], data[offset + 5], data[offset + 6], data[offset + 7]]) as usize;
+        let body = data.get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| anyhow!("PUD section runs past the end of the data"))?;
+        if &data[offset..offset + 4] == name.as_bytes() {
+            return Ok(Some(body));
+        }
+        offset += 8 + length;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_rules() {
+        let rules = GameRules::embedded();
+        let footman = rules.unit("footman").unwrap();
+        assert_eq!(footman.id, 0);
+        assert_eq!((footman.gold, footman.hp, footman.armor), (600, 60, 2));
+        assert_eq!(rules.unit_by_id(0x4A).unwrap().key, "town_hall");
+        assert_eq!(rules.counterpart("grunt"), Some("footman"));
+        assert_eq!(rules.counterpart("footman"), Some("grunt"));
+        assert!(rules.spell("blizzard").is_some());
+    }
+
+    #[test]
+    fn test_tech_tree() {
+        let rules = GameRules::embedded();
+        let path = rules.prerequisites("knight");
+        for needed in ["peasant", "town_hall", "barracks", "keep", "stables"] {
+            assert!(path.contains(&needed), "knight needs {}", needed);
+        }
+        let position = |key| path.iter().position(|&entry| entry == key).unwrap();
+        assert!(position("town_hall") < position("keep"));
+        assert!(position("keep") < position("stables"));
+
+        let owned: BTreeSet<&str> = ["peasant", "town_hall", "barracks"].into_iter().collect();
+        assert!(rules.is_available("footman", &owned));
+        assert!(!rules.is_available("archer", &owned));
+        assert_eq!(rules.missing("archer", &owned), vec!["lumber_mill"]);
+        assert!(rules.unlocks("lumber_mill").contains(&"archer"));
+    }
+
+    #[test]
+    fn test_udta_override() {
+        let mut udta = vec![0u8; UDTA_SIZE];
+        let hp = 2 + UDTA_UNITS * 2 + 508 * 2 + UDTA_UNITS * 4;
+        udta[hp..hp + 2].copy_from_slice(&99u16.to_le_bytes());
+        let gold = hp + UDTA_UNITS * 2 + UDTA_UNITS * 2;
+        udta[gold] = 42;
+
+        let mut pud = b"UDTA".to_vec();
+        pud.extend_from_slice(&(UDTA_SIZE as u32).to_le_bytes());
+        pud.extend_from_slice(&udta);
+        let rules = GameRules::from_pud(&pud).unwrap();
+        let footman = rules.unit("footman").unwrap();
+        assert_eq!((footman.hp, footman.gold), (99, 420));
+
+        udta[0] = 1;
+        let mut defaults = GameRules::embedded().clone();
+        assert!(!defaults.apply_udta(&udta).unwrap());
+        assert_eq!(&defaults, GameRules::embedded());
+    }
+}
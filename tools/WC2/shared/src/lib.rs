@@ -1,10 +1,20 @@
 //! Warcraft II Shared Utilities
 //! 
-//! This library provides shared utilities specific to Warcraft II.
+//! This library provides shared utilities specific to Warcraft II,
+//! including the canonical game-rules table in [`game_rules`] and
+//! process-independent memory access in [`memory_source`] with
+//! `.wc2snap` snapshot files in [`wc2snap`] and the guest memory of
+//! DOSBox in [`dosbox`]. PUD maps are read by [`pud_parser`].
 
 pub mod game_utils;
 pub mod asset_utils;
 pub mod data_utils;
+pub mod game_rules;
+pub mod pud_parser;
+pub mod memory_source;
+#[cfg(feature = "snapshots")]
+pub mod wc2snap;
+pub mod dosbox;
 
 /// Result type for the WC2 shared library
 pub type Result<T> = anyhow::Result<T>;
@@ -0,0 +1,628 @@
+//! Read-only access to game memory
+//!
+//! A [`MemorySource`] enumerates the mapped regions of an address space and
+//! reads bytes at an address. Backends exist for a live Windows process
+//! ([`Win32Source`]), a live Linux process such as the game running under Wine
+//! or DOSBox ([`ProcSource`]), and memory captured earlier ([`SnapshotSource`]),
+//! so memory tooling written against the trait runs the same on all three.
+
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Bytes read at a time when copying a region
+const CAPTURE_CHUNK: usize = 1 << 20;
+
+/// A mapped range of an address space
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    pub base: u64,
+    pub size: u64,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+    /// Backing file or module, where the source knows it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl MemoryRegion {
+    pub fn end(&self) -> u64 {
+        self.base.saturating_add(self.size)
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.base && address < self.end()
+    }
+
+    /// Access flags as a Win32 `PAGE_*` protection value
+    pub fn win32_protection(&self) -> u32 {
+        match (self.readable, self.writable, self.executable) {
+            (_, true, true) => 0x40,     // PAGE_EXECUTE_READWRITE
+            (true, false, true) => 0x20, // PAGE_EXECUTE_READ
+            (false, false, true) => 0x10, // PAGE_EXECUTE
+            (_, true, false) => 0x04,    // PAGE_READWRITE
+            (true, false, false) => 0x02, // PAGE_READONLY
+            (false, false, false) => 0x01, // PAGE_NOACCESS
+        }
+    }
+}
+
+/// Readable, writable and executable flags of a Win32 `PAGE_*` protection value
+pub fn win32_access(protection: u32) -> (bool, bool, bool) {
+    // Guard pages fault on first touch, so treat them as unreadable
+    if protection & 0x100 != 0 {
+        return (false, false, false);
+    }
+    let readable = protection & 0xEE != 0;
+    let writable = protection & 0xCC != 0;
+    let executable = protection & 0xF0 != 0;
+    (readable, writable, executable)
+}
+
+/// Something whose memory can be enumerated and read
+pub trait MemorySource: Send + Sync {
+    /// Short description for logs, e.g. "pid 4242"
+    fn describe(&self) -> String;
+
+    /// Process the memory belongs to, if it is or was a live process
+    fn pid(&self) -> Option<u32> {
+        None
+    }
+
+    /// Mapped regions in address order
+    fn regions(&self) -> Result<Vec<MemoryRegion>>;
+
+    /// Read into `buf` from `address`, returning how many bytes were read
+    ///
+    /// Reads stop early at the end of mapped memory; an unmapped `address` is an error.
+    fn read(&self, address: u64, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read up to `len` bytes from `address`
+    fn read_bytes(&self, address: u64, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        let read = self.read(address, &mut buf)?;
+        buf.truncate(read);
+        Ok(buf)
+    }
+
+    /// Fill `buf` from `address`, failing on a short read
+    fn read_exact(&self, address: u64, buf: &mut [u8]) -> Result<()> {
+        let read = self.read(address, buf)?;
+        if read < buf.len() {
+            bail!("Only {} of {} bytes readable at 0x{:x} in {}", read, buf.len(), address, self.describe());
+        }
+        Ok(())
+    }
+
+    fn read_u32(&self, address: u64) -> Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.read_exact(address, &mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+}
+
+/// Open a live process with the backend for this platform
+pub fn open_process(pid: u32) -> Result<Box<dyn MemorySource>> {
+    #[cfg(windows)]
+    return Ok(Box::new(Win32Source::open(pid)?));
+    #[cfg(target_os = "linux")]
+    return Ok(Box::new(ProcSource::open(pid)?));
+    #[cfg(not(any(windows, target_os = "linux")))]
+    bail!("Reading memory of process {} is not supported on this platform", pid);
+}
+
+/// Running processes whose name or command line contains one of `fragments`, ignoring case
+///
+/// Returns (pid, name) pairs.
+pub fn find_processes(fragments: &[&str]) -> Result<Vec<(u32, String)>> {
+    let fragments: Vec<String> = fragments.iter().map(|fragment| fragment.to_lowercase()).collect();
+    let matches = |text: &str| {
+        let text = text.to_lowercase();
+        fragments.iter().any(|fragment| text.contains(fragment.as_str()))
+    };
+
+    #[cfg(windows)]
+    return win32::find_processes(matches);
+    #[cfg(target_os = "linux")]
+    return proc::find_processes(matches);
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        let _ = matches;
+        bail!("Listing processes is not supported on this platform");
+    }
+}
+
+/// Parse the text of `/proc/<pid>/maps`
+pub fn parse_proc_maps(text: &str) -> Result<Vec<MemoryRegion>> {
+    let mut regions = Vec::new();
+    for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        // address perms offset dev inode [pathname], the pathname may contain spaces
+        let mut rest = line;
+        let mut fields = Vec::with_capacity(5);
+        for _ in 0..5 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let invalid = || anyhow!("Invalid maps line {}: {}", index + 1, line);
+
+        let (start, end) = fields[0].split_once('-').ok_or_else(invalid)?;
+        let start = u64::from_str_radix(start, 16).map_err(|_| invalid())?;
+        let end = u64::from_str_radix(end, 16).map_err(|_| invalid())?;
+        let perms = fields[1].as_bytes();
+        if end < start || perms.len() < 3 {
+            return Err(invalid());
+        }
+
+        let name = rest.trim();
+        regions.push(MemoryRegion {
+            base: start,
+            size: end - start,
+            readable: perms[0] == b'r',
+            writable: perms[1] == b'w',
+            executable: perms[2] == b'x',
+            name: (!name.is_empty()).then(|| name.to_string()),
+        });
+    }
+    Ok(regions)
+}
+
+/// A live Linux process, read through `process_vm_readv`
+///
+/// Needs the same user as the target and ptrace permission, which
+/// `kernel.yama.ptrace_scope` may restrict to child processes.
+#[cfg(target_os = "linux")]
+pub struct ProcSource {
+    pid: u32,
+}
+
+#[cfg(target_os = "linux")]
+impl ProcSource {
+    pub fn open(pid: u32) -> Result<Self> {
+        let maps = format!("/proc/{}/maps", pid);
+        std::fs::metadata(&maps).with_context(|| format!("Process {} not found", pid))?;
+        Ok(Self { pid })
+    }
+
+    /// Find running processes by name, see [`find_processes`]
+    pub fn find(fragments: &[&str]) -> Result<Vec<(u32, String)>> {
+        find_processes(fragments)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl MemorySource for ProcSource {
+    fn describe(&self) -> String {
+        format!("pid {}", self.pid)
+    }
+
+    fn pid(&self) -> Option<u32> {
+        Some(self.pid)
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        let maps = std::fs::read_to_string(format!("/proc/{}/maps", self.pid))
+            .with_context(|| format!("Failed to read memory map of process {}", self.pid))?;
+        parse_proc_maps(&maps)
+    }
+
+    fn read(&self, address: u64, buf: &mut [u8]) -> Result<usize> {
+        proc::read(self.pid, address, buf)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod proc {
+    use super::*;
+    use std::os::unix::fs::FileExt;
+
+    pub(super) fn read(pid: u32, address: u64, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let local = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: buf.len() };
+        let remote = libc::iovec { iov_base: address as *mut libc::c_void, iov_len: buf.len() };
+        // SAFETY: both iovecs describe `buf.len()` bytes and `local` points into `buf`
+        let read = unsafe { libc::process_vm_readv(pid as libc::pid_t, &local, 1, &remote, 1, 0) };
+        if read >= 0 {
+            return Ok(read as usize);
+        }
+
+        // Kernels without CONFIG_CROSS_MEMORY_ATTACH still allow reading through /proc
+        let error = std::io::Error::last_os_error();
+        debug!("process_vm_readv at 0x{:x} in pid {} failed: {}", address, pid, error);
+        let mem = std::fs::File::open(format!("/proc/{}/mem", pid))
+            .with_context(|| format!("Failed to read process {} at 0x{:x}: {}", pid, address, error))?;
+        mem.read_at(buf, address)
+            .with_context(|| format!("Failed to read process {} at 0x{:x}", pid, address))
+    }
+
+    pub(super) fn find_processes(matches: impl Fn(&str) -> bool) -> Result<Vec<(u32, String)>> {
+        let mut found = Vec::new();
+        for entry in std::fs::read_dir("/proc").context("Failed to list /proc")? {
+            let entry = entry?;
+            let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+                continue;
+            };
+            // Processes exit while we look; skip the ones that are gone
+            let Ok(comm) = std::fs::read_to_string(entry.path().join("comm")) else {
+                continue;
+            };
+            // Under Wine the Windows executable path is only in the command line
+            let cmdline = std::fs::read(entry.path().join("cmdline"))
+                .map(|bytes| String::from_utf8_lossy(&bytes).replace('\0', " "))
+                .unwrap_or_default();
+            if matches(&comm) || matches(&cmdline) {
+                found.push((pid, comm.trim().to_string()));
+            }
+        }
+        found.sort();
+        Ok(found)
+    }
+}
+
+/// A live Windows process, read through `ReadProcessMemory`
+#[cfg(windows)]
+pub struct Win32Source {
+    handle: windows::Win32::Foundation::HANDLE,
+    pid: u32,
+}
+
+#[cfg(windows)]
+impl Win32Source {
+    pub fn open(pid: u32) -> Result<Self> {
+        use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
+
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid) }
+            .with_context(|| format!("Failed to open process {}", pid))?;
+        if handle.is_invalid() {
+            bail!("Failed to open process {}", pid);
+        }
+        Ok(Self { handle, pid })
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Win32Source {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = windows::Win32::Foundation::CloseHandle(self.handle);
+        }
+    }
+}
+
+#[cfg(windows)]
+impl MemorySource for Win32Source {
+    fn describe(&self) -> String {
+        format!("pid {}", self.pid)
+    }
+
+    fn pid(&self) -> Option<u32> {
+        Some(self.pid)
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        use windows::Win32::System::Memory::{VirtualQueryEx, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_IMAGE};
+        use windows::Win32::System::ProcessStatus::GetMappedFileNameW;
+
+        let mut regions = Vec::new();
+        let mut address: u64 = 0;
+        loop {
+            let mut info = MEMORY_BASIC_INFORMATION::default();
+            let result = unsafe {
+                VirtualQueryEx(
+                    self.handle,
+                    Some(address as *const _),
+                    &mut info,
+                    std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                )
+            };
+            if result == 0 {
+                break;
+            }
+
+            if info.State.0 == MEM_COMMIT.0 && info.RegionSize > 0 {
+                let (readable, writable, executable) = win32_access(info.Protect.0);
+                // Name mapped executables and DLLs so pointer paths can be module-relative
+                let name = (info.Type.0 == MEM_IMAGE.0).then(|| {
+                    let mut path = [0u16; 512];
+                    let len = unsafe { GetMappedFileNameW(self.handle, info.BaseAddress, &mut path) } as usize;
+                    String::from_utf16_lossy(&path[..len.min(path.len())])
+                }).filter(|path| !path.is_empty());
+                regions.push(MemoryRegion {
+                    base: info.BaseAddress as u64,
+                    size: info.RegionSize as u64,
+                    readable,
+                    writable,
+                    executable,
+                    name,
+                });
+            }
+
+            let next = info.BaseAddress as u64 + info.RegionSize as u64;
+            if next <= address {
+                break;
+            }
+            address = next;
+        }
+        Ok(regions)
+    }
+
+    fn read(&self, address: u64, buf: &mut [u8]) -> Result<usize> {
+        use windows::Win32::System::Diagnostics::Debug::ReadProcessMemory;
+
+        let mut read: usize = 0;
+        let result = unsafe {
+            ReadProcessMemory(
+                self.handle,
+                address as *const std::ffi::c_void,
+                buf.as_mut_ptr() as *mut std::ffi::c_void,
+                buf.len(),
+                Some(&mut read),
+            )
+        };
+        // A read running into unmapped memory fails but still reports the bytes copied
+        match result {
+            Err(e) if read == 0 => Err(anyhow!("Failed to read process {} at 0x{:x}: {}", self.pid, address, e)),
+            _ => Ok(read),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod win32 {
+    use super::*;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+    };
+
+    pub(super) fn find_processes(matches: impl Fn(&str) -> bool) -> Result<Vec<(u32, String)>> {
+        let mut found = Vec::new();
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)?;
+            let mut entry = PROCESSENTRY32W {
+                dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+                ..Default::default()
+            };
+
+            let mut result = Process32FirstW(snapshot, &mut entry);
+            while result.is_ok() {
+                let name = String::from_utf16_lossy(&entry.szExeFile).trim_matches('\0').to_string();
+                if matches(&name) {
+                    found.push((entry.th32ProcessID, name));
+                }
+                result = Process32NextW(snapshot, &mut entry);
+            }
+            let _ = CloseHandle(snapshot);
+        }
+        Ok(found)
+    }
+}
+
+/// Memory held in this process: captured from another source or loaded from dumps
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotSource {
+    label: String,
+    pid: Option<u32>,
+    /// Regions in address order with their contents
+    regions: Vec<(MemoryRegion, Vec<u8>)>,
+}
+
+impl SnapshotSource {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self { label: label.into(), ..Default::default() }
+    }
+
+    /// Record the process the memory was taken from
+    pub fn with_pid(mut self, pid: Option<u32>) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    /// Add a region; its size is taken from `bytes`
+    pub fn add_region(&mut self, mut region: MemoryRegion, bytes: Vec<u8>) -> Result<()> {
+        region.size = bytes.len() as u64;
+        let index = self.regions.partition_point(|(existing, _)| existing.base < region.base);
+        let overlaps_previous = index > 0 && self.regions[index - 1].0.end() > region.base;
+        let overlaps_next = self.regions.get(index).is_some_and(|(next, _)| next.base < region.end());
+        if overlaps_previous || overlaps_next {
+            bail!("Region 0x{:x}-0x{:x} overlaps one already in {}", region.base, region.end(), self.label);
+        }
+        self.regions.insert(index, (region, bytes));
+        Ok(())
+    }
+
+    /// Copy every readable region of `source`
+    ///
+    /// Regions that cannot be read are skipped, and ones that end early are kept
+    /// up to the last byte read.
+    pub fn capture(source: &dyn MemorySource) -> Result<Self> {
+        let regions: Vec<MemoryRegion> = source.regions()?.into_iter().filter(|region| region.readable).collect();
+        Self::capture_regions(source, &regions)
+    }
+
+    /// Copy the given regions of `source`, skipping and truncating like [`Self::capture`]
+    pub fn capture_regions(source: &dyn MemorySource, regions: &[MemoryRegion]) -> Result<Self> {
+        let mut snapshot = Self::new(source.describe()).with_pid(source.pid());
+        for region in regions.iter().cloned() {
+            let mut bytes = Vec::new();
+            while (bytes.len() as u64) < region.size {
+                let len = CAPTURE_CHUNK.min((region.size - bytes.len() as u64) as usize);
+                match source.read_bytes(region.base + bytes.len() as u64, len) {
+                    Ok(chunk) if !chunk.is_empty() => {
+                        let short = chunk.len() < len;
+                        bytes.extend_from_slice(&chunk);
+                        if short {
+                            break;
+                        }
+                    }
+                    Ok(_) => break,
+                    Err(e) => {
+                        debug!("Stopped capturing 0x{:x} after {} bytes: {}", region.base, bytes.len(), e);
+                        break;
+                    }
+                }
+            }
+
+            if bytes.is_empty() {
+                warn!("Skipping unreadable region 0x{:x}-0x{:x} of {}", region.base, region.end(), source.describe());
+                continue;
+            }
+            snapshot.add_region(region, bytes)?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Load a raw memory dump that was taken starting at `base`
+    pub fn from_raw_dump(path: &Path, base: u64) -> Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read memory dump {}", path.display()))?;
+        let mut snapshot = Self::new(path.display().to_string());
+        let region = MemoryRegion {
+            base,
+            size: 0,
+            readable: true,
+            writable: true,
+            executable: false,
+            name: path.file_name().map(|name| name.to_string_lossy().to_string()),
+        };
+        snapshot.add_region(region, bytes)?;
+        Ok(snapshot)
+    }
+
+    /// Regions with their contents, in address order
+    pub fn region_data(&self) -> impl Iterator<Item = (&MemoryRegion, &[u8])> {
+        self.regions.iter().map(|(region, bytes)| (region, bytes.as_slice()))
+    }
+
+    /// Total bytes held
+    pub fn total_bytes(&self) -> u64 {
+        self.regions.iter().map(|(region, _)| region.size).sum()
+    }
+}
+
+impl MemorySource for SnapshotSource {
+    fn describe(&self) -> String {
+        format!("snapshot {}", self.label)
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    fn regions(&self) -> Result<Vec<MemoryRegion>> {
+        Ok(self.regions.iter().map(|(region, _)| region.clone()).collect())
+    }
+
+    fn read(&self, address: u64, buf: &mut [u8]) -> Result<usize> {
+        let mut index = self.regions.partition_point(|(region, _)| region.end() <= address);
+        if !self.regions.get(index).is_some_and(|(region, _)| region.contains(address)) {
+            bail!("Address 0x{:x} is not mapped in {}", address, self.describe());
+        }
+
+        // Continue into following regions as long as they are contiguous
+        let mut read = 0;
+        let mut next = address;
+        while read < buf.len() {
+            let Some((region, bytes)) = self.regions.get(index).filter(|(region, _)| region.contains(next)) else {
+                break;
+            };
+            let offset = (next - region.base) as usize;
+            let len = (buf.len() - read).min(bytes.len() - offset);
+            buf[read..read + len].copy_from_slice(&bytes[offset..offset + len]);
+            read += len;
+            next += len as u64;
+            index += 1;
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(base: u64) -> MemoryRegion {
+        MemoryRegion { base, size: 0, readable: true, writable: true, executable: false, name: None }
+    }
+
+    #[test]
+    fn test_parse_proc_maps() {
+        let maps = "\
+00400000-00452000 r-xp 00000000 08:02 173521      /home/user/.wine/drive_c/Warcraft II/Warcraft II BNE.exe
+00651000-00652000 rw-p 00051000 08:02 173521      /home/user/.wine/drive_c/Warcraft II/Warcraft II BNE.exe
+7ffd1a3c0000-7ffd1a3e1000 rw-p 00000000 00:00 0                          [stack]
+7ffd1a3f0000-7ffd1a3f1000 ---p 00000000 00:00 0
+";
+        let regions = parse_proc_maps(maps).unwrap();
+        assert_eq!(regions.len(), 4);
+        assert_eq!((regions[0].base, regions[0].size), (0x400000, 0x52000));
+        assert!(regions[0].readable && !regions[0].writable && regions[0].executable);
+        assert_eq!(regions[0].name.as_deref(), Some("/home/user/.wine/drive_c/Warcraft II/Warcraft II BNE.exe"));
+        assert_eq!(regions[2].name.as_deref(), Some("[stack]"));
+        assert!(!regions[3].readable);
+        assert_eq!(regions[3].name, None);
+        assert!(parse_proc_maps("not a maps line").is_err());
+    }
+
+    #[test]
+    fn test_win32_protection_round_trip() {
+        for protection in [0x01, 0x02, 0x04, 0x10, 0x20, 0x40] {
+            let (readable, writable, executable) = win32_access(protection);
+            let region = MemoryRegion { readable, writable, executable, ..region(0) };
+            assert_eq!(region.win32_protection(), protection);
+        }
+        assert_eq!(win32_access(0x104), (false, false, false));
+    }
+
+    #[test]
+    fn test_snapshot_reads() {
+        let mut snapshot = SnapshotSource::new("test");
+        snapshot.add_region(region(0x1000), vec![1; 16]).unwrap();
+        snapshot.add_region(region(0x1010), vec![2; 16]).unwrap();
+        snapshot.add_region(region(0x2000), vec![3, 0, 0, 0]).unwrap();
+        assert!(snapshot.add_region(region(0x1008), vec![0; 4]).is_err());
+
+        // Contiguous regions read as one, a gap ends the read
+        let bytes = snapshot.read_bytes(0x100c, 64).unwrap();
+        assert_eq!(bytes.len(), 20);
+        assert_eq!((bytes[3], bytes[4]), (1, 2));
+        assert_eq!(snapshot.read_u32(0x2000).unwrap(), 3);
+        assert!(snapshot.read_u32(0x2002).is_err());
+        assert!(snapshot.read_bytes(0x1800, 4).is_err());
+        assert_eq!(snapshot.total_bytes(), 36);
+    }
+
+    #[test]
+    fn test_capture_and_raw_dump() {
+        let mut original = SnapshotSource::new("original").with_pid(Some(42));
+        original.add_region(region(0x400000), (0..=255).collect()).unwrap();
+        original.add_region(MemoryRegion { readable: false, ..region(0x500000) }, vec![0; 8]).unwrap();
+
+        let copy = SnapshotSource::capture(&original).unwrap();
+        assert_eq!(copy.pid(), Some(42));
+        assert_eq!(copy.regions().unwrap().len(), 1);
+        assert_eq!(copy.read_bytes(0x4000fe, 4).unwrap(), vec![254, 255]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ram.bin");
+        std::fs::write(&path, [7u8, 0, 0, 0]).unwrap();
+        let dump = SnapshotSource::from_raw_dump(&path, 0x10000).unwrap();
+        assert_eq!(dump.read_u32(0x10000).unwrap(), 7);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_proc_source_reads_own_memory() {
+        let marker: Box<[u8; 8]> = Box::new(*b"WC2PROBE");
+        let address = marker.as_ptr() as u64;
+        let source = ProcSource::open(std::process::id()).unwrap();
+
+        let regions = source.regions().unwrap();
+        assert!(regions.iter().any(|region| region.readable && region.contains(address)));
+        assert_eq!(source.read_bytes(address, 8).unwrap(), b"WC2PROBE");
+    }
+}
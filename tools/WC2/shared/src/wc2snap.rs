@@ -0,0 +1,220 @@
+//! `.wc2snap` memory snapshot files
+//!
+//! A snapshot archives the memory of a game process at one moment, such as a
+//! victory screen or a desync, so it can be loaded as a [`SnapshotSource`] and
+//! analysed later on any platform. The file is the magic `WC2SNAP\0` and a
+//! little-endian `u16` format version, followed by one zstd stream holding a
+//! `u32` length, a JSON header with the process metadata and region table, and
+//! then the bytes of every region in table order.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::memory_source::{win32_access, MemoryRegion, MemorySource, SnapshotSource};
+
+/// File extension of memory snapshots
+pub const EXTENSION: &str = "wc2snap";
+
+/// First bytes of every snapshot file
+pub const MAGIC: &[u8; 8] = b"WC2SNAP\0";
+
+/// Format version written by [`save`]
+pub const FORMAT_VERSION: u16 = 1;
+
+/// zstd level used when writing
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Upper bound on the header, to reject corrupt files before allocating
+const MAX_HEADER_SIZE: u32 = 64 << 20;
+
+/// Where and when a snapshot was taken
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotMetadata {
+    pub pid: Option<u32>,
+    pub process_name: Option<String>,
+    /// The memory source captured, e.g. "pid 4242"
+    pub source: String,
+    /// OS the capture ran on
+    pub platform: String,
+    pub captured_at: DateTime<Utc>,
+    /// Why the snapshot was taken, e.g. "desync at 12:40"
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl SnapshotMetadata {
+    /// Metadata for capturing `source` now
+    pub fn for_source(source: &dyn MemorySource) -> Self {
+        Self {
+            pid: source.pid(),
+            process_name: None,
+            source: source.describe(),
+            platform: std::env::consts::OS.to_string(),
+            captured_at: Utc::now(),
+            note: None,
+        }
+    }
+}
+
+/// One entry of the region table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RegionEntry {
+    base: u64,
+    size: u64,
+    /// Win32 `PAGE_*` protection value
+    protection: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Header {
+    metadata: SnapshotMetadata,
+    regions: Vec<RegionEntry>,
+}
+
+impl From<&RegionEntry> for MemoryRegion {
+    fn from(entry: &RegionEntry) -> Self {
+        let (readable, writable, executable) = win32_access(entry.protection);
+        Self { base: entry.base, size: entry.size, readable, writable, executable, name: entry.name.clone() }
+    }
+}
+
+/// Write `snapshot` to `path`
+pub fn save(path: &Path, metadata: &SnapshotMetadata, snapshot: &SnapshotSource) -> Result<()> {
+    let header = Header {
+        metadata: metadata.clone(),
+        regions: snapshot.region_data()
+            .map(|(region, _)| RegionEntry {
+                base: region.base,
+                size: region.size,
+                protection: region.win32_protection(),
+                name: region.name.clone(),
+            })
+            .collect(),
+    };
+    let header = serde_json::to_vec(&header)?;
+
+    let file = File::create(path).with_context(|| format!("Failed to create snapshot {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_u16::<LittleEndian>(FORMAT_VERSION)?;
+
+    let mut encoder = zstd::Encoder::new(writer, COMPRESSION_LEVEL)?;
+    encoder.write_u32::<LittleEndian>(header.len() as u32)?;
+    encoder.write_all(&header)?;
+    for (_, bytes) in snapshot.region_data() {
+        encoder.write_all(bytes)?;
+    }
+    encoder.finish()?.flush()
+        .with_context(|| format!("Failed to write snapshot {}", path.display()))?;
+    Ok(())
+}
+
+/// Open a snapshot and decode its header, leaving the stream at the region bytes
+fn open(path: &Path) -> Result<(Header, impl Read)> {
+    let file = File::open(path).with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).with_context(|| format!("{} is not a memory snapshot", path.display()))?;
+    if &magic != MAGIC {
+        bail!("{} is not a memory snapshot", path.display());
+    }
+    let version = reader.read_u16::<LittleEndian>()?;
+    if version != FORMAT_VERSION {
+        bail!("{} is snapshot format version {}, only version {} is supported", path.display(), version, FORMAT_VERSION);
+    }
+
+    let mut decoder = zstd::Decoder::new(reader)?;
+    let length = decoder.read_u32::<LittleEndian>()?;
+    if length > MAX_HEADER_SIZE {
+        bail!("{} has a corrupt header", path.display());
+    }
+    let mut header = vec![0u8; length as usize];
+    decoder.read_exact(&mut header)?;
+    let header = serde_json::from_slice(&header)
+        .with_context(|| format!("{} has a corrupt header", path.display()))?;
+    Ok((header, decoder))
+}
+
+/// Read the metadata and region table without loading region contents
+pub fn read_header(path: &Path) -> Result<(SnapshotMetadata, Vec<MemoryRegion>)> {
+    let (header, _) = open(path)?;
+    let regions = header.regions.iter().map(MemoryRegion::from).collect();
+    Ok((header.metadata, regions))
+}
+
+/// Load a snapshot for reading through [`MemorySource`]
+pub fn load(path: &Path) -> Result<(SnapshotMetadata, SnapshotSource)> {
+    let (header, mut stream) = open(path)?;
+    let mut snapshot = SnapshotSource::new(path.display().to_string()).with_pid(header.metadata.pid);
+    for entry in &header.regions {
+        // Grow the buffer as data arrives rather than trusting the size up front
+        let mut bytes = Vec::new();
+        (&mut stream).take(entry.size).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < entry.size {
+            bail!("{} is truncated in region 0x{:x}", path.display(), entry.base);
+        }
+        snapshot.add_region(MemoryRegion::from(entry), bytes)?;
+    }
+    Ok((header.metadata, snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let mut snapshot = SnapshotSource::new("test").with_pid(Some(1234));
+        let code = MemoryRegion {
+            base: 0x400000,
+            size: 0,
+            readable: true,
+            writable: false,
+            executable: true,
+            name: Some("Warcraft II BNE.exe".to_string()),
+        };
+        let data = MemoryRegion { base: 0x4a0000, writable: true, executable: false, name: None, ..code.clone() };
+        snapshot.add_region(code, vec![0x90; 4096]).unwrap();
+        snapshot.add_region(data, (0..=255).cycle().take(10_000).collect()).unwrap();
+
+        let mut metadata = SnapshotMetadata::for_source(&snapshot);
+        metadata.process_name = Some("Warcraft II BNE.exe".to_string());
+        metadata.note = Some("victory screen".to_string());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("victory.{}", EXTENSION));
+        save(&path, &metadata, &snapshot).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() < 14_096);
+
+        let (header_metadata, regions) = read_header(&path).unwrap();
+        assert_eq!(header_metadata, metadata);
+        assert_eq!(regions, snapshot.regions().unwrap());
+
+        let (loaded_metadata, loaded) = load(&path).unwrap();
+        assert_eq!(loaded_metadata.pid, Some(1234));
+        assert_eq!(loaded.pid(), Some(1234));
+        assert_eq!(loaded.regions().unwrap(), snapshot.regions().unwrap());
+        assert_eq!(loaded.read_bytes(0x4a0000 + 9_998, 8).unwrap(), vec![14, 15]);
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not.wc2snap");
+        std::fs::write(&path, b"PK\x03\x04 something else").unwrap();
+        assert!(load(&path).is_err());
+
+        let mut truncated = MAGIC.to_vec();
+        truncated.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        std::fs::write(&path, truncated).unwrap();
+        assert!(read_header(&path).is_err());
+    }
+}
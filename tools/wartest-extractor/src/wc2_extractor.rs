@@ -4,6 +4,7 @@ use bson::{doc, Document};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
+use wc2_shared::game_rules::{GameRules, Race, UnitClass, UnitRules};
 use chrono;
 
 /// WC2 Unit data structure for MongoDB
@@ -122,104 +123,48 @@ impl WC2Extractor {
     fn extract_units_and_buildings(&mut self) -> Result<()> {
         println!("Extracting units and buildings...");
         
-        // Define known WC2 units and buildings (more extensive than WC1)
-        let human_units = [
-            ("peasant", "Peasant", "unit", "human", 60, 2, 0, 1.0, 0, (75, 0, 1)),
-            ("footman", "Footman", "unit", "human", 120, 12, 2, 1.0, 0, (135, 0, 1)),
-            ("knight", "Knight", "unit", "human", 180, 20, 6, 1.0, 0, (400, 0, 2)),
-            ("archer", "Archer", "unit", "human", 60, 8, 0, 1.0, 0, (130, 0, 1)),
-            ("ranger", "Ranger", "unit", "human", 90, 12, 0, 1.0, 0, (200, 0, 1)),
-            ("paladin", "Paladin", "unit", "human", 240, 25, 8, 1.0, 0, (800, 0, 3)),
-            ("cleric", "Cleric", "unit", "human", 60, 4, 0, 1.0, 200, (160, 0, 1)),
-            ("conjurer", "Conjurer", "unit", "human", 80, 6, 0, 1.0, 400, (200, 0, 1)),
-            ("wizard", "Wizard", "unit", "human", 100, 8, 0, 1.0, 600, (300, 0, 1)),
-            ("catapult", "Catapult", "unit", "human", 200, 80, 0, 0.5, 0, (800, 0, 4)),
-            ("ballista", "Ballista", "unit", "human", 150, 60, 0, 0.5, 0, (600, 0, 3)),
-            ("gryphon_rider", "Gryphon Rider", "unit", "human", 120, 16, 0, 1.5, 0, (500, 0, 2)),
-        ];
-
-        let human_buildings = [
-            ("townhall", "Town Hall", "building", "human", 2400, 0, 0, 0.0, 0, (400, 0, 0)),
-            ("farm", "Farm", "building", "human", 1000, 0, 0, 0.0, 0, (75, 0, 0)),
-            ("barracks", "Barracks", "building", "human", 1600, 0, 0, 0.0, 0, (180, 0, 0)),
-            ("lumbermill", "Lumber Mill", "building", "human", 1200, 0, 0, 0.0, 0, (100, 0, 0)),
-            ("blacksmith", "Blacksmith", "building", "human", 1400, 0, 0, 0.0, 0, (150, 0, 0)),
-            ("church", "Church", "building", "human", 1200, 0, 0, 0.0, 0, (200, 0, 0)),
-            ("tower", "Guard Tower", "building", "human", 1600, 0, 0, 0.0, 0, (120, 0, 0)),
-            ("cannon_tower", "Cannon Tower", "building", "human", 2000, 0, 0, 0.0, 0, (200, 0, 0)),
-            ("stables", "Stables", "building", "human", 1400, 0, 0, 0.0, 0, (200, 0, 0)),
-            ("workshop", "Workshop", "building", "human", 1600, 0, 0, 0.0, 0, (250, 0, 0)),
-            ("gryphon_aviary", "Gryphon Aviary", "building", "human", 1800, 0, 0, 0.0, 0, (300, 0, 0)),
-        ];
-
-        let orc_units = [
-            ("peon", "Peon", "unit", "orc", 60, 2, 0, 1.0, 0, (75, 0, 1)),
-            ("grunt", "Grunt", "unit", "orc", 140, 14, 2, 1.0, 0, (140, 0, 1)),
-            ("raider", "Raider", "unit", "orc", 180, 20, 4, 1.0, 0, (400, 0, 2)),
-            ("spearman", "Spearman", "unit", "orc", 80, 10, 0, 1.0, 0, (140, 0, 1)),
-            ("berserker", "Berserker", "unit", "orc", 120, 15, 0, 1.0, 0, (200, 0, 1)),
-            ("ogre", "Ogre", "unit", "orc", 300, 30, 8, 1.0, 0, (800, 0, 3)),
-            ("necrolyte", "Necrolyte", "unit", "orc", 60, 4, 0, 1.0, 200, (160, 0, 1)),
-            ("warlock", "Warlock", "unit", "orc", 80, 6, 0, 1.0, 400, (200, 0, 1)),
-            ("death_knight", "Death Knight", "unit", "orc", 100, 8, 0, 1.0, 600, (300, 0, 1)),
-            ("catapult", "Catapult", "unit", "orc", 200, 80, 0, 0.5, 0, (800, 0, 4)),
-            ("ballista", "Ballista", "unit", "orc", 150, 60, 0, 0.5, 0, (600, 0, 3)),
-            ("wyvern_rider", "Wyvern Rider", "unit", "orc", 120, 16, 0, 1.5, 0, (500, 0, 2)),
-        ];
-
-        let orc_buildings = [
-            ("greathall", "Great Hall", "building", "orc", 2400, 0, 0, 0.0, 0, (400, 0, 0)),
-            ("farm", "Farm", "building", "orc", 1000, 0, 0, 0.0, 0, (75, 0, 0)),
-            ("barracks", "Barracks", "building", "orc", 1600, 0, 0, 0.0, 0, (180, 0, 0)),
-            ("lumbermill", "Lumber Mill", "building", "orc", 1200, 0, 0, 0.0, 0, (100, 0, 0)),
-            ("blacksmith", "Blacksmith", "building", "orc", 1400, 0, 0, 0.0, 0, (150, 0, 0)),
-            ("temple", "Temple", "building", "orc", 1200, 0, 0, 0.0, 0, (200, 0, 0)),
-            ("tower", "Guard Tower", "building", "orc", 1600, 0, 0, 0.0, 0, (120, 0, 0)),
-            ("cannon_tower", "Cannon Tower", "building", "orc", 2000, 0, 0, 0.0, 0, (200, 0, 0)),
-            ("kennels", "Kennels", "building", "orc", 1400, 0, 0, 0.0, 0, (200, 0, 0)),
-            ("workshop", "Workshop", "building", "orc", 1600, 0, 0, 0.0, 0, (250, 0, 0)),
-            ("wyvern_roost", "Wyvern Roost", "building", "orc", 1800, 0, 0, 0.0, 0, (300, 0, 0)),
-        ];
-
-        // Add all units and buildings
-        for (id, name, category, unit_type, health, damage, armor, speed, mana, (gold, lumber, food)) in human_units.iter() {
-            self.add_unit(id, name, category, unit_type, *health, *damage, *armor, *speed, *mana, *gold, *lumber, *food);
-        }
-
-        for (id, name, category, unit_type, health, damage, armor, speed, mana, (gold, lumber, food)) in human_buildings.iter() {
-            self.add_unit(id, name, category, unit_type, *health, *damage, *armor, *speed, *mana, *gold, *lumber, *food);
-        }
-
-        for (id, name, category, unit_type, health, damage, armor, speed, mana, (gold, lumber, food)) in orc_units.iter() {
-            self.add_unit(id, name, category, unit_type, *health, *damage, *armor, *speed, *mana, *gold, *lumber, *food);
-        }
-
-        for (id, name, category, unit_type, health, damage, armor, speed, mana, (gold, lumber, food)) in orc_buildings.iter() {
-            self.add_unit(id, name, category, unit_type, *health, *damage, *armor, *speed, *mana, *gold, *lumber, *food);
+        // Stats and costs come from the shared game-rules table
+        let rules = GameRules::embedded();
+        for unit in rules.units.iter().filter(|unit| unit.race != Race::Neutral) {
+            let produces = rules
+                .units
+                .iter()
+                .filter(|other| other.produced_at.contains(&unit.key))
+                .map(|other| other.key.clone())
+                .collect();
+            self.add_unit(unit, produces);
         }
 
         Ok(())
     }
 
-    fn add_unit(&mut self, id: &str, name: &str, category: &str, unit_type: &str, 
-                health: u32, damage: u32, armor: u32, speed: f32, mana: u32, 
-                gold: u32, lumber: u32, food: u32) {
+    fn add_unit(&mut self, rules: &UnitRules, produces: Vec<String>) {
+        let category = match rules.class {
+            UnitClass::Unit => "unit",
+            UnitClass::Building => "building",
+        };
+        let race = match rules.race {
+            Race::Human => "human",
+            Race::Orc => "orc",
+            Race::Neutral => "neutral",
+        };
+
         let unit = WC2Unit {
-            _id: id.to_string(),
-            name: id.to_string(),
-            display_name: name.to_string(),
-            unit_type: unit_type.to_string(),
+            _id: rules.key.clone(),
+            name: rules.key.clone(),
+            display_name: rules.name.clone(),
+            unit_type: race.to_string(),
             category: category.to_string(),
-            health: Some(health),
-            damage: Some(damage),
-            armor: Some(armor),
-            speed: Some(speed),
-            mana: if mana > 0 { Some(mana) } else { None },
-            cost: Some(UnitCost { gold, lumber, food }),
+            health: Some(rules.hp),
+            damage: Some(rules.basic_damage + rules.piercing_damage),
+            armor: Some(rules.armor),
+            speed: Some(rules.speed as f32),
+            mana: if rules.mana > 0 { Some(rules.mana) } else { None },
+            cost: Some(UnitCost { gold: rules.gold, lumber: rules.wood, food: rules.food }),
             abilities: Vec::new(),
             spells: Vec::new(),
-            build_time: None,
-            produces: Vec::new(),
+            build_time: Some(rules.build_time),
+            produces,
             upgrades: Vec::new(),
             image_path: None,
             sprite_data: None,